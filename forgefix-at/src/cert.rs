@@ -0,0 +1,405 @@
+//! Certification scenarios run against a counterparty.
+//!
+//! Each [`Scenario`] logs on as an initiator, exercises one part of the session or order flow, and
+//! logs out. Sequence numbers are kept in memory for the length of a run: the first logon resets
+//! them with `ResetSeqNumFlag(141)=Y`, and later scenarios continue from there.
+use crate::profile::VenueProfile;
+
+use forgefix::fix::decode::{parse, MessageParseError, ParserCallback};
+use forgefix::fix::encode::{formatted_time, MessageBuilder};
+use forgefix::fix::generated::{MsgType, Tags};
+use forgefix::fix::mem::MsgBuf;
+use forgefix::fix::memory_store::MemoryStore;
+use forgefix::{FixApplicationHandle, FixApplicationInitiator, SessionSettings};
+
+use chrono::naive::NaiveTime;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Scenario {
+    /// Log on and log out
+    LogonLogout,
+    /// Log on with a gap in the incoming sequence numbers, and expect the gap to be filled
+    Resend,
+    /// Send an order with an OrderQty(38) of zero, and expect it to be rejected
+    Reject,
+    /// Send an order and cancel it, expecting an ExecutionReport<8> for each
+    OrderFlow,
+}
+
+impl Scenario {
+    pub(crate) const ALL: [Scenario; 4] = [
+        Scenario::LogonLogout,
+        Scenario::Resend,
+        Scenario::Reject,
+        Scenario::OrderFlow,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Scenario::LogonLogout => "logon-logout",
+            Scenario::Resend => "resend",
+            Scenario::Reject => "reject",
+            Scenario::OrderFlow => "order-flow",
+        }
+    }
+
+    async fn run(&self, driver: &Driver) -> Result<(), String> {
+        match self {
+            Scenario::LogonLogout => logon_logout(driver).await,
+            Scenario::Resend => resend(driver).await,
+            Scenario::Reject => reject(driver).await,
+            Scenario::OrderFlow => order_flow(driver).await,
+        }
+    }
+}
+
+/// The session a certification run connects with.
+pub(crate) struct Driver {
+    sender_comp_id: String,
+    target_comp_id: String,
+    addr: SocketAddr,
+    epoch: String,
+    log_dir: PathBuf,
+    profile: VenueProfile,
+    store: MemoryStore,
+    start_time: NaiveTime,
+    next_id: AtomicU32,
+}
+
+type Receiver = mpsc::UnboundedReceiver<Arc<MsgBuf>>;
+
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+impl Driver {
+    pub(crate) fn new(
+        sender_comp_id: &str,
+        target_comp_id: &str,
+        addr: SocketAddr,
+        epoch: &str,
+        log_dir: PathBuf,
+        profile: VenueProfile,
+    ) -> Driver {
+        Driver {
+            sender_comp_id: sender_comp_id.to_string(),
+            target_comp_id: target_comp_id.to_string(),
+            addr,
+            epoch: epoch.to_string(),
+            log_dir,
+            profile,
+            store: MemoryStore::new(),
+            // only the first logon of the run starts a new session
+            start_time: chrono::Utc::now().time(),
+            next_id: AtomicU32::new(0),
+        }
+    }
+
+    /// Run each scenario in turn.
+    pub(crate) async fn run(&self, scenarios: &[Scenario]) -> Report {
+        let mut outcomes = Vec::new();
+        for scenario in scenarios {
+            let started = Instant::now();
+            let result = scenario.run(self).await;
+            outcomes.push(Outcome {
+                scenario: *scenario,
+                result,
+                elapsed: started.elapsed(),
+            });
+        }
+        Report {
+            venue: self.profile.name.clone(),
+            outcomes,
+        }
+    }
+
+    async fn logon(&self) -> Result<(FixApplicationHandle, Receiver), String> {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id(&self.sender_comp_id)
+            .with_target_comp_id(&self.target_comp_id)
+            .with_socket_addr(self.addr)
+            .with_begin_string("FIX.4.2")
+            .with_epoch(&self.epoch)
+            .with_log_dir(self.log_dir.clone())
+            .with_memory_store(self.store.clone())
+            .with_heartbeat_timeout(self.profile.heartbeat_timeout())
+            .with_start_time(self.start_time)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let (handle, receiver) = FixApplicationInitiator::build(settings)
+            .map_err(|e| e.to_string())?
+            .initiate()
+            .await
+            .map_err(|e| format!("could not connect: {e}"))?;
+        within(self.profile.logon_timeout(), "Logon<A>", handle.start_async())
+            .await?
+            .map_err(|e| format!("logon failed: {e}"))?;
+        Ok((handle, receiver))
+    }
+
+    async fn logout(&self, handle: FixApplicationHandle) -> Result<(), String> {
+        within(self.profile.logon_timeout(), "Logout<5>", handle.end_async())
+            .await?
+            .map_err(|e| format!("logout failed: {e}"))?;
+        // give the counterparty time to close its side before the next logon
+        tokio::time::sleep(SETTLE_TIME).await;
+        Ok(())
+    }
+
+    fn cl_ord_id(&self) -> String {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("cert-{}-{id}", chrono::Utc::now().format("%H%M%S"))
+    }
+
+    fn order(&self, handle: &FixApplicationHandle, cl_ord_id: &str, order_qty: &str) -> MessageBuilder {
+        let order = &self.profile.order;
+        let mut builder = MessageBuilder::new(&handle.begin_string(), MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, cl_ord_id.as_bytes())
+            .push(Tags::HandlInst, b"1")
+            .push(Tags::Symbol, order.symbol.as_bytes())
+            .push(Tags::Side, order.side.as_bytes())
+            .push(Tags::TransactTime, formatted_time().as_bytes())
+            .push(Tags::OrderQty, order_qty.as_bytes())
+            .push(Tags::OrdType, order.ord_type.as_bytes());
+        if let Some(ref price) = order.price {
+            builder = builder.push(Tags::Price, price.as_bytes());
+        }
+        self.push_required_tags(builder)
+    }
+
+    fn cancel(&self, handle: &FixApplicationHandle, cl_ord_id: &str, orig_cl_ord_id: &str) -> MessageBuilder {
+        let order = &self.profile.order;
+        let builder = MessageBuilder::new(&handle.begin_string(), MsgType::ORDER_CANCEL_REQUEST.into())
+            .push(Tags::OrigClOrdID, orig_cl_ord_id.as_bytes())
+            .push(Tags::ClOrdID, cl_ord_id.as_bytes())
+            .push(Tags::Symbol, order.symbol.as_bytes())
+            .push(Tags::Side, order.side.as_bytes())
+            .push(Tags::TransactTime, formatted_time().as_bytes())
+            .push(Tags::OrderQty, order.order_qty.as_bytes());
+        self.push_required_tags(builder)
+    }
+
+    fn push_required_tags(&self, mut builder: MessageBuilder) -> MessageBuilder {
+        for (tag, value) in self.profile.required_tags(builder.msg_type()) {
+            builder = builder.push(tag, value.as_bytes());
+        }
+        builder
+    }
+
+    // Wait for the first message that `matches` returns a result for.
+    async fn expect<T>(
+        &self,
+        receiver: &mut Receiver,
+        what: &str,
+        mut matches: impl FnMut(&Fields) -> Option<T>,
+    ) -> Result<T, String> {
+        within(self.profile.response_timeout(), what, async {
+            while let Some(msg) = receiver.recv().await {
+                if let Some(result) = Fields::parse(&msg).as_ref().and_then(&mut matches) {
+                    return Ok(result);
+                }
+            }
+            Err(String::from("the session ended"))
+        })
+        .await?
+    }
+}
+
+async fn within<T>(timeout: Duration, what: &str, fut: impl Future<Output = T>) -> Result<T, String> {
+    tokio::time::timeout(timeout, fut)
+        .await
+        .map_err(|_| format!("no {what} within {}s", timeout.as_secs()))
+}
+
+async fn logon_logout(driver: &Driver) -> Result<(), String> {
+    let (handle, _receiver) = driver.logon().await?;
+    driver.logout(handle).await
+}
+
+async fn resend(driver: &Driver) -> Result<(), String> {
+    let (handle, _receiver) = driver.logon().await?;
+    driver.logout(handle).await?;
+
+    let (next_incoming, next_outgoing) = driver
+        .store
+        .sequences(&driver.epoch)
+        .ok_or("no sequence numbers were stored")?;
+    if next_incoming < 2 {
+        return Err(String::from("no messages were received"));
+    }
+    // the last message received is now missing
+    driver.store.set_sequences(&driver.epoch, next_incoming - 1, next_outgoing);
+
+    let (handle, _receiver) = driver.logon().await?;
+    tokio::time::sleep(driver.profile.response_timeout().min(Duration::from_secs(1))).await;
+    driver.logout(handle).await?;
+    match driver.store.sequences(&driver.epoch) {
+        Some((incoming, _)) if incoming > next_incoming => Ok(()),
+        _ => Err(format!("MsgSeqNum(34) {} was not resent or gap filled", next_incoming - 1)),
+    }
+}
+
+async fn reject(driver: &Driver) -> Result<(), String> {
+    let (handle, mut receiver) = driver.logon().await?;
+    let cl_ord_id = driver.cl_ord_id();
+    handle
+        .send_message_async(driver.order(&handle, &cl_ord_id, "0"))
+        .await
+        .map_err(|e| format!("could not send the order: {e}"))?;
+    let rejected = driver
+        .expect(&mut receiver, "rejection", |fields| match fields.msg_type {
+            '8' if fields.get(Tags::ClOrdID) == Some(cl_ord_id.as_str()) => Some(fields.get(Tags::OrdStatus) == Some("8")),
+            'j' if fields.get(Tags::BusinessRejectRefID) == Some(cl_ord_id.as_str()) => Some(true),
+            _ => None,
+        })
+        .await?;
+    driver.logout(handle).await?;
+    if !rejected {
+        return Err(String::from("the order with an OrderQty(38) of zero was accepted"));
+    }
+    Ok(())
+}
+
+async fn order_flow(driver: &Driver) -> Result<(), String> {
+    let (handle, mut receiver) = driver.logon().await?;
+    let cl_ord_id = driver.cl_ord_id();
+    handle
+        .send_message_async(driver.order(&handle, &cl_ord_id, &driver.profile.order.order_qty))
+        .await
+        .map_err(|e| format!("could not send the order: {e}"))?;
+    let ord_status = driver
+        .expect(&mut receiver, "ExecutionReport<8> for the order", |fields| {
+            (fields.msg_type == '8' && fields.get(Tags::ClOrdID) == Some(cl_ord_id.as_str()))
+                .then(|| (fields.get(Tags::OrdStatus).unwrap_or_default().to_string(), fields.text()))
+        })
+        .await?;
+    if ord_status.0 == "8" {
+        return Err(format!("the order was rejected: {}", ord_status.1));
+    }
+
+    let cancel_id = driver.cl_ord_id();
+    handle
+        .send_message_async(driver.cancel(&handle, &cancel_id, &cl_ord_id))
+        .await
+        .map_err(|e| format!("could not send the cancel: {e}"))?;
+    let canceled = driver
+        .expect(&mut receiver, "response to the cancel", |fields| {
+            match (fields.msg_type, fields.get(Tags::ClOrdID) == Some(cancel_id.as_str())) {
+                ('8', true) => Some(Ok(())).filter(|_| matches!(fields.get(Tags::OrdStatus), Some("4" | "6"))),
+                ('9', true) => Some(Err(format!("the cancel was rejected: {}", fields.text()))),
+                _ => None,
+            }
+        })
+        .await?;
+    driver.logout(handle).await?;
+    canceled
+}
+
+// The fields of an application message, by tag.
+struct Fields {
+    msg_type: char,
+    values: HashMap<u32, String>,
+}
+
+impl Fields {
+    fn parse(msg: &MsgBuf) -> Option<Fields> {
+        let mut fields = Fields {
+            msg_type: '\0',
+            values: HashMap::new(),
+        };
+        parse(&msg[..], &mut fields).ok()?;
+        Some(fields)
+    }
+
+    fn get(&self, tag: Tags) -> Option<&str> {
+        self.values.get(&u32::from(tag)).map(String::as_str)
+    }
+
+    fn text(&self) -> String {
+        self.get(Tags::Text).unwrap_or("no Text(58)").to_string()
+    }
+}
+
+impl<'a> ParserCallback<'a> for Fields {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let (Ok(Tags::MsgType), [msg_type]) = (key.try_into(), value) {
+            self.msg_type = *msg_type as char;
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.values.insert(key, String::from_utf8_lossy(value).into_owned());
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+pub(crate) struct Outcome {
+    scenario: Scenario,
+    result: Result<(), String>,
+    elapsed: Duration,
+}
+
+/// The outcome of each scenario of a run.
+pub(crate) struct Report {
+    venue: String,
+    outcomes: Vec<Outcome>,
+}
+
+impl Report {
+    pub(crate) fn passed(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.result.is_ok())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "certification report for {}", self.venue)?;
+        for outcome in &self.outcomes {
+            let elapsed = outcome.elapsed.as_secs_f64();
+            match outcome.result {
+                Ok(()) => writeln!(f, "  PASS {:<14} {elapsed:.2}s", outcome.scenario.name())?,
+                Err(ref reason) => {
+                    writeln!(f, "  FAIL {:<14} {elapsed:.2}s  {reason}", outcome.scenario.name())?
+                }
+            }
+        }
+        let passed = self.outcomes.iter().filter(|outcome| outcome.result.is_ok()).count();
+        write!(f, "{passed} passed, {} failed", self.outcomes.len() - passed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use forgefix::testing::Counterparty;
+
+    #[tokio::test]
+    async fn test_scenarios() {
+        let peer = Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let driver = Driver::new(
+            "my_id",
+            "peer_id",
+            peer.addr(),
+            "cert",
+            peer.log_dir(),
+            VenueProfile::default(),
+        );
+        let report = driver.run(&Scenario::ALL).await;
+        assert!(report.passed(), "{report}");
+        assert!(report.to_string().ends_with("4 passed, 0 failed"));
+    }
+}
@@ -1,4 +1,9 @@
+mod cert;
+mod profile;
+
+use cert::{Driver, Scenario};
 use clap::{Parser, ValueHint};
+use profile::VenueProfile;
 use forgefix::{
     fix,
     fix::generated::{MsgType, Tags},
@@ -67,6 +72,22 @@ struct Opts {
     /// Time session should start each day in format HH:MM:SS
     #[arg(long, default_value = "23:59:59", value_parser = parse_time)]
     start: NaiveTime, 
+
+    /// Run every certification scenario against the counterparty
+    #[arg(long, conflicts_with = "listen")]
+    certify: bool,
+
+    /// Run a certification scenario against the counterparty. May be repeated
+    #[arg(long, value_enum, conflicts_with = "listen")]
+    scenario: Vec<Scenario>,
+
+    /// Venue profile used by the certification scenarios, in TOML
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    profile: Option<PathBuf>,
+
+    /// Also write the certification report to this file
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    report: Option<PathBuf>,
 }
 
 impl Opts {
@@ -115,6 +136,10 @@ async fn main() -> Result<(), forgefix::ApplicationError> {
     // let addr = "138.8.53.226:12189".parse().unwrap();
     let is_server = opts.listen;
 
+    if opts.certify || !opts.scenario.is_empty() {
+        certify(&opts).await;
+    }
+
     let settings = SessionSettings::builder()
         .with_sender_comp_id(opts.sender_comp_id.as_str())
         .with_target_comp_id(opts.target_comp_id.as_str())
@@ -198,6 +223,40 @@ async fn main() -> Result<(), forgefix::ApplicationError> {
     Ok(())
 }
 
+// Run the certification scenarios, print the report, and exit with a non-zero status if any
+// scenario failed.
+async fn certify(opts: &Opts) -> ! {
+    let profile = match opts.profile {
+        Some(ref path) => VenueProfile::load(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }),
+        None => VenueProfile::default(),
+    };
+    let scenarios = if opts.scenario.is_empty() {
+        Scenario::ALL.to_vec()
+    } else {
+        opts.scenario.clone()
+    };
+    let driver = Driver::new(
+        &opts.sender_comp_id,
+        &opts.target_comp_id,
+        opts.addr,
+        &opts.epoch,
+        opts.log.clone(),
+        profile,
+    );
+    let report = driver.run(&scenarios).await;
+    println!("{report}");
+    if let Some(ref path) = opts.report {
+        if let Err(e) = std::fs::write(path, format!("{report}\n")) {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(2);
+        }
+    }
+    std::process::exit(if report.passed() { 0 } else { 1 });
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn send_order(
     fix_app_client: &FixApplicationHandle,
@@ -0,0 +1,151 @@
+//! Venue profiles for certification runs.
+//!
+//! A profile holds what differs between counterparties: how long to wait for them, the order
+//! used in the order scenarios, and tags each venue requires on its messages. Profiles are TOML
+//! files, such as:
+//!
+//! ```toml
+//! name = "example-broker"
+//! heartbeat_timeout = 30
+//! logon_timeout = 10
+//! response_timeout = 10
+//!
+//! [order]
+//! symbol = "AAPL"
+//! side = "1"
+//! order_qty = "100"
+//! ord_type = "2"
+//! price = "1.00"
+//!
+//! # tags added to every message of a MsgType
+//! [required_tags.D]
+//! 1 = "ACCOUNT1"
+//! 100 = "XNAS"
+//! ```
+//!
+//! Every field is optional.
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct VenueProfile {
+    pub(crate) name: String,
+    heartbeat_timeout: u64,
+    logon_timeout: u64,
+    response_timeout: u64,
+    pub(crate) order: OrderProfile,
+    required_tags: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct OrderProfile {
+    pub(crate) symbol: String,
+    pub(crate) side: String,
+    pub(crate) order_qty: String,
+    pub(crate) ord_type: String,
+    pub(crate) price: Option<String>,
+}
+
+impl Default for VenueProfile {
+    fn default() -> VenueProfile {
+        VenueProfile {
+            name: String::from("default"),
+            heartbeat_timeout: 30,
+            logon_timeout: 10,
+            response_timeout: 10,
+            order: OrderProfile::default(),
+            required_tags: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for OrderProfile {
+    fn default() -> OrderProfile {
+        OrderProfile {
+            symbol: String::from("AAPL"),
+            side: String::from("1"),
+            order_qty: String::from("100"),
+            ord_type: String::from("2"),
+            price: Some(String::from("1.00")),
+        }
+    }
+}
+
+impl VenueProfile {
+    pub(crate) fn load(path: &Path) -> Result<VenueProfile, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        VenueProfile::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<VenueProfile, String> {
+        let profile: VenueProfile = toml::from_str(text).map_err(|e| e.to_string())?;
+        for (msg_type, tags) in &profile.required_tags {
+            if msg_type.chars().count() != 1 {
+                return Err(format!("required_tags: `{msg_type}` is not a MsgType"));
+            }
+            if let Some(tag) = tags.keys().find(|tag| tag.parse::<u32>().is_err()) {
+                return Err(format!("required_tags.{msg_type}: `{tag}` is not a tag number"));
+            }
+        }
+        Ok(profile)
+    }
+
+    pub(crate) fn heartbeat_timeout(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_timeout)
+    }
+
+    pub(crate) fn logon_timeout(&self) -> Duration {
+        Duration::from_secs(self.logon_timeout)
+    }
+
+    pub(crate) fn response_timeout(&self) -> Duration {
+        Duration::from_secs(self.response_timeout)
+    }
+
+    // The tags the venue requires on messages of `msg_type`.
+    pub(crate) fn required_tags(&self, msg_type: char) -> Vec<(u32, &str)> {
+        self.required_tags
+            .get(&msg_type.to_string())
+            .into_iter()
+            .flatten()
+            .filter_map(|(tag, value)| Some((tag.parse().ok()?, value.as_str())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile = VenueProfile::parse(
+            r#"
+            name = "broker"
+            response_timeout = 5
+
+            [order]
+            symbol = "MSFT"
+
+            [required_tags.D]
+            1 = "ACCOUNT1"
+            100 = "XNAS"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(profile.name, "broker");
+        assert_eq!(profile.response_timeout(), Duration::from_secs(5));
+        assert_eq!(profile.logon_timeout(), Duration::from_secs(10));
+        assert_eq!(profile.order.symbol, "MSFT");
+        assert_eq!(profile.order.side, "1");
+        assert_eq!(profile.required_tags('D'), vec![(1, "ACCOUNT1"), (100, "XNAS")]);
+        assert!(profile.required_tags('F').is_empty());
+
+        assert!(VenueProfile::parse("[required_tags.D]\nAccount = \"A\"").is_err());
+        assert!(VenueProfile::parse("unknown = 1").is_err());
+    }
+}
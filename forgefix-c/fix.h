@@ -8,16 +8,34 @@
 #include "fix_fields.h"
 
 typedef enum c_fix_error {
-  C_FIX_ERROR_OK,
-  C_FIX_ERROR_IO_ERROR,
-  C_FIX_ERROR_SESSION_ENDED,
-  C_FIX_ERROR_LOGON_FAILED,
-  C_FIX_ERROR_LOGOUT_FAILED,
-  C_FIX_ERROR_SEND_MESSAGE_FAILED,
-  C_FIX_ERROR_NULL_POINTER,
-  C_FIX_ERROR_BAD_STRING,
-  C_FIX_ERROR_SETTING_REQUIRED,
-  C_FIX_ERROR_UNKNOWN,
+  C_FIX_ERROR_OK = 0,
+  C_FIX_ERROR_IO_ERROR = 1,
+  C_FIX_ERROR_SESSION_ENDED = 2,
+  C_FIX_ERROR_LOGON_FAILED = 3,
+  C_FIX_ERROR_LOGOUT_FAILED = 4,
+  C_FIX_ERROR_SEND_MESSAGE_FAILED = 5,
+  C_FIX_ERROR_NULL_POINTER = 6,
+  C_FIX_ERROR_BAD_STRING = 7,
+  C_FIX_ERROR_SETTING_REQUIRED = 8,
+  C_FIX_ERROR_UNKNOWN = 9,
+  C_FIX_ERROR_SESSION_PAUSED = 10,
+  C_FIX_ERROR_ALREADY_SENT = 11,
+  C_FIX_ERROR_UNKNOWN_SESSION = 12,
+  C_FIX_ERROR_DUPLICATE_SESSION = 13,
+  C_FIX_ERROR_QUEUE_FULL = 14,
+  C_FIX_ERROR_NO_CL_ORD_ID = 15,
+  C_FIX_ERROR_ACK_TIMED_OUT = 16,
+  C_FIX_ERROR_INVALID_MESSAGE = 17,
+  C_FIX_ERROR_BAD_VALUE = 18,
+  C_FIX_ERROR_INVALID_SEQUENCE_NUMBER = 19,
+  C_FIX_ERROR_RESERVED_HEADER_TAG = 20,
+  C_FIX_ERROR_TIMEOUT = 21,
+  C_FIX_ERROR_BUFFER_TOO_SMALL = 22,
+  C_FIX_ERROR_FIELD_NOT_FOUND = 23,
+  C_FIX_ERROR_INVALID_HOST = 24,
+  C_FIX_ERROR_RATE_LIMITED = 25,
+  C_FIX_ERROR_MESSAGE_TOO_LARGE = 26,
+  C_FIX_ERROR_UNSUPPORTED_BEGIN_STRING = 27,
 } c_fix_error;
 
 typedef struct BlockingFixApplicationClient BlockingFixApplicationClient;
@@ -38,6 +56,8 @@ typedef struct SessionSettingsBuilder *session_settings_builder_t;
 
 fix_app_client_t fix_app_client_build(session_settings_t settings);
 
+fix_app_client_t fix_app_client_build_subscribed(session_settings_t settings);
+
 enum c_fix_error fix_app_client_start(fix_app_client_t client);
 
 enum c_fix_error fix_app_client_end(fix_app_client_t client);
@@ -46,6 +66,21 @@ void fix_app_client_free(fix_app_client_t client);
 
 enum c_fix_error fix_app_client_send_message(fix_app_client_t client, message_builder_t builder);
 
+enum c_fix_error fix_app_client_poll_message(fix_app_client_t client,
+                                             unsigned long timeout_ms,
+                                             uint8_t *out_buf,
+                                             uintptr_t *out_len);
+
+enum c_fix_error fix_message_get_field(const uint8_t *msg,
+                                       uintptr_t msg_len,
+                                       uint32_t tag,
+                                       const uint8_t **out_value,
+                                       uintptr_t *out_value_len);
+
+enum c_fix_error fix_app_client_pause(fix_app_client_t client);
+
+enum c_fix_error fix_app_client_resume(fix_app_client_t client);
+
 message_builder_t message_builder_new(const char *begin_string, char msg_type);
 
 enum c_fix_error message_builder_push_str(message_builder_t builder,
@@ -56,6 +91,14 @@ enum c_fix_error message_builder_push_int(message_builder_t builder,
                                           tags tag_param,
                                           intptr_t value);
 
+enum c_fix_error message_builder_push_i64(message_builder_t builder, tags tag_param, int64_t value);
+
+enum c_fix_error message_builder_push_u64(message_builder_t builder,
+                                          tags tag_param,
+                                          uint64_t value);
+
+enum c_fix_error message_builder_push_f64(message_builder_t builder, tags tag_param, double value);
+
 enum c_fix_error message_builder_push_field(message_builder_t builder,
                                             tags tag_param,
                                             intptr_t value);
@@ -74,6 +117,8 @@ enum c_fix_error ssb_set_target_comp_id(session_settings_builder_t builder,
 
 enum c_fix_error ssb_set_socket_addr(session_settings_builder_t builder, const char *addr);
 
+enum c_fix_error ssb_set_host(session_settings_builder_t builder, const char *host);
+
 enum c_fix_error ssb_set_begin_string(session_settings_builder_t builder, const char *begin_string);
 
 enum c_fix_error ssb_set_epoch(session_settings_builder_t builder, const char *epoch);
@@ -87,6 +132,11 @@ enum c_fix_error ssb_set_heartbeat_timeout(session_settings_builder_t builder,
 
 enum c_fix_error ssb_set_start_time(session_settings_builder_t builder, const char *start_time_str);
 
+enum c_fix_error ssb_set_reset_seq_num(session_settings_builder_t builder, bool reset_seq_num);
+
+enum c_fix_error ssb_set_reset_flag_on_initial_logon(session_settings_builder_t builder,
+                                                     bool reset_flag_on_initial_logon);
+
 session_settings_t ssb_build(session_settings_builder_t builder);
 
 void session_settings_builder_free(session_settings_builder_t builder);
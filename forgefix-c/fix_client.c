@@ -38,7 +38,7 @@ int c_main(const char *log, const char *store) {
         return C_FIX_ERROR_UNKNOWN;
     }
 
-    fix_app_client_t app = fix_app_client_build(settings); 
+    fix_app_client_t app = fix_app_client_build_subscribed(settings); 
     if (app == NULL) {
         printf("fix_app_client failed to build\n");
         return C_FIX_ERROR_UNKNOWN; 
@@ -65,6 +65,21 @@ int c_main(const char *log, const char *store) {
 
     sleep(1); 
 
+    uint8_t buf[4096];
+    uintptr_t len = sizeof(buf);
+    while ((err = fix_app_client_poll_message(app, 500, buf, &len)) == C_FIX_ERROR_OK) {
+        const uint8_t *cl_ord_id;
+        uintptr_t cl_ord_id_len;
+        if (fix_message_get_field(buf, len, TAGS_CL_ORD_ID, &cl_ord_id, &cl_ord_id_len) == C_FIX_ERROR_OK) {
+            printf("received a message for order %.*s\n", (int) cl_ord_id_len, (const char*) cl_ord_id);
+        }
+        len = sizeof(buf);
+    }
+    if (err != C_FIX_ERROR_TIMEOUT) {
+        printf("fix_app_client failed to poll a message\n");
+        return err;
+    }
+
     err = fix_app_client_end(app);
     if (err) {
         printf("fix_app_client failed to end\n");
@@ -102,7 +117,7 @@ c_fix_error send_order(
     message_builder_push_str(mb, TAGS_ACCOUNT, account);
     message_builder_push_str(mb, TAGS_CL_ORD_ID, sguid); 
     message_builder_push_field(mb, TAGS_ID_SOURCE, ID_SOURCE);
-    message_builder_push_int(mb, TAGS_ORDER_QTY, qty); 
+    message_builder_push_i64(mb, TAGS_ORDER_QTY, qty); 
     message_builder_push_field(mb, TAGS_ORD_TYPE, ORD_TYPE_LIMIT); 
     message_builder_push_str(mb, TAGS_PRICE, price); 
     message_builder_push_str(mb, TAGS_SECURITY_ID, symbol);
@@ -1,26 +1,51 @@
+use forgefix::fix::blocking::BlockingReceiver;
+use forgefix::fix::decode::grouped_fields;
 use forgefix::fix::encode::SerializedInt;
 use forgefix::fix::generated::Tags;
+use forgefix::fix::mem::MsgBuf;
 use forgefix::{SessionSettingsBuilder, SessionSettings, ApplicationError, FixApplicationHandle, FixApplicationInitiator};
 
 use std::ffi::{c_char, c_ulong, CStr};
 use std::net::SocketAddr;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
 use std::time::Duration;
 
 const TIME_FORMAT: &str = "%H:%M:%S";
 
+// The codes are part of the ABI of compiled clients: a code keeps its value, and new codes are
+// added after the last one.
 #[repr(C)]
 #[derive(Debug)]
 pub enum CFixError {
-    OK,
-    IoError,
-    SessionEnded,
-    LogonFailed,
-    LogoutFailed,
-    SendMessageFailed,
-    NullPointer,
-    BadString,
-    SettingRequired, 
-    Unknown,
+    OK = 0,
+    IoError = 1,
+    SessionEnded = 2,
+    LogonFailed = 3,
+    LogoutFailed = 4,
+    SendMessageFailed = 5,
+    NullPointer = 6,
+    BadString = 7,
+    SettingRequired = 8,
+    Unknown = 9,
+    SessionPaused = 10,
+    AlreadySent = 11,
+    UnknownSession = 12,
+    DuplicateSession = 13,
+    QueueFull = 14,
+    NoClOrdId = 15,
+    AckTimedOut = 16,
+    InvalidMessage = 17,
+    BadValue = 18,
+    InvalidSequenceNumber = 19,
+    ReservedHeaderTag = 20,
+    Timeout = 21,
+    BufferTooSmall = 22,
+    FieldNotFound = 23,
+    InvalidHost = 24,
+    RateLimited = 25,
+    MessageTooLarge = 26,
+    UnsupportedBeginString = 27,
 }
 
 impl<T> From<Result<T, ApplicationError>> for CFixError {
@@ -28,11 +53,25 @@ impl<T> From<Result<T, ApplicationError>> for CFixError {
         match res {
             Ok(_) => CFixError::OK,
             Err(ApplicationError::IoError(_)) => CFixError::IoError,
-            Err(ApplicationError::SessionEnded) => CFixError::SessionEnded,
+            Err(ApplicationError::SessionEnded(..)) => CFixError::SessionEnded,
             Err(ApplicationError::LogonFailed) => CFixError::LogonFailed,
             Err(ApplicationError::LogoutFailed) => CFixError::LogoutFailed,
             Err(ApplicationError::SendMessageFailed) => CFixError::SendMessageFailed,
+            Err(ApplicationError::SessionPaused) => CFixError::SessionPaused,
+            Err(ApplicationError::AlreadySent) => CFixError::AlreadySent,
             Err(ApplicationError::SettingRequired(..)) => CFixError::SettingRequired,
+            Err(ApplicationError::UnknownSession(..)) => CFixError::UnknownSession,
+            Err(ApplicationError::DuplicateSession(..)) => CFixError::DuplicateSession,
+            Err(ApplicationError::QueueFull) => CFixError::QueueFull,
+            Err(ApplicationError::NoClOrdId) => CFixError::NoClOrdId,
+            Err(ApplicationError::AckTimedOut) => CFixError::AckTimedOut,
+            Err(ApplicationError::InvalidMessage(..)) => CFixError::InvalidMessage,
+            Err(ApplicationError::InvalidSequenceNumber(..)) => CFixError::InvalidSequenceNumber,
+            Err(ApplicationError::ReservedHeaderTag(..)) => CFixError::ReservedHeaderTag,
+            Err(ApplicationError::InvalidHost(..)) => CFixError::InvalidHost,
+            Err(ApplicationError::RateLimited) => CFixError::RateLimited,
+            Err(ApplicationError::MessageTooLarge { .. }) => CFixError::MessageTooLarge,
+            Err(ApplicationError::UnsupportedBeginString(..)) => CFixError::UnsupportedBeginString,
         }
     }
 }
@@ -40,16 +79,35 @@ impl<T> From<Result<T, ApplicationError>> for CFixError {
 #[allow(non_camel_case_types)]
 pub type fix_app_client_t = *mut BlockingFixApplicationClient;
 
+/// Builds a client that discards the application messages received from the peer. Use
+/// `fix_app_client_build_subscribed` to receive them with `fix_app_client_poll_message`.
+///
 /// # Safety
 ///
 /// This function should be called with Utf-8 valid strings.
 #[no_mangle]
 pub unsafe extern "C" fn fix_app_client_build(settings: session_settings_t) -> fix_app_client_t {
+    build_client(settings, false)
+}
+
+/// Builds a client that keeps the application messages received from the peer until they are
+/// polled with `fix_app_client_poll_message`. Messages that are never polled are kept for the
+/// lifetime of the client.
+///
+/// # Safety
+///
+/// This function should be called with Utf-8 valid strings.
+#[no_mangle]
+pub unsafe extern "C" fn fix_app_client_build_subscribed(settings: session_settings_t) -> fix_app_client_t {
+    build_client(settings, true)
+}
+
+unsafe fn build_client(settings: session_settings_t, subscribe: bool) -> fix_app_client_t {
     if settings.is_null() {
         return std::ptr::null_mut();
     }
 
-    let fix_app = match BlockingFixApplicationClient::build((*settings).clone()) {
+    let fix_app = match BlockingFixApplicationClient::build((*settings).clone(), subscribe) {
         Ok(app) => app,
         Err(_) => return std::ptr::null_mut(),
     };
@@ -104,18 +162,127 @@ pub unsafe extern "C" fn fix_app_client_send_message(
     (*client).send_message(builder).into()
 }
 
+/// Waits up to `timeout_ms` milliseconds for the next application message received from the peer,
+/// and copies it to `out_buf`. `out_len` holds the capacity of `out_buf` when called, and the
+/// length of the message on return.
+///
+/// Returns `TIMEOUT` if no message arrived in time, and `SESSION_ENDED` once the session has ended
+/// and every message was polled, or at once if the client was not built with
+/// `fix_app_client_build_subscribed`. If the message is longer than `out_buf`, `BUFFER_TOO_SMALL` is
+/// returned with its length in `out_len`, and the message is kept for the next call.
+///
+/// # Safety
+///
+/// None of the pointers should be NULL, and `out_buf` should be valid for writes of `*out_len`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fix_app_client_poll_message(
+    client: fix_app_client_t,
+    timeout_ms: c_ulong,
+    out_buf: *mut u8,
+    out_len: *mut usize,
+) -> CFixError {
+    if client.is_null() || out_buf.is_null() || out_len.is_null() {
+        return CFixError::NullPointer;
+    }
+    let msg = match (*client).poll_message(Duration::from_millis(timeout_ms)) {
+        Ok(msg) => msg,
+        Err(RecvTimeoutError::Timeout) => return CFixError::Timeout,
+        Err(RecvTimeoutError::Disconnected) => return CFixError::SessionEnded,
+    };
+    let capacity = *out_len;
+    *out_len = msg.len();
+    if msg.len() > capacity {
+        (*client).pending = Some(msg);
+        return CFixError::BufferTooSmall;
+    }
+    std::ptr::copy_nonoverlapping(msg.0.as_ptr(), out_buf, msg.len());
+    CFixError::OK
+}
+
+/// Finds the first field with `tag` in the message of `msg_len` bytes at `msg`, such as one
+/// returned by `fix_app_client_poll_message`. On success, `out_value` points to the value inside
+/// `msg`, which is not NUL terminated, and `out_value_len` holds its length.
+///
+/// Returns `FIELD_NOT_FOUND` if the message has no such field, and `BAD_VALUE` if it is not a
+/// well formed FIX message.
+///
+/// # Safety
+///
+/// None of the pointers should be NULL, and `msg` should be valid for reads of `msg_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fix_message_get_field(
+    msg: *const u8,
+    msg_len: usize,
+    tag: u32,
+    out_value: *mut *const u8,
+    out_value_len: *mut usize,
+) -> CFixError {
+    if msg.is_null() || out_value.is_null() || out_value_len.is_null() {
+        return CFixError::NullPointer;
+    }
+    let msg = std::slice::from_raw_parts(msg, msg_len);
+    for field in grouped_fields(msg, &[]) {
+        match field {
+            Ok(field) if field.tag == tag => {
+                *out_value = field.value.as_ptr();
+                *out_value_len = field.value.len();
+                return CFixError::OK;
+            }
+            Ok(_) => {}
+            Err(_) => return CFixError::BadValue,
+        }
+    }
+    CFixError::FieldNotFound
+}
+
+/// # Safety
+///
+/// fix_app_client_t should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn fix_app_client_pause(client: fix_app_client_t) -> CFixError {
+    if client.is_null() {
+        return CFixError::NullPointer;
+    }
+    (*client).pause();
+    CFixError::OK
+}
+
+/// # Safety
+///
+/// fix_app_client_t should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn fix_app_client_resume(client: fix_app_client_t) -> CFixError {
+    if client.is_null() {
+        return CFixError::NullPointer;
+    }
+    (*client).resume();
+    CFixError::OK
+}
+
 pub struct BlockingFixApplicationClient {
     inner: FixApplicationHandle,
+    receiver: BlockingReceiver,
+    // a polled message that did not fit in the buffer of the caller
+    pending: Option<Arc<MsgBuf>>,
 }
 
 impl BlockingFixApplicationClient {
     #[allow(clippy::too_many_arguments)]
-    pub fn build(settings: SessionSettings) -> Result<BlockingFixApplicationClient, ApplicationError> {
+    pub fn build(settings: SessionSettings, subscribe: bool) -> Result<BlockingFixApplicationClient, ApplicationError> {
         let fix_app_initiator = FixApplicationInitiator::build(settings)?;
-        let (inner, mut event_receiver) = fix_app_initiator.initiate_sync()?; 
-        event_receiver.close();
+        let (inner, receiver) = fix_app_initiator.initiate_sync()?; 
+        let mut receiver = BlockingReceiver::new(receiver);
+        // unless subscribed, incoming messages are dropped instead of piling up unread
+        if !subscribe {
+            receiver.close();
+        }
 
-        Ok(BlockingFixApplicationClient { inner })
+        Ok(BlockingFixApplicationClient {
+            inner,
+            receiver,
+            pending: None,
+        })
     }
 
     pub fn start(&mut self) -> Result<(), ApplicationError> {
@@ -129,6 +296,21 @@ impl BlockingFixApplicationClient {
     pub fn send_message(&mut self, builder: MessageBuilder) -> Result<(), ApplicationError> {
         self.inner.send_message_sync(builder)
     }
+
+    pub fn poll_message(&mut self, timeout: Duration) -> Result<Arc<MsgBuf>, RecvTimeoutError> {
+        match self.pending.take() {
+            Some(msg) => Ok(msg),
+            None => self.receiver.recv_timeout(timeout),
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.inner.pause()
+    }
+
+    pub fn resume(&mut self) {
+        self.inner.resume()
+    }
 }
 
 pub type MessageBuilder = forgefix::fix::encode::MessageBuilder;
@@ -176,19 +358,67 @@ pub unsafe extern "C" fn message_builder_push_str(
 /// # Safety
 ///
 /// The message_builder_t should not be NULL.
+#[deprecated(note = "the width of `isize` depends on the platform, use `message_builder_push_i64`")]
 #[no_mangle]
 pub unsafe extern "C" fn message_builder_push_int(
     builder: message_builder_t,
     tag_param: Tags,
     value: isize,
+) -> CFixError {
+    message_builder_push_i64(builder, tag_param, value as i64)
+}
+
+/// # Safety
+///
+/// The message_builder_t should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn message_builder_push_i64(
+    builder: message_builder_t,
+    tag_param: Tags,
+    value: i64,
 ) -> CFixError {
     if builder.is_null() {
         return CFixError::NullPointer;
     }
-    (*builder).push_mut(
-        tag_param as u32,
-        SerializedInt::from(value as u32).as_bytes(),
-    );
+    (*builder).push_mut(tag_param as u32, SerializedInt::from(value).as_bytes());
+    CFixError::OK
+}
+
+/// # Safety
+///
+/// The message_builder_t should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn message_builder_push_u64(
+    builder: message_builder_t,
+    tag_param: Tags,
+    value: u64,
+) -> CFixError {
+    if builder.is_null() {
+        return CFixError::NullPointer;
+    }
+    (*builder).push_mut(tag_param as u32, SerializedInt::from(value).as_bytes());
+    CFixError::OK
+}
+
+/// Pushes `value` in decimal notation, with the fewest digits that read back as the same
+/// `double`. Returns `BadValue` for NaN and infinities, which FIX cannot represent.
+///
+/// # Safety
+///
+/// The message_builder_t should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn message_builder_push_f64(
+    builder: message_builder_t,
+    tag_param: Tags,
+    value: f64,
+) -> CFixError {
+    if builder.is_null() {
+        return CFixError::NullPointer;
+    }
+    if !value.is_finite() {
+        return CFixError::BadValue;
+    }
+    (*builder).push_mut(tag_param as u32, value.to_string().as_bytes());
     CFixError::OK
 }
 
@@ -310,6 +540,29 @@ pub unsafe extern "C" fn ssb_set_socket_addr(
     CFixError::OK
 }
 
+/// Set the `host:port` to connect to or listen on, such as `fix.broker.com:9878`, instead of a
+/// socket address. An initiator resolves the host each time it connects.
+///
+/// # Safety
+///
+/// This function should be called with Utf-8 valid strings.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_set_host(
+    builder: session_settings_builder_t,
+    host: *const c_char,
+) -> CFixError {
+    if builder.is_null() || host.is_null() {
+        return CFixError::NullPointer;
+    }
+
+    let host = match CStr::from_ptr(host).to_str() {
+        Ok(s) => s,
+        Err(_) => return CFixError::BadString,
+    };
+    (*builder).set_host(host);
+    CFixError::OK
+}
+
 /// # Safety
 ///
 /// The pointers should not be NULL.
@@ -434,6 +687,36 @@ pub unsafe extern "C" fn ssb_set_start_time(
     CFixError::OK
 }
 
+/// # Safety
+///
+/// The pointers should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_set_reset_seq_num(
+    builder: session_settings_builder_t,
+    reset_seq_num: bool,
+) -> CFixError {
+    if builder.is_null() {
+        return CFixError::NullPointer; 
+    }
+    (*builder).set_reset_seq_num(reset_seq_num);
+    CFixError::OK
+}
+
+/// # Safety
+///
+/// The pointers should not be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_set_reset_flag_on_initial_logon(
+    builder: session_settings_builder_t,
+    reset_flag_on_initial_logon: bool,
+) -> CFixError {
+    if builder.is_null() {
+        return CFixError::NullPointer; 
+    }
+    (*builder).set_reset_flag_on_initial_logon(reset_flag_on_initial_logon);
+    CFixError::OK
+}
+
 /// # Safety
 ///
 /// The pointer should not be NULL.
@@ -458,3 +741,208 @@ pub unsafe extern "C" fn session_settings_builder_free(builder: session_settings
         drop(Box::from_raw(builder));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use forgefix::testing::Counterparty;
+
+    // A client of `counterparty`, started, and whether it keeps the messages it receives.
+    unsafe fn started_client(counterparty: &Counterparty, subscribe: bool) -> fix_app_client_t {
+        let settings = Box::into_raw(Box::new(SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr())));
+        let client = if subscribe {
+            fix_app_client_build_subscribed(settings)
+        } else {
+            fix_app_client_build(settings)
+        };
+        drop(Box::from_raw(settings));
+        assert!(!client.is_null());
+        assert!(matches!(fix_app_client_start(client), CFixError::OK));
+        client
+    }
+
+    #[test]
+    fn test_error_codes() {
+        // the codes of the first release
+        let codes = [
+            CFixError::OK,
+            CFixError::IoError,
+            CFixError::SessionEnded,
+            CFixError::LogonFailed,
+            CFixError::LogoutFailed,
+            CFixError::SendMessageFailed,
+            CFixError::NullPointer,
+            CFixError::BadString,
+            CFixError::SettingRequired,
+            CFixError::Unknown,
+        ];
+        for (value, code) in codes.into_iter().enumerate() {
+            assert_eq!(code as usize, value);
+        }
+        assert_eq!(CFixError::SessionPaused as u32, 10);
+        assert_eq!(CFixError::AlreadySent as u32, 11);
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        let mut buf = [0u8; 16];
+        let mut len = buf.len();
+        let mut value = std::ptr::null();
+        let mut value_len = 0;
+        unsafe {
+            assert!(fix_app_client_build_subscribed(std::ptr::null_mut()).is_null());
+            assert!(matches!(
+                fix_app_client_poll_message(std::ptr::null_mut(), 0, buf.as_mut_ptr(), &mut len),
+                CFixError::NullPointer
+            ));
+            assert!(matches!(
+                fix_message_get_field(std::ptr::null(), 0, 35, &mut value, &mut value_len),
+                CFixError::NullPointer
+            ));
+            assert!(matches!(
+                fix_message_get_field(buf.as_ptr(), buf.len(), 35, std::ptr::null_mut(), &mut value_len),
+                CFixError::NullPointer
+            ));
+            assert!(matches!(
+                fix_message_get_field(buf.as_ptr(), buf.len(), 35, &mut value, std::ptr::null_mut()),
+                CFixError::NullPointer
+            ));
+        }
+    }
+
+    #[test]
+    fn test_poll_message() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let counterparty = runtime.block_on(Counterparty::start("BROKER", "MY_ID")).unwrap();
+        unsafe {
+            let client = started_client(&counterparty, true);
+            let mut buf = vec![0u8; 1024];
+            let mut len = buf.len();
+            assert!(matches!(
+                fix_app_client_poll_message(client, 10, std::ptr::null_mut(), &mut len),
+                CFixError::NullPointer
+            ));
+            assert!(matches!(
+                fix_app_client_poll_message(client, 10, buf.as_mut_ptr(), &mut len),
+                CFixError::Timeout
+            ));
+
+            let snapshot = MessageBuilder::new("FIX.4.2", 'W')
+                .push(Tags::Symbol, b"AAPL")
+                .push(Tags::NoMDEntries, b"2")
+                .push(Tags::MDEntryType, b"0")
+                .push(Tags::MDEntryPx, b"1.00")
+                .push(Tags::MDEntryType, b"1")
+                .push(Tags::MDEntryPx, b"1.01");
+            counterparty.send(snapshot);
+
+            // the message is kept until a buffer large enough is passed
+            let mut small = [0u8; 8];
+            let mut small_len = small.len();
+            assert!(matches!(
+                fix_app_client_poll_message(client, 5000, small.as_mut_ptr(), &mut small_len),
+                CFixError::BufferTooSmall
+            ));
+            assert!(small_len > small.len());
+            assert!(matches!(
+                fix_app_client_poll_message(client, 0, buf.as_mut_ptr(), &mut len),
+                CFixError::OK
+            ));
+            assert_eq!(len, small_len);
+            let msg = &buf[..len];
+
+            let mut value = std::ptr::null();
+            let mut value_len = 0;
+            let mut field = |tag: Tags| {
+                let res = fix_message_get_field(msg.as_ptr(), msg.len(), tag as u32, &mut value, &mut value_len);
+                matches!(res, CFixError::OK).then(|| std::slice::from_raw_parts(value, value_len).to_vec())
+            };
+            assert_eq!(field(Tags::Symbol).as_deref(), Some(&b"AAPL"[..]));
+            // the first entry of the repeating group
+            assert_eq!(field(Tags::MDEntryPx).as_deref(), Some(&b"1.00"[..]));
+            assert_eq!(field(Tags::ClOrdID), None);
+            assert!(matches!(
+                fix_message_get_field(msg.as_ptr(), msg.len(), Tags::ClOrdID as u32, &mut value, &mut value_len),
+                CFixError::FieldNotFound
+            ));
+            let garbled = b"8=FIX.4.2\x019=5\x0135=W\x01x=1\x01";
+            assert!(matches!(
+                fix_message_get_field(garbled.as_ptr(), garbled.len(), Tags::ClOrdID as u32, &mut value, &mut value_len),
+                CFixError::BadValue
+            ));
+
+            assert!(matches!(fix_app_client_end(client), CFixError::OK));
+            fix_app_client_free(client);
+        }
+    }
+
+    #[test]
+    fn test_poll_message_unsubscribed() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let counterparty = runtime.block_on(Counterparty::start("BROKER", "MY_ID")).unwrap();
+        unsafe {
+            let client = started_client(&counterparty, false);
+            let mut buf = vec![0u8; 1024];
+            let mut len = buf.len();
+            // returns at once, instead of waiting for the timeout
+            let polled = std::time::Instant::now();
+            assert!(matches!(
+                fix_app_client_poll_message(client, 60_000, buf.as_mut_ptr(), &mut len),
+                CFixError::SessionEnded
+            ));
+            assert!(polled.elapsed() < Duration::from_secs(10));
+            assert!(matches!(fix_app_client_end(client), CFixError::OK));
+            fix_app_client_free(client);
+        }
+    }
+
+    // The value pushed by `push` to a new builder.
+    unsafe fn pushed(push: impl FnOnce(message_builder_t) -> CFixError) -> Result<Vec<u8>, CFixError> {
+        let begin_string = std::ffi::CString::new("FIX.4.2").unwrap();
+        let builder = message_builder_new(begin_string.as_ptr(), b'D' as c_char);
+        let res = match push(builder) {
+            CFixError::OK => Ok((*builder).field(Tags::Price as u32).unwrap().to_vec()),
+            e => Err(e),
+        };
+        message_builder_free(builder);
+        res
+    }
+
+    #[test]
+    fn test_push_numbers() {
+        unsafe {
+            let i64_value = |value| pushed(|builder| message_builder_push_i64(builder, Tags::Price, value)).unwrap();
+            assert_eq!(i64_value(i64::MIN), i64::MIN.to_string().as_bytes());
+            assert_eq!(i64_value(i64::MAX), i64::MAX.to_string().as_bytes());
+            assert_eq!(i64_value(0), b"0");
+            let u64_value = |value| pushed(|builder| message_builder_push_u64(builder, Tags::Price, value)).unwrap();
+            assert_eq!(u64_value(u64::MAX), b"18446744073709551615");
+
+            // the deprecated `isize` variant writes the same as `message_builder_push_i64`
+            #[allow(deprecated)]
+            let int_value = |value| pushed(|builder| message_builder_push_int(builder, Tags::Price, value)).unwrap();
+            for value in [isize::MIN, -1, 0, 42, isize::MAX] {
+                assert_eq!(int_value(value), i64_value(value as i64));
+            }
+
+            // the shortest decimal that reads back as the same `double`
+            let f64_value = |value| pushed(|builder| message_builder_push_f64(builder, Tags::Price, value));
+            assert_eq!(f64_value(0.1).unwrap(), b"0.1");
+            assert_eq!(f64_value(-1.5).unwrap(), b"-1.5");
+            assert_eq!(f64_value(100.0).unwrap(), b"100");
+            for value in [0.1 + 0.2, 1.0 / 3.0, f64::MIN_POSITIVE, f64::MAX, 123456.789] {
+                let pushed = f64_value(value).unwrap();
+                let pushed = std::str::from_utf8(&pushed).unwrap();
+                assert!(pushed.bytes().all(|b| b.is_ascii_digit() || b == b'.' || b == b'-'));
+                assert_eq!(pushed.parse::<f64>().unwrap(), value);
+            }
+            for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+                assert!(matches!(f64_value(value), Err(CFixError::BadValue)));
+            }
+
+            assert!(matches!(message_builder_push_i64(std::ptr::null_mut(), Tags::Price, 1), CFixError::NullPointer));
+            assert!(matches!(message_builder_push_u64(std::ptr::null_mut(), Tags::Price, 1), CFixError::NullPointer));
+            assert!(matches!(message_builder_push_f64(std::ptr::null_mut(), Tags::Price, 1.0), CFixError::NullPointer));
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Python bindings for ForgeFIX
+//!
+//! The module mirrors the C API of `forgefix-c`: build `SessionSettings`, initiate a connection
+//! with a `FixApplicationInitiator`, then start, send messages on and end the session with the
+//! returned `FixApplicationHandle`. Incoming application messages are read from the returned
+//! `Messages`, as `bytes`.
+//!
+//! ```python
+//! import forgefix
+//!
+//! settings = forgefix.SessionSettings(
+//!     sender_comp_id="TW",
+//!     target_comp_id="ISLD",
+//!     addr="127.0.0.1:9000",
+//!     store_path="./store",
+//!     log_dir="./log",
+//! )
+//! handle, messages = forgefix.FixApplicationInitiator(settings).initiate()
+//! handle.start()
+//!
+//! order = forgefix.MessageBuilder("FIX.4.2", "D")
+//! order.push(11, "ID1")
+//! order.push(38, 100)
+//! order.push_current_time(60)
+//! handle.send_message(order)
+//!
+//! for msg in messages:
+//!     print(msg)
+//! ```
+//!
+//! Every call that waits on the engine releases the GIL, so other Python threads keep running
+//! while a thread is blocked receiving messages or logging on.
+
+use forgefix::fix::blocking::BlockingReceiver;
+use forgefix::fix::encode::SerializedInt;
+use forgefix::{ApplicationError, SessionSettingsBuilder};
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTimeoutError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyFloat, PyInt, PyString};
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+const TIME_FORMAT: &str = "%H:%M:%S";
+
+// How long a blocking receive waits between checks for signals, such as a KeyboardInterrupt.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+create_exception!(forgefix, FixError, PyException, "An error returned by the FIX engine.");
+
+fn to_py_err(e: ApplicationError) -> PyErr {
+    FixError::new_err(e.to_string())
+}
+
+/// The settings of a FIX session.
+///
+/// `sender_comp_id`, `target_comp_id`, `addr`, `store_path` and `log_dir` are required. The `addr`
+/// is a socket address or a `host:port`, which is resolved each time the initiator connects. The
+/// `heartbeat_timeout` is in seconds, and the `start_time` is a UTC time formatted `HH:MM:SS`.
+#[pyclass(frozen)]
+struct SessionSettings {
+    inner: forgefix::SessionSettings,
+}
+
+#[pymethods]
+impl SessionSettings {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        sender_comp_id,
+        target_comp_id,
+        addr,
+        store_path,
+        log_dir,
+        begin_string = None,
+        epoch = None,
+        heartbeat_timeout = None,
+        start_time = None,
+        reset_seq_num = None,
+        reset_flag_on_initial_logon = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        sender_comp_id: &str,
+        target_comp_id: &str,
+        addr: &str,
+        store_path: PathBuf,
+        log_dir: PathBuf,
+        begin_string: Option<&str>,
+        epoch: Option<&str>,
+        heartbeat_timeout: Option<u64>,
+        start_time: Option<&str>,
+        reset_seq_num: Option<bool>,
+        reset_flag_on_initial_logon: Option<bool>,
+    ) -> PyResult<SessionSettings> {
+        let mut builder = SessionSettingsBuilder::new()
+            .with_sender_comp_id(sender_comp_id)
+            .with_target_comp_id(target_comp_id)
+            .with_store_path(store_path)
+            .with_log_dir(log_dir);
+        match addr.parse::<SocketAddr>() {
+            Ok(addr) => builder.set_socket_addr(addr),
+            Err(_) => builder.set_host(addr),
+        }
+        if let Some(begin_string) = begin_string {
+            builder.set_begin_string(begin_string);
+        }
+        if let Some(epoch) = epoch {
+            builder.set_epoch(epoch);
+        }
+        if let Some(heartbeat_timeout) = heartbeat_timeout {
+            builder.set_heartbeat_timeout(Duration::from_secs(heartbeat_timeout));
+        }
+        if let Some(start_time) = start_time {
+            let start_time = chrono::NaiveTime::parse_from_str(start_time, TIME_FORMAT)
+                .map_err(|e| PyValueError::new_err(format!("bad start_time `{start_time}`: {e}")))?;
+            builder.set_start_time(start_time);
+        }
+        if let Some(reset_seq_num) = reset_seq_num {
+            builder.set_reset_seq_num(reset_seq_num);
+        }
+        if let Some(reset_flag_on_initial_logon) = reset_flag_on_initial_logon {
+            builder.set_reset_flag_on_initial_logon(reset_flag_on_initial_logon);
+        }
+        Ok(SessionSettings {
+            inner: builder.build().map_err(to_py_err)?,
+        })
+    }
+}
+
+/// Initiates a TCP connection to the address of its `SessionSettings`.
+#[pyclass]
+struct FixApplicationInitiator {
+    inner: Option<forgefix::FixApplicationInitiator>,
+}
+
+#[pymethods]
+impl FixApplicationInitiator {
+    #[new]
+    fn new(settings: &SessionSettings) -> PyResult<FixApplicationInitiator> {
+        let inner = forgefix::FixApplicationInitiator::build(settings.inner.clone()).map_err(to_py_err)?;
+        Ok(FixApplicationInitiator { inner: Some(inner) })
+    }
+
+    /// Connect to the peer, and return the `FixApplicationHandle` of the engine along with the
+    /// `Messages` it receives. The engine runs on its own threads. Can only be called once.
+    fn initiate(&mut self, py: Python<'_>) -> PyResult<(FixApplicationHandle, Messages)> {
+        let initiator = self
+            .inner
+            .take()
+            .ok_or_else(|| PyValueError::new_err("the initiator was already used"))?;
+        let (inner, receiver) = py
+            .allow_threads(|| initiator.initiate_sync())
+            .map_err(to_py_err)?;
+        let messages = Messages {
+            receiver: BlockingReceiver::new(receiver),
+        };
+        Ok((FixApplicationHandle { inner }, messages))
+    }
+}
+
+/// A handle on a running FIX engine.
+#[pyclass(frozen)]
+struct FixApplicationHandle {
+    inner: forgefix::FixApplicationHandle,
+}
+
+#[pymethods]
+impl FixApplicationHandle {
+    /// Log on, waiting until the peer answered.
+    fn start(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| self.inner.start_sync()).map_err(to_py_err)
+    }
+
+    /// Send the message of `builder`, which cannot be sent again.
+    fn send_message(&self, py: Python<'_>, builder: &mut MessageBuilder) -> PyResult<()> {
+        let builder = builder.take()?;
+        py.allow_threads(|| self.inner.send_message_sync(builder))
+            .map_err(to_py_err)
+    }
+
+    /// Log out, waiting until the peer answered or the logout timed out.
+    fn end(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| self.inner.end_sync()).map_err(to_py_err)
+    }
+
+    /// Refuse application messages until `resume` is called: `send_message` raises a `FixError`
+    /// and the message is not sent, nor kept to be sent later. Heartbeats and other session
+    /// messages continue as normal.
+    fn pause(&self) {
+        self.inner.pause()
+    }
+
+    /// Allow application messages to be sent again after `pause`.
+    fn resume(&self) {
+        self.inner.resume()
+    }
+}
+
+/// The application messages received by a FIX engine, as `bytes`.
+///
+/// Iterating blocks until the next message arrives, and stops once the engine has ended and
+/// every message was received.
+#[pyclass]
+struct Messages {
+    receiver: BlockingReceiver,
+}
+
+#[pymethods]
+impl Messages {
+    /// Receive the next message, waiting for at most `timeout` seconds if it is set. Returns
+    /// `None` once the engine has ended and every message was received, and raises a
+    /// `TimeoutError` if `timeout` elapsed first.
+    #[pyo3(signature = (timeout = None))]
+    fn recv(&mut self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<Py<PyBytes>>> {
+        let timeout = timeout
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(|e| PyValueError::new_err(format!("bad timeout: {e}")))?;
+        let mut remaining = timeout;
+        loop {
+            let wait = remaining.map_or(SIGNAL_CHECK_INTERVAL, |r| r.min(SIGNAL_CHECK_INTERVAL));
+            let receiver = &mut self.receiver;
+            match py.allow_threads(|| receiver.recv_timeout(wait)) {
+                Ok(msg) => return Ok(Some(PyBytes::new(py, &msg.0).unbind())),
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            py.check_signals()?;
+            if let Some(r) = remaining.as_mut() {
+                *r = r.saturating_sub(wait);
+                if r.is_zero() {
+                    return Err(PyTimeoutError::new_err("no message was received in time"));
+                }
+            }
+        }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyBytes>>> {
+        self.recv(py, None)
+    }
+}
+
+/// Builds a FIX message, field by field.
+///
+/// Values can be `str`, `bytes`, `int`, `float`, or `bool`, which is sent as `Y` or `N`.
+#[pyclass]
+struct MessageBuilder {
+    inner: Option<forgefix::fix::encode::MessageBuilder>,
+}
+
+impl MessageBuilder {
+    fn inner(&mut self) -> PyResult<&mut forgefix::fix::encode::MessageBuilder> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err("the message was already sent"))
+    }
+
+    fn take(&mut self) -> PyResult<forgefix::fix::encode::MessageBuilder> {
+        self.inner
+            .take()
+            .ok_or_else(|| PyValueError::new_err("the message was already sent"))
+    }
+}
+
+#[pymethods]
+impl MessageBuilder {
+    #[new]
+    fn new(begin_string: &str, msg_type: char) -> MessageBuilder {
+        MessageBuilder {
+            inner: Some(forgefix::fix::encode::MessageBuilder::new(begin_string, msg_type)),
+        }
+    }
+
+    /// Push a field with `tag` and `value`.
+    fn push(&mut self, tag: u32, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        // bool is checked first, as it is also an int
+        if let Ok(value) = value.downcast::<PyBool>() {
+            let value: &[u8] = if value.is_true() { b"Y" } else { b"N" };
+            self.inner()?.push_mut(tag, value);
+        } else if let Ok(value) = value.downcast::<PyString>() {
+            self.inner()?.push_mut(tag, value.to_str()?.as_bytes());
+        } else if let Ok(value) = value.downcast::<PyBytes>() {
+            self.inner()?.push_mut(tag, value.as_bytes());
+        } else if let Ok(value) = value.downcast::<PyInt>() {
+            let value: i64 = value.extract()?;
+            self.inner()?.push_mut(tag, SerializedInt::from(value).as_bytes());
+        } else if let Ok(value) = value.downcast::<PyFloat>() {
+            let value = value.value();
+            if !value.is_finite() {
+                return Err(PyValueError::new_err("NaN and infinities cannot be sent"));
+            }
+            self.inner()?.push_mut(tag, value.to_string().as_bytes());
+        } else {
+            return Err(PyTypeError::new_err(format!(
+                "cannot push a value of type {}",
+                value.get_type().name()?
+            )));
+        }
+        Ok(())
+    }
+
+    /// Push a field with `tag` and the current UTC time.
+    fn push_current_time(&mut self, tag: u32) -> PyResult<()> {
+        let now = forgefix::fix::encode::formatted_time();
+        self.inner()?.push_mut(tag, now.as_bytes());
+        Ok(())
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "forgefix")]
+fn forgefix_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("FixError", m.py().get_type::<FixError>())?;
+    m.add_class::<SessionSettings>()?;
+    m.add_class::<FixApplicationInitiator>()?;
+    m.add_class::<FixApplicationHandle>()?;
+    m.add_class::<Messages>()?;
+    m.add_class::<MessageBuilder>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pyo3::types::PyList;
+
+    fn settings(addr: &str, begin_string: Option<&str>) -> PyResult<SessionSettings> {
+        SessionSettings::new(
+            "TW",
+            "ISLD",
+            addr,
+            PathBuf::from("./store"),
+            PathBuf::from("./log"),
+            begin_string,
+            None,
+            Some(30),
+            Some("09:30:00"),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_settings() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            settings("127.0.0.1:9000", None).unwrap();
+            settings("localhost:9000", Some("FIX.4.2")).unwrap();
+
+            let e = settings("127.0.0.1:9000", Some("FIX.4.4")).err().unwrap();
+            assert!(e.is_instance_of::<FixError>(py));
+
+            let e = SessionSettings::new(
+                "TW",
+                "ISLD",
+                "127.0.0.1:9000",
+                PathBuf::from("./store"),
+                PathBuf::from("./log"),
+                None,
+                None,
+                None,
+                Some("9:30"),
+                None,
+                None,
+            )
+            .err()
+            .unwrap();
+            assert!(e.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn test_message_builder() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut builder = MessageBuilder::new("FIX.4.2", 'D');
+            builder.push(11, PyString::new(py, "ID1").as_any()).unwrap();
+            builder.push(38, 100i64.into_pyobject(py).unwrap().as_any()).unwrap();
+            builder.push(44, 10.5f64.into_pyobject(py).unwrap().as_any()).unwrap();
+            builder.push(21, PyBool::new(py, true).as_any()).unwrap();
+            builder.push(58, PyBytes::new(py, b"text").as_any()).unwrap();
+
+            let e = builder.push(59, PyList::empty(py).as_any()).unwrap_err();
+            assert!(e.is_instance_of::<PyTypeError>(py));
+            let e = builder.push(44, f64::NAN.into_pyobject(py).unwrap().as_any()).unwrap_err();
+            assert!(e.is_instance_of::<PyValueError>(py));
+
+            let inner = builder.take().unwrap();
+            assert_eq!(inner.field(11), Some(&b"ID1"[..]));
+            assert_eq!(inner.field(38), Some(&b"100"[..]));
+            assert_eq!(inner.field(44), Some(&b"10.5"[..]));
+            assert_eq!(inner.field(21), Some(&b"Y"[..]));
+            assert_eq!(inner.field(58), Some(&b"text"[..]));
+
+            let e = builder.push(11, PyString::new(py, "ID2").as_any()).unwrap_err();
+            assert!(e.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn test_errors() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let e = to_py_err(ApplicationError::SessionPaused);
+            assert!(e.is_instance_of::<FixError>(py));
+            assert!(e.is_instance_of::<PyException>(py));
+            assert_eq!(e.value(py).to_string(), ApplicationError::SessionPaused.to_string());
+        });
+    }
+}
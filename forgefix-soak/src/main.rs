@@ -0,0 +1,349 @@
+//! Soak test for ForgeFIX.
+//!
+//! Runs an acceptor and an initiator connected over loopback. The initiator sends
+//! `NewOrderSingle<D>` messages at a fixed rate, and the acceptor answers each with an
+//! `ExecutionReport<8>` carrying the same `ClOrdID(11)`.
+//!
+//! Every report interval, one JSON object is written to stdout with the round trip latency
+//! percentiles, resident memory and sequence counters of the interval. A final `summary` object
+//! records whether the run stayed within the configured bounds, and the process exits with a
+//! non-zero status if it did not.
+use clap::Parser;
+use forgefix::{
+    fix,
+    fix::decode::{parse, MessageParseError, ParserCallback},
+    fix::encode::{formatted_time, MessageBuilder, SerializedInt},
+    fix::generated::{ExecTransType, ExecType, MsgType, OrdStatus, OrdType, Side, Tags},
+    fix::mem::MsgBuf,
+    ApplicationError, FixApplicationAcceptor, FixApplicationHandle, FixApplicationInitiator,
+    SessionSettings,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+fn parse_duration(s: &str) -> Result<Duration, std::num::ParseIntError> {
+    let seconds = s.parse()?;
+    Ok(std::time::Duration::from_secs(seconds))
+}
+
+#[derive(Parser, Clone)]
+#[command(author, version, about, long_about = None)]
+struct Opts {
+    /// Orders sent per second
+    #[arg(long, default_value = "1000")]
+    rate: u32,
+
+    /// Length of the soak in seconds
+    #[arg(long, default_value = "3600", value_parser = parse_duration)]
+    duration: Duration,
+
+    /// Seconds between reports
+    #[arg(long, default_value = "10", value_parser = parse_duration)]
+    report_interval: Duration,
+
+    /// Fail if the p99 round trip latency of any interval exceeds this many microseconds
+    #[arg(long)]
+    max_p99_us: Option<u64>,
+
+    /// Fail if resident memory grows by more than this many KiB after the first interval
+    #[arg(long)]
+    max_rss_growth_kb: Option<u64>,
+
+    /// Seconds to wait for outstanding orders to be acknowledged after the last order is sent
+    #[arg(long, default_value = "10", value_parser = parse_duration)]
+    drain_timeout: Duration,
+
+    /// Directory for the stores and logs of both sessions
+    #[arg(long)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct ClOrdIdParser {
+    cl_ord_id: Option<u64>,
+}
+
+impl<'a> ParserCallback<'a> for ClOrdIdParser {
+    type Err = MessageParseError;
+    fn header(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let Ok(Tags::ClOrdID) = key.try_into() {
+            self.cl_ord_id = fix::decode::parse_field::<u64>(value).ok();
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+fn cl_ord_id(msg: &MsgBuf) -> Option<u64> {
+    let mut cb: ClOrdIdParser = Default::default();
+    parse(&msg[..], &mut cb).ok()?;
+    cb.cl_ord_id
+}
+
+fn settings(dir: &Path, sender: &str, target: &str, addr: SocketAddr) -> Result<SessionSettings, ApplicationError> {
+    SessionSettings::builder()
+        .with_sender_comp_id(sender)
+        .with_target_comp_id(target)
+        .with_socket_addr(addr)
+        .with_store_path(dir.join(format!("{sender}-store")))
+        .with_log_dir(dir.join(format!("{sender}-log")))
+        .build()
+}
+
+fn order(handle: &FixApplicationHandle, cl_ord_id: u64) -> MessageBuilder {
+    handle
+        .builder(MsgType::ORDER_SINGLE.into())
+        .push(Tags::ClOrdID, SerializedInt::from(cl_ord_id).as_bytes())
+        .push(Tags::HandlInst, b"1")
+        .push(Tags::Symbol, b"SOAK")
+        .push(Tags::Side, Side::BUY.into())
+        .push(Tags::OrderQty, b"100")
+        .push(Tags::OrdType, OrdType::MARKET.into())
+        .push(Tags::TransactTime, formatted_time().as_bytes())
+}
+
+fn execution_report(handle: &FixApplicationHandle, cl_ord_id: u64) -> MessageBuilder {
+    let cl_ord_id = SerializedInt::from(cl_ord_id);
+    handle
+        .builder(MsgType::EXECUTION_REPORT.into())
+        .push(Tags::OrderID, cl_ord_id.as_bytes())
+        .push(Tags::ClOrdID, cl_ord_id.as_bytes())
+        .push(Tags::ExecID, cl_ord_id.as_bytes())
+        .push(Tags::ExecTransType, ExecTransType::NEW.into())
+        .push(Tags::ExecType, ExecType::NEW.into())
+        .push(Tags::OrdStatus, OrdStatus::NEW.into())
+        .push(Tags::Symbol, b"SOAK")
+        .push(Tags::Side, Side::BUY.into())
+        .push(Tags::LeavesQty, b"100")
+        .push(Tags::CumQty, b"0")
+        .push(Tags::AvgPx, b"0")
+}
+
+// Resident set size of this process in KiB, read from procfs. `None` on platforms without it.
+fn rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((sorted.len() as f64) * p).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn json_opt(value: Option<u64>) -> String {
+    value.map_or_else(|| String::from("null"), |v| v.to_string())
+}
+
+#[derive(Default)]
+struct Stats {
+    sent: u64,
+    received: u64,
+    out_of_order: u64,
+    unknown: u64,
+    expected_next: u64,
+    pending: HashMap<u64, Instant>,
+    latencies_us: Vec<u64>,
+}
+
+impl Stats {
+    fn sent(&mut self, cl_ord_id: u64) {
+        self.sent += 1;
+        self.pending.insert(cl_ord_id, Instant::now());
+    }
+
+    fn received(&mut self, cl_ord_id: Option<u64>) {
+        let Some(sent_at) = cl_ord_id.and_then(|id| self.pending.remove(&id)) else {
+            self.unknown += 1;
+            return;
+        };
+        let cl_ord_id = cl_ord_id.unwrap();
+        self.received += 1;
+        if cl_ord_id != self.expected_next {
+            self.out_of_order += 1;
+        }
+        self.expected_next = cl_ord_id + 1;
+        self.latencies_us.push(sent_at.elapsed().as_micros() as u64);
+    }
+}
+
+struct Report {
+    max_p99_us: u64,
+    rss_kb_start: Option<u64>,
+    rss_kb_max: Option<u64>,
+}
+
+impl Report {
+    fn interval(&mut self, elapsed: Duration, stats: &mut Stats) {
+        let mut latencies = std::mem::take(&mut stats.latencies_us);
+        latencies.sort_unstable();
+        let p99 = percentile(&latencies, 0.99);
+        self.max_p99_us = self.max_p99_us.max(p99);
+
+        let rss = rss_kb();
+        self.rss_kb_start = self.rss_kb_start.or(rss);
+        self.rss_kb_max = self.rss_kb_max.max(rss);
+
+        println!(
+            "{{\"type\":\"interval\",\"elapsed_s\":{},\"sent\":{},\"received\":{},\"in_flight\":{},\"out_of_order\":{},\"unknown\":{},\"samples\":{},\"p50_us\":{},\"p90_us\":{},\"p99_us\":{},\"max_us\":{},\"rss_kb\":{}}}",
+            elapsed.as_secs(),
+            stats.sent,
+            stats.received,
+            stats.pending.len(),
+            stats.out_of_order,
+            stats.unknown,
+            latencies.len(),
+            percentile(&latencies, 0.50),
+            percentile(&latencies, 0.90),
+            p99,
+            latencies.last().copied().unwrap_or(0),
+            json_opt(rss),
+        );
+    }
+
+    fn summary(&self, opts: &Opts, elapsed: Duration, stats: &Stats) -> bool {
+        let rss_growth_kb = self.rss_kb_start.zip(self.rss_kb_max).map(|(start, max)| max.saturating_sub(start));
+        let mut failures = Vec::new();
+        if !stats.pending.is_empty() {
+            failures.push(format!("{} orders were never acknowledged", stats.pending.len()));
+        }
+        if stats.out_of_order > 0 {
+            failures.push(format!("{} acknowledgements arrived out of order", stats.out_of_order));
+        }
+        if stats.unknown > 0 {
+            failures.push(format!("{} acknowledgements did not match an order", stats.unknown));
+        }
+        if let Some(max_p99_us) = opts.max_p99_us.filter(|max| self.max_p99_us > *max) {
+            failures.push(format!("p99 latency of {}us exceeded {}us", self.max_p99_us, max_p99_us));
+        }
+        if let (Some(max), Some(growth)) = (opts.max_rss_growth_kb, rss_growth_kb) {
+            if growth > max {
+                failures.push(format!("resident memory grew by {growth}KiB, more than {max}KiB"));
+            }
+        }
+
+        let failures_json: Vec<String> = failures.iter().map(|f| format!("\"{f}\"")).collect();
+        println!(
+            "{{\"type\":\"summary\",\"elapsed_s\":{},\"rate\":{},\"sent\":{},\"received\":{},\"in_flight\":{},\"out_of_order\":{},\"unknown\":{},\"max_p99_us\":{},\"rss_kb_start\":{},\"rss_kb_max\":{},\"rss_growth_kb\":{},\"passed\":{},\"failures\":[{}]}}",
+            elapsed.as_secs(),
+            opts.rate,
+            stats.sent,
+            stats.received,
+            stats.pending.len(),
+            stats.out_of_order,
+            stats.unknown,
+            self.max_p99_us,
+            json_opt(self.rss_kb_start),
+            json_opt(self.rss_kb_max),
+            json_opt(rss_growth_kb),
+            failures.is_empty(),
+            failures_json.join(","),
+        );
+        failures.is_empty()
+    }
+}
+
+async fn run_acceptor(mut acceptor: FixApplicationAcceptor) -> Result<(), ApplicationError> {
+    let (handle, mut receiver) = acceptor.accept().await?;
+    handle.start_async().await?;
+    while let Some(msg) = receiver.recv().await {
+        if let Some(cl_ord_id) = cl_ord_id(&msg) {
+            handle.send_message(execution_report(&handle, cl_ord_id))?;
+        }
+    }
+    Ok(())
+}
+
+async fn run_initiator(
+    opts: &Opts,
+    handle: &FixApplicationHandle,
+    receiver: &mut mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+) -> Result<bool, ApplicationError> {
+    let mut stats = Stats::default();
+    let mut report = Report {
+        max_p99_us: 0,
+        rss_kb_start: None,
+        rss_kb_max: None,
+    };
+
+    let start = Instant::now();
+    let mut send_interval = tokio::time::interval(Duration::from_secs_f64(1.0 / opts.rate.max(1) as f64));
+    let mut report_interval = tokio::time::interval_at(
+        tokio::time::Instant::now() + opts.report_interval,
+        opts.report_interval,
+    );
+    let mut next_cl_ord_id = 0;
+
+    while start.elapsed() < opts.duration {
+        tokio::select! {
+            _ = send_interval.tick() => {
+                handle.send_message(order(handle, next_cl_ord_id))?;
+                stats.sent(next_cl_ord_id);
+                next_cl_ord_id += 1;
+            }
+            Some(msg) = receiver.recv() => stats.received(cl_ord_id(&msg)),
+            _ = report_interval.tick() => report.interval(start.elapsed(), &mut stats),
+        }
+    }
+
+    let _ = tokio::time::timeout(opts.drain_timeout, async {
+        while !stats.pending.is_empty() {
+            match receiver.recv().await {
+                Some(msg) => stats.received(cl_ord_id(&msg)),
+                None => break,
+            }
+        }
+    })
+    .await;
+    report.interval(start.elapsed(), &mut stats);
+
+    Ok(report.summary(opts, start.elapsed(), &stats))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ApplicationError> {
+    let opts = Opts::parse();
+    let dir = opts
+        .dir
+        .clone()
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("forgefix-soak-{}", std::process::id())));
+    std::fs::create_dir_all(&dir)?;
+
+    let acceptor = FixApplicationAcceptor::build(settings(&dir, "SOAK_ACCEPTOR", "SOAK_INITIATOR", "127.0.0.1:0".parse().unwrap())?)?;
+    let addr = acceptor.local_addr()?;
+    let acceptor = tokio::spawn(run_acceptor(acceptor));
+
+    let (handle, mut receiver) = FixApplicationInitiator::build(settings(&dir, "SOAK_INITIATOR", "SOAK_ACCEPTOR", addr)?)?
+        .initiate()
+        .await?;
+    handle.start_async().await?;
+
+    let passed = run_initiator(&opts, &handle, &mut receiver).await?;
+
+    handle.end_async().await?;
+    let _ = acceptor.await;
+    if opts.dir.is_none() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
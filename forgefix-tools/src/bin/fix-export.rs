@@ -0,0 +1,164 @@
+//! Export the messages of a FIX session as pipe-delimited FIX, JSON or CSV.
+//!
+//! Reads the messages from the sqlite store of a session with `--store`, or from each log file
+//! given, or from stdin. Messages can be selected by time with `--from` and `--to`, and by
+//! `MsgType(35)` with `--msg-type`, which can be repeated. Times are RFC 3339, or
+//! `YYYYMMDD-HH:MM:SS` in UTC. An encrypted store is read with its `--key`.
+use chrono::{DateTime, NaiveDateTime, Utc};
+use clap::Parser;
+use forgefix::SessionSettings;
+use forgefix_tools::export::{read_log, read_store, write_messages, ExportFilter, Format};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Opts {
+    /// Log files to read messages from, or `-` for stdin. Reads stdin if none are given and
+    /// there is no `--store`
+    files: Vec<PathBuf>,
+
+    /// The sqlite store to read messages from, instead of log files
+    #[arg(long, conflicts_with = "files", requires_all = ["sender_comp_id", "target_comp_id"])]
+    store: Option<PathBuf>,
+
+    /// The SenderCompID(49) of the session in the store
+    #[arg(long)]
+    sender_comp_id: Option<String>,
+
+    /// The TargetCompID(56) of the session in the store
+    #[arg(long)]
+    target_comp_id: Option<String>,
+
+    /// The epoch of the session in the store, if it is not the default one
+    #[arg(long)]
+    epoch: Option<String>,
+
+    /// Read every shard of a store sharded by date
+    #[arg(long)]
+    shard_by_date: bool,
+
+    /// The key of an encrypted store, as 64 hex digits
+    #[arg(long, value_parser = parse_key)]
+    key: Option<[u8; 32]>,
+
+    /// The format to write: fix, json or csv
+    #[arg(long, default_value = "fix")]
+    format: Format,
+
+    /// Only export the messages sent or received at or after this time
+    #[arg(long, value_parser = parse_time)]
+    from: Option<DateTime<Utc>>,
+
+    /// Only export the messages sent or received at or before this time
+    #[arg(long, value_parser = parse_time)]
+    to: Option<DateTime<Utc>>,
+
+    /// Only export the messages of this MsgType(35)
+    #[arg(long = "msg-type")]
+    msg_types: Vec<char>,
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y%m%d-%H:%M:%S%.f").map(|time| time.and_utc()))
+        .map_err(|e| format!("bad time `{s}`: {e}"))
+}
+
+fn parse_key(s: &str) -> Result<[u8; 32], String> {
+    let mut key = [0; 32];
+    if s.len() != 2 * key.len() || !s.is_ascii() {
+        return Err(String::from("a key is 64 hex digits"));
+    }
+    for (byte, digits) in key.iter_mut().zip(s.as_bytes().chunks(2)) {
+        let digits = std::str::from_utf8(digits).unwrap();
+        *byte = u8::from_str_radix(digits, 16).map_err(|e| format!("bad key: {e}"))?;
+    }
+    Ok(key)
+}
+
+fn read_input(path: &PathBuf) -> std::io::Result<Vec<u8>> {
+    let mut input = Vec::new();
+    if path.as_os_str() == "-" {
+        std::io::stdin().read_to_end(&mut input)?;
+    } else {
+        input = std::fs::read(path)?;
+    }
+    Ok(input)
+}
+
+fn store_settings(opts: &Opts, store: PathBuf) -> Result<SessionSettings, forgefix::ApplicationError> {
+    let mut builder = SessionSettings::builder()
+        .with_sender_comp_id(opts.sender_comp_id.as_deref().unwrap_or_default())
+        .with_target_comp_id(opts.target_comp_id.as_deref().unwrap_or_default())
+        .with_store_path(store)
+        .with_store_shard_by_date(opts.shard_by_date)
+        // no engine runs with these settings, so nothing is logged or connected to
+        .with_log_dir(std::env::temp_dir())
+        .with_socket_addr("127.0.0.1:0".parse().unwrap());
+    if let Some(ref epoch) = opts.epoch {
+        builder.set_epoch(epoch);
+    }
+    if let Some(key) = opts.key {
+        builder.set_store_encryption_key(key);
+    }
+    builder.build()
+}
+
+fn main() -> ExitCode {
+    let opts = Opts::parse();
+    let mut filter = ExportFilter::new();
+    if let Some(from) = opts.from {
+        filter.set_from(from);
+    }
+    if let Some(to) = opts.to {
+        filter.set_to(to);
+    }
+    for msg_type in opts.msg_types.iter() {
+        filter.set_msg_type(*msg_type);
+    }
+
+    let messages = match opts.store.clone() {
+        Some(store) => {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to start a runtime");
+            let messages = store_settings(&opts, store)
+                .map(|settings| runtime.block_on(async { read_store(&settings, &filter).await }));
+            match messages {
+                Ok(Ok(messages)) => messages,
+                Ok(Err(e)) | Err(e) => {
+                    eprintln!("{e}");
+                    return ExitCode::from(2);
+                }
+            }
+        }
+        None => {
+            let files = if opts.files.is_empty() {
+                vec![PathBuf::from("-")]
+            } else {
+                opts.files
+            };
+            let mut messages = Vec::new();
+            for path in files {
+                match read_input(&path) {
+                    Ok(input) => messages.extend(read_log(&input, &filter)),
+                    Err(e) => {
+                        eprintln!("{}: {e}", path.display());
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+            messages
+        }
+    };
+
+    if let Err(e) = write_messages(&mut std::io::stdout().lock(), opts.format, &messages) {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
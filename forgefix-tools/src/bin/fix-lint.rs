@@ -0,0 +1,78 @@
+//! Check raw FIX messages against the FIX 4.2 dictionary.
+//!
+//! Reads messages from each file given, or from stdin, and prints the problems found in each
+//! message. Fields may be delimited by SOH or `|`. Exits with a non-zero status if any message has
+//! an error, or a warning with `--deny-warnings`.
+use clap::Parser;
+use forgefix_tools::{lint, split_messages, Severity};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Opts {
+    /// Files to read messages from, or `-` for stdin. Reads stdin if none are given
+    files: Vec<PathBuf>,
+
+    /// Treat warnings as errors
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// Only print messages that have problems
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+fn read_input(path: &PathBuf) -> std::io::Result<Vec<u8>> {
+    let mut input = Vec::new();
+    if path.as_os_str() == "-" {
+        std::io::stdin().read_to_end(&mut input)?;
+    } else {
+        input = std::fs::read(path)?;
+    }
+    Ok(input)
+}
+
+fn main() -> ExitCode {
+    let opts = Opts::parse();
+    let files = if opts.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        opts.files
+    };
+
+    let mut failed = false;
+    for path in files {
+        let input = match read_input(&path) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                return ExitCode::from(2);
+            }
+        };
+        for (i, msg) in split_messages(&input).iter().enumerate() {
+            let diagnostics = lint(msg);
+            failed |= diagnostics
+                .iter()
+                .any(|d| d.severity == Severity::Error || opts.deny_warnings);
+            if opts.quiet && diagnostics.is_empty() {
+                continue;
+            }
+            let printable = String::from_utf8_lossy(msg).replace('\x01', "|");
+            println!("{} #{}: {printable}", path.display(), i + 1);
+            if diagnostics.is_empty() {
+                println!("  ok");
+            }
+            for diagnostic in diagnostics {
+                println!("  {diagnostic}");
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,359 @@
+//! Export the messages of a FIX session as text, JSON or CSV.
+//!
+//! Messages are read from the sqlite store of an engine with [`read_store`], or from the log files
+//! it writes with [`read_log`], and selected with an [`ExportFilter`] by time and `MsgType(35)`.
+//! [`write_messages`] then writes them in one of the [`Format`]s.
+use forgefix::fix::decode::FixMessage;
+use forgefix::fix::generated::Tags;
+use forgefix::fix::mem::MsgBuf;
+use forgefix::fix::replay::{MessageQuery, StoreReader};
+use forgefix::{ApplicationError, SessionSettings};
+
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::str::FromStr;
+
+// The format of `SendingTime(52)`, and of the timestamp an engine writes before each message of
+// its log files, in local time.
+const TIME_FORMAT: &str = "%Y%m%d-%H:%M:%S%.f";
+
+/// How [`write_messages`] writes messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One message per line, with its fields delimited by `|`.
+    Fix,
+    /// One JSON object per line, with the tag, name and value of each field.
+    Json,
+    /// A header, then one row per message.
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "fix" => Ok(Format::Fix),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format `{s}`, expected fix, json or csv")),
+        }
+    }
+}
+
+/// Whether a message was sent or received by the engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Sent => "sent",
+            Direction::Received => "received",
+        }
+    }
+}
+
+/// A message to export.
+#[derive(Debug)]
+pub struct ExportedMessage {
+    /// When the message was sent or received, or its `SendingTime(52)` if that is not known.
+    pub time: Option<DateTime<Utc>>,
+    /// Whether the message was sent or received, if known. Messages read from log files have no
+    /// direction.
+    pub direction: Option<Direction>,
+    /// The message.
+    pub msg: MsgBuf,
+}
+
+impl ExportedMessage {
+    fn field(&self, tag: Tags) -> Option<Vec<u8>> {
+        FixMessage::parse(&self.msg).ok()?.get(tag).map(<[u8]>::to_vec)
+    }
+
+    fn msg_type(&self) -> Option<char> {
+        match self.field(Tags::MsgType)?[..] {
+            [msg_type] => Some(msg_type as char),
+            _ => None,
+        }
+    }
+}
+
+/// The messages to export. Every message matches an empty filter.
+#[derive(Clone, Debug, Default)]
+pub struct ExportFilter {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    msg_types: Vec<char>,
+}
+
+impl ExportFilter {
+    pub fn new() -> ExportFilter {
+        ExportFilter::default()
+    }
+
+    /// Only match the messages sent or received at or after `from`.
+    pub fn with_from(mut self, from: DateTime<Utc>) -> ExportFilter {
+        self.set_from(from);
+        self
+    }
+    pub fn set_from(&mut self, from: DateTime<Utc>) {
+        self.from = Some(from);
+    }
+
+    /// Only match the messages sent or received at or before `to`.
+    pub fn with_to(mut self, to: DateTime<Utc>) -> ExportFilter {
+        self.set_to(to);
+        self
+    }
+    pub fn set_to(&mut self, to: DateTime<Utc>) {
+        self.to = Some(to);
+    }
+
+    /// Only match the messages of `msg_type`. Adding more than one type matches the messages of
+    /// any of them.
+    pub fn with_msg_type(mut self, msg_type: char) -> ExportFilter {
+        self.set_msg_type(msg_type);
+        self
+    }
+    pub fn set_msg_type(&mut self, msg_type: char) {
+        self.msg_types.push(msg_type);
+    }
+
+    /// Whether `msg` matches. A message without a time does not match a time range.
+    pub fn matches(&self, msg: &ExportedMessage) -> bool {
+        let in_range = |bound: Option<DateTime<Utc>>, within: fn(&DateTime<Utc>, &DateTime<Utc>) -> bool| {
+            bound.is_none_or(|bound| msg.time.is_some_and(|time| within(&time, &bound)))
+        };
+        in_range(self.from, |time, from| time >= from)
+            && in_range(self.to, |time, to| time <= to)
+            && (self.msg_types.is_empty() || msg.msg_type().is_some_and(|t| self.msg_types.contains(&t)))
+    }
+}
+
+/// Read the messages kept in the store of `settings` that match `filter`, sent and received, in
+/// the order they were sent or received. Fails if a message cannot be read, such as when the store
+/// is encrypted and `settings` has no key for it.
+///
+/// Only application messages are kept in the store for the received direction, see
+/// [`StoreReader::query_incoming`].
+pub async fn read_store(
+    settings: &SessionSettings,
+    filter: &ExportFilter,
+) -> Result<Vec<ExportedMessage>, ApplicationError> {
+    let reader = StoreReader::open(settings)?;
+    let mut query = MessageQuery::new();
+    if filter.from.is_some() || filter.to.is_some() {
+        // the store compares times as text, which years past 9999 would not sort as
+        let to = filter
+            .to
+            .unwrap_or_else(|| Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap());
+        query.set_time_range(filter.from.unwrap_or(DateTime::UNIX_EPOCH), to);
+    }
+    for msg_type in filter.msg_types.iter() {
+        query.set_msg_type(*msg_type);
+    }
+    let sent = reader.query(&query).await?.into_iter().map(|stored| (stored, Direction::Sent));
+    let received = reader
+        .query_incoming(&query)
+        .await?
+        .into_iter()
+        .map(|stored| (stored, Direction::Received));
+    let mut messages: Vec<ExportedMessage> = sent
+        .chain(received)
+        .map(|(stored, direction)| ExportedMessage {
+            time: Some(stored.time),
+            direction: Some(direction),
+            msg: stored.msg,
+        })
+        .collect();
+    messages.sort_by_key(|msg| msg.time);
+    Ok(messages)
+}
+
+/// Read the messages of a log file that match `filter`, in the order they were logged.
+///
+/// The time of a message is the timestamp the engine wrote before it, or its `SendingTime(52)`
+/// if the line has none, such as for logs written by other engines. Fields may be delimited by
+/// SOH or `|`, see [`split_messages`](crate::split_messages).
+pub fn read_log(input: &[u8], filter: &ExportFilter) -> Vec<ExportedMessage> {
+    let mut messages = Vec::new();
+    for line in input.split(|b| *b == b'\n') {
+        let stamp = log_stamp(line);
+        for msg in crate::split_messages(line) {
+            let mut msg = ExportedMessage {
+                time: stamp,
+                direction: None,
+                msg: MsgBuf(msg),
+            };
+            if msg.time.is_none() {
+                msg.time = msg.field(Tags::SendingTime).as_deref().and_then(sending_time);
+            }
+            if filter.matches(&msg) {
+                messages.push(msg);
+            }
+        }
+    }
+    messages
+}
+
+// The timestamp at the start of a line of a log file, followed by ` : `.
+fn log_stamp(line: &[u8]) -> Option<DateTime<Utc>> {
+    let end = line.windows(3).position(|w| w == b" : ")?;
+    let stamp = std::str::from_utf8(&line[..end]).ok()?;
+    let stamp = NaiveDateTime::parse_from_str(stamp, TIME_FORMAT).ok()?;
+    Local
+        .from_local_datetime(&stamp)
+        .earliest()
+        .map(|stamp| stamp.with_timezone(&Utc))
+}
+
+fn sending_time(value: &[u8]) -> Option<DateTime<Utc>> {
+    let value = std::str::from_utf8(value).ok()?;
+    NaiveDateTime::parse_from_str(value, TIME_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// Write `messages` to `w` in `format`.
+pub fn write_messages(
+    w: &mut impl Write,
+    format: Format,
+    messages: &[ExportedMessage],
+) -> std::io::Result<()> {
+    if format == Format::Csv {
+        writeln!(w, "time,direction,msg_seq_num,msg_type,message")?;
+    }
+    for msg in messages {
+        let line = match format {
+            Format::Fix => printable(&msg.msg.0),
+            Format::Json => json_line(msg),
+            Format::Csv => csv_line(msg),
+        };
+        writeln!(w, "{line}")?;
+    }
+    Ok(())
+}
+
+fn printable(msg: &[u8]) -> String {
+    String::from_utf8_lossy(msg).replace('\x01', "|")
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+fn json_line(msg: &ExportedMessage) -> String {
+    let mut line = String::from("{");
+    let time = msg.time.map(|time| json_string(&format_time(Some(time))));
+    let direction = msg.direction.map(|direction| json_string(direction.as_str()));
+    let _ = write!(
+        line,
+        "\"time\":{},\"direction\":{},\"fields\":[",
+        time.as_deref().unwrap_or("null"),
+        direction.as_deref().unwrap_or("null"),
+    );
+    if let Ok(fields) = FixMessage::parse(&msg.msg) {
+        for (i, (tag, value)) in fields.iter().enumerate() {
+            let name = Tags::try_from(tag)
+                .map(|name| json_string(&format!("{name:?}")))
+                .unwrap_or_else(|_| "null".to_string());
+            let _ = write!(
+                line,
+                "{}{{\"tag\":{tag},\"name\":{name},\"value\":{}}}",
+                if i == 0 { "" } else { "," },
+                json_string(&String::from_utf8_lossy(value)),
+            );
+        }
+    }
+    line.push_str("]}");
+    line
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn csv_line(msg: &ExportedMessage) -> String {
+    let field = |tag| msg.field(tag).map(|value| String::from_utf8_lossy(&value).into_owned());
+    [
+        format_time(msg.time),
+        msg.direction.map(|d| d.as_str().to_string()).unwrap_or_default(),
+        field(Tags::MsgSeqNum).unwrap_or_default(),
+        field(Tags::MsgType).unwrap_or_default(),
+        printable(&msg.msg.0),
+    ]
+    .iter()
+    .map(|value| csv_field(value))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let log = b"8=FIX.4.2|9=5|35=D|34=2|52=20240102-10:00:00.000|11=a,\"b\"|10=000|\n\
+                    8=FIX.4.2|9=5|35=0|34=3|52=20240102-10:00:30.000|10=000|\n\
+                    8=FIX.4.2|9=5|35=D|34=4|52=20240102-10:01:00.000|11=c|10=000|\n";
+        let at = |time: &str| NaiveDateTime::parse_from_str(time, TIME_FORMAT).unwrap().and_utc();
+
+        let filter = ExportFilter::new()
+            .with_msg_type('D')
+            .with_to(at("20240102-10:00:59.000"));
+        let messages = read_log(log, &filter);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].time, Some(at("20240102-10:00:00.000")));
+        assert_eq!(read_log(log, &ExportFilter::new().with_from(at("20240102-10:00:30.000"))).len(), 2);
+
+        let write = |format| {
+            let mut out = Vec::new();
+            write_messages(&mut out, format, &messages).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            write(Format::Fix),
+            "8=FIX.4.2|9=5|35=D|34=2|52=20240102-10:00:00.000|11=a,\"b\"|10=000|\n"
+        );
+        assert!(write(Format::Json).starts_with(
+            "{\"time\":\"2024-01-02T10:00:00.000Z\",\"direction\":null,\"fields\":[\
+             {\"tag\":8,\"name\":\"BeginString\",\"value\":\"FIX.4.2\"},"
+        ));
+        assert!(write(Format::Json).contains("{\"tag\":11,\"name\":\"ClOrdID\",\"value\":\"a,\\\"b\\\"\"}"));
+        assert_eq!(
+            write(Format::Csv),
+            "time,direction,msg_seq_num,msg_type,message\n\
+             2024-01-02T10:00:00.000Z,,2,D,\"8=FIX.4.2|9=5|35=D|34=2|52=20240102-10:00:00.000|11=a,\"\"b\"\"|10=000|\"\n"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Tools for working with FIX messages outside of a session.
+//!
+//! The `fix-lint` binary checks raw messages, read from files or stdin, with
+//! [`forgefix::fix::lint`]. The `fix-export` binary dumps the messages of a session, from its store
+//! or its log files, with [`export`]. This library holds the parts of them that are useful on their
+//! own, such as pulling messages out of log files.
+pub use forgefix::fix::lint::{lint, Diagnostic, Severity};
+
+pub mod export;
+
+/// Pull the FIX messages out of `input`.
+///
+/// A message starts at each `8=FIX` and ends at the next message, or at the end of the line, so
+/// messages can be read from engine logs with a timestamp before each message. If `input`
+/// contains no SOH, `|` is taken as the field delimiter instead, as is common in logs and
+/// certification documents.
+pub fn split_messages(input: &[u8]) -> Vec<Vec<u8>> {
+    let delimiter = if input.contains(&b'\x01') { b'\x01' } else { b'|' };
+    let mut messages = Vec::new();
+    for line in input.split(|b| *b == b'\n') {
+        let starts: Vec<usize> = (0..line.len())
+            .filter(|&at| line[at..].starts_with(b"8=FIX") && (at == 0 || !line[at - 1].is_ascii_alphanumeric()))
+            .collect();
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(line.len());
+            let mut msg = &line[start..end];
+            while let [rest @ .., b' ' | b'\t' | b'\r'] = msg {
+                msg = rest;
+            }
+            messages.push(
+                msg.iter()
+                    .map(|b| if *b == delimiter { b'\x01' } else { *b })
+                    .collect(),
+            );
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_messages() {
+        let log = b"2024-01-02 10:00:00.000 8=FIX.4.2|9=5|35=0|10=000|\r\n\
+                    \n\
+                    8=FIX.4.2|9=5|35=1|10=000| 8=FIX.4.2|9=5|35=2|10=000|\n";
+        assert_eq!(
+            split_messages(log),
+            vec![
+                b"8=FIX.4.2\x019=5\x0135=0\x0110=000\x01".to_vec(),
+                b"8=FIX.4.2\x019=5\x0135=1\x0110=000\x01".to_vec(),
+                b"8=FIX.4.2\x019=5\x0135=2\x0110=000\x01".to_vec(),
+            ]
+        );
+
+        // with SOH delimiters, a | is part of a value
+        let raw = b"8=FIX.4.2\x019=5\x0135=0\x0158=a|b\x0110=000\x01";
+        assert_eq!(split_messages(raw), vec![raw.to_vec()]);
+    }
+}
@@ -1,4 +1,6 @@
 #!/bin/sh
 xsltproc fields.xslt FIX42.xml > ../src/fix/generated/fields.rs
+xsltproc execution_report.xslt FIX42.xml > ../src/fix/generated/execution_report.rs
+xsltproc dictionary.xslt FIX42.xml > ../src/fix/generated/dictionary.rs
 cd ..
 cargo fmt
@@ -5,46 +5,87 @@
 
 use chrono::{DateTime, Utc};
 use chrono::naive::NaiveDateTime;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
-use anyhow::{bail, Result};
+use anyhow::Result;
 use thiserror::Error;
 
+use crate::fix::arena::{Delivery, Incoming};
 use crate::fix::decode::{parse_field, parse_sending_time};
+use crate::fix::acks::PendingAcks;
+use crate::fix::dedup::{is_new_order, SentOrders};
+use crate::fix::echo::EchoTags;
 use crate::fix::encode::{AdditionalHeaders, MessageBuilder, SerializedInt};
 use crate::fix::generated::{
     is_session_message, GapFillFlag, PossDupFlag, SessionRejectReason, Tags,
 };
-use crate::fix::log::{Logger, FileLogger};
+use crate::fix::log::{Logger, SessionLogger};
+use crate::fix::metrics::Metrics;
+use crate::fix::poss_resend::PossResendWindow;
+use crate::fix::rate_limit::RateLimiter;
 use crate::fix::resend::Transformer;
-use crate::fix::session::{Event, MyStateMachine};
+use crate::fix::schedule::{OutsideWindow, SessionSchedule};
+use crate::fix::session::{Event, MyStateMachine, OutboxPermit, Sequences};
 use crate::fix::stopwatch::FixTimeouts;
 use crate::fix::store::Store;
 use crate::fix::validate::validate_msg;
-use crate::{FixEngineType, SessionSettings, Request};
+use crate::{
+    Clock, CloseReason, Drain, FixEngineType, Flush, LogonMsgType, NegotiatedParams, OrphanPolicy, ResendPolicy, SessionCallback, SessionEvent,
+    SessionSettings, Request, ShutdownReport, UnmatchedTestReqId,
+};
 
 use generated::MsgType;
 use generated::MsgType::*;
 use mem::MsgBuf;
 
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::io;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Instant, Duration};
 
+pub mod admin;
+pub mod arena;
+pub mod blocking;
+pub mod bridge;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod decimal;
 pub mod decode;
 pub mod encode;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzz;
 pub mod generated;
+pub mod lint;
+pub mod log;
 pub mod mem;
+pub mod memory_store;
+#[cfg(feature = "typed-messages")]
+pub mod messages;
+pub mod orders;
+pub mod replay;
+pub mod router;
+pub mod schedule;
+#[cfg(feature = "test-util")]
+pub mod state_machine;
 
+pub(crate) mod acks;
 mod checksum;
-mod log;
+mod crypto;
+pub(crate) mod dedup;
+mod echo;
+pub(crate) mod guard;
+pub(crate) mod metrics;
+pub(crate) mod pool;
+mod poss_resend;
+pub(crate) mod rate_limit;
 mod resend;
-mod session;
+pub(crate) mod session;
 mod stopwatch;
 mod store;
-mod stream;
+pub(crate) mod stream;
 mod validate;
 
 #[derive(Debug, Error)]
@@ -89,7 +130,7 @@ impl SessionError {
         msg_type: Option<char>,
     ) -> SessionError {
         SessionError::MessageRejected {
-            text: reason.as_ref().map_or(String::from(""), |r| r.into()),
+            text: reject_text(reason.as_ref(), tag_id, None),
             reject_reason: reason,
             msg_seq_num: seq_num,
             ref_tag_id: tag_id,
@@ -97,12 +138,85 @@ impl SessionError {
         }
     }
 
+    // Include the offending value in the `Text(58)` of the reject. 
+    fn with_received_value(mut self, value: &[u8]) -> SessionError {
+        if let SessionError::MessageRejected {
+            ref mut text,
+            ref reject_reason,
+            ref_tag_id,
+            ..
+        } = self
+        {
+            *text = reject_text(reject_reason.as_ref(), ref_tag_id, Some(value));
+        }
+        self
+    }
+
     fn new_garbled_message(text: String, t: GarbledMessageType) -> SessionError {
         SessionError::GarbledMessage {
             text,
             garbled_msg_type: t,
         }
     }
+
+    fn quarantine_reason(&self) -> String {
+        match self {
+            SessionError::GarbledMessage {
+                text,
+                garbled_msg_type,
+            } => format!("garbled ({garbled_msg_type:?}): {text}"),
+            SessionError::MessageRejected {
+                text,
+                reject_reason,
+                ref_tag_id,
+                ..
+            } => format!("rejected ({reject_reason:?}, tag {ref_tag_id:?}): {text}"),
+            e => e.to_string(),
+        }
+    }
+}
+
+const MAX_REJECT_VALUE_LEN: usize = 32;
+
+// Describe a rejected field for the `Text(58)` of a `Reject<3>`, e.g.
+// `Incorrect data format for value: SendingTime(52)='2022-13-45'`. 
+fn reject_text(
+    reason: Option<&SessionRejectReason>,
+    tag: Option<u32>,
+    value: Option<&[u8]>,
+) -> String {
+    let mut text: String = reason.map_or(String::new(), |r| r.into());
+    let Some(tag) = tag else {
+        return text;
+    };
+    if !text.is_empty() {
+        text.push_str(": ");
+    }
+    match Tags::try_from(tag) {
+        Ok(name) => text.push_str(&format!("{name:?}({tag})")),
+        Err(_) => text.push_str(&format!("tag {tag}")),
+    }
+    if let Some(value) = value {
+        text.push_str(&format!("='{}'", escape_value(value)));
+    }
+    text
+}
+
+// Printable ASCII is kept as is, anything else (including SOH) is hex escaped. Long values are
+// truncated. 
+fn escape_value(value: &[u8]) -> String {
+    let mut escaped = String::new();
+    for b in value.iter().take(MAX_REJECT_VALUE_LEN) {
+        match b {
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7e => escaped.push(*b as char),
+            _ => escaped.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    if value.len() > MAX_REJECT_VALUE_LEN {
+        escaped.push_str("...");
+    }
+    escaped
 }
 
 #[derive(Default)]
@@ -111,10 +225,15 @@ struct SessionParserCallback<'a> {
     msg_seq_num: u32,
     sender_comp_id: Option<&'a [u8]>,
     target_comp_id: Option<&'a [u8]>,
+    sender_sub_id: Option<&'a [u8]>,
+    target_sub_id: Option<&'a [u8]>,
+    deliver_to_comp_id: Option<&'a [u8]>,
+    on_behalf_of_comp_id: Option<&'a [u8]>,
     poss_dup_flag: Option<char>,
     gap_fill: Option<char>,
     new_seq_no: Option<u32>,
     test_req_id: Option<&'a [u8]>,
+    text: Option<&'a [u8]>,
     begin_seq_no: Option<u32>,
     end_seq_no: Option<u32>,
     heart_bt_int: Option<u32>,
@@ -122,6 +241,12 @@ struct SessionParserCallback<'a> {
     orig_sending_time: Option<DateTime<Utc>>,
     encrypt_method: Option<u32>,
     reset_seq_num_flag: Option<char>,
+    max_message_size: Option<u32>,
+    no_msg_types: Option<u32>,
+    msg_types: Vec<(&'a [u8], Option<char>)>,
+    ref_seq_num: Option<u32>,
+    session_reject_reason: Option<u32>,
+    tolerate_out_of_place_fields: bool,
 }
 
 impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
@@ -135,14 +260,15 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     return Err(self.create_message_reject(
                         SessionRejectReason::INVALID_MSGTYPE,
                         Tags::MsgType,
+                        value,
                     ));
                 }
             }
             Ok(Tags::MsgSeqNum) => {
                 self.msg_seq_num =
-                    parse_field::<u32>(value).or(Err(SessionError::MissingMsgSeqNum {
+                    parse_field::<u32>(value).map_err(|_| SessionError::MissingMsgSeqNum {
                         text: String::from("Missing MsgSeqNum"),
-                    }))?;
+                    })?;
             }
             Ok(Tags::TargetCompID) => {
                 self.target_comp_id = Some(value);
@@ -150,6 +276,18 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
             Ok(Tags::SenderCompID) => {
                 self.sender_comp_id = Some(value);
             }
+            Ok(Tags::SenderSubID) => {
+                self.sender_sub_id = Some(value);
+            }
+            Ok(Tags::TargetSubID) => {
+                self.target_sub_id = Some(value);
+            }
+            Ok(Tags::DeliverToCompID) => {
+                self.deliver_to_comp_id = Some(value);
+            }
+            Ok(Tags::OnBehalfOfCompID) => {
+                self.on_behalf_of_comp_id = Some(value);
+            }
             Ok(Tags::PossDupFlag) => {
                 if value.len() == 1 {
                     self.poss_dup_flag = Some(value[0] as char);
@@ -157,6 +295,7 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     return Err(self.create_message_reject(
                         SessionRejectReason::VALUE_IS_INCORRECT,
                         Tags::PossDupFlag,
+                        value,
                     ));
                 }
             }
@@ -168,6 +307,7 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     return Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
                         Tags::SendingTime,
+                        value,
                     ));
                 }
             },
@@ -178,7 +318,8 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                 Err(_) => {
                     return Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
-                        Tags::SendingTime,
+                        Tags::OrigSendingTime,
+                        value,
                     ));
                 }
             },
@@ -189,7 +330,8 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
 
     fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
         if !is_session_message(self.msg_type) {
-            return Ok(false);
+            // Keep looking for header fields that were put after the body.
+            return Ok(self.tolerate_out_of_place_fields);
         }
         match key.try_into() {
             Ok(Tags::GapFillFlag) => {
@@ -199,6 +341,7 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     return Err(self.create_message_reject(
                         SessionRejectReason::VALUE_IS_INCORRECT,
                         Tags::GapFillFlag,
+                        value,
                     ));
                 }
             }
@@ -207,16 +350,21 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
                         Tags::NewSeqNo,
+                        value,
                     )))?);
             }
             Ok(Tags::TestReqID) => {
                 self.test_req_id = Some(value);
             }
+            Ok(Tags::Text) => {
+                self.text = Some(value);
+            }
             Ok(Tags::BeginSeqNo) => {
                 self.begin_seq_no =
                     Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
                         Tags::BeginSeqNo,
+                        value,
                     )))?);
             }
             Ok(Tags::EndSeqNo) => {
@@ -224,6 +372,7 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
                         Tags::EndSeqNo,
+                        value,
                     )))?);
             }
             Ok(Tags::HeartBtInt) => {
@@ -231,13 +380,15 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
                         Tags::HeartBtInt,
+                        value,
                     )))?)
             }
             Ok(Tags::EncryptMethod) => {
                 self.encrypt_method =
                     Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
                         SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
-                        Tags::EndSeqNo,
+                        Tags::EncryptMethod,
+                        value,
                     )))?);
             }
             Ok(Tags::ResetSeqNumFlag) => {
@@ -247,27 +398,65 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
                     return Err(self.create_message_reject(
                         SessionRejectReason::VALUE_IS_INCORRECT,
                         Tags::ResetSeqNumFlag,
+                        value,
                     ));
                 }
             }
+            Ok(Tags::MaxMessageSize) => {
+                self.max_message_size =
+                    Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
+                        SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
+                        Tags::MaxMessageSize,
+                        value,
+                    )))?);
+            }
+            Ok(Tags::NoMsgTypes) => {
+                self.no_msg_types =
+                    Some(parse_field::<u32>(value).or(Err(self.create_message_reject(
+                        SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE,
+                        Tags::NoMsgTypes,
+                        value,
+                    )))?);
+            }
+            Ok(Tags::RefMsgType) => {
+                self.msg_types.push((value, None));
+            }
+            // A malformed `Reject<3>` is not rejected in turn, so these are only parsed if valid.
+            Ok(Tags::RefSeqNum) => {
+                self.ref_seq_num = parse_field::<u32>(value).ok();
+            }
+            Ok(Tags::SessionRejectReason) => {
+                self.session_reject_reason = parse_field::<u32>(value).ok();
+            }
+            Ok(Tags::MsgDirection) => match (self.msg_types.last_mut(), value) {
+                (Some((_, direction @ None)), [d @ (b'S' | b'R')]) => *direction = Some(*d as char),
+                _ => {
+                    return Err(self.create_message_reject(
+                        SessionRejectReason::VALUE_IS_INCORRECT,
+                        Tags::MsgDirection,
+                        value,
+                    ));
+                }
+            },
             _ => (),
         }
         Ok(true)
     }
 
     fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
-        Ok(false)
+        Ok(self.tolerate_out_of_place_fields)
     }
 
     fn parse_error(&mut self, err: decode::MessageParseError) -> Result<(), Self::Err> {
         match err {
-            decode::MessageParseError::BadLengthField(tag, _) => {
+            decode::MessageParseError::BadLengthField(tag, value) => {
                 Err(SessionError::new_message_rejected(
                     Some(SessionRejectReason::INCORRECT_DATA_FORMAT_FOR_VALUE),
                     self.msg_seq_num,
                     Some(tag),
                     None,
-                ))
+                )
+                .with_received_value(&value))
             }
             decode::MessageParseError::UnexpectedByte(..) => {
                 Err(SessionError::GarbledMessage {
@@ -280,32 +469,112 @@ impl<'a> crate::fix::decode::ParserCallback<'a> for SessionParserCallback<'a> {
 }
 
 impl SessionParserCallback<'_> {
-    fn create_message_reject(&self, reason: SessionRejectReason, reg_tag: Tags) -> SessionError {
+    // The `NoMsgTypes(384)` group of a `Logon<A>`, where an entry without its `MsgDirection(385)`
+    // or a count that does not match the entries is rejected.
+    fn to_logon_msg_types(&self) -> Result<Vec<LogonMsgType>, SessionError> {
+        match self.no_msg_types {
+            None if !self.msg_types.is_empty() => {
+                return Err(SessionError::new_message_rejected(
+                    Some(SessionRejectReason::REQUIRED_TAG_MISSING),
+                    self.msg_seq_num,
+                    Some(Tags::NoMsgTypes.into()),
+                    Some(self.msg_type),
+                ));
+            }
+            Some(count) if usize::try_from(count) != Ok(self.msg_types.len()) => {
+                return Err(self.create_message_reject(
+                    SessionRejectReason::VALUE_IS_INCORRECT,
+                    Tags::NoMsgTypes,
+                    count.to_string().as_bytes(),
+                ));
+            }
+            _ => {}
+        }
+        self.msg_types
+            .iter()
+            .map(|(ref_msg_type, msg_direction)| {
+                Ok(LogonMsgType {
+                    ref_msg_type: String::from_utf8_lossy(ref_msg_type).into_owned(),
+                    msg_direction: msg_direction.ok_or_else(|| {
+                        SessionError::new_message_rejected(
+                            Some(SessionRejectReason::REQUIRED_TAG_MISSING),
+                            self.msg_seq_num,
+                            Some(Tags::MsgDirection.into()),
+                            Some(self.msg_type),
+                        )
+                    })?,
+                })
+            })
+            .collect()
+    }
+
+    fn create_message_reject(
+        &self,
+        reason: SessionRejectReason,
+        reg_tag: Tags,
+        value: &[u8],
+    ) -> SessionError {
         SessionError::new_message_rejected(
             Some(reason),
             self.msg_seq_num,
             Some(reg_tag.into()),
             Some(self.msg_type),
         )
+        .with_received_value(value)
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn spin_session(
     mut stream: TcpStream,
     mut request_receiver: mpsc::UnboundedReceiver<Request>,
-    message_received_event_sender: mpsc::UnboundedSender<Arc<MsgBuf>>,
+    mut delivery: Delivery,
     settings: SessionSettings,
-) -> Result<()> {
+    metrics: Arc<Metrics>,
+    event_sender: broadcast::Sender<SessionEvent>,
+    sent_orders: Arc<SentOrders>,
+    pending_acks: Arc<PendingAcks>,
+    negotiated: Arc<Mutex<Option<NegotiatedParams>>>,
+    shared_sequences: Arc<OnceLock<Sequences>>,
+) -> Result<CloseReason> {
 
     // SETUP
 
+    let peer_addr = stream.peer_addr()?;
+    stream = transport(stream, &settings).await?;
     let additional_headers = AdditionalHeaders::build(&settings);
     let store = Store::build(&settings).await?;
-    let mut logger = FileLogger::build(&settings).await?;
+    let mut logger = SessionLogger::build(&settings).await?;
     let sequences = store.get_sequences(settings.epoch.clone()).await?;
+    if sent_orders.enabled() {
+        sent_orders.extend(store.get_sent_orders(settings.epoch.clone()).await?);
+    }
     let mut state_machine = MyStateMachine::new(&settings, sequences);
+    let _ = shared_sequences.set(state_machine.sequences.clone());
 
-    let logon_resp_sender = receive_logon_request(&mut request_receiver).await;
+    let mut watchdog = None;
+    let logon_resp_sender = receive_logon_request(&mut request_receiver, &mut watchdog).await;
+    let _ = event_sender.send(SessionEvent::Connected { peer_addr });
+
+    if let Some(stats) = store.maintain(settings.store_vacuum_budget).await? {
+        let _ = event_sender.send(SessionEvent::StoreOpened { stats });
+    }
+
+    let mut deferred = VecDeque::new();
+    if matches!(settings.engine_type, FixEngineType::Server) && !in_session(&settings) {
+        refuse_logon(&mut state_machine, &mut stream, &additional_headers, &mut logger).await?;
+        if let Some(resp_sender) = logon_resp_sender {
+            let _ = resp_sender.send(false);
+        }
+        disconnect(request_receiver, store, settings.epoch.clone(), &state_machine, stream, logger).await?;
+        return Ok(CloseReason::NotStarted);
+    }
+    if let Some(ref schedule) = settings.schedule {
+        if !wait_for_session(schedule, &settings, &mut request_receiver, &store, &mut deferred, &mut watchdog, &event_sender).await {
+            disconnect(request_receiver, store, settings.epoch.clone(), &state_machine, stream, logger).await?;
+            return Ok(CloseReason::NotStarted);
+        }
+    }
 
     let start_new_session = is_new_session(&store, &settings).await?; 
     match settings.engine_type {
@@ -318,19 +587,57 @@ pub(super) async fn spin_session(
             state_machine.handle(&crate::fix::session::Event::Connect(start_new_session));
         }
     }
+    persist_sequences_reset(&mut state_machine, &store, settings.epoch.clone(), &event_sender).await?;
 
     let epoch = settings.epoch.clone();
     let heartbt_dur = &settings.heartbeat_timeout;
-    let tr_dur = test_request_duration(heartbt_dur);
-    let logout_dur = logout_duration(heartbt_dur);
-    let mut fix_timeouts = FixTimeouts::new(*heartbt_dur, tr_dur, logout_dur);
+    let tr_dur = test_request_duration(&settings, heartbt_dur);
+    let logout_dur = logout_duration(&settings, heartbt_dur);
+    let mut fix_timeouts = FixTimeouts::new(*heartbt_dur, tr_dur, logout_dur, settings.heartbeat_policy.timer);
+    fix_timeouts.set_watchdog(watchdog);
 
     let mut header_buf: stream::HeaderBuf<{ stream::PEEK_LEN }> = stream::HeaderBuf::new(); 
+    let mut resend_queue = ResendQueue::new(settings.resend_policy, settings.max_resend_window);
+    let mut live_buffer = LiveBuffer::new(settings.live_buffer);
+    let mut echo_tags = EchoTags::new(Arc::clone(&settings.echo_tags));
+    let mut poss_resends = settings.poss_resend_window.map(PossResendWindow::new);
+    let mut orphaned = false;
+    let mut orphan_logout: Option<tokio::time::Instant> = None;
+    let mut shutdown: Option<Shutdown> = None;
+    let mut logged_in = false;
+    // the handles also refuse the messages over a rate limit that rejects them, but the messages
+    // they accepted while the session was not running are paced here once it is
+    let rate_limiter = settings.rate_limit.map(|rate_limit| RateLimiter::new(&rate_limit));
+    let session_window = settings
+        .schedule
+        .and_then(|schedule| schedule.current_window(settings.clock.now()));
+    let mut session_end = session_window.map(|(_, end)| instant_at(&*settings.clock, end));
+    let mut session_ending_soon = settings
+        .schedule
+        .and_then(|schedule| schedule.end_warning())
+        .zip(session_window)
+        .map(|(end_warning, (_, end))| {
+            (end, instant_at(&*settings.clock, end - chrono::Duration::from_std(end_warning).unwrap_or_default()))
+        });
 
     // LOOP
 
     loop {
-        send_outgoing_messages(
+        if logged_in != session::is_logged_in(&state_machine) {
+            logged_in = !logged_in;
+            if logged_in {
+                tracing::info!("logged on");
+            } else {
+                tracing::info!("logged out");
+            }
+            let _ = event_sender.send(if logged_in { SessionEvent::LoggedOn } else { SessionEvent::LoggedOut });
+        }
+
+        if !deferred.is_empty() && session::is_logged_in(&state_machine) && in_session(&settings) {
+            release_deferred(&mut deferred, &mut state_machine, &echo_tags);
+        }
+
+        let discarded = send_outgoing_messages(
             &mut state_machine,
             &mut stream,
             &additional_headers,
@@ -338,10 +645,45 @@ pub(super) async fn spin_session(
             Arc::clone(&epoch),
             &mut logger,
             &mut fix_timeouts,
+            settings.session_callback.as_deref(),
+            &metrics,
+            rate_limiter.as_ref(),
+            &sent_orders,
         )
         .await?;
+        if let Some(shutdown) = shutdown.as_mut() {
+            shutdown.dropped += discarded;
+        }
+        // the next application message is left in the outbox until the rate limit allows it
+        let throttled_until = rate_limiter
+            .as_ref()
+            .filter(|_| {
+                state_machine
+                    .outbox_front()
+                    .is_some_and(|(msg, ..)| !is_session_message(msg.msg_type()))
+            })
+            .map(RateLimiter::ready_at);
 
-        if session::should_disconnect(&state_machine) {
+        if session::should_reconnect(&state_machine) {
+            tracing::info!("reconnecting");
+            let _ = stream.shutdown().await;
+            stream = crate::StreamFactory::build(&settings)?.stream().await?;
+            let peer_addr = stream.peer_addr()?;
+            stream = transport(stream, &settings).await?;
+            tracing::info!(%peer_addr, "reconnected");
+            let _ = event_sender.send(SessionEvent::Connected { peer_addr });
+            header_buf = stream::HeaderBuf::new();
+            state_machine.handle(&Event::Connect(settings.reset_seq_num));
+            persist_sequences_reset(&mut state_machine, &store, settings.epoch.clone(), &event_sender).await?;
+            continue;
+        }
+
+        if !resend_queue.is_empty() {
+            resend_queue.send_batch(&mut stream, &additional_headers, &mut logger, &metrics).await?;
+        }
+
+        let shutdown_timed_out = shutdown.as_ref().is_some_and(|shutdown| shutdown.timed_out);
+        if session::should_disconnect(&state_machine) || shutdown_timed_out {
             let resp = disconnect(
                 request_receiver,
                 store,
@@ -351,25 +693,38 @@ pub(super) async fn spin_session(
                 logger,
             )
             .await;
-            let logout_success = !session::in_error_state(&state_machine);
+            let logout_success = !session::in_error_state(&state_machine) && !shutdown_timed_out;
+            tracing::info!(error = !logout_success, "disconnected");
             state_machine.send_logout_response(logout_success && resp.is_ok());
+            if let Some(shutdown) = shutdown.take() {
+                shutdown.respond(logout_success && resp.is_ok());
+            }
             resp?;
-            break;
+            let close_reason = state_machine.take_close_reason();
+            return Ok(match close_reason {
+                Some(close_reason) => close_reason,
+                None if shutdown_timed_out => CloseReason::LogoutTimeout,
+                None => CloseReason::LoggedOut { text: None },
+            });
         }
 
+        let shutdown_deadline = shutdown
+            .as_ref()
+            .filter(|shutdown| !shutdown.timed_out)
+            .map(|shutdown| shutdown.deadline);
         let next_timeout = fix_timeouts.next_expiring_timeout();
         let (timeout_fut, timeout_event) = next_timeout.timeout();
 
         tokio::select! {
             maybe_err = stream::read_header(&mut stream, &mut header_buf) => {
                 let maybe_message = match maybe_err {
-                    Ok(()) => stream::read_message(&mut stream, &mut header_buf, &mut logger).await,
-                    Err(SessionError::IoError(e)) => bail!("{e:?}"),
+                    Ok(()) => stream::read_incoming(&mut stream, &mut header_buf, settings.max_message_size, &mut logger, &mut delivery).await,
+                    Err(SessionError::IoError(e)) => return Err(e.into()),
                     Err(e) => Err(e),
                 };
 
                 if let Err(SessionError::IoError(e)) = maybe_message {
-                    bail!("{e:?}");
+                    return Err(e.into());
                 }
 
                 handle_msg(
@@ -378,71 +733,448 @@ pub(super) async fn spin_session(
                     &mut fix_timeouts,
                     &store,
                     &settings,
-                    &mut stream, 
                     &mut logger,
-                    &additional_headers,
-                    &message_received_event_sender,
+                    &mut delivery,
+                    &metrics,
+                    &event_sender,
+                    &mut resend_queue,
+                    &mut live_buffer,
+                    &mut echo_tags,
+                    &mut poss_resends,
+                    &pending_acks,
+                    &negotiated,
                 ).await?; 
             }
-            Some(req) = request_receiver.recv() => {
-                handle_req(req, &mut state_machine);
+            maybe_req = request_receiver.recv(), if !orphaned => {
+                match maybe_req {
+                    Some(req) => {
+                        if let Some(req) = defer_outside_session(req, &settings, &mut deferred) {
+                            handle_req(
+                                req,
+                                &mut state_machine,
+                                &mut fix_timeouts,
+                                &echo_tags,
+                                &settings,
+                                &store,
+                                &mut deferred,
+                                &mut shutdown,
+                            );
+                        }
+                    }
+                    None => {
+                        orphaned = true;
+                        orphan_logout = handle_orphaned(&settings, &event_sender);
+                    }
+                }
+            }
+            _ = tokio::time::sleep_until(orphan_logout.unwrap_or_else(tokio::time::Instant::now)),
+                if orphan_logout.is_some() => {
+                orphan_logout = None;
+                let begin_string = Arc::clone(&state_machine.begin_string);
+                state_machine.outbox_push(crate::fix::session::build_logout_message(&begin_string));
+            }
+            _ = tokio::time::sleep_until(shutdown_deadline.unwrap_or_else(tokio::time::Instant::now)),
+                if shutdown_deadline.is_some() => {
+                if let Some(shutdown) = shutdown.as_mut() {
+                    shutdown.timed_out = true;
+                    shutdown.dropped += deferred.drain(..).count() + state_machine.outbox_discard_app_messages();
+                    tracing::warn!(dropped = shutdown.dropped, "shutdown timed out");
+                }
+            }
+            _ = tokio::time::sleep_until(session_ending_soon.map_or_else(tokio::time::Instant::now, |(_, at)| at)),
+                if session_ending_soon.is_some() => {
+                if let Some((end, _)) = session_ending_soon.take() {
+                    let _ = event_sender.send(SessionEvent::SessionEndingSoon { end });
+                }
+            }
+            _ = tokio::time::sleep_until(session_end.unwrap_or_else(tokio::time::Instant::now)),
+                if session_end.is_some() => {
+                session_end = None;
+                let begin_string = Arc::clone(&state_machine.begin_string);
+                state_machine.outbox_push(crate::fix::session::build_logout_message_with_text(
+                    &begin_string,
+                    b"End of session",
+                ));
             }
+            _ = std::future::ready(()), if !resend_queue.is_empty() => {}
+            _ = tokio::time::sleep_until(throttled_until.unwrap_or_else(tokio::time::Instant::now)),
+                if throttled_until.is_some() => {}
             _ = timeout_fut => {
+                match timeout_event {
+                    Event::WatchdogExpired(window) => {
+                        let _ = event_sender.send(SessionEvent::StrategyWatchdogExpired { window: *window });
+                    }
+                    Event::LogoutExpired => {
+                        let _ = event_sender.send(SessionEvent::LogoutTimeout { timeout: next_timeout.duration() });
+                    }
+                    _ => {}
+                }
                 state_machine.handle(timeout_event);
                 next_timeout.reset_timeout();
             }
         };
     }
-    Ok(())
 }
 
-fn test_request_duration(timeout_dur: &Duration) -> Duration {
-    (*timeout_dur * 17) / 10
+fn test_request_duration(settings: &SessionSettings, timeout_dur: &Duration) -> Option<Duration> {
+    settings
+        .heartbeat_policy
+        .test_request_after
+        .map(|intervals| heartbeat_intervals(timeout_dur, intervals))
+}
+
+fn logout_duration(settings: &SessionSettings, timeout_dur: &Duration) -> Duration {
+    settings
+        .logout_timeout
+        .unwrap_or_else(|| heartbeat_intervals(timeout_dur, settings.heartbeat_policy.logout_after))
+}
+
+// `intervals` heartbeat intervals, or one interval if `intervals` is not a positive number.
+fn heartbeat_intervals(timeout_dur: &Duration, intervals: f64) -> Duration {
+    Duration::try_from_secs_f64(timeout_dur.as_secs_f64() * intervals)
+        .ok()
+        .filter(|dur| !dur.is_zero())
+        .unwrap_or(*timeout_dur)
+}
+
+// The tokio instant of a point in time of `clock`, or now if it has passed.
+fn instant_at(clock: &dyn Clock, at: DateTime<Utc>) -> tokio::time::Instant {
+    tokio::time::Instant::now() + (at - clock.now()).to_std().unwrap_or_default()
+}
+
+// Whether the session of the schedule is active, if there is a schedule.
+fn in_session(settings: &SessionSettings) -> bool {
+    settings
+        .schedule
+        .is_none_or(|schedule| schedule.is_active(settings.clock.now()))
+}
+
+type Deferred = VecDeque<(MessageBuilder, oneshot::Sender<bool>, Option<OutboxPermit>)>;
+
+// Queue the messages held until the session of the schedule started.
+fn release_deferred(deferred: &mut Deferred, state_machine: &mut MyStateMachine, echo_tags: &EchoTags) {
+    for (builder, resp_sender, permit) in deferred.drain(..) {
+        state_machine.outbox_push_with_permit(echo_tags.apply(builder), resp_sender, permit);
+    }
 }
 
-fn logout_duration(timeout_dur: &Duration) -> Duration {
-    *timeout_dur * 2
+// A shutdown requested with `FixApplicationHandle::shutdown`, answered once the engine
+// disconnects.
+struct Shutdown {
+    deadline: tokio::time::Instant,
+    timed_out: bool,
+    dropped: usize,
+    resp_senders: Vec<oneshot::Sender<ShutdownReport>>,
 }
 
-fn handle_req(req: Request, state_machine: &mut MyStateMachine) {
+impl Shutdown {
+    fn new(timeout: Duration) -> Shutdown {
+        Shutdown {
+            deadline: tokio::time::Instant::now() + timeout,
+            timed_out: false,
+            dropped: 0,
+            resp_senders: Vec::new(),
+        }
+    }
+
+    fn respond(self, logged_out: bool) {
+        for resp_sender in self.resp_senders {
+            let _ = resp_sender.send(ShutdownReport {
+                dropped: self.dropped,
+                timed_out: self.timed_out,
+                logged_out,
+            });
+        }
+    }
+}
+
+// Refuse or queue a message sent outside the session of the schedule. Returns the request if it
+// should be handled now.
+fn defer_outside_session(req: Request, settings: &SessionSettings, deferred: &mut Deferred) -> Option<Request> {
+    let Some(schedule) = settings.schedule.filter(|_| !in_session(settings)) else {
+        return Some(req);
+    };
     match req {
-        Request::SendMessage {
-            resp_sender,
-            builder,
-        } => {
-            state_machine.outbox_push_with_sender(builder, resp_sender);
+        Request::SendMessage { resp_sender, builder, permit } => {
+            match schedule.outside_window() {
+                OutsideWindow::Refuse => {
+                    let _ = resp_sender.send(false);
+                }
+                OutsideWindow::Queue => deferred.push_back((builder, resp_sender, permit)),
+            }
+            None
+        }
+        Request::SendBatch { resp_sender, builders, permit } => {
+            match schedule.outside_window() {
+                OutsideWindow::Refuse => {
+                    let _ = resp_sender.send(false);
+                }
+                OutsideWindow::Queue => deferred.extend(with_last_sender(builders, resp_sender, permit)),
+            }
+            None
+        }
+        req => Some(req),
+    }
+}
+
+// Wait for the session of the schedule to start, refusing or queueing the messages sent
+// meanwhile. Returns `false` if the engine should end instead, because the session never starts,
+// a logout was requested, or every handle was dropped and the orphan policy is to log out.
+async fn wait_for_session(
+    schedule: &SessionSchedule,
+    settings: &SessionSettings,
+    request_receiver: &mut mpsc::UnboundedReceiver<Request>,
+    store: &Store,
+    deferred: &mut Deferred,
+    watchdog: &mut Option<Duration>,
+    event_sender: &broadcast::Sender<SessionEvent>,
+) -> bool {
+    let now = settings.clock.now();
+    if schedule.is_active(now) {
+        return true;
+    }
+    let Some(start) = schedule.next_start(now) else {
+        return false;
+    };
+    let start = tokio::time::sleep_until(instant_at(&*settings.clock, start));
+    tokio::pin!(start);
+    let mut orphaned = false;
+    loop {
+        tokio::select! {
+            _ = &mut start => return true,
+            maybe_req = request_receiver.recv(), if !orphaned => {
+                let Some(req) = maybe_req else {
+                    if handle_orphaned(settings, event_sender).is_some() {
+                        return false;
+                    }
+                    orphaned = true;
+                    continue;
+                };
+                match defer_outside_session(req, settings, deferred) {
+                    Some(Request::Logout { resp_sender }) => {
+                        let _ = resp_sender.send(true);
+                        return false;
+                    }
+                    Some(Request::Shutdown { resp_sender, .. }) => {
+                        let _ = resp_sender.send(ShutdownReport {
+                            dropped: deferred.len(),
+                            logged_out: true,
+                            ..Default::default()
+                        });
+                        return false;
+                    }
+                    Some(
+                        Request::Logon { resp_sender }
+                        | Request::ResetSequence { resp_sender, .. }
+                        | Request::Resend { resp_sender, .. },
+                    ) => {
+                        let _ = resp_sender.send(false);
+                    }
+                    Some(Request::Watchdog { window }) => *watchdog = window,
+                    Some(Request::Flush { resp_sender, until }) => {
+                        let last_sender = deferred.back_mut().map(|(_, last_sender, _)| last_sender);
+                        queue_flush(resp_sender, until, last_sender, store);
+                    }
+                    Some(Request::SendMessage { .. } | Request::SendBatch { .. }) | None => {}
+                }
+            }
+        }
+    }
+}
+
+// How long the peer of a refused logon has to close the connection after reading the
+// `Logout<5>`.
+const REFUSED_LOGON_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Answer the `Logon<A>` of a peer connecting outside the session of the schedule with a
+// `Logout<5>`, without processing it.
+async fn refuse_logon(
+    state_machine: &mut MyStateMachine,
+    stream: &mut TcpStream,
+    additional_headers: &AdditionalHeaders,
+    logger: &mut SessionLogger,
+) -> Result<()> {
+    tracing::info!("refusing logon outside of the session schedule");
+    let builder = crate::fix::session::build_logout_message_with_text(
+        &state_machine.begin_string,
+        b"Logon outside of the session schedule",
+    );
+    let msg_seq_num = state_machine.sequences.next_outgoing();
+    let msg_buf = build_message_with_headers(builder, msg_seq_num, additional_headers).await?;
+    stream::send_messages(&[msg_buf], stream, logger).await?;
+    // closing with the `Logon<A>` unread resets the connection, which can discard the
+    // `Logout<5>` before the peer reads it, so wait for the peer to close first
+    let _ = stream.shutdown().await;
+    let mut unread = [0; 1024];
+    let _ = tokio::time::timeout(REFUSED_LOGON_TIMEOUT, async {
+        while matches!(stream.read(&mut unread).await, Ok(n) if n > 0) {}
+    })
+    .await;
+    Ok(())
+}
+
+// Every handle was dropped. Returns when to log out, if the policy is to log out.
+fn handle_orphaned(
+    settings: &SessionSettings,
+    event_sender: &broadcast::Sender<SessionEvent>,
+) -> Option<tokio::time::Instant> {
+    let logout_after = match settings.orphan_policy {
+        OrphanPolicy::KeepAlive => None,
+        OrphanPolicy::Logout(grace) => Some(grace),
+    };
+    tracing::warn!(?logout_after, "every handle to the engine was dropped");
+    let _ = event_sender.send(SessionEvent::AllHandlesDropped { logout_after });
+    logout_after.map(|grace| tokio::time::Instant::now() + grace)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_req(
+    req: Request,
+    state_machine: &mut MyStateMachine,
+    fix_timeouts: &mut FixTimeouts,
+    echo_tags: &EchoTags,
+    settings: &SessionSettings,
+    store: &Store,
+    deferred: &mut Deferred,
+    shutdown: &mut Option<Shutdown>,
+) {
+    match req {
+        Request::SendMessage { resp_sender, builder, permit } => {
+            state_machine.outbox_push_with_permit(echo_tags.apply(builder), resp_sender, permit);
+        }
+        Request::SendBatch { resp_sender, builders, permit } => {
+            for (builder, resp_sender, permit) in with_last_sender(builders, resp_sender, permit) {
+                state_machine.outbox_push_with_permit(echo_tags.apply(builder), resp_sender, permit);
+            }
         }
         Request::Logout { resp_sender } => {
-            let begin_string = Arc::clone(&state_machine.begin_string);
-            state_machine
-                .outbox_push_with_sender(
-                    crate::fix::session::build_logout_message(&begin_string), 
-                    resp_sender,
-                );
+            queue_logout(state_machine, resp_sender);
+        }
+        Request::Shutdown { resp_sender, drain, timeout } => {
+            let shutdown = shutdown.get_or_insert_with(|| Shutdown::new(timeout));
+            shutdown.resp_senders.push(resp_sender);
+            shutdown.dropped += match drain {
+                Drain::All if session::is_logged_in(state_machine) && in_session(settings) => {
+                    release_deferred(deferred, state_machine, echo_tags);
+                    0
+                }
+                Drain::All => deferred.drain(..).count(),
+                Drain::None => deferred.drain(..).count() + state_machine.outbox_discard_app_messages(),
+            };
+            let (resp_sender, _) = oneshot::channel();
+            queue_logout(state_machine, resp_sender);
         }
         Request::Logon { resp_sender } => {
             let _ = resp_sender.send(true);
         }
+        Request::Watchdog { window } => {
+            fix_timeouts.set_watchdog(window);
+        }
+        Request::ResetSequence { resp_sender, new_seq_no } => {
+            state_machine.queue_sequence_reset(new_seq_no, resp_sender);
+        }
+        Request::Resend {
+            resp_sender,
+            begin_seq_no,
+            end_seq_no,
+        } => {
+            state_machine.queue_resend_request(begin_seq_no, end_seq_no, resp_sender);
+        }
+        Request::Flush { resp_sender, until } => {
+            let last_sender = match deferred.back_mut() {
+                Some((_, last_sender, _)) => Some(last_sender),
+                None => state_machine.outbox_last_sender(),
+            };
+            queue_flush(resp_sender, until, last_sender, store);
+        }
+    }
+}
+
+// Answer `resp_sender` once the message of `last_sender`, which was the last one queued by the
+// handles, was written, and every message before it was stored if `until` says so. Messages are
+// written in order, so every message queued before it was written by then.
+fn queue_flush(
+    resp_sender: oneshot::Sender<bool>,
+    until: Flush,
+    last_sender: Option<&mut oneshot::Sender<bool>>,
+    store: &Store,
+) {
+    let last_written = last_sender.map(|last_sender| {
+        let (sender, receiver) = oneshot::channel();
+        (std::mem::replace(last_sender, sender), receiver)
+    });
+    let store = (until == Flush::Stored).then(|| store.clone());
+    tokio::spawn(async move {
+        let mut flushed = true;
+        if let Some((last_sender, receiver)) = last_written {
+            flushed = receiver.await == Ok(true);
+            let _ = last_sender.send(flushed);
+        }
+        if let Some(store) = store.filter(|_| flushed) {
+            flushed = store.flush().await.is_ok();
+        }
+        let _ = resp_sender.send(flushed);
+    });
+}
+
+// Pair each message of a batch with a response sender, where only the sender of the last message
+// is answered to the handle. The permit of the batch is released with its last message. An empty
+// batch has nothing to send, so the handle is answered at once.
+fn with_last_sender(
+    builders: Vec<MessageBuilder>,
+    resp_sender: oneshot::Sender<bool>,
+    permit: Option<OutboxPermit>,
+) -> impl Iterator<Item = (MessageBuilder, oneshot::Sender<bool>, Option<OutboxPermit>)> {
+    let mut last_sender = match builders.len().checked_sub(1) {
+        Some(last) => Some((last, resp_sender, permit)),
+        None => {
+            let _ = resp_sender.send(true);
+            None
+        }
+    };
+    builders.into_iter().enumerate().map(move |(i, builder)| match last_sender.take_if(|(last, ..)| i == *last) {
+        Some((_, resp_sender, permit)) => (builder, resp_sender, permit),
+        None => (builder, oneshot::channel().0, None),
+    })
+}
+
+// Queue a `Logout<5>`, or join the logout in flight, answering `resp_sender` once it is done.
+fn queue_logout(state_machine: &mut MyStateMachine, resp_sender: oneshot::Sender<bool>) {
+    if let Err(resp_sender) = state_machine.join_logout(resp_sender) {
+        let begin_string = Arc::clone(&state_machine.begin_string);
+        state_machine
+            .outbox_push_with_sender(
+                crate::fix::session::build_logout_message(&begin_string), 
+                resp_sender,
+            );
     }
 }
 
 #[allow(clippy::too_many_arguments)]
 async fn handle_msg(
-    maybe_msg: Result<MsgBuf, SessionError>,
+    maybe_msg: Result<Incoming, SessionError>,
     state_machine: &mut MyStateMachine,
     fix_timeouts: &mut FixTimeouts,
     store: &Store,
     settings: &SessionSettings,
-    stream: &mut TcpStream,
     logger: &mut impl Logger,
-    additional_headers: &AdditionalHeaders,
-    message_received_event_sender: &mpsc::UnboundedSender<Arc<MsgBuf>>,
+    delivery: &mut Delivery,
+    metrics: &Metrics,
+    event_sender: &broadcast::Sender<SessionEvent>,
+    resend_queue: &mut ResendQueue,
+    live_buffer: &mut LiveBuffer,
+    echo_tags: &mut EchoTags,
+    poss_resends: &mut Option<PossResendWindow>,
+    pending_acks: &PendingAcks,
+    negotiated: &Mutex<Option<NegotiatedParams>>,
 ) -> Result<()> {
     fix_timeouts.reset_test_request();
+    let msg_count = metrics.incr_messages_received();
 
     let msg = match maybe_msg {
-        Ok(b) => Arc::new(b),
+        Ok(msg) => msg,
         Err(error) => {
+            count_bad_message(&error, metrics);
             state_machine.handle(&Event::SessionErrorReceived { error }); 
             return Ok(());
         }
@@ -450,12 +1182,29 @@ async fn handle_msg(
 
     // PARSE
 
-    let mut cb: SessionParserCallback = Default::default();
+    let mut cb = SessionParserCallback {
+        tolerate_out_of_place_fields: settings.tolerate_out_of_place_fields,
+        ..Default::default()
+    };
 
-    if let Err(error) = crate::fix::decode::parse(&msg.as_ref()[..], &mut cb) {
+    if let Err(error) = crate::fix::decode::parse_with_sections(msg.bytes(), &mut cb, &settings.field_sections) {
+        quarantine_message(msg.bytes(), &error, logger, metrics)?;
         state_machine.handle(&Event::SessionErrorReceived { error });
         return Ok(());
     };
+    let logon_msg_types = if cb.msg_type == LOGON.into() {
+        cb.to_logon_msg_types()
+    } else {
+        Ok(Vec::new())
+    };
+    let logon_msg_types = match logon_msg_types {
+        Ok(logon_msg_types) => logon_msg_types,
+        Err(error) => {
+            quarantine_message(msg.bytes(), &error, logger, metrics)?;
+            state_machine.handle(&Event::SessionErrorReceived { error });
+            return Ok(());
+        }
+    };
 
     // VALIDATE
 
@@ -471,14 +1220,36 @@ async fn handle_msg(
         cb.orig_sending_time,
         cb.begin_seq_no,
         cb.end_seq_no,
+        settings.clock.now(),
     ) {
+        quarantine_message(msg.bytes(), &error, logger, metrics)?;
         state_machine.handle(&Event::SessionErrorReceived { error });
         return Ok(());
     }
 
-    if let Err(error) = validate::validate_checksum(&msg) {
-        state_machine.handle(&Event::SessionErrorReceived { error });
-        return Ok(());
+    if settings.sub_id_validation {
+        // the peer addresses its messages back to the IDs it received
+        let sub_ids = [
+            (Tags::SenderSubID, settings.target_sub_id.as_deref(), cb.sender_sub_id),
+            (Tags::TargetSubID, settings.sender_sub_id.as_deref(), cb.target_sub_id),
+            (Tags::DeliverToCompID, settings.on_behalf_of_comp_id.as_deref(), cb.deliver_to_comp_id),
+            (Tags::OnBehalfOfCompID, settings.deliver_to_comp_id.as_deref(), cb.on_behalf_of_comp_id),
+        ];
+        if let Err(error) = validate::validate_sub_ids(sub_ids, cb.msg_type, cb.msg_seq_num) {
+            quarantine_message(msg.bytes(), &error, logger, metrics)?;
+            state_machine.handle(&Event::SessionErrorReceived { error });
+            return Ok(());
+        }
+    }
+
+    if settings.checksum_validation.should_validate(msg_count) {
+        if let Err(error) = validate::validate_checksum(msg.bytes()) {
+            quarantine_message(msg.bytes(), &error, logger, metrics)?;
+            state_machine.handle(&Event::SessionErrorReceived { error });
+            return Ok(());
+        }
+    } else {
+        metrics.incr_checksums_skipped();
     }
 
     // HANDLE
@@ -486,33 +1257,81 @@ async fn handle_msg(
     let msg_seq_num = cb.msg_seq_num;
     let maybe_msg_type = cb.msg_type.try_into(); 
 
+    if to_poss_dup_flag(cb.poss_dup_flag) != Some(PossDupFlag::YES) {
+        let app_msg = || (!is_session_message(cb.msg_type)).then(|| msg.to_shared());
+        live_buffer.hold(session::resend_end(state_machine), msg_seq_num, app_msg);
+    }
+
+    if let Some(session_callback) = settings.session_callback.as_deref() {
+        if is_session_message(cb.msg_type) {
+            session_callback.on_admin_msg_in(msg.bytes());
+        }
+    }
+
     match maybe_msg_type {
         Ok(LOGON) => {
+            let reset_seq_num_flag = cb.reset_seq_num_flag.map(|f| f == 'Y').unwrap_or(false);
+            let _ = event_sender.send(SessionEvent::LogonReceived {
+                heart_bt_int: cb.heart_bt_int,
+                max_message_size: cb.max_message_size,
+                msg_types: logon_msg_types,
+                encrypt_method: cb.encrypt_method,
+                reset_seq_num: reset_seq_num_flag,
+            });
+            if session::is_logged_in(state_machine) && !reset_seq_num_flag {
+                let _ = event_sender.send(SessionEvent::DuplicateLogonReceived {
+                    msg_seq_num,
+                    poss_dup: to_poss_dup_flag(cb.poss_dup_flag) == Some(PossDupFlag::YES),
+                });
+            }
             let mut heartbt_secs = settings.heartbeat_timeout.as_secs() as u32;
             if let Some(i) = cb.heart_bt_int {
                 heartbt_secs = i;
                 let heartbt_dur = tokio::time::Duration::from_secs(i as u64);
                 fix_timeouts.set_durations(
                     heartbt_dur,
-                    test_request_duration(&heartbt_dur),
-                    logout_duration(&heartbt_dur),
+                    test_request_duration(settings, &heartbt_dur),
+                    logout_duration(settings, &heartbt_dur),
                 );
             }
+            *negotiated.lock().unwrap() = Some(NegotiatedParams {
+                heartbeat_interval: Duration::from_secs(heartbt_secs as u64),
+                encrypt_method: cb.encrypt_method,
+                reset_seq_num: reset_seq_num_flag,
+                max_message_size: cb.max_message_size,
+            });
             state_machine.handle(&Event::LogonReceived(
                 msg_seq_num,
                 heartbt_secs,
                 cb.encrypt_method,
-                cb.reset_seq_num_flag.map(|f| f == 'Y').unwrap_or(false),
+                reset_seq_num_flag,
                 to_poss_dup_flag(cb.poss_dup_flag),
             ));
+            persist_sequences_reset(state_machine, store, settings.epoch.clone(), event_sender).await?;
         }
         Ok(LOGOUT) => {
+            let expected = cb
+                .text
+                .and_then(|text| expected_msg_seq_num(text, &settings.sequence_too_low_patterns));
+            if let Some(expected) = expected {
+                let next_outgoing = state_machine.sequences.peek_outgoing();
+                let _ = event_sender.send(SessionEvent::OutgoingSequenceTooLow { expected, next_outgoing });
+                if heal_outgoing_sequence(state_machine, expected, msg_seq_num, store, settings, event_sender).await? {
+                    return Ok(());
+                }
+            }
             state_machine.handle(&Event::LogoutReceived(
                 msg_seq_num,
+                cb.text.map(|text| String::from_utf8_lossy(text).into_owned()),
                 to_poss_dup_flag(cb.poss_dup_flag),
             ));
         }
         Ok(HEARTBEAT) => {
+            fix_timeouts.reset_watchdog();
+            metrics.record_heartbeat_received(settings.clock.now());
+            if let Some(test_req_id) = cb.test_req_id {
+                correlate_test_request(test_req_id, state_machine, settings, metrics);
+            }
             state_machine.handle(&Event::HeartbeatReceived(
                 msg_seq_num,
                 to_poss_dup_flag(cb.poss_dup_flag),
@@ -534,6 +1353,17 @@ async fn handle_msg(
             }
         }
         Ok(REJECT) => {
+            tracing::warn!(
+                ref_seq_num = cb.ref_seq_num,
+                session_reject_reason = cb.session_reject_reason,
+                "reject received: {}",
+                String::from_utf8_lossy(cb.text.unwrap_or_default())
+            );
+            let _ = event_sender.send(SessionEvent::RejectReceived {
+                ref_seq_num: cb.ref_seq_num,
+                session_reject_reason: cb.session_reject_reason,
+                text: cb.text.map(|text| String::from_utf8_lossy(text).into_owned()),
+            });
             state_machine.handle(&Event::RejectReceived(
                 msg_seq_num,
                 to_poss_dup_flag(cb.poss_dup_flag),
@@ -554,6 +1384,9 @@ async fn handle_msg(
                 _ => state_machine.sequences.peek_outgoing() - 1,
             };
             let b = cb.begin_seq_no.unwrap_or(e);
+            tracing::info!(begin_seq_no = b, end_seq_no = e, "resend requested");
+            let _ = event_sender.send(SessionEvent::ResendRequested { begin_seq_no: b, end_seq_no: e });
+            metrics.incr_resend_requests_received();
 
             if session::should_resend(state_machine) {
                 let prev_messages = store
@@ -564,7 +1397,7 @@ async fn handle_msg(
                         state_machine.sequences.peek_outgoing() - 1,
                     )
                     .await?;
-                resend_messages(prev_messages, stream, additional_headers, logger).await?;
+                resend_queue.push(prev_messages);
             }
             state_machine.handle(&Event::ResendRequestReceived(
                 cb.msg_seq_num,
@@ -574,8 +1407,14 @@ async fn handle_msg(
             ));
         }
         Ok(ref msg_type) if msg_type.is_application() => {
-            if session::should_pass_app_message(state_machine, msg_seq_num) {
-                let _ = message_received_event_sender.send(Arc::clone(&msg));
+            fix_timeouts.reset_watchdog();
+            if *msg_type == BUSINESS_MESSAGE_REJECT {
+                publish_business_reject(&msg.to_shared(), event_sender);
+            }
+            if session::should_pass_app_message(state_machine, msg_seq_num)
+                && check_poss_resend(msg_seq_num, msg.bytes(), poss_resends, settings, event_sender)
+            {
+                deliver_app_message(msg_seq_num, &msg, settings, store, delivery, echo_tags, pending_acks)?;
             }
             state_machine.handle(&Event::ApplicationMessageReceived(
                 msg_seq_num,
@@ -592,16 +1431,250 @@ async fn handle_msg(
             state_machine.handle(&Event::SessionErrorReceived { error });
         }
     }
+
+    if !live_buffer.is_empty() && session::resend_end(state_machine).is_none() {
+        for (msg_seq_num, msg) in live_buffer.release(&mut state_machine.sequences) {
+            if check_poss_resend(msg_seq_num, &msg[..], poss_resends, settings, event_sender) {
+                let msg = Incoming::Shared(msg);
+                deliver_app_message(msg_seq_num, &msg, settings, store, delivery, echo_tags, pending_acks)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn deliver_app_message(
+    msg_seq_num: u32,
+    msg: &Incoming,
+    settings: &SessionSettings,
+    store: &Store,
+    delivery: &mut Delivery,
+    echo_tags: &mut EchoTags,
+    pending_acks: &PendingAcks,
+) -> Result<()> {
+    if settings.store_incoming {
+        store.store_incoming(Arc::clone(&settings.epoch), msg_seq_num, Instant::now(), msg.to_shared())?;
+    }
+    echo_tags.capture(msg.bytes());
+    if let Some(session_callback) = settings.session_callback.as_deref() {
+        session_callback.on_app_msg_in(msg.bytes());
+    }
+    if settings.delivery_filter.delivers(msg) {
+        delivery.send(msg);
+    }
+    pending_acks.resolve(msg);
+    Ok(())
+}
+
+// Publish the fields of a `BusinessMessageReject<j>`. The ones that cannot be parsed are left out.
+fn publish_business_reject(msg: &MsgBuf, event_sender: &broadcast::Sender<SessionEvent>) {
+    let Ok(msg) = crate::fix::decode::FixMessage::parse(msg) else {
+        return;
+    };
+    let string = |tag: Tags| msg.get(tag).map(|value| String::from_utf8_lossy(value).into_owned());
+    let ref_seq_num = msg.get_as(Tags::RefSeqNum).and_then(Result::ok);
+    let text = string(Tags::Text);
+    tracing::warn!(ref_seq_num, "business reject received: {}", text.as_deref().unwrap_or_default());
+    let _ = event_sender.send(SessionEvent::BusinessRejectReceived {
+        ref_seq_num,
+        ref_msg_type: string(Tags::RefMsgType),
+        business_reject_reason: msg.get_as(Tags::BusinessRejectReason).and_then(Result::ok),
+        business_reject_ref_id: string(Tags::BusinessRejectRefID),
+        text,
+    });
+}
+
+// Publish whether an application message with `PossResend(97)=Y` was already received. Returns
+// false if the message is a duplicate that should be dropped.
+fn check_poss_resend(
+    msg_seq_num: u32,
+    msg: &[u8],
+    poss_resends: &mut Option<PossResendWindow>,
+    settings: &SessionSettings,
+    event_sender: &broadcast::Sender<SessionEvent>,
+) -> bool {
+    let Some(duplicate) = poss_resends.as_mut().and_then(|window| window.check(msg)) else {
+        return true;
+    };
+    if duplicate {
+        tracing::info!(msg_seq_num, dropped = settings.drop_poss_resend_duplicates, "duplicate PossResend received");
+    }
+    let _ = event_sender.send(SessionEvent::PossResendReceived { msg_seq_num, duplicate });
+    !(duplicate && settings.drop_poss_resend_duplicates)
+}
+
+// The live messages received while waiting for the peer to resend a gap, by `MsgSeqNum(34)`, with
+// the application messages to deliver once the gap is filled. Session messages were already
+// handled, and only take up their sequence number.
+struct LiveBuffer {
+    capacity: usize,
+    messages: BTreeMap<u32, Option<Arc<MsgBuf>>>,
+}
+
+impl LiveBuffer {
+    fn new(capacity: usize) -> LiveBuffer {
+        LiveBuffer {
+            capacity,
+            messages: BTreeMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // Hold a live message if it arrived after the end of the resend being waited for, with its
+    // application message, if any, from `app_msg`.
+    fn hold(
+        &mut self,
+        resend_end: Option<u32>,
+        msg_seq_num: u32,
+        app_msg: impl FnOnce() -> Option<Arc<MsgBuf>>,
+    ) {
+        match resend_end {
+            Some(end) if msg_seq_num > end && self.messages.len() < self.capacity => {
+                self.messages.entry(msg_seq_num).or_insert_with(app_msg);
+            }
+            _ => {}
+        }
+    }
+
+    // Take the held messages that follow the filled gap without another gap, in sequence. The
+    // others are dropped, to be requested again.
+    fn release(&mut self, sequences: &mut session::Sequences) -> Vec<(u32, Arc<MsgBuf>)> {
+        let mut released = Vec::new();
+        for (msg_seq_num, app_msg) in std::mem::take(&mut self.messages) {
+            if msg_seq_num < sequences.peek_incoming() {
+                continue;
+            }
+            if msg_seq_num > sequences.peek_incoming() {
+                break;
+            }
+            sequences.incr_incoming();
+            released.extend(app_msg.map(|msg| (msg_seq_num, msg)));
+        }
+        released
+    }
+}
+
+// Get the `MsgSeqNum(34)` a peer expected from the `Text(58)` of its `Logout<5>`.
+fn expected_msg_seq_num(text: &[u8], patterns: &[regex::Regex]) -> Option<u32> {
+    let text = std::str::from_utf8(text).ok()?;
+    patterns
+        .iter()
+        .find_map(|pattern| pattern.captures(text)?.get(1)?.as_str().parse().ok())
+}
+
+// Raise the outgoing sequence number to the one expected by the peer, if approved by the
+// settings. Returns `true` if the engine should reconnect and logon again.
+async fn heal_outgoing_sequence(
+    state_machine: &mut MyStateMachine,
+    expected: u32,
+    msg_seq_num: u32,
+    store: &Store,
+    settings: &SessionSettings,
+    event_sender: &broadcast::Sender<SessionEvent>,
+) -> Result<bool> {
+    if !settings.sequence_auto_heal || !matches!(settings.engine_type, FixEngineType::Client) {
+        return Ok(false);
+    }
+    let Some(from) = state_machine.heal_outgoing_sequence(expected, msg_seq_num) else {
+        return Ok(false);
+    };
+    store
+        .set_sequences(
+            settings.epoch.clone(),
+            state_machine.sequences.peek_outgoing(),
+            state_machine.sequences.peek_incoming(),
+        )
+        .await?;
+    let _ = event_sender.send(SessionEvent::OutgoingSequenceHealed { from, to: expected });
+    Ok(true)
+}
+
+fn count_bad_message(error: &SessionError, metrics: &Metrics) {
+    match error {
+        SessionError::GarbledMessage { .. } => metrics.incr_garbled_messages_received(),
+        SessionError::MessageRejected { .. } | SessionError::MissingMsgSeqNum { .. } => {
+            metrics.incr_rejected_messages_received()
+        }
+        _ => {}
+    }
+}
+
+fn quarantine_message(
+    msg: &[u8],
+    error: &SessionError,
+    logger: &mut impl Logger,
+    metrics: &Metrics,
+) -> Result<(), SessionError> {
+    count_bad_message(error, metrics);
+    tracing::warn!("quarantined incoming message: {error}");
+    logger.quarantine(msg, &error.quarantine_reason())?;
     Ok(())
 }
 
+fn correlate_test_request(
+    test_req_id: &[u8],
+    state_machine: &mut MyStateMachine,
+    settings: &SessionSettings,
+    metrics: &Metrics,
+) {
+    if let Some(round_trip) = state_machine.answer_test_request(test_req_id) {
+        metrics.record_test_request_answered(round_trip);
+        return;
+    }
+    match settings.unmatched_test_req_id {
+        UnmatchedTestReqId::Ignore => {}
+        UnmatchedTestReqId::Count => metrics.incr_unmatched_test_req_ids(),
+        UnmatchedTestReqId::Log => {
+            metrics.incr_unmatched_test_req_ids();
+            tracing::warn!(
+                "Heartbeat with unmatched TestReqID: {}",
+                String::from_utf8_lossy(test_req_id)
+            );
+        }
+    }
+}
+
+// A reset of the sequence numbers is written to the store right away, so a restart before the
+// next disconnect cannot bring back the old sequence numbers.
+async fn persist_sequences_reset(
+    state_machine: &mut MyStateMachine,
+    store: &Store,
+    epoch: Arc<String>,
+    event_sender: &broadcast::Sender<SessionEvent>,
+) -> Result<()> {
+    if let Some(initiated_by_peer) = state_machine.take_sequences_reset() {
+        store
+            .set_sequences(
+                epoch,
+                state_machine.sequences.peek_outgoing(),
+                state_machine.sequences.peek_incoming(),
+            )
+            .await?;
+        let _ = event_sender.send(SessionEvent::SequencesReset { initiated_by_peer });
+    }
+    Ok(())
+}
+
+// Put the faults of the `chaos` feature, if set, between the engine and `stream`.
+#[cfg_attr(not(feature = "chaos"), allow(unused_variables))]
+async fn transport(stream: TcpStream, settings: &SessionSettings) -> io::Result<TcpStream> {
+    #[cfg(feature = "chaos")]
+    if let Some(ref chaos) = settings.chaos {
+        return chaos::wrap(stream, chaos).await;
+    }
+    Ok(stream)
+}
+
 async fn disconnect(
     mut request_receiver: mpsc::UnboundedReceiver<Request>,
     store: Store,
     epoch: Arc<String>,
     state_machine: &MyStateMachine,
     stream: TcpStream,
-    mut logger: FileLogger, 
+    mut logger: SessionLogger, 
 ) -> Result<()> {
     request_receiver.close();
     store
@@ -619,18 +1692,33 @@ async fn disconnect(
 
 async fn receive_logon_request(
     request_receiver: &mut mpsc::UnboundedReceiver<Request>,
+    watchdog: &mut Option<Duration>,
 ) -> Option<oneshot::Sender<bool>> {
     loop {
         match request_receiver.recv().await {
             Some(Request::Logon { resp_sender }) => {
                 return Some(resp_sender);
             }
-            Some(Request::SendMessage { resp_sender, .. }) => {
+            Some(
+                Request::SendMessage { resp_sender, .. }
+                | Request::SendBatch { resp_sender, .. }
+                | Request::ResetSequence { resp_sender, .. }
+                | Request::Resend { resp_sender, .. },
+            ) => {
                 let _ = resp_sender.send(false);
             }
-            Some(Request::Logout { resp_sender, .. }) => {
+            Some(Request::Logout { resp_sender, .. } | Request::Flush { resp_sender, .. }) => {
                 let _ = resp_sender.send(true);
             }
+            Some(Request::Shutdown { resp_sender, .. }) => {
+                let _ = resp_sender.send(ShutdownReport {
+                    logged_out: true,
+                    ..Default::default()
+                });
+            }
+            Some(Request::Watchdog { window }) => {
+                *watchdog = window;
+            }
             None => {
                 return None;
             }
@@ -638,6 +1726,13 @@ async fn receive_logon_request(
     }
 }
 
+// Send the messages of the outbox. Returns the number of application messages discarded because
+// they were queued after a `Logout<5>`.
+//
+// Sending stops at the first application message over the rate limit, which is left in the
+// outbox with every message queued after it. The session messages of the engine are queued ahead
+// of the messages of the handles, so they are still sent.
+#[allow(clippy::too_many_arguments)]
 async fn send_outgoing_messages(
     state_machine: &mut MyStateMachine,
     stream: &mut TcpStream,
@@ -646,74 +1741,230 @@ async fn send_outgoing_messages(
     epoch: Arc<String>,
     logger: &mut impl Logger,
     fix_timeouts: &mut FixTimeouts,
-) -> Result<(), SessionError> {
-    if !state_machine.outbox.is_empty() {
-        fix_timeouts.reset_heartbeat();
+    session_callback: Option<&dyn SessionCallback>,
+    metrics: &Metrics,
+    rate_limiter: Option<&RateLimiter>,
+    sent_orders: &SentOrders,
+) -> Result<usize, SessionError> {
+    let mut discarded = 0;
+    if state_machine.outbox.is_empty() {
+        return Ok(discarded);
     }
-    while let Some((msg, maybe_resp_sender)) = state_machine.outbox_pop() {
+
+    // Build every queued message up to a `Logout<5>`, then write them all at once.
+    let started = Instant::now();
+    let mut msg_seq_nums = Vec::with_capacity(state_machine.outbox.len());
+    let mut msg_bufs = Vec::with_capacity(state_machine.outbox.len());
+    let mut resp_senders = Vec::new();
+    let mut logout_resp_sender = None;
+    let mut sequence_reset = false;
+    let mut written_orders = HashSet::new();
+    let mut permits = Vec::new();
+    while let Some((next, ..)) = state_machine.outbox_front() {
+        if rate_limiter.is_some_and(|rate_limiter| {
+            !is_session_message(next.msg_type()) && !rate_limiter.try_acquire(1)
+        }) {
+            break;
+        }
+        let Some((mut msg, maybe_resp_sender, permit)) = state_machine.outbox_pop() else {
+            break;
+        };
         let is_logout = msg.msg_type() == MsgType::LOGOUT.into();
+        let new_seq_no = reset_new_seq_no(&msg);
+        if new_seq_no.is_some_and(|new_seq_no| new_seq_no <= state_machine.sequences.peek_outgoing()) {
+            // the messages queued ahead of the reset took the sequence numbers up to it
+            if let Some(resp_sender) = maybe_resp_sender {
+                let _ = resp_sender.send(false);
+            }
+            continue;
+        }
+        if let Some(session_callback) = session_callback {
+            if is_session_message(msg.msg_type()) {
+                session_callback.on_admin_msg_out(&mut msg);
+            } else {
+                session_callback.on_app_msg_out(&mut msg);
+            }
+        }
+        if sent_orders.enabled() && is_new_order(msg.msg_type()) {
+            // a second order with the ID was queued before the first one was written
+            if let Some(cl_ord_id) = msg.field(Tags::ClOrdID.into()) {
+                if sent_orders.contains(cl_ord_id) || !written_orders.insert(cl_ord_id.to_vec()) {
+                    if let Some(resp_sender) = maybe_resp_sender {
+                        let _ = resp_sender.send(false);
+                    }
+                    continue;
+                }
+            }
+        }
 
         let msg_seq_num = state_machine.sequences.next_outgoing();
-        let msg_buf = build_message_with_headers(msg, msg_seq_num, additional_headers).await?;
-        stream::send_message(&msg_buf, stream, logger).await?;
-
-        store
-            .store_outgoing(epoch.clone(), msg_seq_num, Instant::now(), Arc::new(msg_buf))
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        msg_bufs.push(build_message_with_headers(msg, msg_seq_num, additional_headers).await?);
+        msg_seq_nums.push(msg_seq_num);
+        permits.extend(permit);
+        if let Some(new_seq_no) = new_seq_no {
+            state_machine.sequences.set_outgoing(new_seq_no);
+            sequence_reset = true;
+        }
 
         if is_logout {
-            state_machine.outbox_clear();
-            state_machine.set_logout_resp_sender(maybe_resp_sender);
-            state_machine.handle(&Event::LogoutSent);
-            fix_timeouts.start_logout_timeout();
+            logout_resp_sender = Some(maybe_resp_sender);
             break;
-        } else if let Some(resp_sender) = maybe_resp_sender {
-            let _ = resp_sender.send(true);
         }
+        resp_senders.extend(maybe_resp_sender);
     }
-    Ok(())
+    if msg_bufs.is_empty() {
+        return Ok(discarded);
+    }
+    fix_timeouts.messages_sent();
+    stream::send_messages(&msg_bufs, stream, logger).await?;
+    sent_orders.extend(written_orders);
+    // the written messages no longer count against the outbox of the handles
+    drop(permits);
+
+    let send_instant = Instant::now();
+    for (msg_seq_num, msg_buf) in msg_seq_nums.into_iter().zip(msg_bufs) {
+        metrics.record_message_sent(started.elapsed());
+        store
+            .store_outgoing(epoch.clone(), msg_seq_num, send_instant, Arc::new(msg_buf))
+            .map_err(std::io::Error::other)?;
+    }
+    if sequence_reset {
+        store
+            .set_sequences(
+                epoch,
+                state_machine.sequences.peek_outgoing(),
+                state_machine.sequences.peek_incoming(),
+            )
+            .await
+            .map_err(std::io::Error::other)?;
+    }
+    for resp_sender in resp_senders {
+        let _ = resp_sender.send(true);
+    }
+    if let Some(maybe_resp_sender) = logout_resp_sender {
+        discarded = state_machine.outbox_discard_app_messages();
+        state_machine.outbox_clear();
+        state_machine.add_logout_resp_sender(maybe_resp_sender);
+        state_machine.handle(&Event::LogoutSent);
+        fix_timeouts.start_logout_timeout();
+    }
+    Ok(discarded)
 }
 
-async fn resend_messages(
-    mut messages: Vec<(u32, Vec<u8>)>,
-    stream: &mut TcpStream,
-    additional_headers: &AdditionalHeaders,
-    logger: &mut impl Logger,
-) -> Result<(), SessionError> {
-    messages.sort_by(|(a, _), (b, _)| a.cmp(b));
-    let mut session_msg_count = 0;
-    for (_, (msg_seq_num, msg)) in messages.iter().enumerate() {
-        let transformer = Transformer::try_from(msg.clone())?;
-        let msg_type =
-            MsgType::try_from(transformer.msg_type).or(Err(SessionError::ResendError))?;
-        if msg_type.is_session() {
-            session_msg_count += 1;
-            continue;
+// The `NewSeqNo(36)` of a `SequenceReset<4>` in reset mode, such as one queued by
+// `FixApplicationHandle::reset_sequence`.
+fn reset_new_seq_no(msg: &MessageBuilder) -> Option<u32> {
+    if msg.msg_type() != MsgType::SEQUENCE_RESET.into() || msg.field(Tags::GapFillFlag.into()) == Some(b"Y") {
+        return None;
+    }
+    std::str::from_utf8(msg.field(Tags::NewSeqNo.into())?).ok()?.parse().ok()
+}
+
+// The number of stored messages resent in each iteration of the engine's loop, so that a large
+// `ResendRequest<2>` does not hold up heartbeats and live messages.
+const RESEND_BATCH_SIZE: usize = 64;
+
+// The messages waiting to be resent, in the order their `ResendRequest<2>`s were received. Runs of
+// session messages, or of every message with `ResendPolicy::GapFill`, are replaced by a single gap
+// fill, even across batches. So are the application messages of a request before the last
+// `max_window` ones, which are marked as not to be replayed.
+#[derive(Default)]
+struct ResendQueue {
+    messages: VecDeque<(u32, Vec<u8>, bool)>,
+    session_msg_count: u32,
+    last_seq_num: u32,
+    policy: ResendPolicy,
+    max_window: Option<u32>,
+}
+
+impl ResendQueue {
+    fn new(policy: ResendPolicy, max_window: Option<u32>) -> ResendQueue {
+        ResendQueue {
+            policy,
+            max_window,
+            ..Default::default()
         }
-        if session_msg_count > 0 {
-            let msg_buf = build_gap_fill_msg(
-                msg_seq_num - session_msg_count,
-                *msg_seq_num,
-                additional_headers,
-            )
-            .await?;
+    }
+
+    fn push(&mut self, mut messages: Vec<(u32, Vec<u8>)>) {
+        messages.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let replay_from = match (self.max_window, messages.last()) {
+            (Some(max_window), Some((last, _))) => (last + 1).saturating_sub(max_window),
+            _ => 0,
+        };
+        self.messages.extend(
+            messages
+                .into_iter()
+                .map(|(msg_seq_num, msg)| (msg_seq_num, msg, msg_seq_num >= replay_from)),
+        );
+    }
+
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.session_msg_count == 0
+    }
+
+    async fn send_batch<W: tokio::io::AsyncWrite + Unpin>(
+        &mut self,
+        stream: &mut W,
+        additional_headers: &AdditionalHeaders,
+        logger: &mut impl Logger,
+        metrics: &Metrics,
+    ) -> Result<(), SessionError> {
+        for _ in 0..RESEND_BATCH_SIZE {
+            let Some((msg_seq_num, msg, replay)) = self.messages.pop_front() else {
+                break;
+            };
+            if self.session_msg_count > 0 && msg_seq_num != self.last_seq_num + 1 {
+                // the next message belongs to another request
+                self.send_gap_fill(stream, additional_headers, logger, metrics).await?;
+            }
+            self.last_seq_num = msg_seq_num;
+            let transformer = Transformer::try_from(msg)?;
+            let msg_type =
+                MsgType::try_from(transformer.msg_type).or(Err(SessionError::ResendError))?;
+            if msg_type.is_session() || self.policy == ResendPolicy::GapFill || !replay {
+                self.session_msg_count += 1;
+                continue;
+            }
+            if self.session_msg_count > 0 {
+                let msg_buf = build_gap_fill_msg(
+                    msg_seq_num - self.session_msg_count,
+                    msg_seq_num,
+                    additional_headers,
+                )
+                .await?;
+                stream::send_message(&msg_buf, stream, logger).await?;
+                metrics.incr_gap_fills_sent();
+                self.session_msg_count = 0;
+            }
+            let msg_buf = transform_message(transformer, additional_headers).await?;
             stream::send_message(&msg_buf, stream, logger).await?;
-            session_msg_count = 0;
+            metrics.incr_messages_resent();
         }
-        let msg_buf = transform_message(transformer).await?;
-        stream::send_message(&msg_buf, stream, logger).await?;
+        if self.messages.is_empty() && self.session_msg_count > 0 {
+            self.send_gap_fill(stream, additional_headers, logger, metrics).await?;
+        }
+        Ok(())
     }
-    if session_msg_count > 0 {
-        let last_seq_num = messages[messages.len() - 1].0;
+
+    async fn send_gap_fill<W: tokio::io::AsyncWrite + Unpin>(
+        &mut self,
+        stream: &mut W,
+        additional_headers: &AdditionalHeaders,
+        logger: &mut impl Logger,
+        metrics: &Metrics,
+    ) -> Result<(), SessionError> {
         let msg_buf = build_gap_fill_msg(
-            last_seq_num - session_msg_count + 1,
-            last_seq_num + 1,
+            self.last_seq_num - self.session_msg_count + 1,
+            self.last_seq_num + 1,
             additional_headers,
         )
         .await?;
         stream::send_message(&msg_buf, stream, logger).await?;
+        metrics.incr_gap_fills_sent();
+        self.session_msg_count = 0;
+        Ok(())
     }
-    Ok(())
 }
 
 async fn build_message_with_headers(
@@ -724,7 +1975,7 @@ async fn build_message_with_headers(
     let mut buf = Vec::new();
     let mut cur = tokio::io::BufWriter::new(&mut buf);
 
-    msg.build_async(&mut cur, msg_seq_num, additional_headers, Utc::now())
+    msg.build_async(&mut cur, msg_seq_num, additional_headers, additional_headers.now())
         .await?;
     cur.flush().await?;
     Ok(buf.into())
@@ -735,19 +1986,22 @@ async fn build_gap_fill_msg(
     new_seq_num: u32,
     additional_headers: &AdditionalHeaders,
 ) -> Result<MsgBuf, SessionError> {
-    let builder = MessageBuilder::new("FIX.4.2", MsgType::SEQUENCE_RESET.into())
+    let builder = MessageBuilder::new(additional_headers.begin_string(), MsgType::SEQUENCE_RESET.into())
         .push(Tags::NewSeqNo, SerializedInt::from(new_seq_num).as_bytes())
         .push(Tags::GapFillFlag, b"Y");
     let msg = build_message_with_headers(builder, msg_seq_num, additional_headers).await?;
     let transformer = Transformer::try_from(msg.0)?;
-    transform_message(transformer).await
+    transform_message(transformer, additional_headers).await
 }
 
-async fn transform_message(transformer: Transformer) -> Result<MsgBuf, SessionError> {
+async fn transform_message(
+    transformer: Transformer,
+    additional_headers: &AdditionalHeaders,
+) -> Result<MsgBuf, SessionError> {
     let mut buf = Vec::new();
     let mut cur = tokio::io::BufWriter::new(&mut buf);
     transformer
-        .build_async(&mut cur)
+        .build_async(&mut cur, &additional_headers.format_time(additional_headers.now()))
         .await
         .or(Err(SessionError::ResendError))?;
     cur.flush().await?;
@@ -758,12 +2012,20 @@ fn to_poss_dup_flag(maybe_flag: Option<char>) -> Option<PossDupFlag> {
     maybe_flag.map(|f| PossDupFlag::try_from(f).unwrap_or(PossDupFlag::NO))
 }
 
+// Whether the first `Logon<A>` of the engine should reset the sequence numbers.
 async fn is_new_session(store: &Store, settings: &SessionSettings) -> Result<bool> {
     if matches!(settings.engine_type, FixEngineType::Server) {
         return Ok(false);
     }
+    if settings.reset_seq_num || settings.reset_flag_on_initial_logon {
+        return Ok(true);
+    }
     let last_send_time = store.last_send_time(settings.epoch.clone()).await?;
-    let start_time = NaiveDateTime::new(Utc::now().date_naive(), settings.start_time).and_utc(); 
+    if let Some(schedule) = settings.schedule {
+        let start = schedule.last_start(settings.clock.now()).filter(|_| schedule.sequence_reset());
+        return Ok(start.is_some() && last_send_time < start);
+    }
+    let start_time = NaiveDateTime::new(settings.clock.now().date_naive(), settings.start_time).and_utc(); 
     Ok(last_send_time < Some(start_time))
 }
 
@@ -856,4 +2118,409 @@ mod test {
             false
         );
     }
+
+    #[test]
+    fn test_reject_text() {
+        let mut cb: SessionParserCallback = Default::default();
+        let err = crate::fix::decode::parse(
+            &b"8=FIX.4.2\x019=40\x0135=A\x0134=1\x0152=20220920-17:0x:58\x0110=000\x01"[..],
+            &mut cb,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SessionError::MessageRejected { ref text, .. }
+                if text == "Incorrect data format for value: SendingTime(52)='20220920-17:0x:58'"
+        ));
+
+        assert_eq!(
+            reject_text(Some(&SessionRejectReason::VALUE_IS_INCORRECT), Some(43), Some(b"a\x01\\")),
+            "Value is incorrect: PossDupFlag(43)='a\\x01\\\\'"
+        );
+        assert_eq!(
+            reject_text(Some(&SessionRejectReason::INVALID_TAG_NUMBER), Some(99999), None),
+            "Invalid tag number: tag 99999"
+        );
+        assert_eq!(
+            reject_text(None, Some(56), Some(&[b'x'; 40])),
+            format!("TargetCompID(56)='{}...'", "x".repeat(MAX_REJECT_VALUE_LEN))
+        );
+        assert_eq!(reject_text(Some(&SessionRejectReason::COMPID_PROBLEM), None, None), "CompID problem");
+    }
+
+    #[test]
+    fn test_parse_logon_msg_types() {
+        let mut cb: SessionParserCallback = Default::default();
+        crate::fix::decode::parse(
+            &b"8=FIX.4.2\x019=58\x0135=A\x0198=0\x01108=30\x01383=4096\x01384=2\x01372=D\x01385=S\x01372=8\x01385=R\x0110=000\x01"[..],
+            &mut cb,
+        )
+        .expect("logon should parse");
+        assert_eq!(cb.max_message_size, Some(4096));
+        assert_eq!(
+            cb.to_logon_msg_types().unwrap(),
+            vec![
+                LogonMsgType { ref_msg_type: String::from("D"), msg_direction: 'S' },
+                LogonMsgType { ref_msg_type: String::from("8"), msg_direction: 'R' },
+            ]
+        );
+
+        // an entry without its direction is rejected, not dropped
+        let mut cb: SessionParserCallback = Default::default();
+        crate::fix::decode::parse(
+            &b"8=FIX.4.2\x019=58\x0135=A\x0134=1\x0198=0\x01108=30\x01384=2\x01372=D\x01372=8\x01385=R\x0110=000\x01"[..],
+            &mut cb,
+        )
+        .expect("logon should parse");
+        assert!(matches!(
+            cb.to_logon_msg_types(),
+            Err(SessionError::MessageRejected {
+                reject_reason: Some(SessionRejectReason::REQUIRED_TAG_MISSING),
+                msg_seq_num: 1,
+                ref_tag_id: Some(385),
+                ..
+            })
+        ));
+
+        let mut cb: SessionParserCallback = Default::default();
+        assert!(crate::fix::decode::parse(
+            &b"8=FIX.4.2\x019=21\x0135=A\x01385=S\x0110=000\x01"[..],
+            &mut cb,
+        )
+        .is_err());
+
+        // only sending and receiving are directions
+        let mut cb: SessionParserCallback = Default::default();
+        assert!(matches!(
+            crate::fix::decode::parse(
+                &b"8=FIX.4.2\x019=44\x0135=A\x0134=1\x0198=0\x01108=30\x01384=1\x01372=D\x01385=X\x0110=000\x01"[..],
+                &mut cb,
+            ),
+            Err(SessionError::MessageRejected {
+                reject_reason: Some(SessionRejectReason::VALUE_IS_INCORRECT),
+                ref_tag_id: Some(385),
+                ..
+            })
+        ));
+
+        // the count has to match the entries
+        for logon in [
+            &b"8=FIX.4.2\x019=44\x0135=A\x0134=1\x0198=0\x01108=30\x01384=3\x01372=D\x01385=S\x01372=8\x01385=R\x0110=000\x01"[..],
+            &b"8=FIX.4.2\x019=44\x0135=A\x0134=1\x0198=0\x01108=30\x01384=1\x01372=D\x01385=S\x01372=8\x01385=R\x0110=000\x01"[..],
+        ] {
+            let mut cb: SessionParserCallback = Default::default();
+            crate::fix::decode::parse(logon, &mut cb).expect("logon should parse");
+            assert!(matches!(
+                cb.to_logon_msg_types(),
+                Err(SessionError::MessageRejected {
+                    reject_reason: Some(SessionRejectReason::VALUE_IS_INCORRECT),
+                    ref_tag_id: Some(384),
+                    ..
+                })
+            ));
+        }
+        let mut cb: SessionParserCallback = Default::default();
+        crate::fix::decode::parse(
+            &b"8=FIX.4.2\x019=44\x0135=A\x0134=1\x0198=0\x01108=30\x01372=D\x01385=S\x0110=000\x01"[..],
+            &mut cb,
+        )
+        .expect("logon should parse");
+        assert!(matches!(
+            cb.to_logon_msg_types(),
+            Err(SessionError::MessageRejected {
+                reject_reason: Some(SessionRejectReason::REQUIRED_TAG_MISSING),
+                ref_tag_id: Some(384),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_out_of_place_fields() {
+        let msg = &b"8=FIX.4.2\x019=5\x0135=D\x0134=2\x0155=AAA\x0152=20240102-10:00:00\x0110=000\x01"[..];
+
+        let mut cb: SessionParserCallback = Default::default();
+        crate::fix::decode::parse(msg, &mut cb).unwrap();
+        assert_eq!(cb.msg_seq_num, 2);
+        assert!(cb.sending_time.is_none());
+
+        let mut cb = SessionParserCallback {
+            tolerate_out_of_place_fields: true,
+            ..Default::default()
+        };
+        crate::fix::decode::parse(msg, &mut cb).unwrap();
+        assert!(cb.sending_time.is_some());
+    }
+
+    #[test]
+    fn test_checksum_validation_sampling() {
+        use crate::ChecksumValidation;
+
+        assert!((1..=10).all(|n| ChecksumValidation::Always.should_validate(n)));
+        assert!((1..=10).all(|n| !ChecksumValidation::Skip.should_validate(n)));
+        assert!((1..=10).all(|n| ChecksumValidation::Sample(0).should_validate(n)));
+        assert!((1..=10).all(|n| ChecksumValidation::Sample(1).should_validate(n)));
+
+        let validated: Vec<u64> = (1..=10)
+            .filter(|n| ChecksumValidation::Sample(4).should_validate(*n))
+            .collect();
+        assert_eq!(validated, vec![4, 8]);
+    }
+
+    struct NullLogger;
+    impl Logger for NullLogger {
+        fn log_message(&mut self, _: &MsgBuf) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_with_last_sender() {
+        let (resp_sender, mut resp_receiver) = oneshot::channel();
+        assert_eq!(with_last_sender(Vec::new(), resp_sender, None).count(), 0);
+        assert_eq!(resp_receiver.try_recv(), Ok(true));
+
+        let (resp_sender, mut resp_receiver) = oneshot::channel();
+        let builders = vec![MessageBuilder::new("FIX.4.2", 'D'), MessageBuilder::new("FIX.4.2", 'F')];
+        let mut batch: Vec<_> = with_last_sender(builders, resp_sender, None).collect();
+        assert_eq!(batch.len(), 2);
+        let (_, last_sender, _) = batch.pop().unwrap();
+        let (_, first_sender, _) = batch.pop().unwrap();
+        let _ = first_sender.send(false);
+        assert!(resp_receiver.try_recv().is_err());
+        let _ = last_sender.send(true);
+        assert_eq!(resp_receiver.try_recv(), Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_resend_queue_batches() {
+        let additional_headers = AdditionalHeaders::new(vec![
+            (Tags::SenderCompID.into(), b"A".to_vec()),
+            (Tags::TargetCompID.into(), b"B".to_vec()),
+        ]);
+        let mut stored = Vec::new();
+        for msg_seq_num in 1..=70 {
+            let msg_type = if msg_seq_num <= 3 || msg_seq_num == 70 {
+                MsgType::HEARTBEAT
+            } else {
+                MsgType::ORDER_SINGLE
+            };
+            let builder = MessageBuilder::new("FIX.4.2", msg_type.into());
+            let msg = build_message_with_headers(builder, msg_seq_num, &additional_headers)
+                .await
+                .unwrap();
+            stored.push((msg_seq_num, msg.0));
+        }
+        stored.reverse();
+
+        let mut queue = ResendQueue::default();
+        queue.push(stored);
+        let metrics = Metrics::new();
+        let count = |buf: &[u8], field: &[u8]| buf.windows(field.len()).filter(|w| w == &field).count();
+
+        let mut sent = Vec::new();
+        queue.send_batch(&mut sent, &additional_headers, &mut NullLogger, &metrics).await.unwrap();
+        assert!(!queue.is_empty());
+        assert_eq!(count(&sent, b"\x0135=4\x01"), 1);
+        assert_eq!(count(&sent, b"\x0136=4\x01"), 1);
+        assert_eq!(count(&sent, b"\x0135=D\x01"), RESEND_BATCH_SIZE - 3);
+
+        let mut sent = Vec::new();
+        queue.send_batch(&mut sent, &additional_headers, &mut NullLogger, &metrics).await.unwrap();
+        assert!(queue.is_empty());
+        assert_eq!(count(&sent, b"\x0135=D\x01"), 66 - (RESEND_BATCH_SIZE - 3));
+        assert_eq!(count(&sent, b"\x0136=71\x01"), 1);
+        assert_eq!(count(&sent, b"\x0143=Y\x01"), 66 - (RESEND_BATCH_SIZE - 3) + 1);
+        assert_eq!(metrics.snapshot().messages_resent, 66);
+        assert_eq!(metrics.snapshot().gap_fills_sent, 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_resend_window() {
+        let additional_headers = AdditionalHeaders::new(vec![
+            (Tags::SenderCompID.into(), b"A".to_vec()),
+            (Tags::TargetCompID.into(), b"B".to_vec()),
+        ]);
+        let mut stored = Vec::new();
+        for msg_seq_num in 1..=10 {
+            let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into());
+            let msg = build_message_with_headers(builder, msg_seq_num, &additional_headers)
+                .await
+                .unwrap();
+            stored.push((msg_seq_num, msg.0));
+        }
+
+        let mut queue = ResendQueue::new(ResendPolicy::Resend, Some(3));
+        queue.push(stored);
+        let mut sent = Vec::new();
+        queue.send_batch(&mut sent, &additional_headers, &mut NullLogger, &Metrics::new()).await.unwrap();
+        assert!(queue.is_empty());
+        let count = |field: &[u8]| sent.windows(field.len()).filter(|w| w == &field).count();
+        assert_eq!(count(b"\x0135=4\x01"), 1);
+        assert_eq!(count(b"\x0136=8\x01"), 1);
+        assert_eq!(count(b"\x0135=D\x01"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_venue_quirks() {
+        let quirks = crate::VenueQuirks {
+            header_extras: vec![(Tags::OnBehalfOfCompID.into(), b"CLIENT1".to_vec())],
+            ..crate::VenueQuirks::legacy()
+        };
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .with_venue_quirks(quirks)
+            .with_checksum_validation(crate::ChecksumValidation::Always)
+            .build()
+            .unwrap();
+        assert_eq!(settings.checksum_validation, crate::ChecksumValidation::Always);
+        assert!(settings.tolerate_out_of_place_fields);
+
+        let additional_headers = AdditionalHeaders::build(&settings);
+        let mut stored = Vec::new();
+        for msg_seq_num in 1..=3 {
+            let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into());
+            let msg = build_message_with_headers(builder, msg_seq_num, &additional_headers)
+                .await
+                .unwrap();
+            stored.push((msg_seq_num, msg.0));
+        }
+        let msg = &stored[0].1;
+        assert!(decode::parse_peeked_prefix(msg).is_ok());
+        let fields: Vec<&[u8]> = msg.split(|b| *b == b'\x01').collect();
+        assert!(fields.contains(&&b"115=CLIENT1"[..]));
+        let sending_time = fields.iter().find_map(|f| f.strip_prefix(b"52=")).unwrap();
+        assert_eq!(sending_time.len(), 17);
+        let body_len: usize = std::str::from_utf8(&fields[1][2..]).unwrap().parse().unwrap();
+        let body_start = fields[0].len() + fields[1].len() + 2;
+        assert_eq!(body_len, msg.len() - body_start - b"10=000\x01".len());
+
+        let mut queue = ResendQueue::new(settings.resend_policy, settings.max_resend_window);
+        queue.push(stored);
+        let mut sent = Vec::new();
+        queue.send_batch(&mut sent, &additional_headers, &mut NullLogger, &Metrics::new()).await.unwrap();
+        assert!(queue.is_empty());
+        assert!(!sent.windows(6).any(|w| w == b"\x0135=D\x01"));
+        assert!(sent.windows(6).any(|w| w == b"\x0136=4\x01"));
+    }
+
+    #[tokio::test]
+    async fn test_sub_ids() {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_sender_sub_id("desk1")
+            .with_on_behalf_of_comp_id("CLIENT1")
+            .with_sub_id_validation(true)
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .build()
+            .unwrap();
+        let additional_headers = AdditionalHeaders::build(&settings);
+        let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into());
+        let msg = build_message_with_headers(builder, 1, &additional_headers).await.unwrap();
+        let fields: Vec<&[u8]> = msg.0.split(|b| *b == b'\x01').collect();
+        assert!(fields.contains(&&b"50=desk1"[..]));
+        assert!(fields.contains(&&b"115=CLIENT1"[..]));
+        assert!(!fields.iter().any(|f| f.starts_with(b"57=")));
+
+        // only the sub IDs that are set are expected back
+        let sub_ids = |target_sub_id, deliver_to_comp_id| {
+            [
+                (Tags::SenderSubID, settings.target_sub_id.as_deref(), None),
+                (Tags::TargetSubID, settings.sender_sub_id.as_deref(), target_sub_id),
+                (Tags::DeliverToCompID, settings.on_behalf_of_comp_id.as_deref(), deliver_to_comp_id),
+                (Tags::OnBehalfOfCompID, settings.deliver_to_comp_id.as_deref(), None),
+            ]
+        };
+        let validate = |sub_ids| validate::validate_sub_ids(sub_ids, 'D', 2);
+        assert!(validate(sub_ids(Some(&b"desk1"[..]), Some(&b"CLIENT1"[..]))).is_ok());
+        assert!(matches!(
+            validate(sub_ids(Some(&b"desk2"[..]), Some(&b"CLIENT1"[..]))),
+            Err(SessionError::MessageRejected { ref_tag_id: Some(57), reject_reason: Some(SessionRejectReason::COMPID_PROBLEM), .. })
+        ));
+        assert!(matches!(
+            validate(sub_ids(Some(&b"desk1"[..]), None)),
+            Err(SessionError::MessageRejected { ref_tag_id: Some(128), reject_reason: Some(SessionRejectReason::REQUIRED_TAG_MISSING), .. })
+        ));
+
+        // a deliver to CompID is sent, and expected back as the on behalf of CompID
+        let mut settings = settings;
+        settings.deliver_to_comp_id = Some(String::from("BROKER1"));
+        let msg = build_message_with_headers(
+            MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into()),
+            1,
+            &AdditionalHeaders::build(&settings),
+        )
+        .await
+        .unwrap();
+        assert!(msg.0.split(|b| *b == b'\x01').any(|f| f == b"128=BROKER1"));
+        let on_behalf_of = |on_behalf_of_comp_id| {
+            [(Tags::OnBehalfOfCompID, settings.deliver_to_comp_id.as_deref(), on_behalf_of_comp_id)]
+        };
+        assert!(validate::validate_sub_ids(on_behalf_of(Some(&b"BROKER1"[..])), 'D', 2).is_ok());
+        assert!(matches!(
+            validate::validate_sub_ids(on_behalf_of(Some(&b"BROKER2"[..])), 'D', 2),
+            Err(SessionError::MessageRejected { ref_tag_id: Some(115), reject_reason: Some(SessionRejectReason::COMPID_PROBLEM), .. })
+        ));
+    }
+
+    #[test]
+    fn test_live_buffer() {
+        let msg = |msg_seq_num: u32| move || Some(Arc::new(MsgBuf(msg_seq_num.to_string().into_bytes())));
+        let released = |buffer: &mut LiveBuffer, sequences: &mut session::Sequences| {
+            buffer
+                .release(sequences)
+                .iter()
+                .map(|(_, msg)| String::from_utf8(msg.0.clone()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // waiting for 5..=10 to be resent
+        let mut buffer = LiveBuffer::new(3);
+        buffer.hold(None, 4, msg(4));
+        buffer.hold(Some(10), 10, msg(10));
+        buffer.hold(Some(10), 12, msg(12));
+        buffer.hold(Some(10), 11, || None);
+        buffer.hold(Some(10), 14, msg(14));
+        buffer.hold(Some(10), 15, msg(15));
+        assert_eq!(buffer.messages.len(), 3);
+
+        // the heartbeat 11 and the order 12 follow the gap, 13 is missing
+        let mut sequences: session::Sequences = (11, 1).into();
+        assert_eq!(released(&mut buffer, &mut sequences), vec!["12"]);
+        assert_eq!(sequences.peek_incoming(), 13);
+        assert!(buffer.is_empty());
+
+        let mut buffer = LiveBuffer::new(0);
+        buffer.hold(Some(10), 11, msg(11));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_expected_msg_seq_num() {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .build()
+            .unwrap();
+        let patterns = &settings.sequence_too_low_patterns;
+        for (text, expected) in [
+            (&b"MsgSeqNum too low, expecting 105 but received 3"[..], Some(105)),
+            (b"Expected sequence number: 42", Some(42)),
+            (b"expected MsgSeqNum=7", Some(7)),
+            (b"Too low (received 3), expected 12", Some(12)),
+            (b"Logout requested by user", None),
+        ] {
+            assert_eq!(expected_msg_seq_num(text, patterns), expected, "{:?}", text);
+        }
+    }
 }
@@ -0,0 +1,134 @@
+use crate::fix::arena::Incoming;
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::generated::{MsgType, Tags};
+use crate::fix::mem::MsgBuf;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+type Waiters = HashMap<Vec<u8>, Vec<oneshot::Sender<Arc<MsgBuf>>>>;
+
+// The application messages waiting for the peer to answer them, by `ClOrdID(11)`, shared between
+// the engine and its handles. The engine hands every `ExecutionReport<8>` and
+// `OrderCancelReject<9>` to the waiters of its `ClOrdID(11)`. Incoming messages are only parsed
+// while something is waiting.
+#[derive(Default)]
+pub(crate) struct PendingAcks {
+    waiting: AtomicUsize,
+    waiters: Mutex<Waiters>,
+}
+
+impl PendingAcks {
+    // Wait for the answer to the message with `cl_ord_id`.
+    pub(crate) fn register(&self, cl_ord_id: &[u8]) -> oneshot::Receiver<Arc<MsgBuf>> {
+        let (sender, receiver) = oneshot::channel();
+        let mut waiters = self.waiters.lock().unwrap();
+        waiters.entry(cl_ord_id.to_vec()).or_default().push(sender);
+        self.waiting.store(waiters.len(), Ordering::Release);
+        receiver
+    }
+
+    // Forget the waiters of `cl_ord_id` that stopped waiting.
+    pub(crate) fn abandon(&self, cl_ord_id: &[u8]) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if let Some(senders) = waiters.get_mut(cl_ord_id) {
+            senders.retain(|sender| !sender.is_closed());
+            if senders.is_empty() {
+                waiters.remove(cl_ord_id);
+            }
+        }
+        self.waiting.store(waiters.len(), Ordering::Release);
+    }
+
+    // Hand `msg` to the waiters of its `ClOrdID(11)` if it answers an order.
+    pub(super) fn resolve(&self, msg: &Incoming) {
+        if self.waiting.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        let mut cb = AckParser::default();
+        if parse(msg.bytes(), &mut cb).is_err() {
+            return;
+        }
+        let (Some(msg_type), Some(cl_ord_id)) = (cb.msg_type, cb.cl_ord_id) else {
+            return;
+        };
+        if !matches!(
+            MsgType::try_from(msg_type),
+            Ok(MsgType::EXECUTION_REPORT | MsgType::ORDER_CANCEL_REJECT)
+        ) {
+            return;
+        }
+        let mut waiters = self.waiters.lock().unwrap();
+        if let Some(senders) = waiters.remove(cl_ord_id) {
+            for sender in senders {
+                let _ = sender.send(msg.to_shared());
+            }
+        }
+        self.waiting.store(waiters.len(), Ordering::Release);
+    }
+}
+
+#[derive(Default)]
+struct AckParser<'a> {
+    msg_type: Option<char>,
+    cl_ord_id: Option<&'a [u8]>,
+}
+
+impl<'a> ParserCallback<'a> for AckParser<'a> {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let (Ok(Tags::MsgType), [msg_type]) = (key.try_into(), value) {
+            self.msg_type = Some(*msg_type as char);
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let Ok(Tags::ClOrdID) = key.try_into() {
+            self.cl_ord_id = Some(value);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn msg(msg_type: char, cl_ord_id: &str) -> Incoming {
+        let msg =
+            format!("8=FIX.4.2\x019=5\x0135={msg_type}\x0134=2\x0111={cl_ord_id}\x0110=000\x01");
+        Incoming::Shared(Arc::new(MsgBuf(msg.into_bytes())))
+    }
+
+    #[test]
+    fn test_pending_acks() {
+        let acks = PendingAcks::default();
+        let mut first = acks.register(b"order-1");
+        let mut second = acks.register(b"order-2");
+
+        // Only an answer to an order resolves its waiter.
+        acks.resolve(&msg('D', "order-1"));
+        acks.resolve(&msg('8', "order-3"));
+        assert!(first.try_recv().is_err());
+        acks.resolve(&msg('8', "order-1"));
+        assert_eq!(first.try_recv().unwrap().0, msg('8', "order-1").bytes());
+        acks.resolve(&msg('9', "order-2"));
+        assert_eq!(second.try_recv().unwrap().0, msg('9', "order-2").bytes());
+        assert_eq!(acks.waiting.load(Ordering::Acquire), 0);
+
+        drop(acks.register(b"order-4"));
+        acks.abandon(b"order-4");
+        assert!(acks.waiters.lock().unwrap().is_empty());
+        assert_eq!(acks.waiting.load(Ordering::Acquire), 0);
+    }
+}
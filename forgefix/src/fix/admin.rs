@@ -0,0 +1,207 @@
+//! Adjust the sequence numbers of a FIX session while no engine is running
+//!
+//! After a counterparty resets its side of a session, or a session is moved to another host, the
+//! next sequence numbers kept in the store may need to be looked at, or changed before logging
+//! on. A [`SequenceAdmin`] opens the store of a [`SessionSettings`] and reads or writes the next
+//! `MsgSeqNum(34)` of each direction for the epoch of the settings.
+//!
+//! An engine writes its sequence numbers to the store as it disconnects, so changes made while
+//! an engine with the same settings is running are overwritten. Use
+//! [`FixApplicationHandle::sequence_numbers`] to see the numbers of a running engine.
+//!
+//! ```
+//! use forgefix::{ApplicationError, SequenceNumbers, SessionSettings};
+//! use forgefix::fix::admin::SequenceAdmin;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! let settings = SessionSettings::builder()
+//!     .with_sender_comp_id("my_id")
+//!     .with_target_comp_id("peer_id")
+//! #   .with_store_path(peer.store_path())
+//! #   .with_log_dir(peer.log_dir())
+//! #   .with_socket_addr(peer.addr())
+//!     .build()?;
+//! let admin = SequenceAdmin::open(&settings);
+//! println!("next sequence numbers: {:?}", admin.sequence_numbers().await?);
+//!
+//! // the broker reset its outgoing sequence numbers
+//! admin.set_next_incoming(1).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SessionSettings`]: crate::SessionSettings
+//! [`FixApplicationHandle::sequence_numbers`]: crate::FixApplicationHandle::sequence_numbers
+
+use crate::fix::crypto::StoreCipher;
+use crate::fix::memory_store::MemoryStore;
+use crate::fix::store;
+use crate::{ApplicationError, Clock, SecretProvider, SequenceNumbers, SessionSettings};
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Reads and writes the next sequence numbers kept in the store of a FIX engine. See the
+/// [module documentation](self).
+pub struct SequenceAdmin {
+    source: Source,
+    epoch: Arc<String>,
+    store_encryption: Option<Arc<dyn SecretProvider>>,
+}
+
+enum Source {
+    Sqlite { store_path: PathBuf, shard_by_date: bool, clock: Arc<dyn Clock> },
+    Memory(MemoryStore),
+}
+
+impl SequenceAdmin {
+    /// Open the store of `settings`: its [memory store], or the sqlite file at its store path, or
+    /// its latest shard if it is [sharded by date]. The sequence numbers of the epoch of
+    /// `settings` are read and written.
+    ///
+    /// If the store is [encrypted], its sequence numbers are sealed with the key of the epoch, which
+    /// is fetched each time they are read or written.
+    ///
+    /// [memory store]: crate::SessionSettingsBuilder::with_memory_store
+    /// [sharded by date]: crate::SessionSettingsBuilder::with_store_shard_by_date
+    /// [encrypted]: crate::SessionSettingsBuilder::with_store_encryption
+    pub fn open(settings: &SessionSettings) -> SequenceAdmin {
+        let source = match settings.memory_store {
+            Some(ref memory_store) => Source::Memory(memory_store.clone()),
+            None => Source::Sqlite {
+                store_path: settings.store_path.clone(),
+                shard_by_date: settings.store_shard_by_date,
+                clock: Arc::clone(&settings.clock),
+            },
+        };
+        SequenceAdmin {
+            source,
+            epoch: Arc::clone(&settings.epoch),
+            store_encryption: settings.store_encryption.clone(),
+        }
+    }
+
+    fn cipher(&self) -> Result<Option<StoreCipher>, ApplicationError> {
+        match self.store_encryption {
+            Some(ref secret_provider) => Ok(Some(StoreCipher::new(
+                &secret_provider.store_encryption_key(&self.epoch)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// The next sequence numbers of the session. Both are 1 if the session was never started.
+    pub async fn sequence_numbers(&self) -> Result<SequenceNumbers, ApplicationError> {
+        let (next_incoming, next_outgoing) = match self.source {
+            Source::Memory(ref memory_store) => memory_store.get_sequences(&self.epoch),
+            Source::Sqlite {
+                ref store_path,
+                shard_by_date,
+                ref clock,
+            } => {
+                let path = store::sequences_file(store_path, shard_by_date, clock.now().date_naive());
+                store::read_sequences(&path, self.cipher()?.as_ref(), Arc::clone(&self.epoch))
+                    .await
+                    .map_err(std::io::Error::other)?
+            }
+        };
+        Ok(SequenceNumbers {
+            next_incoming,
+            next_outgoing,
+        })
+    }
+
+    /// Set both next sequence numbers of the session.
+    ///
+    /// Returns [`ApplicationError::InvalidSequenceNumber`] if either is 0.
+    pub async fn set_sequence_numbers(&self, numbers: SequenceNumbers) -> Result<(), ApplicationError> {
+        for msg_seq_num in [numbers.next_incoming, numbers.next_outgoing] {
+            if msg_seq_num == 0 {
+                return Err(ApplicationError::InvalidSequenceNumber(msg_seq_num));
+            }
+        }
+        match self.source {
+            Source::Memory(ref memory_store) => {
+                memory_store.set_sequences(&self.epoch, numbers.next_incoming, numbers.next_outgoing);
+            }
+            Source::Sqlite {
+                ref store_path,
+                shard_by_date,
+                ref clock,
+            } => {
+                let path = store::sequences_file(store_path, shard_by_date, clock.now().date_naive());
+                store::write_sequences(
+                    &path,
+                    self.cipher()?.as_ref(),
+                    Arc::clone(&self.epoch),
+                    numbers.next_outgoing,
+                    numbers.next_incoming,
+                )
+                .await
+                .map_err(std::io::Error::other)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the `MsgSeqNum(34)` expected on the next message from the peer, keeping the next
+    /// outgoing sequence number.
+    pub async fn set_next_incoming(&self, next_incoming: u32) -> Result<(), ApplicationError> {
+        let numbers = self.sequence_numbers().await?;
+        self.set_sequence_numbers(SequenceNumbers {
+            next_incoming,
+            ..numbers
+        })
+        .await
+    }
+
+    /// Set the `MsgSeqNum(34)` of the next message sent to the peer, keeping the next incoming
+    /// sequence number.
+    pub async fn set_next_outgoing(&self, next_outgoing: u32) -> Result<(), ApplicationError> {
+        let numbers = self.sequence_numbers().await?;
+        self.set_sequence_numbers(SequenceNumbers {
+            next_outgoing,
+            ..numbers
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sequence_admin() {
+        let dir = std::env::temp_dir().join(format!("forgefix-admin-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .build()
+            .unwrap();
+        let admin = SequenceAdmin::open(&settings);
+        let numbers = |next_incoming, next_outgoing| SequenceNumbers {
+            next_incoming,
+            next_outgoing,
+        };
+
+        assert_eq!(admin.sequence_numbers().await.unwrap(), numbers(1, 1));
+        admin.set_sequence_numbers(numbers(10, 20)).await.unwrap();
+        admin.set_next_incoming(11).await.unwrap();
+        assert_eq!(admin.sequence_numbers().await.unwrap(), numbers(11, 20));
+        admin.set_next_outgoing(21).await.unwrap();
+        let reopened = SequenceAdmin::open(&settings);
+        assert_eq!(reopened.sequence_numbers().await.unwrap(), numbers(11, 21));
+        assert!(matches!(
+            admin.set_next_outgoing(0).await,
+            Err(ApplicationError::InvalidSequenceNumber(0))
+        ));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,418 @@
+//! Delivery of incoming application messages through a ring arena
+//!
+//! By default, each incoming application message is delivered as its own [`Arc<MsgBuf>`]. On hot
+//! paths, such as a drop copy session, an engine can instead read messages straight into a single
+//! ring arena that is allocated up front, and deliver [`MsgView`]s that borrow from it. Messages
+//! are parsed and validated where they were read, so delivering one allocates nothing, as long as
+//! the [`Logger`] of the engine does not and incoming messages are not kept in the store (see
+//! [`with_store_incoming`] and [`Logger::log_bytes`]).
+//!
+//! Each message is identified by its end position in the arena, which serves as its epoch. When a
+//! [`MsgView`] is dropped, the receiver publishes that epoch, and the engine reclaims every byte
+//! written before it. Because a view borrows its [`ArenaReceiver`] mutably, only one view can be
+//! held at a time, and the borrow checker guarantees a view is dropped before the next message is
+//! received. Copy the bytes out (see [`MsgView::to_msg_buf`]) to keep a message for longer.
+//!
+//! If the arena is full because the receiver has fallen behind, or a message is larger than the
+//! arena, the message is delivered as an owned [`Arc<MsgBuf>`] instead, so the engine never waits
+//! on the receiver.
+//!
+//! See [`FixApplicationInitiator::initiate_with_arena`] and
+//! [`FixApplicationAcceptor::accept_with_arena`].
+//!
+//! ```
+//! use forgefix::{ApplicationError, FixApplicationInitiator, SessionSettings};
+//! use forgefix::fix::encode::MessageBuilder;
+//! use forgefix::fix::generated::{MsgType, Tags};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! #    let settings = SessionSettings::builder()
+//! #        .with_sender_comp_id("my_id")
+//! #        .with_target_comp_id("peer_id")
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
+//! #        .build()?;
+//! let (handle, mut receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate_with_arena(1 << 20)
+//!     .await?;
+//! handle.start_async().await?;
+//!
+//! let order = MessageBuilder::new(&handle.begin_string(), MsgType::ORDER_SINGLE.into())
+//!     .push(Tags::ClOrdID, b"order-1")
+//!     .push(Tags::Symbol, b"AAPL")
+//!     .push(Tags::Side, b"1");
+//! handle.send_message_async(order).await?;
+//!
+//! if let Some(view) = receiver.recv().await {
+//!     println!("got {} bytes: {}", view.len(), view);
+//! #   assert!(view.in_arena());
+//! }
+//! handle.end_async().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`FixApplicationInitiator::initiate_with_arena`]: crate::FixApplicationInitiator::initiate_with_arena
+//! [`FixApplicationAcceptor::accept_with_arena`]: crate::FixApplicationAcceptor::accept_with_arena
+//! [`Logger`]: crate::fix::log::Logger
+//! [`Logger::log_bytes`]: crate::fix::log::Logger::log_bytes
+//! [`with_store_incoming`]: crate::SessionSettingsBuilder::with_store_incoming
+
+use crate::fix::mem::MsgBuf;
+
+use std::cell::UnsafeCell;
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+struct Ring {
+    buf: Box<[UnsafeCell<u8>]>,
+    // The epoch of the last message released by the receiver. Every position before it may be
+    // overwritten by the engine.
+    released: AtomicU64,
+}
+
+// The engine only writes to positions the receiver has released, and the receiver only reads
+// positions that were sent to it, so the two never access the same bytes concurrently.
+unsafe impl Sync for Ring {}
+unsafe impl Send for Ring {}
+
+impl Ring {
+    fn new(capacity: usize) -> Ring {
+        Ring {
+            buf: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+            released: AtomicU64::new(0),
+        }
+    }
+
+    fn capacity(&self) -> u64 {
+        self.buf.len() as u64
+    }
+
+    fn ptr(&self, pos: u64) -> *mut u8 {
+        UnsafeCell::raw_get(self.buf[..].as_ptr()).wrapping_add((pos % self.capacity()) as usize)
+    }
+}
+
+enum Slot {
+    Arena { start: u64, len: usize },
+    Owned(Arc<MsgBuf>),
+}
+
+// How an engine delivers incoming application messages.
+pub(crate) enum Delivery {
+    Shared(mpsc::UnboundedSender<Arc<MsgBuf>>),
+    Arena(ArenaSender),
+}
+
+impl Delivery {
+    pub(crate) fn send(&mut self, msg: &Incoming) {
+        let _ = match (self, msg) {
+            (Delivery::Shared(sender), msg) => sender.send(msg.to_shared()).or(Err(())),
+            (Delivery::Arena(sender), Incoming::Arena(reserved)) => sender.commit(reserved),
+            (Delivery::Arena(sender), Incoming::Shared(msg)) => sender.send(msg),
+        };
+    }
+
+    // Space in the arena to read the next message of `len` bytes into, if the engine delivers
+    // through one and it has room.
+    pub(crate) fn reserve(&mut self, len: usize) -> Option<Reserved> {
+        match self {
+            Delivery::Shared(_) => None,
+            Delivery::Arena(sender) => sender.reserve(len),
+        }
+    }
+}
+
+// An incoming message, read into its own buffer or into space reserved in the arena.
+pub(crate) enum Incoming {
+    Shared(Arc<MsgBuf>),
+    Arena(Reserved),
+}
+
+impl Incoming {
+    pub(crate) fn bytes(&self) -> &[u8] {
+        match self {
+            Incoming::Shared(msg) => &msg.0,
+            Incoming::Arena(reserved) => reserved.bytes(),
+        }
+    }
+
+    // The message as its own buffer, copied out of the arena if it was read into it.
+    pub(crate) fn to_shared(&self) -> Arc<MsgBuf> {
+        match self {
+            Incoming::Shared(msg) => Arc::clone(msg),
+            Incoming::Arena(reserved) => Arc::new(MsgBuf(reserved.bytes().to_vec())),
+        }
+    }
+}
+
+// Space for one message in the arena, from where the previous message ended. The engine reads and
+// parses the message there, and commits it to deliver it without a copy. Until then the space is
+// not taken, and the next reservation reuses it.
+pub(crate) struct Reserved {
+    ring: Arc<Ring>,
+    start: u64,
+    len: usize,
+}
+
+impl Reserved {
+    pub(crate) fn bytes(&self) -> &[u8] {
+        // SAFETY: the space was released by the receiver, and the engine does not read a
+        // reservation once it has written another message to the arena.
+        unsafe { std::slice::from_raw_parts(self.ring.ptr(self.start), self.len) }
+    }
+
+    pub(crate) fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as for `bytes`, and the receiver reads the space only once it is committed.
+        unsafe { std::slice::from_raw_parts_mut(self.ring.ptr(self.start), self.len) }
+    }
+}
+
+/// Create the two ends of an arena delivery channel with an arena of `capacity` bytes.
+pub(crate) fn channel(capacity: usize) -> (ArenaSender, ArenaReceiver) {
+    let ring = Arc::new(Ring::new(capacity));
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (
+        ArenaSender {
+            ring: Arc::clone(&ring),
+            sender,
+            head: 0,
+        },
+        ArenaReceiver { ring, receiver },
+    )
+}
+
+pub(crate) struct ArenaSender {
+    ring: Arc<Ring>,
+    sender: mpsc::UnboundedSender<Slot>,
+    head: u64,
+}
+
+impl ArenaSender {
+    pub(crate) fn send(&mut self, msg: &Arc<MsgBuf>) -> Result<(), ()> {
+        let slot = match self.find_space(msg.len() as u64) {
+            Some(start) => {
+                // SAFETY: `find_space` only returns positions that were released by the receiver,
+                // and do not wrap past the end of the arena.
+                unsafe {
+                    std::ptr::copy_nonoverlapping(msg.0.as_ptr(), self.ring.ptr(start), msg.len());
+                }
+                self.head = start + msg.len() as u64;
+                Slot::Arena {
+                    start,
+                    len: msg.len(),
+                }
+            }
+            None => Slot::Owned(Arc::clone(msg)),
+        };
+        self.sender.send(slot).or(Err(()))
+    }
+
+    fn reserve(&self, len: usize) -> Option<Reserved> {
+        let start = self.find_space(len as u64)?;
+        Some(Reserved {
+            ring: Arc::clone(&self.ring),
+            start,
+            len,
+        })
+    }
+
+    // Deliver the message read into `reserved`, which must be the latest reservation.
+    fn commit(&mut self, reserved: &Reserved) -> Result<(), ()> {
+        self.head = reserved.start + reserved.len as u64;
+        self.sender
+            .send(Slot::Arena {
+                start: reserved.start,
+                len: reserved.len,
+            })
+            .or(Err(()))
+    }
+
+    // Find where a message of `len` bytes can be written, skipping to the start of the arena if it
+    // does not fit before the end.
+    fn find_space(&self, len: u64) -> Option<u64> {
+        let capacity = self.ring.capacity();
+        if len > capacity {
+            return None;
+        }
+        let offset = self.head % capacity;
+        let start = if offset + len > capacity {
+            self.head + capacity - offset
+        } else {
+            self.head
+        };
+        let released = self.ring.released.load(Ordering::Acquire);
+        (start + len - released <= capacity).then_some(start)
+    }
+}
+
+/// The receiving end of an arena delivery channel.
+///
+/// Receives the incoming application messages of one engine as [`MsgView`]s.
+pub struct ArenaReceiver {
+    ring: Arc<Ring>,
+    receiver: mpsc::UnboundedReceiver<Slot>,
+}
+
+impl ArenaReceiver {
+    /// Receive the next message, or `None` once the engine has ended.
+    pub async fn recv(&mut self) -> Option<MsgView<'_>> {
+        let slot = self.receiver.recv().await?;
+        Some(self.view(slot))
+    }
+
+    /// Receive the next message, blocking the current thread.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if called within an asynchronous execution context.
+    pub fn blocking_recv(&mut self) -> Option<MsgView<'_>> {
+        let slot = self.receiver.blocking_recv()?;
+        Some(self.view(slot))
+    }
+
+    /// Receive the next message if one is available.
+    pub fn try_recv(&mut self) -> Option<MsgView<'_>> {
+        let slot = self.receiver.try_recv().ok()?;
+        Some(self.view(slot))
+    }
+
+    /// Close the receiver. Messages already sent can still be received.
+    pub fn close(&mut self) {
+        self.receiver.close();
+    }
+
+    fn view(&self, slot: Slot) -> MsgView<'_> {
+        match slot {
+            Slot::Arena { start, len } => {
+                // SAFETY: the engine wrote `len` bytes at `start` before sending the slot, and
+                // will not overwrite them until the view is dropped.
+                let bytes = unsafe { std::slice::from_raw_parts(self.ring.ptr(start), len) };
+                MsgView(View::Arena {
+                    bytes,
+                    released: &self.ring.released,
+                    epoch: start + len as u64,
+                })
+            }
+            Slot::Owned(msg) => MsgView(View::Owned(msg)),
+        }
+    }
+}
+
+/// A message borrowed from an [`ArenaReceiver`].
+///
+/// Dereferences to the bytes of the message, which can be parsed with [`parse`]. The space the
+/// message occupies in the arena is reclaimed when the view is dropped.
+///
+/// [`parse`]: crate::fix::decode::parse
+pub struct MsgView<'a>(View<'a>);
+
+enum View<'a> {
+    Arena {
+        bytes: &'a [u8],
+        released: &'a AtomicU64,
+        epoch: u64,
+    },
+    Owned(Arc<MsgBuf>),
+}
+
+impl MsgView<'_> {
+    /// Whether the message was delivered from the arena, rather than as an owned buffer because
+    /// the arena was full.
+    pub fn in_arena(&self) -> bool {
+        matches!(self.0, View::Arena { .. })
+    }
+
+    /// Copy the message out of the arena.
+    pub fn to_msg_buf(&self) -> MsgBuf {
+        MsgBuf(self[..].to_vec())
+    }
+}
+
+impl std::ops::Deref for MsgView<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.0 {
+            View::Arena { bytes, .. } => bytes,
+            View::Owned(msg) => &msg.0,
+        }
+    }
+}
+
+impl Drop for MsgView<'_> {
+    fn drop(&mut self) {
+        if let View::Arena { released, epoch, .. } = self.0 {
+            released.store(epoch, Ordering::Release);
+        }
+    }
+}
+
+impl Debug for MsgView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.to_msg_buf(), f)
+    }
+}
+
+impl Display for MsgView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.to_msg_buf(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn msg(bytes: &[u8]) -> Arc<MsgBuf> {
+        Arc::new(MsgBuf(bytes.to_vec()))
+    }
+
+    #[test]
+    fn test_arena_delivery() {
+        let (mut sender, mut receiver) = channel(16);
+
+        sender.send(&msg(b"0123456789")).unwrap();
+        {
+            let view = receiver.try_recv().unwrap();
+            assert!(view.in_arena());
+            assert_eq!(&view[..], b"0123456789");
+        }
+
+        // does not fit before the end of the arena, so wraps to the start
+        sender.send(&msg(b"abcdefgh")).unwrap();
+        // the arena is still holding "abcdefgh", so this is delivered as an owned buffer
+        sender.send(&msg(b"ABCDEFGHIJ")).unwrap();
+        {
+            let view = receiver.try_recv().unwrap();
+            assert!(view.in_arena());
+            assert_eq!(&view[..], b"abcdefgh");
+        }
+        {
+            let view = receiver.try_recv().unwrap();
+            assert!(!view.in_arena());
+            assert_eq!(&view[..], b"ABCDEFGHIJ");
+        }
+
+        // larger than the arena
+        sender.send(&msg(&[b'x'; 17])).unwrap();
+        assert!(!receiver.try_recv().unwrap().in_arena());
+
+        for i in 0..100u8 {
+            sender.send(&msg(&[i; 5])).unwrap();
+            let view = receiver.try_recv().unwrap();
+            assert!(view.in_arena());
+            assert_eq!(&view[..], &[i; 5]);
+        }
+        assert!(receiver.try_recv().is_none());
+
+        drop(receiver);
+        assert!(sender.send(&msg(b"0")).is_err());
+    }
+}
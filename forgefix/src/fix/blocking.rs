@@ -0,0 +1,179 @@
+//! Receive application messages without an async runtime
+//!
+//! The synchronous API, such as [`FixApplicationInitiator::initiate_sync`], delivers incoming
+//! application messages on a tokio channel. [`BlockingReceiver`] wraps that channel for
+//! applications that have no async runtime of their own: [`BlockingReceiver::recv_timeout`] parks
+//! the calling thread until a message arrives or the timeout elapses, and
+//! [`BlockingReceiver::try_recv`] returns at once. Neither of them spins.
+//!
+//! ```
+//! use forgefix::{ApplicationError, FixApplicationInitiator, SessionSettings};
+//! use forgefix::fix::blocking::BlockingReceiver;
+//! use std::sync::mpsc::RecvTimeoutError;
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! #    let settings = SessionSettings::builder()
+//! #        .with_sender_comp_id("my_id")
+//! #        .with_target_comp_id("peer_id")
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
+//! #        .build()?;
+//! let (handle, receiver) = FixApplicationInitiator::build(settings)?.initiate_sync()?;
+//! let mut receiver = BlockingReceiver::new(receiver);
+//! handle.start_sync()?;
+//!
+//! loop {
+//!     match receiver.recv_timeout(Duration::from_millis(100)) {
+//!         Ok(msg) => println!("got an application message: {msg}"),
+//!         Err(RecvTimeoutError::Timeout) => break, // do other work here
+//!         Err(RecvTimeoutError::Disconnected) => break,
+//!     }
+//! }
+//! handle.end_sync()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`FixApplicationInitiator::initiate_sync`]: crate::FixApplicationInitiator::initiate_sync
+
+use crate::fix::mem::MsgBuf;
+
+use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::Thread;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+/// A receiver of application messages for threads without an async runtime. See the
+/// [module documentation](self).
+pub struct BlockingReceiver {
+    receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+}
+
+impl BlockingReceiver {
+    pub fn new(receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>) -> BlockingReceiver {
+        BlockingReceiver { receiver }
+    }
+
+    /// Receive the next message, parking the current thread until one arrives. Returns `None`
+    /// once the engine has ended and every message was received.
+    pub fn recv(&mut self) -> Option<Arc<MsgBuf>> {
+        self.park_until(None).ok()
+    }
+
+    /// Receive the next message, parking the current thread for at most `timeout`.
+    ///
+    /// Returns `Err(RecvTimeoutError::Disconnected)` once the engine has ended and every message
+    /// was received.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Arc<MsgBuf>, RecvTimeoutError> {
+        self.park_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Receive the next message if one is available, without waiting.
+    pub fn try_recv(&mut self) -> Result<Arc<MsgBuf>, TryRecvError> {
+        self.receiver.try_recv().map_err(|e| match e {
+            mpsc::error::TryRecvError::Empty => TryRecvError::Empty,
+            mpsc::error::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        })
+    }
+
+    /// Close the receiver. Messages already sent can still be received.
+    pub fn close(&mut self) {
+        self.receiver.close();
+    }
+
+    /// The wrapped channel.
+    pub fn into_inner(self) -> mpsc::UnboundedReceiver<Arc<MsgBuf>> {
+        self.receiver
+    }
+
+    // Poll the channel with a waker that unparks this thread, and park between polls. A deadline
+    // of `None` waits forever.
+    fn park_until(&mut self, deadline: Option<Instant>) -> Result<Arc<MsgBuf>, RecvTimeoutError> {
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match self.receiver.poll_recv(&mut cx) {
+                Poll::Ready(Some(msg)) => return Ok(msg),
+                Poll::Ready(None) => return Err(RecvTimeoutError::Disconnected),
+                Poll::Pending => {}
+            }
+            match deadline {
+                None => std::thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    std::thread::park_timeout(deadline - now);
+                }
+            }
+        }
+    }
+}
+
+impl From<mpsc::UnboundedReceiver<Arc<MsgBuf>>> for BlockingReceiver {
+    fn from(receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>) -> BlockingReceiver {
+        BlockingReceiver::new(receiver)
+    }
+}
+
+impl Iterator for BlockingReceiver {
+    type Item = Arc<MsgBuf>;
+    fn next(&mut self) -> Option<Arc<MsgBuf>> {
+        self.recv()
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn msg(i: u8) -> Arc<MsgBuf> {
+        Arc::new(MsgBuf(vec![i]))
+    }
+
+    #[test]
+    fn test_blocking_receiver() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut receiver = BlockingReceiver::new(receiver);
+        assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Empty);
+        assert_eq!(
+            receiver
+                .recv_timeout(Duration::from_millis(10))
+                .unwrap_err(),
+            RecvTimeoutError::Timeout
+        );
+
+        // A message sent from another thread wakes the parked receiver.
+        let sending = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            sender.send(msg(1)).unwrap();
+            sender.send(msg(2)).unwrap();
+        });
+        let received = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received.0, vec![1]);
+        sending.join().unwrap();
+
+        assert_eq!(receiver.try_recv().unwrap().0, vec![2]);
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)).unwrap_err(),
+            RecvTimeoutError::Disconnected
+        );
+        assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Disconnected);
+        assert!(receiver.recv().is_none());
+    }
+}
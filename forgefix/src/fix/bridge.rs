@@ -0,0 +1,278 @@
+//! Bridge an engine to bounded tokio channels
+//!
+//! An engine delivers incoming application messages on an unbounded channel. For applications
+//! built around bounded [`mpsc`] or [`broadcast`] channels, [`bridge_to_mpsc`] and
+//! [`bridge_to_broadcast`] spawn a task that moves each message into a channel of a fixed
+//! capacity. When an [`mpsc`] channel is full, the [`DropPolicy`] decides whether the task waits
+//! for space or drops the message. A [`broadcast`] channel never waits: receivers that fall
+//! behind by more than its capacity miss the oldest messages, and see a `Lagged` error.
+//!
+//! [`fan_out`] hands every message to several consumers without dropping any, such as to
+//! record the `ExecutionReport<8>`s of a session for risk while the trading application consumes
+//! them too. Each consumer has an unbounded channel of its own, so a slow consumer does not hold
+//! up the others.
+//!
+//! In the other direction, [`bridge_from_mpsc`] spawns a task that sends every [`MessageBuilder`]
+//! received on a bounded [`mpsc`] channel through a [`FixApplicationHandle`], one at a time, so
+//! senders wait while the engine is busy.
+//!
+//! These functions must be called within a tokio runtime.
+//!
+//! ```
+//! use forgefix::{ApplicationError, FixApplicationInitiator, SessionSettings};
+//! use forgefix::fix::bridge::{bridge_from_mpsc, bridge_to_mpsc, DropPolicy};
+//! use forgefix::fix::encode::MessageBuilder;
+//! use forgefix::fix::generated::{MsgType, Tags};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! #    let settings = SessionSettings::builder()
+//! #        .with_sender_comp_id("my_id")
+//! #        .with_target_comp_id("peer_id")
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
+//! #        .build()?;
+//! let (handle, receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate()
+//!     .await?;
+//! handle.start_async().await?;
+//!
+//! let (mut incoming, bridge) = bridge_to_mpsc(receiver, 1024, DropPolicy::DropNewest);
+//! let (outgoing, _) = bridge_from_mpsc(handle.clone(), 1024);
+//!
+//! let order = MessageBuilder::new(&handle.begin_string(), MsgType::ORDER_SINGLE.into())
+//!     .push(Tags::ClOrdID, b"order-1")
+//!     .push(Tags::Symbol, b"AAPL")
+//!     .push(Tags::Side, b"1");
+//! outgoing.send(order).await.expect("bridge ended");
+//!
+//! if let Some(msg) = incoming.recv().await {
+//!     println!("got: {msg}");
+//! }
+//! println!("dropped {} messages", bridge.dropped());
+//! handle.end_async().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::fix::encode::MessageBuilder;
+use crate::fix::mem::MsgBuf;
+use crate::{ApplicationError, FixApplicationHandle};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+/// What a bridge does with a message when its [`mpsc`] channel is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Wait for space in the channel. Messages queue up in the engine's unbounded channel in the
+    /// meantime.
+    #[default]
+    Block,
+    /// Drop the message, and count it in [`Bridge::dropped`].
+    DropNewest,
+}
+
+/// The task moving incoming messages into a bounded channel.
+pub struct Bridge {
+    dropped: Arc<AtomicU64>,
+    task: JoinHandle<()>,
+}
+
+impl Bridge {
+    /// The number of messages dropped because the channel was full, or had no receivers.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Whether the task has ended, because the engine ended or every receiver was dropped.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+/// Move the messages from `receiver` into an [`mpsc`] channel of `capacity` messages.
+///
+/// The task ends once the engine has ended and every message has been moved, or once the returned
+/// receiver is dropped.
+pub fn bridge_to_mpsc(
+    mut receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+    capacity: usize,
+    drop_policy: DropPolicy,
+) -> (mpsc::Receiver<Arc<MsgBuf>>, Bridge) {
+    let (sender, bounded) = mpsc::channel(capacity);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&dropped);
+    let task = tokio::spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            let sent = match drop_policy {
+                DropPolicy::Block => sender.send(msg).await.map_err(|_| ()),
+                DropPolicy::DropNewest => match sender.try_send(msg) {
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                        Ok(())
+                    }
+                    res => res.map_err(|_| ()),
+                },
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+    (bounded, Bridge { dropped, task })
+}
+
+/// Move the messages from `receiver` into a [`broadcast`] channel of `capacity` messages.
+///
+/// Further receivers can be created with [`broadcast::Receiver::resubscribe`]. Messages sent
+/// while there are no receivers are dropped. The task ends once the engine has ended and every
+/// message has been moved.
+pub fn bridge_to_broadcast(
+    mut receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+    capacity: usize,
+) -> (broadcast::Receiver<Arc<MsgBuf>>, Bridge) {
+    let (sender, subscriber) = broadcast::channel(capacity);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&dropped);
+    let task = tokio::spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            if sender.send(msg).is_err() {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+    (subscriber, Bridge { dropped, task })
+}
+
+/// Hand every message from `receiver` to each of `consumers` unbounded channels.
+///
+/// Messages are shared, not copied. A consumer that drops its receiver stops receiving messages,
+/// without affecting the others. The task ends once the engine has ended and every message has
+/// been handed out, or once every receiver is dropped.
+pub fn fan_out(
+    mut receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+    consumers: usize,
+) -> (Vec<mpsc::UnboundedReceiver<Arc<MsgBuf>>>, Bridge) {
+    let (mut senders, receivers): (Vec<_>, Vec<_>) =
+        (0..consumers).map(|_| mpsc::unbounded_channel()).unzip();
+    let dropped = Arc::new(AtomicU64::new(0));
+    let task = tokio::spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            senders.retain(|sender| sender.send(Arc::clone(&msg)).is_ok());
+            if senders.is_empty() {
+                break;
+            }
+        }
+    });
+    (receivers, Bridge { dropped, task })
+}
+
+/// Send every [`MessageBuilder`] received on an [`mpsc`] channel of `capacity` messages through
+/// `handle`.
+///
+/// The task ends once every sender is dropped, or with the error of the first message that could
+/// not be sent.
+pub fn bridge_from_mpsc(
+    handle: FixApplicationHandle,
+    capacity: usize,
+) -> (mpsc::Sender<MessageBuilder>, JoinHandle<Result<(), ApplicationError>>) {
+    let (sender, mut receiver) = mpsc::channel::<MessageBuilder>(capacity);
+    let task = tokio::spawn(async move {
+        while let Some(builder) = receiver.recv().await {
+            handle.send_message_async(builder).await?;
+        }
+        Ok(())
+    });
+    (sender, task)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn msg(i: u8) -> Arc<MsgBuf> {
+        Arc::new(MsgBuf(vec![i]))
+    }
+
+    #[tokio::test]
+    async fn test_bridge_to_mpsc() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        for i in 0..5 {
+            sender.send(msg(i)).unwrap();
+        }
+        drop(sender);
+        let (mut bounded, bridge) = bridge_to_mpsc(receiver, 2, DropPolicy::DropNewest);
+        while !bridge.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(bridge.dropped(), 3);
+        assert_eq!(bounded.recv().await.unwrap().0, vec![0]);
+        assert_eq!(bounded.recv().await.unwrap().0, vec![1]);
+        assert!(bounded.recv().await.is_none());
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        for i in 0..5 {
+            sender.send(msg(i)).unwrap();
+        }
+        drop(sender);
+        let (mut bounded, bridge) = bridge_to_mpsc(receiver, 2, DropPolicy::Block);
+        for i in 0..5 {
+            assert_eq!(bounded.recv().await.unwrap().0, vec![i]);
+        }
+        assert!(bounded.recv().await.is_none());
+        assert_eq!(bridge.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bridge_to_broadcast() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (mut first, bridge) = bridge_to_broadcast(receiver, 2);
+        let mut second = first.resubscribe();
+        for i in 0..3 {
+            sender.send(msg(i)).unwrap();
+        }
+        drop(sender);
+        while !bridge.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        // both receivers lagged behind by one message
+        assert!(matches!(first.recv().await, Err(broadcast::error::RecvError::Lagged(1))));
+        assert_eq!(first.recv().await.unwrap().0, vec![1]);
+        assert!(matches!(second.recv().await, Err(broadcast::error::RecvError::Lagged(1))));
+        assert_eq!(bridge.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fan_out() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (consumers, bridge) = fan_out(receiver, 3);
+        let [mut trading, mut risk, stale]: [_; 3] = consumers.try_into().unwrap();
+        drop(stale);
+        for i in 0..3 {
+            sender.send(msg(i)).unwrap();
+        }
+        drop(sender);
+        for i in 0..3 {
+            let msg = trading.recv().await.unwrap();
+            assert!(Arc::ptr_eq(&msg, &risk.recv().await.unwrap()));
+            assert_eq!(msg.0, vec![i]);
+        }
+        assert!(trading.recv().await.is_none());
+        assert!(risk.recv().await.is_none());
+        assert_eq!(bridge.dropped(), 0);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (consumers, bridge) = fan_out(receiver, 2);
+        drop(consumers);
+        sender.send(msg(0)).unwrap();
+        while !bridge.is_finished() {
+            tokio::task::yield_now().await;
+        }
+    }
+}
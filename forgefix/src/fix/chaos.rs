@@ -0,0 +1,391 @@
+//! Fault injection on the connection to the peer, for chaos testing
+//!
+//! Only available with the `chaos` feature. A [`Chaos`] set with
+//! [`SessionSettingsBuilder::with_chaos`] puts a relay between the engine and its TCP connection,
+//! which passes each FIX message on in both directions, and can:
+//!
+//! * hold every message for a random latency,
+//! * truncate a message, dropping the rest of its bytes,
+//! * deliver a message twice,
+//! * drop the connection, at random or on demand.
+//!
+//! Faults are controlled at runtime through any clone of the [`Chaos`], and apply to every
+//! connection of every engine it was given to, including the connections made when an engine
+//! reconnects. All faults are off until set.
+//!
+//! ```
+//! use forgefix::{ApplicationError, FixApplicationInitiator, SessionSettings};
+//! use forgefix::fix::chaos::Chaos;
+//! use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! let chaos = Chaos::with_seed(42);
+//! let settings = SessionSettings::builder()
+//!     .with_sender_comp_id("my_id")
+//!     .with_target_comp_id("peer_id")
+//! #   .with_store_path(peer.store_path())
+//! #   .with_log_dir(peer.log_dir())
+//! #   .with_socket_addr(peer.addr())
+//!     .with_chaos(chaos.clone())
+//!     .build()?;
+//! let (handle, receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate()
+//!     .await?;
+//! handle.start_async().await?;
+//!
+//! chaos.set_latency(Duration::from_millis(5), Duration::from_millis(50));
+//! chaos.set_duplicate_probability(0.01);
+//! // run the strategy against the degraded link...
+//! chaos.clear();
+//! # handle.end_async().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SessionSettingsBuilder::with_chaos`]: crate::SessionSettingsBuilder::with_chaos
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tracing::Instrument;
+
+// Every FIX message ends with its `CheckSum(10)` field.
+const TRAILER: &[u8] = b"\x0110=";
+
+// Bytes that do not end a message are passed on once this many are buffered.
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// The faults injected on the connections of an engine. See the [module documentation](self).
+#[derive(Clone)]
+pub struct Chaos {
+    shared: Arc<Shared>,
+}
+
+/// The number of faults a [`Chaos`] has injected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    /// Messages passed on, in both directions.
+    pub messages: u64,
+    /// Messages held for a latency.
+    pub delayed: u64,
+    /// Messages truncated.
+    pub truncated: u64,
+    /// Messages delivered twice.
+    pub duplicated: u64,
+    /// Connections dropped.
+    pub disconnects: u64,
+}
+
+struct Shared {
+    faults: Mutex<Faults>,
+    disconnect: watch::Sender<u64>,
+    messages: AtomicU64,
+    delayed: AtomicU64,
+    truncated: AtomicU64,
+    duplicated: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+struct Faults {
+    rng: fastrand::Rng,
+    latency: Option<(Duration, Duration)>,
+    truncate_probability: f64,
+    duplicate_probability: f64,
+    disconnect_probability: f64,
+}
+
+// What to do with one message.
+#[derive(Default)]
+struct Action {
+    delay: Option<Duration>,
+    truncate_at: Option<usize>,
+    duplicate: bool,
+    disconnect: bool,
+}
+
+impl Chaos {
+    /// Create a `Chaos` with every fault off, seeded from the OS.
+    pub fn new() -> Chaos {
+        Chaos::with_rng(fastrand::Rng::new())
+    }
+
+    /// Create a `Chaos` with every fault off, whose random faults follow `seed`.
+    pub fn with_seed(seed: u64) -> Chaos {
+        Chaos::with_rng(fastrand::Rng::with_seed(seed))
+    }
+
+    fn with_rng(rng: fastrand::Rng) -> Chaos {
+        let faults = Faults {
+            rng,
+            latency: None,
+            truncate_probability: 0.0,
+            duplicate_probability: 0.0,
+            disconnect_probability: 0.0,
+        };
+        Chaos {
+            shared: Arc::new(Shared {
+                faults: Mutex::new(faults),
+                disconnect: watch::channel(0).0,
+                messages: AtomicU64::new(0),
+                delayed: AtomicU64::new(0),
+                truncated: AtomicU64::new(0),
+                duplicated: AtomicU64::new(0),
+                disconnects: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Hold every message for a random time between `min` and `max`. Messages stay in order, so
+    /// a message also waits for the messages before it.
+    pub fn set_latency(&self, min: Duration, max: Duration) {
+        self.faults().latency = Some((min, max.max(min)));
+    }
+
+    /// Truncate each message with probability `probability`, between `0.0` and `1.0`. The
+    /// message is cut at a random length, and the rest of it is dropped.
+    pub fn set_truncate_probability(&self, probability: f64) {
+        self.faults().truncate_probability = probability;
+    }
+
+    /// Deliver each message twice with probability `probability`, between `0.0` and `1.0`.
+    pub fn set_duplicate_probability(&self, probability: f64) {
+        self.faults().duplicate_probability = probability;
+    }
+
+    /// Drop the connection before a message with probability `probability`, between `0.0` and
+    /// `1.0`. The message is lost.
+    pub fn set_disconnect_probability(&self, probability: f64) {
+        self.faults().disconnect_probability = probability;
+    }
+
+    /// Turn every fault off. Connections that were dropped stay dropped.
+    pub fn clear(&self) {
+        let mut faults = self.faults();
+        faults.latency = None;
+        faults.truncate_probability = 0.0;
+        faults.duplicate_probability = 0.0;
+        faults.disconnect_probability = 0.0;
+    }
+
+    /// Drop every connection now. Messages still held for a latency are lost.
+    pub fn disconnect(&self) {
+        self.shared
+            .disconnect
+            .send_modify(|generation| *generation += 1);
+    }
+
+    /// The number of faults injected so far.
+    pub fn stats(&self) -> ChaosStats {
+        let shared = &self.shared;
+        ChaosStats {
+            messages: shared.messages.load(Ordering::Relaxed),
+            delayed: shared.delayed.load(Ordering::Relaxed),
+            truncated: shared.truncated.load(Ordering::Relaxed),
+            duplicated: shared.duplicated.load(Ordering::Relaxed),
+            disconnects: shared.disconnects.load(Ordering::Relaxed),
+        }
+    }
+
+    fn faults(&self) -> std::sync::MutexGuard<'_, Faults> {
+        self.shared.faults.lock().unwrap()
+    }
+
+    fn next_action(&self, len: usize) -> Action {
+        let mut faults = self.faults();
+        let Faults {
+            rng,
+            latency,
+            truncate_probability,
+            duplicate_probability,
+            disconnect_probability,
+        } = &mut *faults;
+        let mut action = Action::default();
+        if rng.f64() < *disconnect_probability {
+            action.disconnect = true;
+            return action;
+        }
+        if let Some((min, max)) = *latency {
+            let nanos = rng.u64(min.as_nanos() as u64..=max.as_nanos() as u64);
+            action.delay = Some(Duration::from_nanos(nanos));
+        }
+        if len > 1 && rng.f64() < *truncate_probability {
+            action.truncate_at = Some(rng.usize(1..len));
+        } else if rng.f64() < *duplicate_probability {
+            action.duplicate = true;
+        }
+        action
+    }
+
+    fn count(&self, counter: fn(&Shared) -> &AtomicU64) {
+        counter(&self.shared).fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Default for Chaos {
+    fn default() -> Chaos {
+        Chaos::new()
+    }
+}
+
+impl std::fmt::Debug for Chaos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Chaos")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+// Put a relay injecting the faults of `chaos` between the engine and `stream`, and return the
+// end of the relay the engine uses instead of `stream`.
+pub(super) async fn wrap(stream: TcpStream, chaos: &Chaos) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+    let (engine_end, relay_end) = tokio::join!(
+        TcpStream::connect(listener.local_addr()?),
+        listener.accept()
+    );
+    let (engine_end, (relay_end, _)) = (engine_end?, relay_end?);
+    engine_end.set_nodelay(true)?;
+    relay_end.set_nodelay(true)?;
+    let chaos = chaos.clone();
+    tokio::spawn(relay(stream, relay_end, chaos).in_current_span());
+    Ok(engine_end)
+}
+
+async fn relay(peer: TcpStream, engine: TcpStream, chaos: Chaos) {
+    let mut disconnect = chaos.shared.disconnect.subscribe();
+    let (peer_read, peer_write) = peer.into_split();
+    let (engine_read, engine_write) = engine.into_split();
+    tokio::select! {
+        _ = pass_on(peer_read, engine_write, &chaos) => {}
+        _ = pass_on(engine_read, peer_write, &chaos) => {}
+        _ = disconnect.changed() => {
+            tracing::info!("chaos: dropping the connection");
+            chaos.count(|shared| &shared.disconnects);
+        }
+    }
+    // Both connections are closed once their halves are dropped here.
+}
+
+// Pass every message read from `from` on to `to`, until either side is closed, or a fault drops
+// the connection.
+async fn pass_on(mut from: OwnedReadHalf, mut to: OwnedWriteHalf, chaos: &Chaos) -> io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = vec![0; 8192];
+    loop {
+        let n = from.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        while let Some(len) = frame_len(&buf) {
+            let frame: Vec<u8> = buf.drain(..len).collect();
+            if !pass_on_frame(&frame, &mut to, chaos).await? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Returns false if the connection should be dropped.
+async fn pass_on_frame(frame: &[u8], to: &mut OwnedWriteHalf, chaos: &Chaos) -> io::Result<bool> {
+    let action = chaos.next_action(frame.len());
+    if action.disconnect {
+        tracing::info!("chaos: dropping the connection");
+        chaos.count(|shared| &shared.disconnects);
+        return Ok(false);
+    }
+    chaos.count(|shared| &shared.messages);
+    if let Some(delay) = action.delay {
+        chaos.count(|shared| &shared.delayed);
+        tokio::time::sleep(delay).await;
+    }
+    match action.truncate_at {
+        Some(len) => {
+            to.write_all(&frame[..len]).await?;
+            chaos.count(|shared| &shared.truncated);
+        }
+        None => to.write_all(frame).await?,
+    }
+    if action.duplicate {
+        to.write_all(frame).await?;
+        chaos.count(|shared| &shared.duplicated);
+    }
+    Ok(true)
+}
+
+// The length of the first complete message in `buf`, which ends after the SOH of its
+// `CheckSum(10)` field. Bytes that never end a message are passed on in large chunks.
+fn frame_len(buf: &[u8]) -> Option<usize> {
+    let trailer = buf.windows(TRAILER.len()).position(|w| w == TRAILER);
+    match trailer {
+        Some(start) => {
+            let value_start = start + TRAILER.len();
+            let end = buf[value_start..].iter().position(|b| *b == b'\x01')?;
+            Some(value_start + end + 1)
+        }
+        None if buf.len() >= MAX_FRAME_LEN => Some(buf.len()),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frame_len() {
+        let msg = b"8=FIX.4.2\x019=5\x0135=0\x01100=XNAS\x0110=123\x01";
+        assert_eq!(frame_len(msg), Some(msg.len()));
+        assert_eq!(frame_len(&[&msg[..], b"8=FIX"].concat()), Some(msg.len()));
+        assert_eq!(frame_len(&msg[..msg.len() - 1]), None);
+        assert_eq!(frame_len(b"8=FIX.4.2\x019=5\x01100=XNAS\x01"), None);
+    }
+
+    #[tokio::test]
+    async fn test_relay_faults() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let (peer, accepted) = tokio::join!(
+            TcpStream::connect(listener.local_addr().unwrap()),
+            listener.accept()
+        );
+        let mut peer = peer.unwrap();
+        let chaos = Chaos::with_seed(7);
+        let mut engine = wrap(accepted.unwrap().0, &chaos).await.unwrap();
+        let msg = b"8=FIX.4.2\x019=5\x0135=0\x0110=000\x01";
+        let mut buf = vec![0; msg.len() * 2];
+
+        peer.write_all(msg).await.unwrap();
+        engine.read_exact(&mut buf[..msg.len()]).await.unwrap();
+        assert_eq!(&buf[..msg.len()], msg);
+
+        chaos.set_duplicate_probability(1.0);
+        engine.write_all(msg).await.unwrap();
+        peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, [&msg[..], &msg[..]].concat());
+
+        chaos.clear();
+        chaos.set_truncate_probability(1.0);
+        peer.write_all(msg).await.unwrap();
+        while chaos.stats().truncated == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        chaos.disconnect();
+        buf.clear();
+        engine.read_to_end(&mut buf).await.unwrap();
+        assert!(!buf.is_empty() && msg.starts_with(&buf));
+        assert!(buf.len() < msg.len());
+
+        let stats = chaos.stats();
+        assert_eq!(stats.messages, 3);
+        assert_eq!(stats.duplicated, 1);
+        assert_eq!(stats.disconnects, 1);
+    }
+}
@@ -102,6 +102,7 @@ fn checksum_matches(msg: &[u8], checksum: i32) -> bool {
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_checksum_matches() {
@@ -133,4 +134,40 @@ mod test {
             );
         }
     }
+
+    fn with_checksum(body: &[u8]) -> Vec<u8> {
+        let mut msg = body.to_vec();
+        msg.extend_from_slice(format!("10={:03}\x01", calc_checksum(body)).as_bytes());
+        msg
+    }
+
+    proptest! {
+        #[test]
+        fn prop_checksum_arbitrary_bytes(msg in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = checksum_is_valid(&msg);
+        }
+
+        #[test]
+        fn prop_checksum_valid(body in prop::collection::vec(any::<u8>(), 0..256)) {
+            prop_assert!(checksum_is_valid(&with_checksum(&body)));
+        }
+
+        #[test]
+        fn prop_checksum_corrupted(
+            body in prop::collection::vec(any::<u8>(), 1..256),
+            at in any::<prop::sample::Index>(),
+            delta in 1u8..=255,
+        ) {
+            let mut msg = with_checksum(&body);
+            let i = at.index(body.len());
+            msg[i] = msg[i].wrapping_add(delta);
+            prop_assert!(!checksum_is_valid(&msg));
+        }
+
+        #[test]
+        fn prop_checksum_truncated(body in prop::collection::vec(any::<u8>(), 0..64), cut in 1usize..7) {
+            let msg = with_checksum(&body);
+            prop_assert!(!checksum_is_valid(&msg[..msg.len() - cut]));
+        }
+    }
 }
@@ -0,0 +1,148 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use anyhow::{anyhow, bail, Result};
+
+use std::fmt::Display;
+
+const NONCE_LEN: usize = 12;
+
+// The kinds of blobs, which are told apart in their associated data.
+const OUTGOING: u8 = b'O';
+const INCOMING: u8 = b'I';
+const CL_ORD_ID: u8 = b'C';
+const SEQUENCES: u8 = b'S';
+
+// Encrypts message blobs before they are written to the store. Each blob is stored as a random
+// nonce followed by the AES-256-GCM ciphertext. The epoch and MsgSeqNum of the message are used as
+// associated data, so a blob cannot be moved to another row without failing to decrypt. Incoming
+// messages are told apart from outgoing ones with the same MsgSeqNum in the associated data.
+//
+// The ClOrdIDs kept for duplicate detection are encrypted the same way, and the sequence numbers
+// of an epoch are sealed, so that they cannot be changed without the key.
+#[derive(Clone)]
+pub(super) struct StoreCipher(Aes256Gcm);
+
+impl StoreCipher {
+    pub(super) fn new(key: &[u8; 32]) -> StoreCipher {
+        StoreCipher(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)))
+    }
+
+    pub(super) fn encrypt(&self, epoch: &str, msg_seq_num: u32, msg: &[u8]) -> Result<Vec<u8>> {
+        let what = format!("message {msg_seq_num}");
+        self.seal(&associated_data(epoch, OUTGOING, msg_seq_num), &what, msg)
+    }
+
+    pub(super) fn decrypt(&self, epoch: &str, msg_seq_num: u32, blob: &[u8]) -> Result<Vec<u8>> {
+        let what = format!("message {msg_seq_num}");
+        self.open(&associated_data(epoch, OUTGOING, msg_seq_num), &what, blob)
+    }
+
+    pub(super) fn encrypt_incoming(&self, epoch: &str, msg_seq_num: u32, msg: &[u8]) -> Result<Vec<u8>> {
+        let what = format!("message {msg_seq_num}");
+        self.seal(&associated_data(epoch, INCOMING, msg_seq_num), &what, msg)
+    }
+
+    pub(super) fn decrypt_incoming(&self, epoch: &str, msg_seq_num: u32, blob: &[u8]) -> Result<Vec<u8>> {
+        let what = format!("message {msg_seq_num}");
+        self.open(&associated_data(epoch, INCOMING, msg_seq_num), &what, blob)
+    }
+
+    pub(super) fn encrypt_cl_ord_id(&self, epoch: &str, msg_seq_num: u32, cl_ord_id: &[u8]) -> Result<Vec<u8>> {
+        let what = format!("ClOrdID of message {msg_seq_num}");
+        self.seal(&associated_data(epoch, CL_ORD_ID, msg_seq_num), &what, cl_ord_id)
+    }
+
+    pub(super) fn decrypt_cl_ord_id(&self, epoch: &str, msg_seq_num: u32, blob: &[u8]) -> Result<Vec<u8>> {
+        let what = format!("ClOrdID of message {msg_seq_num}");
+        self.open(&associated_data(epoch, CL_ORD_ID, msg_seq_num), &what, blob)
+    }
+
+    // Seal the `(next_incoming, next_outgoing)` sequence numbers of `epoch`.
+    pub(super) fn seal_sequences(&self, epoch: &str, next_incoming: u32, next_outgoing: u32) -> Result<Vec<u8>> {
+        let mut numbers = next_incoming.to_be_bytes().to_vec();
+        numbers.extend(next_outgoing.to_be_bytes());
+        self.seal(&associated_data(epoch, SEQUENCES, 0), "sequence numbers", &numbers)
+    }
+
+    pub(super) fn open_sequences(&self, epoch: &str, seal: &[u8]) -> Result<(u32, u32)> {
+        let numbers = self.open(&associated_data(epoch, SEQUENCES, 0), "sequence numbers", seal)?;
+        match numbers[..] {
+            [a, b, c, d, e, f, g, h] => Ok((
+                u32::from_be_bytes([a, b, c, d]),
+                u32::from_be_bytes([e, f, g, h]),
+            )),
+            _ => bail!("stored sequence numbers are malformed"),
+        }
+    }
+
+    fn seal(&self, aad: &[u8], what: impl Display, msg: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, Payload { msg, aad })
+            .map_err(|_| anyhow!("failed to encrypt {what}"))?;
+        let mut blob = nonce.to_vec();
+        blob.extend(ciphertext);
+        Ok(blob)
+    }
+
+    fn open(&self, aad: &[u8], what: impl Display, blob: &[u8]) -> Result<Vec<u8>> {
+        if blob.len() < NONCE_LEN {
+            bail!("stored {what} is too short to be encrypted");
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| anyhow!("failed to decrypt stored {what}"))
+    }
+}
+
+// The epoch is prefixed with its length, and followed by the kind of blob and the MsgSeqNum at a
+// fixed width, so that no two blobs share their associated data whatever the epoch holds.
+fn associated_data(epoch: &str, kind: u8, msg_seq_num: u32) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(4 + epoch.len() + 1 + 4);
+    aad.extend((epoch.len() as u32).to_be_bytes());
+    aad.extend(epoch.as_bytes());
+    aad.push(kind);
+    aad.extend(msg_seq_num.to_be_bytes());
+    aad
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_store_cipher() {
+        let cipher = StoreCipher::new(&[7; 32]);
+        let msg = b"8=FIX.4.2\x019=5\x0135=D\x0110=000\x01";
+
+        let blob = cipher.encrypt("epoch", 3, msg).unwrap();
+        assert_ne!(&blob[NONCE_LEN..], &msg[..]);
+        assert_eq!(cipher.decrypt("epoch", 3, &blob).unwrap(), msg);
+
+        assert!(cipher.decrypt("epoch", 4, &blob).is_err());
+        assert!(cipher.decrypt("other", 3, &blob).is_err());
+        assert!(StoreCipher::new(&[8; 32]).decrypt("epoch", 3, &blob).is_err());
+        assert!(cipher.decrypt("epoch", 3, &blob[..4]).is_err());
+
+        let blob = cipher.encrypt_incoming("epoch", 3, msg).unwrap();
+        assert_eq!(cipher.decrypt_incoming("epoch", 3, &blob).unwrap(), msg);
+        assert!(cipher.decrypt("epoch", 3, &blob).is_err());
+
+        let blob = cipher.encrypt_cl_ord_id("epoch", 3, b"order-1").unwrap();
+        assert_eq!(cipher.decrypt_cl_ord_id("epoch", 3, &blob).unwrap(), b"order-1");
+        assert!(cipher.decrypt("epoch", 3, &blob).is_err());
+
+        let seal = cipher.seal_sequences("epoch", 5, 9).unwrap();
+        assert_eq!(cipher.open_sequences("epoch", &seal).unwrap(), (5, 9));
+        assert!(cipher.open_sequences("other", &seal).is_err());
+
+        // an epoch that ends like the kind of another blob does not open it
+        let blob = cipher.encrypt("epoch:in", 3, msg).unwrap();
+        assert!(cipher.decrypt_incoming("epoch", 3, &blob).is_err());
+        let blob = cipher.encrypt("epoch:order", 3, b"order-1").unwrap();
+        assert!(cipher.decrypt_cl_ord_id("epoch", 3, &blob).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! An exact decimal type for prices and quantities.
+//!
+//! Floats cannot represent most decimal values exactly, so a price such as `2.31` read into an
+//! [`f64`] and written back can come out as `2.3099999`. A [`FixDecimal`] keeps the digits of a
+//! value as they were sent, so that values round-trip exactly between messages.
+//!
+//! ## Example
+//! ```rust
+//! use forgefix::fix::decimal::FixDecimal;
+//! use forgefix::fix::encode::MessageBuilder;
+//! use forgefix::fix::generated::{MsgType, Tags};
+//!
+//! let price: FixDecimal = "2.31".parse().unwrap();
+//! assert_eq!(price, FixDecimal::new(231, 2));
+//! assert_eq!(price.to_string(), "2.31");
+//!
+//! let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+//!     .push_fix_decimal(Tags::Price, price);
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use thiserror::Error;
+
+// the largest scale whose power of ten fits in an `i64`
+const MAX_SCALE: u32 = 18;
+
+/// A decimal number: an integer `mantissa` divided by `10^scale`.
+///
+/// Two decimals are equal when their values are, so `1.5` and `1.50` are equal, while each keeps
+/// its own number of digits when written.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FixDecimal {
+    mantissa: i64,
+    scale: u32,
+}
+
+/// The error returned when a value is not a decimal number, or has more digits than a
+/// [`FixDecimal`] holds.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("invalid decimal `{0}`")]
+pub struct ParseDecimalError(String);
+
+impl FixDecimal {
+    /// Creates the decimal `mantissa / 10^scale`, such as `FixDecimal::new(231, 2)` for `2.31`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is more than 18.
+    pub fn new(mantissa: i64, scale: u32) -> FixDecimal {
+        assert!(scale <= MAX_SCALE, "scale {scale} is more than {MAX_SCALE}");
+        FixDecimal { mantissa, scale }
+    }
+
+    /// Rounds `value` to `scale` digits after the decimal point. Returns `None` if `value` is not
+    /// finite, does not fit, or `scale` is more than 18.
+    pub fn from_f64(value: f64, scale: u32) -> Option<FixDecimal> {
+        if scale > MAX_SCALE {
+            return None;
+        }
+        // formatting rounds from the exact value of the float, which scaling it first would not
+        format!("{value:.*}", scale as usize).parse().ok()
+    }
+
+    /// The digits of the decimal, without the decimal point.
+    pub fn mantissa(&self) -> i64 {
+        self.mantissa
+    }
+
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// The nearest [`f64`] to the decimal.
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The same value without trailing zeros after the decimal point, such as `1.5` for `1.50`.
+    pub fn normalize(&self) -> FixDecimal {
+        let mut normalized = *self;
+        while normalized.scale > 0 && normalized.mantissa % 10 == 0 {
+            normalized.mantissa /= 10;
+            normalized.scale -= 1;
+        }
+        normalized
+    }
+
+    // The mantissa of the value at `scale`, which is at least the scale of the decimal.
+    fn mantissa_at(&self, scale: u32) -> i128 {
+        self.mantissa as i128 * 10i128.pow(scale - self.scale)
+    }
+}
+
+impl From<i64> for FixDecimal {
+    fn from(value: i64) -> Self {
+        FixDecimal::new(value, 0)
+    }
+}
+
+impl From<u32> for FixDecimal {
+    fn from(value: u32) -> Self {
+        FixDecimal::new(value.into(), 0)
+    }
+}
+
+impl PartialEq for FixDecimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FixDecimal {}
+
+impl Ord for FixDecimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.mantissa_at(scale).cmp(&other.mantissa_at(scale))
+    }
+}
+
+impl PartialOrd for FixDecimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for FixDecimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalize();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl fmt::Display for FixDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs();
+        if self.scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+        let divisor = 10u64.pow(self.scale);
+        write!(
+            f,
+            "{sign}{}.{:0width$}",
+            digits / divisor,
+            digits % divisor,
+            width = self.scale as usize
+        )
+    }
+}
+
+impl FromStr for FixDecimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseDecimalError(s.to_string());
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s),
+        };
+        let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if (int.is_empty() && frac.is_empty())
+            || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+            || frac.len() > MAX_SCALE as usize
+        {
+            return Err(err());
+        }
+        // accumulated with its sign, as i64::MIN has no positive counterpart
+        let sign = if negative { -1 } else { 1 };
+        let mut mantissa: i64 = 0;
+        for b in int.bytes().chain(frac.bytes()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(sign * (b - b'0') as i64))
+                .ok_or_else(err)?;
+        }
+        Ok(FixDecimal::new(mantissa, frac.len() as u32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fix_decimal() {
+        for s in ["2.31", "-0.05", "100", "0.000001", "12.50", "9223372036854775807", "-9223372036854775808", "-922337203685477.5808"] {
+            assert_eq!(s.parse::<FixDecimal>().unwrap().to_string(), s);
+        }
+        assert_eq!(".5".parse::<FixDecimal>().unwrap().to_string(), "0.5");
+        for s in ["", "-", ".", "1.2.3", "1e5", "+1", "abc", "9223372036854775808", "-9223372036854775809"] {
+            assert!(s.parse::<FixDecimal>().is_err(), "{s}");
+        }
+
+        let price = FixDecimal::new(231, 2);
+        assert_eq!(price, "2.310".parse().unwrap());
+        assert!(price < FixDecimal::new(2311, 3));
+        assert_eq!(FixDecimal::new(-1230, 3).normalize().to_string(), "-1.23");
+        assert_eq!(FixDecimal::from_f64(2.31, 2), Some(price));
+        assert_eq!(FixDecimal::from_f64(10.425, 2).unwrap().to_string(), "10.43");
+        assert_eq!(FixDecimal::from_f64(f64::NAN, 2), None);
+        assert_eq!(price.to_f64(), 2.31);
+    }
+}
@@ -37,6 +37,12 @@
 //!
 //! The [`Tags`] enum and [`parse_field`] function are tools to support parsing of tags and values. 
 //!
+//! Messages with repeating groups, such as market data or allocations, can be walked with
+//! [`grouped_fields`], which tells which entry of which group each field belongs to. 
+//!
+//! Where convenience matters more than speed, such as in tests or when debugging,
+//! [`FixMessage::parse`] keeps every field of a message for lookups by tag. 
+//!
 //! # Errors
 //!
 //! If a message is malformed or contains invalid data, then decoding the message will likely cause an error. 
@@ -58,7 +64,7 @@
 //!
 //! # Example
 //!
-//! ```no_run
+//! ```
 //! use anyhow::{Error, bail, Result}; 
 //! use forgefix::fix::decode::{ParserCallback, parse_field, parse, MessageParseError}; 
 //! use forgefix::fix::generated::{Tags, MsgType, ExecType, OrdStatus};
@@ -124,39 +130,52 @@
 //! }
 //!
 //! # use forgefix::{SessionSettings, FixApplicationInitiator}; 
+//! # use forgefix::fix::encode::MessageBuilder;
 //! #[tokio::main]
 //! async fn main() -> Result<()> {
 //! 
 //!     // create SessionSettings...
 //!
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
 //! #    let settings = SessionSettings::builder()
 //! #        .with_sender_comp_id("my_id")
 //! #        .with_target_comp_id("peer_id")
-//! #        .with_store_path("./store".into())
-//! #        .with_log_dir("./log".into())
-//! #        .with_socket_addr("127.0.0.1:0".parse().unwrap())
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
 //! #        .build()?; 
 //!     let (handle, mut receiver) = FixApplicationInitiator::build(settings)?
 //!         .initiate()
 //!         .await?;
 //!
-//!     tokio::spawn(async move {
+//!     let parsed = tokio::spawn(async move {
 //!         while let Some(msg) = receiver.recv().await {
 //!             let mut callback: ExecutionReportParser = Default::default(); 
 //!             match parse(&msg[..], &mut callback) {
-//!                 Ok(()) => println!("Received execution report: {:?}", callback),
+//!                 Ok(()) => {
+//!                     println!("Received execution report: {:?}", callback);
+//!                     return Some(callback.order_status);
+//!                 }
 //!                 Err(e) => println!("Error parsing execution report: {:?}", e),
 //!             }
 //!         }
+//!         None
 //!     }); 
 //!
 //!     // run application ...
+//! #    handle.start_async().await?;
+//! #    let order = MessageBuilder::new(&handle.begin_string(), MsgType::ORDER_SINGLE.into())
+//! #        .push(Tags::ClOrdID, b"order-1");
+//! #    handle.send_message_async(order).await?;
+//! #    assert_eq!(parsed.await?, Some(OrdStatus::NEW));
+//! #    handle.end_async().await?;
 //!     # Ok(())
 //! }
 //! ```
 
 use crate::fix::generated::{get_data_ref, Tags};
 use crate::fix::{GarbledMessageType, SessionError};
+use crate::fix::mem::MsgBuf;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use lazy_static::lazy_static;
 use std::collections::{BTreeSet, HashMap};
@@ -164,7 +183,8 @@ use std::result;
 use thiserror::Error;
 
 const TIME_FORMAT_SHORT: &str = "%Y%m%d-%H:%M:%S";
-const TIME_FORMAT_LONG: &str = "%Y%m%d-%H:%M:%S%.3f";
+// any fraction of a second, from milliseconds to nanoseconds
+const TIME_FORMAT_LONG: &str = "%Y%m%d-%H:%M:%S%.f";
 
 lazy_static! {
     static ref HEADER_FIELDS: BTreeSet<u32> = [
@@ -177,6 +197,49 @@ lazy_static! {
     static ref TRAILER_FIELDS: BTreeSet<u32> = [93, 89, 10].iter().cloned().collect();
 }
 
+/// The tags that are parsed as header or trailer fields, in addition to those of the
+/// [FIX dictionary]. 
+///
+/// Some peers send custom tags in the header, or put header fields where the dictionary does not
+/// expect them. Passing `FieldSections` to [`parse_with_sections`] routes such tags to
+/// [`ParserCallback::header`] or [`ParserCallback::trailer`] instead of
+/// [`ParserCallback::body`]. 
+///
+/// [FIX dictionary]: https://btobits.com/fixopaedia/fixdic42/index.html
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldSections {
+    header_tags: BTreeSet<u32>,
+    trailer_tags: BTreeSet<u32>,
+}
+
+impl FieldSections {
+    pub fn new() -> FieldSections {
+        Default::default()
+    }
+
+    /// Parse `tag` as a header field. 
+    pub fn with_header_tag(mut self, tag: impl Into<u32>) -> Self {
+        self.header_tags.insert(tag.into());
+        self
+    }
+
+    /// Parse `tag` as a trailer field. 
+    pub fn with_trailer_tag(mut self, tag: impl Into<u32>) -> Self {
+        self.trailer_tags.insert(tag.into());
+        self
+    }
+
+    /// Whether `tag` is parsed as a header field. 
+    pub fn is_header(&self, tag: u32) -> bool {
+        HEADER_FIELDS.contains(&tag) || self.header_tags.contains(&tag)
+    }
+
+    /// Whether `tag` is parsed as a trailer field. 
+    pub fn is_trailer(&self, tag: u32) -> bool {
+        TRAILER_FIELDS.contains(&tag) || self.trailer_tags.contains(&tag)
+    }
+}
+
 /// Errors that can occur while splitting a message into fields.
 #[derive(Error, Debug)]
 pub enum MessageParseError {
@@ -223,6 +286,22 @@ pub enum DecodeError {
     /// The attempted [`Tags`] and [`u8`] are contained in the error 
     #[error("int {1:?} does not match a known variant of {0:?}")]
     UnknownInt(Tags, u8),
+    /// A field required by the message was not present
+    ///
+    /// The missing [`Tags`] is contained in the error
+    #[error("required tag {0:?} is missing")]
+    MissingTag(Tags),
+    /// The message was not of the expected `MsgType(35)`
+    ///
+    /// The [`Vec<u8>`] contains the `MsgType(35)` of the message
+    #[error("unexpected MsgType {0:?}")]
+    UnexpectedMsgType(Vec<u8>),
+    /// A repeating group did not have as many entries as its count field, or an entry did not
+    /// start with the delimiter field of the group
+    ///
+    /// The [`u32`] is the count tag of the group, such as `NoAllocs(78)`
+    #[error("repeating group {0:?} is malformed")]
+    BadGroup(u32),
 }
     
 #[derive(PartialEq, Eq, Debug)]
@@ -233,7 +312,7 @@ enum FieldState {
     InField,
     Error,
 }
-struct FieldIter<'a> {
+pub(super) struct FieldIter<'a> {
     inner: std::iter::Enumerate<std::slice::Iter<'a, u8>>,
     msg: &'a [u8],
     state: FieldState,
@@ -243,7 +322,7 @@ struct FieldIter<'a> {
 }
 
 impl<'a> FieldIter<'a> {
-    fn new(msg: &'a [u8]) -> Self {
+    pub(super) fn new(msg: &'a [u8]) -> Self {
         FieldIter {
             inner: msg.iter().enumerate(), 
             msg,
@@ -255,8 +334,8 @@ impl<'a> FieldIter<'a> {
     }
 
     fn skip_ahead(&mut self, n: u32) {
-        for _ in 0..n {
-            _ = self.inner.next();
+        if n > 0 {
+            _ = self.inner.nth(n as usize - 1);
         }
     }
 }
@@ -271,16 +350,24 @@ impl<'a> Iterator for FieldIter<'a> {
                 (&FieldState::Start, '0'..='9') | (&FieldState::InTag, '0'..='9') => {
                     if self.state == FieldState::Start {
                         self.tag_accum = 0;
-                    } else {
-                        self.tag_accum *= 10; 
                     }
-                    self.tag_accum += *b as u32 - '0' as u32; 
+                    match self
+                        .tag_accum
+                        .checked_mul(10)
+                        .and_then(|tag| tag.checked_add(*b as u32 - '0' as u32))
+                    {
+                        Some(tag) => self.tag_accum = tag,
+                        None => {
+                            self.state = FieldState::Error;
+                            return Some(Err(MessageParseError::UnexpectedByte(i, self.msg.to_vec())));
+                        }
+                    }
                     self.state = FieldState::InTag;
                 }
                 (&FieldState::InTag, '=') => {
                     self.field_start = i + 1;
                     if let Some(len) = self.field_lengths.get(&self.tag_accum) {
-                        self.skip_ahead(len - 1);
+                        self.skip_ahead(len.saturating_sub(1));
                     }
                     self.state = FieldState::SeenEquals; 
                 }
@@ -405,6 +492,18 @@ pub fn parse<'a, T: ParserCallback<'a>>(
     msg: &'a [u8],
     callbacks: &mut T,
 ) -> result::Result<(), T::Err> 
+{
+    parse_with_sections(msg, callbacks, &FieldSections::default())
+}
+
+/// Parse a [`MsgBuf`] like [`parse`], but with additional header and trailer tags. 
+///
+/// [`MsgBuf`]: crate::fix::mem::MsgBuf
+pub fn parse_with_sections<'a, T: ParserCallback<'a>>(
+    msg: &'a [u8],
+    callbacks: &mut T,
+    sections: &FieldSections,
+) -> result::Result<(), T::Err> 
 {
     let field_iter = FieldIter::new(msg); 
     for res in field_iter {
@@ -413,9 +512,9 @@ pub fn parse<'a, T: ParserCallback<'a>>(
             Err(e) => return callbacks.parse_error(e),
         };
         let cont =
-            if HEADER_FIELDS.contains(&tag) {
+            if sections.is_header(tag) {
                 callbacks.header(tag, val)?
-            } else if TRAILER_FIELDS.contains(&tag) {
+            } else if sections.is_trailer(tag) {
                 callbacks.trailer(tag, val)?
             } else {
                 callbacks.body(tag, val)?
@@ -427,6 +526,187 @@ pub fn parse<'a, T: ParserCallback<'a>>(
     Ok(())
 }
 
+/// The layout of a repeating group, used by [`grouped_fields`] to find its entries. 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupSpec {
+    count_tag: u32,
+    delimiter_tag: u32,
+    member_tags: Vec<u32>,
+    nested: Vec<GroupSpec>,
+}
+
+impl GroupSpec {
+    /// A group counted by `count_tag`, such as `NoMDEntries(268)`, whose entries start with
+    /// `delimiter_tag`, such as `MDEntryType(269)`, and may contain any of `member_tags`. 
+    pub fn new<T: Into<u32>>(
+        count_tag: impl Into<u32>,
+        delimiter_tag: impl Into<u32>,
+        member_tags: impl IntoIterator<Item = T>,
+    ) -> GroupSpec {
+        GroupSpec {
+            count_tag: count_tag.into(),
+            delimiter_tag: delimiter_tag.into(),
+            member_tags: member_tags.into_iter().map(Into::into).collect(),
+            nested: Vec::new(),
+        }
+    }
+
+    /// Add a group nested in the entries of this group. 
+    pub fn with_nested(mut self, nested: GroupSpec) -> GroupSpec {
+        self.nested.push(nested);
+        self
+    }
+
+    fn contains(&self, tag: u32) -> bool {
+        tag == self.delimiter_tag
+            || self.member_tags.contains(&tag)
+            || self.nested.iter().any(|nested| nested.count_tag == tag)
+    }
+}
+
+/// The entry of a repeating group a field belongs to. 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupPosition {
+    /// The count tag of the innermost group of the field. 
+    pub count_tag: u32,
+    /// The index of the entry in the group, from 0. 
+    pub entry: usize,
+    /// How deep the group is nested, 0 for a group that is not nested in another. 
+    pub depth: usize,
+}
+
+/// A field yielded by [`GroupedFields`]. 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupedField<'a> {
+    pub tag: u32,
+    pub value: &'a [u8],
+    /// The entry the field belongs to, or `None` if it is not in a repeating group. The count
+    /// field of a group belongs to the entry the group is nested in, if any. 
+    pub group: Option<GroupPosition>,
+}
+
+struct OpenGroup<'s> {
+    spec: &'s GroupSpec,
+    count: usize,
+    entries: usize,
+}
+
+/// An iterator over the fields of a message that knows which repeating group entry each field
+/// belongs to. Created with [`grouped_fields`]. 
+pub struct GroupedFields<'a, 's> {
+    fields: FieldIter<'a>,
+    specs: &'s [GroupSpec],
+    open: Vec<OpenGroup<'s>>,
+    failed: bool,
+}
+
+/// Iterate over the fields of `msg` in order, with the position of each field in the repeating
+/// groups described by `specs`. 
+///
+/// An error is yielded, and the iteration ends, if the message cannot be split into fields, or if
+/// a group has more or fewer entries than its count field. 
+///
+/// ```
+/// use forgefix::fix::decode::{grouped_fields, GroupSpec};
+/// use forgefix::fix::generated::Tags;
+///
+/// let msg = b"8=FIX.4.2\x019=5\x0135=W\x0155=AAPL\x01268=2\x01\
+///             269=0\x01270=1.00\x01271=100\x01269=1\x01270=1.01\x0110=000\x01";
+/// let spec = GroupSpec::new(Tags::NoMDEntries, Tags::MDEntryType, [Tags::MDEntryPx, Tags::MDEntrySize]);
+/// let prices: Vec<(usize, &[u8])> = grouped_fields(msg, &[spec])
+///     .filter_map(Result::ok)
+///     .filter(|field| field.tag == u32::from(Tags::MDEntryPx))
+///     .map(|field| (field.group.unwrap().entry, field.value))
+///     .collect();
+/// assert_eq!(prices, vec![(0, &b"1.00"[..]), (1, &b"1.01"[..])]);
+/// ```
+pub fn grouped_fields<'a, 's>(msg: &'a [u8], specs: &'s [GroupSpec]) -> GroupedFields<'a, 's> {
+    GroupedFields {
+        fields: FieldIter::new(msg),
+        specs,
+        open: Vec::new(),
+        failed: false,
+    }
+}
+
+impl<'a, 's> GroupedFields<'a, 's> {
+    // Find the entry of `tag`, closing the groups that it does not belong to.
+    fn position(&mut self, tag: u32) -> Result<Option<GroupPosition>, DecodeError> {
+        while let Some(group) = self.open.last_mut() {
+            if tag == group.spec.delimiter_tag {
+                group.entries += 1;
+                if group.entries > group.count {
+                    return Err(DecodeError::BadGroup(group.spec.count_tag));
+                }
+            } else if group.spec.contains(tag) {
+                if group.entries == 0 {
+                    return Err(DecodeError::BadGroup(group.spec.count_tag));
+                }
+            } else {
+                if group.entries != group.count {
+                    return Err(DecodeError::BadGroup(group.spec.count_tag));
+                }
+                self.open.pop();
+                continue;
+            }
+            return Ok(Some(GroupPosition {
+                count_tag: group.spec.count_tag,
+                entry: group.entries - 1,
+                depth: self.open.len() - 1,
+            }));
+        }
+        Ok(None)
+    }
+
+    fn next_field(&mut self) -> Option<Result<GroupedField<'a>, DecodeError>> {
+        let Some(field) = self.fields.next() else {
+            // the trailer closes every group, unless the message was cut short
+            return self
+                .open
+                .pop()
+                .map(|group| Err(DecodeError::BadGroup(group.spec.count_tag)));
+        };
+        let (tag, value) = match field {
+            Ok(field) => field,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let group = match self.position(tag) {
+            Ok(group) => group,
+            Err(e) => return Some(Err(e)),
+        };
+        let scope = match self.open.last() {
+            Some(open) => &open.spec.nested[..],
+            None => self.specs,
+        };
+        if let Some(spec) = scope.iter().find(|spec| spec.count_tag == tag) {
+            let Some(count) = bytes_to_u32(value) else {
+                return Some(Err(DecodeError::BadValue(value.to_vec())));
+            };
+            if count > 0 {
+                self.open.push(OpenGroup {
+                    spec,
+                    count: count as usize,
+                    entries: 0,
+                });
+            }
+        }
+        Some(Ok(GroupedField { tag, value, group }))
+    }
+}
+
+impl<'a, 's> Iterator for GroupedFields<'a, 's> {
+    type Item = Result<GroupedField<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let next = self.next_field();
+        self.failed = matches!(next, Some(Err(_)));
+        next
+    }
+}
+
 fn bytes_to_u32(bytes: &[u8]) -> Option<u32> {
     let mut accum: u32 = 0;
     for b in bytes.iter() {
@@ -450,7 +730,12 @@ pub(super) fn parse_header(header: &[u8]) -> Result<usize, SessionError> {
     let prefix = parse_peeked_prefix(header)?; 
     // body_length does not account for the 7 byte checksum (10=xxx|) 
     // and len_end is 1 less that we would like 
-    Ok(prefix.body_length - (header.len() - (prefix.len_end + 1)) + 7)
+    (prefix.body_length + 7)
+        .checked_sub(header.len() - (prefix.len_end + 1))
+        .ok_or_else(|| SessionError::new_garbled_message(
+            String::from("BodyLength too small"),
+            GarbledMessageType::BodyLengthIssue,
+        ))
 }
 
 pub(super) struct ParsedPeek {
@@ -462,39 +747,40 @@ pub(super) struct ParsedPeek {
 }
 pub(super) fn parse_peeked_prefix(peeked: &[u8]) -> result::Result<ParsedPeek, SessionError> {
     const EXPECTED_PREFIX: &[u8] = b"8=FIX.4.2\x019=";
-    if &peeked[..2] == b"8=" && &peeked[2..9] != b"FIX.4.2" {
+    if peeked.starts_with(b"8=") && peeked.get(2..9) != Some(b"FIX.4.2") {
         return Err(SessionError::new_garbled_message(
             String::from("Incorrect BeginString"),
             GarbledMessageType::BeginStringIssue,
         ));
     }
 
-    if &peeked[..EXPECTED_PREFIX.len()] != EXPECTED_PREFIX {
+    let len_start = EXPECTED_PREFIX.len();
+    if !peeked.starts_with(EXPECTED_PREFIX) {
         return Err(SessionError::new_garbled_message(
             String::from("BeginString not first"),
             GarbledMessageType::Other,
         ));
     }
-    let mut at = EXPECTED_PREFIX.len();
+    let mut at = len_start;
     let mut body_length: usize = 0;
     let mut saw_end = false;
-    for c in peeked[EXPECTED_PREFIX.len()..].iter() {
+    for c in peeked[len_start..].iter() {
         at += 1;
         match *c as char {
             '0'..='9' => {
                 body_length =
                     body_length
                         .checked_mul(10)
-                        .ok_or(SessionError::new_garbled_message(
+                        .ok_or_else(|| SessionError::new_garbled_message(
                             String::from("BodyLength too large"),
                             GarbledMessageType::BodyLengthIssue,
                         ))?;
-                body_length = body_length.checked_add((*c - (b'0')) as usize).ok_or(
+                body_length = body_length.checked_add((*c - (b'0')) as usize).ok_or_else(|| {
                     SessionError::new_garbled_message(
                         String::from("BodyLength too large"),
                         GarbledMessageType::BodyLengthIssue,
-                    ),
-                )?;
+                    )
+                })?;
             }
             '\x01' => {
                 saw_end = true;
@@ -516,25 +802,133 @@ pub(super) fn parse_peeked_prefix(peeked: &[u8]) -> result::Result<ParsedPeek, S
         ));
     }
 
-    let msg_type = if &peeked[at..at + 3] == b"35=" && peeked[at + 4] == b'\x01' {
-        peeked[at + 3]
-    } else {
-        return Err(SessionError::new_garbled_message(
-            String::from("Missing MsgType"),
-            GarbledMessageType::MsgTypeIssue,
-        ));
+    let msg_type = match peeked.get(at..at + 5) {
+        Some([b'3', b'5', b'=', msg_type, b'\x01']) => *msg_type,
+        _ => {
+            return Err(SessionError::new_garbled_message(
+                String::from("Missing MsgType"),
+                GarbledMessageType::MsgTypeIssue,
+            ));
+        }
     };
     let fixed_fields_end = at + 5;
 
     Ok(ParsedPeek {
         msg_type: msg_type as char,
-        len_start: EXPECTED_PREFIX.len(),
+        len_start,
         len_end,
         fixed_fields_end,
         body_length,
     })
 }
 
+/// A parsed message that keeps every field, in order. 
+///
+/// `FixMessage` trades some speed for convenience, for debugging, tests and tools that want
+/// to look at many fields of a message without writing a [`ParserCallback`]. The message is copied
+/// once, and each field refers to its value in that copy. A tag that appears more than once,
+/// such as a field of a repeating group, keeps all of its values. 
+///
+/// Its [`Display`](std::fmt::Display) renders the message with a `|` for each `SOH`. 
+///
+/// ```
+/// use forgefix::fix::decode::FixMessage;
+/// use forgefix::fix::generated::Tags;
+/// use forgefix::fix::mem::MsgBuf;
+///
+/// # fn main() -> Result<(), forgefix::fix::decode::DecodeError> {
+/// let msg = MsgBuf(b"8=FIX.4.2\x019=5\x0135=W\x01268=2\x01270=1.00\x01270=1.01\x0110=000\x01".to_vec());
+/// let msg = FixMessage::parse(&msg)?;
+/// assert_eq!(msg.get(Tags::MsgType), Some(&b"W"[..]));
+/// assert_eq!(msg.get_all(Tags::MDEntryPx).collect::<Vec<_>>(), vec![&b"1.00"[..], b"1.01"]);
+/// assert_eq!(msg.to_string(), "8=FIX.4.2|9=5|35=W|268=2|270=1.00|270=1.01|10=000|");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixMessage {
+    buf: Vec<u8>,
+    // the tag of each field, and where its value is in `buf`
+    fields: Vec<(u32, std::ops::Range<usize>)>,
+}
+
+impl FixMessage {
+    /// Split `msg` into its fields. 
+    ///
+    /// Returns a [`DecodeError::BadMessage`] if the message cannot be split into fields. 
+    pub fn parse(msg: &MsgBuf) -> Result<FixMessage, DecodeError> {
+        let buf = msg.0.clone();
+        let start = buf.as_ptr() as usize;
+        let fields = FieldIter::new(&buf)
+            .map(|field| {
+                let (tag, value) = field?;
+                let value_start = value.as_ptr() as usize - start;
+                Ok((tag, value_start..value_start + value.len()))
+            })
+            .collect::<Result<Vec<_>, MessageParseError>>()?;
+        Ok(FixMessage { buf, fields })
+    }
+
+    /// The value of the first field with `tag`, if any. 
+    pub fn get(&self, tag: impl Into<u32>) -> Option<&[u8]> {
+        self.get_all(tag).next()
+    }
+
+    /// The values of every field with `tag`, in order. 
+    pub fn get_all(&self, tag: impl Into<u32>) -> impl Iterator<Item = &[u8]> {
+        let tag = tag.into();
+        self.iter()
+            .filter(move |(field_tag, _)| *field_tag == tag)
+            .map(|(_, value)| value)
+    }
+
+    /// Parse the value of the first field with `tag` with [`parse_field`]. Returns `None` if the
+    /// message has no such field. 
+    pub fn get_as<T>(&self, tag: impl Into<u32>) -> Option<Result<T, DecodeError>>
+    where
+        T: std::str::FromStr,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        self.get(tag).map(parse_field::<T>)
+    }
+
+    /// Whether the message has a field with `tag`. 
+    pub fn contains(&self, tag: impl Into<u32>) -> bool {
+        self.get(tag).is_some()
+    }
+
+    /// The tag and value of every field, in order. 
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.fields
+            .iter()
+            .map(|(tag, value)| (*tag, &self.buf[value.clone()]))
+    }
+
+    /// The number of fields. 
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether the message has no fields. 
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl std::fmt::Display for FixMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+        for (tag, value) in self.iter() {
+            write!(f, "{tag}=")?;
+            for b in value {
+                f.write_char(*b as char)?;
+            }
+            f.write_char('|')?;
+        }
+        Ok(())
+    }
+}
+
 /// Attempts to parse a FIX value into any type that `impl`'s [`FromStr`]
 ///
 /// # Primitives
@@ -542,7 +936,7 @@ pub(super) fn parse_peeked_prefix(peeked: &[u8]) -> result::Result<ParsedPeek, S
 /// Rust primitives generally `impl` [`FromStr`]. And most FIX data type can be represented by rust primitives. Consider 
 /// using the following for each FIX type: 
 /// * `int` -- [`i32`], [`u32`]
-/// * `float` -- [`f32`]
+/// * `float` -- [`FixDecimal`], which keeps the exact digits of prices and quantities, or [`f32`]
 /// * `char` -- [`char`]
 /// * `String` -- [`&str`]*, [`String`]
 /// * `data` -- `&[u8]`
@@ -563,6 +957,7 @@ pub(super) fn parse_peeked_prefix(peeked: &[u8]) -> result::Result<ParsedPeek, S
 ///
 ///
 /// [`FromStr`]: std::str::FromStr
+/// [`FixDecimal`]: crate::fix::decimal::FixDecimal
 /// [`MsgType`]: crate::fix::generated::MsgType
 /// [`from_utf8`]: std::str::from_utf8
 ///
@@ -620,6 +1015,8 @@ pub(super) fn parse_sending_time(sending_time_bytes: &[u8]) -> Result<DateTime<U
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
     #[test]
     fn test_body_length_too_long() {
         if let Ok(_) = parse_peeked_prefix(b"8=FIX.4.2\x019=33333333333333333333333") {
@@ -627,6 +1024,47 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_grouped_fields_malformed() {
+        let specs = [GroupSpec::new(Tags::NoMDEntries, Tags::MDEntryType, [Tags::MDEntryPx])];
+        let last = |msg: &'static [u8]| grouped_fields(msg, &specs).last().unwrap();
+        assert!(last(b"8=FIX.4.2\x01268=1\x01269=0\x01270=1\x0110=000\x01").is_ok());
+        // fewer entries than counted
+        assert!(matches!(
+            last(b"8=FIX.4.2\x01268=2\x01269=0\x01270=1\x0110=000\x01"),
+            Err(DecodeError::BadGroup(268))
+        ));
+        // more entries than counted
+        assert!(matches!(
+            last(b"8=FIX.4.2\x01268=1\x01269=0\x01269=1\x0110=000\x01"),
+            Err(DecodeError::BadGroup(268))
+        ));
+        // an entry that does not start with the delimiter
+        assert!(matches!(
+            last(b"8=FIX.4.2\x01268=1\x01270=1\x01269=0\x0110=000\x01"),
+            Err(DecodeError::BadGroup(268))
+        ));
+        // cut short
+        assert!(matches!(last(b"8=FIX.4.2\x01268=1\x01"), Err(DecodeError::BadGroup(268))));
+        assert!(matches!(last(b"8=FIX.4.2\x01268=x\x01"), Err(DecodeError::BadValue(_))));
+    }
+
+    #[test]
+    fn test_fix_message() {
+        let msg = MsgBuf(b"8=FIX.4.2\x019=20\x0135=8\x0195=3\x0196=a\x01b\x0111=ID1\x0138=100\x0110=000\x01".to_vec());
+        let parsed = FixMessage::parse(&msg).unwrap();
+        assert_eq!(parsed.len(), 8);
+        // the data field keeps its SOH
+        assert_eq!(parsed.get(Tags::RawData), Some(&b"a\x01b"[..]));
+        assert_eq!(parsed.get_as::<u32>(Tags::OrderQty).unwrap().unwrap(), 100);
+        assert!(parsed.get_as::<u32>(Tags::ClOrdID).unwrap().is_err());
+        assert!(!parsed.contains(Tags::Price));
+        assert_eq!(parsed.iter().last(), Some((10, &b"000"[..])));
+
+        let garbled = MsgBuf(b"8=FIX.4.2\x01x=1\x01".to_vec());
+        assert!(matches!(FixMessage::parse(&garbled), Err(DecodeError::BadMessage(_))));
+    }
+
     #[test]
     fn test_bytes_to_u32() {
         assert_eq!(bytes_to_u32(b"234").unwrap(), 234);
@@ -665,4 +1103,213 @@ mod test {
             }
         }
     }
+
+    fn encode_fields(fields: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut msg = Vec::new();
+        for (tag, value) in fields {
+            msg.extend_from_slice(format!("{tag}=").as_bytes());
+            msg.extend_from_slice(value);
+            msg.push(b'\x01');
+        }
+        msg
+    }
+
+    // A field whose tag is not the length of a data field.
+    fn plain_field() -> impl Strategy<Value = (u32, Vec<u8>)> {
+        (
+            (1u32..100_000).prop_filter("length tag", |tag| get_data_ref(*tag).is_none()),
+            prop::collection::vec(any::<u8>().prop_filter("SOH", |b| *b != b'\x01'), 0..16),
+        )
+    }
+
+    fn header(body_length: &str, msg_type: &str) -> Vec<u8> {
+        format!("8=FIX.4.2\x019={body_length}\x0135={msg_type}\x0134=1\x0149=A\x01").into_bytes()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_field_iter_arbitrary_bytes(msg in prop::collection::vec(any::<u8>(), 0..256)) {
+            let mut seen_error = false;
+            for field in FieldIter::new(&msg) {
+                prop_assert!(!seen_error, "field after an error");
+                match field {
+                    Ok((_, value)) => prop_assert!(value.len() <= msg.len()),
+                    Err(_) => seen_error = true,
+                }
+            }
+        }
+
+        #[test]
+        fn prop_field_iter_round_trip(fields in prop::collection::vec(plain_field(), 0..32)) {
+            let msg = encode_fields(&fields);
+            let parsed: Vec<(u32, Vec<u8>)> = FieldIter::new(&msg)
+                .map(|field| field.map(|(tag, value)| (tag, value.to_vec())))
+                .collect::<Result<_, _>>()
+                .unwrap();
+            prop_assert_eq!(parsed, fields);
+        }
+
+        #[test]
+        fn prop_field_iter_data_fields(
+            data in prop::collection::vec(any::<u8>(), 0..64),
+            declared in 0u32..80,
+            giant in any::<bool>(),
+        ) {
+            // a data field with its length, which may not match the data
+            let length = if giant { u32::MAX.to_string() } else { declared.to_string() };
+            let mut msg = encode_fields(&[(93, length.into_bytes())]);
+            msg.extend_from_slice(b"89=");
+            msg.extend_from_slice(&data);
+            msg.extend_from_slice(b"\x0110=000\x01");
+            let fields: Vec<_> = FieldIter::new(&msg).collect();
+            if declared as usize == data.len() && !giant {
+                prop_assert_eq!(fields[1].as_ref().unwrap(), &(89, &data[..]));
+            }
+        }
+
+        #[test]
+        fn prop_field_iter_inserted_soh(
+            fields in prop::collection::vec(plain_field(), 1..16),
+            at in prop::collection::vec(any::<prop::sample::Index>(), 1..8),
+        ) {
+            let mut msg = encode_fields(&fields);
+            for index in at {
+                let i = index.index(msg.len());
+                msg.insert(i, b'\x01');
+            }
+            let _ = FieldIter::new(&msg).count();
+            let _ = parse(&msg, &mut NullParserCallback);
+        }
+
+        #[test]
+        fn prop_field_iter_giant_tags(tag in "[0-9]{1,40}", value in "[0-9]{0,8}") {
+            let msg = format!("{tag}={value}\x01").into_bytes();
+            let field = FieldIter::new(&msg).next().unwrap();
+            match tag.parse::<u32>() {
+                Ok(tag) => prop_assert_eq!(field.unwrap(), (tag, value.as_bytes())),
+                Err(_) => prop_assert!(field.is_err()),
+            }
+        }
+
+        #[test]
+        fn prop_peeked_prefix_arbitrary_bytes(peeked in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = parse_peeked_prefix(&peeked);
+            let _ = parse_header(&peeked);
+        }
+
+        #[test]
+        fn prop_peeked_prefix_valid(
+            body_length in 0usize..1_000_000,
+            msg_type in "[0-9A-Za-z]",
+        ) {
+            let peeked = header(&body_length.to_string(), &msg_type);
+            let prefix = parse_peeked_prefix(&peeked).unwrap();
+            prop_assert_eq!(prefix.body_length, body_length);
+            prop_assert_eq!(prefix.msg_type, msg_type.chars().next().unwrap());
+            let digits = body_length.to_string();
+            prop_assert_eq!(&peeked[prefix.len_start..prefix.len_end], digits.as_bytes());
+        }
+
+        #[test]
+        fn prop_peeked_prefix_truncated(
+            body_length in "[0-9]{1,30}",
+            len in 0usize..64,
+        ) {
+            let peeked = header(&body_length, "D");
+            let truncated = &peeked[..len.min(peeked.len())];
+            let _ = parse_peeked_prefix(truncated);
+            let _ = parse_header(truncated);
+            if body_length.parse::<usize>().is_err() {
+                prop_assert!(parse_peeked_prefix(&peeked).is_err());
+            }
+        }
+
+        #[test]
+        fn prop_peeked_prefix_inserted_soh(
+            body_length in 0usize..1000,
+            at in any::<prop::sample::Index>(),
+        ) {
+            let mut peeked = header(&body_length.to_string(), "D");
+            let i = at.index(peeked.len());
+            peeked.insert(i, b'\x01');
+            let _ = parse_peeked_prefix(&peeked);
+            let _ = parse_header(&peeked);
+        }
+    }
+
+    #[derive(Default)]
+    struct SectionRecorder(Vec<(char, u32)>);
+
+    impl<'a> ParserCallback<'a> for SectionRecorder {
+        type Err = MessageParseError;
+        fn header(&mut self, key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+            self.0.push(('H', key));
+            Ok(true)
+        }
+        fn body(&mut self, key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+            self.0.push(('B', key));
+            Ok(true)
+        }
+        fn trailer(&mut self, key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+            self.0.push(('T', key));
+            Ok(true)
+        }
+        fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+            Err(err)
+        }
+    }
+
+    #[test]
+    fn test_parse_with_sections() {
+        let msg = b"8=FIX.4.2\x019=5\x0135=D\x0155=AAA\x015000=X\x0150=desk\x016000=Y\x0110=000\x01";
+
+        let mut cb = SectionRecorder::default();
+        parse(msg, &mut cb).unwrap();
+        assert_eq!(&cb.0[3..], &[('B', 55), ('B', 5000), ('H', 50), ('B', 6000), ('T', 10)]);
+
+        let sections = FieldSections::new().with_header_tag(5000u32).with_trailer_tag(6000u32);
+        let mut cb = SectionRecorder::default();
+        parse_with_sections(msg, &mut cb, &sections).unwrap();
+        assert_eq!(&cb.0[3..], &[('B', 55), ('H', 5000), ('H', 50), ('T', 6000), ('T', 10)]);
+    }
+
+    #[test]
+    fn test_execution_report() {
+        use crate::fix::decimal::FixDecimal;
+        use crate::fix::generated::{ExecType, ExecutionReport, OrdStatus, Side};
+
+        let msg = MsgBuf(
+            b"8=FIX.4.2\x019=5\x0135=8\x0134=2\x0137=broker-1\x0111=order-1\x0117=exec-1\x0120=0\x01\
+              150=1\x0139=1\x0155=AAPL\x0154=1\x0138=100\x0132=40\x0131=187.5\x01151=60\x0114=40\x01\
+              6=187.5\x0160=20240102-10:00:00.000\x0158=partial\x0110=000\x01"
+                .to_vec(),
+        );
+        let report = ExecutionReport::try_from(&msg).unwrap();
+        assert_eq!(report.order_id, "broker-1");
+        assert_eq!(report.cl_ord_id, Some("order-1"));
+        assert_eq!(report.exec_type, ExecType::PARTIAL_FILL);
+        assert_eq!(report.ord_status, OrdStatus::PARTIALLY_FILLED);
+        assert_eq!(report.side, Side::BUY);
+        assert_eq!(report.order_qty, Some(FixDecimal::new(100, 0)));
+        assert_eq!(report.last_shares, Some(FixDecimal::new(40, 0)));
+        assert_eq!(report.last_px, Some(FixDecimal::new(1875, 1)));
+        assert_eq!(report.leaves_qty, FixDecimal::new(60, 0));
+        assert_eq!(report.cum_qty, FixDecimal::new(40, 0));
+        assert_eq!(report.avg_px, FixDecimal::new(1875, 1));
+        assert_eq!(report.text, Some("partial"));
+        assert!(report.transact_time.is_some());
+        assert!(report.price.is_none());
+
+        let missing = MsgBuf(b"8=FIX.4.2\x019=5\x0135=8\x0134=2\x0137=broker-1\x0110=000\x01".to_vec());
+        assert!(matches!(
+            ExecutionReport::try_from(&missing),
+            Err(DecodeError::MissingTag(Tags::ExecID))
+        ));
+
+        let order = MsgBuf(b"8=FIX.4.2\x019=5\x0135=D\x0134=2\x0111=order-1\x0110=000\x01".to_vec());
+        assert!(matches!(
+            ExecutionReport::try_from(&order),
+            Err(DecodeError::UnexpectedMsgType(_))
+        ));
+    }
 }
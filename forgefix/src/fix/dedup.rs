@@ -0,0 +1,152 @@
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::generated::{MsgType, Tags};
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+// The `ClOrdID(11)`s of the new orders sent in this epoch, shared between the engine and its
+// handles. The engine loads the IDs that were stored before a restart and records each ID once
+// its order is written. A handle reserves the ID of each new order it queues, so that two handles
+// sending the same ID at once cannot both queue it.
+#[derive(Default)]
+pub(crate) struct SentOrders {
+    enabled: bool,
+    cl_ord_ids: Mutex<ClOrdIds>,
+}
+
+#[derive(Default)]
+struct ClOrdIds {
+    sent: HashSet<Vec<u8>>,
+    reserved: HashSet<Vec<u8>>,
+}
+
+impl SentOrders {
+    pub(crate) fn new(enabled: bool) -> SentOrders {
+        SentOrders {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn contains(&self, cl_ord_id: &[u8]) -> bool {
+        self.cl_ord_ids.lock().unwrap().sent.contains(cl_ord_id)
+    }
+
+    // Reserve every ID of `cl_ord_ids` until the returned reservation is dropped. Returns `None`
+    // if any ID was already sent or reserved, or appears twice in `cl_ord_ids`.
+    pub(crate) fn reserve(self: &Arc<Self>, cl_ord_ids: &[&[u8]]) -> Option<Reservation> {
+        let mut ids = self.cl_ord_ids.lock().unwrap();
+        let mut batch = HashSet::new();
+        let free = cl_ord_ids.iter().all(|cl_ord_id| {
+            !ids.sent.contains(*cl_ord_id) && !ids.reserved.contains(*cl_ord_id) && batch.insert(*cl_ord_id)
+        });
+        if !free {
+            return None;
+        }
+        let cl_ord_ids: Vec<Vec<u8>> = cl_ord_ids.iter().map(|cl_ord_id| cl_ord_id.to_vec()).collect();
+        ids.reserved.extend(cl_ord_ids.iter().cloned());
+        Some(Reservation {
+            sent_orders: Arc::clone(self),
+            cl_ord_ids,
+        })
+    }
+
+    pub(super) fn extend(&self, cl_ord_ids: impl IntoIterator<Item = Vec<u8>>) {
+        self.cl_ord_ids.lock().unwrap().sent.extend(cl_ord_ids);
+    }
+}
+
+// The IDs a handle reserved for the new orders it queued, held with the orders until they leave
+// the outbox. The engine records the IDs of the orders it writes before dropping the reservation,
+// so an ID is only given back if its order was refused or discarded.
+pub(crate) struct Reservation {
+    sent_orders: Arc<SentOrders>,
+    cl_ord_ids: Vec<Vec<u8>>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut ids = self.sent_orders.cl_ord_ids.lock().unwrap();
+        for cl_ord_id in &self.cl_ord_ids {
+            ids.reserved.remove(cl_ord_id);
+        }
+    }
+}
+
+// Whether a message of `msg_type` is a new order, whose `ClOrdID(11)` must not be reused. Other
+// application messages, such as an `OrderStatusRequest<H>`, refer to the ID of an order already
+// sent.
+pub(crate) fn is_new_order(msg_type: char) -> bool {
+    msg_type == MsgType::ORDER_SINGLE.into() || msg_type == MsgType::ORDER_LIST.into()
+}
+
+// Get the `ClOrdID(11)` of a serialized new order. Other messages have none.
+pub(super) fn cl_ord_id(msg: &[u8]) -> Option<&[u8]> {
+    let mut cb = ClOrdIdParser::default();
+    parse(msg, &mut cb).ok()?;
+    cb.cl_ord_id
+}
+
+#[derive(Default)]
+struct ClOrdIdParser<'a> {
+    cl_ord_id: Option<&'a [u8]>,
+}
+
+impl<'a> ParserCallback<'a> for ClOrdIdParser<'a> {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let (Ok(Tags::MsgType), [msg_type]) = (key.try_into(), value) {
+            return Ok(is_new_order(*msg_type as char));
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let Ok(Tags::ClOrdID) = key.try_into() {
+            self.cl_ord_id = Some(value);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cl_ord_id() {
+        let order = b"8=FIX.4.2\x019=5\x0135=D\x0134=2\x0111=order-1\x0155=AAA\x0110=000\x01";
+        assert_eq!(cl_ord_id(order), Some(&b"order-1"[..]));
+
+        let heartbeat = b"8=FIX.4.2\x019=5\x0135=0\x0134=2\x0111=order-1\x0110=000\x01";
+        assert_eq!(cl_ord_id(heartbeat), None);
+        let order_status = b"8=FIX.4.2\x019=5\x0135=H\x0134=3\x0111=order-1\x0155=AAA\x0110=000\x01";
+        assert_eq!(cl_ord_id(order_status), None);
+
+        let sent_orders = Arc::new(SentOrders::new(true));
+        sent_orders.extend([b"order-1".to_vec()]);
+        assert!(sent_orders.contains(b"order-1"));
+        assert!(!sent_orders.contains(b"order-2"));
+        assert!(sent_orders.reserve(&[b"order-2", b"order-1"]).is_none());
+        assert!(sent_orders.reserve(&[b"order-2", b"order-2"]).is_none());
+
+        // a reserved ID is refused until its reservation is dropped
+        let reservation = sent_orders.reserve(&[b"order-2", b"order-3"]).unwrap();
+        assert!(!sent_orders.contains(b"order-2"));
+        assert!(sent_orders.reserve(&[b"order-3"]).is_none());
+        sent_orders.extend([b"order-2".to_vec()]);
+        drop(reservation);
+        assert!(sent_orders.reserve(&[b"order-2"]).is_none());
+        assert!(sent_orders.reserve(&[b"order-3"]).is_some());
+    }
+}
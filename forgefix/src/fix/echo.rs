@@ -0,0 +1,129 @@
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::encode::MessageBuilder;
+use crate::fix::generated::Tags;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// The values of the echo tags received on incoming application messages, keyed by their
+// `ClOrdID(11)`. Outgoing messages about the same order get the values appended, unless they
+// already contain the tag.
+pub(super) struct EchoTags {
+    tags: Arc<Vec<u32>>,
+    values: HashMap<Vec<u8>, Vec<(u32, Vec<u8>)>>,
+}
+
+impl EchoTags {
+    pub(super) fn new(tags: Arc<Vec<u32>>) -> EchoTags {
+        EchoTags {
+            tags,
+            values: HashMap::new(),
+        }
+    }
+
+    // Record the echo tags of an incoming application message.
+    pub(super) fn capture(&mut self, msg: &[u8]) {
+        if self.tags.is_empty() {
+            return;
+        }
+        let mut cb = EchoTagParser {
+            tags: &self.tags,
+            cl_ord_id: None,
+            values: Vec::new(),
+        };
+        if parse(msg, &mut cb).is_err() || cb.values.is_empty() {
+            return;
+        }
+        if let Some(cl_ord_id) = cb.cl_ord_id {
+            let values = self.values.entry(cl_ord_id.to_vec()).or_default();
+            for (tag, value) in cb.values {
+                values.retain(|(t, _)| *t != tag);
+                values.push((tag, value.to_vec()));
+            }
+        }
+    }
+
+    // Append the echo tags recorded for the order an outgoing message refers to, by its
+    // `OrigClOrdID(41)` or else its `ClOrdID(11)`.
+    pub(super) fn apply(&self, mut builder: MessageBuilder) -> MessageBuilder {
+        let values = [Tags::OrigClOrdID, Tags::ClOrdID]
+            .into_iter()
+            .filter_map(|tag| builder.field(tag.into()))
+            .find_map(|cl_ord_id| self.values.get(cl_ord_id));
+        let Some(values) = values else {
+            return builder;
+        };
+        let missing: Vec<_> = values
+            .iter()
+            .filter(|(tag, _)| builder.field(*tag).is_none())
+            .collect();
+        for (tag, value) in missing {
+            builder.push_mut(*tag, value);
+        }
+        builder
+    }
+}
+
+struct EchoTagParser<'a, 'b> {
+    tags: &'b [u32],
+    cl_ord_id: Option<&'a [u8]>,
+    values: Vec<(u32, &'a [u8])>,
+}
+
+impl<'a, 'b> EchoTagParser<'a, 'b> {
+    fn field(&mut self, key: u32, value: &'a [u8]) -> Result<bool, MessageParseError> {
+        if key == u32::from(Tags::ClOrdID) {
+            self.cl_ord_id = Some(value);
+        }
+        if self.tags.contains(&key) {
+            self.values.push((key, value));
+        }
+        Ok(true)
+    }
+}
+
+impl<'a, 'b> ParserCallback<'a> for EchoTagParser<'a, 'b> {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.field(key, value)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.field(key, value)
+    }
+    fn trailer(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.field(key, value)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fix::generated::MsgType;
+
+    #[test]
+    fn test_echo_tags() {
+        let mut echo_tags = EchoTags::new(Arc::new(vec![9001, 9002]));
+        echo_tags.capture(b"8=FIX.4.2\x019=5\x0135=8\x0134=2\x0111=order-1\x019001=abc\x0110=000\x01");
+        echo_tags.capture(b"8=FIX.4.2\x019=5\x0135=8\x0134=3\x0111=order-2\x0158=none\x0110=000\x01");
+
+        let cancel = MessageBuilder::new("FIX.4.2", MsgType::ORDER_CANCEL_REQUEST.into())
+            .push(Tags::OrigClOrdID, b"order-1")
+            .push(Tags::ClOrdID, b"order-1.C1");
+        let cancel = echo_tags.apply(cancel);
+        assert_eq!(cancel.field(9001), Some(&b"abc"[..]));
+        assert_eq!(cancel.field(9002), None);
+
+        // a tag the builder already has is not replaced
+        let order = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1")
+            .push(9001u32, b"mine");
+        let order = echo_tags.apply(order);
+        assert_eq!(order.field(9001), Some(&b"mine"[..]));
+
+        let other = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into()).push(Tags::ClOrdID, b"order-2");
+        assert_eq!(echo_tags.apply(other).field(9001), None);
+    }
+}
@@ -9,6 +9,9 @@
 //! * [`SerializedInt`] for integer values
 //! * `b"..."` for ASCII fields like text and floats (see [FIX dictionary])
 //!
+//! Typed helpers, such as [`MessageBuilder::push_int`] and [`MessageBuilder::push_fix_decimal`],
+//! format values straight into the message instead. 
+//!
 //! [generated enums]: crate::fix::generated
 //! [`Tags`]: ../generated/enum.Tags.html
 //! [`MsgType`]: ../generated/enum.MsgType.html
@@ -33,10 +36,14 @@
 //! ```
 
 use crate::fix::checksum::AsyncChecksumWriter;
+use crate::fix::decimal::FixDecimal;
 use crate::fix::generated::Tags;
-use crate::SessionSettings;
+use crate::fix::lint::{lint_body, Diagnostic};
+use crate::fix::pool::{BuilderPool, BUFFER_CAPACITY};
+use crate::{Clock, SessionSettings};
 use chrono::{DateTime, Utc};
 use std::io::{Cursor, Write};
+use std::sync::Arc;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 /// The time format string represented in [chrono format syntax]
@@ -62,6 +69,9 @@ pub fn formatted_time() -> String {
 /// * `SendingTime(52)`
 /// * `Checksum(10)`
 ///
+/// The `SendingTime(52)` is the time the message is sent, unless set with
+/// [`with_sending_time`](MessageBuilder::with_sending_time).
+///
 /// MessageBuilder fields do not get checked for validity, therefore it is possible to send invalid
 /// FIX messages if a particular value is invalid for the given field. 
 ///
@@ -90,6 +100,11 @@ pub struct MessageBuilder {
     preamble: Cursor<[u8; 32]>, // e.g. 8=FIX.4.2^9=_________________
     msg_type: char,
     main_buffer: Cursor<Vec<u8>>,
+    // the positions in `main_buffer` where the sending time is inserted
+    deferred_times: Vec<usize>,
+    sending_time: Option<DateTime<Utc>>,
+    // the pool the buffer is given back to when the builder is dropped
+    pool: Option<Arc<BuilderPool>>,
 }
 
 pub(super) const SOH: &[u8] = &[b'\x01'];
@@ -100,16 +115,27 @@ impl MessageBuilder {
     ///
     /// [`MsgType`]: ../generated/enum.MsgType.html
     pub fn new(begin_string: &str, msg_type: char) -> Self {
+        Self::with_buffer(begin_string, msg_type, Vec::with_capacity(BUFFER_CAPACITY), None)
+    }
+
+    // A builder whose buffer is taken from `pool`, and given back when it is dropped.
+    pub(crate) fn pooled(begin_string: &str, msg_type: char, pool: &Arc<BuilderPool>) -> Self {
+        Self::with_buffer(begin_string, msg_type, pool.take(), Some(Arc::clone(pool)))
+    }
+
+    fn with_buffer(begin_string: &str, msg_type: char, buffer: Vec<u8>, pool: Option<Arc<BuilderPool>>) -> Self {
         let mut writer = Cursor::new([0_u8; 32]);
         writer
             .write_fmt(format_args!("8={}\x019=", begin_string))
             .unwrap();
-        let main_buffer = Cursor::new(Vec::with_capacity(1024));
 
         MessageBuilder {
             preamble: writer,
             msg_type,
-            main_buffer,
+            main_buffer: Cursor::new(buffer),
+            deferred_times: Vec::new(),
+            sending_time: None,
+            pool,
         }
     }
 
@@ -127,20 +153,190 @@ impl MessageBuilder {
     }
 
     pub fn push_mut(&mut self, tag_param: impl Into<u32>, value: &[u8]) {
-        let tag: u32 = tag_param.into();
-        let _ = self.write_bytes(tag.to_string().as_bytes());
-        let _ = self.write_bytes(b"=");
+        self.write_tag(tag_param.into());
         let _ = self.write_bytes(value);
         let _ = self.write_bytes(SOH);
     }
 
+    // Writes `tag=`, the start of a field whose value is written next.
+    fn write_tag(&mut self, tag: u32) {
+        let _ = self.write_bytes(SerializedInt::from(tag).as_bytes());
+        let _ = self.write_bytes(b"=");
+    }
+
+    // Writes a field whose value is formatted straight into the message.
+    fn write_fmt_field(&mut self, tag: u32, value: std::fmt::Arguments) {
+        self.write_tag(tag);
+        let _ = self.main_buffer.write_fmt(value);
+        let _ = self.write_bytes(SOH);
+    }
+
+    /// Adds an integer field, such as `OrderQty(38)`. 
+    ///
+    /// ```rust
+    /// use forgefix::fix::encode::MessageBuilder;
+    /// use forgefix::fix::generated::{MsgType, OrdType, Side, Tags};
+    /// use chrono::{TimeZone, Utc};
+    ///
+    /// let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+    ///     .push_int(Tags::OrderQty, 100)
+    ///     .push_decimal(Tags::Price, 10.425, 2)
+    ///     .push_enum(Tags::Side, Side::BUY)
+    ///     .push_enum(Tags::OrdType, OrdType::LIMIT)
+    ///     .push_char(Tags::HandlInst, '1')
+    ///     .push_bool(Tags::LocateReqd, false)
+    ///     .push_utc_timestamp(Tags::TransactTime, Utc.with_ymd_and_hms(2024, 1, 2, 15, 4, 5).unwrap());
+    /// ```
+    pub fn push_int(mut self, tag_param: impl Into<u32>, value: u64) -> Self {
+        self.push_int_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_int_mut(&mut self, tag_param: impl Into<u32>, value: u64) {
+        self.push_mut(tag_param, SerializedInt::from(value).as_bytes());
+    }
+
+    /// Adds a decimal field, such as `Price(44)`, rounded to `precision` digits after the decimal
+    /// point. See [`push_fix_decimal`](MessageBuilder::push_fix_decimal) to send a value with
+    /// exactly the digits it was received with. 
+    pub fn push_decimal(mut self, tag_param: impl Into<u32>, value: f64, precision: usize) -> Self {
+        self.push_decimal_mut(tag_param, value, precision);
+        self
+    }
+
+    pub fn push_decimal_mut(&mut self, tag_param: impl Into<u32>, value: f64, precision: usize) {
+        self.write_fmt_field(tag_param.into(), format_args!("{:.*}", precision, value));
+    }
+
+    /// Adds an exact decimal field, such as `Price(44)`, with the digits of `value`. 
+    pub fn push_fix_decimal(mut self, tag_param: impl Into<u32>, value: FixDecimal) -> Self {
+        self.push_fix_decimal_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_fix_decimal_mut(&mut self, tag_param: impl Into<u32>, value: FixDecimal) {
+        self.write_fmt_field(tag_param.into(), format_args!("{value}"));
+    }
+
+    /// Adds a single character field, such as `HandlInst(21)`. 
+    pub fn push_char(mut self, tag_param: impl Into<u32>, value: char) -> Self {
+        self.push_char_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_char_mut(&mut self, tag_param: impl Into<u32>, value: char) {
+        self.push_mut(tag_param, value.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+
+    /// Adds a boolean field, such as `LocateReqd(114)`, as `Y` or `N`. 
+    pub fn push_bool(mut self, tag_param: impl Into<u32>, value: bool) -> Self {
+        self.push_bool_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_bool_mut(&mut self, tag_param: impl Into<u32>, value: bool) {
+        self.push_mut(tag_param, if value { b"Y" } else { b"N" });
+    }
+
+    /// Adds a UTC timestamp field, such as `ExpireTime(126)`, in milliseconds
+    /// ([`TIME_FORMAT`]). 
+    pub fn push_utc_timestamp(mut self, tag_param: impl Into<u32>, value: DateTime<Utc>) -> Self {
+        self.push_utc_timestamp_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_utc_timestamp_mut(&mut self, tag_param: impl Into<u32>, value: DateTime<Utc>) {
+        self.write_fmt_field(tag_param.into(), format_args!("{}", value.format(TIME_FORMAT)));
+    }
+
+    /// Adds a field of a [generated enum](crate::fix::generated), such as
+    /// [`Side`](crate::fix::generated::Side). 
+    pub fn push_enum(mut self, tag_param: impl Into<u32>, value: impl Into<&'static [u8]>) -> Self {
+        self.push_enum_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_enum_mut(&mut self, tag_param: impl Into<u32>, value: impl Into<&'static [u8]>) {
+        self.push_mut(tag_param, value.into());
+    }
+
+    /// Adds a `tag_param` field, such as `TransactTime(60)`, whose value is filled in by the engine
+    /// with the `SendingTime(52)` of the message, right before it is written to the connection. 
+    ///
+    /// Unlike a timestamp pushed when the message is built, the time stays accurate while the
+    /// message waits to be sent. 
+    pub fn push_deferred_time(mut self, tag_param: impl Into<u32>) -> Self {
+        self.push_deferred_time_mut(tag_param);
+        self
+    }
+
+    pub fn push_deferred_time_mut(&mut self, tag_param: impl Into<u32>) {
+        self.write_tag(tag_param.into());
+        self.deferred_times.push(self.main_buffer.position() as usize);
+        let _ = self.write_bytes(SOH);
+    }
+
+    /// Sends the message with `sending_time` as its `SendingTime(52)`, instead of the time of the
+    /// engine's [`Clock`] when the message is sent, such as to keep the time an order was decided
+    /// on. Fields added with [`push_deferred_time`](MessageBuilder::push_deferred_time) get the
+    /// same time. 
+    ///
+    /// The time is formatted with the timestamp precision of the session. 
+    pub fn with_sending_time(mut self, sending_time: DateTime<Utc>) -> Self {
+        self.set_sending_time(sending_time);
+        self
+    }
+
+    pub fn set_sending_time(&mut self, sending_time: DateTime<Utc>) {
+        self.sending_time = Some(sending_time);
+    }
+
+    /// Adds a repeating group to the message: the `count_tag` field with the number of `entries`,
+    /// such as `NoAllocs(78)`, followed by the fields of each entry in order. 
+    ///
+    /// ```rust
+    /// use forgefix::fix::encode::{GroupEntry, MessageBuilder};
+    /// use forgefix::fix::generated::{MsgType, Tags};
+    ///
+    /// let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+    ///     .push(Tags::ClOrdID, b"order-1")
+    ///     .push_group(Tags::NoAllocs, vec![
+    ///         GroupEntry::new().push(Tags::AllocAccount, b"A1").push(Tags::AllocShares, b"60"),
+    ///         GroupEntry::new().push(Tags::AllocAccount, b"A2").push(Tags::AllocShares, b"40"),
+    ///     ]);
+    /// ```
+    pub fn push_group(mut self, count_tag: impl Into<u32>, entries: Vec<GroupEntry>) -> Self {
+        self.push_group_mut(count_tag, entries);
+        self
+    }
+
+    pub fn push_group_mut(&mut self, count_tag: impl Into<u32>, entries: Vec<GroupEntry>) {
+        self.push_mut(count_tag, SerializedInt::from(entries.len() as u64).as_bytes());
+        for (tag, value) in entries.iter().flat_map(|entry| &entry.fields) {
+            self.push_mut(*tag, value);
+        }
+    }
+
     fn body_len(&self) -> usize {
         let body_len = self.main_buffer.position() as usize;
         let msg_type_len = 5;
         body_len + msg_type_len
     }
 
-    pub(super) async fn build_async<'a, W>(
+    // The length of the message built with `msg_seq_num` and `additional_headers`, from
+    // `BeginString(8)` to `CheckSum(10)`.
+    pub(crate) fn encoded_len(&self, msg_seq_num: u32, additional_headers: &AdditionalHeaders) -> usize {
+        let deferred_time_len = additional_headers.format_time(DateTime::UNIX_EPOCH).len();
+        let msg_seq_num_len = format!("34={}\x01", msg_seq_num).len();
+        let body_len = self.body_len()
+            + self.deferred_times.len() * deferred_time_len
+            + additional_headers.len()
+            + msg_seq_num_len;
+        let checksum_len = 7;
+        self.preamble.position() as usize + body_len.to_string().len() + 1 + body_len + checksum_len
+    }
+
+    pub(crate) async fn build_async<'a, W>(
         &self,
         sink: W,
         msg_seq_num: u32,
@@ -151,7 +347,9 @@ impl MessageBuilder {
         W: AsyncWrite + Unpin,
     {
         let mut writer = AsyncChecksumWriter::new(sink);
-        let body_len = self.body_len();
+        let sending_time = self.sending_time.unwrap_or(sending_time);
+        let deferred_time = additional_headers.format_time(sending_time);
+        let body_len = self.body_len() + self.deferred_times.len() * deferred_time.len();
         let msg_seq_num_str = format!("34={}\x01", msg_seq_num);
 
         writer
@@ -169,7 +367,13 @@ impl MessageBuilder {
             .write_all(&mut writer, sending_time)
             .await?;
 
-        writer.write_all(self.main_buffer.get_ref()).await?;
+        let mut written = 0;
+        for at in &self.deferred_times {
+            writer.write_all(&self.main_buffer.get_ref()[written..*at]).await?;
+            writer.write_all(deferred_time.as_bytes()).await?;
+            written = *at;
+        }
+        writer.write_all(&self.main_buffer.get_ref()[written..]).await?;
         let checksum: usize = writer.checksum();
         let checksum_str = format!("{:0>3}", checksum);
         writer.write_all(b"10=").await?;
@@ -182,6 +386,104 @@ impl MessageBuilder {
     pub fn msg_type(&self) -> char {
         self.msg_type
     }
+
+    /// Checks the message against the FIX 4.2 dictionary before it is sent: the fields required
+    /// by its `MsgType(35)`, the value of each field, including enumerated values, and that no
+    /// field of the standard header or trailer was pushed into the body. Returns the first
+    /// problem found.
+    ///
+    /// Fields added with [`push_deferred_time`](MessageBuilder::push_deferred_time) are always
+    /// valid. See [`SessionSettingsBuilder::with_outbound_validation`] to check every message
+    /// sent. 
+    ///
+    /// ```rust
+    /// use forgefix::fix::encode::MessageBuilder;
+    /// use forgefix::fix::generated::{MsgType, Tags};
+    ///
+    /// let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_CANCEL_REQUEST.into())
+    ///     .push(Tags::OrigClOrdID, b"order-1")
+    ///     .push(Tags::ClOrdID, b"cancel-1")
+    ///     .push(Tags::Symbol, b"AAPL")
+    ///     .push(Tags::Side, b"X")
+    ///     .push_deferred_time(Tags::TransactTime);
+    /// let err = builder.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "error: Side(54): \"X\" is not one of the allowed values");
+    /// ```
+    ///
+    /// [`SessionSettingsBuilder::with_outbound_validation`]: crate::SessionSettingsBuilder::with_outbound_validation
+    pub fn validate(&self) -> Result<(), Diagnostic> {
+        let buffer = self.main_buffer.get_ref();
+        let mut fields = Vec::new();
+        let mut at = 0;
+        while let Some(len) = buffer[at..].iter().position(|b| *b == SOH[0]) {
+            let field = &buffer[at..at + len];
+            let eq = field.iter().position(|b| *b == b'=').unwrap_or(len);
+            let tag = std::str::from_utf8(&field[..eq]).ok().and_then(|tag| tag.parse().ok());
+            let value = &buffer[(at + eq + 1).min(at + len)..at + len];
+            // a deferred time is filled in at the position right after the `=`
+            let deferred = self.deferred_times.contains(&(at + eq + 1));
+            fields.push((tag.unwrap_or(0), (!deferred).then_some(value)));
+            at += len + 1;
+        }
+        match lint_body(self.msg_type, &fields).into_iter().next() {
+            Some(diagnostic) => Err(diagnostic),
+            None => Ok(()),
+        }
+    }
+
+    /// Gets the value of the first field pushed with `tag`, if any. 
+    pub fn field(&self, tag: u32) -> Option<&[u8]> {
+        let prefix = format!("{tag}=");
+        self.main_buffer
+            .get_ref()
+            .split(|b| *b == SOH[0])
+            .find_map(|field| field.strip_prefix(prefix.as_bytes()))
+    }
+}
+
+impl Drop for MessageBuilder {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.give(std::mem::take(self.main_buffer.get_mut()));
+        }
+    }
+}
+
+/// An entry of a repeating group, added to a message with [`MessageBuilder::push_group`]. 
+///
+/// The first field of each entry must be the delimiter field of the group, such as
+/// `AllocAccount(79)` for `NoAllocs(78)`. The other fields follow in the order they were pushed. 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GroupEntry {
+    fields: Vec<(u32, Vec<u8>)>,
+}
+
+impl GroupEntry {
+    /// Creates an empty entry. 
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the `tag_param`/`value` pair to the entry. 
+    pub fn push(mut self, tag_param: impl Into<u32>, value: &[u8]) -> Self {
+        self.push_mut(tag_param, value);
+        self
+    }
+
+    pub fn push_mut(&mut self, tag_param: impl Into<u32>, value: &[u8]) {
+        self.fields.push((tag_param.into(), value.to_vec()));
+    }
+
+    /// Adds a repeating group nested in the entry, like [`MessageBuilder::push_group`]. 
+    pub fn push_group(mut self, count_tag: impl Into<u32>, entries: Vec<GroupEntry>) -> Self {
+        self.push_group_mut(count_tag, entries);
+        self
+    }
+
+    pub fn push_group_mut(&mut self, count_tag: impl Into<u32>, entries: Vec<GroupEntry>) {
+        self.push_mut(count_tag, SerializedInt::from(entries.len() as u64).as_bytes());
+        self.fields.extend(entries.into_iter().flat_map(|entry| entry.fields));
+    }
 }
 
 /// A [`u64`]/[`u32`] wrapper that can convert an int to its ASCII representation
@@ -228,11 +530,25 @@ impl From<u64> for SerializedInt {
         ser
     }
 }
+impl From<i64> for SerializedInt {
+    fn from(i: i64) -> Self {
+        let mut ser = Self::from(i.unsigned_abs());
+        if i < 0 {
+            ser.1 += 1;
+            let at = ser.0.len() - ser.1;
+            ser.0[at] = b'-';
+        }
+        ser
+    }
+}
 
-#[derive(Default, Debug)]
-pub(super) struct AdditionalHeaders {
+#[derive(Default)]
+pub(crate) struct AdditionalHeaders {
     prefix: Vec<u8>,
     suffix: Vec<u8>,
+    time_format: Option<&'static str>,
+    begin_string: Option<Arc<String>>,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 fn format_fields(fields: &[(u32, Vec<u8>)]) -> Vec<u8> {
@@ -258,14 +574,62 @@ impl AdditionalHeaders {
         AdditionalHeaders {
             prefix: format_fields(prefix_fields),
             suffix: format_fields(suffix_fields),
+            time_format: None,
+            begin_string: None,
+            clock: None,
         }
     }
 
     pub fn build(settings: &SessionSettings) -> Self {
-        AdditionalHeaders::new(comp_id_headers(
-            &settings.sender_comp_id,
-            &settings.target_comp_id,
-        ))
+        let mut fields = comp_id_headers(&settings.sender_comp_id, &settings.target_comp_id);
+        let sub_ids = [
+            (Tags::SenderSubID, &settings.sender_sub_id),
+            (Tags::TargetSubID, &settings.target_sub_id),
+            (Tags::OnBehalfOfCompID, &settings.on_behalf_of_comp_id),
+            (Tags::DeliverToCompID, &settings.deliver_to_comp_id),
+        ];
+        for (tag, value) in sub_ids {
+            if let Some(value) = value {
+                fields.push((tag.into(), value.as_bytes().to_vec()));
+            }
+        }
+        fields.extend(settings.header_extras.iter().cloned());
+        fields.sort_by_key(|(tag, _)| *tag);
+        AdditionalHeaders {
+            time_format: Some(settings.timestamp_precision.time_format()),
+            begin_string: Some(Arc::clone(&settings.begin_string)),
+            clock: Some(Arc::clone(&settings.clock)),
+            ..AdditionalHeaders::new(fields)
+        }
+    }
+
+    // The format of the `SendingTime(52)` of every message, and of the new `SendingTime(52)` of
+    // resent messages.
+    pub(super) fn time_format(&self) -> &'static str {
+        self.time_format.unwrap_or(TIME_FORMAT)
+    }
+
+    // The `BeginString(8)` of the messages built by the engine itself, such as gap fills.
+    pub(super) fn begin_string(&self) -> &str {
+        self.begin_string.as_deref().map_or("FIX.4.2", String::as_str)
+    }
+
+    // The time of the session's clock, stamped as the `SendingTime(52)` of the messages sent.
+    pub(super) fn now(&self) -> DateTime<Utc> {
+        self.clock.as_ref().map_or_else(Utc::now, |clock| clock.now())
+    }
+
+    pub(super) fn format_time(&self, time: DateTime<Utc>) -> String {
+        time.format(self.time_format()).to_string()
+    }
+
+    fn sending_time_field(&self, sending_time: DateTime<Utc>) -> Vec<u8> {
+        format!(
+            "{}={}\x01",
+            u32::from(Tags::SendingTime),
+            self.format_time(sending_time)
+        )
+        .into_bytes()
     }
 
     pub(super) async fn write_all<W>(
@@ -276,19 +640,14 @@ impl AdditionalHeaders {
     where
         W: AsyncWrite + Unpin,
     {
-        let sending_time_field = format!(
-            "{}={}\x01",
-            u32::from(Tags::SendingTime),
-            sending_time.format(TIME_FORMAT)
-        )
-        .into_bytes();
-        assert_eq!(sending_time_field.len(), 21 + 4);
+        let sending_time_field = self.sending_time_field(sending_time);
         w.write_all(&self.prefix[..]).await?;
         w.write_all(&sending_time_field[..]).await?;
         w.write_all(&self.suffix[..]).await
     }
-    pub(super) fn len(&self) -> usize {
-        self.prefix.len() + 25 + self.suffix.len()
+    pub(crate) fn len(&self) -> usize {
+        // every `SendingTime(52)` formatted with the same precision has the same length
+        self.prefix.len() + self.sending_time_field(DateTime::UNIX_EPOCH).len() + self.suffix.len()
     }
 }
 
@@ -317,6 +676,11 @@ mod test {
             let si: SerializedInt = num.into();
             assert_eq!(si.as_bytes(), s.as_bytes());
         }
+        let tests = vec![(-1i64, "-1"), (i64::MIN, "-9223372036854775808"), (i64::MAX, "9223372036854775807")];
+        for (num, s) in tests.into_iter() {
+            let si: SerializedInt = num.into();
+            assert_eq!(si.as_bytes(), s.as_bytes());
+        }
     }
 
     #[test]
@@ -354,6 +718,139 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_push_deferred_time() {
+        let builder = MessageBuilder::new("FIX.4.2", 'D')
+            .push(Tags::ClOrdID, b"order-1")
+            .push_deferred_time(Tags::TransactTime)
+            .push(Tags::Symbol, b"AAPL");
+        let mut buf = Vec::new();
+        let mut cur = tokio::io::BufWriter::new(&mut buf);
+        let additional_headers: AdditionalHeaders = Default::default();
+        builder
+            .build_async(&mut cur, 1, &additional_headers, std::time::UNIX_EPOCH.into())
+            .await
+            .unwrap();
+        cur.flush().await.unwrap();
+        let msg = String::from_utf8(buf).unwrap();
+        assert!(msg.contains("\x0111=order-1\x0160=19700101-00:00:00.000\x0155=AAPL\x01"));
+        assert!(crate::fix::checksum::checksum_is_valid(msg.as_bytes()));
+        let prefix =
+            crate::fix::decode::parse_peeked_prefix(msg.as_bytes()).unwrap();
+        assert_eq!(prefix.body_length, msg.len() - (prefix.len_end + 1) - 7);
+        assert_eq!(builder.encoded_len(1, &additional_headers), msg.len());
+    }
+
+    #[test]
+    fn test_typed_push() {
+        use crate::fix::generated::Side;
+        use chrono::TimeZone;
+
+        let builder = MessageBuilder::new("FIX.4.2", 'D')
+            .push_int(Tags::OrderQty, 100)
+            .push_decimal(Tags::Price, 10.425, 2)
+            .push_decimal(Tags::StopPx, 9.0, 0)
+            .push_fix_decimal(Tags::AvgPx, FixDecimal::new(-231, 3))
+            .push_enum(Tags::Side, Side::SELL_SHORT)
+            .push_char(Tags::HandlInst, '1')
+            .push_bool(Tags::LocateReqd, true)
+            .push_utc_timestamp(
+                Tags::ExpireTime,
+                Utc.with_ymd_and_hms(2024, 1, 2, 15, 4, 5).unwrap() + chrono::Duration::milliseconds(7),
+            );
+        assert_eq!(
+            std::str::from_utf8(builder.main_buffer.get_ref()).unwrap(),
+            "38=100\x0144=10.43\x0199=9\x016=-0.231\x0154=5\x0121=1\x01114=Y\x01126=20240102-15:04:05.007\x01"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_push_group() {
+        use crate::fix::decode::{grouped_fields, GroupPosition, GroupSpec};
+
+        let builder = MessageBuilder::new("FIX.4.2", 'E')
+            .push(Tags::ListID, b"list")
+            .push_group(
+                Tags::NoOrders,
+                vec![
+                    GroupEntry::new()
+                        .push(Tags::ClOrdID, b"a")
+                        .push_group(
+                            Tags::NoAllocs,
+                            vec![
+                                GroupEntry::new().push(Tags::AllocAccount, b"A1"),
+                                GroupEntry::new().push(Tags::AllocAccount, b"A2"),
+                            ],
+                        )
+                        .push(Tags::Symbol, b"AAPL"),
+                    GroupEntry::new().push(Tags::ClOrdID, b"b"),
+                ],
+            )
+            .push(Tags::Text, b"done");
+        let mut buf = Vec::new();
+        let mut cur = tokio::io::BufWriter::new(&mut buf);
+        builder
+            .build_async(&mut cur, 1, &Default::default(), std::time::UNIX_EPOCH.into())
+            .await
+            .unwrap();
+        cur.flush().await.unwrap();
+        assert!(String::from_utf8_lossy(&buf)
+            .contains("66=list\x0173=2\x0111=a\x0178=2\x0179=A1\x0179=A2\x0155=AAPL\x0111=b\x0158=done\x01"));
+
+        let specs = [GroupSpec::new(Tags::NoOrders, Tags::ClOrdID, [Tags::Symbol])
+            .with_nested(GroupSpec::new(Tags::NoAllocs, Tags::AllocAccount, [Tags::AllocShares]))];
+        let at = |count_tag: Tags, entry, depth| {
+            Some(GroupPosition {
+                count_tag: count_tag.into(),
+                entry,
+                depth,
+            })
+        };
+        let positions: Vec<_> = grouped_fields(&buf, &specs)
+            .map(|field| field.map(|field| (field.tag, field.group)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            &positions[5..],
+            &[
+                (66, None),
+                (73, None),
+                (11, at(Tags::NoOrders, 0, 0)),
+                (78, at(Tags::NoOrders, 0, 0)),
+                (79, at(Tags::NoAllocs, 0, 1)),
+                (79, at(Tags::NoAllocs, 1, 1)),
+                (55, at(Tags::NoOrders, 0, 0)),
+                (11, at(Tags::NoOrders, 1, 0)),
+                (58, None),
+                (10, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let order = || {
+            MessageBuilder::new("FIX.4.2", 'D')
+                .push(Tags::ClOrdID, b"order-1")
+                .push(Tags::HandlInst, b"1")
+                .push(Tags::Symbol, b"AAPL")
+                .push(Tags::Side, b"1")
+                .push_deferred_time(Tags::TransactTime)
+        };
+        let tag = |builder: MessageBuilder| builder.validate().unwrap_err().tag;
+        assert_eq!(tag(order()), Some(Tags::OrdType.into()));
+        assert!(order().push(Tags::OrdType, b"2").validate().is_ok());
+        assert_eq!(tag(order().push(Tags::OrdType, b"Z")), Some(Tags::OrdType.into()));
+        assert_eq!(tag(order().push(Tags::OrdType, b"2").push(Tags::OrderQty, b"1e5")), Some(38));
+        // unknown fields are allowed, but header fields must not follow the body
+        assert!(order().push(Tags::OrdType, b"2").push(5001u32, b"x").validate().is_ok());
+        assert_eq!(
+            tag(order().push(Tags::OnBehalfOfCompID, b"desk").push(Tags::OrdType, b"2")),
+            Some(Tags::OnBehalfOfCompID.into())
+        );
+        assert_eq!(tag(MessageBuilder::new("FIX.4.2", '~')), Some(Tags::MsgType.into()));
+    }
+
     #[tokio::test]
     async fn test_checksum() {
         let datas = vec![
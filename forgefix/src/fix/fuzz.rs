@@ -0,0 +1,19 @@
+// Entry points for the cargo-fuzz targets in `forgefix/fuzz`, which build the crate with
+// `--cfg fuzzing`. Each one must never panic, whatever the input.
+
+use crate::fix::decode::{self, FieldIter, NullParserCallback};
+use crate::fix::checksum;
+
+pub fn field_iter(msg: &[u8]) {
+    for _ in FieldIter::new(msg) {}
+    let _ = decode::parse(msg, &mut NullParserCallback);
+}
+
+pub fn peeked_prefix(peeked: &[u8]) {
+    let _ = decode::parse_peeked_prefix(peeked);
+    let _ = decode::parse_header(peeked);
+}
+
+pub fn checksum(msg: &[u8]) {
+    let _ = checksum::checksum_is_valid(msg);
+}
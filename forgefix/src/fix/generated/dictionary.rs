@@ -0,0 +1,641 @@
+//! The FIX 4.2 dictionary: the type and values of each field, and the fields each message requires.
+
+/// The type of a field's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Amt,
+    Boolean,
+    Char,
+    Currency,
+    Data,
+    DayOfMonth,
+    Exchange,
+    Float,
+    Int,
+    Length,
+    LocalMktDate,
+    MonthYear,
+    MultipleValueString,
+    Price,
+    PriceOffset,
+    Qty,
+    String,
+    UtcDate,
+    UtcTimeOnly,
+    UtcTimestamp,
+}
+
+/// The [`FieldType`] of `tag`, or `None` if `tag` is not in the dictionary.
+pub fn field_type(tag: u32) -> Option<FieldType> {
+    match tag {
+        1 => Some(FieldType::String),
+        2 => Some(FieldType::String),
+        3 => Some(FieldType::String),
+        4 => Some(FieldType::Char),
+        5 => Some(FieldType::String),
+        6 => Some(FieldType::Price),
+        7 => Some(FieldType::Int),
+        8 => Some(FieldType::String),
+        9 => Some(FieldType::Int),
+        10 => Some(FieldType::String),
+        11 => Some(FieldType::String),
+        12 => Some(FieldType::Amt),
+        13 => Some(FieldType::Char),
+        14 => Some(FieldType::Qty),
+        15 => Some(FieldType::Currency),
+        16 => Some(FieldType::Int),
+        17 => Some(FieldType::String),
+        18 => Some(FieldType::MultipleValueString),
+        19 => Some(FieldType::String),
+        20 => Some(FieldType::Char),
+        21 => Some(FieldType::Char),
+        22 => Some(FieldType::String),
+        23 => Some(FieldType::String),
+        24 => Some(FieldType::Char),
+        25 => Some(FieldType::Char),
+        26 => Some(FieldType::String),
+        27 => Some(FieldType::String),
+        28 => Some(FieldType::Char),
+        29 => Some(FieldType::Char),
+        30 => Some(FieldType::Exchange),
+        31 => Some(FieldType::Price),
+        32 => Some(FieldType::Qty),
+        33 => Some(FieldType::Int),
+        34 => Some(FieldType::Int),
+        35 => Some(FieldType::Char),
+        36 => Some(FieldType::Int),
+        37 => Some(FieldType::String),
+        38 => Some(FieldType::Qty),
+        39 => Some(FieldType::Char),
+        40 => Some(FieldType::Char),
+        41 => Some(FieldType::String),
+        42 => Some(FieldType::UtcTimestamp),
+        43 => Some(FieldType::Boolean),
+        44 => Some(FieldType::Price),
+        45 => Some(FieldType::Int),
+        46 => Some(FieldType::String),
+        47 => Some(FieldType::Char),
+        48 => Some(FieldType::String),
+        49 => Some(FieldType::String),
+        50 => Some(FieldType::String),
+        51 => Some(FieldType::LocalMktDate),
+        52 => Some(FieldType::UtcTimestamp),
+        53 => Some(FieldType::Qty),
+        54 => Some(FieldType::Char),
+        55 => Some(FieldType::String),
+        56 => Some(FieldType::String),
+        57 => Some(FieldType::String),
+        58 => Some(FieldType::String),
+        59 => Some(FieldType::Char),
+        60 => Some(FieldType::UtcTimestamp),
+        61 => Some(FieldType::Char),
+        62 => Some(FieldType::UtcTimestamp),
+        63 => Some(FieldType::Char),
+        64 => Some(FieldType::LocalMktDate),
+        65 => Some(FieldType::String),
+        66 => Some(FieldType::String),
+        67 => Some(FieldType::Int),
+        68 => Some(FieldType::Int),
+        69 => Some(FieldType::String),
+        70 => Some(FieldType::String),
+        71 => Some(FieldType::Char),
+        72 => Some(FieldType::String),
+        73 => Some(FieldType::Int),
+        74 => Some(FieldType::Int),
+        75 => Some(FieldType::LocalMktDate),
+        76 => Some(FieldType::String),
+        77 => Some(FieldType::Char),
+        78 => Some(FieldType::Int),
+        79 => Some(FieldType::String),
+        80 => Some(FieldType::Qty),
+        81 => Some(FieldType::Char),
+        82 => Some(FieldType::Int),
+        83 => Some(FieldType::Int),
+        84 => Some(FieldType::Qty),
+        85 => Some(FieldType::Int),
+        86 => Some(FieldType::String),
+        87 => Some(FieldType::Int),
+        88 => Some(FieldType::Int),
+        89 => Some(FieldType::Data),
+        90 => Some(FieldType::Length),
+        91 => Some(FieldType::Data),
+        92 => Some(FieldType::String),
+        93 => Some(FieldType::Length),
+        94 => Some(FieldType::Char),
+        95 => Some(FieldType::Length),
+        96 => Some(FieldType::Data),
+        97 => Some(FieldType::Boolean),
+        98 => Some(FieldType::Int),
+        99 => Some(FieldType::Price),
+        100 => Some(FieldType::Exchange),
+        102 => Some(FieldType::Int),
+        103 => Some(FieldType::Int),
+        104 => Some(FieldType::Char),
+        105 => Some(FieldType::String),
+        106 => Some(FieldType::String),
+        107 => Some(FieldType::String),
+        108 => Some(FieldType::Int),
+        109 => Some(FieldType::String),
+        110 => Some(FieldType::Qty),
+        111 => Some(FieldType::Qty),
+        112 => Some(FieldType::String),
+        113 => Some(FieldType::Boolean),
+        114 => Some(FieldType::Boolean),
+        115 => Some(FieldType::String),
+        116 => Some(FieldType::String),
+        117 => Some(FieldType::String),
+        118 => Some(FieldType::Amt),
+        119 => Some(FieldType::Amt),
+        120 => Some(FieldType::Currency),
+        121 => Some(FieldType::Boolean),
+        122 => Some(FieldType::UtcTimestamp),
+        123 => Some(FieldType::Boolean),
+        124 => Some(FieldType::Int),
+        125 => Some(FieldType::Char),
+        126 => Some(FieldType::UtcTimestamp),
+        127 => Some(FieldType::Char),
+        128 => Some(FieldType::String),
+        129 => Some(FieldType::String),
+        130 => Some(FieldType::Boolean),
+        131 => Some(FieldType::String),
+        132 => Some(FieldType::Price),
+        133 => Some(FieldType::Price),
+        134 => Some(FieldType::Qty),
+        135 => Some(FieldType::Qty),
+        136 => Some(FieldType::Int),
+        137 => Some(FieldType::Amt),
+        138 => Some(FieldType::Currency),
+        139 => Some(FieldType::Char),
+        140 => Some(FieldType::Price),
+        141 => Some(FieldType::Boolean),
+        142 => Some(FieldType::String),
+        143 => Some(FieldType::String),
+        144 => Some(FieldType::String),
+        145 => Some(FieldType::String),
+        146 => Some(FieldType::Int),
+        147 => Some(FieldType::String),
+        148 => Some(FieldType::String),
+        149 => Some(FieldType::String),
+        150 => Some(FieldType::Char),
+        151 => Some(FieldType::Qty),
+        152 => Some(FieldType::Qty),
+        153 => Some(FieldType::Price),
+        154 => Some(FieldType::Amt),
+        155 => Some(FieldType::Float),
+        156 => Some(FieldType::Char),
+        157 => Some(FieldType::Int),
+        158 => Some(FieldType::Float),
+        159 => Some(FieldType::Amt),
+        160 => Some(FieldType::Char),
+        161 => Some(FieldType::String),
+        162 => Some(FieldType::String),
+        163 => Some(FieldType::Char),
+        164 => Some(FieldType::String),
+        165 => Some(FieldType::Char),
+        166 => Some(FieldType::String),
+        167 => Some(FieldType::String),
+        168 => Some(FieldType::UtcTimestamp),
+        169 => Some(FieldType::Int),
+        170 => Some(FieldType::String),
+        171 => Some(FieldType::String),
+        172 => Some(FieldType::Int),
+        173 => Some(FieldType::String),
+        174 => Some(FieldType::String),
+        175 => Some(FieldType::String),
+        176 => Some(FieldType::String),
+        177 => Some(FieldType::String),
+        178 => Some(FieldType::String),
+        179 => Some(FieldType::String),
+        180 => Some(FieldType::String),
+        181 => Some(FieldType::String),
+        182 => Some(FieldType::String),
+        183 => Some(FieldType::String),
+        184 => Some(FieldType::String),
+        185 => Some(FieldType::String),
+        186 => Some(FieldType::String),
+        187 => Some(FieldType::String),
+        188 => Some(FieldType::Price),
+        189 => Some(FieldType::PriceOffset),
+        190 => Some(FieldType::Price),
+        191 => Some(FieldType::PriceOffset),
+        192 => Some(FieldType::Qty),
+        193 => Some(FieldType::LocalMktDate),
+        194 => Some(FieldType::Price),
+        195 => Some(FieldType::PriceOffset),
+        196 => Some(FieldType::String),
+        197 => Some(FieldType::Int),
+        198 => Some(FieldType::String),
+        199 => Some(FieldType::Int),
+        200 => Some(FieldType::MonthYear),
+        201 => Some(FieldType::Int),
+        202 => Some(FieldType::Price),
+        203 => Some(FieldType::Int),
+        204 => Some(FieldType::Int),
+        205 => Some(FieldType::DayOfMonth),
+        206 => Some(FieldType::Char),
+        207 => Some(FieldType::Exchange),
+        208 => Some(FieldType::Boolean),
+        209 => Some(FieldType::Int),
+        210 => Some(FieldType::Qty),
+        211 => Some(FieldType::PriceOffset),
+        212 => Some(FieldType::Length),
+        213 => Some(FieldType::Data),
+        214 => Some(FieldType::String),
+        215 => Some(FieldType::Int),
+        216 => Some(FieldType::Int),
+        217 => Some(FieldType::String),
+        218 => Some(FieldType::PriceOffset),
+        219 => Some(FieldType::Char),
+        223 => Some(FieldType::Float),
+        231 => Some(FieldType::Float),
+        262 => Some(FieldType::String),
+        263 => Some(FieldType::Char),
+        264 => Some(FieldType::Int),
+        265 => Some(FieldType::Int),
+        266 => Some(FieldType::Boolean),
+        267 => Some(FieldType::Int),
+        268 => Some(FieldType::Int),
+        269 => Some(FieldType::Char),
+        270 => Some(FieldType::Price),
+        271 => Some(FieldType::Qty),
+        272 => Some(FieldType::UtcDate),
+        273 => Some(FieldType::UtcTimeOnly),
+        274 => Some(FieldType::Char),
+        275 => Some(FieldType::Exchange),
+        276 => Some(FieldType::MultipleValueString),
+        277 => Some(FieldType::MultipleValueString),
+        278 => Some(FieldType::String),
+        279 => Some(FieldType::Char),
+        280 => Some(FieldType::String),
+        281 => Some(FieldType::Char),
+        282 => Some(FieldType::String),
+        283 => Some(FieldType::String),
+        284 => Some(FieldType::String),
+        285 => Some(FieldType::Char),
+        286 => Some(FieldType::Char),
+        287 => Some(FieldType::Int),
+        288 => Some(FieldType::String),
+        289 => Some(FieldType::String),
+        290 => Some(FieldType::Int),
+        291 => Some(FieldType::Char),
+        292 => Some(FieldType::Char),
+        293 => Some(FieldType::Qty),
+        294 => Some(FieldType::Qty),
+        295 => Some(FieldType::Int),
+        296 => Some(FieldType::Int),
+        297 => Some(FieldType::Int),
+        298 => Some(FieldType::Int),
+        299 => Some(FieldType::String),
+        300 => Some(FieldType::Int),
+        301 => Some(FieldType::Int),
+        302 => Some(FieldType::String),
+        303 => Some(FieldType::Int),
+        304 => Some(FieldType::Int),
+        305 => Some(FieldType::String),
+        306 => Some(FieldType::String),
+        307 => Some(FieldType::String),
+        308 => Some(FieldType::Exchange),
+        309 => Some(FieldType::String),
+        310 => Some(FieldType::String),
+        311 => Some(FieldType::String),
+        312 => Some(FieldType::String),
+        313 => Some(FieldType::MonthYear),
+        314 => Some(FieldType::DayOfMonth),
+        315 => Some(FieldType::Int),
+        316 => Some(FieldType::Price),
+        317 => Some(FieldType::Char),
+        318 => Some(FieldType::Currency),
+        319 => Some(FieldType::Qty),
+        320 => Some(FieldType::String),
+        321 => Some(FieldType::Int),
+        322 => Some(FieldType::String),
+        323 => Some(FieldType::Int),
+        324 => Some(FieldType::String),
+        325 => Some(FieldType::Boolean),
+        326 => Some(FieldType::Int),
+        327 => Some(FieldType::Char),
+        328 => Some(FieldType::Boolean),
+        329 => Some(FieldType::Boolean),
+        330 => Some(FieldType::Qty),
+        331 => Some(FieldType::Qty),
+        332 => Some(FieldType::Price),
+        333 => Some(FieldType::Price),
+        334 => Some(FieldType::Int),
+        335 => Some(FieldType::String),
+        336 => Some(FieldType::String),
+        337 => Some(FieldType::String),
+        338 => Some(FieldType::Int),
+        339 => Some(FieldType::Int),
+        340 => Some(FieldType::Int),
+        341 => Some(FieldType::UtcTimestamp),
+        342 => Some(FieldType::UtcTimestamp),
+        343 => Some(FieldType::UtcTimestamp),
+        344 => Some(FieldType::UtcTimestamp),
+        345 => Some(FieldType::UtcTimestamp),
+        346 => Some(FieldType::Int),
+        347 => Some(FieldType::String),
+        348 => Some(FieldType::Length),
+        349 => Some(FieldType::Data),
+        350 => Some(FieldType::Length),
+        351 => Some(FieldType::Data),
+        352 => Some(FieldType::Length),
+        353 => Some(FieldType::Data),
+        354 => Some(FieldType::Length),
+        355 => Some(FieldType::Data),
+        356 => Some(FieldType::Length),
+        357 => Some(FieldType::Data),
+        358 => Some(FieldType::Length),
+        359 => Some(FieldType::Data),
+        360 => Some(FieldType::Length),
+        361 => Some(FieldType::Data),
+        362 => Some(FieldType::Length),
+        363 => Some(FieldType::Data),
+        364 => Some(FieldType::Length),
+        365 => Some(FieldType::Data),
+        366 => Some(FieldType::Price),
+        367 => Some(FieldType::UtcTimestamp),
+        368 => Some(FieldType::Int),
+        369 => Some(FieldType::Int),
+        370 => Some(FieldType::UtcTimestamp),
+        371 => Some(FieldType::Int),
+        372 => Some(FieldType::String),
+        373 => Some(FieldType::Int),
+        374 => Some(FieldType::Char),
+        375 => Some(FieldType::String),
+        376 => Some(FieldType::String),
+        377 => Some(FieldType::Boolean),
+        378 => Some(FieldType::Int),
+        379 => Some(FieldType::String),
+        380 => Some(FieldType::Int),
+        381 => Some(FieldType::Amt),
+        382 => Some(FieldType::Int),
+        383 => Some(FieldType::Int),
+        384 => Some(FieldType::Int),
+        385 => Some(FieldType::Char),
+        386 => Some(FieldType::Int),
+        387 => Some(FieldType::Qty),
+        388 => Some(FieldType::Char),
+        389 => Some(FieldType::PriceOffset),
+        390 => Some(FieldType::String),
+        391 => Some(FieldType::String),
+        392 => Some(FieldType::String),
+        393 => Some(FieldType::Int),
+        394 => Some(FieldType::Int),
+        395 => Some(FieldType::Int),
+        396 => Some(FieldType::Amt),
+        397 => Some(FieldType::Amt),
+        398 => Some(FieldType::Int),
+        399 => Some(FieldType::Int),
+        400 => Some(FieldType::String),
+        401 => Some(FieldType::Int),
+        402 => Some(FieldType::Float),
+        403 => Some(FieldType::Float),
+        404 => Some(FieldType::Amt),
+        405 => Some(FieldType::Float),
+        406 => Some(FieldType::Amt),
+        407 => Some(FieldType::Float),
+        408 => Some(FieldType::Amt),
+        409 => Some(FieldType::Int),
+        410 => Some(FieldType::Float),
+        411 => Some(FieldType::Boolean),
+        412 => Some(FieldType::Amt),
+        413 => Some(FieldType::Float),
+        414 => Some(FieldType::Int),
+        415 => Some(FieldType::Int),
+        416 => Some(FieldType::Int),
+        417 => Some(FieldType::Int),
+        418 => Some(FieldType::Char),
+        419 => Some(FieldType::Char),
+        420 => Some(FieldType::Int),
+        421 => Some(FieldType::String),
+        422 => Some(FieldType::Int),
+        423 => Some(FieldType::Int),
+        424 => Some(FieldType::Qty),
+        425 => Some(FieldType::Qty),
+        426 => Some(FieldType::Price),
+        427 => Some(FieldType::Int),
+        428 => Some(FieldType::Int),
+        429 => Some(FieldType::Int),
+        430 => Some(FieldType::Int),
+        431 => Some(FieldType::Int),
+        432 => Some(FieldType::LocalMktDate),
+        433 => Some(FieldType::Char),
+        434 => Some(FieldType::Char),
+        435 => Some(FieldType::Float),
+        436 => Some(FieldType::Float),
+        437 => Some(FieldType::Qty),
+        438 => Some(FieldType::UtcTimestamp),
+        439 => Some(FieldType::String),
+        440 => Some(FieldType::String),
+        441 => Some(FieldType::Int),
+        442 => Some(FieldType::Char),
+        443 => Some(FieldType::UtcTimestamp),
+        444 => Some(FieldType::String),
+        445 => Some(FieldType::Length),
+        446 => Some(FieldType::Data),
+        _ => None,
+    }
+}
+
+/// The values `tag` is restricted to, or `None` if its values are not enumerated.
+pub fn field_values(tag: u32) -> Option<&'static [&'static str]> {
+    match tag {
+        4 => Some(&["B", "S", "T", "X"]),
+        5 => Some(&["C", "N", "R"]),
+        13 => Some(&["1", "2", "3"]),
+        18 => Some(&[
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E", "F", "G",
+            "I", "L", "M", "N", "O", "P", "R", "S", "T", "U", "V", "W",
+        ]),
+        20 => Some(&["0", "1", "2", "3"]),
+        21 => Some(&["1", "2", "3"]),
+        22 => Some(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "J"]),
+        25 => Some(&["H", "L", "M"]),
+        27 => Some(&["L", "M", "S"]),
+        28 => Some(&["C", "N", "R"]),
+        29 => Some(&["1", "2", "3", "4"]),
+        35 => Some(&[
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "a", "A", "B", "b", "C", "c", "D",
+            "d", "E", "e", "f", "F", "G", "g", "H", "h", "i", "j", "J", "K", "k", "l", "L", "m",
+            "M", "N", "P", "Q", "R", "S", "T", "V", "W", "X", "Y", "Z",
+        ]),
+        39 => Some(&[
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E",
+        ]),
+        40 => Some(&[
+            "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E", "F", "G", "H",
+            "I", "P",
+        ]),
+        43 => Some(&["N", "Y"]),
+        47 => Some(&[
+            "A", "B", "C", "D", "E", "F", "H", "I", "J", "K", "L", "M", "N", "O", "P", "R", "S",
+            "T", "U", "W", "X", "Y", "Z",
+        ]),
+        54 => Some(&["1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        59 => Some(&["0", "1", "2", "3", "4", "5", "6"]),
+        61 => Some(&["0", "1", "2"]),
+        63 => Some(&["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        71 => Some(&["0", "1", "2", "3", "4", "5"]),
+        77 => Some(&["C", "O"]),
+        81 => Some(&["0", "1", "2", "3", "4", "5", "6"]),
+        87 => Some(&["0", "1", "2", "3"]),
+        88 => Some(&["0", "1", "2", "3", "4", "5", "6", "7"]),
+        94 => Some(&["0", "1", "2"]),
+        97 => Some(&["N", "Y"]),
+        98 => Some(&["0", "1", "2", "3", "4", "5", "6"]),
+        102 => Some(&["0", "1", "2", "3"]),
+        103 => Some(&["0", "1", "2", "3", "4", "5", "6", "7", "8"]),
+        104 => Some(&[
+            "A", "C", "I", "L", "M", "O", "P", "Q", "R", "S", "T", "V", "W", "X", "Y", "Z",
+        ]),
+        113 => Some(&["N", "Y"]),
+        114 => Some(&["N", "Y"]),
+        121 => Some(&["N", "Y"]),
+        123 => Some(&["N", "Y"]),
+        127 => Some(&["A", "B", "C", "D", "E", "Z"]),
+        130 => Some(&["N", "Y"]),
+        139 => Some(&["1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        141 => Some(&["N", "Y"]),
+        150 => Some(&[
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E",
+        ]),
+        156 => Some(&["M", "D"]),
+        160 => Some(&["0", "1", "2", "3"]),
+        163 => Some(&["C", "N", "R"]),
+        165 => Some(&["1", "2"]),
+        166 => Some(&["CED", "DTC", "EUR", "FED", "ISO Country Code", "PNY", "PTC"]),
+        167 => Some(&[
+            "?", "BA", "CB", "CD", "CMO", "CORP", "CP", "CPP", "CS", "FHA", "FHL", "FN", "FOR",
+            "FUT", "GN", "GOVT", "IET", "MF", "MIO", "MPO", "MPP", "MPT", "MUNI", "NONE", "OPT",
+            "PS", "RP", "RVRP", "SL", "TD", "USTB", "WAR", "ZOO",
+        ]),
+        169 => Some(&["0", "1", "2", "3"]),
+        197 => Some(&["0", "1"]),
+        201 => Some(&["0", "1"]),
+        203 => Some(&["0", "1"]),
+        204 => Some(&["0", "1"]),
+        208 => Some(&["N", "Y"]),
+        209 => Some(&["1", "2", "3"]),
+        216 => Some(&["1", "2", "3", "4"]),
+        219 => Some(&["1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        263 => Some(&["0", "1", "2"]),
+        265 => Some(&["0", "1"]),
+        266 => Some(&["N", "Y"]),
+        269 => Some(&["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        274 => Some(&["0", "1", "2", "3"]),
+        276 => Some(&["A", "B", "C", "D", "E", "F", "G", "H", "I"]),
+        277 => Some(&[
+            "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N",
+        ]),
+        279 => Some(&["0", "1", "2"]),
+        281 => Some(&["0", "1", "2", "3", "4", "5", "6", "7", "8"]),
+        285 => Some(&["0", "1"]),
+        286 => Some(&["0", "1", "2"]),
+        291 => Some(&["1"]),
+        292 => Some(&["A", "B", "C", "D", "E"]),
+        297 => Some(&["0", "1", "2", "3", "4", "5"]),
+        298 => Some(&["1", "2", "3", "4"]),
+        300 => Some(&["1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        301 => Some(&["0", "1", "2"]),
+        303 => Some(&["1", "2"]),
+        321 => Some(&["0", "1", "2", "3"]),
+        323 => Some(&["1", "2", "3", "4", "5", "6"]),
+        325 => Some(&["N", "Y"]),
+        326 => Some(&[
+            "1", "10", "12", "13", "14", "15", "16", "17", "18", "19", "2", "20", "3", "4", "5",
+            "6", "7", "8", "9",
+        ]),
+        327 => Some(&["D", "E", "I", "M", "P", "X"]),
+        328 => Some(&["N", "Y"]),
+        329 => Some(&["N", "Y"]),
+        334 => Some(&["1", "2", "3"]),
+        338 => Some(&["1", "2", "3"]),
+        339 => Some(&["1", "2", "3"]),
+        340 => Some(&["1", "2", "3", "4", "5"]),
+        347 => Some(&["EUC-JP", "ISO-2022-JP", "SHIFT_JIS", "UTF-8"]),
+        368 => Some(&["1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        373 => Some(&["0", "1", "10", "11", "2", "3", "4", "5", "6", "7", "8", "9"]),
+        374 => Some(&["C", "N"]),
+        377 => Some(&["N", "Y"]),
+        378 => Some(&["0", "1", "2", "3", "4", "5"]),
+        380 => Some(&["0", "1", "2", "3", "4", "5"]),
+        385 => Some(&["R", "S"]),
+        388 => Some(&["0", "1", "2", "3", "4", "5"]),
+        409 => Some(&["1", "2", "3", "4"]),
+        411 => Some(&["N", "Y"]),
+        414 => Some(&["1", "2", "3"]),
+        416 => Some(&["1", "2"]),
+        418 => Some(&["A", "G", "J", "R"]),
+        419 => Some(&[
+            "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "Z",
+        ]),
+        423 => Some(&["1", "2", "3"]),
+        427 => Some(&["0", "1", "2"]),
+        430 => Some(&["1", "2"]),
+        433 => Some(&["1", "2"]),
+        434 => Some(&["1", "2"]),
+        442 => Some(&["1", "2", "3"]),
+        _ => None,
+    }
+}
+
+/// The fields required in the standard header of every message.
+pub const HEADER_REQUIRED_FIELDS: &[u32] = &[8, 9, 35, 49, 56, 34, 52];
+
+/// The fields required in the standard trailer of every message.
+pub const TRAILER_REQUIRED_FIELDS: &[u32] = &[10];
+
+/// The body fields required by `msg_type`, or `None` if `msg_type` is not in the dictionary.
+///
+/// Fields of repeating groups are not included.
+pub fn required_fields(msg_type: &[u8]) -> Option<&'static [u32]> {
+    match msg_type {
+        b"0" => Some(&[]),
+        b"1" => Some(&[112]),
+        b"2" => Some(&[7, 16]),
+        b"3" => Some(&[45]),
+        b"4" => Some(&[36]),
+        b"5" => Some(&[]),
+        b"6" => Some(&[23, 28, 55, 54, 27]),
+        b"7" => Some(&[2, 5, 55, 4, 53]),
+        b"8" => Some(&[37, 17, 20, 150, 39, 55, 54, 151, 14, 6]),
+        b"9" => Some(&[37, 11, 41, 39, 434]),
+        b"A" => Some(&[98, 108]),
+        b"B" => Some(&[148]),
+        b"C" => Some(&[164, 94, 147]),
+        b"D" => Some(&[11, 21, 55, 54, 60, 40]),
+        b"E" => Some(&[66, 394, 68]),
+        b"F" => Some(&[41, 11, 55, 54, 60]),
+        b"G" => Some(&[41, 11, 21, 55, 54, 60, 40]),
+        b"H" => Some(&[11, 55, 54]),
+        b"J" => Some(&[70, 71, 54, 55, 53, 6, 75]),
+        b"K" => Some(&[66, 60]),
+        b"L" => Some(&[66, 60]),
+        b"M" => Some(&[66]),
+        b"N" => Some(&[66, 429, 82, 431, 83, 68]),
+        b"P" => Some(&[70, 75, 87]),
+        b"Q" => Some(&[37, 17, 127, 55, 54]),
+        b"R" => Some(&[131]),
+        b"S" => Some(&[117, 55]),
+        b"T" => Some(&[162, 163, 214, 160, 165, 79, 60]),
+        b"V" => Some(&[262, 263, 264]),
+        b"W" => Some(&[55]),
+        b"X" => Some(&[]),
+        b"Y" => Some(&[262]),
+        b"Z" => Some(&[117, 298]),
+        b"a" => Some(&[55]),
+        b"b" => Some(&[297]),
+        b"c" => Some(&[320, 321]),
+        b"d" => Some(&[320, 322, 393]),
+        b"e" => Some(&[324, 55, 263]),
+        b"f" => Some(&[55]),
+        b"g" => Some(&[335, 263]),
+        b"h" => Some(&[336, 340]),
+        b"i" => Some(&[117]),
+        b"j" => Some(&[372, 380]),
+        b"k" => Some(&[391, 374, 393, 394, 418, 419]),
+        b"l" => Some(&[]),
+        b"m" => Some(&[66, 422]),
+        _ => None,
+    }
+}
@@ -0,0 +1,564 @@
+use super::*;
+use crate::fix::decimal::FixDecimal;
+use crate::fix::decode::{
+    parse, parse_field, parse_sending_time, DecodeError, MessageParseError, ParserCallback,
+};
+use crate::fix::mem::MsgBuf;
+
+use chrono::{DateTime, Utc};
+
+/// An `ExecutionReport<8>` message, decoded from a [`MsgBuf`] with `TryFrom`.
+///
+/// Fields required by FIX 4.2 are always present, and the others are `None` when the message does
+/// not contain them. Prices and quantities are [`FixDecimal`]s, with the digits the peer sent. The
+/// fields of repeating groups are not decoded.
+#[derive(Debug)]
+pub struct ExecutionReport<'a> {
+    /// `OrderID(37)`
+    pub order_id: &'a str,
+    /// `SecondaryOrderID(198)`
+    pub secondary_order_id: Option<&'a str>,
+    /// `ClOrdID(11)`
+    pub cl_ord_id: Option<&'a str>,
+    /// `OrigClOrdID(41)`
+    pub orig_cl_ord_id: Option<&'a str>,
+    /// `ClientID(109)`
+    pub client_id: Option<&'a str>,
+    /// `ExecBroker(76)`
+    pub exec_broker: Option<&'a str>,
+    /// `ListID(66)`
+    pub list_id: Option<&'a str>,
+    /// `ExecID(17)`
+    pub exec_id: &'a str,
+    /// `ExecTransType(20)`
+    pub exec_trans_type: ExecTransType,
+    /// `ExecRefID(19)`
+    pub exec_ref_id: Option<&'a str>,
+    /// `ExecType(150)`
+    pub exec_type: ExecType,
+    /// `OrdStatus(39)`
+    pub ord_status: OrdStatus,
+    /// `OrdRejReason(103)`
+    pub ord_rej_reason: Option<OrdRejReason>,
+    /// `ExecRestatementReason(378)`
+    pub exec_restatement_reason: Option<ExecRestatementReason>,
+    /// `Account(1)`
+    pub account: Option<&'a str>,
+    /// `SettlmntTyp(63)`
+    pub settlmnt_typ: Option<SettlmntTyp>,
+    /// `FutSettDate(64)`
+    pub fut_sett_date: Option<&'a str>,
+    /// `Symbol(55)`
+    pub symbol: &'a str,
+    /// `SymbolSfx(65)`
+    pub symbol_sfx: Option<&'a str>,
+    /// `SecurityID(48)`
+    pub security_id: Option<&'a str>,
+    /// `IDSource(22)`
+    pub id_source: Option<&'a str>,
+    /// `SecurityType(167)`
+    pub security_type: Option<&'a str>,
+    /// `MaturityMonthYear(200)`
+    pub maturity_month_year: Option<&'a str>,
+    /// `MaturityDay(205)`
+    pub maturity_day: Option<i64>,
+    /// `PutOrCall(201)`
+    pub put_or_call: Option<PutOrCall>,
+    /// `StrikePrice(202)`
+    pub strike_price: Option<FixDecimal>,
+    /// `OptAttribute(206)`
+    pub opt_attribute: Option<char>,
+    /// `ContractMultiplier(231)`
+    pub contract_multiplier: Option<FixDecimal>,
+    /// `CouponRate(223)`
+    pub coupon_rate: Option<FixDecimal>,
+    /// `SecurityExchange(207)`
+    pub security_exchange: Option<&'a str>,
+    /// `Issuer(106)`
+    pub issuer: Option<&'a str>,
+    /// `EncodedIssuerLen(348)`
+    pub encoded_issuer_len: Option<u32>,
+    /// `EncodedIssuer(349)`
+    pub encoded_issuer: Option<&'a [u8]>,
+    /// `SecurityDesc(107)`
+    pub security_desc: Option<&'a str>,
+    /// `EncodedSecurityDescLen(350)`
+    pub encoded_security_desc_len: Option<u32>,
+    /// `EncodedSecurityDesc(351)`
+    pub encoded_security_desc: Option<&'a [u8]>,
+    /// `Side(54)`
+    pub side: Side,
+    /// `OrderQty(38)`
+    pub order_qty: Option<FixDecimal>,
+    /// `CashOrderQty(152)`
+    pub cash_order_qty: Option<FixDecimal>,
+    /// `OrdType(40)`
+    pub ord_type: Option<OrdType>,
+    /// `Price(44)`
+    pub price: Option<FixDecimal>,
+    /// `StopPx(99)`
+    pub stop_px: Option<FixDecimal>,
+    /// `PegDifference(211)`
+    pub peg_difference: Option<FixDecimal>,
+    /// `DiscretionInst(388)`
+    pub discretion_inst: Option<DiscretionInst>,
+    /// `DiscretionOffset(389)`
+    pub discretion_offset: Option<FixDecimal>,
+    /// `Currency(15)`
+    pub currency: Option<&'a str>,
+    /// `ComplianceID(376)`
+    pub compliance_id: Option<&'a str>,
+    /// `SolicitedFlag(377)`
+    pub solicited_flag: Option<SolicitedFlag>,
+    /// `TimeInForce(59)`
+    pub time_in_force: Option<TimeInForce>,
+    /// `EffectiveTime(168)`
+    pub effective_time: Option<DateTime<Utc>>,
+    /// `ExpireDate(432)`
+    pub expire_date: Option<&'a str>,
+    /// `ExpireTime(126)`
+    pub expire_time: Option<DateTime<Utc>>,
+    /// `ExecInst(18)`
+    pub exec_inst: Option<&'a str>,
+    /// `Rule80A(47)`
+    pub rule80_a: Option<Rule80A>,
+    /// `LastShares(32)`
+    pub last_shares: Option<FixDecimal>,
+    /// `LastPx(31)`
+    pub last_px: Option<FixDecimal>,
+    /// `LastSpotRate(194)`
+    pub last_spot_rate: Option<FixDecimal>,
+    /// `LastForwardPoints(195)`
+    pub last_forward_points: Option<FixDecimal>,
+    /// `LastMkt(30)`
+    pub last_mkt: Option<&'a str>,
+    /// `TradingSessionID(336)`
+    pub trading_session_id: Option<&'a str>,
+    /// `LastCapacity(29)`
+    pub last_capacity: Option<LastCapacity>,
+    /// `LeavesQty(151)`
+    pub leaves_qty: FixDecimal,
+    /// `CumQty(14)`
+    pub cum_qty: FixDecimal,
+    /// `AvgPx(6)`
+    pub avg_px: FixDecimal,
+    /// `DayOrderQty(424)`
+    pub day_order_qty: Option<FixDecimal>,
+    /// `DayCumQty(425)`
+    pub day_cum_qty: Option<FixDecimal>,
+    /// `DayAvgPx(426)`
+    pub day_avg_px: Option<FixDecimal>,
+    /// `GTBookingInst(427)`
+    pub gt_booking_inst: Option<GTBookingInst>,
+    /// `TradeDate(75)`
+    pub trade_date: Option<&'a str>,
+    /// `TransactTime(60)`
+    pub transact_time: Option<DateTime<Utc>>,
+    /// `ReportToExch(113)`
+    pub report_to_exch: Option<ReportToExch>,
+    /// `Commission(12)`
+    pub commission: Option<FixDecimal>,
+    /// `CommType(13)`
+    pub comm_type: Option<CommType>,
+    /// `GrossTradeAmt(381)`
+    pub gross_trade_amt: Option<FixDecimal>,
+    /// `SettlCurrAmt(119)`
+    pub settl_curr_amt: Option<FixDecimal>,
+    /// `SettlCurrency(120)`
+    pub settl_currency: Option<&'a str>,
+    /// `SettlCurrFxRate(155)`
+    pub settl_curr_fx_rate: Option<FixDecimal>,
+    /// `SettlCurrFxRateCalc(156)`
+    pub settl_curr_fx_rate_calc: Option<SettlCurrFxRateCalc>,
+    /// `HandlInst(21)`
+    pub handl_inst: Option<HandlInst>,
+    /// `MinQty(110)`
+    pub min_qty: Option<FixDecimal>,
+    /// `MaxFloor(111)`
+    pub max_floor: Option<FixDecimal>,
+    /// `OpenClose(77)`
+    pub open_close: Option<OpenClose>,
+    /// `MaxShow(210)`
+    pub max_show: Option<FixDecimal>,
+    /// `Text(58)`
+    pub text: Option<&'a str>,
+    /// `EncodedTextLen(354)`
+    pub encoded_text_len: Option<u32>,
+    /// `EncodedText(355)`
+    pub encoded_text: Option<&'a [u8]>,
+    /// `FutSettDate2(193)`
+    pub fut_sett_date2: Option<&'a str>,
+    /// `OrderQty2(192)`
+    pub order_qty2: Option<FixDecimal>,
+    /// `ClearingFirm(439)`
+    pub clearing_firm: Option<&'a str>,
+    /// `ClearingAccount(440)`
+    pub clearing_account: Option<&'a str>,
+    /// `MultiLegReportingType(442)`
+    pub multi_leg_reporting_type: Option<MultiLegReportingType>,
+}
+
+impl<'a> TryFrom<&'a MsgBuf> for ExecutionReport<'a> {
+    type Error = DecodeError;
+    fn try_from(msg: &'a MsgBuf) -> Result<Self, Self::Error> {
+        let mut fields = ExecutionReportFields::default();
+        parse(&msg[..], &mut fields)?;
+        Ok(ExecutionReport {
+            order_id: fields
+                .order_id
+                .ok_or(DecodeError::MissingTag(Tags::OrderID))?,
+            secondary_order_id: fields.secondary_order_id,
+            cl_ord_id: fields.cl_ord_id,
+            orig_cl_ord_id: fields.orig_cl_ord_id,
+            client_id: fields.client_id,
+            exec_broker: fields.exec_broker,
+            list_id: fields.list_id,
+            exec_id: fields
+                .exec_id
+                .ok_or(DecodeError::MissingTag(Tags::ExecID))?,
+            exec_trans_type: fields
+                .exec_trans_type
+                .ok_or(DecodeError::MissingTag(Tags::ExecTransType))?,
+            exec_ref_id: fields.exec_ref_id,
+            exec_type: fields
+                .exec_type
+                .ok_or(DecodeError::MissingTag(Tags::ExecType))?,
+            ord_status: fields
+                .ord_status
+                .ok_or(DecodeError::MissingTag(Tags::OrdStatus))?,
+            ord_rej_reason: fields.ord_rej_reason,
+            exec_restatement_reason: fields.exec_restatement_reason,
+            account: fields.account,
+            settlmnt_typ: fields.settlmnt_typ,
+            fut_sett_date: fields.fut_sett_date,
+            symbol: fields.symbol.ok_or(DecodeError::MissingTag(Tags::Symbol))?,
+            symbol_sfx: fields.symbol_sfx,
+            security_id: fields.security_id,
+            id_source: fields.id_source,
+            security_type: fields.security_type,
+            maturity_month_year: fields.maturity_month_year,
+            maturity_day: fields.maturity_day,
+            put_or_call: fields.put_or_call,
+            strike_price: fields.strike_price,
+            opt_attribute: fields.opt_attribute,
+            contract_multiplier: fields.contract_multiplier,
+            coupon_rate: fields.coupon_rate,
+            security_exchange: fields.security_exchange,
+            issuer: fields.issuer,
+            encoded_issuer_len: fields.encoded_issuer_len,
+            encoded_issuer: fields.encoded_issuer,
+            security_desc: fields.security_desc,
+            encoded_security_desc_len: fields.encoded_security_desc_len,
+            encoded_security_desc: fields.encoded_security_desc,
+            side: fields.side.ok_or(DecodeError::MissingTag(Tags::Side))?,
+            order_qty: fields.order_qty,
+            cash_order_qty: fields.cash_order_qty,
+            ord_type: fields.ord_type,
+            price: fields.price,
+            stop_px: fields.stop_px,
+            peg_difference: fields.peg_difference,
+            discretion_inst: fields.discretion_inst,
+            discretion_offset: fields.discretion_offset,
+            currency: fields.currency,
+            compliance_id: fields.compliance_id,
+            solicited_flag: fields.solicited_flag,
+            time_in_force: fields.time_in_force,
+            effective_time: fields.effective_time,
+            expire_date: fields.expire_date,
+            expire_time: fields.expire_time,
+            exec_inst: fields.exec_inst,
+            rule80_a: fields.rule80_a,
+            last_shares: fields.last_shares,
+            last_px: fields.last_px,
+            last_spot_rate: fields.last_spot_rate,
+            last_forward_points: fields.last_forward_points,
+            last_mkt: fields.last_mkt,
+            trading_session_id: fields.trading_session_id,
+            last_capacity: fields.last_capacity,
+            leaves_qty: fields
+                .leaves_qty
+                .ok_or(DecodeError::MissingTag(Tags::LeavesQty))?,
+            cum_qty: fields
+                .cum_qty
+                .ok_or(DecodeError::MissingTag(Tags::CumQty))?,
+            avg_px: fields.avg_px.ok_or(DecodeError::MissingTag(Tags::AvgPx))?,
+            day_order_qty: fields.day_order_qty,
+            day_cum_qty: fields.day_cum_qty,
+            day_avg_px: fields.day_avg_px,
+            gt_booking_inst: fields.gt_booking_inst,
+            trade_date: fields.trade_date,
+            transact_time: fields.transact_time,
+            report_to_exch: fields.report_to_exch,
+            commission: fields.commission,
+            comm_type: fields.comm_type,
+            gross_trade_amt: fields.gross_trade_amt,
+            settl_curr_amt: fields.settl_curr_amt,
+            settl_currency: fields.settl_currency,
+            settl_curr_fx_rate: fields.settl_curr_fx_rate,
+            settl_curr_fx_rate_calc: fields.settl_curr_fx_rate_calc,
+            handl_inst: fields.handl_inst,
+            min_qty: fields.min_qty,
+            max_floor: fields.max_floor,
+            open_close: fields.open_close,
+            max_show: fields.max_show,
+            text: fields.text,
+            encoded_text_len: fields.encoded_text_len,
+            encoded_text: fields.encoded_text,
+            fut_sett_date2: fields.fut_sett_date2,
+            order_qty2: fields.order_qty2,
+            clearing_firm: fields.clearing_firm,
+            clearing_account: fields.clearing_account,
+            multi_leg_reporting_type: fields.multi_leg_reporting_type,
+        })
+    }
+}
+
+#[derive(Default)]
+struct ExecutionReportFields<'a> {
+    order_id: Option<&'a str>,
+    secondary_order_id: Option<&'a str>,
+    cl_ord_id: Option<&'a str>,
+    orig_cl_ord_id: Option<&'a str>,
+    client_id: Option<&'a str>,
+    exec_broker: Option<&'a str>,
+    list_id: Option<&'a str>,
+    exec_id: Option<&'a str>,
+    exec_trans_type: Option<ExecTransType>,
+    exec_ref_id: Option<&'a str>,
+    exec_type: Option<ExecType>,
+    ord_status: Option<OrdStatus>,
+    ord_rej_reason: Option<OrdRejReason>,
+    exec_restatement_reason: Option<ExecRestatementReason>,
+    account: Option<&'a str>,
+    settlmnt_typ: Option<SettlmntTyp>,
+    fut_sett_date: Option<&'a str>,
+    symbol: Option<&'a str>,
+    symbol_sfx: Option<&'a str>,
+    security_id: Option<&'a str>,
+    id_source: Option<&'a str>,
+    security_type: Option<&'a str>,
+    maturity_month_year: Option<&'a str>,
+    maturity_day: Option<i64>,
+    put_or_call: Option<PutOrCall>,
+    strike_price: Option<FixDecimal>,
+    opt_attribute: Option<char>,
+    contract_multiplier: Option<FixDecimal>,
+    coupon_rate: Option<FixDecimal>,
+    security_exchange: Option<&'a str>,
+    issuer: Option<&'a str>,
+    encoded_issuer_len: Option<u32>,
+    encoded_issuer: Option<&'a [u8]>,
+    security_desc: Option<&'a str>,
+    encoded_security_desc_len: Option<u32>,
+    encoded_security_desc: Option<&'a [u8]>,
+    side: Option<Side>,
+    order_qty: Option<FixDecimal>,
+    cash_order_qty: Option<FixDecimal>,
+    ord_type: Option<OrdType>,
+    price: Option<FixDecimal>,
+    stop_px: Option<FixDecimal>,
+    peg_difference: Option<FixDecimal>,
+    discretion_inst: Option<DiscretionInst>,
+    discretion_offset: Option<FixDecimal>,
+    currency: Option<&'a str>,
+    compliance_id: Option<&'a str>,
+    solicited_flag: Option<SolicitedFlag>,
+    time_in_force: Option<TimeInForce>,
+    effective_time: Option<DateTime<Utc>>,
+    expire_date: Option<&'a str>,
+    expire_time: Option<DateTime<Utc>>,
+    exec_inst: Option<&'a str>,
+    rule80_a: Option<Rule80A>,
+    last_shares: Option<FixDecimal>,
+    last_px: Option<FixDecimal>,
+    last_spot_rate: Option<FixDecimal>,
+    last_forward_points: Option<FixDecimal>,
+    last_mkt: Option<&'a str>,
+    trading_session_id: Option<&'a str>,
+    last_capacity: Option<LastCapacity>,
+    leaves_qty: Option<FixDecimal>,
+    cum_qty: Option<FixDecimal>,
+    avg_px: Option<FixDecimal>,
+    day_order_qty: Option<FixDecimal>,
+    day_cum_qty: Option<FixDecimal>,
+    day_avg_px: Option<FixDecimal>,
+    gt_booking_inst: Option<GTBookingInst>,
+    trade_date: Option<&'a str>,
+    transact_time: Option<DateTime<Utc>>,
+    report_to_exch: Option<ReportToExch>,
+    commission: Option<FixDecimal>,
+    comm_type: Option<CommType>,
+    gross_trade_amt: Option<FixDecimal>,
+    settl_curr_amt: Option<FixDecimal>,
+    settl_currency: Option<&'a str>,
+    settl_curr_fx_rate: Option<FixDecimal>,
+    settl_curr_fx_rate_calc: Option<SettlCurrFxRateCalc>,
+    handl_inst: Option<HandlInst>,
+    min_qty: Option<FixDecimal>,
+    max_floor: Option<FixDecimal>,
+    open_close: Option<OpenClose>,
+    max_show: Option<FixDecimal>,
+    text: Option<&'a str>,
+    encoded_text_len: Option<u32>,
+    encoded_text: Option<&'a [u8]>,
+    fut_sett_date2: Option<&'a str>,
+    order_qty2: Option<FixDecimal>,
+    clearing_firm: Option<&'a str>,
+    clearing_account: Option<&'a str>,
+    multi_leg_reporting_type: Option<MultiLegReportingType>,
+}
+
+impl<'a> ParserCallback<'a> for ExecutionReportFields<'a> {
+    type Err = DecodeError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let Ok(Tags::MsgType) = key.try_into() {
+            if value != b"8" {
+                return Err(DecodeError::UnexpectedMsgType(value.to_vec()));
+            }
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        match key.try_into() {
+            Ok(Tags::OrderID) => self.order_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SecondaryOrderID) => {
+                self.secondary_order_id = Some(std::str::from_utf8(value)?)
+            }
+            Ok(Tags::ClOrdID) => self.cl_ord_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::OrigClOrdID) => self.orig_cl_ord_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ClientID) => self.client_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ExecBroker) => self.exec_broker = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ListID) => self.list_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ExecID) => self.exec_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ExecTransType) => {
+                self.exec_trans_type = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::ExecRefID) => self.exec_ref_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ExecType) => self.exec_type = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::OrdStatus) => self.ord_status = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::OrdRejReason) => {
+                self.ord_rej_reason = Some(parse_field::<u8>(value)?.try_into()?)
+            }
+            Ok(Tags::ExecRestatementReason) => {
+                self.exec_restatement_reason = Some(parse_field::<u8>(value)?.try_into()?)
+            }
+            Ok(Tags::Account) => self.account = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SettlmntTyp) => {
+                self.settlmnt_typ = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::FutSettDate) => self.fut_sett_date = Some(std::str::from_utf8(value)?),
+            Ok(Tags::Symbol) => self.symbol = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SymbolSfx) => self.symbol_sfx = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SecurityID) => self.security_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::IDSource) => self.id_source = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SecurityType) => self.security_type = Some(std::str::from_utf8(value)?),
+            Ok(Tags::MaturityMonthYear) => {
+                self.maturity_month_year = Some(std::str::from_utf8(value)?)
+            }
+            Ok(Tags::MaturityDay) => self.maturity_day = Some(parse_field::<i64>(value)?),
+            Ok(Tags::PutOrCall) => self.put_or_call = Some(parse_field::<u8>(value)?.try_into()?),
+            Ok(Tags::StrikePrice) => self.strike_price = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::OptAttribute) => self.opt_attribute = Some(parse_field::<char>(value)?),
+            Ok(Tags::ContractMultiplier) => {
+                self.contract_multiplier = Some(parse_field::<FixDecimal>(value)?)
+            }
+            Ok(Tags::CouponRate) => self.coupon_rate = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::SecurityExchange) => {
+                self.security_exchange = Some(std::str::from_utf8(value)?)
+            }
+            Ok(Tags::Issuer) => self.issuer = Some(std::str::from_utf8(value)?),
+            Ok(Tags::EncodedIssuerLen) => {
+                self.encoded_issuer_len = Some(parse_field::<u32>(value)?)
+            }
+            Ok(Tags::EncodedIssuer) => self.encoded_issuer = Some(value),
+            Ok(Tags::SecurityDesc) => self.security_desc = Some(std::str::from_utf8(value)?),
+            Ok(Tags::EncodedSecurityDescLen) => {
+                self.encoded_security_desc_len = Some(parse_field::<u32>(value)?)
+            }
+            Ok(Tags::EncodedSecurityDesc) => self.encoded_security_desc = Some(value),
+            Ok(Tags::Side) => self.side = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::OrderQty) => self.order_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::CashOrderQty) => self.cash_order_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::OrdType) => self.ord_type = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::Price) => self.price = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::StopPx) => self.stop_px = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::PegDifference) => self.peg_difference = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::DiscretionInst) => {
+                self.discretion_inst = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::DiscretionOffset) => self.discretion_offset = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::Currency) => self.currency = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ComplianceID) => self.compliance_id = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SolicitedFlag) => {
+                self.solicited_flag = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::TimeInForce) => {
+                self.time_in_force = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::EffectiveTime) => self.effective_time = Some(parse_sending_time(value)?),
+            Ok(Tags::ExpireDate) => self.expire_date = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ExpireTime) => self.expire_time = Some(parse_sending_time(value)?),
+            Ok(Tags::ExecInst) => self.exec_inst = Some(std::str::from_utf8(value)?),
+            Ok(Tags::Rule80A) => self.rule80_a = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::LastShares) => self.last_shares = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::LastPx) => self.last_px = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::LastSpotRate) => self.last_spot_rate = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::LastForwardPoints) => {
+                self.last_forward_points = Some(parse_field::<FixDecimal>(value)?)
+            }
+            Ok(Tags::LastMkt) => self.last_mkt = Some(std::str::from_utf8(value)?),
+            Ok(Tags::TradingSessionID) => {
+                self.trading_session_id = Some(std::str::from_utf8(value)?)
+            }
+            Ok(Tags::LastCapacity) => {
+                self.last_capacity = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::LeavesQty) => self.leaves_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::CumQty) => self.cum_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::AvgPx) => self.avg_px = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::DayOrderQty) => self.day_order_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::DayCumQty) => self.day_cum_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::DayAvgPx) => self.day_avg_px = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::GTBookingInst) => {
+                self.gt_booking_inst = Some(parse_field::<u8>(value)?.try_into()?)
+            }
+            Ok(Tags::TradeDate) => self.trade_date = Some(std::str::from_utf8(value)?),
+            Ok(Tags::TransactTime) => self.transact_time = Some(parse_sending_time(value)?),
+            Ok(Tags::ReportToExch) => {
+                self.report_to_exch = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::Commission) => self.commission = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::CommType) => self.comm_type = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::GrossTradeAmt) => self.gross_trade_amt = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::SettlCurrAmt) => self.settl_curr_amt = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::SettlCurrency) => self.settl_currency = Some(std::str::from_utf8(value)?),
+            Ok(Tags::SettlCurrFxRate) => self.settl_curr_fx_rate = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::SettlCurrFxRateCalc) => {
+                self.settl_curr_fx_rate_calc = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            Ok(Tags::HandlInst) => self.handl_inst = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::MinQty) => self.min_qty = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::MaxFloor) => self.max_floor = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::OpenClose) => self.open_close = Some(parse_field::<char>(value)?.try_into()?),
+            Ok(Tags::MaxShow) => self.max_show = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::Text) => self.text = Some(std::str::from_utf8(value)?),
+            Ok(Tags::EncodedTextLen) => self.encoded_text_len = Some(parse_field::<u32>(value)?),
+            Ok(Tags::EncodedText) => self.encoded_text = Some(value),
+            Ok(Tags::FutSettDate2) => self.fut_sett_date2 = Some(std::str::from_utf8(value)?),
+            Ok(Tags::OrderQty2) => self.order_qty2 = Some(parse_field::<FixDecimal>(value)?),
+            Ok(Tags::ClearingFirm) => self.clearing_firm = Some(std::str::from_utf8(value)?),
+            Ok(Tags::ClearingAccount) => self.clearing_account = Some(std::str::from_utf8(value)?),
+            Ok(Tags::MultiLegReportingType) => {
+                self.multi_leg_reporting_type = Some(parse_field::<char>(value)?.try_into()?)
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(true)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err.into())
+    }
+}
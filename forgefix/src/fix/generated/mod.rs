@@ -1,6 +1,13 @@
 //! FIX [`Tags`], [`MsgType`], and values. 
+//!
+//! Also contains [`ExecutionReport`], a typed `ExecutionReport<8>` that can be decoded from a
+//! [`MsgBuf`](crate::fix::mem::MsgBuf) with `TryFrom`, and the [`dictionary`] of field types and
+//! required fields. 
 
+pub mod dictionary;
+mod execution_report;
 mod fields;
+pub use execution_report::ExecutionReport;
 pub use fields::*;
 impl MsgType {
     pub fn is_session(&self) -> bool {
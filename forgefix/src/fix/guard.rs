@@ -0,0 +1,135 @@
+// Checks that a connection accepted by an acceptor speaks FIX before an engine is created for it,
+// so port scanners and TLS clients connecting to a plaintext port are dropped without allocating a
+// store or a log. An acceptor with more than one session also peeks at the CompIDs of the first
+// message, to find the session of the connection.
+
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+
+// Every FIX message, and so every session, starts with `BeginString(8)`.
+const BANNER: &[u8] = b"8=FIX";
+
+// How long to wait for the rest of the banner once part of it was received, doubled after each
+// wait up to `MAX_PARTIAL_WAIT`. A peek returns at once while any data is unread, so the rest of a
+// partial message cannot be awaited.
+const PARTIAL_BANNER_WAIT: Duration = Duration::from_millis(1);
+const MAX_PARTIAL_WAIT: Duration = Duration::from_millis(50);
+
+// How much of the first message to peek at for its CompIDs, which are in the header.
+const HEADER_PEEK_LEN: usize = 512;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Sniff {
+    Fix,
+    // The connection sent something else, or was closed.
+    NotFix,
+    TimedOut,
+}
+
+// Peek at the first bytes of `stream`, without consuming them, and check that they start a FIX
+// message within `timeout`.
+pub(crate) async fn sniff(stream: &TcpStream, timeout: Duration) -> Sniff {
+    let mut buf = [0; BANNER.len()];
+    let peek = async {
+        let mut wait = PARTIAL_BANNER_WAIT;
+        loop {
+            match stream.peek(&mut buf).await {
+                Ok(0) | Err(_) => return Sniff::NotFix,
+                Ok(n) if buf[..n] != BANNER[..n] => return Sniff::NotFix,
+                Ok(n) if n == BANNER.len() => return Sniff::Fix,
+                Ok(_) => wait = backoff(wait).await,
+            }
+        }
+    };
+    tokio::time::timeout(timeout, peek)
+        .await
+        .unwrap_or(Sniff::TimedOut)
+}
+
+// Peek at the first message of `stream`, without consuming it, for its `SenderCompID(49)` and
+// `TargetCompID(56)`. `None` if the connection is closed, or does not send both within `timeout`.
+pub(crate) async fn peek_comp_ids(stream: &TcpStream, timeout: Duration) -> Option<(String, String)> {
+    let mut buf = [0; HEADER_PEEK_LEN];
+    let peek = async {
+        let mut peeked = 0;
+        let mut wait = PARTIAL_BANNER_WAIT;
+        loop {
+            match stream.peek(&mut buf).await {
+                Ok(0) | Err(_) => return None,
+                Ok(n) if n == peeked => wait = backoff(wait).await,
+                Ok(n) => {
+                    if let Some(comp_ids) = comp_ids(&buf[..n]) {
+                        return Some(comp_ids);
+                    }
+                    if n == buf.len() {
+                        return None;
+                    }
+                    peeked = n;
+                }
+            }
+        }
+    };
+    tokio::time::timeout(timeout, peek).await.ok().flatten()
+}
+
+// Sleep for `wait`, and return how long to wait the next time.
+async fn backoff(wait: Duration) -> Duration {
+    tokio::time::sleep(wait).await;
+    (wait * 2).min(MAX_PARTIAL_WAIT)
+}
+
+// The CompIDs of the complete fields of `header`, once both were received.
+fn comp_ids(header: &[u8]) -> Option<(String, String)> {
+    let mut sender_comp_id = None;
+    let mut target_comp_id = None;
+    let complete = &header[..=header.iter().rposition(|b| *b == b'\x01')?];
+    for field in complete.split(|b| *b == b'\x01') {
+        if let Some(value) = field.strip_prefix(b"49=") {
+            sender_comp_id = Some(String::from_utf8_lossy(value).into_owned());
+        } else if let Some(value) = field.strip_prefix(b"56=") {
+            target_comp_id = Some(String::from_utf8_lossy(value).into_owned());
+        }
+    }
+    Some((sender_comp_id?, target_comp_id?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    async fn sniff_with(first: &[u8], second: &[u8]) -> Sniff {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        client.write_all(first).await.unwrap();
+        let sniffed = tokio::spawn(async move { sniff(&server, Duration::from_millis(200)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        client.write_all(second).await.unwrap();
+        sniffed.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sniff() {
+        assert_eq!(sniff_with(b"8=FIX.4.2\x019=", b"").await, Sniff::Fix);
+        assert_eq!(sniff_with(b"8=FI", b"XT.1.1\x01").await, Sniff::Fix);
+        assert_eq!(sniff_with(b"\x16\x03\x01", b"").await, Sniff::NotFix);
+        assert_eq!(sniff_with(b"GET / HTTP/1.1\r\n", b"").await, Sniff::NotFix);
+        assert_eq!(sniff_with(b"", b"").await, Sniff::TimedOut);
+        assert_eq!(sniff_with(b"8=", b"").await, Sniff::TimedOut);
+    }
+
+    #[test]
+    fn test_comp_ids() {
+        let logon = b"8=FIX.4.2\x019=70\x0135=A\x0149=client\x0156=server\x0134=1\x01";
+        let expected = Some((String::from("client"), String::from("server")));
+        assert_eq!(comp_ids(logon), expected);
+        assert_eq!(comp_ids(&logon[..32]), None);
+        assert_eq!(comp_ids(&logon[..39]), None);
+        assert_eq!(comp_ids(&logon[..40]), expected);
+    }
+}
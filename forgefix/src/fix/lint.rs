@@ -0,0 +1,372 @@
+//! Check raw FIX messages against the FIX 4.2 dictionary
+//!
+//! [`lint`] validates a serialized message without a session: its framing (`BeginString(8)`,
+//! `BodyLength(9)`, `MsgType(35)` and `CheckSum(10)`), the fields required by its `MsgType(35)`,
+//! and the value of each field against the [dictionary]. It is meant for checking messages before
+//! they are sent to a venue, and is used by the `fix-lint` tool of `forgefix-tools`.
+//!
+//! ```
+//! use forgefix::fix::lint::{lint, Severity};
+//!
+//! let msg = b"8=FIX.4.2\x019=71\x0135=D\x0149=my_id\x0156=peer_id\x0134=2\x01\
+//!             52=20240102-10:00:00.000\x0111=order-1\x0140=Z\x0110=195\x01";
+//! let diagnostics = lint(msg);
+//! for diagnostic in &diagnostics {
+//!     println!("{diagnostic}");
+//! }
+//! # assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+//! # assert_eq!(diagnostics.len(), 5);
+//! ```
+//!
+//! [dictionary]: crate::fix::generated::dictionary
+
+use crate::fix::checksum::calc_checksum;
+use crate::fix::decode::{parse, parse_sending_time, MessageParseError, ParserCallback};
+use crate::fix::generated::dictionary::{self, FieldType};
+use crate::fix::generated::Tags;
+
+use std::fmt::Display;
+
+use chrono::{NaiveDate, NaiveTime};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The message does not conform to FIX 4.2, and will likely be rejected.
+    Error,
+    /// The message is valid, but may not be what was intended.
+    Warning,
+}
+
+/// A problem found in a message by [`lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The tag of the field the problem was found in, if any.
+    pub tag: Option<u32>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(tag: Option<u32>, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            tag,
+            message: message.into(),
+        }
+    }
+
+    fn warning(tag: Option<u32>, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            tag,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: ")?,
+            Severity::Warning => write!(f, "warning: ")?,
+        }
+        if let Some(tag) = self.tag {
+            match Tags::try_from(tag) {
+                Ok(name) => write!(f, "{name:?}({tag}): ")?,
+                Err(_) => write!(f, "tag {tag}: ")?,
+            }
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Check `msg` against the FIX 4.2 dictionary, and return every problem found.
+///
+/// An empty result means the message is valid.
+pub fn lint(msg: &[u8]) -> Vec<Diagnostic> {
+    let mut diagnostics = lint_framing(msg);
+
+    let mut fields = FieldCollector::default();
+    if let Err(err) = parse(msg, &mut fields) {
+        diagnostics.push(Diagnostic::error(None, format!("message could not be split into fields: {err}")));
+        return diagnostics;
+    }
+    let fields = fields.0;
+
+    for (tag, value) in fields.iter() {
+        diagnostics.extend(lint_value(*tag, value));
+    }
+
+    let has = |tag: &u32| fields.iter().any(|(t, _)| t == tag);
+    let msg_type = fields.iter().find(|(tag, _)| *tag == u32::from(Tags::MsgType)).map(|(_, value)| *value);
+    let required = match msg_type.map(dictionary::required_fields) {
+        Some(Some(required)) => required,
+        Some(None) => {
+            diagnostics.push(Diagnostic::error(
+                Some(Tags::MsgType.into()),
+                format!("{:?} is not a FIX 4.2 message type", String::from_utf8_lossy(msg_type.unwrap_or_default())),
+            ));
+            &[]
+        }
+        None => &[],
+    };
+    for tag in dictionary::HEADER_REQUIRED_FIELDS
+        .iter()
+        .chain(required.iter())
+        .chain(dictionary::TRAILER_REQUIRED_FIELDS.iter())
+    {
+        if !has(tag) {
+            diagnostics.push(Diagnostic::error(Some(*tag), "required field is missing"));
+        }
+    }
+    diagnostics
+}
+
+// The fields of the standard header and trailer of FIX 4.2, which the engine writes around the
+// body of a message.
+const HEADER_FIELDS: &[u32] = &[
+    8, 9, 35, 49, 56, 115, 128, 90, 91, 34, 50, 142, 57, 143, 116, 144, 129, 145, 43, 97, 52, 122,
+    212, 213, 347, 369, 370,
+];
+const TRAILER_FIELDS: &[u32] = &[93, 89, 10];
+
+// Check the body of an outgoing message of `msg_type`: the fields it requires, the value of each
+// field, and that no header or trailer field is out of place in it. A value of `None` is filled in
+// by the engine when the message is sent. Only errors are returned, since unknown fields are
+// allowed in outgoing messages.
+pub(crate) fn lint_body(msg_type: char, fields: &[(u32, Option<&[u8]>)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let Some(required) = dictionary::required_fields(msg_type.to_string().as_bytes()) else {
+        diagnostics.push(Diagnostic::error(
+            Some(Tags::MsgType.into()),
+            format!("{msg_type:?} is not a FIX 4.2 message type"),
+        ));
+        return diagnostics;
+    };
+
+    for (tag, value) in fields {
+        if HEADER_FIELDS.contains(tag) {
+            diagnostics.push(Diagnostic::error(Some(*tag), "is a header field, but comes after the header"));
+        } else if TRAILER_FIELDS.contains(tag) {
+            diagnostics.push(Diagnostic::error(Some(*tag), "is a trailer field, but comes before the trailer"));
+        } else if let Some(value) = value {
+            diagnostics.extend(lint_value(*tag, value).filter(|d| d.severity == Severity::Error));
+        }
+    }
+
+    for tag in required {
+        if !fields.iter().any(|(t, _)| t == tag) {
+            diagnostics.push(Diagnostic::error(Some(*tag), "required field is missing"));
+        }
+    }
+    diagnostics
+}
+
+// The engine's framing rules: BeginString, BodyLength and MsgType come first, in that order, and
+// CheckSum comes last.
+fn lint_framing(msg: &[u8]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut fields = msg.split(|b| *b == b'\x01');
+
+    match fields.next() {
+        Some(b"8=FIX.4.2") => {}
+        Some(field) if field.starts_with(b"8=") => diagnostics.push(Diagnostic::error(
+            Some(Tags::BeginString.into()),
+            format!("expected \"FIX.4.2\", found {:?}", String::from_utf8_lossy(&field[2..])),
+        )),
+        _ => diagnostics.push(Diagnostic::error(Some(Tags::BeginString.into()), "must be the first field")),
+    }
+
+    let body_length = match fields.next() {
+        Some(field) if field.starts_with(b"9=") => match std::str::from_utf8(&field[2..]).map(str::parse::<usize>) {
+            Ok(Ok(body_length)) => Some(body_length),
+            _ => {
+                diagnostics.push(Diagnostic::error(Some(Tags::BodyLength.into()), "is not a number"));
+                None
+            }
+        },
+        _ => {
+            diagnostics.push(Diagnostic::error(Some(Tags::BodyLength.into()), "must be the second field"));
+            None
+        }
+    };
+
+    if !matches!(fields.next(), Some(field) if field.starts_with(b"35=")) {
+        diagnostics.push(Diagnostic::error(Some(Tags::MsgType.into()), "must be the third field"));
+    }
+
+    let checksum_start = msg.len().saturating_sub(7);
+    let trailer = &msg[checksum_start..];
+    let has_checksum = trailer.len() == 7
+        && trailer.starts_with(b"10=")
+        && trailer[3..6].iter().all(u8::is_ascii_digit)
+        && trailer[6] == b'\x01'
+        && (checksum_start == 0 || msg[checksum_start - 1] == b'\x01');
+    if !has_checksum {
+        diagnostics.push(Diagnostic::error(
+            Some(Tags::CheckSum.into()),
+            "must be the last field, with three digits and a trailing SOH",
+        ));
+        return diagnostics;
+    }
+
+    if let Some(body_length) = body_length {
+        // BodyLength counts every byte after its own field, up to the CheckSum field.
+        let body_start = msg.iter().enumerate().filter(|(_, b)| **b == b'\x01').nth(1).map_or(0, |(at, _)| at + 1);
+        let actual = checksum_start.saturating_sub(body_start);
+        if actual != body_length {
+            diagnostics.push(Diagnostic::error(
+                Some(Tags::BodyLength.into()),
+                format!("is {body_length}, but the body is {actual} bytes"),
+            ));
+        }
+    }
+
+    let checksum = std::str::from_utf8(&trailer[3..6]).unwrap_or_default().parse::<i32>().unwrap_or_default();
+    let expected = calc_checksum(&msg[..checksum_start]);
+    if checksum != expected {
+        diagnostics.push(Diagnostic::error(
+            Some(Tags::CheckSum.into()),
+            format!("is {checksum:03}, but should be {expected:03}"),
+        ));
+    }
+    diagnostics
+}
+
+fn lint_value(tag: u32, value: &[u8]) -> Option<Diagnostic> {
+    let Some(field_type) = dictionary::field_type(tag) else {
+        return Some(Diagnostic::warning(Some(tag), "is not in the FIX 4.2 dictionary"));
+    };
+    if value.is_empty() {
+        return Some(Diagnostic::error(Some(tag), "has no value"));
+    }
+    if field_type == FieldType::Data {
+        return None;
+    }
+    let Ok(text) = std::str::from_utf8(value) else {
+        return Some(Diagnostic::error(Some(tag), "is not valid UTF-8"));
+    };
+
+    if !valid_format(field_type, text) {
+        return Some(Diagnostic::error(Some(tag), format!("{text:?} is not a valid {field_type:?}")));
+    }
+
+    if let Some(values) = dictionary::field_values(tag) {
+        let mut given = match field_type {
+            FieldType::MultipleValueString => text.split(' ').collect(),
+            _ => vec![text],
+        };
+        given.retain(|value| !values.contains(value));
+        if let Some(value) = given.first() {
+            return Some(Diagnostic::error(Some(tag), format!("{value:?} is not one of the allowed values")));
+        }
+    }
+    None
+}
+
+fn valid_format(field_type: FieldType, text: &str) -> bool {
+    match field_type {
+        FieldType::Int => text.parse::<i64>().is_ok(),
+        FieldType::Length => text.parse::<u32>().is_ok(),
+        FieldType::DayOfMonth => matches!(text.parse::<u8>(), Ok(1..=31)),
+        FieldType::Float | FieldType::Price | FieldType::PriceOffset | FieldType::Qty | FieldType::Amt => {
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            !digits.is_empty()
+                && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+                && digits.chars().filter(|c| *c == '.').count() <= 1
+                && digits != "."
+        }
+        FieldType::Char | FieldType::Boolean => text.chars().count() == 1,
+        FieldType::UtcTimestamp => parse_sending_time(text.as_bytes()).is_ok(),
+        FieldType::UtcDate | FieldType::LocalMktDate => {
+            text.len() == 8 && NaiveDate::parse_from_str(text, "%Y%m%d").is_ok()
+        }
+        FieldType::UtcTimeOnly => {
+            NaiveTime::parse_from_str(text, "%H:%M:%S").is_ok()
+                || NaiveTime::parse_from_str(text, "%H:%M:%S%.3f").is_ok()
+        }
+        FieldType::MonthYear => {
+            text.len() == 6 && NaiveDate::parse_from_str(&format!("{text}01"), "%Y%m%d").is_ok()
+        }
+        FieldType::Currency
+        | FieldType::Data
+        | FieldType::Exchange
+        | FieldType::MultipleValueString
+        | FieldType::String => true,
+    }
+}
+
+#[derive(Default)]
+struct FieldCollector<'a>(Vec<(u32, &'a [u8])>);
+
+impl<'a> ParserCallback<'a> for FieldCollector<'a> {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.0.push((key, value));
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.0.push((key, value));
+        Ok(true)
+    }
+    fn trailer(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        self.0.push((key, value));
+        Ok(true)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Fill in BodyLength and CheckSum for a message given without them.
+    fn frame(body: &[u8]) -> Vec<u8> {
+        let mut msg = format!("8=FIX.4.2\x019={}\x01", body.len()).into_bytes();
+        msg.extend_from_slice(body);
+        let checksum = calc_checksum(&msg);
+        msg.extend_from_slice(format!("10={checksum:03}\x01").as_bytes());
+        msg
+    }
+
+    #[test]
+    fn test_lint() {
+        let order = frame(
+            b"35=D\x0149=my_id\x0156=peer_id\x0134=2\x0152=20240102-10:00:00.000\x01\
+              11=order-1\x0121=1\x0155=AAPL\x0154=1\x0160=20240102-10:00:00.000\x0138=100\x0140=2\x0144=187.5\x01",
+        );
+        assert_eq!(lint(&order), vec![]);
+
+        let bad_values = frame(
+            b"35=D\x0149=my_id\x0156=peer_id\x0134=2\x0152=20240102-10:00\x01\
+              11=order-1\x0121=1\x0155=AAPL\x0154=1\x0160=20240102-10:00:00.000\x0138=1e5\x0140=Z\x015001=x\x01",
+        );
+        let diagnostics = lint(&bad_values);
+        let tags: Vec<_> = diagnostics.iter().map(|d| (d.severity, d.tag)).collect();
+        assert_eq!(
+            tags,
+            vec![
+                (Severity::Error, Some(52)),
+                (Severity::Error, Some(38)),
+                (Severity::Error, Some(40)),
+                (Severity::Warning, Some(5001)),
+            ]
+        );
+        assert_eq!(diagnostics[2].to_string(), "error: OrdType(40): \"Z\" is not one of the allowed values");
+
+        let mut bad_framing = frame(b"35=0\x0149=my_id\x0156=peer_id\x0134=2\x0152=20240102-10:00:00.000\x01");
+        bad_framing[12] = b'9';
+        let checksum_start = bad_framing.len() - 4;
+        bad_framing[checksum_start..checksum_start + 3].copy_from_slice(b"000");
+        let diagnostics = lint(&bad_framing);
+        assert!(diagnostics.iter().any(|d| d.tag == Some(9)));
+        assert!(diagnostics.iter().any(|d| d.tag == Some(10)));
+
+        let missing = frame(b"35=1\x0149=my_id\x0156=peer_id\x0152=20240102-10:00:00.000\x01");
+        let missing: Vec<_> = lint(&missing).into_iter().map(|d| d.tag).collect();
+        assert_eq!(missing, vec![Some(34), Some(112)]);
+    }
+}
@@ -1,57 +1,213 @@
-use crate::SessionSettings;
+//! Record the messages a FIX engine sends and receives
+//!
+//! By default, an engine writes every message to a file in the [log dir] of its
+//! [`SessionSettings`], and garbled frames to a quarantine file if the [quarantine log] is on.
+//! A [`Logger`] given to [`SessionSettingsBuilder::with_logger`] replaces those files, such as
+//! to ship raw messages to a message broker or keep the latest ones in memory.
+//!
+//! The engine calls the logger from its session task, as each message is written to or read from
+//! the connection, so a logger that does not return quickly holds up the session.
+//!
+//! ```
+//! use forgefix::SessionSettings;
+//! use forgefix::fix::log::Logger;
+//! use forgefix::fix::mem::MsgBuf;
+//! use std::collections::VecDeque;
+//!
+//! // keeps the last 1000 messages
+//! struct RingBufferLogger(VecDeque<String>);
+//!
+//! impl Logger for RingBufferLogger {
+//!     fn log_message(&mut self, msg: &MsgBuf) -> std::io::Result<()> {
+//!         if self.0.len() == 1000 {
+//!             self.0.pop_front();
+//!         }
+//!         self.0.push_back(format!("{:?}", msg));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), forgefix::ApplicationError> {
+//! let settings = SessionSettings::builder()
+//!     .with_sender_comp_id("my_id")
+//!     .with_target_comp_id("peer_id")
+//!     .with_store_path("./store".into())
+//!     .with_log_dir("./log".into())
+//!     .with_socket_addr("127.0.0.1:0".parse().unwrap())
+//!     .with_logger(Box::new(RingBufferLogger(VecDeque::new())))
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [log dir]: crate::SessionSettingsBuilder::with_log_dir
+//! [quarantine log]: crate::SessionSettingsBuilder::with_quarantine_log
+//! [`SessionSettings`]: crate::SessionSettings
+//! [`SessionSettingsBuilder::with_logger`]: crate::SessionSettingsBuilder::with_logger
+
+use crate::{LogRotation, SessionSettings};
 use crate::fix::mem::MsgBuf;
 use crate::fix::SessionError;
 
 use chrono::offset::{Local};
-use chrono::{Duration, DateTime};
+use chrono::{Duration, DateTime, NaiveDate};
 
 use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncWriteExt};
 use tokio::sync::{oneshot, mpsc}; 
+use tracing::Instrument;
 
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Instant; 
 
 use anyhow::Result;
 
 const LOG_FILE_TYPE: &str = "txt";
+const QUARANTINE_FILE_SUFFIX: &str = "quarantine";
+const COMPRESSED_FILE_TYPE: &str = "gz";
+const LOG_DATE_FORMAT: &str = "%Y%m%d";
+
+/// Records the messages of a FIX session. See the [module documentation](self).
+///
+/// An error returned by a logger ends the session, as failing to write the message log does.
+pub trait Logger: Send {
+    /// Called with each message written to the connection, and each message read from it,
+    /// including session messages such as heartbeats.
+    fn log_message(&mut self, msg: &MsgBuf) -> std::io::Result<()>;
+
+    /// Called instead of [`log_message`](Logger::log_message) with each message read into the
+    /// [arena](crate::fix::arena) of an engine that delivers through one. Defaults to copying the
+    /// message into a [`MsgBuf`] for `log_message`; override it to log without allocating.
+    fn log_bytes(&mut self, msg: &[u8]) -> std::io::Result<()> {
+        self.log_message(&MsgBuf(msg.to_vec()))
+    }
+
+    /// Called with the exact bytes of a garbled or rejected frame, along with the reason it was
+    /// not processed. Discarded by default.
+    fn quarantine(&mut self, _bytes: &[u8], _reason: &str) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Called as the engine disconnects, once every message of the session was logged.
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
 
 enum LoggerRequest {
     Log(String, Instant),
+    Quarantine(Vec<u8>, String, Instant),
     Disconnect(oneshot::Sender<Result<(), SessionError>>),
 }
 
 pub(super) struct FileLogger {
     sender: mpsc::UnboundedSender<LoggerRequest>,
+    quarantine_enabled: bool,
 }
 
-pub(super) trait Logger {
-    fn log_message(&mut self, msg: &MsgBuf) -> Result<(), SessionError>;
+// The logger of a running engine: the files of the session, or the logger of its settings.
+pub(super) enum SessionLogger {
+    File(FileLogger),
+    Custom(Arc<Mutex<Box<dyn Logger>>>),
 }
 
 impl Logger for FileLogger {
-    fn log_message(&mut self, buf: &MsgBuf) -> Result<(), SessionError> {
+    fn log_message(&mut self, buf: &MsgBuf) -> std::io::Result<()> {
         let req = LoggerRequest::Log(format!("{}", buf), Instant::now()); 
         self.sender.send(req).map_err(to_io_err)?;
         Ok(())
     }
+
+    fn log_bytes(&mut self, msg: &[u8]) -> std::io::Result<()> {
+        let req = LoggerRequest::Log(msg.iter().map(|b| *b as char).collect(), Instant::now());
+        self.sender.send(req).map_err(to_io_err)?;
+        Ok(())
+    }
+
+    fn quarantine(&mut self, bytes: &[u8], reason: &str) -> std::io::Result<()> {
+        if !self.quarantine_enabled {
+            return Ok(());
+        }
+        let req = LoggerRequest::Quarantine(bytes.to_vec(), reason.to_string(), Instant::now());
+        self.sender.send(req).map_err(to_io_err)?;
+        Ok(())
+    }
 }
 
+impl Logger for SessionLogger {
+    fn log_message(&mut self, msg: &MsgBuf) -> std::io::Result<()> {
+        match self {
+            SessionLogger::File(logger) => logger.log_message(msg),
+            SessionLogger::Custom(logger) => lock(logger)?.log_message(msg),
+        }
+    }
+
+    fn log_bytes(&mut self, msg: &[u8]) -> std::io::Result<()> {
+        match self {
+            SessionLogger::File(logger) => logger.log_bytes(msg),
+            SessionLogger::Custom(logger) => lock(logger)?.log_bytes(msg),
+        }
+    }
+
+    fn quarantine(&mut self, bytes: &[u8], reason: &str) -> std::io::Result<()> {
+        match self {
+            SessionLogger::File(logger) => logger.quarantine(bytes, reason),
+            SessionLogger::Custom(logger) => lock(logger)?.quarantine(bytes, reason),
+        }
+    }
+}
+
+impl SessionLogger {
+    pub(super) async fn build(settings: &SessionSettings) -> Result<SessionLogger> {
+        match settings.logger {
+            Some(ref logger) => Ok(SessionLogger::Custom(Arc::clone(logger))),
+            None => Ok(SessionLogger::File(FileLogger::build(settings).await?)),
+        }
+    }
+
+    pub(super) async fn disconnect(&mut self) -> Result<(), SessionError> {
+        match self {
+            SessionLogger::File(logger) => logger.disconnect().await,
+            SessionLogger::Custom(logger) => Ok(lock(logger)?.flush()?),
+        }
+    }
+}
+
+// A logger is only used by one engine at a time, unless settings with a logger are used to start
+// more than one, so the lock is not expected to be contended.
+fn lock(logger: &Mutex<Box<dyn Logger>>) -> std::io::Result<std::sync::MutexGuard<'_, Box<dyn Logger>>> {
+    logger.lock().map_err(|_| std::io::Error::other("logger poisoned"))
+}
 
 impl FileLogger {
-    pub(super) async fn build(settings: &SessionSettings) -> Result<FileLogger> {
+    async fn build(settings: &SessionSettings) -> Result<FileLogger> {
         let log_path = &settings.log_dir;
         let sendercompid = settings.expected_sender_comp_id();
         let targetcompid = settings.expected_target_comp_id();
         std::fs::create_dir_all(log_path)?;
-        let mut logs = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(
-                log_path
-                    .join(format!("{}-{}", sendercompid, targetcompid))
-                    .with_extension(LOG_FILE_TYPE)
-            )
-            .await?;
+        let mut logs = LogFile::open(
+            log_path.clone(),
+            format!("{}-{}", sendercompid, targetcompid),
+            settings.log_rotation,
+            Local::now().date_naive(),
+        )
+        .await?;
+
+        let mut quarantine = None;
+        if settings.quarantine_log {
+            quarantine = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(
+                        log_path
+                            .join(format!("{}-{}-{}", sendercompid, targetcompid, QUARANTINE_FILE_SUFFIX))
+                            .with_extension(LOG_FILE_TYPE)
+                    )
+                    .await?,
+            );
+        }
 
         let (sender, mut receiver) = mpsc::unbounded_channel(); 
 
@@ -66,21 +222,39 @@ impl FileLogger {
                             Err(_) => Local::now(),
                         };
                         if let Err(e) = log_message(&mut logs, msg, send_time).await {
-                            eprintln!("error logging message: {e:?}")
+                            tracing::error!("error logging message: {e:?}")
+                        }
+                    }
+                    LoggerRequest::Quarantine(bytes, reason, instant) => {
+                        let Some(quarantine) = quarantine.as_mut() else {
+                            continue;
+                        };
+                        let recv_time = match Duration::from_std(instant.duration_since(begin_instant)) {
+                            Ok(d) => begin_time + d,
+                            Err(_) => Local::now(),
+                        };
+                        if let Err(e) = quarantine_message(quarantine, &bytes, &reason, recv_time).await {
+                            tracing::error!("error quarantining message: {e:?}")
                         }
                     }
                     LoggerRequest::Disconnect(sender) => {
-                        let resp = disconnect(&mut logs).await;
+                        let mut resp = disconnect(&mut logs.file).await;
+                        if let (Ok(()), Some(quarantine)) = (&resp, quarantine.as_mut()) {
+                            resp = disconnect(quarantine).await;
+                        }
                         let _ = sender.send(resp);
                     }
                 }
             }
-        }); 
+        }.in_current_span()); 
 
-        Ok(FileLogger { sender })
+        Ok(FileLogger {
+            sender,
+            quarantine_enabled: settings.quarantine_log,
+        })
     }
 
-    pub(super) async fn disconnect(&mut self) -> Result<(), SessionError> {
+    async fn disconnect(&mut self) -> Result<(), SessionError> {
         let (sender, receiver) = oneshot::channel();
         let req = LoggerRequest::Disconnect(sender);
         self.sender.send(req).map_err(to_io_err)?;
@@ -88,11 +262,118 @@ impl FileLogger {
     }
 }
 
-async fn log_message(logs: &mut File, buf: String, time: DateTime<Local>) -> Result<(), SessionError> {
-    logs
-        .write_all(format!("{} : {}\n", message_stamp(time), buf).as_bytes())
-        .await?;
-    logs.flush().await?;
+async fn log_message(logs: &mut LogFile, buf: String, time: DateTime<Local>) -> Result<(), SessionError> {
+    let record = format!("{} : {}\n", message_stamp(time), buf);
+    logs.write(record.as_bytes(), time.date_naive()).await
+}
+
+// The message log of a session, which moves on to a new file when it is rotated.
+struct LogFile {
+    dir: PathBuf,
+    session: String,
+    rotation: LogRotation,
+    date: NaiveDate,
+    index: u32,
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl LogFile {
+    // Open the file of `date`, continuing the last one of the day if it is not full.
+    async fn open(dir: PathBuf, session: String, rotation: LogRotation, date: NaiveDate) -> Result<LogFile, SessionError> {
+        let (index, path) = next_log_path(&dir, &session, &rotation, date, 0);
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        let len = file.metadata().await?.len();
+        Ok(LogFile { dir, session, rotation, date, index, path, file, len })
+    }
+
+    async fn write(&mut self, record: &[u8], date: NaiveDate) -> Result<(), SessionError> {
+        let new_day = self.rotation.daily && date != self.date;
+        let full = self
+            .rotation
+            .max_bytes
+            .is_some_and(|max_bytes| self.len > 0 && self.len + record.len() as u64 > max_bytes);
+        if new_day || full {
+            self.rotate(date).await?;
+        }
+        self.file.write_all(record).await?;
+        self.file.flush().await?;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self, date: NaiveDate) -> Result<(), SessionError> {
+        self.file.flush().await?;
+        let first_index = if date != self.date { 0 } else { self.index + 1 };
+        let (index, path) = next_log_path(&self.dir, &self.session, &self.rotation, date, first_index);
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        let completed = std::mem::replace(&mut self.path, path);
+        self.len = file.metadata().await?.len();
+        self.file = file;
+        self.date = date;
+        self.index = index;
+        if self.rotation.compress {
+            tokio::task::spawn_blocking(move || compress(&completed))
+                .await
+                .map_err(to_io_err)??;
+        }
+        Ok(())
+    }
+}
+
+// The first log file of `date`, from `first_index` on, that was not completed yet, along with
+// its index. Without rotation, there is only one file per session.
+fn next_log_path(dir: &Path, session: &str, rotation: &LogRotation, date: NaiveDate, first_index: u32) -> (u32, PathBuf) {
+    if !rotation.is_enabled() {
+        return (0, dir.join(session).with_extension(LOG_FILE_TYPE));
+    }
+    let mut index = first_index;
+    loop {
+        let date = date.format(LOG_DATE_FORMAT);
+        let file_name = match index {
+            0 => format!("{session}.{date}.{LOG_FILE_TYPE}"),
+            _ => format!("{session}.{date}.{index}.{LOG_FILE_TYPE}"),
+        };
+        let path = dir.join(file_name);
+        let compressed = compressed_path(&path).exists();
+        let full = rotation.max_bytes.is_some_and(|max_bytes| {
+            std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() >= max_bytes)
+        });
+        if !compressed && !full {
+            return (index, path);
+        }
+        index += 1;
+    }
+}
+
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{COMPRESSED_FILE_TYPE}"));
+    path.with_file_name(file_name)
+}
+
+// Replace the completed log file at `path` by a gzip file.
+fn compress(path: &Path) -> std::io::Result<()> {
+    let mut input = std::fs::File::open(path)?;
+    let output = std::fs::File::create(compressed_path(path))?;
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::remove_file(path)
+}
+
+async fn quarantine_message(
+    quarantine: &mut File,
+    bytes: &[u8],
+    reason: &str,
+    time: DateTime<Local>,
+) -> Result<(), SessionError> {
+    let mut record = format!("{} : {} : ", message_stamp(time), reason).into_bytes();
+    record.extend_from_slice(bytes);
+    record.push(b'\n');
+    quarantine.write_all(&record).await?;
+    quarantine.flush().await?;
     Ok(())
 }
 
@@ -113,3 +394,40 @@ where E: Into<Box<dyn std::error::Error + Send + Sync>>
 {
     std::io::Error::new(std::io::ErrorKind::Other, e)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+
+    #[tokio::test]
+    async fn test_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("forgefix-log-rotation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rotation = LogRotation {
+            daily: true,
+            max_bytes: Some(40),
+            compress: true,
+        };
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let record = [b'x'; 30];
+
+        let mut logs = LogFile::open(dir.clone(), String::from("peer-me"), rotation, day(2)).await.unwrap();
+        logs.write(&record, day(2)).await.unwrap();
+        logs.write(&record, day(2)).await.unwrap();
+        assert_eq!(logs.path, dir.join("peer-me.20240102.1.txt"));
+        logs.write(&record, day(3)).await.unwrap();
+        assert_eq!(logs.path, dir.join("peer-me.20240103.txt"));
+
+        let mut compressed = Vec::new();
+        let file = std::fs::File::open(dir.join("peer-me.20240102.txt.gz")).unwrap();
+        flate2::read::GzDecoder::new(file).read_to_end(&mut compressed).unwrap();
+        assert_eq!(compressed, record);
+        assert!(dir.join("peer-me.20240102.1.txt.gz").exists());
+        assert!(!dir.join("peer-me.20240102.1.txt").exists());
+
+        let logs = LogFile::open(dir.clone(), String::from("peer-me"), rotation, day(3)).await.unwrap();
+        assert_eq!((logs.path, logs.len), (dir.join("peer-me.20240103.txt"), 30));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
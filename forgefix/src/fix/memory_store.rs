@@ -0,0 +1,222 @@
+//! A store that keeps sequence numbers and outgoing messages in memory
+//!
+//! By default, an engine keeps its sequence numbers and the messages it sent in a sqlite file at
+//! the store path, so a session can continue after a restart. Tests and ephemeral sessions that
+//! start from sequence number 1 on every run can use a [`MemoryStore`] instead, with
+//! [`SessionSettingsBuilder::with_memory_store`]. Nothing is written to disk, and everything is
+//! lost once the last clone of the store is dropped.
+//!
+//! A clone of the store shares its contents, so a test can keep one to look at the sequence
+//! numbers and messages of an engine, or hand it to the next engine to continue the session.
+//!
+//! ```
+//! use forgefix::{ApplicationError, FixApplicationInitiator, SessionSettings};
+//! use forgefix::fix::memory_store::MemoryStore;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! let store = MemoryStore::new();
+//! let settings = SessionSettings::builder()
+//!     .with_sender_comp_id("my_id")
+//!     .with_target_comp_id("peer_id")
+//! #    .with_log_dir(peer.log_dir())
+//! #    .with_socket_addr(peer.addr())
+//!     .with_memory_store(store.clone())
+//!     // ...
+//!     .build()?;
+//! let (handle, _receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate()
+//!     .await?;
+//! handle.start_async().await?;
+//! handle.end_async().await?;
+//!
+//! println!("next sequence numbers: {:?}", store.sequences("my_id_peer_id"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SessionSettingsBuilder::with_memory_store`]: crate::SessionSettingsBuilder::with_memory_store
+
+use crate::fix::dedup;
+use crate::fix::store::Direction;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+
+/// A store that keeps everything in memory. See the [module documentation](self).
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    epochs: Arc<Mutex<HashMap<String, Epoch>>>,
+}
+
+struct Epoch {
+    next_incoming: u32,
+    next_outgoing: u32,
+    outgoing: Vec<(u32, DateTime<Utc>, Vec<u8>)>,
+    incoming: Vec<(u32, DateTime<Utc>, Vec<u8>)>,
+    sent_orders: Vec<Vec<u8>>,
+}
+
+impl Default for Epoch {
+    fn default() -> Epoch {
+        Epoch {
+            next_incoming: 1,
+            next_outgoing: 1,
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+            sent_orders: Vec::new(),
+        }
+    }
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// The next incoming and outgoing sequence numbers of `epoch`, or `None` if no engine has
+    /// used the epoch.
+    pub fn sequences(&self, epoch: &str) -> Option<(u32, u32)> {
+        let epochs = self.epochs.lock().unwrap();
+        epochs.get(epoch).map(|e| (e.next_incoming, e.next_outgoing))
+    }
+
+    /// The messages sent in `epoch` with their `MsgSeqNum(34)`, in the order they were sent.
+    pub fn outgoing_messages(&self, epoch: &str) -> Vec<(u32, Vec<u8>)> {
+        let epochs = self.epochs.lock().unwrap();
+        epochs
+            .get(epoch)
+            .map(|e| e.outgoing.iter().map(|(seq, _, msg)| (*seq, msg.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    /// The application messages received in `epoch` with their `MsgSeqNum(34)`, in the order
+    /// they were received. See [`SessionSettingsBuilder::with_store_incoming`].
+    ///
+    /// [`SessionSettingsBuilder::with_store_incoming`]: crate::SessionSettingsBuilder::with_store_incoming
+    pub fn incoming_messages(&self, epoch: &str) -> Vec<(u32, Vec<u8>)> {
+        let epochs = self.epochs.lock().unwrap();
+        epochs
+            .get(epoch)
+            .map(|e| e.incoming.iter().map(|(seq, _, msg)| (*seq, msg.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    /// Set the next incoming and outgoing sequence numbers of `epoch`, such as to start an engine
+    /// with a gap in the sequence numbers.
+    pub fn set_sequences(&self, epoch: &str, next_incoming: u32, next_outgoing: u32) {
+        self.with_epoch(epoch, |e| {
+            e.next_incoming = next_incoming;
+            e.next_outgoing = next_outgoing;
+        })
+    }
+
+    fn with_epoch<T>(&self, epoch: &str, f: impl FnOnce(&mut Epoch) -> T) -> T {
+        let mut epochs = self.epochs.lock().unwrap();
+        f(epochs.entry(epoch.to_string()).or_default())
+    }
+
+    pub(super) fn get_sequences(&self, epoch: &str) -> (u32, u32) {
+        self.with_epoch(epoch, |e| (e.next_incoming, e.next_outgoing))
+    }
+
+    pub(super) fn store_outgoing(
+        &self,
+        epoch: &str,
+        msg_seq_num: u32,
+        send_time: DateTime<Utc>,
+        msg: &[u8],
+        outgoing_dedup: bool,
+    ) {
+        self.with_epoch(epoch, |e| {
+            if outgoing_dedup {
+                if let Some(cl_ord_id) = dedup::cl_ord_id(msg) {
+                    e.sent_orders.push(cl_ord_id.to_vec());
+                }
+            }
+            e.outgoing.push((msg_seq_num, send_time, msg.to_vec()));
+        })
+    }
+
+    pub(super) fn store_incoming(&self, epoch: &str, msg_seq_num: u32, receive_time: DateTime<Utc>, msg: &[u8]) {
+        self.with_epoch(epoch, |e| e.incoming.push((msg_seq_num, receive_time, msg.to_vec())))
+    }
+
+    // Like the sqlite store, only the last `last_seq_no` messages stored are searched, so
+    // messages of an earlier sequence reset are not resent.
+    pub(super) fn get_prev_messages(
+        &self,
+        epoch: &str,
+        begin_seq_no: u32,
+        end_seq_no: u32,
+        last_seq_no: u32,
+    ) -> Vec<(u32, Vec<u8>)> {
+        self.with_epoch(epoch, |e| {
+            e.outgoing
+                .iter()
+                .rev()
+                .take(last_seq_no as usize)
+                .filter(|(seq, _, _)| (begin_seq_no..=end_seq_no).contains(seq))
+                .map(|(seq, _, msg)| (*seq, msg.clone()))
+                .collect()
+        })
+    }
+
+    pub(super) fn last_send_time(&self, epoch: &str) -> Option<DateTime<Utc>> {
+        self.with_epoch(epoch, |e| e.outgoing.iter().map(|(_, send_time, _)| *send_time).max())
+    }
+
+    // The messages sent or received in `epoch` with their sequence numbers and the times they
+    // were sent or received.
+    pub(super) fn read_messages(&self, epoch: &str, direction: Direction) -> Vec<(u32, DateTime<Utc>, Vec<u8>)> {
+        let epochs = self.epochs.lock().unwrap();
+        let Some(e) = epochs.get(epoch) else {
+            return Vec::new();
+        };
+        match direction {
+            Direction::Outgoing => e.outgoing.clone(),
+            Direction::Incoming => e.incoming.clone(),
+        }
+    }
+
+    pub(super) fn get_sent_orders(&self, epoch: &str) -> Vec<Vec<u8>> {
+        self.with_epoch(epoch, |e| e.sent_orders.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::new();
+        assert_eq!(store.sequences("epoch"), None);
+        assert_eq!(store.get_sequences("epoch"), (1, 1));
+        assert_eq!(store.sequences("epoch"), Some((1, 1)));
+
+        let order = b"8=FIX.4.2\x019=5\x0135=D\x0111=order-1\x0110=000\x01";
+        let heartbeat = b"8=FIX.4.2\x019=5\x0135=0\x0110=000\x01";
+        store.store_outgoing("epoch", 1, Utc::now(), order, true);
+        store.store_outgoing("epoch", 2, Utc::now(), heartbeat, true);
+        store.set_sequences("epoch", 2, 3);
+        assert_eq!(store.clone().sequences("epoch"), Some((2, 3)));
+        assert_eq!(store.get_sent_orders("epoch"), vec![b"order-1".to_vec()]);
+        assert!(store.last_send_time("epoch").is_some());
+        assert_eq!(store.last_send_time("other"), None);
+
+        // after a sequence reset, only the latest message with sequence number 1 is found
+        store.store_outgoing("epoch", 1, Utc::now(), heartbeat, true);
+        assert_eq!(store.get_prev_messages("epoch", 1, 2, 1), vec![(1, heartbeat.to_vec())]);
+        assert_eq!(store.get_prev_messages("epoch", 1, 2, 3).len(), 3);
+        assert_eq!(store.outgoing_messages("epoch").len(), 3);
+
+        store.store_incoming("epoch", 4, Utc::now(), order);
+        assert_eq!(store.incoming_messages("epoch"), vec![(4, order.to_vec())]);
+        assert_eq!(store.read_messages("epoch", Direction::Incoming).len(), 1);
+    }
+}
@@ -0,0 +1,797 @@
+//! Typed application messages
+//!
+//! The engine delivers incoming application messages as raw [`MsgBuf`]s. This module provides an
+//! optional decoding stage that classifies each message by its `MsgType(35)`, so consumers can
+//! `match` on the kinds of messages they handle.
+//!
+//! For outgoing messages, [`NewOrderSingle`], [`OrderCancelRequest`],
+//! [`OrderCancelReplaceRequest`], [`OrderStatusRequest`] and [`BusinessMessageReject`] take the
+//! fields required by FIX 4.2 as constructor arguments, so a message missing one of them does not
+//! compile. Each converts into a [`MessageBuilder`] that can be sent with a
+//! [`FixApplicationHandle`].
+//!
+//! [`IncomingAppMessage`] is deliberately exhaustive: when a new variant is added in a later
+//! release, code matching on it will fail to compile until the new variant is handled.
+//!
+//! # Example
+//!
+//! ```
+//! use forgefix::{SessionSettings, FixApplicationInitiator, ApplicationError};
+//! use forgefix::fix::decimal::FixDecimal;
+//! use forgefix::fix::generated::{HandlInst, OrdType, Side};
+//! use forgefix::fix::messages::{IncomingAppMessage, NewOrderSingle};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! #    let settings = SessionSettings::builder()
+//! #        .with_sender_comp_id("my_id")
+//! #        .with_target_comp_id("peer_id")
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
+//! #        .build()?;
+//! let (handle, mut receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate()
+//!     .await?;
+//! handle.start_async().await?;
+//!
+//! let order = NewOrderSingle::new(
+//!     "order-1",
+//!     HandlInst::AUTOMATED_EXECUTION_ORDER_PRIVATE_NO_BROKER_INTERVENTION,
+//!     "AAPL",
+//!     Side::BUY,
+//!     FixDecimal::new(100, 0),
+//!     OrdType::LIMIT,
+//! )
+//! .with_price("187.50".parse().unwrap());
+//! handle.send_message_async(order.into_builder(&handle.begin_string())).await?;
+//!
+//! while let Some(msg) = receiver.recv().await {
+//!     match IncomingAppMessage::from(msg) {
+//!         IncomingAppMessage::ExecutionReport(msg) => {
+//!             println!("fill or ack: {}", msg);
+//!             break;
+//!         }
+//!         IncomingAppMessage::OrderCancelReject(msg) => println!("cancel rejected: {}", msg),
+//!         IncomingAppMessage::News(msg) => println!("news: {}", msg),
+//!         IncomingAppMessage::BusinessMessageReject(msg) => println!("rejected: {}", msg),
+//!         IncomingAppMessage::Unknown(msg) => println!("unhandled: {}", msg),
+//!     }
+//! }
+//! handle.end_async().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`MessageBuilder`]: crate::fix::encode::MessageBuilder
+//! [`FixApplicationHandle`]: crate::FixApplicationHandle
+
+use crate::fix::decimal::FixDecimal;
+use crate::fix::decode::{parse, DecodeError, FixMessage, MessageParseError, ParserCallback};
+use crate::fix::encode::{MessageBuilder, SerializedInt, TIME_FORMAT};
+use crate::fix::generated::{BusinessRejectReason, HandlInst, MsgType, OrdType, Side, Tags, TimeInForce};
+use crate::fix::mem::MsgBuf;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+/// An incoming application message, classified by its `MsgType(35)`.
+#[derive(Debug)]
+pub enum IncomingAppMessage {
+    /// An `ExecutionReport<8>` message.
+    ExecutionReport(Arc<MsgBuf>),
+    /// An `OrderCancelReject<9>` message.
+    OrderCancelReject(Arc<MsgBuf>),
+    /// A `News<B>` message.
+    News(Arc<MsgBuf>),
+    /// A `BusinessMessageReject<j>` message. A [`SessionEvent::BusinessRejectReceived`] is also
+    /// published for it.
+    ///
+    /// [`SessionEvent::BusinessRejectReceived`]: crate::SessionEvent::BusinessRejectReceived
+    BusinessMessageReject(Arc<MsgBuf>),
+    /// Any other message, including messages whose `MsgType(35)` could not be parsed.
+    Unknown(Arc<MsgBuf>),
+}
+
+impl IncomingAppMessage {
+    /// Get the underlying [`MsgBuf`] of the message.
+    pub fn msg_buf(&self) -> &Arc<MsgBuf> {
+        match self {
+            IncomingAppMessage::ExecutionReport(msg)
+            | IncomingAppMessage::OrderCancelReject(msg)
+            | IncomingAppMessage::News(msg)
+            | IncomingAppMessage::BusinessMessageReject(msg)
+            | IncomingAppMessage::Unknown(msg) => msg,
+        }
+    }
+}
+
+impl From<Arc<MsgBuf>> for IncomingAppMessage {
+    fn from(msg: Arc<MsgBuf>) -> IncomingAppMessage {
+        let mut cb = MsgTypeParser(None);
+        let _ = parse(&msg[..], &mut cb);
+        match cb.0.map(MsgType::try_from) {
+            Some(Ok(MsgType::EXECUTION_REPORT)) => IncomingAppMessage::ExecutionReport(msg),
+            Some(Ok(MsgType::ORDER_CANCEL_REJECT)) => IncomingAppMessage::OrderCancelReject(msg),
+            Some(Ok(MsgType::NEWS)) => IncomingAppMessage::News(msg),
+            Some(Ok(MsgType::BUSINESS_MESSAGE_REJECT)) => IncomingAppMessage::BusinessMessageReject(msg),
+            _ => IncomingAppMessage::Unknown(msg),
+        }
+    }
+}
+
+impl From<MsgBuf> for IncomingAppMessage {
+    fn from(msg: MsgBuf) -> IncomingAppMessage {
+        IncomingAppMessage::from(Arc::new(msg))
+    }
+}
+
+struct MsgTypeParser(Option<char>);
+
+impl<'a> ParserCallback<'a> for MsgTypeParser {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let (Ok(Tags::MsgType), [msg_type]) = (key.try_into(), value) {
+            self.0 = Some(*msg_type as char);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    fn body(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+/// A `NewOrderSingle<D>` message.
+///
+/// `TransactTime(60)` is set to the time the message is sent, which is also its `SendingTime(52)`,
+/// unless set with [`with_transact_time`]. A `Price(44)` should be set for limit orders.
+///
+/// [`with_transact_time`]: NewOrderSingle::with_transact_time
+#[derive(Debug)]
+pub struct NewOrderSingle {
+    cl_ord_id: String,
+    handl_inst: HandlInst,
+    symbol: String,
+    side: Side,
+    order_qty: FixDecimal,
+    ord_type: OrdType,
+    price: Option<FixDecimal>,
+    stop_px: Option<FixDecimal>,
+    time_in_force: Option<TimeInForce>,
+    account: Option<String>,
+    ex_destination: Option<String>,
+    text: Option<String>,
+    transact_time: Option<DateTime<Utc>>,
+}
+
+impl NewOrderSingle {
+    /// Create a `NewOrderSingle<D>` from its required fields.
+    pub fn new(
+        cl_ord_id: impl Into<String>,
+        handl_inst: HandlInst,
+        symbol: impl Into<String>,
+        side: Side,
+        order_qty: FixDecimal,
+        ord_type: OrdType,
+    ) -> NewOrderSingle {
+        NewOrderSingle {
+            cl_ord_id: cl_ord_id.into(),
+            handl_inst,
+            symbol: symbol.into(),
+            side,
+            order_qty,
+            ord_type,
+            price: None,
+            stop_px: None,
+            time_in_force: None,
+            account: None,
+            ex_destination: None,
+            text: None,
+            transact_time: None,
+        }
+    }
+
+    /// Set the `Price(44)` of the order.
+    pub fn with_price(mut self, price: FixDecimal) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    /// Set the `StopPx(99)` of the order.
+    pub fn with_stop_px(mut self, stop_px: FixDecimal) -> Self {
+        self.stop_px = Some(stop_px);
+        self
+    }
+
+    /// Set the `TimeInForce(59)` of the order.
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    /// Set the `Account(1)` of the order.
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account = Some(account.into());
+        self
+    }
+
+    /// Set the `ExDestination(100)` of the order.
+    pub fn with_ex_destination(mut self, ex_destination: impl Into<String>) -> Self {
+        self.ex_destination = Some(ex_destination.into());
+        self
+    }
+
+    /// Set the `Text(58)` of the order.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Set the `TransactTime(60)` of the order.
+    pub fn with_transact_time(mut self, transact_time: DateTime<Utc>) -> Self {
+        self.transact_time = Some(transact_time);
+        self
+    }
+
+    /// Convert the order into a [`MessageBuilder`] with `begin_string`.
+    pub fn into_builder(self, begin_string: &str) -> MessageBuilder {
+        let builder = MessageBuilder::new(begin_string, MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, self.cl_ord_id.as_bytes())
+            .push(Tags::HandlInst, self.handl_inst.into())
+            .push(Tags::Symbol, self.symbol.as_bytes())
+            .push(Tags::Side, self.side.into())
+            .push_fix_decimal(Tags::OrderQty, self.order_qty)
+            .push(Tags::OrdType, self.ord_type.into());
+        let builder = push_opt_decimal(builder, Tags::Price, self.price);
+        let builder = push_opt_decimal(builder, Tags::StopPx, self.stop_px);
+        let builder = push_opt(
+            builder,
+            Tags::TimeInForce,
+            self.time_in_force.map(<&[u8]>::from),
+        );
+        let builder = push_opt(builder, Tags::Account, self.account.as_deref());
+        let builder = push_opt(builder, Tags::ExDestination, self.ex_destination.as_deref());
+        let builder = push_opt(builder, Tags::Text, self.text.as_deref());
+        push_transact_time(builder, self.transact_time)
+    }
+}
+
+impl From<NewOrderSingle> for MessageBuilder {
+    /// Convert the order into a FIX 4.2 [`MessageBuilder`].
+    fn from(order: NewOrderSingle) -> MessageBuilder {
+        order.into_builder("FIX.4.2")
+    }
+}
+
+/// An `OrderCancelRequest<F>` message.
+///
+/// `TransactTime(60)` is set to the time the message is sent, which is also its `SendingTime(52)`,
+/// unless set with [`with_transact_time`].
+///
+/// [`with_transact_time`]: OrderCancelRequest::with_transact_time
+#[derive(Debug)]
+pub struct OrderCancelRequest {
+    orig_cl_ord_id: String,
+    cl_ord_id: String,
+    symbol: String,
+    side: Side,
+    order_qty: FixDecimal,
+    order_id: Option<String>,
+    account: Option<String>,
+    text: Option<String>,
+    transact_time: Option<DateTime<Utc>>,
+}
+
+impl OrderCancelRequest {
+    /// Create an `OrderCancelRequest<F>` from its required fields.
+    pub fn new(
+        orig_cl_ord_id: impl Into<String>,
+        cl_ord_id: impl Into<String>,
+        symbol: impl Into<String>,
+        side: Side,
+        order_qty: FixDecimal,
+    ) -> OrderCancelRequest {
+        OrderCancelRequest {
+            orig_cl_ord_id: orig_cl_ord_id.into(),
+            cl_ord_id: cl_ord_id.into(),
+            symbol: symbol.into(),
+            side,
+            order_qty,
+            order_id: None,
+            account: None,
+            text: None,
+            transact_time: None,
+        }
+    }
+
+    /// Set the `OrderID(37)` assigned to the order by the peer.
+    pub fn with_order_id(mut self, order_id: impl Into<String>) -> Self {
+        self.order_id = Some(order_id.into());
+        self
+    }
+
+    /// Set the `Account(1)` of the order.
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account = Some(account.into());
+        self
+    }
+
+    /// Set the `Text(58)` of the request.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Set the `TransactTime(60)` of the request.
+    pub fn with_transact_time(mut self, transact_time: DateTime<Utc>) -> Self {
+        self.transact_time = Some(transact_time);
+        self
+    }
+
+    /// Convert the request into a [`MessageBuilder`] with `begin_string`.
+    pub fn into_builder(self, begin_string: &str) -> MessageBuilder {
+        let builder = MessageBuilder::new(begin_string, MsgType::ORDER_CANCEL_REQUEST.into())
+            .push(Tags::OrigClOrdID, self.orig_cl_ord_id.as_bytes())
+            .push(Tags::ClOrdID, self.cl_ord_id.as_bytes())
+            .push(Tags::Symbol, self.symbol.as_bytes())
+            .push(Tags::Side, self.side.into())
+            .push_fix_decimal(Tags::OrderQty, self.order_qty);
+        let builder = push_opt(builder, Tags::OrderID, self.order_id.as_deref());
+        let builder = push_opt(builder, Tags::Account, self.account.as_deref());
+        let builder = push_opt(builder, Tags::Text, self.text.as_deref());
+        push_transact_time(builder, self.transact_time)
+    }
+}
+
+impl From<OrderCancelRequest> for MessageBuilder {
+    /// Convert the request into a FIX 4.2 [`MessageBuilder`].
+    fn from(request: OrderCancelRequest) -> MessageBuilder {
+        request.into_builder("FIX.4.2")
+    }
+}
+
+/// An `OrderCancelReplaceRequest<G>` message.
+///
+/// `TransactTime(60)` is set to the time the message is sent, which is also its `SendingTime(52)`,
+/// unless set with [`with_transact_time`].
+///
+/// [`with_transact_time`]: OrderCancelReplaceRequest::with_transact_time
+#[derive(Debug)]
+pub struct OrderCancelReplaceRequest {
+    orig_cl_ord_id: String,
+    cl_ord_id: String,
+    handl_inst: HandlInst,
+    symbol: String,
+    side: Side,
+    order_qty: FixDecimal,
+    ord_type: OrdType,
+    price: Option<FixDecimal>,
+    stop_px: Option<FixDecimal>,
+    time_in_force: Option<TimeInForce>,
+    order_id: Option<String>,
+    account: Option<String>,
+    text: Option<String>,
+    transact_time: Option<DateTime<Utc>>,
+}
+
+impl OrderCancelReplaceRequest {
+    /// Create an `OrderCancelReplaceRequest<G>` from its required fields.
+    pub fn new(
+        orig_cl_ord_id: impl Into<String>,
+        cl_ord_id: impl Into<String>,
+        handl_inst: HandlInst,
+        symbol: impl Into<String>,
+        side: Side,
+        order_qty: FixDecimal,
+        ord_type: OrdType,
+    ) -> OrderCancelReplaceRequest {
+        OrderCancelReplaceRequest {
+            orig_cl_ord_id: orig_cl_ord_id.into(),
+            cl_ord_id: cl_ord_id.into(),
+            handl_inst,
+            symbol: symbol.into(),
+            side,
+            order_qty,
+            ord_type,
+            price: None,
+            stop_px: None,
+            time_in_force: None,
+            order_id: None,
+            account: None,
+            text: None,
+            transact_time: None,
+        }
+    }
+
+    /// Set the new `Price(44)` of the order.
+    pub fn with_price(mut self, price: FixDecimal) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    /// Set the new `StopPx(99)` of the order.
+    pub fn with_stop_px(mut self, stop_px: FixDecimal) -> Self {
+        self.stop_px = Some(stop_px);
+        self
+    }
+
+    /// Set the new `TimeInForce(59)` of the order.
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    /// Set the `OrderID(37)` assigned to the order by the peer.
+    pub fn with_order_id(mut self, order_id: impl Into<String>) -> Self {
+        self.order_id = Some(order_id.into());
+        self
+    }
+
+    /// Set the `Account(1)` of the order.
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account = Some(account.into());
+        self
+    }
+
+    /// Set the `Text(58)` of the request.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Set the `TransactTime(60)` of the request.
+    pub fn with_transact_time(mut self, transact_time: DateTime<Utc>) -> Self {
+        self.transact_time = Some(transact_time);
+        self
+    }
+
+    /// Convert the request into a [`MessageBuilder`] with `begin_string`.
+    pub fn into_builder(self, begin_string: &str) -> MessageBuilder {
+        let builder =
+            MessageBuilder::new(begin_string, MsgType::ORDER_CANCEL_REPLACE_REQUEST.into())
+                .push(Tags::OrigClOrdID, self.orig_cl_ord_id.as_bytes())
+                .push(Tags::ClOrdID, self.cl_ord_id.as_bytes())
+                .push(Tags::HandlInst, self.handl_inst.into())
+                .push(Tags::Symbol, self.symbol.as_bytes())
+                .push(Tags::Side, self.side.into())
+                .push_fix_decimal(Tags::OrderQty, self.order_qty)
+                .push(Tags::OrdType, self.ord_type.into());
+        let builder = push_opt_decimal(builder, Tags::Price, self.price);
+        let builder = push_opt_decimal(builder, Tags::StopPx, self.stop_px);
+        let builder = push_opt(
+            builder,
+            Tags::TimeInForce,
+            self.time_in_force.map(<&[u8]>::from),
+        );
+        let builder = push_opt(builder, Tags::OrderID, self.order_id.as_deref());
+        let builder = push_opt(builder, Tags::Account, self.account.as_deref());
+        let builder = push_opt(builder, Tags::Text, self.text.as_deref());
+        push_transact_time(builder, self.transact_time)
+    }
+}
+
+impl From<OrderCancelReplaceRequest> for MessageBuilder {
+    /// Convert the request into a FIX 4.2 [`MessageBuilder`].
+    fn from(request: OrderCancelReplaceRequest) -> MessageBuilder {
+        request.into_builder("FIX.4.2")
+    }
+}
+
+/// An `OrderStatusRequest<H>` message.
+#[derive(Debug)]
+pub struct OrderStatusRequest {
+    cl_ord_id: String,
+    symbol: String,
+    side: Side,
+    order_id: Option<String>,
+}
+
+impl OrderStatusRequest {
+    /// Create an `OrderStatusRequest<H>` from its required fields.
+    pub fn new(
+        cl_ord_id: impl Into<String>,
+        symbol: impl Into<String>,
+        side: Side,
+    ) -> OrderStatusRequest {
+        OrderStatusRequest {
+            cl_ord_id: cl_ord_id.into(),
+            symbol: symbol.into(),
+            side,
+            order_id: None,
+        }
+    }
+
+    /// Set the `OrderID(37)` assigned to the order by the peer.
+    pub fn with_order_id(mut self, order_id: impl Into<String>) -> Self {
+        self.order_id = Some(order_id.into());
+        self
+    }
+
+    /// Convert the request into a [`MessageBuilder`] with `begin_string`.
+    pub fn into_builder(self, begin_string: &str) -> MessageBuilder {
+        let builder = MessageBuilder::new(begin_string, MsgType::ORDER_STATUS_REQUEST.into())
+            .push(Tags::ClOrdID, self.cl_ord_id.as_bytes())
+            .push(Tags::Symbol, self.symbol.as_bytes())
+            .push(Tags::Side, self.side.into());
+        push_opt(builder, Tags::OrderID, self.order_id.as_deref())
+    }
+}
+
+impl From<OrderStatusRequest> for MessageBuilder {
+    /// Convert the request into a FIX 4.2 [`MessageBuilder`].
+    fn from(request: OrderStatusRequest) -> MessageBuilder {
+        request.into_builder("FIX.4.2")
+    }
+}
+
+/// A `BusinessMessageReject<j>` message, to reject an application message that cannot be
+/// processed, such as one of an unsupported `MsgType(35)`, or one referring to an unknown ID.
+///
+/// [`for_message`] fills in the `RefSeqNum(45)`, `RefMsgType(372)` and `BusinessRejectRefID(379)`
+/// of the rejected message.
+///
+/// ```
+/// use forgefix::fix::encode::MessageBuilder;
+/// use forgefix::fix::generated::BusinessRejectReason;
+/// use forgefix::fix::mem::MsgBuf;
+/// use forgefix::fix::messages::BusinessMessageReject;
+///
+/// # fn main() -> Result<(), forgefix::fix::decode::DecodeError> {
+/// let quote_request = MsgBuf::from(b"8=FIX.4.2\x019=20\x0135=R\x0134=12\x01131=req-1\x0110=000\x01".to_vec());
+/// // RefSeqNum(45)=12 and RefMsgType(372)=R
+/// let reject: MessageBuilder =
+///     BusinessMessageReject::for_message(&quote_request, BusinessRejectReason::UNSUPPORTED_MESSAGE_TYPE)?
+///         .with_text("quotes are not supported")
+///         .into();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`for_message`]: BusinessMessageReject::for_message
+#[derive(Debug)]
+pub struct BusinessMessageReject {
+    ref_msg_type: String,
+    business_reject_reason: u32,
+    ref_seq_num: Option<u32>,
+    business_reject_ref_id: Option<String>,
+    text: Option<String>,
+}
+
+impl BusinessMessageReject {
+    /// Create a `BusinessMessageReject<j>` from its required fields.
+    pub fn new(ref_msg_type: char, business_reject_reason: BusinessRejectReason) -> BusinessMessageReject {
+        BusinessMessageReject {
+            ref_msg_type: ref_msg_type.to_string(),
+            business_reject_reason: business_reject_reason as u32,
+            ref_seq_num: None,
+            business_reject_ref_id: None,
+            text: None,
+        }
+    }
+
+    /// Create a `BusinessMessageReject<j>` rejecting `msg`, with its `MsgSeqNum(34)` as the
+    /// `RefSeqNum(45)`, its `MsgType(35)` as the `RefMsgType(372)`, and its `ClOrdID(11)`, if
+    /// any, as the `BusinessRejectRefID(379)`.
+    ///
+    /// Returns an error if `msg` cannot be parsed, or has no `MsgType(35)`.
+    pub fn for_message(
+        msg: &MsgBuf,
+        business_reject_reason: BusinessRejectReason,
+    ) -> Result<BusinessMessageReject, DecodeError> {
+        let msg = FixMessage::parse(msg)?;
+        let ref_msg_type = msg
+            .get(Tags::MsgType)
+            .ok_or(DecodeError::MissingTag(Tags::MsgType))?;
+        Ok(BusinessMessageReject {
+            ref_msg_type: std::str::from_utf8(ref_msg_type)?.to_string(),
+            business_reject_reason: business_reject_reason as u32,
+            ref_seq_num: msg.get_as(Tags::MsgSeqNum).and_then(Result::ok),
+            business_reject_ref_id: msg
+                .get(Tags::ClOrdID)
+                .map(|cl_ord_id| String::from_utf8_lossy(cl_ord_id).into_owned()),
+            text: None,
+        })
+    }
+
+    /// Set the `RefSeqNum(45)` of the rejected message.
+    pub fn with_ref_seq_num(mut self, ref_seq_num: u32) -> Self {
+        self.ref_seq_num = Some(ref_seq_num);
+        self
+    }
+
+    /// Set the `BusinessRejectRefID(379)`, the business-level ID of the rejected message, such as
+    /// its `ClOrdID(11)`.
+    pub fn with_business_reject_ref_id(mut self, business_reject_ref_id: impl Into<String>) -> Self {
+        self.business_reject_ref_id = Some(business_reject_ref_id.into());
+        self
+    }
+
+    /// Set the `Text(58)` of the reject.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Convert the reject into a [`MessageBuilder`] with `begin_string`.
+    pub fn into_builder(self, begin_string: &str) -> MessageBuilder {
+        let builder = MessageBuilder::new(begin_string, MsgType::BUSINESS_MESSAGE_REJECT.into());
+        let builder = push_opt(
+            builder,
+            Tags::RefSeqNum,
+            self.ref_seq_num.map(SerializedInt::from).as_ref().map(SerializedInt::as_bytes),
+        );
+        let builder = builder
+            .push(Tags::RefMsgType, self.ref_msg_type.as_bytes())
+            .push(
+                Tags::BusinessRejectReason,
+                SerializedInt::from(self.business_reject_reason).as_bytes(),
+            );
+        let builder = push_opt(builder, Tags::BusinessRejectRefID, self.business_reject_ref_id.as_deref());
+        push_opt(builder, Tags::Text, self.text.as_deref())
+    }
+}
+
+impl From<BusinessMessageReject> for MessageBuilder {
+    /// Convert the reject into a FIX 4.2 [`MessageBuilder`].
+    fn from(reject: BusinessMessageReject) -> MessageBuilder {
+        reject.into_builder("FIX.4.2")
+    }
+}
+
+fn push_opt<T: AsRef<[u8]>>(
+    builder: MessageBuilder,
+    tag: Tags,
+    value: Option<T>,
+) -> MessageBuilder {
+    match value {
+        Some(value) => builder.push(tag, value.as_ref()),
+        None => builder,
+    }
+}
+
+fn push_opt_decimal(builder: MessageBuilder, tag: Tags, value: Option<FixDecimal>) -> MessageBuilder {
+    match value {
+        Some(value) => builder.push_fix_decimal(tag, value),
+        None => builder,
+    }
+}
+
+fn push_transact_time(
+    builder: MessageBuilder,
+    transact_time: Option<DateTime<Utc>>,
+) -> MessageBuilder {
+    match transact_time {
+        Some(transact_time) => builder.push(
+            Tags::TransactTime,
+            transact_time.format(TIME_FORMAT).to_string().as_bytes(),
+        ),
+        None => builder.push_deferred_time(Tags::TransactTime),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_incoming_app_message_from_msg_buf() {
+        let exec_report = MsgBuf::from(b"8=FIX.4.2\x019=5\x0135=8\x0110=000\x01".to_vec());
+        assert!(matches!(
+            IncomingAppMessage::from(exec_report),
+            IncomingAppMessage::ExecutionReport(_)
+        ));
+
+        let cancel_reject = MsgBuf::from(b"8=FIX.4.2\x019=5\x0135=9\x0110=000\x01".to_vec());
+        assert!(matches!(
+            IncomingAppMessage::from(cancel_reject),
+            IncomingAppMessage::OrderCancelReject(_)
+        ));
+
+        let news = MsgBuf::from(b"8=FIX.4.2\x019=5\x0135=B\x0110=000\x01".to_vec());
+        assert!(matches!(
+            IncomingAppMessage::from(news),
+            IncomingAppMessage::News(_)
+        ));
+
+        let reject = MsgBuf::from(b"8=FIX.4.2\x019=5\x0135=j\x0110=000\x01".to_vec());
+        assert!(matches!(
+            IncomingAppMessage::from(reject),
+            IncomingAppMessage::BusinessMessageReject(_)
+        ));
+
+        let order = MsgBuf::from(b"8=FIX.4.2\x019=5\x0135=D\x0110=000\x01".to_vec());
+        assert!(matches!(
+            IncomingAppMessage::from(order),
+            IncomingAppMessage::Unknown(_)
+        ));
+
+        let garbled = MsgBuf::from(b"8=FIX.4.2\x01garbage".to_vec());
+        let msg = IncomingAppMessage::from(garbled);
+        assert!(matches!(msg, IncomingAppMessage::Unknown(_)));
+        assert_eq!(&msg.msg_buf()[..], b"8=FIX.4.2\x01garbage");
+    }
+
+    #[test]
+    fn test_outgoing_messages() {
+        let transact_time = DateTime::parse_from_rfc3339("2024-01-02T10:00:00Z")
+            .unwrap()
+            .to_utc();
+        let order = NewOrderSingle::new(
+            "order-1",
+            HandlInst::AUTOMATED_EXECUTION_ORDER_PRIVATE_NO_BROKER_INTERVENTION,
+            "AAPL",
+            Side::BUY,
+            FixDecimal::new(100, 0),
+            OrdType::LIMIT,
+        )
+        .with_price(FixDecimal::new(18750, 2))
+        .with_time_in_force(TimeInForce::DAY)
+        .with_transact_time(transact_time)
+        .into_builder("FIX.4.2");
+        assert_eq!(order.msg_type(), MsgType::ORDER_SINGLE.into());
+        assert_eq!(order.field(Tags::ClOrdID.into()), Some(&b"order-1"[..]));
+        assert_eq!(order.field(Tags::HandlInst.into()), Some(&b"1"[..]));
+        assert_eq!(order.field(Tags::Side.into()), Some(&b"1"[..]));
+        assert_eq!(order.field(Tags::OrdType.into()), Some(&b"2"[..]));
+        assert_eq!(order.field(Tags::Price.into()), Some(&b"187.50"[..]));
+        assert_eq!(order.field(Tags::TimeInForce.into()), Some(&b"0"[..]));
+        assert_eq!(
+            order.field(Tags::TransactTime.into()),
+            Some(&b"20240102-10:00:00.000"[..])
+        );
+        assert_eq!(order.field(Tags::StopPx.into()), None);
+
+        let cancel: MessageBuilder =
+            OrderCancelRequest::new("order-1", "order-1.C1", "AAPL", Side::BUY, FixDecimal::new(100, 0)).into();
+        assert_eq!(cancel.msg_type(), MsgType::ORDER_CANCEL_REQUEST.into());
+        assert_eq!(
+            cancel.field(Tags::OrigClOrdID.into()),
+            Some(&b"order-1"[..])
+        );
+        assert!(cancel.field(Tags::TransactTime.into()).is_some());
+
+        let replace: MessageBuilder = OrderCancelReplaceRequest::new(
+            "order-1",
+            "order-1.R1",
+            HandlInst::AUTOMATED_EXECUTION_ORDER_PRIVATE_NO_BROKER_INTERVENTION,
+            "AAPL",
+            Side::BUY,
+            FixDecimal::new(200, 0),
+            OrdType::LIMIT,
+        )
+        .with_price(FixDecimal::new(18725, 2))
+        .into();
+        assert_eq!(
+            replace.msg_type(),
+            MsgType::ORDER_CANCEL_REPLACE_REQUEST.into()
+        );
+        assert_eq!(replace.field(Tags::OrderQty.into()), Some(&b"200"[..]));
+
+        let status: MessageBuilder = OrderStatusRequest::new("order-1", "AAPL", Side::BUY)
+            .with_order_id("broker-1")
+            .into();
+        assert_eq!(status.msg_type(), MsgType::ORDER_STATUS_REQUEST.into());
+        assert_eq!(status.field(Tags::OrderID.into()), Some(&b"broker-1"[..]));
+
+        let order = MsgBuf::from(b"8=FIX.4.2\x019=5\x0135=D\x0134=7\x0111=order-1\x0110=000\x01".to_vec());
+        let reject: MessageBuilder =
+            BusinessMessageReject::for_message(&order, BusinessRejectReason::APPLICATION_NOT_AVAILABLE)
+                .unwrap()
+                .into();
+        assert_eq!(reject.msg_type(), MsgType::BUSINESS_MESSAGE_REJECT.into());
+        assert_eq!(reject.field(Tags::RefSeqNum.into()), Some(&b"7"[..]));
+        assert_eq!(reject.field(Tags::RefMsgType.into()), Some(&b"D"[..]));
+        assert_eq!(reject.field(Tags::BusinessRejectReason.into()), Some(&b"4"[..]));
+        assert_eq!(reject.field(Tags::BusinessRejectRefID.into()), Some(&b"order-1"[..]));
+        assert_eq!(reject.field(Tags::Text.into()), None);
+    }
+}
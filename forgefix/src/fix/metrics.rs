@@ -0,0 +1,333 @@
+use crate::{SessionHealth, SessionMetrics};
+
+use chrono::{DateTime, Utc};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Counts messages in one-second windows, to track the current rate and the largest burst.
+struct Rate {
+    start: Instant,
+    window: AtomicU64,
+    count: AtomicU64,
+    last_count: AtomicU64,
+    max_count: AtomicU64,
+}
+
+impl Default for Rate {
+    fn default() -> Rate {
+        Rate {
+            start: Instant::now(),
+            window: Default::default(),
+            count: Default::default(),
+            last_count: Default::default(),
+            max_count: Default::default(),
+        }
+    }
+}
+
+impl Rate {
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_secs()
+    }
+
+    fn record(&self) {
+        let now = self.now();
+        let window = self.window.load(Ordering::Relaxed);
+        if now != window
+            && self
+                .window
+                .compare_exchange(window, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let count = self.count.swap(0, Ordering::Relaxed);
+            let last_count = if now == window + 1 { count } else { 0 };
+            self.last_count.store(last_count, Ordering::Relaxed);
+        }
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        self.max_count.fetch_max(count, Ordering::Relaxed);
+    }
+
+    // The number of messages in the last complete window.
+    fn per_second(&self) -> u64 {
+        let now = self.now();
+        let window = self.window.load(Ordering::Relaxed);
+        if now == window {
+            self.last_count.load(Ordering::Relaxed)
+        } else if now == window + 1 {
+            self.count.load(Ordering::Relaxed)
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Metrics {
+    messages_received: AtomicU64,
+    checksums_skipped: AtomicU64,
+    garbled_messages_received: AtomicU64,
+    rejected_messages_received: AtomicU64,
+    test_requests_answered: AtomicU64,
+    unmatched_test_req_ids: AtomicU64,
+    last_test_request_round_trip_us: AtomicU64,
+    // smoothed like the round-trip time of TCP, giving each new sample a weight of 1/8
+    average_test_request_round_trip_us: AtomicU64,
+    max_test_request_round_trip_us: AtomicU64,
+    // milliseconds since the unix epoch, or 0 before the first message
+    last_message_received_ms: AtomicU64,
+    received_rate: Rate,
+    messages_sent: AtomicU64,
+    last_send_latency_us: AtomicU64,
+    max_send_latency_us: AtomicU64,
+    resend_requests_received: AtomicU64,
+    messages_resent: AtomicU64,
+    gap_fills_sent: AtomicU64,
+    // milliseconds since the unix epoch, or 0 before the first heartbeat
+    last_heartbeat_received_ms: AtomicU64,
+    // The metrics of every session with the same counterparty, which are updated along with these.
+    parent: Option<Arc<Metrics>>,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Metrics {
+        Default::default()
+    }
+
+    pub(crate) fn with_parent(parent: Arc<Metrics>) -> Metrics {
+        Metrics {
+            parent: Some(parent),
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn incr_messages_received(&self) -> u64 {
+        if let Some(parent) = &self.parent {
+            parent.incr_messages_received();
+        }
+        self.received_rate.record();
+        self.last_message_received_ms
+            .store(Utc::now().timestamp_millis().max(1) as u64, Ordering::Relaxed);
+        self.messages_received.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub(super) fn incr_checksums_skipped(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_checksums_skipped();
+        }
+        self.checksums_skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn incr_garbled_messages_received(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_garbled_messages_received();
+        }
+        self.garbled_messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn incr_rejected_messages_received(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_rejected_messages_received();
+        }
+        self.rejected_messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_test_request_answered(&self, round_trip: Duration) {
+        if let Some(parent) = &self.parent {
+            parent.record_test_request_answered(round_trip);
+        }
+        let round_trip_us = round_trip.as_micros() as u64;
+        self.last_test_request_round_trip_us
+            .store(round_trip_us, Ordering::Relaxed);
+        self.max_test_request_round_trip_us
+            .fetch_max(round_trip_us, Ordering::Relaxed);
+        let first = self.test_requests_answered.load(Ordering::Relaxed) == 0;
+        let _ = self.average_test_request_round_trip_us.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |average_us| match first {
+                true => Some(round_trip_us),
+                false => Some(average_us - average_us / 8 + round_trip_us / 8),
+            },
+        );
+        self.test_requests_answered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn incr_unmatched_test_req_ids(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_unmatched_test_req_ids();
+        }
+        self.unmatched_test_req_ids.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // `latency` is how long the message took to be serialized and written to the connection.
+    pub(super) fn record_message_sent(&self, latency: Duration) {
+        if let Some(parent) = &self.parent {
+            parent.record_message_sent(latency);
+        }
+        let latency_us = latency.as_micros() as u64;
+        self.last_send_latency_us.store(latency_us, Ordering::Relaxed);
+        self.max_send_latency_us.fetch_max(latency_us, Ordering::Relaxed);
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn incr_resend_requests_received(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_resend_requests_received();
+        }
+        self.resend_requests_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn incr_messages_resent(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_messages_resent();
+        }
+        self.messages_resent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn incr_gap_fills_sent(&self) {
+        if let Some(parent) = &self.parent {
+            parent.incr_gap_fills_sent();
+        }
+        self.gap_fills_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_heartbeat_received(&self, at: DateTime<Utc>) {
+        if let Some(parent) = &self.parent {
+            parent.record_heartbeat_received(at);
+        }
+        self.last_heartbeat_received_ms
+            .store(at.timestamp_millis().max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> SessionMetrics {
+        let messages_sent = self.messages_sent.load(Ordering::Relaxed);
+        let last_heartbeat_received_ms = self.last_heartbeat_received_ms.load(Ordering::Relaxed);
+        let test_requests_answered = self.test_requests_answered.load(Ordering::Relaxed);
+        SessionMetrics {
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            checksums_skipped: self.checksums_skipped.load(Ordering::Relaxed),
+            garbled_messages_received: self.garbled_messages_received.load(Ordering::Relaxed),
+            rejected_messages_received: self.rejected_messages_received.load(Ordering::Relaxed),
+            test_requests_answered,
+            unmatched_test_req_ids: self.unmatched_test_req_ids.load(Ordering::Relaxed),
+            last_test_request_round_trip: (test_requests_answered > 0).then(|| {
+                Duration::from_micros(self.last_test_request_round_trip_us.load(Ordering::Relaxed))
+            }),
+            messages_received_per_second: self.received_rate.per_second(),
+            max_messages_received_per_second: self.received_rate.max_count.load(Ordering::Relaxed),
+            messages_sent,
+            last_send_latency: (messages_sent > 0).then(|| {
+                Duration::from_micros(self.last_send_latency_us.load(Ordering::Relaxed))
+            }),
+            max_send_latency: (messages_sent > 0).then(|| {
+                Duration::from_micros(self.max_send_latency_us.load(Ordering::Relaxed))
+            }),
+            resend_requests_received: self.resend_requests_received.load(Ordering::Relaxed),
+            messages_resent: self.messages_resent.load(Ordering::Relaxed),
+            gap_fills_sent: self.gap_fills_sent.load(Ordering::Relaxed),
+            last_heartbeat_received: (last_heartbeat_received_ms > 0)
+                .then(|| DateTime::from_timestamp_millis(last_heartbeat_received_ms as i64))
+                .flatten(),
+        }
+    }
+
+    // `heartbeat_interval` is the one negotiated with the peer, if it logged on.
+    pub(crate) fn health(&self, heartbeat_interval: Option<Duration>) -> SessionHealth {
+        let last_message_received_ms = self.last_message_received_ms.load(Ordering::Relaxed);
+        let last_message_received = (last_message_received_ms > 0)
+            .then(|| DateTime::from_timestamp_millis(last_message_received_ms as i64))
+            .flatten();
+        let test_requests_answered = self.test_requests_answered.load(Ordering::Relaxed) > 0;
+        let round_trip = |round_trip_us: &AtomicU64| {
+            test_requests_answered.then(|| Duration::from_micros(round_trip_us.load(Ordering::Relaxed)))
+        };
+        SessionHealth {
+            last_message_received,
+            since_last_message_received: last_message_received
+                .map(|at| (Utc::now() - at).to_std().unwrap_or_default()),
+            last_test_request_round_trip: round_trip(&self.last_test_request_round_trip_us),
+            average_test_request_round_trip: round_trip(&self.average_test_request_round_trip_us),
+            max_test_request_round_trip: round_trip(&self.max_test_request_round_trip_us),
+            heartbeat_interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parent_metrics() {
+        let parent = Arc::new(Metrics::new());
+        let first = Metrics::with_parent(Arc::clone(&parent));
+        let second = Metrics::with_parent(Arc::clone(&parent));
+
+        for _ in 0..3 {
+            first.incr_messages_received();
+        }
+        second.incr_messages_received();
+        second.incr_rejected_messages_received();
+
+        let snapshot = parent.snapshot();
+        assert_eq!(snapshot.messages_received, 4);
+        assert_eq!(snapshot.rejected_messages_received, 1);
+        assert_eq!(snapshot.max_messages_received_per_second, 4);
+        assert_eq!(first.snapshot().max_messages_received_per_second, 3);
+        assert_eq!(second.snapshot().rejected_messages_received, 1);
+        assert_eq!(first.snapshot().rejected_messages_received, 0);
+    }
+
+    #[test]
+    fn test_send_metrics() {
+        let metrics = Metrics::new();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.last_send_latency, None);
+        assert_eq!(snapshot.last_heartbeat_received, None);
+
+        metrics.record_message_sent(Duration::from_micros(30));
+        metrics.record_message_sent(Duration::from_micros(10));
+        metrics.incr_resend_requests_received();
+        metrics.incr_messages_resent();
+        metrics.incr_gap_fills_sent();
+        let heartbeat = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+        metrics.record_heartbeat_received(heartbeat);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.messages_sent, 2);
+        assert_eq!(snapshot.last_send_latency, Some(Duration::from_micros(10)));
+        assert_eq!(snapshot.max_send_latency, Some(Duration::from_micros(30)));
+        assert_eq!(snapshot.resend_requests_received, 1);
+        assert_eq!(snapshot.messages_resent, 1);
+        assert_eq!(snapshot.gap_fills_sent, 1);
+        assert_eq!(snapshot.last_heartbeat_received, Some(heartbeat));
+    }
+
+    #[test]
+    fn test_health() {
+        let metrics = Metrics::new();
+        let heartbeat_interval = Some(Duration::from_secs(30));
+        let health = metrics.health(heartbeat_interval);
+        assert_eq!(health.last_message_received, None);
+        assert_eq!(health.average_test_request_round_trip, None);
+        assert!(!health.is_overdue());
+
+        metrics.incr_messages_received();
+        metrics.record_test_request_answered(Duration::from_millis(80));
+        metrics.record_test_request_answered(Duration::from_millis(160));
+        let health = metrics.health(heartbeat_interval);
+        assert!(health.since_last_message_received.unwrap() < Duration::from_secs(1));
+        assert_eq!(health.last_test_request_round_trip, Some(Duration::from_millis(160)));
+        assert_eq!(health.average_test_request_round_trip, Some(Duration::from_millis(90)));
+        assert_eq!(health.max_test_request_round_trip, Some(Duration::from_millis(160)));
+        assert!(!health.is_overdue());
+
+        let health = SessionHealth {
+            since_last_message_received: Some(Duration::from_secs(31)),
+            ..health
+        };
+        assert!(health.is_overdue());
+    }
+}
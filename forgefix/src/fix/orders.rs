@@ -0,0 +1,727 @@
+//! Open order tracking and bulk cancels
+//!
+//! [`OrderTracker`] follows the orders sent in a session through the `ExecutionReport<8>` and
+//! `OrderCancelReject<9>` messages of the peer, and keeps the list of orders that are still open.
+//! [`OrderClient`] combines a tracker with a [`FixApplicationHandle`], and can cancel every open
+//! order with [`OrderClient::cancel_all_open_orders`]. [`OrderClient::order_status`] asks the peer
+//! for the state of a single order, such as after a reconnect.
+//!
+//! # Example
+//!
+//! ```
+//! use forgefix::{SessionSettings, FixApplicationInitiator, ApplicationError};
+//! use forgefix::fix::encode::MessageBuilder;
+//! use forgefix::fix::generated::{MsgType, Tags};
+//! use forgefix::fix::orders::{CancelResult, OrderClient, StatusResult};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! #    let settings = SessionSettings::builder()
+//! #        .with_sender_comp_id("my_id")
+//! #        .with_target_comp_id("peer_id")
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
+//! #        .build()?;
+//! let (handle, receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate()
+//!     .await?;
+//! let (client, mut receiver) = OrderClient::new(handle, receiver);
+//! tokio::spawn(async move {
+//!     while let Some(msg) = receiver.recv().await {
+//!         println!("got an application message: {}", msg);
+//!     }
+//! });
+//! client.handle().start_async().await?;
+//!
+//! let order = MessageBuilder::new(&client.handle().begin_string(), MsgType::ORDER_SINGLE.into())
+//!     .push(Tags::ClOrdID, b"order-1")
+//!     .push(Tags::Symbol, b"AAPL")
+//!     .push(Tags::Side, b"1")
+//!     .push(Tags::OrderQty, b"100");
+//! client.send_message_async(order).await?;
+//!
+//! if let StatusResult::Report(report) = client.order_status("order-1", None, "AAPL", '1').await {
+//!     println!("order-1 is {}", report);
+//! }
+//!
+//! for outcome in client.cancel_all_open_orders(|order| order.symbol == "AAPL").await {
+//!     println!("{}: {:?}", outcome.cl_ord_id, outcome.result);
+//! #   assert!(matches!(outcome.result, CancelResult::Canceled));
+//! }
+//! client.handle().end_async().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::encode::{formatted_time, MessageBuilder};
+use crate::fix::generated::{ExecTransType, MsgType, OrdStatus, Tags, UnsolicitedIndicator};
+use crate::fix::mem::MsgBuf;
+use crate::{ApplicationError, FixApplicationHandle};
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+
+/// An order that has been sent and is not yet filled, canceled, rejected or otherwise done.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenOrder {
+    /// The `ClOrdID(11)` the order is currently known by. This changes when a cancel/replace
+    /// request is accepted, and is the `OrigClOrdID(41)` of the next cancel or replace request.
+    pub cl_ord_id: String,
+    /// The `Symbol(55)` of the order.
+    pub symbol: String,
+    /// The `Side(54)` of the order.
+    pub side: char,
+    /// The `OrderQty(38)` of the order.
+    pub order_qty: String,
+    /// The last `CumQty(14)` reported by the peer.
+    pub cum_qty: String,
+    /// The last `OrdStatus(39)` reported by the peer, or `None` if the order was not yet
+    /// acknowledged.
+    pub ord_status: Option<char>,
+    /// The `ClOrdID(11)` of an outstanding `OrderCancelRequest<F>` for the order.
+    pub pending_cancel: Option<String>,
+}
+
+/// How a cancel sent by [`OrderClient::cancel_all_open_orders`] ended.
+#[derive(Debug)]
+pub enum CancelResult {
+    /// The peer canceled the order.
+    Canceled,
+    /// The order was filled before the cancel took effect.
+    Filled,
+    /// The order was done for another reason, such as expiring, before the cancel took effect.
+    Closed,
+    /// The peer rejected the cancel, with the `Text(58)` of its `OrderCancelReject<9>`.
+    Rejected(Option<String>),
+    /// The cancel could not be sent.
+    Failed(ApplicationError),
+    /// The peer did not answer the cancel in time. The order may still be open.
+    TimedOut,
+}
+
+/// The result of canceling a single order. See [`OrderClient::cancel_all_open_orders`].
+#[derive(Debug)]
+pub struct CancelOutcome {
+    /// The `ClOrdID(11)` of the order.
+    pub cl_ord_id: String,
+    /// The `ClOrdID(11)` of the `OrderCancelRequest<F>`, if one was sent.
+    pub cancel_cl_ord_id: Option<String>,
+    /// How the cancel ended.
+    pub result: CancelResult,
+}
+
+/// How a request sent by [`OrderClient::order_status`] ended.
+#[derive(Debug)]
+pub enum StatusResult {
+    /// The `ExecutionReport<8>` the peer answered with.
+    Report(Arc<MsgBuf>),
+    /// The `OrderStatusRequest<H>` could not be sent.
+    Failed(ApplicationError),
+    /// The peer did not answer in time.
+    TimedOut,
+}
+
+// The value of `ExecType(150)` in the reply to an `OrderStatusRequest<H>` from FIX 4.3 on, where
+// `ExecTransType(20)` was removed.
+const EXEC_TYPE_ORDER_STATUS: char = 'I';
+
+enum PendingRequest {
+    Cancel,
+    Replace,
+}
+
+/// Tracks the open orders of a session.
+///
+/// Feed every sent message to [`OrderTracker::on_sent`] and every received application message
+/// to [`OrderTracker::on_received`]. [`OrderClient`] does both automatically.
+#[derive(Default)]
+pub struct OrderTracker {
+    orders: HashMap<String, OpenOrder>,
+    // The `ClOrdID(11)` of each outstanding cancel or replace request, mapped to the `ClOrdID(11)`
+    // of the order it applies to.
+    pending: HashMap<String, (String, PendingRequest)>,
+    cancel_waiters: HashMap<String, oneshot::Sender<CancelResult>>,
+    status_waiters: HashMap<String, Vec<oneshot::Sender<Arc<MsgBuf>>>>,
+}
+
+impl OrderTracker {
+    pub fn new() -> OrderTracker {
+        Default::default()
+    }
+
+    /// The orders that are currently open.
+    pub fn open_orders(&self) -> Vec<OpenOrder> {
+        self.orders.values().cloned().collect()
+    }
+
+    /// Record a message sent to the peer. `NewOrderSingle<D>` messages open an order, and
+    /// `OrderCancelRequest<F>` and `OrderCancelReplaceRequest<G>` messages become pending on the
+    /// order of their `OrigClOrdID(41)`.
+    pub fn on_sent(&mut self, builder: &MessageBuilder) {
+        let field = |tag: Tags| {
+            builder
+                .field(tag.into())
+                .map(|value| String::from_utf8_lossy(value).into_owned())
+        };
+        let Some(cl_ord_id) = field(Tags::ClOrdID) else {
+            return;
+        };
+        match MsgType::try_from(builder.msg_type()) {
+            Ok(MsgType::ORDER_SINGLE) => {
+                let order = OpenOrder {
+                    cl_ord_id: cl_ord_id.clone(),
+                    symbol: field(Tags::Symbol).unwrap_or_default(),
+                    side: field(Tags::Side).and_then(|s| s.chars().next()).unwrap_or_default(),
+                    order_qty: field(Tags::OrderQty).unwrap_or_default(),
+                    cum_qty: String::from("0"),
+                    ord_status: None,
+                    pending_cancel: None,
+                };
+                self.orders.insert(cl_ord_id, order);
+            }
+            Ok(MsgType::ORDER_CANCEL_REQUEST) => {
+                let Some(orig_cl_ord_id) = field(Tags::OrigClOrdID) else {
+                    return;
+                };
+                if let Some(order) = self.orders.get_mut(&orig_cl_ord_id) {
+                    order.pending_cancel = Some(cl_ord_id.clone());
+                }
+                self.pending.insert(cl_ord_id, (orig_cl_ord_id, PendingRequest::Cancel));
+            }
+            Ok(MsgType::ORDER_CANCEL_REPLACE_REQUEST) => {
+                let Some(orig_cl_ord_id) = field(Tags::OrigClOrdID) else {
+                    return;
+                };
+                self.pending.insert(cl_ord_id, (orig_cl_ord_id, PendingRequest::Replace));
+            }
+            _ => {}
+        }
+    }
+
+    /// Record a message received from the peer. Messages other than `ExecutionReport<8>` and
+    /// `OrderCancelReject<9>` are ignored.
+    pub fn on_received(&mut self, msg: &[u8]) {
+        let mut report = OrderFields::default();
+        if parse(msg, &mut report).is_err() {
+            return;
+        }
+        let Some(cl_ord_id) = report.cl_ord_id.clone() else {
+            return;
+        };
+        match report.msg_type.map(MsgType::try_from) {
+            Some(Ok(MsgType::EXECUTION_REPORT)) => self.on_execution_report(cl_ord_id, report),
+            Some(Ok(MsgType::ORDER_CANCEL_REJECT)) => self.on_cancel_reject(cl_ord_id, report),
+            _ => {}
+        }
+    }
+
+    fn on_execution_report(&mut self, cl_ord_id: String, report: OrderFields) {
+        let ord_status = report.ord_status.and_then(|s| OrdStatus::try_from(s).ok());
+        let key = match self.pending.get(&cl_ord_id) {
+            Some((orig_cl_ord_id, PendingRequest::Replace))
+                if ord_status != Some(OrdStatus::PENDING_REPLACE) =>
+            {
+                // The replace was accepted, so the order is known by its new ClOrdID from now on.
+                let orig_cl_ord_id = orig_cl_ord_id.clone();
+                self.pending.remove(&cl_ord_id);
+                if let Some(mut order) = self.orders.remove(&orig_cl_ord_id) {
+                    order.cl_ord_id = cl_ord_id.clone();
+                    self.orders.insert(cl_ord_id.clone(), order);
+                }
+                cl_ord_id
+            }
+            Some((orig_cl_ord_id, _)) => orig_cl_ord_id.clone(),
+            None => cl_ord_id,
+        };
+        let Some(order) = self.orders.get_mut(&key) else {
+            return;
+        };
+        if let Some(cum_qty) = report.cum_qty {
+            order.cum_qty = cum_qty;
+        }
+        order.ord_status = report.ord_status;
+
+        let result = match ord_status {
+            Some(OrdStatus::CANCELED) => CancelResult::Canceled,
+            Some(OrdStatus::FILLED) => CancelResult::Filled,
+            Some(OrdStatus::DONE_FOR_DAY | OrdStatus::REJECTED | OrdStatus::EXPIRED) => {
+                CancelResult::Closed
+            }
+            _ => return,
+        };
+        if let Some(order) = self.orders.remove(&key) {
+            if let Some(cancel_cl_ord_id) = order.pending_cancel {
+                self.resolve_cancel(&cancel_cl_ord_id, result);
+            }
+        }
+    }
+
+    fn on_cancel_reject(&mut self, cl_ord_id: String, report: OrderFields) {
+        let Some((orig_cl_ord_id, _)) = self.pending.remove(&cl_ord_id) else {
+            return;
+        };
+        // A cancel is usually rejected because the order was filled while it was in flight.
+        let closed = match report.ord_status.and_then(|s| OrdStatus::try_from(s).ok()) {
+            Some(OrdStatus::FILLED) => Some(CancelResult::Filled),
+            Some(OrdStatus::CANCELED | OrdStatus::DONE_FOR_DAY | OrdStatus::EXPIRED) => {
+                Some(CancelResult::Closed)
+            }
+            _ => None,
+        };
+        let result = match closed {
+            Some(result) => {
+                self.orders.remove(&orig_cl_ord_id);
+                result
+            }
+            None => {
+                if let Some(order) = self.orders.get_mut(&orig_cl_ord_id) {
+                    if order.pending_cancel.as_ref() == Some(&cl_ord_id) {
+                        order.pending_cancel = None;
+                    }
+                }
+                CancelResult::Rejected(report.text)
+            }
+        };
+        self.resolve_cancel(&cl_ord_id, result);
+    }
+
+    fn resolve_cancel(&mut self, cancel_cl_ord_id: &str, result: CancelResult) {
+        self.pending.remove(cancel_cl_ord_id);
+        if let Some(waiter) = self.cancel_waiters.remove(cancel_cl_ord_id) {
+            let _ = waiter.send(result);
+        }
+    }
+
+    // Start a cancel of the order currently known by `cl_ord_id`. Returns `None` if the order is
+    // no longer open or already has a cancel outstanding.
+    fn begin_cancel(
+        &mut self,
+        cl_ord_id: &str,
+        cancel_cl_ord_id: &str,
+        begin_string: &str,
+    ) -> Option<(MessageBuilder, oneshot::Receiver<CancelResult>)> {
+        let order = self.orders.get(cl_ord_id)?;
+        if order.pending_cancel.is_some() {
+            return None;
+        }
+        let builder = cancel_request(begin_string, order, cancel_cl_ord_id);
+        self.on_sent(&builder);
+        let (sender, receiver) = oneshot::channel();
+        self.cancel_waiters.insert(cancel_cl_ord_id.to_string(), sender);
+        Some((builder, receiver))
+    }
+
+    // Wait for the reply to an `OrderStatusRequest<H>` for `cl_ord_id`.
+    fn begin_status(&mut self, cl_ord_id: &str) -> oneshot::Receiver<Arc<MsgBuf>> {
+        let (sender, receiver) = oneshot::channel();
+        self.status_waiters
+            .entry(cl_ord_id.to_string())
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    fn abandon_status(&mut self, cl_ord_id: &str) {
+        if let Some(waiters) = self.status_waiters.get_mut(cl_ord_id) {
+            waiters.retain(|waiter| !waiter.is_closed());
+            if waiters.is_empty() {
+                self.status_waiters.remove(cl_ord_id);
+            }
+        }
+    }
+
+    // Resolve the waiters of `msg` if it is the reply to an `OrderStatusRequest<H>`: an
+    // `ExecutionReport<8>` with a status `ExecTransType(20)` or `ExecType(150)` that was not sent
+    // unsolicited.
+    fn resolve_status(&mut self, msg: &Arc<MsgBuf>) {
+        if self.status_waiters.is_empty() {
+            return;
+        }
+        let mut report = OrderFields::default();
+        if parse(&msg[..], &mut report).is_err()
+            || report.msg_type != Some(MsgType::EXECUTION_REPORT.into())
+            || report.unsolicited == Some(UnsolicitedIndicator::YES.into())
+        {
+            return;
+        }
+        let is_status = report.exec_trans_type == Some(ExecTransType::STATUS.into())
+            || report.exec_type == Some(EXEC_TYPE_ORDER_STATUS);
+        if !is_status {
+            return;
+        }
+        let Some(waiters) = report
+            .cl_ord_id
+            .and_then(|cl_ord_id| self.status_waiters.remove(&cl_ord_id))
+        else {
+            return;
+        };
+        for waiter in waiters {
+            let _ = waiter.send(Arc::clone(msg));
+        }
+    }
+
+    fn abandon_cancel(&mut self, cancel_cl_ord_id: &str) {
+        self.cancel_waiters.remove(cancel_cl_ord_id);
+        if let Some((orig_cl_ord_id, _)) = self.pending.remove(cancel_cl_ord_id) {
+            if let Some(order) = self.orders.get_mut(&orig_cl_ord_id) {
+                order.pending_cancel = None;
+            }
+        }
+    }
+}
+
+fn cancel_request(begin_string: &str, order: &OpenOrder, cancel_cl_ord_id: &str) -> MessageBuilder {
+    let mut side = [0; 4];
+    MessageBuilder::new(begin_string, MsgType::ORDER_CANCEL_REQUEST.into())
+        .push(Tags::OrigClOrdID, order.cl_ord_id.as_bytes())
+        .push(Tags::ClOrdID, cancel_cl_ord_id.as_bytes())
+        .push(Tags::Symbol, order.symbol.as_bytes())
+        .push(Tags::Side, order.side.encode_utf8(&mut side).as_bytes())
+        .push(Tags::OrderQty, order.order_qty.as_bytes())
+        .push(Tags::TransactTime, formatted_time().as_bytes())
+}
+
+#[derive(Default)]
+struct OrderFields {
+    msg_type: Option<char>,
+    cl_ord_id: Option<String>,
+    ord_status: Option<char>,
+    cum_qty: Option<String>,
+    text: Option<String>,
+    exec_trans_type: Option<char>,
+    exec_type: Option<char>,
+    unsolicited: Option<char>,
+}
+
+impl<'a> ParserCallback<'a> for OrderFields {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let (Ok(Tags::MsgType), [msg_type]) = (key.try_into(), value) {
+            self.msg_type = Some(*msg_type as char);
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        let string = || Some(String::from_utf8_lossy(value).into_owned());
+        match key.try_into() {
+            Ok(Tags::ClOrdID) => self.cl_ord_id = string(),
+            Ok(Tags::OrdStatus) => self.ord_status = value.first().map(|b| *b as char),
+            Ok(Tags::CumQty) => self.cum_qty = string(),
+            Ok(Tags::Text) => self.text = string(),
+            Ok(Tags::ExecTransType) => self.exec_trans_type = value.first().map(|b| *b as char),
+            Ok(Tags::ExecType) => self.exec_type = value.first().map(|b| *b as char),
+            Ok(Tags::UnsolicitedIndicator) => self.unsolicited = value.first().map(|b| *b as char),
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+const DEFAULT_CANCEL_INTERVAL: Duration = Duration::from_millis(10);
+const DEFAULT_CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A [`FixApplicationHandle`] that tracks the orders sent through it.
+///
+/// Orders must be sent with [`OrderClient::send_message_async`] to be tracked.
+pub struct OrderClient {
+    handle: FixApplicationHandle,
+    tracker: Arc<Mutex<OrderTracker>>,
+    cancel_interval: Duration,
+    cancel_timeout: Duration,
+    status_timeout: Duration,
+    next_cancel_id: AtomicU64,
+}
+
+impl OrderClient {
+    /// Wrap `handle`, and track the messages of `receiver`. Every message is passed on to the
+    /// returned receiver after it was tracked.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn new(
+        handle: FixApplicationHandle,
+        mut receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+    ) -> (OrderClient, mpsc::UnboundedReceiver<Arc<MsgBuf>>) {
+        let tracker = Arc::new(Mutex::new(OrderTracker::new()));
+        let (sender, forwarded) = mpsc::unbounded_channel();
+        let task_tracker = Arc::clone(&tracker);
+        tokio::spawn(async move {
+            while let Some(msg) = receiver.recv().await {
+                let mut tracker = task_tracker.lock().unwrap();
+                tracker.on_received(&msg[..]);
+                tracker.resolve_status(&msg);
+                drop(tracker);
+                let _ = sender.send(msg);
+            }
+        });
+        let client = OrderClient {
+            handle,
+            tracker,
+            cancel_interval: DEFAULT_CANCEL_INTERVAL,
+            cancel_timeout: DEFAULT_CANCEL_TIMEOUT,
+            status_timeout: DEFAULT_STATUS_TIMEOUT,
+            next_cancel_id: AtomicU64::new(1),
+        };
+        (client, forwarded)
+    }
+
+    /// The minimum time between two cancels sent by [`OrderClient::cancel_all_open_orders`].
+    /// Defaults to 10 milliseconds.
+    pub fn with_cancel_interval(mut self, cancel_interval: Duration) -> Self {
+        self.set_cancel_interval(cancel_interval);
+        self
+    }
+    pub fn set_cancel_interval(&mut self, cancel_interval: Duration) {
+        self.cancel_interval = cancel_interval;
+    }
+
+    /// How long [`OrderClient::cancel_all_open_orders`] waits for the peer to answer the cancels
+    /// after the last one was sent. Defaults to 5 seconds.
+    pub fn with_cancel_timeout(mut self, cancel_timeout: Duration) -> Self {
+        self.set_cancel_timeout(cancel_timeout);
+        self
+    }
+    pub fn set_cancel_timeout(&mut self, cancel_timeout: Duration) {
+        self.cancel_timeout = cancel_timeout;
+    }
+
+    /// How long [`OrderClient::order_status`] waits for the peer to answer. Defaults to 5
+    /// seconds.
+    pub fn with_status_timeout(mut self, status_timeout: Duration) -> Self {
+        self.set_status_timeout(status_timeout);
+        self
+    }
+    pub fn set_status_timeout(&mut self, status_timeout: Duration) {
+        self.status_timeout = status_timeout;
+    }
+
+    /// The underlying [`FixApplicationHandle`].
+    pub fn handle(&self) -> &FixApplicationHandle {
+        &self.handle
+    }
+
+    /// The orders that are currently open.
+    pub fn open_orders(&self) -> Vec<OpenOrder> {
+        self.tracker.lock().unwrap().open_orders()
+    }
+
+    /// Track the message in `builder`, then send it like
+    /// [`FixApplicationHandle::send_message_async`].
+    pub async fn send_message_async(&self, builder: MessageBuilder) -> Result<(), ApplicationError> {
+        self.tracker.lock().unwrap().on_sent(&builder);
+        self.handle.send_message_async(builder).await
+    }
+
+    /// Send an `OrderStatusRequest<H>` for the order with `cl_ord_id`, and wait for the
+    /// `ExecutionReport<8>` the peer answers with.
+    ///
+    /// The reply is the first `ExecutionReport<8>` with the same `ClOrdID(11)` and a status
+    /// `ExecTransType(20)`, or a status `ExecType(150)` from FIX 4.3 on, that is not flagged with
+    /// `UnsolicitedIndicator(325)`. The order does not need to be tracked by the client, so this
+    /// can reconcile orders whose state is unknown after a reconnect. Like every other message,
+    /// the reply is also passed on to the receiver returned by [`OrderClient::new`].
+    pub async fn order_status(
+        &self,
+        cl_ord_id: &str,
+        order_id: Option<&str>,
+        symbol: &str,
+        side: char,
+    ) -> StatusResult {
+        let mut side_buf = [0; 4];
+        let mut builder = MessageBuilder::new(
+            &self.handle.begin_string(),
+            MsgType::ORDER_STATUS_REQUEST.into(),
+        )
+        .push(Tags::ClOrdID, cl_ord_id.as_bytes());
+        if let Some(order_id) = order_id {
+            builder = builder.push(Tags::OrderID, order_id.as_bytes());
+        }
+        let builder = builder
+            .push(Tags::Symbol, symbol.as_bytes())
+            .push(Tags::Side, side.encode_utf8(&mut side_buf).as_bytes());
+
+        // Wait before sending, so a fast reply is not missed.
+        let receiver = self.tracker.lock().unwrap().begin_status(cl_ord_id);
+        if let Err(err) = self.handle.send_message_async(builder).await {
+            drop(receiver);
+            self.tracker.lock().unwrap().abandon_status(cl_ord_id);
+            return StatusResult::Failed(err);
+        }
+        let result = match tokio::time::timeout(self.status_timeout, receiver).await {
+            Ok(Ok(report)) => StatusResult::Report(report),
+            Ok(Err(_)) | Err(_) => StatusResult::TimedOut,
+        };
+        self.tracker.lock().unwrap().abandon_status(cl_ord_id);
+        result
+    }
+
+    /// Send an `OrderCancelRequest<F>` for every open order accepted by `filter`, and wait for
+    /// the peer to answer each of them.
+    ///
+    /// Cancels are sent at most once per cancel interval, and each cancel references the
+    /// `ClOrdID(11)` the order is currently known by, following any accepted replaces. Orders
+    /// that already have a cancel outstanding are skipped. An outcome is returned for every other
+    /// order accepted by `filter`, including orders that were filled before their cancel took
+    /// effect.
+    pub async fn cancel_all_open_orders(
+        &self,
+        filter: impl Fn(&OpenOrder) -> bool,
+    ) -> Vec<CancelOutcome> {
+        let orders: Vec<OpenOrder> = self
+            .open_orders()
+            .into_iter()
+            .filter(|order| order.pending_cancel.is_none() && filter(order))
+            .collect();
+
+        let begin_string = self.handle.begin_string();
+        let mut outcomes = Vec::new();
+        let mut in_flight = Vec::new();
+        let mut interval = tokio::time::interval(self.cancel_interval);
+        for order in orders {
+            interval.tick().await;
+            let cancel_cl_ord_id = format!(
+                "{}.C{}",
+                order.cl_ord_id,
+                self.next_cancel_id.fetch_add(1, Ordering::Relaxed)
+            );
+            let begun = self
+                .tracker
+                .lock()
+                .unwrap()
+                .begin_cancel(&order.cl_ord_id, &cancel_cl_ord_id, &begin_string);
+            let Some((builder, receiver)) = begun else {
+                // The order was done since the list of open orders was taken.
+                outcomes.push(CancelOutcome {
+                    cl_ord_id: order.cl_ord_id,
+                    cancel_cl_ord_id: None,
+                    result: CancelResult::Closed,
+                });
+                continue;
+            };
+            match self.handle.send_message_async(builder).await {
+                Ok(()) => in_flight.push((order.cl_ord_id, cancel_cl_ord_id, receiver)),
+                Err(err) => {
+                    self.tracker.lock().unwrap().abandon_cancel(&cancel_cl_ord_id);
+                    outcomes.push(CancelOutcome {
+                        cl_ord_id: order.cl_ord_id,
+                        cancel_cl_ord_id: Some(cancel_cl_ord_id),
+                        result: CancelResult::Failed(err),
+                    });
+                }
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + self.cancel_timeout;
+        for (cl_ord_id, cancel_cl_ord_id, receiver) in in_flight {
+            let result = match tokio::time::timeout_at(deadline, receiver).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(_)) | Err(_) => CancelResult::TimedOut,
+            };
+            outcomes.push(CancelOutcome {
+                cl_ord_id,
+                cancel_cl_ord_id: Some(cancel_cl_ord_id),
+                result,
+            });
+        }
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn order(cl_ord_id: &str) -> MessageBuilder {
+        MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, cl_ord_id.as_bytes())
+            .push(Tags::Symbol, b"AAA")
+            .push(Tags::Side, b"1")
+            .push(Tags::OrderQty, b"100")
+    }
+
+    fn execution_report(cl_ord_id: &str, ord_status: &str, cum_qty: &str) -> Vec<u8> {
+        format!("8=FIX.4.2\x019=5\x0135=8\x0111={cl_ord_id}\x0139={ord_status}\x0114={cum_qty}\x0110=000\x01")
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_order_tracker() {
+        let mut tracker = OrderTracker::new();
+        tracker.on_sent(&order("A"));
+        tracker.on_sent(&order("B"));
+        tracker.on_received(&execution_report("A", "0", "0"));
+        tracker.on_received(&execution_report("B", "1", "40"));
+        assert_eq!(tracker.open_orders().len(), 2);
+
+        // A replace of A is accepted, so A is canceled by its new ClOrdID.
+        tracker.on_sent(
+            &MessageBuilder::new("FIX.4.2", MsgType::ORDER_CANCEL_REPLACE_REQUEST.into())
+                .push(Tags::OrigClOrdID, b"A")
+                .push(Tags::ClOrdID, b"A2"),
+        );
+        tracker.on_received(&execution_report("A2", "0", "0"));
+        let (builder, mut canceled) = tracker.begin_cancel("A2", "A2.C1", "FIX.4.2").unwrap();
+        assert_eq!(builder.field(Tags::OrigClOrdID.into()), Some(&b"A2"[..]));
+        assert_eq!(builder.field(Tags::Symbol.into()), Some(&b"AAA"[..]));
+        assert!(tracker.begin_cancel("A2", "A2.C2", "FIX.4.2").is_none());
+        tracker.on_received(&execution_report("A2.C1", "6", "0"));
+        assert!(canceled.try_recv().is_err());
+        tracker.on_received(&execution_report("A2.C1", "4", "0"));
+        assert!(matches!(canceled.try_recv(), Ok(CancelResult::Canceled)));
+
+        // B fills while its cancel is in flight, and the cancel is rejected.
+        let (_, mut filled) = tracker.begin_cancel("B", "B.C3", "FIX.4.2").unwrap();
+        tracker.on_received(
+            b"8=FIX.4.2\x019=5\x0135=9\x0111=B.C3\x0141=B\x0139=2\x0158=too late\x0110=000\x01",
+        );
+        assert!(matches!(filled.try_recv(), Ok(CancelResult::Filled)));
+        assert!(tracker.open_orders().is_empty());
+
+        // A rejected cancel leaves the order open, and it can be canceled again.
+        tracker.on_sent(&order("C"));
+        let (_, mut rejected) = tracker.begin_cancel("C", "C.C4", "FIX.4.2").unwrap();
+        tracker.on_received(b"8=FIX.4.2\x019=5\x0135=9\x0111=C.C4\x0141=C\x0139=0\x0158=no\x0110=000\x01");
+        assert!(matches!(rejected.try_recv(), Ok(CancelResult::Rejected(Some(text))) if text == "no"));
+        assert!(tracker.begin_cancel("C", "C.C5", "FIX.4.2").is_some());
+    }
+
+    #[test]
+    fn test_order_status() {
+        let mut tracker = OrderTracker::new();
+        let mut status = tracker.begin_status("A");
+        let report = |fields: &str| {
+            Arc::new(MsgBuf(
+                format!("8=FIX.4.2\x019=5\x0135=8\x0111=A\x0139=1\x01{fields}10=000\x01").into_bytes(),
+            ))
+        };
+
+        // Only a status report that was not sent unsolicited answers the request.
+        tracker.resolve_status(&report("20=0\x01"));
+        tracker.resolve_status(&report("20=3\x01325=Y\x01"));
+        assert!(status.try_recv().is_err());
+        tracker.resolve_status(&report("20=3\x01"));
+        assert_eq!(status.try_recv().unwrap().0, report("20=3\x01").0);
+
+        // From FIX 4.3 on, the reply is flagged by its ExecType.
+        let mut status = tracker.begin_status("A");
+        tracker.resolve_status(&report("150=I\x01"));
+        assert!(status.try_recv().is_ok());
+
+        drop(tracker.begin_status("A"));
+        tracker.abandon_status("A");
+        assert!(tracker.status_waiters.is_empty());
+    }
+}
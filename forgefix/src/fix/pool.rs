@@ -0,0 +1,71 @@
+use std::sync::Mutex;
+
+// the most buffers kept for reuse
+const MAX_POOLED: usize = 256;
+// buffers that grew past this size are dropped instead of kept, so that one large message does
+// not pin its memory for the life of the session
+const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+pub(crate) const BUFFER_CAPACITY: usize = 1024;
+
+// The body buffers of the `MessageBuilder`s handed out by `FixApplicationHandle::builder`,
+// shared between the handles of a session. A pooled builder gives its buffer back when it is
+// dropped, which is right after the engine wrote its message.
+#[derive(Debug, Default)]
+pub(crate) struct BuilderPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BuilderPool {
+    // Takes an empty buffer from the pool, or allocates one if the pool is empty.
+    pub(crate) fn take(&self) -> Vec<u8> {
+        self.buffers
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(BUFFER_CAPACITY))
+    }
+
+    pub(crate) fn give(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < MAX_POOLED {
+            buffers.push(buffer);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.buffers.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fix::encode::MessageBuilder;
+    use crate::fix::generated::{MsgType, Tags};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_builder_pool() {
+        let pool = Arc::new(BuilderPool::default());
+        let builder = MessageBuilder::pooled("FIX.4.2", MsgType::ORDER_SINGLE.into(), &pool)
+            .push(Tags::ClOrdID, b"order-1");
+        assert_eq!(builder.field(Tags::ClOrdID.into()), Some(&b"order-1"[..]));
+        drop(builder);
+        assert_eq!(pool.len(), 1);
+
+        let builder = MessageBuilder::pooled("FIX.4.2", MsgType::ORDER_SINGLE.into(), &pool);
+        assert_eq!(pool.len(), 0);
+        assert_eq!(builder.field(Tags::ClOrdID.into()), None);
+        drop(builder);
+
+        pool.give(Vec::with_capacity(MAX_POOLED_CAPACITY + 1));
+        assert_eq!(pool.len(), 1);
+        drop(MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into()));
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::generated::Tags;
+
+use std::collections::{HashSet, VecDeque};
+
+// The IDs of the last application messages received, to tell whether a message with
+// `PossResend(97)=Y` was already received under another `MsgSeqNum(34)`. A message is identified
+// by its `MsgType(35)` and its `ExecID(17)`, or its `ClOrdID(11)` if it has no `ExecID(17)`.
+pub(super) struct PossResendWindow {
+    capacity: usize,
+    order: VecDeque<Vec<u8>>,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl PossResendWindow {
+    pub(super) fn new(capacity: usize) -> PossResendWindow {
+        PossResendWindow {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    // Remember the ID of `msg`. Returns `None` if `msg` does not have `PossResend(97)=Y`, or
+    // whether its ID was already seen if it does.
+    pub(super) fn check(&mut self, msg: &[u8]) -> Option<bool> {
+        let mut cb = PossResendParser::default();
+        parse(msg, &mut cb).ok()?;
+        let id = cb.id();
+        let duplicate = id.as_ref().is_some_and(|id| self.seen.contains(id));
+        if let Some(id) = id.filter(|_| !duplicate && self.capacity > 0) {
+            if self.order.len() == self.capacity {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.seen.remove(&oldest);
+                }
+            }
+            self.seen.insert(id.clone());
+            self.order.push_back(id);
+        }
+        cb.poss_resend.then_some(duplicate)
+    }
+}
+
+#[derive(Default)]
+struct PossResendParser<'a> {
+    msg_type: &'a [u8],
+    poss_resend: bool,
+    exec_id: Option<&'a [u8]>,
+    cl_ord_id: Option<&'a [u8]>,
+}
+
+impl PossResendParser<'_> {
+    fn id(&self) -> Option<Vec<u8>> {
+        let id = self.exec_id.or(self.cl_ord_id)?;
+        Some([self.msg_type, b"\x01", id].concat())
+    }
+}
+
+impl<'a> ParserCallback<'a> for PossResendParser<'a> {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        match key.try_into() {
+            Ok(Tags::MsgType) => self.msg_type = value,
+            Ok(Tags::PossResend) => self.poss_resend = value == b"Y",
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        match key.try_into() {
+            Ok(Tags::ExecID) => self.exec_id = Some(value),
+            Ok(Tags::ClOrdID) => self.cl_ord_id = Some(value),
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_poss_resend_window() {
+        let report = |exec_id: &str, poss_resend: bool| {
+            let poss_resend = if poss_resend { "97=Y\x01" } else { "" };
+            format!("8=FIX.4.2\x019=5\x0135=8\x0134=2\x01{poss_resend}11=order-1\x0117={exec_id}\x0110=000\x01")
+                .into_bytes()
+        };
+        let mut window = PossResendWindow::new(2);
+        assert_eq!(window.check(&report("exec-1", false)), None);
+        assert_eq!(window.check(&report("exec-2", false)), None);
+        assert_eq!(window.check(&report("exec-1", true)), Some(true));
+        assert_eq!(window.check(&report("exec-3", true)), Some(false));
+        // only the last 2 IDs are remembered
+        assert_eq!(window.check(&report("exec-1", true)), Some(false));
+
+        // an order is identified by its ClOrdID(11)
+        let order = b"8=FIX.4.2\x019=5\x0135=D\x0134=3\x0197=Y\x0111=order-1\x0110=000\x01";
+        assert_eq!(window.check(order), Some(false));
+        assert_eq!(window.check(order), Some(true));
+    }
+}
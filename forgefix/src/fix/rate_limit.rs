@@ -0,0 +1,111 @@
+use crate::RateLimit;
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+// A token bucket holding up to `burst` messages, refilled at `messages_per_second`. It is shared
+// between the handles of an engine that refuses the messages over the limit, and owned by the
+// engine when it delays them instead.
+pub(crate) struct RateLimiter {
+    per_message: Duration,
+    burst: u32,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: u32,
+    // when the next token is added, if the bucket is not full
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate_limit: &RateLimit) -> RateLimiter {
+        let burst = rate_limit.burst.max(1);
+        RateLimiter {
+            per_message: Duration::from_secs(1) / rate_limit.messages_per_second.max(1),
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    // Take `n` tokens, or none of them if there are not enough. Returns false in that case.
+    pub(crate) fn try_acquire(&self, n: u32) -> bool {
+        self.try_acquire_at(n, Instant::now())
+    }
+
+    // When the next token is available.
+    pub(crate) fn ready_at(&self) -> Instant {
+        self.ready_at_from(Instant::now())
+    }
+
+    fn try_acquire_at(&self, n: u32, now: Instant) -> bool {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, now);
+        if bucket.tokens < n {
+            return false;
+        }
+        bucket.tokens -= n;
+        true
+    }
+
+    fn ready_at_from(&self, now: Instant) -> Instant {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, now);
+        if bucket.tokens > 0 {
+            now
+        } else {
+            bucket.refilled + self.per_message
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        if bucket.tokens >= self.burst {
+            bucket.refilled = now;
+            return;
+        }
+        let elapsed = now.saturating_duration_since(bucket.refilled);
+        let added = elapsed.as_nanos() / self.per_message.as_nanos().max(1);
+        let added = u32::try_from(added).unwrap_or(u32::MAX);
+        bucket.tokens = bucket.tokens.saturating_add(added).min(self.burst);
+        if bucket.tokens >= self.burst {
+            bucket.refilled = now;
+        } else {
+            bucket.refilled += self.per_message * added;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::RateLimitExceeded;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(&RateLimit {
+            messages_per_second: 10,
+            burst: 3,
+            exceeded: RateLimitExceeded::Delay,
+        });
+        let start = limiter.bucket.lock().unwrap().refilled;
+        let at = |millis| start + Duration::from_millis(millis);
+        assert!(!limiter.try_acquire_at(4, at(0)));
+        assert!(limiter.try_acquire_at(2, at(0)));
+        assert!(limiter.try_acquire_at(1, at(0)));
+        assert!(!limiter.try_acquire_at(1, at(0)));
+        assert_eq!(limiter.ready_at_from(at(0)), at(100));
+
+        assert!(limiter.try_acquire_at(2, at(250)));
+        assert!(!limiter.try_acquire_at(1, at(250)));
+        assert_eq!(limiter.ready_at_from(at(250)), at(300));
+
+        // the bucket does not fill up past the burst
+        assert!(limiter.try_acquire_at(3, at(10_000)));
+        assert!(!limiter.try_acquire_at(1, at(10_000)));
+    }
+}
@@ -0,0 +1,293 @@
+//! Read the messages a FIX engine has stored
+//!
+//! Every message an engine sends is kept in its store, to be resent if the peer asks for it, and
+//! so is every application message it receives, unless
+//! [`with_store_incoming`](crate::SessionSettingsBuilder::with_store_incoming) is turned off. A
+//! [`StoreReader`] opens the store of a [`SessionSettings`] to read those messages back, such as
+//! to rebuild the state of the orders sent before the application restarted, or to reconcile
+//! with everything the peer sent. It can be used whether or not an engine is running with the
+//! same settings.
+//!
+//! A [`MessageQuery`] selects messages by `MsgSeqNum(34)`, by the time they were sent or
+//! received, and by `MsgType(35)`. Messages are returned in the order they were stored.
+//!
+//! ```
+//! use forgefix::{ApplicationError, SessionSettings};
+//! use forgefix::fix::generated::MsgType;
+//! use forgefix::fix::replay::{MessageQuery, StoreReader};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! let settings = SessionSettings::builder()
+//!     .with_sender_comp_id("my_id")
+//!     .with_target_comp_id("peer_id")
+//! #   .with_store_path(peer.store_path())
+//! #   .with_log_dir(peer.log_dir())
+//! #   .with_socket_addr(peer.addr())
+//!     .build()?;
+//! let reader = StoreReader::open(&settings)?;
+//! let query = MessageQuery::new()
+//!     .with_msg_type(MsgType::ORDER_SINGLE.into())
+//!     .with_msg_type(MsgType::ORDER_CANCEL_REQUEST.into());
+//! for stored in reader.query(&query).await? {
+//!     println!("{} sent at {}: {}", stored.msg_seq_num, stored.time, stored.msg);
+//! }
+//! let fills = MessageQuery::new().with_msg_type(MsgType::EXECUTION_REPORT.into());
+//! for stored in reader.query_incoming(&fills).await? {
+//!     println!("{} received at {}: {}", stored.msg_seq_num, stored.time, stored.msg);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SessionSettings`]: crate::SessionSettings
+
+use crate::fix::crypto::StoreCipher;
+use crate::fix::mem::MsgBuf;
+use crate::fix::memory_store::MemoryStore;
+use crate::fix::store::{self, Direction};
+use crate::{ApplicationError, SessionSettings};
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+/// Reads the messages kept in the store of a FIX engine. See the
+/// [module documentation](self).
+pub struct StoreReader {
+    source: Source,
+    epoch: Arc<String>,
+    cipher: Option<StoreCipher>,
+}
+
+enum Source {
+    Sqlite { store_path: PathBuf, shard_by_date: bool },
+    Memory(MemoryStore),
+}
+
+/// The messages to read with a [`StoreReader`]. Every message matches an empty query.
+#[derive(Clone, Debug, Default)]
+pub struct MessageQuery {
+    seq_nums: Option<(u32, u32)>,
+    times: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    msg_types: Vec<char>,
+}
+
+/// A message read from the store.
+#[derive(Debug)]
+pub struct StoredMessage {
+    /// The `MsgSeqNum(34)` of the message.
+    pub msg_seq_num: u32,
+    /// When the message was written to the connection, or received from it, to the millisecond.
+    pub time: DateTime<Utc>,
+    /// The `MsgType(35)` of the message.
+    pub msg_type: char,
+    /// The message, as it was sent or received.
+    pub msg: MsgBuf,
+}
+
+impl StoreReader {
+    /// Open the store of `settings`: its [memory store], or the sqlite file at its store path,
+    /// with every shard if it is [sharded by date]. The messages of the epoch of `settings` are
+    /// read.
+    ///
+    /// [memory store]: crate::SessionSettingsBuilder::with_memory_store
+    /// [sharded by date]: crate::SessionSettingsBuilder::with_store_shard_by_date
+    pub fn open(settings: &SessionSettings) -> Result<StoreReader, ApplicationError> {
+        let source = match settings.memory_store {
+            Some(ref memory_store) => Source::Memory(memory_store.clone()),
+            None => Source::Sqlite {
+                store_path: settings.store_path.clone(),
+                shard_by_date: settings.store_shard_by_date,
+            },
+        };
+        let cipher = match settings.store_encryption {
+            Some(ref secret_provider) => {
+                Some(StoreCipher::new(&secret_provider.store_encryption_key(&settings.epoch)?))
+            }
+            None => None,
+        };
+        Ok(StoreReader {
+            source,
+            epoch: Arc::clone(&settings.epoch),
+            cipher,
+        })
+    }
+
+    /// Read the sent messages that match `query`, in the order they were sent.
+    ///
+    /// Fails if a message cannot be read, such as when the store is encrypted and `settings`
+    /// has no key for it, or the wrong one.
+    ///
+    /// After a sequence reset, the store holds more than one message with the same
+    /// `MsgSeqNum(34)`, and all of them are returned.
+    pub async fn query(&self, query: &MessageQuery) -> Result<Vec<StoredMessage>, ApplicationError> {
+        self.read(query, Direction::Outgoing).await
+    }
+
+    /// Read the received application messages that match `query`, in the order they were
+    /// received. Session messages, such as heartbeats, are not stored.
+    pub async fn query_incoming(&self, query: &MessageQuery) -> Result<Vec<StoredMessage>, ApplicationError> {
+        self.read(query, Direction::Incoming).await
+    }
+
+    async fn read(
+        &self,
+        query: &MessageQuery,
+        direction: Direction,
+    ) -> Result<Vec<StoredMessage>, ApplicationError> {
+        let messages = match self.source {
+            Source::Memory(ref memory_store) => memory_store.read_messages(&self.epoch, direction),
+            Source::Sqlite {
+                ref store_path,
+                shard_by_date,
+            } => {
+                let mut messages = Vec::new();
+                for path in store::store_files(store_path, shard_by_date) {
+                    let read = store::read_messages(
+                        &path,
+                        self.cipher.as_ref(),
+                        Arc::clone(&self.epoch),
+                        direction,
+                        query.seq_nums,
+                        query.times,
+                    )
+                    .await
+                    .map_err(std::io::Error::other)?;
+                    messages.extend(read);
+                }
+                messages
+            }
+        };
+        let mut matched = Vec::new();
+        for (msg_seq_num, time, msg) in messages {
+            // an encrypted message read without the key of the store
+            let msg_type = msg_type(&msg).ok_or_else(|| {
+                std::io::Error::other(format!(
+                    "message {msg_seq_num} is not a FIX message, the store may be encrypted"
+                ))
+            })?;
+            if query.matches(msg_seq_num, time, msg_type) {
+                matched.push(StoredMessage {
+                    msg_seq_num,
+                    time,
+                    msg_type,
+                    msg: MsgBuf(msg),
+                });
+            }
+        }
+        Ok(matched)
+    }
+}
+
+impl MessageQuery {
+    /// Create a query that matches every message.
+    pub fn new() -> MessageQuery {
+        MessageQuery::default()
+    }
+
+    /// Only match the messages sent with a `MsgSeqNum(34)` from `begin` to `end`, inclusive.
+    pub fn with_seq_range(mut self, begin: u32, end: u32) -> MessageQuery {
+        self.set_seq_range(begin, end);
+        self
+    }
+    pub fn set_seq_range(&mut self, begin: u32, end: u32) {
+        self.seq_nums = Some((begin, end));
+    }
+
+    /// Only match the messages sent or received from `from` to `to`, inclusive.
+    pub fn with_time_range(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> MessageQuery {
+        self.set_time_range(from, to);
+        self
+    }
+    pub fn set_time_range(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) {
+        self.times = Some((from, to));
+    }
+
+    /// Only match the messages of `msg_type`. Adding more than one type matches the messages of
+    /// any of them.
+    pub fn with_msg_type(mut self, msg_type: char) -> MessageQuery {
+        self.set_msg_type(msg_type);
+        self
+    }
+    pub fn set_msg_type(&mut self, msg_type: char) {
+        self.msg_types.push(msg_type);
+    }
+
+    fn matches(&self, msg_seq_num: u32, time: DateTime<Utc>, msg_type: char) -> bool {
+        self.seq_nums
+            .is_none_or(|(begin, end)| (begin..=end).contains(&msg_seq_num))
+            && self
+                .times
+                .is_none_or(|(from, to)| from <= time && time <= to)
+            && (self.msg_types.is_empty() || self.msg_types.contains(&msg_type))
+    }
+}
+
+// The `MsgType(35)` of a stored message, which the engine always writes as its third field, or of
+// a received message that passed validation, which checks that it is the third field.
+pub(crate) fn msg_type(msg: &[u8]) -> Option<char> {
+    match msg.split(|b| *b == b'\x01').nth(2)? {
+        [b'3', b'5', b'=', msg_type] => Some(*msg_type as char),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_store_reader() {
+        let memory_store = MemoryStore::new();
+        let epoch = "my_id_peer_id";
+        let sent = Utc::now();
+        let msg = |msg_type: char| format!("8=FIX.4.2\x019=5\x0135={msg_type}\x0110=000\x01").into_bytes();
+        memory_store.store_outgoing(epoch, 1, sent, &msg('A'), false);
+        memory_store.store_outgoing(epoch, 2, sent + Duration::seconds(1), &msg('D'), false);
+        memory_store.store_outgoing(epoch, 3, sent + Duration::seconds(2), &msg('F'), false);
+        memory_store.store_outgoing(epoch, 4, sent + Duration::seconds(3), &msg('D'), false);
+        memory_store.store_incoming(epoch, 2, sent + Duration::seconds(1), &msg('8'));
+
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_memory_store(memory_store.clone())
+            .with_log_dir(std::env::temp_dir())
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .build()
+            .unwrap();
+        let reader = StoreReader::open(&settings).unwrap();
+        let seq_nums = |query: MessageQuery| {
+            let reader = &reader;
+            async move {
+                let stored = reader.query(&query).await.unwrap();
+                stored.iter().map(|m| m.msg_seq_num).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(seq_nums(MessageQuery::new()).await, vec![1, 2, 3, 4]);
+        assert_eq!(seq_nums(MessageQuery::new().with_seq_range(2, 3)).await, vec![2, 3]);
+        let query = MessageQuery::new().with_msg_type('D').with_msg_type('F');
+        assert_eq!(seq_nums(query).await, vec![2, 3, 4]);
+        let query = MessageQuery::new()
+            .with_msg_type('D')
+            .with_time_range(sent, sent + Duration::seconds(2));
+        assert_eq!(seq_nums(query).await, vec![2]);
+
+        let stored = reader.query(&MessageQuery::new().with_seq_range(4, 4)).await.unwrap();
+        assert_eq!(stored[0].msg_type, 'D');
+        assert_eq!(stored[0].msg.0, msg('D'));
+
+        let received = reader.query_incoming(&MessageQuery::new()).await.unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!((received[0].msg_seq_num, received[0].msg_type), (2, '8'));
+
+        // a message that cannot be read is not skipped
+        memory_store.store_incoming(epoch, 3, sent + Duration::seconds(2), b"\x8f\x02ciphertext");
+        assert!(reader.query_incoming(&MessageQuery::new()).await.is_err());
+    }
+}
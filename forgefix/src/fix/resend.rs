@@ -1,9 +1,8 @@
 use crate::fix::checksum::AsyncChecksumWriter;
 use crate::fix::decode::ParsedPeek;
-use crate::fix::encode::{SerializedInt, SOH, TIME_FORMAT};
+use crate::fix::encode::{SerializedInt, SOH};
 use crate::fix::SessionError;
 use anyhow::Result;
-use chrono::offset::Utc;
 use std::str;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
@@ -25,7 +24,11 @@ impl Transformer {
         &self.msg[self.sending_time_start..self.sending_time_end]
     }
 
-    pub(super) async fn build_async<'a, W>(self, sink: W) -> Result<(), SessionError>
+    pub(super) async fn build_async<'a, W>(
+        self,
+        sink: W,
+        new_sending_time: &str,
+    ) -> Result<(), SessionError>
     where
         W: AsyncWrite + Unpin,
     {
@@ -39,7 +42,6 @@ impl Transformer {
 
         // get the original sending time and new sending time
         let orig_sending_time: &[u8] = self.original_sending_time();
-        let new_sending_time = format!("{}", Utc::now().format(TIME_FORMAT));
 
         // calc the new sending time len
         let new_sending_time_len = new_sending_time.len() as u32;
@@ -92,7 +94,7 @@ impl TryFrom<Vec<u8>> for Transformer {
             len_end,
             fixed_fields_end,
             ..
-        } = crate::fix::decode::parse_peeked_prefix(&msg[..])?;
+        } = crate::fix::decode::parse_peeked_prefix(&msg)?;
         let (sending_time_start, sending_time_end) = sending_time_indices(&msg);
         Ok(Transformer {
             msg,
@@ -147,7 +149,7 @@ mod test {
             let t: Transformer = in_msg.to_vec().try_into().unwrap();
             let mut buf = Vec::new();
             let mut cur = tokio::io::BufWriter::new(&mut buf);
-            t.build_async(&mut cur).await.expect("building");
+            t.build_async(&mut cur, "00000000-00:00:00.000").await.expect("building");
             cur.flush().await.unwrap();
 
             assert_eq!(
@@ -0,0 +1,299 @@
+//! Route incoming application messages to the strategies they belong to
+//!
+//! When several strategies share one session, each of them usually only wants its own
+//! `ExecutionReport<8>`s. [`MessageRouter::spawn`] starts a task that reads the value of one field
+//! of each incoming application message, chosen by a [`RouteBy`], and moves the message to the
+//! channel subscribed with that value. Messages that match no subscription, or whose subscriber
+//! was dropped, go to the default route returned by [`MessageRouter::spawn`].
+//!
+//! Routes can be subscribed and unsubscribed while the session is running.
+//! [`MessageRouter::spawn`] must be called within a tokio runtime.
+//!
+//! ```
+//! use forgefix::{ApplicationError, FixApplicationInitiator, SessionSettings};
+//! use forgefix::fix::router::{MessageRouter, RouteBy};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//! #    let settings = SessionSettings::builder()
+//! #        .with_sender_comp_id("my_id")
+//! #        .with_target_comp_id("peer_id")
+//! #        .with_store_path(peer.store_path())
+//! #        .with_log_dir(peer.log_dir())
+//! #        .with_socket_addr(peer.addr())
+//! #        .build()?;
+//! let (handle, receiver) = FixApplicationInitiator::build(settings)?
+//!     .initiate()
+//!     .await?;
+//! let (router, mut unrouted) = MessageRouter::spawn(receiver, RouteBy::ClOrdIdPrefix);
+//! let mut momentum = router.subscribe("MOM-");
+//! tokio::spawn(async move {
+//!     while let Some(msg) = momentum.recv().await {
+//!         println!("momentum: {msg}");
+//!     }
+//! });
+//! tokio::spawn(async move {
+//!     while let Some(msg) = unrouted.recv().await {
+//!         println!("not for any strategy: {msg}");
+//!     }
+//! });
+//! handle.start_async().await?;
+//! // ...
+//! handle.end_async().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::generated::Tags;
+use crate::fix::mem::MsgBuf;
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// The field that decides the route of a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteBy {
+    /// The route whose key is the `Account(1)` of the message.
+    Account,
+    /// The route whose key is the `SenderSubID(50)` of the message.
+    SenderSubId,
+    /// The route whose key is the longest prefix of the `ClOrdID(11)` of the message, such as
+    /// `"MOM-"` for `"MOM-42"`.
+    ClOrdIdPrefix,
+    /// The route whose key is the value of the field with this tag.
+    Tag(u32),
+}
+
+impl RouteBy {
+    fn tag(&self) -> u32 {
+        match self {
+            RouteBy::Account => Tags::Account.into(),
+            RouteBy::SenderSubId => Tags::SenderSubID.into(),
+            RouteBy::ClOrdIdPrefix => Tags::ClOrdID.into(),
+            RouteBy::Tag(tag) => *tag,
+        }
+    }
+}
+
+type Routes = BTreeMap<Vec<u8>, mpsc::UnboundedSender<Arc<MsgBuf>>>;
+
+/// The task routing incoming messages. See the [module documentation](self).
+pub struct MessageRouter {
+    route_by: RouteBy,
+    routes: Arc<Mutex<Routes>>,
+    unrouted: Arc<AtomicU64>,
+    task: JoinHandle<()>,
+}
+
+impl MessageRouter {
+    /// Route the messages from `receiver` by the field chosen by `route_by`. Returns the router,
+    /// and the default route, which receives every message that was not routed to a subscriber.
+    ///
+    /// The task ends once the engine has ended and every message has been routed.
+    pub fn spawn(
+        mut receiver: mpsc::UnboundedReceiver<Arc<MsgBuf>>,
+        route_by: RouteBy,
+    ) -> (MessageRouter, mpsc::UnboundedReceiver<Arc<MsgBuf>>) {
+        let (default_sender, default_receiver) = mpsc::unbounded_channel();
+        let routes = Arc::new(Mutex::new(Routes::new()));
+        let unrouted = Arc::new(AtomicU64::new(0));
+        let task_routes = Arc::clone(&routes);
+        let counter = Arc::clone(&unrouted);
+        let task = tokio::spawn(async move {
+            while let Some(msg) = receiver.recv().await {
+                let msg = match route(&task_routes, route_by, msg) {
+                    Ok(()) => continue,
+                    Err(msg) => msg,
+                };
+                counter.fetch_add(1, Ordering::Relaxed);
+                let _ = default_sender.send(msg);
+            }
+        });
+        let router = MessageRouter {
+            route_by,
+            routes,
+            unrouted,
+            task,
+        };
+        (router, default_receiver)
+    }
+
+    /// Subscribe to the messages routed to `key`, such as an account, or a `ClOrdID(11)` prefix.
+    /// A later subscription to the same key replaces this one.
+    pub fn subscribe(&self, key: &str) -> mpsc::UnboundedReceiver<Arc<MsgBuf>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(key.as_bytes().to_vec(), sender);
+        receiver
+    }
+
+    /// Stop routing messages to `key`. Its messages go to the default route instead. Returns
+    /// whether there was a subscription to `key`.
+    pub fn unsubscribe(&self, key: &str) -> bool {
+        self.routes.lock().unwrap().remove(key.as_bytes()).is_some()
+    }
+
+    /// The field that decides the route of a message.
+    pub fn route_by(&self) -> RouteBy {
+        self.route_by
+    }
+
+    /// The number of messages sent to the default route.
+    pub fn unrouted(&self) -> u64 {
+        self.unrouted.load(Ordering::Relaxed)
+    }
+
+    /// Whether the task has ended, because the engine ended.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+// Send `msg` to its subscriber, or give it back if it has none. A subscriber that was dropped is
+// unsubscribed.
+fn route(routes: &Mutex<Routes>, route_by: RouteBy, msg: Arc<MsgBuf>) -> Result<(), Arc<MsgBuf>> {
+    let Some(value) = field_value(&msg[..], route_by.tag()) else {
+        return Err(msg);
+    };
+    let mut routes = routes.lock().unwrap();
+    let key = match route_by {
+        RouteBy::ClOrdIdPrefix => routes
+            .range(..=value.clone())
+            .rev()
+            .map(|(key, _)| key)
+            .find(|key| value.starts_with(key))
+            .cloned(),
+        _ => Some(value),
+    };
+    let Some(key) = key else {
+        return Err(msg);
+    };
+    let Some(sender) = routes.get(&key) else {
+        return Err(msg);
+    };
+    if let Err(mpsc::error::SendError(msg)) = sender.send(msg) {
+        routes.remove(&key);
+        return Err(msg);
+    }
+    Ok(())
+}
+
+fn field_value(msg: &[u8], tag: u32) -> Option<Vec<u8>> {
+    let mut cb = FieldParser { tag, value: None };
+    parse(msg, &mut cb).ok()?;
+    cb.value.map(<[u8]>::to_vec)
+}
+
+struct FieldParser<'a> {
+    tag: u32,
+    value: Option<&'a [u8]>,
+}
+
+impl<'a> FieldParser<'a> {
+    fn field(&mut self, key: u32, value: &'a [u8]) -> bool {
+        if key == self.tag {
+            self.value = Some(value);
+        }
+        self.value.is_none()
+    }
+}
+
+impl<'a> ParserCallback<'a> for FieldParser<'a> {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(self.field(key, value))
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(self.field(key, value))
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn report(account: &str, cl_ord_id: &str) -> Arc<MsgBuf> {
+        let msg = format!("8=FIX.4.2\x019=5\x0135=8\x0134=2\x0149=peer\x011={account}\x0111={cl_ord_id}\x0110=000\x01");
+        Arc::new(MsgBuf(msg.into_bytes()))
+    }
+
+    // Route `(account, cl_ord_id)` reports, and return the ClOrdIDs received by each subscriber
+    // of `keys`, and by the default route.
+    async fn run(
+        route_by: RouteBy,
+        keys: &[&str],
+        reports: &[(&str, &str)],
+    ) -> (Vec<Vec<String>>, Vec<String>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (router, mut unrouted) = MessageRouter::spawn(receiver, route_by);
+        let mut subscribers: Vec<_> = keys.iter().map(|key| router.subscribe(key)).collect();
+        for (account, cl_ord_id) in reports {
+            sender.send(report(account, cl_ord_id)).unwrap();
+        }
+        drop(sender);
+        while !router.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        let drain = |receiver: &mut mpsc::UnboundedReceiver<Arc<MsgBuf>>| {
+            std::iter::from_fn(|| receiver.try_recv().ok())
+                .map(|msg| {
+                    String::from_utf8(field_value(&msg[..], Tags::ClOrdID.into()).unwrap()).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        let routed = subscribers.iter_mut().map(drain).collect();
+        let unrouted_ids = drain(&mut unrouted);
+        assert_eq!(router.unrouted(), unrouted_ids.len() as u64);
+        (routed, unrouted_ids)
+    }
+
+    #[tokio::test]
+    async fn test_route_by_account() {
+        let reports = [("A1", "x"), ("A2", "y"), ("A3", "z"), ("A1", "w")];
+        let (routed, unrouted) = run(RouteBy::Account, &["A1", "A2"], &reports).await;
+        assert_eq!(routed, vec![vec!["x", "w"], vec!["y"]]);
+        assert_eq!(unrouted, vec!["z"]);
+    }
+
+    #[tokio::test]
+    async fn test_route_by_cl_ord_id_prefix() {
+        let reports = [
+            ("A", "MOM-1"),
+            ("A", "MOM-X-2"),
+            ("A", "ARB-3"),
+            ("A", "MO"),
+        ];
+        let (routed, unrouted) = run(
+            RouteBy::ClOrdIdPrefix,
+            &["MOM-", "MOM-X-", "ARB-"],
+            &reports,
+        )
+        .await;
+        assert_eq!(routed, vec![vec!["MOM-1"], vec!["MOM-X-2"], vec!["ARB-3"]]);
+        assert_eq!(unrouted, vec!["MO"]);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_subscriber() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (router, mut unrouted) = MessageRouter::spawn(receiver, RouteBy::Account);
+        drop(router.subscribe("A1"));
+        sender.send(report("A1", "x")).unwrap();
+        assert_eq!(unrouted.recv().await.unwrap().0, report("A1", "x").0);
+        assert!(!router.unsubscribe("A1"));
+    }
+}
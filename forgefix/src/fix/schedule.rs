@@ -0,0 +1,288 @@
+//! The times at which a FIX session is active
+//!
+//! Most counterparties only run a FIX session during part of the day or week, and expect a new
+//! session, starting again from sequence number 1, each time. A [`SessionSchedule`] describes
+//! when the session is active, and is passed to an engine with
+//! [`SessionSettingsBuilder::with_schedule`]. The engine then:
+//!
+//! * waits for the session to start before logging on, so starting the engine early waits until
+//!   the start of the session,
+//! * as an acceptor, answers a `Logon<A>` received outside the session with a `Logout<5>` and
+//!   disconnects,
+//! * resets the sequence numbers when logging on for the first time in a session, if
+//!   [`with_sequence_reset`] is set,
+//! * publishes a [`SessionEvent::SessionEndingSoon`] ahead of the end of the session, if
+//!   [`with_end_warning`] is set,
+//! * logs out at the end of the session, and
+//! * refuses or queues messages sent outside the session, following [`OutsideWindow`].
+//!
+//! Times are in a fixed offset from UTC, so a schedule in a timezone with daylight saving time
+//! must be updated when the offset changes.
+//!
+//! ```
+//! use chrono::{FixedOffset, NaiveTime, Weekday};
+//! use forgefix::fix::schedule::{OutsideWindow, SessionSchedule};
+//!
+//! // 08:00 to 17:30 in UTC-5, on weekdays
+//! let schedule = SessionSchedule::daily(
+//!     NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+//!     NaiveTime::from_hms_opt(17, 30, 0).unwrap(),
+//! )
+//! .with_days(&[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri])
+//! .with_utc_offset(FixedOffset::west_opt(5 * 3600).unwrap())
+//! .with_outside_window(OutsideWindow::Queue);
+//!
+//! // from Sunday 17:00 to Friday 17:00 in UTC
+//! let weekly = SessionSchedule::weekly(
+//!     Weekday::Sun,
+//!     NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+//!     Weekday::Fri,
+//!     NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+//! );
+//! ```
+//!
+//! [`SessionSettingsBuilder::with_schedule`]: crate::SessionSettingsBuilder::with_schedule
+//! [`with_sequence_reset`]: SessionSchedule::with_sequence_reset
+//! [`with_end_warning`]: SessionSchedule::with_end_warning
+//! [`SessionEvent::SessionEndingSoon`]: crate::SessionEvent::SessionEndingSoon
+
+use chrono::{Datelike, DateTime, Days, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::time::Duration;
+
+/// What happens to a message sent while the session is not active.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutsideWindow {
+    /// Fail the send.
+    #[default]
+    Refuse,
+    /// Hold the message, and send it once the next session is logged on. Messages still held when
+    /// the engine ends are not sent.
+    Queue,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    // A session starting on each of the days, indexed from Monday.
+    Daily([bool; 7]),
+    // A single session starting on `start_day` and ending on `end_day`.
+    Weekly { start_day: Weekday, end_day: Weekday },
+}
+
+/// When a FIX session is active. See the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionSchedule {
+    kind: Kind,
+    start_time: NaiveTime,
+    end_time: NaiveTime,
+    utc_offset: FixedOffset,
+    sequence_reset: bool,
+    outside_window: OutsideWindow,
+    end_warning: Option<Duration>,
+}
+
+impl SessionSchedule {
+    /// A session every day from `start_time` to `end_time`. If `end_time` is not after
+    /// `start_time`, the session ends on the next day.
+    pub fn daily(start_time: NaiveTime, end_time: NaiveTime) -> SessionSchedule {
+        SessionSchedule::new(Kind::Daily([true; 7]), start_time, end_time)
+    }
+
+    /// A single session each week, from `start_time` on `start_day` to `end_time` on `end_day`.
+    pub fn weekly(
+        start_day: Weekday,
+        start_time: NaiveTime,
+        end_day: Weekday,
+        end_time: NaiveTime,
+    ) -> SessionSchedule {
+        SessionSchedule::new(Kind::Weekly { start_day, end_day }, start_time, end_time)
+    }
+
+    fn new(kind: Kind, start_time: NaiveTime, end_time: NaiveTime) -> SessionSchedule {
+        SessionSchedule {
+            kind,
+            start_time,
+            end_time,
+            utc_offset: FixedOffset::east_opt(0).unwrap(),
+            sequence_reset: true,
+            outside_window: OutsideWindow::default(),
+            end_warning: None,
+        }
+    }
+
+    /// Only start a daily session on the given days. Has no effect on a weekly schedule.
+    pub fn with_days(mut self, days: &[Weekday]) -> Self {
+        if let Kind::Daily(ref mut active) = self.kind {
+            *active = [false; 7];
+            for day in days {
+                active[day.num_days_from_monday() as usize] = true;
+            }
+        }
+        self
+    }
+
+    /// The offset from UTC of the times and days of the schedule. Defaults to UTC.
+    pub fn with_utc_offset(mut self, utc_offset: FixedOffset) -> Self {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// Reset the sequence numbers when logging on for the first time in a session. Defaults to
+    /// `true`.
+    pub fn with_sequence_reset(mut self, sequence_reset: bool) -> Self {
+        self.sequence_reset = sequence_reset;
+        self
+    }
+
+    /// What happens to messages sent outside the session. Defaults to [`OutsideWindow::Refuse`].
+    pub fn with_outside_window(mut self, outside_window: OutsideWindow) -> Self {
+        self.outside_window = outside_window;
+        self
+    }
+
+    /// Publish a [`SessionEvent::SessionEndingSoon`] `end_warning` before the end of each
+    /// session, such as to stop sending new orders ahead of the close. Not set by default.
+    ///
+    /// [`SessionEvent::SessionEndingSoon`]: crate::SessionEvent::SessionEndingSoon
+    pub fn with_end_warning(mut self, end_warning: Duration) -> Self {
+        self.end_warning = Some(end_warning);
+        self
+    }
+
+    /// Whether the sequence numbers are reset at the start of each session.
+    pub fn sequence_reset(&self) -> bool {
+        self.sequence_reset
+    }
+
+    /// What happens to messages sent outside the session.
+    pub fn outside_window(&self) -> OutsideWindow {
+        self.outside_window
+    }
+
+    /// How long before the end of each session a [`SessionEvent::SessionEndingSoon`] is
+    /// published, if at all.
+    ///
+    /// [`SessionEvent::SessionEndingSoon`]: crate::SessionEvent::SessionEndingSoon
+    pub fn end_warning(&self) -> Option<Duration> {
+        self.end_warning
+    }
+
+    /// Whether a session is active at `at`.
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        self.current_window(at).is_some()
+    }
+
+    /// The start and end of the session active at `at`, if any.
+    pub fn current_window(&self, at: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let today = at.with_timezone(&self.utc_offset).date_naive();
+        (0..=7)
+            .filter_map(|i| today.checked_sub_days(Days::new(i)))
+            .filter_map(|date| self.window(date))
+            .find(|(start, end)| *start <= at && at < *end)
+    }
+
+    /// The start of the first session starting after `at`, or `None` if no session is ever
+    /// active.
+    pub fn next_start(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = at.with_timezone(&self.utc_offset).date_naive();
+        (0..=7)
+            .filter_map(|i| today.checked_add_days(Days::new(i)))
+            .filter_map(|date| self.window(date))
+            .map(|(start, _)| start)
+            .find(|start| *start > at)
+    }
+
+    /// The start of the last session that started at or before `at`, or `None` if no session is
+    /// ever active.
+    pub fn last_start(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = at.with_timezone(&self.utc_offset).date_naive();
+        (0..=7)
+            .filter_map(|i| today.checked_sub_days(Days::new(i)))
+            .filter_map(|date| self.window(date))
+            .map(|(start, _)| start)
+            .find(|start| *start <= at)
+    }
+
+    // The session starting on `date`, if one does.
+    fn window(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        // the days from the start to the end of the session, and the length of its cycle
+        let (days, cycle) = match self.kind {
+            Kind::Daily(active) if active[date.weekday().num_days_from_monday() as usize] => (0, 1),
+            Kind::Weekly { start_day, end_day } if date.weekday() == start_day => {
+                ((7 + end_day.num_days_from_monday() - start_day.num_days_from_monday()) % 7, 7)
+            }
+            _ => return None,
+        };
+        let start = self.utc_at(date, self.start_time);
+        let mut end = self.utc_at(date.checked_add_days(Days::new(days.into()))?, self.end_time);
+        if end <= start {
+            end += chrono::Duration::days(cycle);
+        }
+        Some((start, end))
+    }
+
+    fn utc_at(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        self.utc_offset
+            .from_local_datetime(&date.and_time(time))
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_daily_schedule() {
+        // 2024-01-05 is a Friday
+        let schedule = SessionSchedule::daily(time(8, 0), time(17, 0))
+            .with_days(&[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri])
+            .with_utc_offset(FixedOffset::west_opt(5 * 3600).unwrap());
+        assert!(!schedule.is_active(utc("2024-01-05T12:59:59Z")));
+        assert_eq!(
+            schedule.current_window(utc("2024-01-05T13:00:00Z")),
+            Some((utc("2024-01-05T13:00:00Z"), utc("2024-01-05T22:00:00Z")))
+        );
+        assert!(!schedule.is_active(utc("2024-01-05T22:00:00Z")));
+        assert!(!schedule.is_active(utc("2024-01-06T15:00:00Z")));
+        assert_eq!(schedule.next_start(utc("2024-01-05T22:00:00Z")), Some(utc("2024-01-08T13:00:00Z")));
+        assert_eq!(schedule.last_start(utc("2024-01-07T00:00:00Z")), Some(utc("2024-01-05T13:00:00Z")));
+
+        // overnight
+        let schedule = SessionSchedule::daily(time(22, 0), time(6, 0));
+        assert_eq!(
+            schedule.current_window(utc("2024-01-05T02:00:00Z")),
+            Some((utc("2024-01-04T22:00:00Z"), utc("2024-01-05T06:00:00Z")))
+        );
+        assert!(!schedule.is_active(utc("2024-01-05T12:00:00Z")));
+
+        let never = SessionSchedule::daily(time(8, 0), time(17, 0)).with_days(&[]);
+        assert_eq!(never.next_start(utc("2024-01-05T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_weekly_schedule() {
+        let schedule = SessionSchedule::weekly(Weekday::Sun, time(17, 0), Weekday::Fri, time(17, 0));
+        let window = Some((utc("2024-01-07T17:00:00Z"), utc("2024-01-12T17:00:00Z")));
+        assert_eq!(schedule.current_window(utc("2024-01-07T17:00:00Z")), window);
+        assert_eq!(schedule.current_window(utc("2024-01-10T03:00:00Z")), window);
+        assert!(!schedule.is_active(utc("2024-01-12T17:00:00Z")));
+        assert!(!schedule.is_active(utc("2024-01-13T12:00:00Z")));
+        assert_eq!(schedule.next_start(utc("2024-01-13T12:00:00Z")), Some(utc("2024-01-14T17:00:00Z")));
+
+        // a whole week, ending when the next session starts
+        let schedule = SessionSchedule::weekly(Weekday::Mon, time(0, 0), Weekday::Mon, time(0, 0));
+        assert_eq!(
+            schedule.current_window(utc("2024-01-10T00:00:00Z")),
+            Some((utc("2024-01-08T00:00:00Z"), utc("2024-01-15T00:00:00Z")))
+        );
+    }
+}
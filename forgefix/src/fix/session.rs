@@ -1,19 +1,25 @@
+use crate::fix::dedup::Reservation;
 use crate::fix::encode::{MessageBuilder, SerializedInt};
-use crate::fix::generated::{GapFillFlag, MsgType, PossDupFlag, SessionRejectReason, Tags};
+use crate::fix::generated::{is_session_message, GapFillFlag, MsgType, PossDupFlag, SessionRejectReason, Tags};
 use crate::fix::{GarbledMessageType, SessionError};
-use crate::SessionSettings;
+use crate::{CloseReason, CompIdMismatch, DuplicateLogon, EngineError, HeartbeatTimer, LogonMsgType, SequenceNumbers, SessionSettings};
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
-use tokio::sync::oneshot;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, OwnedSemaphorePermit};
+
+// How many unanswered TestRequests are remembered. Older ones are considered stale.
+const MAX_OUTSTANDING_TEST_REQUESTS: usize = 8;
 
 enum Response {
     Handled,
     Transition(State),
 }
 
-#[derive(Debug, Clone)]
-pub(super) enum State {
+/// The states of a FIX connection, as seen by the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
     Start,
     Connected,
     LogonSent,
@@ -26,22 +32,81 @@ pub(super) enum State {
 }
 
 pub(super) struct MyStateMachine {
-    pub(super) outbox: VecDeque<(MessageBuilder, Option<oneshot::Sender<bool>>)>, // https://github.com/mdeloof/statig/issues/7
+    pub(super) outbox: Outbox, // https://github.com/mdeloof/statig/issues/7
     pub(super) sequences: Sequences,
     pub(super) begin_string: Arc<String>,
+    max_message_size: Option<u32>,
+    logon_msg_types: Arc<Vec<LogonMsgType>>,
+    logon_fields: Arc<Vec<(u32, Vec<u8>)>>,
+    duplicate_logon: DuplicateLogon,
+    comp_id_mismatch: CompIdMismatch,
+    watchdog_test_request: bool,
+    heartbeat_timer: HeartbeatTimer,
+    test_request_count: u32,
+    watchdog_expiries: u32,
+    outstanding_test_requests: VecDeque<(String, Instant)>,
     rereceive_range: Option<(u32, u32)>,
-    logout_resp_sender: Option<oneshot::Sender<bool>>,
+    reset_seq_num_sent: bool,
+    sequences_reset: Option<bool>,
+    outgoing_sequence_healed: bool,
+    logout_resp_senders: Vec<oneshot::Sender<bool>>,
     logon_resp_sender: Option<oneshot::Sender<bool>>,
+    close_reason: Option<CloseReason>,
     state: State,
 }
 
+// A message waiting to be sent, with the sender answered once it is written and the permit of the
+// outbox of the handles released with it.
+pub(super) type Queued = (MessageBuilder, Option<oneshot::Sender<bool>>, Option<OutboxPermit>);
+
+// What a message of a handle holds until it leaves the outbox: its room in the outbox of the
+// handles, and the `ClOrdID(11)`s reserved for it. Both are given back when it is dropped.
+pub(crate) struct OutboxPermit {
+    _room: Option<OwnedSemaphorePermit>,
+    _cl_ord_ids: Option<Reservation>,
+}
+
+impl OutboxPermit {
+    pub(crate) fn new(room: Option<OwnedSemaphorePermit>, cl_ord_ids: Option<Reservation>) -> OutboxPermit {
+        OutboxPermit {
+            _room: room,
+            _cl_ord_ids: cl_ord_ids,
+        }
+    }
+}
+
+// The messages waiting to be sent, in two tiers. The session messages of the state machine, such
+// as heartbeats, test requests, resend requests, rejects, and the logouts it answers or starts,
+// are sent ahead of the messages sent through the handles, so that a burst of application
+// messages does not hold them up. The messages of the handles are sent in order, including a
+// `Logout<5>` requested through a handle, which is sent after the messages queued before it.
+#[derive(Default)]
+pub(super) struct Outbox {
+    priority: VecDeque<Queued>,
+    queued: VecDeque<Queued>,
+}
+
+impl Outbox {
+    pub(super) fn len(&self) -> usize {
+        self.priority.len() + self.queued.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.priority.is_empty() && self.queued.is_empty()
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Queued> {
+        self.priority.iter().chain(self.queued.iter())
+    }
+}
+
 #[derive(Debug)]
 pub(super) enum Event {
     Connect(bool),
     Accept,
     LogonReceived(u32, u32, Option<u32>, bool, Option<PossDupFlag>),
     LogoutSent,
-    LogoutReceived(u32, Option<PossDupFlag>),
+    LogoutReceived(u32, Option<String>, Option<PossDupFlag>),
     HeartbeatReceived(u32, Option<PossDupFlag>),
     SequenceResetReceived {
         msg_seq_num: u32,
@@ -63,6 +128,7 @@ pub(super) enum Event {
     ResendRequestReceived(u32, u32, u32, Option<PossDupFlag>),
     RejectReceived(u32, Option<PossDupFlag>),
     LogoutExpired,
+    WatchdogExpired(std::time::Duration),
 }
 impl Event {
     fn get_msg_seq_num(&self) -> Option<u32> {
@@ -116,18 +182,64 @@ impl Event {
 impl MyStateMachine {
     pub(super) fn new(settings: &SessionSettings, seqs: (u32, u32)) -> Self {
         MyStateMachine {
-            outbox: VecDeque::new(),
+            outbox: Outbox::default(),
             sequences: seqs.into(),
             begin_string: Arc::clone(&settings.begin_string),
+            max_message_size: settings.max_message_size,
+            logon_msg_types: Arc::clone(&settings.logon_msg_types),
+            logon_fields: Arc::clone(&settings.logon_fields),
+            duplicate_logon: settings.duplicate_logon,
+            comp_id_mismatch: settings.comp_id_mismatch,
+            watchdog_test_request: settings.watchdog_test_request,
+            heartbeat_timer: settings.heartbeat_policy.timer,
+            test_request_count: 0,
+            watchdog_expiries: 0,
+            outstanding_test_requests: VecDeque::new(),
             logon_resp_sender: None,
-            logout_resp_sender: None,
+            logout_resp_senders: Vec::new(),
             rereceive_range: None,
+            reset_seq_num_sent: false,
+            sequences_reset: None,
+            outgoing_sequence_healed: false,
+            close_reason: None,
             state: State::Start,
         }
     }
     pub(super) fn state(&self) -> &State {
         &self.state
     }
+    fn send_test_request(&mut self) {
+        self.test_request_count = self.test_request_count.wrapping_add(1);
+        self.send_test_request_with_id(format!("TEST-{}", self.test_request_count));
+    }
+    // Each expiry of the watchdog is numbered on its own, so that its `TestReqID(112)` tells which
+    // expiry a heartbeat answers.
+    fn send_watchdog_test_request(&mut self) {
+        self.watchdog_expiries = self.watchdog_expiries.wrapping_add(1);
+        self.send_test_request_with_id(format!("WATCHDOG-{}", self.watchdog_expiries));
+    }
+    fn send_test_request_with_id(&mut self, test_req_id: String) {
+        let builder = MessageBuilder::new(&self.begin_string, MsgType::TEST_REQUEST.into())
+            .push(Tags::TestReqID, test_req_id.as_bytes());
+        self.outbox_push(builder);
+
+        if self.outstanding_test_requests.len() == MAX_OUTSTANDING_TEST_REQUESTS {
+            self.outstanding_test_requests.pop_front();
+        }
+        self.outstanding_test_requests.push_back((test_req_id, Instant::now()));
+    }
+    // Match the `TestReqID(112)` of a heartbeat to an outstanding TestRequest, returning how long
+    // the peer took to answer it. Returns `None` if the ID was never issued, was already answered,
+    // or is stale.
+    pub(super) fn answer_test_request(&mut self, test_req_id: &[u8]) -> Option<Duration> {
+        let i = self
+            .outstanding_test_requests
+            .iter()
+            .position(|(id, _)| id.as_bytes() == test_req_id)?;
+        self.outstanding_test_requests
+            .remove(i)
+            .map(|(_, sent)| sent.elapsed())
+    }
     pub(super) fn handle(&mut self, event: &Event) {
         if let Response::Transition(new_state) = match &self.state {
             State::Start => self.start(event),
@@ -142,30 +254,126 @@ impl MyStateMachine {
             State::End => self.end(event),
             State::Error => self.error(event),
         } {
+            tracing::debug!(from = ?self.state, to = ?new_state, ?event, "state transition");
+            if matches!(new_state, State::End | State::Error) && self.close_reason.is_none() {
+                self.close_reason = Some(close_reason(&self.state, &new_state, event));
+            }
             self.state = new_state;
         }
     }
+    // Queue a session message ahead of the messages of the handles.
     pub(super) fn outbox_push(&mut self, builder: MessageBuilder) {
-        self.outbox.push_back((builder, None));
+        self.outbox.priority.push_back((builder, None, None));
     }
+    // Queue a message of a handle, behind the ones queued before it.
     pub(super) fn outbox_push_with_sender(
         &mut self,
         builder: MessageBuilder,
         resp_sender: oneshot::Sender<bool>,
     ) {
-        self.outbox.push_back((builder, Some(resp_sender)));
+        self.outbox_push_with_permit(builder, resp_sender, None);
+    }
+    // Queue a message of a handle holding `permit`, which is released once the message is sent.
+    pub(super) fn outbox_push_with_permit(
+        &mut self,
+        builder: MessageBuilder,
+        resp_sender: oneshot::Sender<bool>,
+        permit: Option<OutboxPermit>,
+    ) {
+        self.outbox.queued.push_back((builder, Some(resp_sender), permit));
+    }
+    // Queue a `SequenceReset<4>` in reset mode requested through a handle, behind the messages
+    // queued before it. It is refused unless logged in, with no resend or test request pending.
+    pub(super) fn queue_sequence_reset(&mut self, new_seq_no: u32, resp_sender: oneshot::Sender<bool>) {
+        if !matches!(self.state, State::LoggedIn) {
+            let _ = resp_sender.send(false);
+            return;
+        }
+        let builder = MessageBuilder::new(&self.begin_string, MsgType::SEQUENCE_RESET.into())
+            .push(Tags::GapFillFlag, b"N")
+            .push(Tags::NewSeqNo, SerializedInt::from(new_seq_no).as_bytes());
+        self.outbox_push_with_sender(builder, resp_sender);
+    }
+    // Queue a `ResendRequest<2>` requested through a handle, for messages already received, and
+    // wait for them as for a gap. An `end_seq_no` of 0 asks for every message up to the last one
+    // received. It is refused unless logged in, with no resend or test request pending.
+    pub(super) fn queue_resend_request(
+        &mut self,
+        begin_seq_no: u32,
+        end_seq_no: u32,
+        resp_sender: oneshot::Sender<bool>,
+    ) {
+        let last_received = self.sequences.peek_incoming() - 1;
+        if !matches!(self.state, State::LoggedIn) || begin_seq_no == 0 || begin_seq_no > last_received {
+            let _ = resp_sender.send(false);
+            return;
+        }
+        let end = match end_seq_no {
+            0 => last_received,
+            end_seq_no => end_seq_no.min(last_received),
+        };
+        if end < begin_seq_no {
+            let _ = resp_sender.send(false);
+            return;
+        }
+        self.rereceive_range = Some((begin_seq_no, end));
+        self.state = State::ExpectingResends {
+            return_state: Arc::new(State::LoggedIn),
+        };
+        let builder = MessageBuilder::new(&self.begin_string, MsgType::RESEND_REQUEST.into())
+            .push(Tags::BeginSeqNo, SerializedInt::from(begin_seq_no).as_bytes())
+            .push(Tags::EndSeqNo, SerializedInt::from(end_seq_no.min(end)).as_bytes());
+        self.outbox_push_with_sender(builder, resp_sender);
+    }
+    // The response sender of the last message queued by the handles, if any.
+    pub(super) fn outbox_last_sender(&mut self) -> Option<&mut oneshot::Sender<bool>> {
+        self.outbox.queued.back_mut().and_then(|(_, resp_sender, _)| resp_sender.as_mut())
+    }
+    pub(super) fn outbox_pop(&mut self) -> Option<Queued> {
+        self.outbox
+            .priority
+            .pop_front()
+            .or_else(|| self.outbox.queued.pop_front())
     }
-    pub(super) fn outbox_pop(&mut self) -> Option<(MessageBuilder, Option<oneshot::Sender<bool>>)> {
-        self.outbox.pop_front()
+    // The message `outbox_pop` returns next.
+    pub(super) fn outbox_front(&self) -> Option<&Queued> {
+        self.outbox.priority.front().or_else(|| self.outbox.queued.front())
     }
     pub(super) fn outbox_clear(&mut self) {
-        self.outbox.clear();
+        self.outbox.priority.clear();
+        self.outbox.queued.clear();
+    }
+    // Remove the application messages from the outbox, and return how many were removed. Their
+    // senders are dropped, so they are answered as not sent.
+    pub(super) fn outbox_discard_app_messages(&mut self) -> usize {
+        let queued = self.outbox.queued.len();
+        self.outbox
+            .queued
+            .retain(|(builder, ..)| is_session_message(builder.msg_type()));
+        queued - self.outbox.queued.len()
     }
     pub(super) fn set_logon_resp_sender(&mut self, resp_sender: Option<oneshot::Sender<bool>>) {
         self.logon_resp_sender = resp_sender;
     }
-    pub(super) fn set_logout_resp_sender(&mut self, resp_sender: Option<oneshot::Sender<bool>>) {
-        self.logout_resp_sender = resp_sender;
+    pub(super) fn add_logout_resp_sender(&mut self, resp_sender: Option<oneshot::Sender<bool>>) {
+        self.logout_resp_senders.extend(resp_sender);
+    }
+    // Answer `resp_sender` with the outcome of the logout in flight, if a `Logout<5>` is queued or
+    // was already sent, instead of sending another one. Returns `resp_sender` back if there is no
+    // logout in flight.
+    pub(super) fn join_logout(
+        &mut self,
+        resp_sender: oneshot::Sender<bool>,
+    ) -> Result<(), oneshot::Sender<bool>> {
+        let queued = self
+            .outbox
+            .iter()
+            .any(|(builder, ..)| builder.msg_type() == MsgType::LOGOUT.into());
+        if !queued && !matches!(self.state, State::LogoutSent) {
+            return Err(resp_sender);
+        }
+        self.logout_resp_senders.push(resp_sender);
+        Ok(())
     }
     fn send_logon_response(&mut self, logon_status: bool) {
         if let Some(resp_sender) = self.logon_resp_sender.take() {
@@ -173,10 +381,40 @@ impl MyStateMachine {
         }
     }
     pub(super) fn send_logout_response(&mut self, logout_status: bool) {
-        if let Some(resp_sender) = self.logout_resp_sender.take() {
+        for resp_sender in self.logout_resp_senders.drain(..) {
             let _ = resp_sender.send(logout_status);
         }
     }
+    fn build_logon_message(&self, heart_bt_int: u32) -> MessageBuilder {
+        let mut builder = MessageBuilder::new(&self.begin_string, MsgType::LOGON.into())
+            .push(Tags::EncryptMethod, b"0")
+            .push(Tags::HeartBtInt, SerializedInt::from(heart_bt_int).as_bytes());
+        if let Some(max_message_size) = self.max_message_size {
+            builder = builder.push(
+                Tags::MaxMessageSize,
+                SerializedInt::from(max_message_size).as_bytes(),
+            );
+        }
+        if !self.logon_msg_types.is_empty() {
+            builder = builder.push(
+                Tags::NoMsgTypes,
+                SerializedInt::from(self.logon_msg_types.len() as u32).as_bytes(),
+            );
+            for msg_type in self.logon_msg_types.iter() {
+                let mut direction = [0; 4];
+                builder = builder
+                    .push(Tags::RefMsgType, msg_type.ref_msg_type.as_bytes())
+                    .push(
+                        Tags::MsgDirection,
+                        msg_type.msg_direction.encode_utf8(&mut direction).as_bytes(),
+                    );
+            }
+        }
+        for (tag, value) in self.logon_fields.iter() {
+            builder = builder.push(*tag, value);
+        }
+        builder
+    }
     fn process_sequence(&mut self, event: &Event, return_state: State) -> Option<Response> {
         event.get_msg_seq_num().and_then(|incoming| {
             let expected = self.sequences.peek_incoming();
@@ -203,6 +441,10 @@ impl MyStateMachine {
                     .as_bytes(),
                 );
                 self.outbox_push(message);
+                self.close_reason = Some(CloseReason::SequenceTooLow {
+                    expected,
+                    received: incoming,
+                });
                 Some(Response::Transition(State::Error))
             } else {
                 Some(Response::Handled)
@@ -210,13 +452,91 @@ impl MyStateMachine {
         })
     }
     fn reset_sequences(&mut self) {
-        self.sequences = (1, 1).into()
+        self.sequences.reset();
+    }
+    // Accept a `Logon<A>` with `ResetSeqNumFlag(141)=Y` from the peer. Unless the peer is
+    // confirming a reset requested by the engine, both sequence numbers are reset and the reset
+    // is confirmed with a `Logon<A>` that also has `ResetSeqNumFlag(141)=Y`.
+    fn accept_sequence_reset(&mut self, heart_bt_int: u32) {
+        if std::mem::take(&mut self.reset_seq_num_sent) {
+            return;
+        }
+        self.reset_sequences();
+        self.rereceive_range = None;
+        let builder = self
+            .build_logon_message(heart_bt_int)
+            .push(Tags::ResetSeqNumFlag, b"Y");
+        self.outbox_push(builder);
+        self.sequences_reset = Some(true);
+    }
+    // Handle a `Logon<A>` received while logged in, once its sequence number was processed. A
+    // possible duplicate is a resend of the first `Logon<A>`, and is ignored.
+    fn duplicate_logon(&mut self, msg_seq_num: u32, poss_dup: bool) -> Response {
+        if poss_dup {
+            return Response::Handled;
+        }
+        let text = "Logon received while already logged on";
+        match self.duplicate_logon {
+            DuplicateLogon::Reject => {
+                self.outbox_push(build_message_reject(
+                    &self.begin_string,
+                    &text.to_string(),
+                    &None,
+                    &msg_seq_num,
+                    &None,
+                    &Some(MsgType::LOGON.into()),
+                ));
+                Response::Handled
+            }
+            DuplicateLogon::Disconnect => {
+                self.outbox_push(build_logout_message_with_text(&self.begin_string, text.as_bytes()));
+                self.close_reason = Some(CloseReason::MessageRejected { text: text.to_string() });
+                Response::Transition(State::Error)
+            }
+        }
+    }
+    // Take the pending notification of a reset of the sequence numbers. The value is `true` if the
+    // reset was initiated by the peer.
+    pub(super) fn take_sequences_reset(&mut self) -> Option<bool> {
+        self.sequences_reset.take()
+    }
+    // Why the session ended, once the engine is to disconnect.
+    #[cfg(feature = "test-util")]
+    pub(super) fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+    pub(super) fn take_close_reason(&mut self) -> Option<CloseReason> {
+        self.close_reason.take()
+    }
+    // Raise the next outgoing sequence number to `expected` after the peer logged out because the
+    // `MsgSeqNum(34)` it received was too low, and return to the start state so the engine can
+    // reconnect. Some peers confirm the `Logon<A>` before logging out. Returns the previous next
+    // outgoing sequence number, or `None` if the engine is not logging on or logged in, or the
+    // sequence number was already raised once. The `Logout<5>` itself, numbered `msg_seq_num`, is
+    // counted as received.
+    pub(super) fn heal_outgoing_sequence(&mut self, expected: u32, msg_seq_num: u32) -> Option<u32> {
+        let next_outgoing = self.sequences.peek_outgoing();
+        if !matches!(self.state, State::LogonSent | State::LoggedIn)
+            || self.outgoing_sequence_healed
+            || expected < next_outgoing
+        {
+            return None;
+        }
+        self.sequences.set_outgoing(expected);
+        if self.sequences.peek_incoming() == msg_seq_num {
+            self.sequences.incr_incoming();
+        }
+        self.outgoing_sequence_healed = true;
+        self.outbox_clear();
+        self.state = State::Start;
+        Some(next_outgoing)
     }
     fn reset_expected_incoming(&mut self, msg_seq_num: u32, new_seq_no: u32) {
         match self.sequences.reset_incoming(new_seq_no) {
             Ok(_) => {}
             Err(msg) => {
                 let builder = build_message_reject(
+                    &self.begin_string,
                     &msg.to_string(),
                     &Some(SessionRejectReason::VALUE_IS_INCORRECT),
                     &msg_seq_num,
@@ -253,9 +573,7 @@ impl MyStateMachine {
                 Response::Transition(State::End)
             }
             Event::SendTestRequest(_) => {
-                let builder = MessageBuilder::new(&self.begin_string, MsgType::TEST_REQUEST.into())
-                    .push(Tags::TestReqID, b"TEST");
-                self.outbox_push(builder);
+                self.send_test_request();
                 Response::Transition(State::ExpectingTestResponse)
             }
             Event::SendHeartbeat => {
@@ -263,6 +581,12 @@ impl MyStateMachine {
                 self.outbox_push(builder);
                 Response::Handled
             }
+            Event::WatchdogExpired(_) => {
+                if self.watchdog_test_request {
+                    self.send_watchdog_test_request();
+                }
+                Response::Handled
+            }
             Event::LogoutSent => Response::Transition(State::LogoutSent),
             Event::LogoutExpired => Response::Transition(State::Error),
             _ => Response::Handled,
@@ -325,6 +649,9 @@ impl MyStateMachine {
                 }
                 Response::Transition(State::LoggedIn)
             }
+            // on a fixed timer, heartbeats are due regardless, and the answer is awaited until
+            // the next test request is due
+            Event::SendHeartbeat if self.heartbeat_timer == HeartbeatTimer::Fixed => self.logged_in(event),
             Event::SendHeartbeat | Event::SendTestRequest(_) => Response::Transition(State::Error),
             _ => self.logged_in(event),
         }
@@ -336,6 +663,15 @@ impl MyStateMachine {
             State::LoggedIn
         };
 
+        // The peer reset the sequence numbers in the middle of the session.
+        if let Event::LogonReceived(_, heart_bt_int, _, true, _) = event {
+            self.accept_sequence_reset(*heart_bt_int);
+            if let Some(resp) = self.process_sequence(event, State::LoggedIn) {
+                return resp;
+            }
+            return Response::Transition(State::LoggedIn);
+        }
+
         if let Some(resp) = self.process_sequence(event, next_state) {
             return resp;
         }
@@ -365,6 +701,7 @@ impl MyStateMachine {
                 Response::Handled
             }
             Event::ApplicationMessageReceived(..) => Response::Handled,
+            Event::LogonReceived(msg_seq_num, ..) => self.duplicate_logon(*msg_seq_num, event.is_poss_dup()),
             Event::SessionErrorReceived {
                 error:
                     SessionError::MessageRejected {
@@ -376,7 +713,12 @@ impl MyStateMachine {
                     },
             } => {
                 self.sequences.incr_incoming();
+                let comp_id_problem = *reject_reason == Some(SessionRejectReason::COMPID_PROBLEM);
+                if comp_id_problem && self.comp_id_mismatch == CompIdMismatch::Disconnect {
+                    return Response::Transition(State::Error);
+                }
                 self.outbox_push(build_message_reject(
+                    &self.begin_string,
                     text,
                     reject_reason,
                     msg_seq_num,
@@ -384,7 +726,7 @@ impl MyStateMachine {
                     ref_msg_type,
                 ));
 
-                if *reject_reason == Some(SessionRejectReason::COMPID_PROBLEM)
+                if (comp_id_problem && self.comp_id_mismatch == CompIdMismatch::Logout)
                     || *reject_reason == Some(SessionRejectReason::SENDINGTIME_ACCURACY_PROBLEM)
                 {
                     self.outbox_push(build_logout_message_with_text(
@@ -404,13 +746,12 @@ impl MyStateMachine {
     fn start(&mut self, event: &Event) -> Response {
         match event {
             Event::Connect(reset_seq_num) => {
-                let mut builder: MessageBuilder =
-                    MessageBuilder::new(&self.begin_string, MsgType::LOGON.into())
-                        .push(Tags::EncryptMethod, b"0")
-                        .push(Tags::HeartBtInt, 30.to_string().as_bytes());
+                let mut builder = self.build_logon_message(30);
                 if *reset_seq_num {
                     builder = builder.push(Tags::ResetSeqNumFlag, b"Y");
                     self.reset_sequences();
+                    self.reset_seq_num_sent = true;
+                    self.sequences_reset = Some(false);
                 }
                 self.outbox_push(builder);
                 Response::Transition(State::LogonSent)
@@ -457,24 +798,19 @@ impl MyStateMachine {
                     self.send_logon_response(false);
                     return Response::Transition(State::Error);
                 }
-                let mut builder: MessageBuilder =
-                    MessageBuilder::new(&self.begin_string, MsgType::LOGON.into())
-                        .push(Tags::EncryptMethod, b"0")
-                        .push(
-                            Tags::HeartBtInt,
-                            SerializedInt::from(*heart_bt_int).as_bytes(),
-                        );
                 if *reset_seq_num {
-                    builder = builder.push(Tags::ResetSeqNumFlag, b"Y");
-                    self.reset_sequences();
+                    self.accept_sequence_reset(*heart_bt_int);
+                } else {
+                    let builder = self.build_logon_message(*heart_bt_int);
+                    self.outbox_push(builder);
                 }
-                self.outbox_push(builder);
                 self.send_logon_response(true);
                 if let Some(resp) = self.process_sequence(event, State::LoggedIn) {
                     return resp;
                 }
                 Response::Transition(State::LoggedIn)
             }
+            Event::WatchdogExpired(_) => Response::Handled,
             _ => {
                 self.send_logon_response(false);
                 Response::Transition(State::Error)
@@ -483,10 +819,13 @@ impl MyStateMachine {
     }
     fn logon_sent(&mut self, event: &Event) -> Response {
         match event {
-            Event::LogonReceived(_, _, encrypt_method, _, _) => {
+            Event::LogonReceived(_, heart_bt_int, encrypt_method, reset_seq_num, _) => {
                 if *encrypt_method != Some(0) {
                     return Response::Transition(State::Error);
                 }
+                if *reset_seq_num {
+                    self.accept_sequence_reset(*heart_bt_int);
+                }
                 self.send_logon_response(true);
 
                 if let Some(resp) = self.process_sequence(event, State::LoggedIn) {
@@ -513,6 +852,7 @@ impl MyStateMachine {
                 self.send_logon_response(false);
                 Response::Transition(State::LogoutSent)
             }
+            Event::WatchdogExpired(_) => Response::Handled,
             _ => {
                 self.send_logon_response(false);
                 Response::Transition(State::Error)
@@ -550,6 +890,14 @@ pub(super) fn should_pass_app_message(state_machine: &MyStateMachine, msg_seq_nu
         )
 }
 
+// The last `MsgSeqNum(34)` the peer is expected to resend, while waiting for a resend.
+pub(super) fn resend_end(state_machine: &MyStateMachine) -> Option<u32> {
+    match state_machine.state() {
+        State::ExpectingResends { .. } => state_machine.rereceive_range.map(|(_, end)| end),
+        _ => None,
+    }
+}
+
 pub(super) fn should_resend(state_machine: &MyStateMachine) -> bool {
     matches!(
         state_machine.state(),
@@ -560,10 +908,51 @@ pub(super) fn should_disconnect(state_machine: &MyStateMachine) -> bool {
     matches!(state_machine.state(), State::End | State::Error)
 }
 
+// The engine returns to the start state only to logon again on a new connection.
+pub(super) fn should_reconnect(state_machine: &MyStateMachine) -> bool {
+    matches!(state_machine.state(), State::Start)
+}
+
+pub(super) fn is_logged_in(state_machine: &MyStateMachine) -> bool {
+    matches!(
+        state_machine.state(),
+        State::LoggedIn | State::ExpectingResends { .. } | State::ExpectingTestResponse
+    )
+}
+
 pub(super) fn in_error_state(state_machine: &MyStateMachine) -> bool {
     matches!(state_machine.state(), State::Error)
 }
 
+// Why the session ended, from the event that moved the state machine from `from` to the `End` or
+// `Error` state `to`.
+fn close_reason(from: &State, to: &State, event: &Event) -> CloseReason {
+    let logging_on = matches!(from, State::Connected | State::LogonSent);
+    match event {
+        Event::LogoutReceived(_, text, _) if logging_on => CloseReason::LogonRejected { text: text.clone() },
+        Event::LogoutReceived(_, text, _) => CloseReason::LoggedOut { text: text.clone() },
+        Event::SessionErrorReceived {
+            error: SessionError::TcpDisconnection,
+        } => CloseReason::ConnectionClosed,
+        Event::SessionErrorReceived {
+            error: SessionError::GarbledMessage { text, .. },
+        } => CloseReason::GarbledMessage { text: text.clone() },
+        _ if logging_on => CloseReason::LogonRejected { text: None },
+        Event::SessionErrorReceived {
+            error: SessionError::MessageRejected { text, .. } | SessionError::MissingMsgSeqNum { text },
+        } => CloseReason::MessageRejected { text: text.clone() },
+        Event::SessionErrorReceived { error } => CloseReason::MessageRejected { text: error.to_string() },
+        Event::SendHeartbeat | Event::SendTestRequest(_) => CloseReason::HeartbeatTimeout,
+        Event::LogoutExpired => CloseReason::LogoutTimeout,
+        // the last resend expected before logging out was received
+        _ if matches!(to, State::End) => CloseReason::LoggedOut { text: None },
+        _ => CloseReason::Error(EngineError {
+            message: format!("session error on {event:?}"),
+            io_error_kind: None,
+        }),
+    }
+}
+
 pub(super) fn build_logout_message_with_text(begin_string: &str, text: &[u8]) -> MessageBuilder {
     MessageBuilder::new(begin_string, MsgType::LOGOUT.into()).push(Tags::Text, text)
 }
@@ -573,13 +962,14 @@ pub(super) fn build_logout_message(begin_string: &str) -> MessageBuilder {
 }
 
 fn build_message_reject(
+    begin_string: &str,
     text: &String,
     reject_reason: &Option<SessionRejectReason>,
     msg_seq_num: &u32,
     ref_tag_id: &Option<u32>,
     ref_msg_type: &Option<char>,
 ) -> MessageBuilder {
-    let mut builder: MessageBuilder = MessageBuilder::new("FIX.4.2", MsgType::REJECT.into())
+    let mut builder: MessageBuilder = MessageBuilder::new(begin_string, MsgType::REJECT.into())
         .push(
             Tags::RefSeqNum,
             SerializedInt::from(*msg_seq_num).as_bytes(),
@@ -604,35 +994,325 @@ fn build_message_reject(
     builder
 }
 
-#[derive(Default)]
-pub(super) struct Sequences(AtomicU32, AtomicU32);
+// The next outgoing and incoming sequence numbers. Clones share the same numbers, so that the
+// handles of the engine can read them.
+#[derive(Clone, Default)]
+pub(crate) struct Sequences(Arc<(AtomicU32, AtomicU32)>);
 
 impl Sequences {
     pub(super) fn next_outgoing(&mut self) -> u32 {
-        self.0.fetch_add(1, Ordering::Relaxed)
+        self.0 .0.fetch_add(1, Ordering::Relaxed)
     }
     pub(super) fn incr_incoming(&mut self) -> u32 {
-        self.1.fetch_add(1, Ordering::Relaxed)
+        self.0 .1.fetch_add(1, Ordering::Relaxed)
     }
     pub(super) fn peek_incoming(&self) -> u32 {
-        self.1.load(Ordering::Relaxed)
+        self.0 .1.load(Ordering::Relaxed)
     }
     pub(super) fn peek_outgoing(&self) -> u32 {
-        self.0.load(Ordering::Relaxed)
+        self.0 .0.load(Ordering::Relaxed)
+    }
+    pub(super) fn set_outgoing(&mut self, next_outgoing: u32) {
+        self.0 .0.store(next_outgoing, Ordering::Relaxed);
     }
     pub(super) fn reset_incoming(&mut self, new: u32) -> std::result::Result<(), &'static str> {
-        let old = self.1.fetch_max(new, Ordering::Relaxed);
+        let old = self.0 .1.fetch_max(new, Ordering::Relaxed);
         if old > new {
             Err("Value is incorrect (out of range) for this tag")
         } else {
-            self.1 = new.into();
             Ok(())
         }
     }
+    fn reset(&mut self) {
+        self.0 .0.store(1, Ordering::Relaxed);
+        self.0 .1.store(1, Ordering::Relaxed);
+    }
+    pub(crate) fn numbers(&self) -> SequenceNumbers {
+        SequenceNumbers {
+            next_incoming: self.peek_incoming(),
+            next_outgoing: self.peek_outgoing(),
+        }
+    }
 }
 
 impl From<(u32, u32)> for Sequences {
     fn from((incoming, outgoing): (u32, u32)) -> Self {
-        Sequences(outgoing.into(), incoming.into())
+        Sequences(Arc::new((outgoing.into(), incoming.into())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_answer_test_request() {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .build()
+            .unwrap();
+        let mut state_machine = MyStateMachine::new(&settings, (1, 1));
+
+        state_machine.send_test_request();
+        state_machine.send_watchdog_test_request();
+        state_machine.send_watchdog_test_request();
+        assert_eq!(state_machine.outbox.len(), 3);
+
+        assert!(state_machine.answer_test_request(b"TEST").is_none());
+        assert!(state_machine.answer_test_request(b"WATCHDOG-2").is_some());
+        assert!(state_machine.answer_test_request(b"WATCHDOG-2").is_none());
+        assert!(state_machine.answer_test_request(b"WATCHDOG-1").is_some());
+        assert!(state_machine.answer_test_request(b"TEST-1").is_some());
+
+        for _ in 0..=MAX_OUTSTANDING_TEST_REQUESTS {
+            state_machine.send_test_request();
+        }
+        assert!(state_machine.answer_test_request(b"TEST-2").is_none());
+        assert!(state_machine.answer_test_request(b"TEST-3").is_some());
+    }
+
+    #[test]
+    fn test_logon_fields() {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .with_logon_credentials("user", "secret")
+            .with_additional_logon_fields(vec![(9000, b"custom".to_vec())])
+            .build()
+            .unwrap();
+        let mut state_machine = MyStateMachine::new(&settings, (1, 1));
+
+        state_machine.handle(&Event::Connect(false));
+        let (builder, ..) = state_machine.outbox_pop().unwrap();
+        assert_eq!(builder.msg_type(), MsgType::LOGON.into());
+        assert_eq!(builder.field(553), Some(&b"user"[..]));
+        assert_eq!(builder.field(554), Some(&b"secret"[..]));
+        assert_eq!(builder.field(9000), Some(&b"custom"[..]));
+    }
+
+    fn test_state_machine() -> MyStateMachine {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .build()
+            .unwrap();
+        MyStateMachine::new(&settings, (10, 20))
+    }
+
+    // Pop the next message of the outbox as if it was sent, and return whether it is a
+    // `Logon<A>` with `ResetSeqNumFlag(141)=Y`.
+    fn send_next(state_machine: &mut MyStateMachine) -> bool {
+        let (builder, ..) = state_machine.outbox_pop().unwrap();
+        state_machine.sequences.next_outgoing();
+        builder.msg_type() == MsgType::LOGON.into()
+            && builder.field(Tags::ResetSeqNumFlag.into()) == Some(&b"Y"[..])
+    }
+
+    #[test]
+    fn test_comp_id_mismatch() {
+        for (comp_id_mismatch, expected) in [
+            (CompIdMismatch::Reject, vec![MsgType::REJECT]),
+            (CompIdMismatch::Logout, vec![MsgType::REJECT, MsgType::LOGOUT]),
+            (CompIdMismatch::Disconnect, vec![]),
+        ] {
+            let mut state_machine = test_state_machine();
+            state_machine.comp_id_mismatch = comp_id_mismatch;
+            state_machine.handle(&Event::Connect(false));
+            send_next(&mut state_machine);
+            state_machine.handle(&Event::LogonReceived(10, 30, Some(0), false, None));
+            assert!(matches!(state_machine.state(), State::LoggedIn));
+
+            let error = SessionError::new_message_rejected(
+                Some(SessionRejectReason::COMPID_PROBLEM),
+                11,
+                Some(Tags::SenderCompID.into()),
+                Some(MsgType::ORDER_SINGLE.into()),
+            );
+            state_machine.handle(&Event::SessionErrorReceived { error });
+            let mut sent = Vec::new();
+            while let Some((builder, ..)) = state_machine.outbox_pop() {
+                sent.push(MsgType::try_from(builder.msg_type()).unwrap());
+            }
+            assert_eq!(sent, expected);
+            let keeps_running = comp_id_mismatch == CompIdMismatch::Reject;
+            assert_eq!(matches!(state_machine.state(), State::LoggedIn), keeps_running);
+            assert_eq!(state_machine.sequences.peek_incoming(), 12);
+        }
+    }
+
+    #[test]
+    fn test_heal_outgoing_sequence() {
+        let mut state_machine = test_state_machine();
+        assert_eq!(state_machine.heal_outgoing_sequence(50, 20), None);
+        state_machine.handle(&Event::Connect(false));
+        assert!(!send_next(&mut state_machine));
+        assert_eq!(state_machine.sequences.peek_outgoing(), 21);
+
+        assert_eq!(state_machine.heal_outgoing_sequence(50, 10), Some(21));
+        assert!(should_reconnect(&state_machine));
+        assert_eq!(state_machine.sequences.peek_outgoing(), 50);
+        assert_eq!(state_machine.sequences.peek_incoming(), 11);
+
+        // the new logon is sent with the expected sequence number, and is only healed once
+        state_machine.handle(&Event::Connect(false));
+        assert!(!should_reconnect(&state_machine));
+        assert!(!send_next(&mut state_machine));
+        assert_eq!(state_machine.sequences.peek_outgoing(), 51);
+        assert_eq!(state_machine.heal_outgoing_sequence(60, 11), None);
+    }
+
+    #[test]
+    fn test_sequence_reset_by_peer() {
+        let mut state_machine = test_state_machine();
+        state_machine.handle(&Event::Connect(false));
+        assert!(!send_next(&mut state_machine));
+
+        // The peer answers the logon with a reset.
+        state_machine.handle(&Event::LogonReceived(1, 30, Some(0), true, None));
+        assert!(matches!(state_machine.state(), State::LoggedIn));
+        assert_eq!(state_machine.take_sequences_reset(), Some(true));
+        assert!(send_next(&mut state_machine));
+        assert!(state_machine.outbox.is_empty());
+        assert_eq!(state_machine.sequences.peek_outgoing(), 2);
+        assert_eq!(state_machine.sequences.peek_incoming(), 2);
+
+        // The peer resets again in the middle of the session.
+        state_machine.handle(&Event::ApplicationMessageReceived(2, None));
+        state_machine.handle(&Event::LogonReceived(1, 30, Some(0), true, None));
+        assert!(matches!(state_machine.state(), State::LoggedIn));
+        assert_eq!(state_machine.take_sequences_reset(), Some(true));
+        assert!(send_next(&mut state_machine));
+        assert_eq!(state_machine.sequences.peek_outgoing(), 2);
+        assert_eq!(state_machine.sequences.peek_incoming(), 2);
+
+        // The acceptor confirms a reset requested by the initiator.
+        let mut state_machine = test_state_machine();
+        state_machine.handle(&Event::Accept);
+        state_machine.handle(&Event::LogonReceived(1, 30, Some(0), true, None));
+        assert!(matches!(state_machine.state(), State::LoggedIn));
+        assert_eq!(state_machine.take_sequences_reset(), Some(true));
+        assert!(send_next(&mut state_machine));
+        assert_eq!(state_machine.sequences.peek_incoming(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_logon() {
+        fn logged_in(duplicate_logon: DuplicateLogon) -> MyStateMachine {
+            let settings = SessionSettings::builder()
+                .with_sender_comp_id("client")
+                .with_target_comp_id("server")
+                .with_socket_addr("127.0.0.1:0".parse().unwrap())
+                .with_store_path("./store".into())
+                .with_log_dir("./log".into())
+                .with_duplicate_logon(duplicate_logon)
+                .build()
+                .unwrap();
+            let mut state_machine = MyStateMachine::new(&settings, (10, 20));
+            state_machine.handle(&Event::Connect(false));
+            state_machine.outbox_pop().unwrap();
+            state_machine.handle(&Event::LogonReceived(10, 30, Some(0), false, None));
+            assert!(matches!(state_machine.state(), State::LoggedIn));
+            state_machine
+        }
+        let next_msg_type = |state_machine: &mut MyStateMachine| {
+            state_machine.outbox_pop().map(|(builder, ..)| builder.msg_type())
+        };
+
+        // a resend of the first logon is ignored, whatever its sequence number
+        let mut state_machine = logged_in(DuplicateLogon::Disconnect);
+        state_machine.handle(&Event::LogonReceived(10, 30, Some(0), false, Some(PossDupFlag::YES)));
+        state_machine.handle(&Event::LogonReceived(11, 30, Some(0), false, Some(PossDupFlag::YES)));
+        assert!(matches!(state_machine.state(), State::LoggedIn));
+        assert!(state_machine.outbox.is_empty());
+        assert_eq!(state_machine.sequences.peek_incoming(), 12);
+
+        // a new logon is rejected
+        let mut state_machine = logged_in(DuplicateLogon::Reject);
+        state_machine.handle(&Event::LogonReceived(11, 30, Some(0), false, None));
+        assert!(matches!(state_machine.state(), State::LoggedIn));
+        assert_eq!(next_msg_type(&mut state_machine), Some(MsgType::REJECT.into()));
+        assert_eq!(state_machine.sequences.peek_incoming(), 12);
+
+        // or ends the session
+        let mut state_machine = logged_in(DuplicateLogon::Disconnect);
+        state_machine.handle(&Event::LogonReceived(11, 30, Some(0), false, None));
+        assert!(in_error_state(&state_machine));
+        assert_eq!(next_msg_type(&mut state_machine), Some(MsgType::LOGOUT.into()));
+
+        // a logon with a sequence number too low ends the session like any other message
+        let mut state_machine = logged_in(DuplicateLogon::Reject);
+        state_machine.handle(&Event::LogonReceived(5, 30, Some(0), false, None));
+        assert!(in_error_state(&state_machine));
+        assert_eq!(next_msg_type(&mut state_machine), Some(MsgType::LOGOUT.into()));
+
+        // and a gap before it is filled first
+        let mut state_machine = logged_in(DuplicateLogon::Reject);
+        state_machine.handle(&Event::LogonReceived(15, 30, Some(0), false, None));
+        assert!(matches!(state_machine.state(), State::ExpectingResends { .. }));
+        assert_eq!(next_msg_type(&mut state_machine), Some(MsgType::RESEND_REQUEST.into()));
+    }
+
+    #[test]
+    fn test_sequence_reset_by_engine() {
+        let mut state_machine = test_state_machine();
+        state_machine.handle(&Event::Connect(true));
+        assert_eq!(state_machine.take_sequences_reset(), Some(false));
+        assert!(send_next(&mut state_machine));
+
+        // The peer confirms the reset, which is not confirmed again.
+        state_machine.handle(&Event::LogonReceived(1, 30, Some(0), true, None));
+        assert!(matches!(state_machine.state(), State::LoggedIn));
+        assert_eq!(state_machine.take_sequences_reset(), None);
+        assert!(state_machine.outbox.is_empty());
+        assert_eq!(state_machine.sequences.peek_outgoing(), 2);
+        assert_eq!(state_machine.sequences.peek_incoming(), 2);
+    }
+
+    #[test]
+    fn test_outbox_priority() {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .build()
+            .unwrap();
+        let mut state_machine = MyStateMachine::new(&settings, (1, 1));
+        let order = || MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into());
+
+        state_machine.outbox_push_with_sender(order(), oneshot::channel().0);
+        state_machine.outbox_push_with_sender(build_logout_message("FIX.4.2"), oneshot::channel().0);
+        state_machine.outbox_push_with_sender(order(), oneshot::channel().0);
+        state_machine.send_test_request();
+        assert_eq!(state_machine.outbox.len(), 4);
+
+        // the test request jumps ahead, and the requested logout stays behind the order before it
+        let mut msg_types = Vec::new();
+        while let Some((builder, ..)) = state_machine.outbox_pop() {
+            msg_types.push(builder.msg_type());
+        }
+        assert_eq!(msg_types, ['1', 'D', '5', 'D']);
+
+        // the permit of a message is held until it leaves the outbox
+        let permits = Arc::new(tokio::sync::Semaphore::new(1));
+        let permit = Arc::clone(&permits).try_acquire_owned().unwrap();
+        let permit = OutboxPermit::new(Some(permit), None);
+        state_machine.outbox_push_with_permit(order(), oneshot::channel().0, Some(permit));
+        state_machine.send_test_request();
+        assert_eq!(permits.available_permits(), 0);
+        assert_eq!(state_machine.outbox_discard_app_messages(), 1);
+        assert_eq!(state_machine.outbox.len(), 1);
+        assert_eq!(permits.available_permits(), 1);
     }
 }
@@ -0,0 +1,302 @@
+//! Drive the session state machine of the engine directly, without a connection.
+//!
+//! Enabled with the `test-util` feature. A [`StateMachine`] handles the same [`Event`]s the engine
+//! derives from the messages it receives and from its timers, and queues the session messages
+//! it would send in its outbox, so that session logic such as resends and logouts can be unit
+//! tested deterministically.
+//!
+//! The engine numbers and writes the messages of the outbox; [`StateMachine::send_next`] does so
+//! in its place.
+//!
+//! ## Example
+//! ```rust
+//! use forgefix::fix::generated::MsgType;
+//! use forgefix::fix::state_machine::{Event, State, StateMachine};
+//! use forgefix::SessionSettings;
+//!
+//! let settings = SessionSettings::ephemeral("MY_ID", "BROKER", "127.0.0.1:0".parse().unwrap());
+//! let mut state_machine = StateMachine::new(&settings, 1, 1);
+//! state_machine.handle(Event::Connect { reset_seq_num: false });
+//! assert_eq!(state_machine.send_next().unwrap().msg_type(), MsgType::LOGON.into());
+//! state_machine.handle(Event::LogonReceived {
+//!     msg_seq_num: 1,
+//!     heart_bt_int: 30,
+//!     encrypt_method: Some(0),
+//!     reset_seq_num: false,
+//!     poss_dup: false,
+//! });
+//! assert_eq!(state_machine.state(), &State::LoggedIn);
+//!
+//! // a gap in the sequence numbers of the peer is asked to be resent
+//! state_machine.handle(Event::HeartbeatReceived { msg_seq_num: 5, poss_dup: false });
+//! assert_eq!(state_machine.send_next().unwrap().msg_type(), MsgType::RESEND_REQUEST.into());
+//! ```
+
+use crate::fix::encode::MessageBuilder;
+use crate::fix::generated::{GapFillFlag, MsgType, PossDupFlag, SessionRejectReason};
+use crate::fix::session::{self, MyStateMachine};
+use crate::fix::{GarbledMessageType, SessionError};
+use crate::{CloseReason, SequenceNumbers, SessionSettings};
+
+use std::time::Duration;
+
+pub use crate::fix::session::State;
+
+/// What happened to a FIX connection, as handled by a [`StateMachine`].
+#[derive(Debug)]
+pub enum Event {
+    /// The connection to the peer was made by an initiator, which sends its `Logon<A>`.
+    Connect {
+        /// Whether the `Logon<A>` has `ResetSeqNumFlag(141)=Y`.
+        reset_seq_num: bool,
+    },
+    /// The connection of the peer was accepted by an acceptor.
+    Accept,
+    /// A `Logon<A>` was received.
+    LogonReceived {
+        msg_seq_num: u32,
+        /// The `HeartBtInt(108)`, in seconds.
+        heart_bt_int: u32,
+        encrypt_method: Option<u32>,
+        reset_seq_num: bool,
+        poss_dup: bool,
+    },
+    /// A `Logout<5>` was written to the connection.
+    LogoutSent,
+    /// A `Logout<5>` was received.
+    LogoutReceived {
+        msg_seq_num: u32,
+        text: Option<String>,
+        poss_dup: bool,
+    },
+    /// A `Heartbeat<0>` was received.
+    HeartbeatReceived { msg_seq_num: u32, poss_dup: bool },
+    /// A `SequenceReset<4>` was received.
+    SequenceResetReceived {
+        msg_seq_num: u32,
+        gap_fill: bool,
+        new_seq_no: u32,
+        poss_dup: bool,
+    },
+    /// A `TestRequest<1>` was received.
+    TestRequestReceived {
+        msg_seq_num: u32,
+        test_req_id: Vec<u8>,
+        poss_dup: bool,
+    },
+    /// A `ResendRequest<2>` was received.
+    ResendRequestReceived {
+        msg_seq_num: u32,
+        begin_seq_no: u32,
+        end_seq_no: u32,
+        poss_dup: bool,
+    },
+    /// A `Reject<3>` was received.
+    RejectReceived { msg_seq_num: u32, poss_dup: bool },
+    /// An application message was received.
+    ApplicationMessageReceived { msg_seq_num: u32, poss_dup: bool },
+    /// A message failed validation, and is rejected with a `Reject<3>`.
+    MessageRejected {
+        msg_seq_num: u32,
+        reason: Option<SessionRejectReason>,
+        ref_tag_id: Option<u32>,
+        ref_msg_type: Option<char>,
+    },
+    /// A garbled message was received, which is ignored.
+    GarbledMessageReceived,
+    /// The peer closed the connection.
+    Disconnected,
+    /// Nothing was sent for a heartbeat interval.
+    SendHeartbeat,
+    /// Nothing was received for longer than a heartbeat interval.
+    SendTestRequest,
+    /// The peer did not answer a `Logout<5>` in time.
+    LogoutExpired,
+    /// No application message was received within the window of the watchdog.
+    WatchdogExpired(Duration),
+}
+
+fn poss_dup_flag(poss_dup: bool) -> Option<PossDupFlag> {
+    poss_dup.then_some(PossDupFlag::YES)
+}
+
+impl From<Event> for session::Event {
+    fn from(event: Event) -> session::Event {
+        match event {
+            Event::Connect { reset_seq_num } => session::Event::Connect(reset_seq_num),
+            Event::Accept => session::Event::Accept,
+            Event::LogonReceived {
+                msg_seq_num,
+                heart_bt_int,
+                encrypt_method,
+                reset_seq_num,
+                poss_dup,
+            } => session::Event::LogonReceived(
+                msg_seq_num,
+                heart_bt_int,
+                encrypt_method,
+                reset_seq_num,
+                poss_dup_flag(poss_dup),
+            ),
+            Event::LogoutSent => session::Event::LogoutSent,
+            Event::LogoutReceived { msg_seq_num, text, poss_dup } => {
+                session::Event::LogoutReceived(msg_seq_num, text, poss_dup_flag(poss_dup))
+            }
+            Event::HeartbeatReceived { msg_seq_num, poss_dup } => {
+                session::Event::HeartbeatReceived(msg_seq_num, poss_dup_flag(poss_dup))
+            }
+            Event::SequenceResetReceived {
+                msg_seq_num,
+                gap_fill,
+                new_seq_no,
+                poss_dup,
+            } => session::Event::SequenceResetReceived {
+                msg_seq_num,
+                gap_fill: Some(if gap_fill { GapFillFlag::YES } else { GapFillFlag::NO }),
+                new_seq_no,
+                poss_dup: poss_dup_flag(poss_dup),
+            },
+            Event::TestRequestReceived {
+                msg_seq_num,
+                test_req_id,
+                poss_dup,
+            } => session::Event::TestRequestReceived {
+                msg_seq_num,
+                test_req_id,
+                poss_dup: poss_dup_flag(poss_dup),
+            },
+            Event::ResendRequestReceived {
+                msg_seq_num,
+                begin_seq_no,
+                end_seq_no,
+                poss_dup,
+            } => session::Event::ResendRequestReceived(
+                msg_seq_num,
+                begin_seq_no,
+                end_seq_no,
+                poss_dup_flag(poss_dup),
+            ),
+            Event::RejectReceived { msg_seq_num, poss_dup } => {
+                session::Event::RejectReceived(msg_seq_num, poss_dup_flag(poss_dup))
+            }
+            Event::ApplicationMessageReceived { msg_seq_num, poss_dup } => {
+                session::Event::ApplicationMessageReceived(msg_seq_num, poss_dup_flag(poss_dup))
+            }
+            Event::MessageRejected {
+                msg_seq_num,
+                reason,
+                ref_tag_id,
+                ref_msg_type,
+            } => session::Event::SessionErrorReceived {
+                error: SessionError::new_message_rejected(reason, msg_seq_num, ref_tag_id, ref_msg_type),
+            },
+            Event::GarbledMessageReceived => session::Event::SessionErrorReceived {
+                error: SessionError::new_garbled_message(String::from("Garbled message"), GarbledMessageType::Other),
+            },
+            Event::Disconnected => session::Event::SessionErrorReceived {
+                error: SessionError::TcpDisconnection,
+            },
+            Event::SendHeartbeat => session::Event::SendHeartbeat,
+            Event::SendTestRequest => session::Event::SendTestRequest(0),
+            Event::LogoutExpired => session::Event::LogoutExpired,
+            Event::WatchdogExpired(window) => session::Event::WatchdogExpired(window),
+        }
+    }
+}
+
+/// The session state machine of an engine. See the [module](self) documentation.
+pub struct StateMachine {
+    inner: MyStateMachine,
+}
+
+impl StateMachine {
+    /// A state machine in the [`State::Start`] state, with the session settings of `settings`,
+    /// expecting `next_incoming` as the `MsgSeqNum(34)` of the next message of the peer, and
+    /// sending its next message with `next_outgoing`.
+    pub fn new(settings: &SessionSettings, next_incoming: u32, next_outgoing: u32) -> StateMachine {
+        StateMachine {
+            inner: MyStateMachine::new(settings, (next_incoming, next_outgoing)),
+        }
+    }
+
+    /// Handle `event`, which may change the state and queue messages in the outbox.
+    pub fn handle(&mut self, event: Event) {
+        self.inner.handle(&event.into());
+    }
+
+    /// The current state.
+    pub fn state(&self) -> &State {
+        self.inner.state()
+    }
+
+    /// The sequence numbers of the next incoming and outgoing messages.
+    pub fn sequence_numbers(&self) -> SequenceNumbers {
+        self.inner.sequences.numbers()
+    }
+
+    /// Why the connection ended, once the state machine reached [`State::End`] or
+    /// [`State::Error`].
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.inner.close_reason()
+    }
+
+    /// The messages waiting to be sent, in the order they will be sent.
+    pub fn outbox(&self) -> impl Iterator<Item = &MessageBuilder> {
+        self.inner.outbox.iter().map(|(builder, ..)| builder)
+    }
+
+    /// Take the next message of the outbox as if the engine sent it: the next outgoing sequence
+    /// number is used up, and [`Event::LogoutSent`] is handled after a `Logout<5>`.
+    pub fn send_next(&mut self) -> Option<MessageBuilder> {
+        let (builder, ..) = self.inner.outbox_pop()?;
+        self.inner.sequences.next_outgoing();
+        if builder.msg_type() == MsgType::LOGOUT.into() {
+            self.inner.handle(&session::Event::LogoutSent);
+        }
+        Some(builder)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fix::generated::Tags;
+
+    #[test]
+    fn test_state_machine() {
+        let settings = SessionSettings::ephemeral("MY_ID", "BROKER", "127.0.0.1:0".parse().unwrap());
+        let mut state_machine = StateMachine::new(&settings, 1, 1);
+        state_machine.handle(Event::Connect { reset_seq_num: false });
+        assert_eq!(state_machine.send_next().unwrap().msg_type(), MsgType::LOGON.into());
+        state_machine.handle(Event::LogonReceived {
+            msg_seq_num: 1,
+            heart_bt_int: 30,
+            encrypt_method: Some(0),
+            reset_seq_num: false,
+            poss_dup: false,
+        });
+        assert_eq!(state_machine.state(), &State::LoggedIn);
+
+        state_machine.handle(Event::ApplicationMessageReceived { msg_seq_num: 4, poss_dup: false });
+        let resend_request = state_machine.send_next().unwrap();
+        assert_eq!(resend_request.field(Tags::BeginSeqNo.into()), Some(&b"2"[..]));
+        assert!(matches!(state_machine.state(), State::ExpectingResends { .. }));
+        state_machine.handle(Event::SequenceResetReceived {
+            msg_seq_num: 2,
+            gap_fill: true,
+            new_seq_no: 5,
+            poss_dup: true,
+        });
+        assert_eq!(state_machine.state(), &State::LoggedIn);
+        assert_eq!(
+            state_machine.sequence_numbers(),
+            SequenceNumbers { next_incoming: 5, next_outgoing: 3 }
+        );
+
+        state_machine.handle(Event::LogoutReceived { msg_seq_num: 5, text: None, poss_dup: false });
+        assert_eq!(state_machine.outbox().count(), 1);
+        assert_eq!(state_machine.send_next().unwrap().msg_type(), MsgType::LOGOUT.into());
+        assert_eq!(state_machine.state(), &State::End);
+        assert!(state_machine.close_reason().is_some());
+    }
+}
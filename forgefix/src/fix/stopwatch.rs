@@ -1,4 +1,5 @@
 use crate::fix::session::Event;
+use crate::HeartbeatTimer;
 use tokio::time::{sleep_until, Duration, Instant, Sleep};
 
 pub(super) struct Timeout {
@@ -25,6 +26,10 @@ impl Timeout {
         self.reset_timeout();
     }
 
+    pub(super) fn duration(&self) -> Duration {
+        self.duration
+    }
+
     pub(super) fn timeout(&self) -> (Sleep, &Event) {
         (sleep_until(self.next_instant), &self.event)
     }
@@ -32,57 +37,85 @@ impl Timeout {
 
 pub(super) struct FixTimeouts {
     heartbeat_timeout: Timeout,
-    test_request_timeout: Timeout,
+    heartbeat_timer: HeartbeatTimer,
+    // `None` if test requests are not sent
+    test_request_timeout: Option<Timeout>,
     logout_timeout: Timeout,
+    watchdog_timeout: Option<Timeout>,
     awaiting_logout: bool,
 }
 
 impl FixTimeouts {
     pub(super) fn new(
         heartbeat_dur: Duration,
-        test_request_dur: Duration,
+        test_request_dur: Option<Duration>,
         logout_dur: Duration,
+        heartbeat_timer: HeartbeatTimer,
     ) -> FixTimeouts {
         let next_heartbeat_timeout = Instant::now() + heartbeat_dur;
-        let next_test_request_timeout = Instant::now() + test_request_dur;
         let next_logout_timeout = Instant::now() + logout_dur;
         let awaiting_logout = false;
 
         let heartbeat_timeout =
             Timeout::new(next_heartbeat_timeout, heartbeat_dur, Event::SendHeartbeat);
-        let test_request_timeout = Timeout::new(
-            next_test_request_timeout,
-            test_request_dur,
-            Event::SendTestRequest(0),
-        );
+        let test_request_timeout = test_request_dur.map(|test_request_dur| {
+            Timeout::new(
+                Instant::now() + test_request_dur,
+                test_request_dur,
+                Event::SendTestRequest(0),
+            )
+        });
         let logout_timeout = Timeout::new(next_logout_timeout, logout_dur, Event::LogoutExpired);
 
         FixTimeouts {
             heartbeat_timeout,
+            heartbeat_timer,
             test_request_timeout,
             logout_timeout,
+            watchdog_timeout: None,
             awaiting_logout,
         }
     }
 
     pub(super) fn next_expiring_timeout(&mut self) -> &mut Timeout {
-        if !self.awaiting_logout
-            && self.heartbeat_timeout.next_instant < self.test_request_timeout.next_instant
-        {
-            &mut self.heartbeat_timeout
-        } else if !self.awaiting_logout {
-            &mut self.test_request_timeout
-        } else {
-            &mut self.logout_timeout
+        if self.awaiting_logout {
+            return &mut self.logout_timeout;
+        }
+        let mut next = &mut self.heartbeat_timeout;
+        match self.test_request_timeout {
+            Some(ref mut test_request) if test_request.next_instant <= next.next_instant => next = test_request,
+            _ => {}
+        }
+        match self.watchdog_timeout {
+            Some(ref mut watchdog) if watchdog.next_instant < next.next_instant => watchdog,
+            _ => next,
         }
     }
 
-    pub(super) fn reset_heartbeat(&mut self) {
-        self.heartbeat_timeout.reset_timeout();
+    pub(super) fn set_watchdog(&mut self, window: Option<Duration>) {
+        self.watchdog_timeout = window.map(|window| {
+            Timeout::new(Instant::now() + window, window, Event::WatchdogExpired(window))
+        });
+    }
+
+    pub(super) fn reset_watchdog(&mut self) {
+        if let Some(watchdog) = self.watchdog_timeout.as_mut() {
+            watchdog.reset_timeout();
+        }
+    }
+
+    // Messages were sent, which put off the next heartbeat unless heartbeats are sent on a fixed
+    // timer.
+    pub(super) fn messages_sent(&mut self) {
+        if self.heartbeat_timer == HeartbeatTimer::Idle {
+            self.heartbeat_timeout.reset_timeout();
+        }
     }
 
     pub(super) fn reset_test_request(&mut self) {
-        self.test_request_timeout.reset_timeout();
+        if let Some(test_request_timeout) = self.test_request_timeout.as_mut() {
+            test_request_timeout.reset_timeout();
+        }
     }
 
     pub(super) fn start_logout_timeout(&mut self) {
@@ -93,15 +126,53 @@ impl FixTimeouts {
     pub(super) fn set_durations(
         &mut self,
         heartbeat_dur: Duration,
-        test_request_dur: Duration,
+        test_request_dur: Option<Duration>,
         logout_dur: Duration,
     ) {
         self.heartbeat_timeout.set_timeout_duration(heartbeat_dur);
         self.heartbeat_timeout.reset_timeout();
-        self.test_request_timeout
-            .set_timeout_duration(test_request_dur);
-        self.test_request_timeout.reset_timeout();
+        if let (Some(test_request_timeout), Some(test_request_dur)) =
+            (self.test_request_timeout.as_mut(), test_request_dur)
+        {
+            test_request_timeout.set_timeout_duration(test_request_dur);
+            test_request_timeout.reset_timeout();
+        }
         self.logout_timeout.set_timeout_duration(logout_dur);
         self.logout_timeout.reset_timeout();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_heartbeat_timer() {
+        let mut timeouts = FixTimeouts::new(
+            Duration::from_secs(30),
+            Some(Duration::from_secs(10)),
+            Duration::from_secs(60),
+            HeartbeatTimer::Idle,
+        );
+        assert!(matches!(timeouts.next_expiring_timeout().event, Event::SendTestRequest(_)));
+        let heartbeat_due = timeouts.heartbeat_timeout.next_instant;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        timeouts.messages_sent();
+        assert!(timeouts.heartbeat_timeout.next_instant > heartbeat_due);
+
+        // without test requests, only the heartbeat and the watchdog are due
+        let mut timeouts = FixTimeouts::new(
+            Duration::from_secs(30),
+            None,
+            Duration::from_secs(60),
+            HeartbeatTimer::Fixed,
+        );
+        assert!(matches!(timeouts.next_expiring_timeout().event, Event::SendHeartbeat));
+        timeouts.set_watchdog(Some(Duration::from_secs(1)));
+        assert!(matches!(timeouts.next_expiring_timeout().event, Event::WatchdogExpired(_)));
+        let heartbeat_due = timeouts.heartbeat_timeout.next_instant;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        timeouts.messages_sent();
+        assert_eq!(timeouts.heartbeat_timeout.next_instant, heartbeat_due);
+    }
+}
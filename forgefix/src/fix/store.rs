@@ -1,34 +1,71 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 
-use crate::SessionSettings;
+use crate::{Clock, EpochRows, SessionSettings, StoreStats};
+use crate::fix::crypto::StoreCipher;
+use crate::fix::dedup;
 use crate::fix::mem::MsgBuf;
+use crate::fix::memory_store::MemoryStore;
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant; 
 
 use chrono::offset::Utc; 
-use chrono::naive::NaiveDateTime; 
+use chrono::naive::{NaiveDate, NaiveDateTime}; 
 use chrono::{DateTime, Duration}; 
 use tokio::sync::{mpsc, oneshot};
 use tokio_rusqlite::Connection;
+use tracing::Instrument;
 use rusqlite::{OptionalExtension, OpenFlags};
 
 const SQL_ENTER_WAL_MODE: &str = "PRAGMA journal_mode=WAL;";
 const SQL_VACUUM: &str = "VACUUM;";
-const SQL_CREATE_INCOMING_TABLE :&str="CREATE TABLE IF NOT EXISTS incoming_messages (key INTEGER PRIMARY KEY AUTOINCREMENT, epoch_guid VARCHAR, msg_seq_num INT, message BLOB);";
+// Only takes effect on a new store before it enters WAL mode, or on an existing one at its next
+// `VACUUM`.
+const SQL_AUTO_VACUUM_INCREMENTAL: &str = "PRAGMA auto_vacuum = INCREMENTAL;";
+const SQL_CREATE_INCOMING_TABLE :&str="CREATE TABLE IF NOT EXISTS incoming_messages (key INTEGER PRIMARY KEY AUTOINCREMENT, epoch_guid VARCHAR, msg_seq_num INT, message BLOB, receive_time VARCHAR);";
+// Stores created before incoming messages were kept have no `receive_time` column.
+const SQL_HAS_RECEIVE_TIME: &str =
+    "SELECT COUNT(*) FROM pragma_table_info('incoming_messages') WHERE name = 'receive_time';";
+const SQL_ADD_RECEIVE_TIME: &str = "ALTER TABLE incoming_messages ADD COLUMN receive_time VARCHAR;";
 const SQL_CREATE_OUTGOING_TABLE :&str=
     "CREATE TABLE IF NOT EXISTS outgoing_messages (key INTEGER PRIMARY KEY AUTOINCREMENT, epoch_guid VARCHAR, msg_seq_num INT, send_time VARCHAR, message BLOB);";
 const SQL_CREATE_SEQUENCES: &str =
-    "CREATE TABLE IF NOT EXISTS sequences (epoch_guid VARCHAR, next_incoming INTEGER, next_outgoing INTEGER)";
+    "CREATE TABLE IF NOT EXISTS sequences (epoch_guid VARCHAR, next_incoming INTEGER, next_outgoing INTEGER, seal BLOB)";
+// Stores created before sequence numbers were sealed have no `seal` column.
+const SQL_HAS_SEQUENCES_SEAL: &str = "SELECT COUNT(*) FROM pragma_table_info('sequences') WHERE name = 'seal';";
+const SQL_ADD_SEQUENCES_SEAL: &str = "ALTER TABLE sequences ADD COLUMN seal BLOB;";
+// Encrypted messages start with their random nonce, and messages written without encryption with
+// `8=`.
+const SQL_HAS_PLAINTEXT_MESSAGES: &str = "SELECT EXISTS (SELECT 1 FROM outgoing_messages WHERE epoch_guid = ?1 AND substr(message, 1, 2) = CAST('8=' AS BLOB)) OR EXISTS (SELECT 1 FROM incoming_messages WHERE epoch_guid = ?1 AND substr(message, 1, 2) = CAST('8=' AS BLOB));";
+const SQL_SELECT_SEQUENCES: &str =
+    "SELECT next_incoming, next_outgoing, seal FROM sequences WHERE epoch_guid = ?;";
+const SQL_UPDATE_SEQUENCES: &str =
+    "UPDATE sequences SET next_outgoing = ?1, next_incoming = ?2, seal = ?3 WHERE epoch_guid = ?4";
+const SQL_CREATE_SENT_ORDERS_TABLE: &str =
+    "CREATE TABLE IF NOT EXISTS sent_orders (epoch_guid VARCHAR, cl_ord_id BLOB, msg_seq_num INT);";
 const SQL_ENSURE_SEQUENCE_ROW: &str = "INSERT INTO sequences(epoch_guid, next_incoming, next_outgoing) SELECT ?1,1,1 WHERE NOT EXISTS (SELECT * FROM sequences WHERE epoch_guid = ?1);";
 const SQL_INSERT_OUTGOING_MESSAGE: &str =
     "INSERT INTO outgoing_messages (epoch_guid, msg_seq_num, send_time, message) VALUES (?,?,?,?)";
+const SQL_INSERT_INCOMING_MESSAGE: &str =
+    "INSERT INTO incoming_messages (epoch_guid, msg_seq_num, receive_time, message) VALUES (?,?,?,?)";
+const SQL_INSERT_SENT_ORDER: &str =
+    "INSERT INTO sent_orders (epoch_guid, cl_ord_id, msg_seq_num) VALUES (?,?,?)";
+const SQL_SELECT_SENT_ORDERS: &str = "SELECT msg_seq_num, cl_ord_id FROM sent_orders WHERE epoch_guid = ?";
 const SQL_LAST_SEND_TIME: &str =
     "SELECT send_time FROM outgoing_messages WHERE epoch_guid = ? ORDER BY send_time DESC LIMIT 1";
+const SQL_SELECT_OUTGOING_MESSAGES: &str = "SELECT msg_seq_num, send_time, message FROM outgoing_messages WHERE epoch_guid = ?1 AND msg_seq_num BETWEEN ?2 AND ?3 AND send_time BETWEEN ?4 AND ?5 ORDER BY key;";
+const SQL_SELECT_INCOMING_MESSAGES: &str = "SELECT msg_seq_num, receive_time, message FROM incoming_messages WHERE epoch_guid = ?1 AND msg_seq_num BETWEEN ?2 AND ?3 AND receive_time BETWEEN ?4 AND ?5 ORDER BY key;";
+// The number of free pages released by each step of an incremental vacuum, between checks of the
+// time budget.
+const INCREMENTAL_VACUUM_PAGES: u32 = 256;
 const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+const SHARD_DATE_FORMAT: &str = "%Y%m%d";
 
 enum StoreRequest {
     StoreOutgoing(Arc<String>, u32, Instant, Arc<MsgBuf>),
+    StoreIncoming(Arc<String>, u32, Instant, Arc<MsgBuf>),
     #[allow(clippy::type_complexity)]
     GetPrevMessages(
         Arc<String>,
@@ -40,66 +77,177 @@ enum StoreRequest {
     GetSequences(Arc<String>, oneshot::Sender<Result<(u32, u32)>>),
     SetSequences(Arc<String>, u32, u32, oneshot::Sender<Result<()>>),   
     LastSendTime(Arc<String>, oneshot::Sender<Result<Option<DateTime<Utc>>>>),
+    GetSentOrders(Arc<String>, oneshot::Sender<Result<Vec<Vec<u8>>>>),
+    Maintain(Option<std::time::Duration>, oneshot::Sender<Result<Option<StoreStats>>>),
+    Flush(oneshot::Sender<Result<()>>),
     Disconnect(oneshot::Sender<Result<()>>),
 }
 
+#[derive(Clone)]
 pub struct Store {
     sender: mpsc::UnboundedSender<StoreRequest>,
 }
 
 impl Store {
     pub async fn build(settings: &SessionSettings) -> Result<Store> {
-        let conn = Connection::open_with_flags(settings.store_path.clone(), OpenFlags::default()).await?;
+        if let Some(ref memory_store) = settings.memory_store {
+            return Ok(Store::build_in_memory(memory_store.clone(), settings.outgoing_dedup, Arc::clone(&settings.clock)));
+        }
         let epoch = settings.epoch.clone();
-        setup(&conn, epoch).await?;
+        let cipher = match settings.store_encryption {
+            Some(ref secret_provider) => {
+                Some(StoreCipher::new(&secret_provider.store_encryption_key(&settings.epoch)?))
+            }
+            None => None,
+        };
+        let clock = Arc::clone(&settings.clock);
+        let mut shards = Shards::open(
+            settings.store_path.clone(),
+            settings.store_shard_by_date,
+            Arc::clone(&clock),
+            cipher.clone(),
+            Arc::clone(&epoch),
+            settings.store_seal_migration,
+        )
+        .await?;
+        let outgoing_dedup = settings.outgoing_dedup;
         let (sender, mut receiver) = mpsc::unbounded_channel();
 
         tokio::spawn(async move {
-            let begin_time = Utc::now(); 
+            let begin_time = clock.now();
             let begin_instant = Instant::now(); 
             while let Some(req) = receiver.recv().await {
+                if shards.roll(cipher.as_ref(), Arc::clone(&epoch)).await.is_err() {
+                    tracing::error!("error rolling over store shard");
+                }
+                let conn = &shards.conn;
                 match req {
                     StoreRequest::StoreOutgoing(epoch, msg_seq_num, send_instant, msg) => {
                         let send_time = match Duration::from_std(send_instant.duration_since(begin_instant)) {
                             Ok(d) => begin_time + d, 
-                            Err(_) => Utc::now(),
+                            Err(_) => clock.now(),
                         };
-                        if store_outgoing(&conn, epoch, msg_seq_num, send_time, msg)
+                        if outgoing_dedup
+                            && store_sent_order(conn, cipher.as_ref(), Arc::clone(&epoch), msg_seq_num, &msg)
+                                .await
+                                .is_err()
+                        {
+                            tracing::error!("error storing sent order");
+                        }
+                        if store_outgoing(conn, cipher.as_ref(), epoch, msg_seq_num, send_time, msg)
                             .await
                             .is_err()
                         {
-                            eprintln!("error storing outgoing messages");
+                            tracing::error!("error storing outgoing messages");
+                        }
+                    }
+                    StoreRequest::StoreIncoming(epoch, msg_seq_num, receive_instant, msg) => {
+                        let receive_time = match Duration::from_std(receive_instant.duration_since(begin_instant)) {
+                            Ok(d) => begin_time + d,
+                            Err(_) => clock.now(),
+                        };
+                        if store_incoming(conn, cipher.as_ref(), epoch, msg_seq_num, receive_time, msg)
+                            .await
+                            .is_err()
+                        {
+                            tracing::error!("error storing incoming message");
                         }
                     }
                     StoreRequest::GetPrevMessages(epoch, begin, end, last, sender) => {
-                        let resp = get_prev_messages(&conn, epoch, begin, end, last).await;
+                        let resp = shards.get_prev_messages(cipher.as_ref(), epoch, begin, end, last).await;
                         let _ = sender.send(resp);
                     }
                     StoreRequest::GetSequences(epoch, sender) => {
-                        let resp = get_sequences(&conn, epoch).await;
+                        let resp = get_sequences(conn, cipher.as_ref(), epoch).await;
                         let _ = sender.send(resp);
                     }
                     StoreRequest::SetSequences(epoch, outgoing, incoming, sender) => {
-                        let resp = set_sequences(&conn, epoch, outgoing, incoming).await;
+                        let resp = set_sequences(conn, cipher.as_ref(), epoch, outgoing, incoming).await;
                         let _ = sender.send(resp);
                     }
                     StoreRequest::LastSendTime(epoch, sender) => {
-                        let resp = last_send_time(&conn, epoch).await; 
+                        let resp = shards.last_send_time(epoch).await; 
                         let _ = sender.send(resp); 
                     }
+                    StoreRequest::GetSentOrders(epoch, sender) => {
+                        let resp = shards.get_sent_orders(cipher.as_ref(), epoch).await;
+                        let _ = sender.send(resp);
+                    }
+                    StoreRequest::Maintain(budget, sender) => {
+                        let resp = maintain(conn, budget).await.map(Some);
+                        let _ = sender.send(resp);
+                    }
+                    StoreRequest::Flush(sender) => {
+                        let _ = sender.send(Ok(()));
+                    }
                     StoreRequest::Disconnect(sender) => {
-                        let resp = vacuum(&conn).await;
+                        let resp = vacuum(conn).await;
                         let _ = sender.send(resp);
-                        drop(conn);
+                        drop(shards);
                         break;
                     }
                 }
             }
-        });
+        }.in_current_span());
 
         Ok(Store { sender })
     }
 
+    fn build_in_memory(memory_store: MemoryStore, outgoing_dedup: bool, clock: Arc<dyn Clock>) -> Store {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let begin_time = clock.now();
+            let begin_instant = Instant::now();
+            while let Some(req) = receiver.recv().await {
+                match req {
+                    StoreRequest::StoreOutgoing(epoch, msg_seq_num, send_instant, msg) => {
+                        let send_time = match Duration::from_std(send_instant.duration_since(begin_instant)) {
+                            Ok(d) => begin_time + d,
+                            Err(_) => clock.now(),
+                        };
+                        memory_store.store_outgoing(&epoch, msg_seq_num, send_time, &msg[..], outgoing_dedup);
+                    }
+                    StoreRequest::StoreIncoming(epoch, msg_seq_num, receive_instant, msg) => {
+                        let receive_time = match Duration::from_std(receive_instant.duration_since(begin_instant)) {
+                            Ok(d) => begin_time + d,
+                            Err(_) => clock.now(),
+                        };
+                        memory_store.store_incoming(&epoch, msg_seq_num, receive_time, &msg[..]);
+                    }
+                    StoreRequest::GetPrevMessages(epoch, begin, end, last, sender) => {
+                        let _ = sender.send(Ok(memory_store.get_prev_messages(&epoch, begin, end, last)));
+                    }
+                    StoreRequest::GetSequences(epoch, sender) => {
+                        let _ = sender.send(Ok(memory_store.get_sequences(&epoch)));
+                    }
+                    StoreRequest::SetSequences(epoch, outgoing, incoming, sender) => {
+                        memory_store.set_sequences(&epoch, incoming, outgoing);
+                        let _ = sender.send(Ok(()));
+                    }
+                    StoreRequest::LastSendTime(epoch, sender) => {
+                        let _ = sender.send(Ok(memory_store.last_send_time(&epoch)));
+                    }
+                    StoreRequest::GetSentOrders(epoch, sender) => {
+                        let _ = sender.send(Ok(memory_store.get_sent_orders(&epoch)));
+                    }
+                    StoreRequest::Maintain(_, sender) => {
+                        let _ = sender.send(Ok(None));
+                    }
+                    StoreRequest::Flush(sender) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    StoreRequest::Disconnect(sender) => {
+                        let _ = sender.send(Ok(()));
+                        break;
+                    }
+                }
+            }
+        }.in_current_span());
+
+        Store { sender }
+    }
+
     pub fn store_outgoing(
         &self,
         epoch: Arc<String>,
@@ -112,6 +260,18 @@ impl Store {
         Ok(())
     }
 
+    pub fn store_incoming(
+        &self,
+        epoch: Arc<String>,
+        msg_seq_num: u32,
+        receive_instant: Instant,
+        msg: Arc<MsgBuf>,
+    ) -> Result<()> {
+        let req = StoreRequest::StoreIncoming(epoch, msg_seq_num, receive_instant, msg);
+        self.sender.send(req)?;
+        Ok(())
+    }
+
     pub async fn get_sequences(&self, epoch: Arc<String>) -> Result<(u32, u32)> {
         let (sender, receiver) = oneshot::channel();
         let req = StoreRequest::GetSequences(epoch, sender);
@@ -155,6 +315,32 @@ impl Store {
         receiver.await?
     }
 
+    pub async fn get_sent_orders(&self, epoch: Arc<String>) -> Result<Vec<Vec<u8>>> {
+        let (sender, receiver) = oneshot::channel();
+        let req = StoreRequest::GetSentOrders(epoch, sender);
+        self.sender.send(req)?;
+        receiver.await?
+    }
+
+    // Run an incremental vacuum for up to `budget`, if set, and get the stats of the store. The
+    // stats are `None` for a memory store, and only cover the current file of a sharded store.
+    pub async fn maintain(&self, budget: Option<std::time::Duration>) -> Result<Option<StoreStats>> {
+        let (sender, receiver) = oneshot::channel();
+        let req = StoreRequest::Maintain(budget, sender);
+        self.sender.send(req)?;
+        receiver.await?
+    }
+
+    // Wait until every message stored before was written. Each message is committed on its own,
+    // and the requests are handled in order, so there is nothing left to write once this is
+    // answered.
+    pub async fn flush(&self) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        let req = StoreRequest::Flush(sender);
+        self.sender.send(req)?;
+        receiver.await?
+    }
+
     pub async fn disconnect(&self) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
         let req = StoreRequest::Disconnect(sender);
@@ -164,26 +350,233 @@ impl Store {
     }
 }
 
-async fn setup(conn: &tokio_rusqlite::Connection, epoch: Arc<String>) -> Result<(u32, u32)> {
-    conn.call(move |conn| {
+// The sqlite files backing a store. Without sharding, this is the single file at `store_path`.
+// Sharded by date, there is one file per UTC date of the clock of the session next to
+// `store_path`, such as `store.20240102.db`, and only the file of the current date is kept open.
+// Queries for messages that are not in the current file fall through to the files of earlier
+// dates.
+struct Shards {
+    store_path: PathBuf,
+    shard_by_date: bool,
+    clock: Arc<dyn Clock>,
+    date: NaiveDate,
+    conn: Connection,
+}
+
+impl Shards {
+    async fn open(
+        store_path: PathBuf,
+        shard_by_date: bool,
+        clock: Arc<dyn Clock>,
+        cipher: Option<StoreCipher>,
+        epoch: Arc<String>,
+        seal_migration: bool,
+    ) -> Result<Shards> {
+        let cipher = cipher.as_ref();
+        let date = clock.now().date_naive();
+        let path = if shard_by_date {
+            shard_path(&store_path, date)
+        } else {
+            store_path.clone()
+        };
+        let is_new = !path.exists();
+        let conn = Connection::open_with_flags(path, OpenFlags::default()).await?;
+        setup(&conn, cipher, Arc::clone(&epoch), seal_migration).await?;
+        let shards = Shards { store_path, shard_by_date, clock, date, conn };
+
+        // A new shard continues the sequence numbers of the latest earlier shard. A shard that
+        // cannot be read, or whose sequence numbers fail their seal, is an error rather than a
+        // reason to start over at 1.
+        if shard_by_date && is_new {
+            if let Some(prev) = shards.previous().into_iter().next() {
+                let prev = Connection::open_with_flags(prev, OpenFlags::default()).await?;
+                if let Some((incoming, outgoing)) = previous_sequences(&prev, cipher, Arc::clone(&epoch)).await? {
+                    set_sequences(&shards.conn, cipher, epoch, outgoing, incoming).await?;
+                }
+            }
+        }
+        Ok(shards)
+    }
+
+    // Close the file of the previous date and open the file of the current date, carrying the
+    // sequence numbers over. Does nothing if the date has not changed.
+    async fn roll(&mut self, cipher: Option<&StoreCipher>, epoch: Arc<String>) -> Result<()> {
+        let date = self.clock.now().date_naive();
+        if !self.shard_by_date || date == self.date {
+            return Ok(());
+        }
+        let (incoming, outgoing) = get_sequences(&self.conn, cipher, Arc::clone(&epoch)).await?;
+        let conn = Connection::open_with_flags(shard_path(&self.store_path, date), OpenFlags::default()).await?;
+        setup(&conn, cipher, Arc::clone(&epoch), false).await?;
+        set_sequences(&conn, cipher, epoch, outgoing, incoming).await?;
+        vacuum(&self.conn).await?;
+        self.conn = conn;
+        self.date = date;
+        Ok(())
+    }
+
+    // The files of the dates before the current one, newest first.
+    fn previous(&self) -> Vec<PathBuf> {
+        if !self.shard_by_date {
+            return Vec::new();
+        }
+        let mut shards: Vec<(NaiveDate, PathBuf)> = shard_files(&self.store_path)
+            .into_iter()
+            .filter(|(date, _)| *date < self.date)
+            .collect();
+        shards.sort_by(|(a, _), (b, _)| b.cmp(a));
+        shards.into_iter().map(|(_, path)| path).collect()
+    }
+
+    async fn get_prev_messages(
+        &self,
+        cipher: Option<&StoreCipher>,
+        epoch: Arc<String>,
+        begin_seq_no: u32,
+        end_seq_no: u32,
+        last_seq_no: u32,
+    ) -> Result<Vec<(u32, Vec<u8>)>> {
+        let mut output =
+            get_prev_messages(&self.conn, cipher, Arc::clone(&epoch), begin_seq_no, end_seq_no, last_seq_no).await?;
+        for prev in self.previous() {
+            if output.iter().any(|(msg_seq_num, _)| *msg_seq_num <= begin_seq_no) {
+                break;
+            }
+            let conn = Connection::open_with_flags(prev, OpenFlags::default()).await?;
+            output.extend(
+                get_prev_messages(&conn, cipher, Arc::clone(&epoch), begin_seq_no, end_seq_no, last_seq_no).await?,
+            );
+        }
+        Ok(output)
+    }
+
+    async fn last_send_time(&self, epoch: Arc<String>) -> Result<Option<DateTime<Utc>>> {
+        if let Some(send_time) = last_send_time(&self.conn, Arc::clone(&epoch)).await? {
+            return Ok(Some(send_time));
+        }
+        for prev in self.previous() {
+            let conn = Connection::open_with_flags(prev, OpenFlags::default()).await?;
+            if let Some(send_time) = last_send_time(&conn, Arc::clone(&epoch)).await? {
+                return Ok(Some(send_time));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn get_sent_orders(&self, cipher: Option<&StoreCipher>, epoch: Arc<String>) -> Result<Vec<Vec<u8>>> {
+        let mut output = get_sent_orders(&self.conn, cipher, Arc::clone(&epoch)).await?;
+        for prev in self.previous() {
+            let conn = Connection::open_with_flags(prev, OpenFlags::default()).await?;
+            output.extend(get_sent_orders(&conn, cipher, Arc::clone(&epoch)).await?);
+        }
+        Ok(output)
+    }
+}
+
+// Every shard of `store_path` with its date, in no particular order.
+fn shard_files(store_path: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let dir = match store_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let date = shard_date(store_path, &path)?;
+            Some((date, path))
+        })
+        .collect()
+}
+
+// The files of the store at `store_path`, oldest first: the file itself, or its shards by date.
+// Files that do not exist yet are left out.
+pub(super) fn store_files(store_path: &Path, shard_by_date: bool) -> Vec<PathBuf> {
+    if !shard_by_date {
+        return store_path.exists().then(|| store_path.to_path_buf()).into_iter().collect();
+    }
+    let mut shards = shard_files(store_path);
+    shards.sort_by_key(|(date, _)| *date);
+    shards.into_iter().map(|(_, path)| path).collect()
+}
+
+// The file of the store at `store_path` that holds its current sequence numbers: the file itself,
+// or its latest shard by date, or the shard of `today` if there is none yet.
+pub(super) fn sequences_file(store_path: &Path, shard_by_date: bool, today: NaiveDate) -> PathBuf {
+    if !shard_by_date {
+        return store_path.to_path_buf();
+    }
+    store_files(store_path, true)
+        .pop()
+        .unwrap_or_else(|| shard_path(store_path, today))
+}
+
+// The path of the shard of `date`, such as `store.20240102.db` for a `store_path` of `store.db`.
+fn shard_path(store_path: &Path, date: NaiveDate) -> PathBuf {
+    let stem = store_path.file_stem().unwrap_or_default().to_string_lossy();
+    let date = date.format(SHARD_DATE_FORMAT);
+    let file_name = match store_path.extension() {
+        Some(ext) => format!("{stem}.{date}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{date}"),
+    };
+    store_path.with_file_name(file_name)
+}
+
+// The date of the shard at `path`, or `None` if `path` is not a shard of `store_path`.
+fn shard_date(store_path: &Path, path: &Path) -> Option<NaiveDate> {
+    let file_name = path.file_name()?.to_str()?;
+    let stem = store_path.file_stem()?.to_str()?;
+    let rest = file_name.strip_prefix(stem)?.strip_prefix('.')?;
+    let date = match store_path.extension() {
+        Some(ext) => rest.strip_suffix(ext.to_str()?)?.strip_suffix('.')?,
+        None => rest,
+    };
+    if date.len() != 8 {
+        return None;
+    }
+    NaiveDate::parse_from_str(date, SHARD_DATE_FORMAT).ok()
+}
+
+// With a cipher, sequence numbers without a seal are trusted, and sealed, only when `setup` just
+// added them, or for a `seal_migration`. A `seal` column missing from an older store is not
+// enough, since dropping it needs no key.
+async fn setup(
+    conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+    seal_migration: bool,
+) -> Result<(u32, u32)> {
+    let row_epoch = Arc::clone(&epoch);
+    let (had_seal, new_row, plaintext) = conn.call(move |conn| {
+        conn.execute_batch(SQL_AUTO_VACUUM_INCREMENTAL)?;
         conn.query_row(SQL_ENTER_WAL_MODE, (), |_| Ok(()))?;
         conn.execute(SQL_CREATE_SEQUENCES, ())?;
-        conn.execute(SQL_ENSURE_SEQUENCE_ROW, (Arc::clone(&epoch),))?;
+        let had_seal = conn.query_row(SQL_HAS_SEQUENCES_SEAL, (), |row| row.get::<_, u32>(0))? > 0;
+        if !had_seal {
+            conn.execute(SQL_ADD_SEQUENCES_SEAL, ())?;
+        }
+        let new_row = conn.execute(SQL_ENSURE_SEQUENCE_ROW, (Arc::clone(&row_epoch),))? > 0;
         conn.execute(SQL_CREATE_INCOMING_TABLE, ())?;
+        if conn.query_row(SQL_HAS_RECEIVE_TIME, (), |row| row.get::<_, u32>(0))? == 0 {
+            conn.execute(SQL_ADD_RECEIVE_TIME, ())?;
+        }
         conn.execute(SQL_CREATE_OUTGOING_TABLE, ())?;
-
-        conn.query_row(
-            "SELECT next_incoming, next_outgoing FROM sequences where epoch_guid = ?;",
-            (Arc::clone(&epoch),),
-            |r| {
-                let next_incoming: u32 = r.get(0)?;
-                let next_outgoing: u32 = r.get(1)?;
-                Ok((next_incoming, next_outgoing))
-            },
-        )
+        conn.execute(SQL_CREATE_SENT_ORDERS_TABLE, ())?;
+        let plaintext = conn.query_row(SQL_HAS_PLAINTEXT_MESSAGES, (row_epoch,), |row| row.get::<_, bool>(0))?;
+        Ok::<_, rusqlite::Error>((had_seal, new_row, plaintext))
     })
-    .await
-    .map_err(|err| err.into())
+    .await?;
+    if cipher.is_some() {
+        if plaintext {
+            bail!("the store was not encrypted: messages of {epoch} were written without a store encryption key");
+        }
+        if !had_seal && !seal_migration {
+            bail!("the sequence numbers of {epoch} have no seal: the store was encrypted before they were sealed, or the seal was removed without the store encryption key; open it once with a seal migration to seal them");
+        }
+    }
+    check_sequences(conn, cipher, epoch, new_row || seal_migration).await
 }
 
 async fn vacuum(conn: &tokio_rusqlite::Connection) -> Result<()> {
@@ -195,36 +588,130 @@ async fn vacuum(conn: &tokio_rusqlite::Connection) -> Result<()> {
     .map_err(|e| e.into())
 }
 
+async fn maintain(
+    conn: &tokio_rusqlite::Connection,
+    budget: Option<std::time::Duration>,
+) -> Result<StoreStats> {
+    conn.call(move |conn| {
+        let pragma = |name: &str| conn.query_row(&format!("PRAGMA {name};"), [], |r| r.get::<_, u64>(0));
+        let page_size = pragma("page_size")?;
+        let free_pages_before = pragma("freelist_count")?;
+
+        // Stores created before incremental vacuum was enabled free no pages, so stop as soon as
+        // a step makes no progress.
+        if let Some(budget) = budget {
+            let start = Instant::now();
+            let mut free_pages = free_pages_before;
+            while free_pages > 0 && start.elapsed() < budget {
+                conn.execute_batch(&format!("PRAGMA incremental_vacuum({INCREMENTAL_VACUUM_PAGES});"))?;
+                let remaining = pragma("freelist_count")?;
+                if remaining == free_pages {
+                    break;
+                }
+                free_pages = remaining;
+            }
+        }
+
+        let free_pages = pragma("freelist_count")?;
+        let mut epochs: BTreeMap<String, EpochRows> = BTreeMap::new();
+        for table in ["outgoing_messages", "incoming_messages", "sent_orders"] {
+            let mut stmt = conn.prepare(&format!("SELECT epoch_guid, COUNT(*) FROM {table} GROUP BY epoch_guid"))?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, u64>(1)?)))?;
+            for row in rows {
+                let (epoch, count) = row?;
+                let epoch_rows = epochs.entry(epoch).or_default();
+                match table {
+                    "outgoing_messages" => epoch_rows.outgoing_messages = count,
+                    "incoming_messages" => epoch_rows.incoming_messages = count,
+                    _ => epoch_rows.sent_orders = count,
+                }
+            }
+        }
+        Ok::<_, rusqlite::Error>(StoreStats {
+            size_bytes: pragma("page_count")? * page_size,
+            free_bytes: free_pages * page_size,
+            vacuumed_bytes: free_pages_before.saturating_sub(free_pages) * page_size,
+            epochs,
+        })
+    })
+    .await
+    .map_err(|err| err.into())
+}
+
+// With a cipher, the sequence numbers must match their seal, so that they cannot be changed
+// without the key. Sequence numbers without a seal are only trusted, and sealed, by `setup`;
+// otherwise the seal was removed without the key.
 async fn get_sequences(
     conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
     epoch: Arc<String>,
 ) -> Result<(u32, u32)> {
-    conn.call(move |conn| {
-        conn.query_row(
-            "SELECT next_incoming, next_outgoing FROM sequences where epoch_guid = ?;",
-            (Arc::clone(&epoch),),
-            |r| {
+    check_sequences(conn, cipher, epoch, false).await
+}
+
+// The sequence numbers of `epoch` in an earlier shard, or `None` if the shard has no row for the
+// epoch.
+async fn previous_sequences(
+    conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+) -> Result<Option<(u32, u32)>> {
+    let query_epoch = Arc::clone(&epoch);
+    let has_row = conn
+        .call(move |conn| conn.query_row(SQL_SELECT_SEQUENCES, (query_epoch,), |_| Ok(())).optional())
+        .await?
+        .is_some();
+    if !has_row {
+        return Ok(None);
+    }
+    get_sequences(conn, cipher, epoch).await.map(Some)
+}
+
+async fn check_sequences(
+    conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+    unsealed: bool,
+) -> Result<(u32, u32)> {
+    let query_epoch = Arc::clone(&epoch);
+    let (next_incoming, next_outgoing, seal) = conn
+        .call(move |conn| {
+            conn.query_row(SQL_SELECT_SEQUENCES, (query_epoch,), |r| {
                 let next_incoming: u32 = r.get(0)?;
                 let next_outgoing: u32 = r.get(1)?;
-                Ok((next_incoming, next_outgoing))
-            },
-        )
-    })
-    .await
-    .map_err(|err| err.into())
+                let seal: Option<Vec<u8>> = r.get(2)?;
+                Ok((next_incoming, next_outgoing, seal))
+            })
+        })
+        .await?;
+    match (cipher, seal) {
+        (Some(cipher), Some(seal)) => {
+            if cipher.open_sequences(&epoch, &seal)? != (next_incoming, next_outgoing) {
+                bail!("the sequence numbers of {epoch} were changed without the store encryption key");
+            }
+        }
+        (Some(_), None) if unsealed => set_sequences(conn, cipher, epoch, next_outgoing, next_incoming).await?,
+        (Some(_), None) => {
+            bail!("the sequence numbers of {epoch} have no seal, so were changed without the store encryption key");
+        }
+        (None, _) => {}
+    }
+    Ok((next_incoming, next_outgoing))
 }
 
 async fn set_sequences(
     conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
     epoch: Arc<String>,
     new_outgoing: u32,
     new_incoming: u32,
 ) -> Result<()> {
+    let seal = match cipher {
+        Some(cipher) => Some(cipher.seal_sequences(&epoch, new_incoming, new_outgoing)?),
+        None => None,
+    };
     conn.call(move |conn| {
-        conn.execute(
-            "UPDATE sequences SET next_outgoing = ?1, next_incoming = ?2 WHERE epoch_guid = ?3",
-            (new_outgoing, new_incoming, Arc::clone(&epoch)),
-        )
+        conn.execute(SQL_UPDATE_SEQUENCES, (new_outgoing, new_incoming, seal, Arc::clone(&epoch)))
     })
     .await
     .map(|_| ())
@@ -233,15 +720,20 @@ async fn set_sequences(
 
 async fn store_outgoing(
     conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
     epoch: Arc<String>,
     msg_seq_num: u32,
     send_time: DateTime<Utc>,
     msg: Arc<MsgBuf>,
 ) -> Result<()> {
+    let blob = match cipher {
+        Some(cipher) => cipher.encrypt(&epoch, msg_seq_num, &msg[..])?,
+        None => msg.as_ref().0.clone(),
+    };
     conn.call(move |conn| {
         conn.execute(
             SQL_INSERT_OUTGOING_MESSAGE,
-            (epoch, msg_seq_num, format!("{}", send_time.format(TIME_FORMAT)), &msg.as_ref()[..]),
+            (epoch, msg_seq_num, format!("{}", send_time.format(TIME_FORMAT)), blob),
         )
     })
     .await
@@ -249,18 +741,85 @@ async fn store_outgoing(
     .map_err(|err| err.into())
 }
 
+async fn store_incoming(
+    conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+    msg_seq_num: u32,
+    receive_time: DateTime<Utc>,
+    msg: Arc<MsgBuf>,
+) -> Result<()> {
+    let blob = match cipher {
+        Some(cipher) => cipher.encrypt_incoming(&epoch, msg_seq_num, &msg[..])?,
+        None => msg.as_ref().0.clone(),
+    };
+    conn.call(move |conn| {
+        conn.execute(
+            SQL_INSERT_INCOMING_MESSAGE,
+            (epoch, msg_seq_num, format!("{}", receive_time.format(TIME_FORMAT)), blob),
+        )
+    })
+    .await
+    .map(|_| ())
+    .map_err(|err| err.into())
+}
+
+async fn store_sent_order(
+    conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+    msg_seq_num: u32,
+    msg: &MsgBuf,
+) -> Result<()> {
+    let cl_ord_id = match (dedup::cl_ord_id(&msg[..]), cipher) {
+        (Some(cl_ord_id), Some(cipher)) => cipher.encrypt_cl_ord_id(&epoch, msg_seq_num, cl_ord_id)?,
+        (Some(cl_ord_id), None) => cl_ord_id.to_vec(),
+        (None, _) => return Ok(()),
+    };
+    conn.call(move |conn| {
+        conn.execute(SQL_INSERT_SENT_ORDER, (epoch, cl_ord_id, msg_seq_num))
+    })
+    .await
+    .map(|_| ())
+    .map_err(|err| err.into())
+}
+
+async fn get_sent_orders(
+    conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+) -> Result<Vec<Vec<u8>>> {
+    let query_epoch = Arc::clone(&epoch);
+    let sent_orders = conn
+        .call(move |conn| -> rusqlite::Result<Vec<(u32, Vec<u8>)>> {
+            let mut stmt = conn.prepare(SQL_SELECT_SENT_ORDERS)?;
+            let rows = stmt.query_map([query_epoch], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        })
+        .await?;
+    sent_orders
+        .into_iter()
+        .map(|(msg_seq_num, cl_ord_id)| match cipher {
+            Some(cipher) => cipher.decrypt_cl_ord_id(&epoch, msg_seq_num, &cl_ord_id),
+            None => Ok(cl_ord_id),
+        })
+        .collect()
+}
+
 async fn get_prev_messages(
     conn: &tokio_rusqlite::Connection,
+    cipher: Option<&StoreCipher>,
     epoch: Arc<String>,
     begin_seq_no: u32,
     end_seq_no: u32,
     last_seq_no: u32,
 ) -> Result<Vec<(u32, Vec<u8>)>> {
     let mut output: Vec<(u32, Vec<u8>)> = Vec::new();
+    let query_epoch = Arc::clone(&epoch);
     output = conn.call(move |conn| -> Result<Vec<(u32, Vec<u8>)>> {
         let mut stmt = conn.prepare("SELECT msg_seq_num, message FROM (SELECT * FROM outgoing_messages WHERE epoch_guid = ?1 ORDER BY key DESC LIMIT ?2) WHERE msg_seq_num BETWEEN ?3 AND ?4;")?;
         let rows = stmt.query_map(
-            rusqlite::params![Arc::clone(&epoch), &last_seq_no, &begin_seq_no, &end_seq_no], |row| {
+            rusqlite::params![query_epoch, &last_seq_no, &begin_seq_no, &end_seq_no], |row| {
                 Ok((row.get(0)?, row.get(1)?))
             })?;
         for row in rows {
@@ -268,9 +827,91 @@ async fn get_prev_messages(
         }
         Ok(output)
     }).await?;
+    if let Some(cipher) = cipher {
+        for (msg_seq_num, msg) in output.iter_mut() {
+            *msg = cipher.decrypt(&epoch, *msg_seq_num, msg)?;
+        }
+    }
     Ok(output)
 }
 
+// The messages sent or received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+// The messages of `epoch` in `direction` stored in the file at `path`, with their sequence
+// numbers and the times they were sent or received, in the order they were stored. Only the
+// messages within `seq_nums` and `times`, if set, are read.
+// Read the `(next_incoming, next_outgoing)` sequence numbers of `epoch` from the store file at
+// `path`, which start at 1 if there is no such file.
+pub(super) async fn read_sequences(
+    path: &Path,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+) -> Result<(u32, u32)> {
+    if !path.exists() {
+        return Ok((1, 1));
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::default()).await?;
+    setup(&conn, cipher, epoch, false).await
+}
+
+// Write the sequence numbers of `epoch` to the store file at `path`, creating the file if needed.
+pub(super) async fn write_sequences(
+    path: &Path,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+    next_outgoing: u32,
+    next_incoming: u32,
+) -> Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::default()).await?;
+    setup(&conn, cipher, Arc::clone(&epoch), false).await?;
+    set_sequences(&conn, cipher, epoch, next_outgoing, next_incoming).await
+}
+
+pub(super) async fn read_messages(
+    path: &Path,
+    cipher: Option<&StoreCipher>,
+    epoch: Arc<String>,
+    direction: Direction,
+    seq_nums: Option<(u32, u32)>,
+    times: Option<(DateTime<Utc>, DateTime<Utc>)>,
+) -> Result<Vec<(u32, DateTime<Utc>, Vec<u8>)>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).await?;
+    let query_epoch = Arc::clone(&epoch);
+    let (begin_seq_no, end_seq_no) = seq_nums.unwrap_or((0, u32::MAX));
+    let (from, to) = match times {
+        Some((from, to)) => (from.format(TIME_FORMAT).to_string(), to.format(TIME_FORMAT).to_string()),
+        None => (String::new(), String::from("~")),
+    };
+    let mut output = conn.call(move |conn| -> rusqlite::Result<Vec<(u32, NaiveDateTime, Vec<u8>)>> {
+        let mut stmt = conn.prepare(match direction {
+            Direction::Outgoing => SQL_SELECT_OUTGOING_MESSAGES,
+            Direction::Incoming => SQL_SELECT_INCOMING_MESSAGES,
+        })?;
+        let rows = stmt.query_map(
+            rusqlite::params![query_epoch, &begin_seq_no, &end_seq_no, &from, &to], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?;
+        rows.collect()
+    }).await?;
+    if let Some(cipher) = cipher {
+        for (msg_seq_num, _, msg) in output.iter_mut() {
+            *msg = match direction {
+                Direction::Outgoing => cipher.decrypt(&epoch, *msg_seq_num, msg)?,
+                Direction::Incoming => cipher.decrypt_incoming(&epoch, *msg_seq_num, msg)?,
+            };
+        }
+    }
+    Ok(output
+        .into_iter()
+        .map(|(msg_seq_num, send_time, msg)| (msg_seq_num, send_time.and_utc(), msg))
+        .collect())
+}
+
 async fn last_send_time(
     conn: &tokio_rusqlite::Connection, 
     epoch: Arc<String>, 
@@ -285,3 +926,360 @@ async fn last_send_time(
     }).await?; 
     Ok(send_time.map(|n| n.and_utc()))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SecretProvider;
+
+    struct StaticKey([u8; 32]);
+    impl SecretProvider for StaticKey {
+        fn store_encryption_key(&self, _epoch: &str) -> Result<[u8; 32], std::io::Error> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn test_shard_path() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let store_path = Path::new("/var/fix/store.db");
+        let path = shard_path(store_path, date);
+        assert_eq!(path, Path::new("/var/fix/store.20240102.db"));
+        assert_eq!(shard_date(store_path, &path), Some(date));
+        assert_eq!(shard_date(store_path, store_path), None);
+        assert_eq!(shard_date(store_path, Path::new("/var/fix/store.2024.db")), None);
+        assert_eq!(shard_date(store_path, Path::new("/var/fix/other.20240102.db")), None);
+
+        let path = shard_path(Path::new("store"), date);
+        assert_eq!(path, Path::new("store.20240102"));
+        assert_eq!(shard_date(Path::new("store"), &path), Some(date));
+    }
+
+    #[tokio::test]
+    async fn test_sharded_store() {
+        let dir = std::env::temp_dir().join(format!("forgefix-shards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .with_store_shard_by_date(true)
+            .build()
+            .unwrap();
+        let epoch = settings.epoch.clone();
+        let msg = b"8=FIX.4.2\x019=5\x0135=D\x0111=order-1\x0110=000\x01".to_vec();
+
+        // Write a message to the shard of yesterday.
+        let yesterday = Utc::now().date_naive().pred_opt().unwrap();
+        let conn = Connection::open(shard_path(&settings.store_path, yesterday)).await.unwrap();
+        setup(&conn, None, Arc::clone(&epoch), false).await.unwrap();
+        store_outgoing(&conn, None, Arc::clone(&epoch), 1, Utc::now(), Arc::new(msg.clone().into()))
+            .await
+            .unwrap();
+        store_sent_order(&conn, None, Arc::clone(&epoch), 1, &msg.clone().into()).await.unwrap();
+        set_sequences(&conn, None, Arc::clone(&epoch), 2, 5).await.unwrap();
+        drop(conn);
+
+        let store = Store::build(&settings).await.unwrap();
+        assert!(shard_path(&settings.store_path, Utc::now().date_naive()).exists());
+        assert_eq!(store.get_sequences(Arc::clone(&epoch)).await.unwrap(), (5, 2));
+        let prev_messages = store.get_prev_messages(Arc::clone(&epoch), 1, 1, 1).await.unwrap();
+        assert_eq!(prev_messages, vec![(1, msg)]);
+        assert!(store.last_send_time(Arc::clone(&epoch)).await.unwrap().is_some());
+        assert_eq!(store.get_sent_orders(epoch).await.unwrap(), vec![b"order-1".to_vec()]);
+        store.disconnect().await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_sharded_store_previous_shard() {
+        let dir = std::env::temp_dir().join(format!("forgefix-prev-shard-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // the shards are dated by the clock of the session
+        let then = DateTime::parse_from_rfc3339("2024-01-03T10:00:00Z").unwrap().to_utc();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .with_store_shard_by_date(true)
+            .with_store_encryption(Arc::new(StaticKey([42; 32])))
+            .with_clock(Arc::new(FixedClock(then)))
+            .build()
+            .unwrap();
+        let epoch = settings.epoch.clone();
+        let cipher = StoreCipher::new(&[42; 32]);
+        let prev_path = shard_path(&settings.store_path, then.date_naive().pred_opt().unwrap());
+        let today_path = shard_path(&settings.store_path, then.date_naive());
+
+        // an earlier shard without a row for the epoch starts the sequence numbers over
+        let conn = Connection::open(&prev_path).await.unwrap();
+        setup(&conn, Some(&cipher), Arc::new(String::from("other")), false).await.unwrap();
+        drop(conn);
+        let store = Store::build(&settings).await.unwrap();
+        assert!(today_path.exists());
+        assert_eq!(store.get_sequences(Arc::clone(&epoch)).await.unwrap(), (1, 1));
+        store.disconnect().await.unwrap();
+        std::fs::remove_file(&today_path).unwrap();
+
+        // while a row whose seal was broken is an error, not a new start
+        let conn = Connection::open(&prev_path).await.unwrap();
+        setup(&conn, Some(&cipher), Arc::clone(&epoch), false).await.unwrap();
+        set_sequences(&conn, Some(&cipher), Arc::clone(&epoch), 4, 7).await.unwrap();
+        drop(conn);
+        let conn = rusqlite::Connection::open(&prev_path).unwrap();
+        conn.execute("UPDATE sequences SET next_incoming = 1 WHERE epoch_guid = ?1", (epoch.as_str(),))
+            .unwrap();
+        drop(conn);
+        assert!(Store::build(&settings).await.is_err());
+        let _ = std::fs::remove_file(&today_path);
+
+        // and so is an earlier shard that is not a store
+        std::fs::write(&prev_path, b"not a sqlite file").unwrap();
+        assert!(Store::build(&settings).await.is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_maintain_store() {
+        let dir = std::env::temp_dir().join(format!("forgefix-maintain-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .with_outgoing_dedup(true)
+            .build()
+            .unwrap();
+        let msg = format!("8=FIX.4.2\x019=5\x0135=D\x0111=order-1\x0158={}\x0110=000\x01", "x".repeat(1000));
+
+        let store = Store::build(&settings).await.unwrap();
+        for msg_seq_num in 1..=200 {
+            store
+                .store_outgoing(settings.epoch.clone(), msg_seq_num, Instant::now(), Arc::new(msg.clone().into_bytes().into()))
+                .unwrap();
+        }
+        let stats = store.maintain(None).await.unwrap().unwrap();
+        let rows = EpochRows { outgoing_messages: 200, incoming_messages: 0, sent_orders: 200 };
+        assert_eq!(stats.epochs.get(settings.epoch.as_str()), Some(&rows));
+        assert_eq!(stats.free_bytes, 0);
+        assert!(stats.size_bytes > 200_000);
+
+        // free the pages of the messages, and release them within the budget
+        let conn = rusqlite::Connection::open(dir.join("store.db")).unwrap();
+        conn.execute("DELETE FROM outgoing_messages", []).unwrap();
+        drop(conn);
+        let stats = store.maintain(None).await.unwrap().unwrap();
+        assert!(stats.fragmentation() > 0.5);
+        assert_eq!(stats.vacuumed_bytes, 0);
+        let stats = store.maintain(Some(std::time::Duration::from_secs(5))).await.unwrap().unwrap();
+        assert_eq!(stats.free_bytes, 0);
+        assert!(stats.vacuumed_bytes > 200_000);
+        assert_eq!(stats.epochs[settings.epoch.as_str()].outgoing_messages, 0);
+        store.disconnect().await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store() {
+        let dir = std::env::temp_dir().join(format!("forgefix-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .with_store_encryption(Arc::new(StaticKey([42; 32])))
+            .with_outgoing_dedup(true)
+            .build()
+            .unwrap();
+        let msg = b"8=FIX.4.2\x019=5\x0135=D\x0111=order-1\x0110=000\x01".to_vec();
+
+        let store = Store::build(&settings).await.unwrap();
+        store
+            .store_outgoing(settings.epoch.clone(), 1, Instant::now(), Arc::new(msg.clone().into()))
+            .unwrap();
+        let prev_messages = store.get_prev_messages(settings.epoch.clone(), 1, 1, 1).await.unwrap();
+        assert_eq!(prev_messages, vec![(1, msg.clone())]);
+        let sent_orders = store.get_sent_orders(settings.epoch.clone()).await.unwrap();
+        assert_eq!(sent_orders, vec![b"order-1".to_vec()]);
+        store.set_sequences(settings.epoch.clone(), 2, 3).await.unwrap();
+        store.disconnect().await.unwrap();
+
+        let conn = rusqlite::Connection::open(dir.join("store.db")).unwrap();
+        let stored: Vec<u8> = conn
+            .query_row("SELECT message FROM outgoing_messages", [], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.windows(msg.len()).any(|w| w == msg.as_slice()));
+        let cl_ord_id: Vec<u8> = conn
+            .query_row("SELECT cl_ord_id FROM sent_orders", [], |row| row.get(0))
+            .unwrap();
+        assert!(!cl_ord_id.windows(7).any(|w| w == b"order-1"));
+
+        // the sequence numbers are sealed with the key
+        let store = Store::build(&settings).await.unwrap();
+        assert_eq!(store.get_sequences(settings.epoch.clone()).await.unwrap(), (3, 2));
+        store.disconnect().await.unwrap();
+        conn.execute("UPDATE sequences SET next_incoming = 1", []).unwrap();
+        assert!(Store::build(&settings).await.is_err());
+        // removing the seal is caught too
+        conn.execute("UPDATE sequences SET seal = NULL", []).unwrap();
+        assert!(Store::build(&settings).await.is_err());
+
+        // and so is dropping the seal column
+        conn.execute("ALTER TABLE sequences DROP COLUMN seal", []).unwrap();
+        assert!(Store::build(&settings).await.is_err());
+        assert!(Store::build(&settings).await.is_err());
+
+        // only a seal migration trusts and seals them, for a store encrypted before sealing
+        let migration = SessionSettings { store_seal_migration: true, ..settings.clone() };
+        let store = Store::build(&migration).await.unwrap();
+        assert_eq!(store.get_sequences(settings.epoch.clone()).await.unwrap(), (1, 2));
+        store.disconnect().await.unwrap();
+        let seal: Option<Vec<u8>> = conn.query_row("SELECT seal FROM sequences", [], |row| row.get(0)).unwrap();
+        assert!(seal.is_some());
+        let store = Store::build(&settings).await.unwrap();
+        assert_eq!(store.get_sequences(settings.epoch.clone()).await.unwrap(), (1, 2));
+        store.disconnect().await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_wrong_key() {
+        let dir = std::env::temp_dir().join(format!("forgefix-store-key-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .with_store_encryption(Arc::new(StaticKey([42; 32])))
+            .build()
+            .unwrap();
+        let store = Store::build(&settings).await.unwrap();
+        store.set_sequences(settings.epoch.clone(), 2, 3).await.unwrap();
+        store.disconnect().await.unwrap();
+
+        let wrong_key = SessionSettings { store_encryption: Some(Arc::new(StaticKey([7; 32]))), ..settings.clone() };
+        assert!(Store::build(&wrong_key).await.is_err());
+        // even for a seal migration, which only trusts a missing seal
+        let wrong_key = SessionSettings { store_seal_migration: true, ..wrong_key };
+        assert!(Store::build(&wrong_key).await.is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_store_encrypted() {
+        let dir = std::env::temp_dir().join(format!("forgefix-store-plaintext-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(dir.join("store.db"))
+            .with_log_dir(dir.join("log"))
+            .build()
+            .unwrap();
+        let msg = b"8=FIX.4.2\x019=5\x0135=D\x0111=order-1\x0110=000\x01".to_vec();
+        let store = Store::build(&settings).await.unwrap();
+        store
+            .store_outgoing(settings.epoch.clone(), 1, Instant::now(), Arc::new(msg.into()))
+            .unwrap();
+        store.set_sequences(settings.epoch.clone(), 2, 1).await.unwrap();
+        store.disconnect().await.unwrap();
+
+        // the messages could not be resent, so the store is refused rather than sealed
+        let encrypted = SessionSettings {
+            store_encryption: Some(Arc::new(StaticKey([42; 32]))),
+            ..settings.clone()
+        };
+        let err = Store::build(&encrypted).await.err().unwrap();
+        assert!(err.to_string().contains("not encrypted"), "{err}");
+        let migration = SessionSettings { store_seal_migration: true, ..encrypted.clone() };
+        assert!(Store::build(&migration).await.is_err());
+
+        // a new epoch in the same store can be encrypted
+        let new_epoch = SessionSettings { epoch: Arc::new(String::from("encrypted")), ..encrypted };
+        let store = Store::build(&new_epoch).await.unwrap();
+        assert_eq!(store.get_sequences(new_epoch.epoch.clone()).await.unwrap(), (1, 1));
+        store.disconnect().await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_store_incoming() {
+        let dir = std::env::temp_dir().join(format!("forgefix-incoming-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store_path = dir.join("store.db");
+        // a store created before incoming messages were kept
+        rusqlite::Connection::open(&store_path)
+            .unwrap()
+            .execute("CREATE TABLE incoming_messages (key INTEGER PRIMARY KEY AUTOINCREMENT, epoch_guid VARCHAR, msg_seq_num INT, message BLOB);", ())
+            .unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_store_path(store_path.clone())
+            .with_log_dir(dir.join("log"))
+            .build()
+            .unwrap();
+        let msg = b"8=FIX.4.2\x019=5\x0135=8\x0110=000\x01".to_vec();
+
+        let store = Store::build(&settings).await.unwrap();
+        store
+            .store_incoming(settings.epoch.clone(), 7, Instant::now(), Arc::new(msg.clone().into()))
+            .unwrap();
+        store.disconnect().await.unwrap();
+
+        let read = read_messages(&store_path, None, settings.epoch.clone(), Direction::Incoming, None, None)
+            .await
+            .unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!((read[0].0, &read[0].2), (7, &msg));
+        let outgoing = read_messages(&store_path, None, settings.epoch.clone(), Direction::Outgoing, None, None)
+            .await
+            .unwrap();
+        assert!(outgoing.is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    struct FixedClock(DateTime<Utc>);
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_clock() {
+        let then = DateTime::parse_from_rfc3339("2024-01-02T10:00:00Z").unwrap().to_utc();
+        let memory_store = MemoryStore::new();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr("127.0.0.1:0".parse().unwrap())
+            .with_memory_store(memory_store.clone())
+            .with_log_dir(std::env::temp_dir())
+            .with_clock(Arc::new(FixedClock(then)))
+            .build()
+            .unwrap();
+        let store = Store::build(&settings).await.unwrap();
+        store
+            .store_outgoing(settings.epoch.clone(), 1, Instant::now(), Arc::new(b"8=FIX.4.2\x01".to_vec().into()))
+            .unwrap();
+        store.disconnect().await.unwrap();
+
+        // the messages are stamped with the clock of the engine
+        let stored = memory_store.read_messages(&settings.epoch, Direction::Outgoing);
+        assert_eq!(stored[0].1.date_naive(), then.date_naive());
+    }
+}
@@ -1,10 +1,20 @@
+use crate::fix::arena::{Delivery, Incoming};
 use crate::fix::log::Logger;
 use crate::fix::mem::MsgBuf;
-use crate::fix::{decode, validate, SessionError};
+use crate::fix::generated::Tags;
+use crate::fix::{decode, validate, GarbledMessageType, SessionError};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 
+use std::io::IoSlice;
+use std::sync::Arc;
+
 pub(super) const PEEK_LEN: usize = 32;
+// the most a message buffer grows by before the bytes to fill it were read, so that a large
+// BodyLength does not allocate more than the counterparty has sent
+const READ_CHUNK_LEN: usize = 64 * 1024;
+// the most of a message over MaxMessageSize that is kept to be quarantined
+const QUARANTINE_CAP: usize = 64 * 1024;
 
 pub(super) trait TryRead {
     fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error>;
@@ -16,6 +26,13 @@ impl TryRead for TcpStream {
     }
 }
 
+#[cfg(any(test, feature = "test-util"))]
+impl TryRead for std::io::Cursor<&[u8]> {
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        std::io::Read::read(self, buf)
+    }
+}
+
 pub(super) struct HeaderBuf<const N: usize> {
     inner: Box<[u8]>,
     filled_len: usize,
@@ -55,33 +72,140 @@ impl<const N: usize> HeaderBuf<N> {
     }
 }
 
-pub(super) async fn read_message<const N: usize, T>(
+// Read the message whose header was read into `header`, straight into the arena of `delivery` if
+// the engine delivers through one and it has room.
+pub(super) async fn read_incoming<const N: usize, T>(
+    r: &mut T,
+    header: &mut HeaderBuf<N>,
+    max_message_size: Option<u32>,
+    logger: &mut impl Logger,
+    delivery: &mut Delivery,
+) -> Result<Incoming, SessionError>
+where T: TryRead + AsyncRead + Unpin
+{
+    let msg_len = message_len(r, header, max_message_size, logger).await?;
+    let Some(mut reserved) = delivery.reserve(msg_len) else {
+        return Ok(Incoming::Shared(Arc::new(read_body(r, header, msg_len, logger).await?)));
+    };
+    let bytes = reserved.bytes_mut();
+    let header_len = header.filled().len();
+    bytes[..header_len].copy_from_slice(header.filled());
+    header.clear();
+    r.read_exact(&mut bytes[header_len..]).await?;
+
+    let msg = Incoming::Arena(reserved);
+    tracing::trace!("received {}", String::from_utf8_lossy(msg.bytes()));
+    logger.log_bytes(msg.bytes())?;
+
+    if let Err(e) = validate::validate_msg_length(msg.bytes(), msg_len) {
+        let junk = skip_to_next_message(r, header).await?;
+        tracing::warn!("skipped a message with a bad length: {e}");
+        logger.quarantine(&[msg.bytes(), &junk[..]].concat(), &e.quarantine_reason())?;
+        logger.log_message(&junk.into())?;
+        return Err(e);
+    }
+
+    Ok(msg)
+}
+
+// The length of the message whose header was read, or an error if the header is garbled or the
+// message is larger than `max_message_size`, once its bytes were skipped.
+async fn message_len<const N: usize, T>(
     r: &mut T,
     header: &mut HeaderBuf<N>,
+    max_message_size: Option<u32>,
     logger: &mut impl Logger,
-) -> Result<MsgBuf, SessionError> 
+) -> Result<usize, SessionError>
 where T: TryRead + AsyncRead + Unpin
 {
     let body_len = match decode::parse_header(header.filled()) {
         Ok(n) => n,
         Err(e) => {
             let junk = skip_to_next_message(r, header).await?; 
+            tracing::warn!("skipped {} bytes of a garbled message: {e}", junk.len());
+            logger.quarantine(&junk, &e.quarantine_reason())?;
             logger.log_message(&junk.into())?; 
             return Err(e)
         }
     };
 
     let header_len = header.filled().len();
-    let mut msg_vec = vec![0; header_len + body_len];
-    msg_vec[..header_len].copy_from_slice(header.filled()); 
+    let msg_len = header_len + body_len;
+    if let Some(max_message_size) = max_message_size.filter(|max| msg_len > *max as usize) {
+        let text = format!("message of {msg_len} bytes is larger than MaxMessageSize {max_message_size}");
+        let kept_len = msg_len.min(QUARANTINE_CAP);
+        let mut kept = header.take(header_len);
+        kept.resize(kept_len, 0);
+        r.read_exact(&mut kept[header_len..]).await?;
+        tokio::io::copy(&mut (&mut *r).take((msg_len - kept_len) as u64), &mut tokio::io::sink()).await?;
+
+        // The message is rejected like any other, so that its sequence number is used up and
+        // the counterparty is not asked to resend it, unless it is too garbled to tell which
+        // sequence number it has.
+        let e = match seq_num_and_type(&kept) {
+            (Some(msg_seq_num), msg_type) => SessionError::MessageRejected {
+                text,
+                reject_reason: None,
+                msg_seq_num,
+                ref_tag_id: None,
+                ref_msg_type: msg_type,
+            },
+            (None, _) => SessionError::new_garbled_message(text, GarbledMessageType::BodyLengthIssue),
+        };
+        tracing::warn!("skipped {msg_len} bytes of a message: {e}");
+        logger.quarantine(&kept, &e.quarantine_reason())?;
+        logger.log_message(&kept.into())?;
+        return Err(e);
+    }
+    Ok(msg_len)
+}
+
+// The `MsgSeqNum(34)` and `MsgType(35)` of a message, found in its first bytes.
+fn seq_num_and_type(msg: &[u8]) -> (Option<u32>, Option<char>) {
+    let mut msg_seq_num = None;
+    let mut msg_type = None;
+    for field in decode::FieldIter::new(msg) {
+        let Ok((tag, value)) = field else {
+            break;
+        };
+        match (Tags::try_from(tag), value) {
+            (Ok(Tags::MsgSeqNum), _) => msg_seq_num = decode::parse_field::<u32>(value).ok(),
+            (Ok(Tags::MsgType), [t]) => msg_type = Some(*t as char),
+            _ => (),
+        }
+        if msg_seq_num.is_some() && msg_type.is_some() {
+            break;
+        }
+    }
+    (msg_seq_num, msg_type)
+}
+
+async fn read_body<const N: usize, T>(
+    r: &mut T,
+    header: &mut HeaderBuf<N>,
+    msg_len: usize,
+    logger: &mut impl Logger,
+) -> Result<MsgBuf, SessionError>
+where T: TryRead + AsyncRead + Unpin
+{
+    let header_len = header.filled().len();
+    let mut msg_vec = Vec::with_capacity(msg_len.min(header_len + READ_CHUNK_LEN));
+    msg_vec.extend_from_slice(header.filled());
     header.clear(); 
-    r.read_exact(&mut msg_vec[header_len..]).await?; 
+    while msg_vec.len() < msg_len {
+        let filled_len = msg_vec.len();
+        msg_vec.resize(filled_len + (msg_len - filled_len).min(READ_CHUNK_LEN), 0);
+        r.read_exact(&mut msg_vec[filled_len..]).await?; 
+    }
 
     let msg_buf: MsgBuf = msg_vec.into(); 
+    tracing::trace!("received {msg_buf}");
     logger.log_message(&msg_buf)?; 
     
     if let Err(e) = validate::validate_msg_length(msg_buf.0.as_slice(), msg_buf.len()) {
         let junk = skip_to_next_message(r, header).await?;
+        tracing::warn!("skipped a message with a bad length: {e}");
+        logger.quarantine(&[&msg_buf[..], &junk[..]].concat(), &e.quarantine_reason())?;
         logger.log_message(&junk.into())?;
         return Err(e);
     }
@@ -173,32 +297,120 @@ pub(super) async fn send_message<W: AsyncWrite + Unpin>(
     r: &mut W,
     l: &mut impl Logger,
 ) -> Result<(), SessionError> {
-    r.write_all(&msg_buf[..]).await.map_err(|e| {
-        if e.kind() == std::io::ErrorKind::BrokenPipe {
-            SessionError::TcpDisconnection
-        } else {
-            e.into()
-        }
-    })?;
+    r.write_all(&msg_buf[..]).await.map_err(write_error)?;
+    tracing::trace!("sent {msg_buf}");
     l.log_message(msg_buf)?;
     Ok(())
 }
 
+// Write every message of `msg_bufs` with vectored writes, so that a batch of messages takes as few
+// system calls as the stream allows.
+pub(super) async fn send_messages<W: AsyncWrite + Unpin>(
+    msg_bufs: &[MsgBuf],
+    r: &mut W,
+    l: &mut impl Logger,
+) -> Result<(), SessionError> {
+    let mut slices: Vec<IoSlice<'_>> = msg_bufs.iter().map(|msg_buf| IoSlice::new(&msg_buf[..])).collect();
+    let mut slices = &mut slices[..];
+    while !slices.is_empty() {
+        let written = r.write_vectored(slices).await.map_err(write_error)?;
+        if written == 0 {
+            return Err(write_error(std::io::ErrorKind::WriteZero.into()));
+        }
+        IoSlice::advance_slices(&mut slices, written);
+    }
+    for msg_buf in msg_bufs {
+        tracing::trace!("sent {msg_buf}");
+        l.log_message(msg_buf)?;
+    }
+    Ok(())
+}
+
+fn write_error(e: std::io::Error) -> SessionError {
+    if e.kind() == std::io::ErrorKind::BrokenPipe {
+        SessionError::TcpDisconnection
+    } else {
+        e.into()
+    }
+}
+
+/// Reads messages from bytes the way the engine reads them from its connection, delivering them
+/// through an arena.
+///
+/// Enabled with the `test-util` feature, so that the read path can be measured from outside the
+/// crate, such as counting its allocations with a global allocator of its own.
+#[cfg(any(test, feature = "test-util"))]
+pub struct ArenaReader<'a> {
+    incoming: std::io::Cursor<&'a [u8]>,
+    header: HeaderBuf<PEEK_LEN>,
+    delivery: Delivery,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl<'a> ArenaReader<'a> {
+    /// Read the messages of `bytes` into an arena of `capacity` bytes, which are received on the
+    /// returned [`ArenaReceiver`].
+    ///
+    /// [`ArenaReceiver`]: crate::fix::arena::ArenaReceiver
+    pub fn new(bytes: &'a [u8], capacity: usize) -> (ArenaReader<'a>, crate::fix::arena::ArenaReceiver) {
+        let (sender, receiver) = crate::fix::arena::channel(capacity);
+        let reader = ArenaReader {
+            incoming: std::io::Cursor::new(bytes),
+            header: HeaderBuf::new(),
+            delivery: Delivery::Arena(sender),
+        };
+        (reader, receiver)
+    }
+
+    /// Read the next message, check its checksum, parse it as the engine does and deliver it.
+    /// Returns its `MsgType(35)`, or `None` if there is no message left or it is not valid.
+    pub async fn read_next(&mut self) -> Option<char> {
+        read_header(&mut self.incoming, &mut self.header).await.ok()?;
+        let msg = read_incoming(&mut self.incoming, &mut self.header, None, &mut NullLogger, &mut self.delivery)
+            .await
+            .ok()?;
+        validate::validate_checksum(msg.bytes()).ok()?;
+        let mut cb = crate::fix::SessionParserCallback {
+            tolerate_out_of_place_fields: true,
+            ..Default::default()
+        };
+        decode::parse(msg.bytes(), &mut cb).ok()?;
+        self.delivery.send(&msg);
+        Some(cb.msg_type)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+struct NullLogger;
+
+#[cfg(any(test, feature = "test-util"))]
+impl Logger for NullLogger {
+    fn log_message(&mut self, _: &MsgBuf) -> std::io::Result<()> {
+        Ok(())
+    }
+    fn log_bytes(&mut self, _: &[u8]) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::io::Cursor;
-    use crate::fix::GarbledMessageType;
 
-    impl TryRead for Cursor<&[u8]> {
-        fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
-            std::io::Read::read(self, buf) 
-        }
+    async fn read_message<const N: usize>(
+        r: &mut Cursor<&[u8]>,
+        header: &mut HeaderBuf<N>,
+        max_message_size: Option<u32>,
+        logger: &mut impl Logger,
+    ) -> Result<MsgBuf, SessionError> {
+        let msg_len = message_len(r, header, max_message_size, logger).await?;
+        read_body(r, header, msg_len, logger).await
     }
 
     struct MockLogger;
     impl Logger for MockLogger {
-        fn log_message(&mut self, _: &MsgBuf) -> Result<(), SessionError> {
+        fn log_message(&mut self, _: &MsgBuf) -> std::io::Result<()> {
             Ok(())
         }
     }
@@ -260,7 +472,7 @@ mod test {
 
         let expected = MsgBuf(incoming_message.get_ref().to_vec()); 
         assert_eq!(
-            read_message(&mut incoming_message, &mut header_buf, &mut mock_logger).await.unwrap().0,
+            read_message(&mut incoming_message, &mut header_buf, None, &mut mock_logger).await.unwrap().0,
             expected.0,
         ); 
 
@@ -269,7 +481,7 @@ mod test {
         assert!(read_header(&mut incoming_message_bad_header, &mut header_buf).await.is_ok()); 
         assert!(
             matches!(
-                read_message(&mut incoming_message_bad_header, &mut header_buf, &mut mock_logger).await,
+                read_message(&mut incoming_message_bad_header, &mut header_buf, None, &mut mock_logger).await,
                 Err(SessionError::GarbledMessage{ garbled_msg_type: GarbledMessageType::BeginStringIssue, ..}),
             )
         ); 
@@ -281,7 +493,7 @@ mod test {
         assert!(read_header(&mut incoming_message_wrong_len, &mut header_buf).await.is_ok()); 
         assert!(
             matches!(
-                read_message(&mut incoming_message_wrong_len, &mut header_buf, &mut mock_logger).await,
+                read_message(&mut incoming_message_wrong_len, &mut header_buf, None, &mut mock_logger).await,
                 Err(SessionError::GarbledMessage{ garbled_msg_type: GarbledMessageType::BodyLengthIssue, ..}),
             )
         ); 
@@ -289,6 +501,73 @@ mod test {
         assert_eq!(header_buf.filled(), &[]); 
     }
 
+    #[derive(Default)]
+    struct QuarantineLogger(Vec<Vec<u8>>);
+    impl Logger for QuarantineLogger {
+        fn log_message(&mut self, _: &MsgBuf) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn quarantine(&mut self, bytes: &[u8], _: &str) -> std::io::Result<()> {
+            self.0.push(bytes.to_vec());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_message_quarantines_garbled_bytes() {
+        let mut logger = QuarantineLogger::default();
+        let mut header_buf = HeaderBuf::<{ PEEK_LEN }>::new();
+
+        let garbled: &[u8] = b"8=FIX.5.2\x019=67\x0135=A\x0134=1\x0149=ISLD\x0152=20240506-13:59:15.021\x0156=TW\x0110=003\x01";
+        let mut incoming = Cursor::new(garbled);
+        read_header(&mut incoming, &mut header_buf).await.unwrap();
+        assert!(read_message(&mut incoming, &mut header_buf, None, &mut logger).await.is_err());
+
+        let wrong_len: &[u8] = b"8=FIX.4.2\x019=40\x0135=A\x0134=1\x0149=ISLD\x0152=20240506-13:59:15.021\x0156=TW\x0198=0\x01108=30\x01141=Y\x0110=003\x01";
+        let mut incoming = Cursor::new(wrong_len);
+        header_buf.clear();
+        read_header(&mut incoming, &mut header_buf).await.unwrap();
+        assert!(read_message(&mut incoming, &mut header_buf, None, &mut logger).await.is_err());
+
+        assert_eq!(logger.0, vec![garbled.to_vec(), wrong_len.to_vec()]);
+    }
+
+    // A `News<B>` with `RawData(96)` of `raw_len` bytes.
+    fn raw_data_message(raw_len: usize) -> Vec<u8> {
+        let body = format!("35=B\x0134=2\x0149=ISLD\x0152=20240506-13:59:15.021\x0156=TW\x01148=big\x0133=1\x0158=x\x0195={raw_len}\x0196={}\x01", "x".repeat(raw_len));
+        let mut msg = format!("8=FIX.4.2\x019={}\x01{body}", body.len()).into_bytes();
+        let checksum = msg.iter().map(|b| *b as u32).sum::<u32>() % 256;
+        msg.extend(format!("10={checksum:03}\x01").bytes());
+        msg
+    }
+
+    #[tokio::test]
+    async fn test_read_large_message() {
+        let mut logger = QuarantineLogger::default();
+        let mut header_buf = HeaderBuf::<{ PEEK_LEN }>::new();
+
+        let large = raw_data_message(3 * READ_CHUNK_LEN + 17);
+        let mut incoming = Cursor::new(large.as_slice());
+        read_header(&mut incoming, &mut header_buf).await.unwrap();
+        let msg_buf = read_message(&mut incoming, &mut header_buf, None, &mut logger).await.unwrap();
+        assert_eq!(msg_buf.0, large);
+
+        let next = raw_data_message(10);
+        let stream = [large.clone(), next.clone()].concat();
+        let mut incoming = Cursor::new(stream.as_slice());
+        read_header(&mut incoming, &mut header_buf).await.unwrap();
+        assert!(
+            matches!(
+                read_message(&mut incoming, &mut header_buf, Some(4096), &mut logger).await,
+                Err(SessionError::MessageRejected{ msg_seq_num: 2, ref_msg_type: Some('B'), reject_reason: None, .. }),
+            )
+        );
+        assert_eq!(logger.0, vec![large[..QUARANTINE_CAP].to_vec()]);
+        read_header(&mut incoming, &mut header_buf).await.unwrap();
+        let msg_buf = read_message(&mut incoming, &mut header_buf, Some(4096), &mut logger).await.unwrap();
+        assert_eq!(msg_buf.0, next);
+    }
+
     #[tokio::test]
     async fn test_read_header() {
         const incoming_message: &[u8] = b"8=FIX.4.2\x019=54\x0135=A\x01".as_slice();
@@ -1,6 +1,5 @@
 use crate::fix::checksum::checksum_is_valid;
 use crate::fix::generated::{MsgType, SessionRejectReason, Tags};
-use crate::fix::mem::MsgBuf;
 use crate::fix::{GarbledMessageType, SessionError};
 
 use chrono::{DateTime, Duration, Utc};
@@ -18,6 +17,7 @@ pub(super) fn validate_msg<'a>(
     orig_sending_time: Option<DateTime<Utc>>,
     begin_seq_no: Option<u32>,
     end_seq_no: Option<u32>,
+    now: DateTime<Utc>,
 ) -> Result<(), SessionError> {
     if <char as TryInto<MsgType>>::try_into(msg_type).is_err() {
         return Err(SessionError::new_message_rejected(
@@ -25,7 +25,8 @@ pub(super) fn validate_msg<'a>(
             msg_seq_num,
             Some(Tags::MsgType.into()),
             Some(msg_type),
-        ));
+        )
+        .with_received_value(msg_type.to_string().as_bytes()));
     }
     if Some(expected_target_comp_id.as_bytes()) != target_comp_id {
         return Err(SessionError::new_message_rejected(
@@ -33,7 +34,8 @@ pub(super) fn validate_msg<'a>(
             msg_seq_num,
             Some(Tags::TargetCompID.into()),
             Some(msg_type),
-        ));
+        )
+        .with_received_value(target_comp_id.unwrap_or_default()));
     }
 
     if Some(expected_sender_comp_id.as_bytes()) != sender_comp_id {
@@ -42,7 +44,8 @@ pub(super) fn validate_msg<'a>(
             msg_seq_num,
             Some(Tags::SenderCompID.into()),
             Some(msg_type),
-        ));
+        )
+        .with_received_value(sender_comp_id.unwrap_or_default()));
     }
 
     if sending_time.is_none() {
@@ -54,7 +57,7 @@ pub(super) fn validate_msg<'a>(
         ));
     }
 
-    if !valid_sending_time(sending_time.unwrap(), Duration::seconds(10)) {
+    if !valid_sending_time(sending_time.unwrap(), now, Duration::seconds(10)) {
         return Err(SessionError::new_message_rejected(
             Some(SessionRejectReason::SENDINGTIME_ACCURACY_PROBLEM),
             msg_seq_num,
@@ -73,13 +76,14 @@ pub(super) fn validate_msg<'a>(
             )?;
         }
         Some('N') | None => {}
-        Some(_) => {
+        Some(flag) => {
             return Err(SessionError::new_message_rejected(
                 Some(SessionRejectReason::VALUE_IS_INCORRECT),
                 msg_seq_num,
                 Some(Tags::PossDupFlag.into()),
                 Some(msg_type),
-            ));
+            )
+            .with_received_value(flag.to_string().as_bytes()));
         }
     }
 
@@ -96,12 +100,49 @@ pub(super) fn validate_msg<'a>(
     Ok(())
 }
 
+// A header field, the value it is expected to have, if any, and the value it was received with.
+pub(super) type SubId<'a> = (Tags, Option<&'a str>, Option<&'a [u8]>);
+
+// Each header field with an expected value must be in the message with that value.
+pub(super) fn validate_sub_ids<const N: usize>(
+    sub_ids: [SubId<'_>; N],
+    msg_type: char,
+    msg_seq_num: u32,
+) -> Result<(), SessionError> {
+    for (tag, expected, received) in sub_ids {
+        let Some(expected) = expected else {
+            continue;
+        };
+        match received {
+            None => {
+                return Err(SessionError::new_message_rejected(
+                    Some(SessionRejectReason::REQUIRED_TAG_MISSING),
+                    msg_seq_num,
+                    Some(tag.into()),
+                    Some(msg_type),
+                ));
+            }
+            Some(value) if value != expected.as_bytes() => {
+                return Err(SessionError::new_message_rejected(
+                    Some(SessionRejectReason::COMPID_PROBLEM),
+                    msg_seq_num,
+                    Some(tag.into()),
+                    Some(msg_type),
+                )
+                .with_received_value(value));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
 fn valid_resend_request(begin_seq_no: Option<u32>, end_seq_no: Option<u32>) -> bool {
     begin_seq_no.is_some() && end_seq_no.is_some()
 }
 
-pub(super) fn validate_checksum(msg_buf: &MsgBuf) -> Result<(), SessionError> {
-    if !checksum_is_valid(&msg_buf.0) {
+pub(super) fn validate_checksum(msg: &[u8]) -> Result<(), SessionError> {
+    if !checksum_is_valid(msg) {
         return Err(SessionError::new_garbled_message(
             String::from("Checksum invalid"),
             GarbledMessageType::ChecksumIssue,
@@ -120,9 +161,9 @@ pub(super) fn validate_msg_length(msg_buf: &[u8], msg_length: usize) -> Result<(
     Ok(())
 }
 
-fn valid_sending_time(sending_time: DateTime<Utc>, sending_time_threshold: Duration) -> bool {
-    Utc::now() - sending_time < sending_time_threshold
-        && sending_time - Utc::now() < sending_time_threshold
+fn valid_sending_time(sending_time: DateTime<Utc>, now: DateTime<Utc>, sending_time_threshold: Duration) -> bool {
+    now - sending_time < sending_time_threshold
+        && sending_time - now < sending_time_threshold
 }
 
 fn validate_duplicate(
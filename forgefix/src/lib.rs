@@ -18,16 +18,24 @@
 //! to a FIX Session. The engine starts, runs, and ends the FIX connection as defined by the FIX
 //! protocol, and manages all resources that support the connection. 
 //!
+//! ## Tracing
+//! The engine reports logons, logouts, rejects, resends, disconnects and errors through the
+//! [`tracing`](https://docs.rs/tracing) crate, in a `fix_session` span with the
+//! `sender_comp_id` and `target_comp_id` of the session. Install any `tracing` subscriber to
+//! collect them. State transitions are reported at the `debug` level, and every message sent or
+//! received at the `trace` level. 
+//!
 //! ## Examples
 //!
 //! ### Asynchronous API
-//! ```no_run
+//! ```
 //! use forgefix::{
 //!     SessionSettings, FixApplicationHandle, FixApplicationInitiator, ApplicationError,
 //! }; 
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), ApplicationError> {
+//! #   let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
 //!     
 //!     // build session settings 
 //!     let settings = SessionSettings::builder()
@@ -36,6 +44,7 @@
 //!         .with_store_path("./store".into())
 //!         .with_log_dir("./log".into())
 //!         .with_socket_addr("127.0.0.1:0".parse().unwrap())
+//! #       .with_store_path(peer.store_path()).with_log_dir(peer.log_dir()).with_socket_addr(peer.addr())
 //!         .build()?; 
 //!
 //!     // create a FIX engine and intiate TCP connection
@@ -63,12 +72,13 @@
 //! ```
 //!
 //! ### Synchronous API*
-//! ```no_run
+//! ```
 //! use forgefix::{
 //!     SessionSettings, FixApplicationHandle, FixApplicationInitiator, ApplicationError,
 //! }; 
 //!
 //! fn main() -> Result<(), ApplicationError> {
+//! #   let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
 //!
 //!     let settings = SessionSettings::builder()
 //!         .with_sender_comp_id("my_id")
@@ -76,6 +86,7 @@
 //!         .with_store_path("./store".into())
 //!         .with_log_dir("./log".into())
 //!         .with_socket_addr("127.0.0.1:0".parse().unwrap())
+//! #       .with_store_path(peer.store_path()).with_log_dir(peer.log_dir()).with_socket_addr(peer.addr())
 //!         .build()?; 
 //!
 //!     let (fix_handle, mut event_receiver) = FixApplicationInitiator::build(settings)?
@@ -98,21 +109,68 @@
 //! ```
 //! *When using synchronous API, a tokio runtime is still created internally (see
 //! [`FixApplicationInitiator`])
+//!
+//! To wait for messages with a timeout, or poll for them without blocking, wrap the receiver in
+//! a [`BlockingReceiver`](fix::blocking::BlockingReceiver). 
+//!
+//! ### Ephemeral sessions
+//! [`SessionSettings::ephemeral`] keeps messages in memory and logs in a temporary directory, so
+//! an example or a quick test can connect without a store or log directory. 
+//! ```
+//! use forgefix::{SessionSettings, FixApplicationInitiator, ApplicationError};
+//! use std::net::SocketAddr;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), ApplicationError> {
+//! #   let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+//!     let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+//! #   let addr = peer.addr();
+//!     let settings = SessionSettings::ephemeral("my_id", "peer_id", addr);
+//!     let (fix_handle, _event_receiver) = FixApplicationInitiator::build(settings)?
+//!         .initiate()
+//!         .await?;
+//!     fix_handle.start_async().await?;
+//!     fix_handle.end_async().await?;
+//!     Ok(())
+//! }
+//! ```
 
 pub mod fix;
-use fix::encode::MessageBuilder;
+pub mod manager;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+use fix::arena::{ArenaReceiver, Delivery, Incoming};
+use fix::decode::FieldSections;
+use fix::encode::{AdditionalHeaders, MessageBuilder};
+use fix::lint::Diagnostic;
+use fix::log::Logger;
+use fix::generated::{is_session_message, Tags};
 use fix::mem::MsgBuf;
+use fix::memory_store::MemoryStore;
+use fix::schedule::SessionSchedule;
+use fix::session::{OutboxPermit, Sequences};
+use fix::acks::PendingAcks;
+use fix::dedup::{is_new_order, SentOrders};
+use fix::metrics::Metrics;
+use fix::pool::BuilderPool;
+use fix::rate_limit::RateLimiter;
 
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 use thiserror::Error;
 use tokio::net::{TcpListener, TcpSocket, TcpStream};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
 
 use chrono::naive::NaiveTime; 
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use tracing::Instrument;
 
 enum Request {
     Logon {
@@ -121,10 +179,42 @@ enum Request {
     SendMessage {
         resp_sender: oneshot::Sender<bool>,
         builder: MessageBuilder,
+        // counts the message against `SessionSettings::outbox_capacity` until it is taken from the
+        // queue, and holds the `ClOrdID(11)` reserved for a new order
+        permit: Option<OutboxPermit>,
+    },
+    SendBatch {
+        // answered once the last message is sent
+        resp_sender: oneshot::Sender<bool>,
+        builders: Vec<MessageBuilder>,
+        // counts every message of the batch against `SessionSettings::outbox_capacity`, and holds
+        // the `ClOrdID(11)`s reserved for its new orders
+        permit: Option<OutboxPermit>,
     },
     Logout {
         resp_sender: oneshot::Sender<bool>,
     },
+    Shutdown {
+        resp_sender: oneshot::Sender<ShutdownReport>,
+        drain: Drain,
+        timeout: Duration,
+    },
+    Watchdog {
+        window: Option<Duration>,
+    },
+    ResetSequence {
+        resp_sender: oneshot::Sender<bool>,
+        new_seq_no: u32,
+    },
+    Resend {
+        resp_sender: oneshot::Sender<bool>,
+        begin_seq_no: u32,
+        end_seq_no: u32,
+    },
+    Flush {
+        resp_sender: oneshot::Sender<bool>,
+        until: Flush,
+    },
 }
 
 /// Errors that can occur while running ForgeFIX. 
@@ -132,16 +222,119 @@ enum Request {
 pub enum ApplicationError {
     #[error("An I/O error occured: {0}")]
     IoError(#[from] std::io::Error),
-    #[error("Session ended unexpectedly")]
-    SessionEnded,
+    #[error("Session ended unexpectedly{}", .0.as_ref().map(|e| format!(": {e}")).unwrap_or_default())]
+    SessionEnded(Option<EngineError>),
     #[error("Logon has failed")]
     LogonFailed,
     #[error("Logout has failed")]
     LogoutFailed,
     #[error("MessageSend has failed")]
     SendMessageFailed,
+    #[error("Session is paused")]
+    SessionPaused,
+    #[error("A message with the same ClOrdID(11) was already sent")]
+    AlreadySent,
     #[error("setting `{0}` is required")]
     SettingRequired(String),
+    #[error("no session with id `{0}`")]
+    UnknownSession(String),
+    #[error("a session with id `{0}` already exists")]
+    DuplicateSession(String),
+    #[error("The queue of messages waiting to be sent is full")]
+    QueueFull,
+    #[error("The message has no ClOrdID(11)")]
+    NoClOrdId,
+    #[error("The peer did not answer the message in time")]
+    AckTimedOut,
+    #[error("The message is not valid FIX 4.2 ({0})")]
+    InvalidMessage(Diagnostic),
+    #[error("sequence number `{0}` is not valid, sequence numbers start at 1")]
+    InvalidSequenceNumber(u32),
+    #[error("`{0}` is not a host:port address")]
+    InvalidHost(String),
+    #[error("tag `{0}` is set by the engine and cannot be added to the header")]
+    ReservedHeaderTag(u32),
+    #[error("The message would exceed the rate limit of the session")]
+    RateLimited,
+    #[error("The message is {len} bytes, more than the MaxMessageSize(383) of {max_message_size} of the peer")]
+    MessageTooLarge { len: usize, max_message_size: u32 },
+    #[error("BeginString(8) `{0}` is not supported, only FIX.4.2 is")]
+    UnsupportedBeginString(String),
+}
+
+/// The error that ended a FIX engine. 
+///
+/// See [`FixApplicationHandle::last_engine_error`].
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("{message}")]
+pub struct EngineError {
+    /// A description of the error and its causes. 
+    pub message: String,
+    /// The kind of the I/O error that ended the engine, such as
+    /// [`ConnectionReset`](std::io::ErrorKind::ConnectionReset), if it was one. 
+    pub io_error_kind: Option<std::io::ErrorKind>,
+}
+
+impl From<&anyhow::Error> for EngineError {
+    fn from(e: &anyhow::Error) -> EngineError {
+        EngineError {
+            message: format!("{e:#}"),
+            io_error_kind: e
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+                .map(|io_error| io_error.kind()),
+        }
+    }
+}
+
+/// Why a FIX engine ended.
+///
+/// See [`FixApplicationHandle::closed_reason`].
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The `Logout<5>` messages were exchanged with the peer, whichever side started the logout.
+    LoggedOut {
+        /// The `Text(58)` of the `Logout<5>` of the peer, if it had one.
+        text: Option<String>,
+    },
+    /// The logon failed. The peer answered the `Logon<A>` of the engine with a `Logout<5>` or
+    /// a `Reject<3>`, or the engine refused the `Logon<A>` of the peer.
+    LogonRejected {
+        /// The `Text(58)` of the `Logout<5>` of the peer, if it had one.
+        text: Option<String>,
+    },
+    /// The peer sent a garbled message that ended the session, such as one with the wrong
+    /// `BeginString(8)`.
+    GarbledMessage {
+        /// What was wrong with the message.
+        text: String,
+    },
+    /// The engine rejected a message of the peer and logged out, such as for a wrong `CompID`
+    /// or an inaccurate `SendingTime(52)`.
+    MessageRejected {
+        /// Why the message was rejected.
+        text: String,
+    },
+    /// The peer sent a `MsgSeqNum(34)` lower than expected, and the engine logged out.
+    SequenceTooLow {
+        /// The `MsgSeqNum(34)` the engine expected.
+        expected: u32,
+        /// The `MsgSeqNum(34)` the peer sent.
+        received: u32,
+    },
+    /// The peer did not answer a `TestRequest<1>` in time.
+    HeartbeatTimeout,
+    /// The peer did not answer the `Logout<5>` of the engine in time.
+    LogoutTimeout,
+    /// The peer closed the TCP connection without logging out.
+    ConnectionClosed,
+    /// The engine ended outside of its session schedule, before logging on.
+    NotStarted,
+    /// The engine ended with an error, such as an I/O error. A reset of the TCP connection has
+    /// an [`io_error_kind`](EngineError::io_error_kind) of
+    /// [`ConnectionReset`](std::io::ErrorKind::ConnectionReset).
+    Error(EngineError),
 }
 
 /// A collection of settings used to configurate a FIX session. 
@@ -153,12 +346,559 @@ pub struct SessionSettings {
     engine_type: FixEngineType,
     sender_comp_id: String,
     target_comp_id: String,
-    addr: SocketAddr, 
+    sender_sub_id: Option<String>,
+    target_sub_id: Option<String>,
+    on_behalf_of_comp_id: Option<String>,
+    deliver_to_comp_id: Option<String>,
+    sub_id_validation: bool,
+    comp_id_mismatch: CompIdMismatch,
+    endpoints: Arc<[Endpoint]>, 
     epoch: Arc<String>,
     store_path: PathBuf,
     log_dir: PathBuf,
     heartbeat_timeout: Duration,
+    heartbeat_policy: HeartbeatPolicy,
+    logout_timeout: Option<Duration>,
     start_time: NaiveTime, 
+    checksum_validation: ChecksumValidation,
+    max_message_size: Option<u32>,
+    logon_msg_types: Arc<Vec<LogonMsgType>>,
+    quarantine_log: bool,
+    log_rotation: LogRotation,
+    logger: Option<Arc<Mutex<Box<dyn Logger>>>>,
+    watchdog_test_request: bool,
+    unmatched_test_req_id: UnmatchedTestReqId,
+    outgoing_dedup: bool,
+    outbound_validation: bool,
+    outbox_capacity: Option<usize>,
+    rate_limit: Option<RateLimit>,
+    live_buffer: usize,
+    #[cfg(feature = "chaos")]
+    chaos: Option<fix::chaos::Chaos>,
+    ipv6_only: bool,
+    socket_options: SocketOptions,
+    store_encryption: Option<Arc<dyn SecretProvider>>,
+    store_seal_migration: bool,
+    store_shard_by_date: bool,
+    store_incoming: bool,
+    field_sections: Arc<FieldSections>,
+    tolerate_out_of_place_fields: bool,
+    sequence_too_low_patterns: Arc<Vec<Regex>>,
+    sequence_auto_heal: bool,
+    echo_tags: Arc<Vec<u32>>,
+    poss_resend_window: Option<usize>,
+    drop_poss_resend_duplicates: bool,
+    memory_store: Option<MemoryStore>,
+    orphan_policy: OrphanPolicy,
+    schedule: Option<SessionSchedule>,
+    logon_fields: Arc<Vec<(u32, Vec<u8>)>>,
+    duplicate_logon: DuplicateLogon,
+    session_callback: Option<Arc<dyn SessionCallback>>,
+    delivery_filter: DeliveryFilter,
+    pre_logon_guard: Option<Duration>,
+    store_vacuum_budget: Option<Duration>,
+    reset_seq_num: bool,
+    reset_flag_on_initial_logon: bool,
+    timestamp_precision: TimestampPrecision,
+    clock: Arc<dyn Clock>,
+    resend_policy: ResendPolicy,
+    max_resend_window: Option<u32>,
+    header_extras: Arc<Vec<(u32, Vec<u8>)>>,
+}
+
+/// When a FIX engine starts a new log file, and what it does with the completed ones. 
+///
+/// Without rotation, which is the default, all messages of a session are written to one file
+/// named after its CompIDs, such as `peer_id-my_id.txt`. With rotation, files are also named by
+/// their local date, such as `peer_id-my_id.20240102.txt`, followed by `peer_id-my_id.20240102.1.txt`
+/// and so on when a day has more than one file. The quarantine log is not rotated. 
+///
+/// See [`SessionSettingsBuilder::with_log_rotation`].
+///
+/// ```
+/// use forgefix::LogRotation;
+///
+/// // a new file every day, and every 100 MB, compressing the completed files
+/// let rotation = LogRotation {
+///     daily: true,
+///     max_bytes: Some(100 * 1024 * 1024),
+///     compress: true,
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogRotation {
+    /// Start a new file on the first message of each day, in local time. 
+    pub daily: bool,
+    /// Start a new file before a message would take the current one over this many bytes. 
+    pub max_bytes: Option<u64>,
+    /// Compress completed files with gzip, replacing `name.txt` by `name.txt.gz`. 
+    pub compress: bool,
+}
+
+impl LogRotation {
+    fn is_enabled(&self) -> bool {
+        self.daily || self.max_bytes.is_some()
+    }
+}
+
+/// How the `CheckSum(10)` of incoming messages should be validated.
+///
+/// Checksums are validated on every message by default. Skipping or sampling validation should
+/// only be considered on trusted links where message integrity is already guaranteed, such as a
+/// cross-connect where checksums are verified by hardware. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumValidation {
+    /// Validate the checksum of every incoming message.
+    #[default]
+    Always,
+    /// Never validate the checksum of incoming messages.
+    Skip,
+    /// Validate the checksum of one out of every `n` incoming messages. A value of `0` or `1`
+    /// validates every message.
+    Sample(u32),
+}
+
+impl ChecksumValidation {
+    fn should_validate(&self, msg_count: u64) -> bool {
+        match *self {
+            ChecksumValidation::Always => true,
+            ChecksumValidation::Skip => false,
+            ChecksumValidation::Sample(n) => n <= 1 || msg_count.is_multiple_of(n as u64),
+        }
+    }
+}
+
+/// How a `Heartbeat<0>` is handled when its `TestReqID(112)` does not match an outstanding
+/// `TestRequest<1>` sent by the engine, such as an ID that was never issued or was already
+/// answered. 
+///
+/// The heartbeat is processed as usual regardless of the policy. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnmatchedTestReqId {
+    /// Do nothing. 
+    Ignore,
+    /// Count the heartbeat in [`SessionMetrics::unmatched_test_req_ids`].
+    #[default]
+    Count,
+    /// Count the heartbeat, and also log the unmatched ID as a `tracing` warning. 
+    Log,
+}
+
+/// What [`FixApplicationHandle::shutdown`] does with the application messages that were not sent
+/// yet. 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drain {
+    /// Send every message queued before the shutdown, including the messages held until the
+    /// session of the schedule starts if it already started, then log out. 
+    All,
+    /// Log out without sending the messages still queued. 
+    None,
+}
+
+/// The outcome of [`FixApplicationHandle::shutdown`]. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// The number of application messages that were queued, and dropped without being sent. 
+    pub dropped: usize,
+    /// Whether the timeout expired, and the TCP connection was closed before the peer confirmed
+    /// the `Logout<5>`. 
+    pub timed_out: bool,
+    /// Whether the session ended without any issues, as reported by [`FixApplicationHandle::end`]. 
+    pub logged_out: bool,
+}
+
+/// When a FIX engine sends heartbeats and test requests, and how long it waits for the peer to
+/// confirm a logout, in heartbeat intervals. 
+///
+/// The default follows the FIX protocol: a `Heartbeat<0>` once nothing was sent for one interval,
+/// a `TestRequest<1>` once nothing was received for 1.7 intervals, and up to 2 intervals for the
+/// `Logout<5>` of the peer. A number of intervals that is not positive counts as one interval. 
+///
+/// ```
+/// use forgefix::{HeartbeatPolicy, HeartbeatTimer};
+///
+/// // a heartbeat every interval, and no test requests
+/// let policy = HeartbeatPolicy {
+///     timer: HeartbeatTimer::Fixed,
+///     test_request_after: None,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeartbeatPolicy {
+    /// When a `Heartbeat<0>` is sent. 
+    pub timer: HeartbeatTimer,
+    /// Send a `TestRequest<1>` once nothing was received for this many intervals, or never if
+    /// `None`. Without test requests, a peer that stopped sending is only noticed once the TCP
+    /// connection drops. 
+    pub test_request_after: Option<f64>,
+    /// Wait this many intervals for the peer to confirm a `Logout<5>`, unless a timeout is set
+    /// with [`SessionSettingsBuilder::with_logout_timeout`]. 
+    pub logout_after: f64,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> HeartbeatPolicy {
+        HeartbeatPolicy {
+            timer: HeartbeatTimer::Idle,
+            test_request_after: Some(1.7),
+            logout_after: 2.0,
+        }
+    }
+}
+
+/// When a FIX engine sends a `Heartbeat<0>`. See [`HeartbeatPolicy`]. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeartbeatTimer {
+    /// Once nothing was sent for a heartbeat interval, as the FIX protocol specifies. 
+    #[default]
+    Idle,
+    /// Every heartbeat interval, whether or not other messages were sent. The answer to a
+    /// `TestRequest<1>` is then awaited until the next test request is due, rather than for one
+    /// heartbeat interval. 
+    Fixed,
+}
+
+/// What [`FixApplicationHandle::flush_async`] waits for. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Flush {
+    /// The messages were written to the TCP stream. 
+    #[default]
+    Written,
+    /// The messages were written to the TCP stream, and committed to the store. 
+    Stored,
+}
+
+/// A limit on the rate of the application messages sent by a FIX engine. 
+///
+/// The limit is a token bucket: up to `burst` messages can be sent at once, after which one more
+/// message can be sent every `1 / messages_per_second` seconds. Session messages, such as
+/// heartbeats, and the messages resent for a `ResendRequest<2>` are always sent, and are not
+/// counted. 
+///
+/// ```
+/// use forgefix::{RateLimit, RateLimitExceeded};
+///
+/// // 50 messages per second, and bursts of up to 10
+/// let rate_limit = RateLimit {
+///     messages_per_second: 50,
+///     burst: 10,
+///     exceeded: RateLimitExceeded::Delay,
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of messages sent per second, once a burst was sent. At least 1. 
+    pub messages_per_second: u32,
+    /// The number of messages that can be sent at once, after no message was sent for a while.
+    /// At least 1. 
+    pub burst: u32,
+    /// What happens to the messages sent over the limit. 
+    pub exceeded: RateLimitExceeded,
+}
+
+/// What a FIX engine does with the application messages sent over its [`RateLimit`]. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitExceeded {
+    /// Queue the messages, and send them in order as the limit allows. The session messages of
+    /// the engine, such as heartbeats, are sent first, but the messages sent after them through a
+    /// handle, including a `Logout<5>`, wait their turn. 
+    #[default]
+    Delay,
+    /// Refuse the messages: sending returns an `Err(ApplicationError::RateLimited)`, and the
+    /// message is not sent. The messages accepted while they were held back, such as until the
+    /// session schedule starts or until a reconnect, are then sent as the limit allows. 
+    Reject,
+}
+
+/// What a FIX engine does once every [`FixApplicationHandle`] to it was dropped without ending
+/// the session. 
+///
+/// Either way, a [`SessionEvent::AllHandlesDropped`] is published. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Keep the session running until the peer ends it. 
+    #[default]
+    KeepAlive,
+    /// Wait for the grace period, then send a `Logout<5>` message and end the engine once the
+    /// peer confirms it. 
+    Logout(Duration),
+}
+
+/// Which of the application messages received by a FIX engine are delivered to the application. 
+///
+/// Messages that are not delivered are still sequenced, stored and logged, and still passed to
+/// [`SessionCallback::on_app_msg_in`]. 
+///
+/// ```
+/// use forgefix::DeliveryFilter;
+/// use forgefix::fix::generated::MsgType;
+///
+/// // only execution reports and order cancel rejects
+/// let filter = DeliveryFilter::MsgTypes(vec![
+///     MsgType::EXECUTION_REPORT.into(),
+///     MsgType::ORDER_CANCEL_REJECT.into(),
+/// ]);
+/// ```
+#[derive(Clone, Default)]
+pub enum DeliveryFilter {
+    /// Deliver every application message. 
+    #[default]
+    All,
+    /// Deliver the messages with one of these `MsgType(35)`s. 
+    MsgTypes(Vec<char>),
+    /// Deliver the messages for which the function returns true. It is called with the
+    /// `MsgType(35)` and the message, from the engine's task, so it should return quickly. 
+    Predicate(Arc<DeliveryPredicate>),
+}
+
+/// The function of a [`DeliveryFilter::Predicate`]. 
+pub type DeliveryPredicate = dyn Fn(char, &MsgBuf) -> bool + Send + Sync;
+
+impl DeliveryFilter {
+    pub(crate) fn delivers(&self, msg: &Incoming) -> bool {
+        let msg_type = || fix::replay::msg_type(msg.bytes()).unwrap_or_default();
+        match self {
+            DeliveryFilter::All => true,
+            DeliveryFilter::MsgTypes(msg_types) => msg_types.contains(&msg_type()),
+            DeliveryFilter::Predicate(predicate) => predicate(msg_type(), &msg.to_shared()),
+        }
+    }
+}
+
+/// What a FIX engine does when a message received while logged on has a `SenderCompID(49)` or
+/// `TargetCompID(56)` other than the one expected, or a sub ID other than the one expected with
+/// [`SessionSettingsBuilder::with_sub_id_validation`]. 
+///
+/// A `Logon<A>` with an unexpected CompID is always refused. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompIdMismatch {
+    /// Answer with a `Reject<3>` message, and keep the session running. 
+    Reject,
+    /// Answer with a `Reject<3>` message, then send a `Logout<5>` message and disconnect, as the
+    /// FIX specification requires. 
+    #[default]
+    Logout,
+    /// Disconnect without sending anything. 
+    Disconnect,
+}
+
+/// What a FIX engine does when it receives a `Logon<A>` message while already logged on. 
+///
+/// A `Logon<A>` with `ResetSeqNumFlag(141)=Y` resets the sequence numbers instead, and one with
+/// `PossDupFlag(43)=Y` is treated as a resend of the first `Logon<A>`, and ignored. Either way, a
+/// [`SessionEvent::DuplicateLogonReceived`] is published. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateLogon {
+    /// Answer with a `Reject<3>` message, and keep the session running. 
+    #[default]
+    Reject,
+    /// Send a `Logout<5>` message and disconnect. 
+    Disconnect,
+}
+
+/// The precision of the `SendingTime(52)` of the messages sent by a FIX engine. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampPrecision {
+    /// Whole seconds, such as `20240102-15:04:05`. 
+    Seconds,
+    /// Milliseconds, such as `20240102-15:04:05.123`. 
+    #[default]
+    Millis,
+    /// Microseconds, such as `20240102-15:04:05.123456`. 
+    Micros,
+    /// Nanoseconds, such as `20240102-15:04:05.123456789`. 
+    Nanos,
+}
+
+impl TimestampPrecision {
+    fn time_format(&self) -> &'static str {
+        match self {
+            TimestampPrecision::Seconds => "%Y%m%d-%H:%M:%S",
+            TimestampPrecision::Millis => fix::encode::TIME_FORMAT,
+            TimestampPrecision::Micros => "%Y%m%d-%H:%M:%S%.6f",
+            TimestampPrecision::Nanos => "%Y%m%d-%H:%M:%S%.9f",
+        }
+    }
+}
+
+/// How a FIX engine answers a `ResendRequest<2>` for application messages. 
+///
+/// Session messages, such as `Heartbeat<0>`, are never resent, and are replaced by a
+/// `SequenceReset<4>` gap fill. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResendPolicy {
+    /// Resend the stored application messages with `PossDupFlag(43)=Y`. 
+    #[default]
+    Resend,
+    /// Replace every message, application messages included, with a gap fill, for venues that
+    /// never want stale orders resent. 
+    GapFill,
+}
+
+/// The leniencies a FIX engine needs to talk to a counterparty that does not follow the
+/// specification to the letter. 
+///
+/// Rather than setting each leniency on the [`SessionSettingsBuilder`], a deployment can share
+/// named presets, such as [`VenueQuirks::lenient`], and adjust them with struct update syntax.
+/// Settings set individually on the builder, such as
+/// [`with_checksum_validation`](SessionSettingsBuilder::with_checksum_validation), take precedence
+/// over the quirks. 
+///
+/// ```
+/// use forgefix::{TimestampPrecision, VenueQuirks};
+///
+/// let quirks = VenueQuirks {
+///     timestamp_precision: TimestampPrecision::Seconds,
+///     header_extras: vec![(115, b"CLIENT1".to_vec())],
+///     ..VenueQuirks::lenient()
+/// };
+/// assert_eq!(VenueQuirks::preset("lenient"), Some(VenueQuirks::lenient()));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VenueQuirks {
+    /// The precision of the `SendingTime(52)` of outgoing messages. 
+    pub timestamp_precision: TimestampPrecision,
+    /// How the `CheckSum(10)` of incoming messages is validated. 
+    pub checksum_validation: ChecksumValidation,
+    /// Whether header fields the peer puts after body or trailer fields are still found. See
+    /// [`SessionSettingsBuilder::with_tolerate_out_of_place_fields`]. 
+    pub tolerate_out_of_place_fields: bool,
+    /// How a `ResendRequest<2>` for application messages is answered. 
+    pub resend_policy: ResendPolicy,
+    /// Fields added to the header of every outgoing message, such as an `OnBehalfOfCompID(115)`
+    /// the venue requires. 
+    pub header_extras: Vec<(u32, Vec<u8>)>,
+}
+
+impl VenueQuirks {
+    /// The names of the presets, as accepted by [`VenueQuirks::preset`]. 
+    pub const PRESETS: [&'static str; 3] = ["strict", "lenient", "legacy"];
+
+    /// Follow the specification, the default. 
+    pub fn strict() -> VenueQuirks {
+        VenueQuirks::default()
+    }
+
+    /// Accept incoming messages with a wrong `CheckSum(10)`, or with header fields out of place. 
+    pub fn lenient() -> VenueQuirks {
+        VenueQuirks {
+            checksum_validation: ChecksumValidation::Skip,
+            tolerate_out_of_place_fields: true,
+            ..VenueQuirks::default()
+        }
+    }
+
+    /// Like [`VenueQuirks::lenient`], for older venues that only accept a `SendingTime(52)` in
+    /// whole seconds and must not receive resent orders. 
+    pub fn legacy() -> VenueQuirks {
+        VenueQuirks {
+            timestamp_precision: TimestampPrecision::Seconds,
+            resend_policy: ResendPolicy::GapFill,
+            ..VenueQuirks::lenient()
+        }
+    }
+
+    /// The preset named `name`, one of [`VenueQuirks::PRESETS`], or `None` if there is no such
+    /// preset. 
+    pub fn preset(name: &str) -> Option<VenueQuirks> {
+        match name {
+            "strict" => Some(VenueQuirks::strict()),
+            "lenient" => Some(VenueQuirks::lenient()),
+            "legacy" => Some(VenueQuirks::legacy()),
+            _ => None,
+        }
+    }
+}
+
+/// A source of the secrets used by a FIX engine. 
+///
+/// Implement this trait to fetch secrets from a vault, key management service or environment,
+/// instead of keeping them in [`SessionSettings`]. 
+pub trait SecretProvider: Send + Sync {
+    /// Get the 256-bit AES key used to encrypt the messages stored for `epoch`. 
+    fn store_encryption_key(&self, epoch: &str) -> Result<[u8; 32], std::io::Error>;
+}
+
+// The same key for every epoch, given with `SessionSettingsBuilder::with_store_encryption_key`.
+struct StaticStoreKey([u8; 32]);
+
+impl SecretProvider for StaticStoreKey {
+    fn store_encryption_key(&self, _epoch: &str) -> Result<[u8; 32], std::io::Error> {
+        Ok(self.0)
+    }
+}
+
+/// The source of the current time of a FIX engine. 
+///
+/// The clock stamps the `SendingTime(52)` of the messages the engine sends, and is the time the
+/// `SendingTime(52)` of the messages it receives is checked against. The session schedule, the
+/// daily sequence reset, the times kept in the store and the dates of its shards follow it as
+/// well. Implement this trait to read a PTP hardware clock, or to fix the time in tests. Defaults
+/// to [`SystemClock`]. 
+///
+/// ```
+/// use chrono::{DateTime, TimeZone, Utc};
+/// use forgefix::Clock;
+///
+/// struct FixedClock(DateTime<Utc>);
+///
+/// impl Clock for FixedClock {
+///     fn now(&self) -> DateTime<Utc> {
+///         self.0
+///     }
+/// }
+///
+/// let clock = FixedClock(Utc.with_ymd_and_hms(2024, 1, 2, 15, 4, 5).unwrap());
+/// assert_eq!(clock.now().to_string(), "2024-01-02 15:04:05 UTC");
+/// ```
+pub trait Clock: Send + Sync {
+    /// The current time. 
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The time of the system, as given by [`Utc::now`]. 
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Hooks called by a FIX engine for the messages it sends and receives. 
+///
+/// Implement this trait to inspect messages, such as to alert on a `Reject<3>`, or to add fields
+/// to the messages sent by the engine, such as custom tags on the `Logon<A>`. Every hook does
+/// nothing by default. The hooks are called from the engine's task, so they should return
+/// quickly. 
+///
+/// Messages resent in answer to a `ResendRequest<2>` are not passed to the hooks again. 
+pub trait SessionCallback: Send + Sync {
+    /// Called with each session message, such as a `Logon<A>` or `Heartbeat<0>`, before it is
+    /// sent. Fields pushed to `builder` are included in the message. 
+    fn on_admin_msg_out(&self, _builder: &mut MessageBuilder) {}
+    /// Called with each application message before it is sent. Fields pushed to `builder` are
+    /// included in the message. 
+    fn on_app_msg_out(&self, _builder: &mut MessageBuilder) {}
+    /// Called with each session message received from the peer, once it was validated. 
+    fn on_admin_msg_in(&self, _msg: &[u8]) {}
+    /// Called with each application message received from the peer, before it is passed to the
+    /// application. 
+    fn on_app_msg_in(&self, _msg: &[u8]) {}
+}
+
+/// An entry of the `NoMsgTypes(384)` repeating group of a `Logon<A>` message. 
+///
+/// Each entry names a message type, and whether it is sent or received by the party sending the
+/// `Logon<A>`. 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogonMsgType {
+    /// The `RefMsgType(372)` of the entry. 
+    pub ref_msg_type: String,
+    /// The `MsgDirection(385)` of the entry, `'S'` for send or `'R'` for receive. 
+    pub msg_direction: char,
 }
 
 /// A builder for easily configuring all the fields of a [`SessionSettings`]
@@ -167,19 +907,73 @@ pub struct SessionSettings {
 /// * sender comp id
 /// * target comp id
 /// * addr
-/// * store path
+/// * store path, unless a memory store is set
 /// * log dir
 #[derive(Default)]
 pub struct SessionSettingsBuilder {
     sender_comp_id: Option<String>,
     target_comp_id: Option<String>,
-    addr: Option<SocketAddr>, 
+    sender_sub_id: Option<String>,
+    target_sub_id: Option<String>,
+    on_behalf_of_comp_id: Option<String>,
+    deliver_to_comp_id: Option<String>,
+    sub_id_validation: Option<bool>,
+    comp_id_mismatch: Option<CompIdMismatch>,
+    endpoints: Vec<Endpoint>, 
     begin_string: Option<String>, 
     epoch: Option<String>,
     store_path: Option<PathBuf>, 
     log_dir: Option<PathBuf>,
     heartbeat_timeout: Option<Duration>,
+    heartbeat_policy: Option<HeartbeatPolicy>,
+    logout_timeout: Option<Duration>,
     start_time: Option<NaiveTime>, 
+    checksum_validation: Option<ChecksumValidation>,
+    max_message_size: Option<u32>,
+    logon_msg_types: Vec<LogonMsgType>,
+    quarantine_log: Option<bool>,
+    log_rotation: Option<LogRotation>,
+    logger: Option<Box<dyn Logger>>,
+    watchdog_test_request: Option<bool>,
+    unmatched_test_req_id: Option<UnmatchedTestReqId>,
+    outgoing_dedup: Option<bool>,
+    outbound_validation: Option<bool>,
+    outbox_capacity: Option<usize>,
+    rate_limit: Option<RateLimit>,
+    live_buffer: Option<usize>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<fix::chaos::Chaos>,
+    ipv6_only: Option<bool>,
+    socket_options: SocketOptions,
+    store_encryption: Option<Arc<dyn SecretProvider>>,
+    store_seal_migration: Option<bool>,
+    store_shard_by_date: Option<bool>,
+    store_incoming: Option<bool>,
+    field_sections: Option<FieldSections>,
+    tolerate_out_of_place_fields: Option<bool>,
+    sequence_too_low_patterns: Option<Vec<Regex>>,
+    sequence_auto_heal: Option<bool>,
+    echo_tags: Vec<u32>,
+    poss_resend_window: Option<usize>,
+    drop_poss_resend_duplicates: Option<bool>,
+    resend_policy: Option<ResendPolicy>,
+    max_resend_window: Option<u32>,
+    memory_store: Option<MemoryStore>,
+    orphan_policy: Option<OrphanPolicy>,
+    schedule: Option<SessionSchedule>,
+    logon_credentials: Option<(String, String)>,
+    additional_logon_fields: Vec<(u32, Vec<u8>)>,
+    additional_header_fields: Vec<(u32, Vec<u8>)>,
+    duplicate_logon: Option<DuplicateLogon>,
+    session_callback: Option<Arc<dyn SessionCallback>>,
+    delivery_filter: Option<DeliveryFilter>,
+    clock: Option<Arc<dyn Clock>>,
+    timestamp_precision: Option<TimestampPrecision>,
+    pre_logon_guard: Option<Duration>,
+    store_vacuum_budget: Option<Duration>,
+    reset_seq_num: Option<bool>,
+    reset_flag_on_initial_logon: Option<bool>,
+    venue_quirks: VenueQuirks,
 }
 
 
@@ -215,16 +1009,128 @@ impl SessionSettingsBuilder {
         self.target_comp_id = Some(target_comp_id.to_string());
     }
 
-    /// The address to initiate a connection to, or accept connections on. 
+    /// The `SenderSubID(50)` that will be included in each message. 
+    pub fn with_sender_sub_id(mut self, sender_sub_id: &str) -> Self {
+        self.set_sender_sub_id(sender_sub_id);
+        self
+    }
+    pub fn set_sender_sub_id(&mut self, sender_sub_id: &str) {
+        self.sender_sub_id = Some(sender_sub_id.to_string());
+    }
+
+    /// The `TargetSubID(57)` that will be included in each message. 
+    pub fn with_target_sub_id(mut self, target_sub_id: &str) -> Self {
+        self.set_target_sub_id(target_sub_id);
+        self
+    }
+    pub fn set_target_sub_id(&mut self, target_sub_id: &str) {
+        self.target_sub_id = Some(target_sub_id.to_string());
+    }
+
+    /// The `OnBehalfOfCompID(115)` that will be included in each message, for a session that
+    /// sends messages on behalf of another firm. 
+    pub fn with_on_behalf_of_comp_id(mut self, on_behalf_of_comp_id: &str) -> Self {
+        self.set_on_behalf_of_comp_id(on_behalf_of_comp_id);
+        self
+    }
+    pub fn set_on_behalf_of_comp_id(&mut self, on_behalf_of_comp_id: &str) {
+        self.on_behalf_of_comp_id = Some(on_behalf_of_comp_id.to_string());
+    }
+
+    /// The `DeliverToCompID(128)` that will be included in each message, for a session whose
+    /// messages are routed by the peer to another firm. 
+    pub fn with_deliver_to_comp_id(mut self, deliver_to_comp_id: &str) -> Self {
+        self.set_deliver_to_comp_id(deliver_to_comp_id);
+        self
+    }
+    pub fn set_deliver_to_comp_id(&mut self, deliver_to_comp_id: &str) {
+        self.deliver_to_comp_id = Some(deliver_to_comp_id.to_string());
+    }
+
+    /// Whether incoming messages are rejected unless they are addressed back to the IDs set
+    /// above: a `SenderSubID(50)` equal to the target sub ID, a `TargetSubID(57)` equal to the
+    /// sender sub ID, a `DeliverToCompID(128)` equal to the on behalf of CompID, and an
+    /// `OnBehalfOfCompID(115)` equal to the deliver to CompID. Only the IDs that are set are
+    /// validated. Defaults to `false`. 
+    pub fn with_sub_id_validation(mut self, sub_id_validation: bool) -> Self {
+        self.set_sub_id_validation(sub_id_validation);
+        self
+    }
+    pub fn set_sub_id_validation(&mut self, sub_id_validation: bool) {
+        self.sub_id_validation = Some(sub_id_validation);
+    }
+
+    /// What the engine does when a message received while logged on has a CompID or sub ID
+    /// other than the one expected. Defaults to [`CompIdMismatch::Logout`]. 
+    pub fn with_comp_id_mismatch(mut self, comp_id_mismatch: CompIdMismatch) -> Self {
+        self.set_comp_id_mismatch(comp_id_mismatch);
+        self
+    }
+    pub fn set_comp_id_mismatch(&mut self, comp_id_mismatch: CompIdMismatch) {
+        self.comp_id_mismatch = Some(comp_id_mismatch);
+    }
+
+    /// The address to initiate a connection to, or accept connections on. Both IPv4 and IPv6
+    /// addresses are supported. 
     pub fn with_socket_addr(mut self, addr: SocketAddr) -> Self {
-        self.addr = Some(addr);
+        self.set_socket_addr(addr);
         self
     }
     pub fn set_socket_addr(&mut self, addr: SocketAddr) {
-        self.addr = Some(addr);
+        self.endpoints = vec![Endpoint::Addr(addr)];
+    }
+
+    /// The addresses of the gateways of the peer, such as a primary and a backup, to initiate a
+    /// connection to. Replaces the socket address. 
+    ///
+    /// An initiator tries the addresses in order each time it connects, including when it
+    /// reconnects, and connects to the first one that accepts. A [`SessionEvent::Connected`] tells
+    /// which one it connected to. An acceptor listens on the first address. 
+    pub fn with_socket_addrs(mut self, addrs: Vec<SocketAddr>) -> Self {
+        self.set_socket_addrs(addrs);
+        self
+    }
+    pub fn set_socket_addrs(&mut self, addrs: Vec<SocketAddr>) {
+        self.endpoints = addrs.into_iter().map(Endpoint::Addr).collect();
+    }
+
+    /// The `host:port` to initiate a connection to, or accept connections on, such as
+    /// `"fix.broker.com:9878"` or `"[::1]:9878"`. Replaces the socket address. 
+    ///
+    /// An initiator resolves the host each time it connects, including when it reconnects, and
+    /// tries every address it resolves to in turn, so an endpoint behind DNS with rotating
+    /// addresses is followed. An acceptor resolves the host once, when it is built, and listens on
+    /// the first address. 
+    ///
+    /// [`build`](SessionSettingsBuilder::build) returns an `Err(ApplicationError::InvalidHost)`
+    /// if `host` has no port. 
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.set_host(host);
+        self
+    }
+    pub fn set_host(&mut self, host: &str) {
+        self.set_hosts(&[host]);
+    }
+
+    /// The `host:port`s of the gateways of the peer, such as a primary and a backup, to initiate
+    /// a connection to. Replaces the socket address. 
+    ///
+    /// Each host is resolved as with [`with_host`](SessionSettingsBuilder::with_host), and the
+    /// hosts are tried in order as with
+    /// [`with_socket_addrs`](SessionSettingsBuilder::with_socket_addrs). 
+    pub fn with_hosts(mut self, hosts: &[&str]) -> Self {
+        self.set_hosts(hosts);
+        self
+    }
+    pub fn set_hosts(&mut self, hosts: &[&str]) {
+        self.endpoints = hosts.iter().map(|host| Endpoint::Host(Arc::from(*host))).collect();
     }
 
     /// The `BeginString(8)` that will be included in each message. 
+    ///
+    /// Only `FIX.4.2`, the default, is supported, as it is the one dictionary the engine decodes
+    /// and validates messages with. [`build`](SessionSettingsBuilder::build) returns an
+    /// `Err(ApplicationError::UnsupportedBeginString)` for any other. 
     pub fn with_begin_string(mut self, begin_string: &str) -> Self {
         self.set_begin_string(begin_string);
         self
@@ -269,70 +1175,838 @@ impl SessionSettingsBuilder {
         self.heartbeat_timeout = Some(hb_timeout);
     }
 
-    /// Build the [`SessionSettings`] struct. 
+    /// When heartbeats and test requests are sent, relative to the heartbeat timeout. Defaults to
+    /// [`HeartbeatPolicy::default`]. 
+    pub fn with_heartbeat_policy(mut self, heartbeat_policy: HeartbeatPolicy) -> Self {
+        self.set_heartbeat_policy(heartbeat_policy);
+        self
+    }
+    pub fn set_heartbeat_policy(&mut self, heartbeat_policy: HeartbeatPolicy) {
+        self.heartbeat_policy = Some(heartbeat_policy);
+    }
+
+    /// How long to wait for the peer to confirm a `Logout<5>` message before disconnecting.
+    /// Defaults to twice the heartbeat timeout, or as many heartbeat timeouts as the
+    /// [`HeartbeatPolicy`] says. 
     ///
-    /// Returns an `Err(ApplicationError::SettingRequired)` if not all of the required fields
-    /// were set.
-    pub fn build(self) -> Result<SessionSettings, ApplicationError> {
-        let sender_comp_id = self.sender_comp_id.ok_or(ApplicationError::SettingRequired("sender_comp_id".to_string()))?;
-        let target_comp_id = self.target_comp_id.ok_or(ApplicationError::SettingRequired("target_comp_id".to_string()))?;
-        let addr = self.addr.ok_or(ApplicationError::SettingRequired("addr".to_string()))?;
-        let store_path = self.store_path.ok_or(ApplicationError::SettingRequired("store_path".to_string()))?;
-        let log_dir = self.log_dir.ok_or(ApplicationError::SettingRequired("log_dir".to_string()))?;
+    /// If the timeout expires, a [`SessionEvent::LogoutTimeout`] is published and the TCP
+    /// connection is closed. 
+    pub fn with_logout_timeout(mut self, logout_timeout: Duration) -> Self {
+        self.set_logout_timeout(logout_timeout);
+        self
+    }
+    pub fn set_logout_timeout(&mut self, logout_timeout: Duration) {
+        self.logout_timeout = Some(logout_timeout);
+    }
 
-        Ok(SessionSettings {
-            engine_type: FixEngineType::Client,
-            begin_string: Arc::new(self.begin_string.unwrap_or(String::from("FIX.4.2"))),
-            epoch: Arc::new(self.epoch.unwrap_or(format!("{}_{}", &sender_comp_id, &target_comp_id))),
-            heartbeat_timeout: self.heartbeat_timeout.unwrap_or(Duration::from_secs(30)),
-            start_time: self.start_time.unwrap_or_default(),
-            sender_comp_id,
-            target_comp_id,
-            addr,
-            store_path,
-            log_dir,
-        })
+    /// How the `CheckSum(10)` of incoming messages is validated. Defaults to
+    /// [`ChecksumValidation::Always`]. 
+    pub fn with_checksum_validation(mut self, checksum_validation: ChecksumValidation) -> Self {
+        self.set_checksum_validation(checksum_validation);
+        self
+    }
+    pub fn set_checksum_validation(&mut self, checksum_validation: ChecksumValidation) {
+        self.checksum_validation = Some(checksum_validation);
     }
-}
 
-impl SessionSettings {
-    /// Creates a new [`SessionSettingsBuilder`]
-    pub fn builder() -> SessionSettingsBuilder {
-        SessionSettingsBuilder::new()    
+    /// The leniencies needed by the peer. Defaults to [`VenueQuirks::strict`]. 
+    ///
+    /// Settings set individually, such as with
+    /// [`with_checksum_validation`](SessionSettingsBuilder::with_checksum_validation), take
+    /// precedence over the quirks. 
+    pub fn with_venue_quirks(mut self, venue_quirks: VenueQuirks) -> Self {
+        self.set_venue_quirks(venue_quirks);
+        self
+    }
+    pub fn set_venue_quirks(&mut self, venue_quirks: VenueQuirks) {
+        self.venue_quirks = venue_quirks;
     }
 
-    fn expected_sender_comp_id(&self) -> &str {
-        &self.target_comp_id
+    /// The `MaxMessageSize(383)` that will be included in the `Logon<A>` message, and the largest
+    /// message, in bytes, accepted from the counterparty. Larger messages are skipped and answered
+    /// with a `Reject<3>`, or skipped as garbled if they have no `MsgSeqNum(34)`. 
+    pub fn with_max_message_size(mut self, max_message_size: u32) -> Self {
+        self.set_max_message_size(max_message_size);
+        self
+    }
+    pub fn set_max_message_size(&mut self, max_message_size: u32) {
+        self.max_message_size = Some(max_message_size);
     }
 
-    fn expected_target_comp_id(&self) -> &str {
-        &self.sender_comp_id
+    /// The `NoMsgTypes(384)` repeating group that will be included in the `Logon<A>` message. 
+    pub fn with_logon_msg_types(mut self, logon_msg_types: Vec<LogonMsgType>) -> Self {
+        self.set_logon_msg_types(logon_msg_types);
+        self
+    }
+    pub fn set_logon_msg_types(&mut self, logon_msg_types: Vec<LogonMsgType>) {
+        self.logon_msg_types = logon_msg_types;
     }
-}
 
-/// A handle on a FIX engine instance. 
-///
-/// The [`FixApplicationHandle`] allows for requesting the basic operations of starting the FIX connection, sending
-/// a message to the peer, and ending the connection. 
-///
-/// The handle offers asynchronous and synchronous APIs for these operations. As well as functions
-/// that return immedietly with a [`oneshot::Receiver`] that will eventually return the result of the
-/// operation. 
-///
-/// The underlying engine could stop running at any moment for a variety of reasons. Only until you
-/// attempt an operation, will you learn the engine has stopped by receiving an
-/// [`ApplicationError::SessionEnded`]. 
-///
-/// [`FixApplicationHandle`] `impl`'s [`Clone`], [`Send`] and [`Sync`] and therefore multiple
-/// copies of the handle can be made and passed to different threads that can all request messages
-/// to be sent. Only one thread has to call [`end`] for the engine to terminate the connection. 
+    /// The `Username(553)` and `Password(554)` that will be included in every `Logon<A>` message
+    /// the engine sends.
+    ///
+    /// The password is sent in clear text, and is written to the message log like the rest of the
+    /// `Logon<A>` message. 
+    pub fn with_logon_credentials(mut self, username: &str, password: &str) -> Self {
+        self.set_logon_credentials(username, password);
+        self
+    }
+    pub fn set_logon_credentials(&mut self, username: &str, password: &str) {
+        self.logon_credentials = Some((username.to_string(), password.to_string()));
+    }
+
+    /// Fields that will be included, in order, at the end of every `Logon<A>` message the engine
+    /// sends, for counterparties that require fields the engine does not set itself. 
+    pub fn with_additional_logon_fields(mut self, additional_logon_fields: Vec<(u32, Vec<u8>)>) -> Self {
+        self.set_additional_logon_fields(additional_logon_fields);
+        self
+    }
+    pub fn set_additional_logon_fields(&mut self, additional_logon_fields: Vec<(u32, Vec<u8>)>) {
+        self.additional_logon_fields = additional_logon_fields;
+    }
+
+    /// A field to include in the header of every outgoing message, session messages and resent
+    /// messages included, such as a proprietary tag the venue requires. Can be called more than
+    /// once to add more fields. 
+    ///
+    /// [`build`](SessionSettingsBuilder::build) returns [`ApplicationError::ReservedHeaderTag`]
+    /// if `tag` is one the engine sets itself, such as `MsgSeqNum(34)` or `SenderCompID(49)`. 
+    pub fn with_additional_header_field(mut self, tag: u32, value: impl Into<Vec<u8>>) -> Self {
+        self.set_additional_header_field(tag, value);
+        self
+    }
+    pub fn set_additional_header_field(&mut self, tag: u32, value: impl Into<Vec<u8>>) {
+        self.additional_header_fields.push((tag, value.into()));
+    }
+
+    /// What the engine does when it receives a `Logon<A>` message while already logged on.
+    /// Defaults to [`DuplicateLogon::Reject`]. 
+    pub fn with_duplicate_logon(mut self, duplicate_logon: DuplicateLogon) -> Self {
+        self.set_duplicate_logon(duplicate_logon);
+        self
+    }
+    pub fn set_duplicate_logon(&mut self, duplicate_logon: DuplicateLogon) {
+        self.duplicate_logon = Some(duplicate_logon);
+    }
+
+    /// The hooks called by the engine for the messages it sends and receives. 
+    pub fn with_session_callback(mut self, session_callback: Arc<dyn SessionCallback>) -> Self {
+        self.set_session_callback(session_callback);
+        self
+    }
+    pub fn set_session_callback(&mut self, session_callback: Arc<dyn SessionCallback>) {
+        self.session_callback = Some(session_callback);
+    }
+
+    /// Which of the application messages received are delivered to the application, such as
+    /// only `ExecutionReport<8>`s. Defaults to [`DeliveryFilter::All`]. 
+    pub fn with_delivery_filter(mut self, delivery_filter: DeliveryFilter) -> Self {
+        self.set_delivery_filter(delivery_filter);
+        self
+    }
+    pub fn set_delivery_filter(&mut self, delivery_filter: DeliveryFilter) {
+        self.delivery_filter = Some(delivery_filter);
+    }
+
+    /// The source of the time stamped in the `SendingTime(52)` of every message sent, and of the
+    /// time the `SendingTime(52)` of every message received is checked against. Defaults to
+    /// [`SystemClock`]. 
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.set_clock(clock);
+        self
+    }
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
+    /// The precision of the `SendingTime(52)` of the messages sent, such as
+    /// [`TimestampPrecision::Micros`]. Defaults to the timestamp precision of the
+    /// [venue quirks](SessionSettingsBuilder::with_venue_quirks), which is
+    /// [`TimestampPrecision::Millis`] unless set. 
+    pub fn with_timestamp_precision(mut self, timestamp_precision: TimestampPrecision) -> Self {
+        self.set_timestamp_precision(timestamp_precision);
+        self
+    }
+    pub fn set_timestamp_precision(&mut self, timestamp_precision: TimestampPrecision) {
+        self.timestamp_precision = Some(timestamp_precision);
+    }
+
+    /// Drop connections accepted by a [`FixApplicationAcceptor`] that do not start with `8=FIX`
+    /// within `timeout`, before an engine is created for them. Connections are dropped without
+    /// an answer, and counted in [`FixApplicationAcceptor::rejected_connections`]. Disabled by
+    /// default. 
+    ///
+    /// Each connection is checked on a task of its own, so a slow connection does not hold up
+    /// the ones accepted after it. 
+    pub fn with_pre_logon_guard(mut self, timeout: Duration) -> Self {
+        self.set_pre_logon_guard(timeout);
+        self
+    }
+    pub fn set_pre_logon_guard(&mut self, timeout: Duration) {
+        self.pre_logon_guard = Some(timeout);
+    }
+
+    /// Run an incremental vacuum of the store for up to `budget` when the engine starts, before
+    /// logging on, to release the free pages left by deleted messages. Disabled by default. 
+    ///
+    /// Only stores created by this version of the engine support incremental vacuum. Older
+    /// stores are converted by the full vacuum run when the engine ends. 
+    pub fn with_store_vacuum_budget(mut self, budget: Duration) -> Self {
+        self.set_store_vacuum_budget(budget);
+        self
+    }
+    pub fn set_store_vacuum_budget(&mut self, budget: Duration) {
+        self.store_vacuum_budget = Some(budget);
+    }
+
+    /// Reset the sequence numbers with `ResetSeqNumFlag(141)=Y` on every `Logon<A>` the engine
+    /// sends, including when it logs on again after [`with_sequence_auto_heal`]. Only used by an
+    /// initiator. Defaults to `false`. 
+    ///
+    /// A reset requested by the peer is always accepted, and written to the store before the
+    /// peer is answered. 
+    ///
+    /// [`with_sequence_auto_heal`]: SessionSettingsBuilder::with_sequence_auto_heal
+    pub fn with_reset_seq_num(mut self, reset_seq_num: bool) -> Self {
+        self.set_reset_seq_num(reset_seq_num);
+        self
+    }
+    pub fn set_reset_seq_num(&mut self, reset_seq_num: bool) {
+        self.reset_seq_num = Some(reset_seq_num);
+    }
+
+    /// Reset the sequence numbers with `ResetSeqNumFlag(141)=Y` on the first `Logon<A>` the
+    /// engine sends, even if the session was already started today. Only used by an initiator.
+    /// Defaults to `false`, in which case the sequence numbers are only reset on the first
+    /// `Logon<A>` after the start time or the start of the schedule. 
+    pub fn with_reset_flag_on_initial_logon(mut self, reset_flag_on_initial_logon: bool) -> Self {
+        self.set_reset_flag_on_initial_logon(reset_flag_on_initial_logon);
+        self
+    }
+    pub fn set_reset_flag_on_initial_logon(&mut self, reset_flag_on_initial_logon: bool) {
+        self.reset_flag_on_initial_logon = Some(reset_flag_on_initial_logon);
+    }
+
+    /// Whether garbled and rejected incoming frames should be written verbatim to a quarantine
+    /// log file in the log dir, along with a timestamp and the reason. Defaults to `false`. 
+    pub fn with_quarantine_log(mut self, quarantine_log: bool) -> Self {
+        self.set_quarantine_log(quarantine_log);
+        self
+    }
+    pub fn set_quarantine_log(&mut self, quarantine_log: bool) {
+        self.quarantine_log = Some(quarantine_log);
+    }
+
+    /// When to start a new message log file, and whether to compress the completed ones. Defaults
+    /// to one file per session that is never rotated. See [`LogRotation`]. 
+    pub fn with_log_rotation(mut self, log_rotation: LogRotation) -> Self {
+        self.set_log_rotation(log_rotation);
+        self
+    }
+    pub fn set_log_rotation(&mut self, log_rotation: LogRotation) {
+        self.log_rotation = Some(log_rotation);
+    }
+
+    /// Record messages with `logger` instead of writing them to files in the log dir, including
+    /// the quarantine log. Clones of the built settings share
+    /// `logger`. See [`fix::log`]. 
+    pub fn with_logger(mut self, logger: Box<dyn Logger>) -> Self {
+        self.set_logger(logger);
+        self
+    }
+    pub fn set_logger(&mut self, logger: Box<dyn Logger>) {
+        self.logger = Some(logger);
+    }
+
+    /// Whether a `TestRequest<1>` should be sent to the peer each time the watchdog registered
+    /// with [`FixApplicationHandle::expect_activity_within`] expires. Defaults to `false`. 
+    pub fn with_watchdog_test_request(mut self, watchdog_test_request: bool) -> Self {
+        self.set_watchdog_test_request(watchdog_test_request);
+        self
+    }
+    pub fn set_watchdog_test_request(&mut self, watchdog_test_request: bool) {
+        self.watchdog_test_request = Some(watchdog_test_request);
+    }
+
+    /// How a `Heartbeat<0>` with an unexpected `TestReqID(112)` is handled. Defaults to
+    /// [`UnmatchedTestReqId::Count`]. 
+    pub fn with_unmatched_test_req_id(mut self, unmatched_test_req_id: UnmatchedTestReqId) -> Self {
+        self.set_unmatched_test_req_id(unmatched_test_req_id);
+        self
+    }
+    pub fn set_unmatched_test_req_id(&mut self, unmatched_test_req_id: UnmatchedTestReqId) {
+        self.unmatched_test_req_id = Some(unmatched_test_req_id);
+    }
+
+    /// Refuse to send a new order, a `NewOrderSingle<D>` or `NewOrderList<E>`, whose `ClOrdID(11)`
+    /// was already sent in this epoch, including before a restart. Defaults to `false`. 
+    ///
+    /// When enabled, the `ClOrdID(11)` of every new order is recorded in the store once the order
+    /// is written, and [`FixApplicationHandle::send_message`] returns
+    /// [`ApplicationError::AlreadySent`] for a repeated ID. A new order that was refused or failed
+    /// can be sent again, and other messages, such as an `OrderStatusRequest<H>`, may repeat the
+    /// ID of an order. This makes replaying a submission file after a crash idempotent. 
+    pub fn with_outgoing_dedup(mut self, outgoing_dedup: bool) -> Self {
+        self.set_outgoing_dedup(outgoing_dedup);
+        self
+    }
+    pub fn set_outgoing_dedup(&mut self, outgoing_dedup: bool) {
+        self.outgoing_dedup = Some(outgoing_dedup);
+    }
+
+    /// Check every message sent through the handles against the FIX 4.2 dictionary, with
+    /// [`MessageBuilder::validate`]. Defaults to `false`. 
+    ///
+    /// When enabled, [`FixApplicationHandle::send_message`] returns
+    /// [`ApplicationError::InvalidMessage`] for a message that is missing a required field, has
+    /// a value that is not allowed, or has a header or trailer field in its body. Nothing is
+    /// written to the connection for it. 
+    pub fn with_outbound_validation(mut self, outbound_validation: bool) -> Self {
+        self.set_outbound_validation(outbound_validation);
+        self
+    }
+    pub fn set_outbound_validation(&mut self, outbound_validation: bool) {
+        self.outbound_validation = Some(outbound_validation);
+    }
+
+    /// Bound the number of messages sent through the handles that the engine has not written
+    /// yet. By default the queue is unbounded. 
+    ///
+    /// When the queue is full, [`FixApplicationHandle::send_message`] returns
+    /// [`ApplicationError::QueueFull`], and [`FixApplicationHandle::reserve`] waits for room. 
+    pub fn with_outbox_capacity(mut self, outbox_capacity: usize) -> Self {
+        self.set_outbox_capacity(outbox_capacity);
+        self
+    }
+    pub fn set_outbox_capacity(&mut self, outbox_capacity: usize) {
+        self.outbox_capacity = Some(outbox_capacity);
+    }
+
+    /// Limit the rate of the application messages sent to the peer, such as to stay under the
+    /// messages per second allowed by a venue. By default the rate is not limited. See
+    /// [`RateLimit`]. 
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.set_rate_limit(rate_limit);
+        self
+    }
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) {
+        self.rate_limit = Some(rate_limit);
+    }
+
+    /// Hold up to `live_buffer` live messages that arrive while the engine waits for the peer to
+    /// resend a gap, and deliver them in sequence once the gap is filled. Defaults to `0`. 
+    ///
+    /// Without it, live messages that arrive during a resend are dropped and requested again
+    /// after the gap is filled. Once the buffer is full, later live messages are requested again
+    /// as before. 
+    pub fn with_live_buffer(mut self, live_buffer: usize) -> Self {
+        self.set_live_buffer(live_buffer);
+        self
+    }
+    pub fn set_live_buffer(&mut self, live_buffer: usize) {
+        self.live_buffer = Some(live_buffer);
+    }
+
+    /// Inject the faults of `chaos`, such as latency and dropped connections, on every
+    /// connection of the engine. Only available with the `chaos` feature. 
+    ///
+    /// See [`fix::chaos`]. 
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: fix::chaos::Chaos) -> Self {
+        self.set_chaos(chaos);
+        self
+    }
+    #[cfg(feature = "chaos")]
+    pub fn set_chaos(&mut self, chaos: fix::chaos::Chaos) {
+        self.chaos = Some(chaos);
+    }
+
+    /// Whether an acceptor listening on an IPv6 address should only accept IPv6 connections.
+    /// Defaults to `false`, so listening on `[::]` accepts both IPv4 and IPv6 connections. 
+    pub fn with_ipv6_only(mut self, ipv6_only: bool) -> Self {
+        self.set_ipv6_only(ipv6_only);
+        self
+    }
+    pub fn set_ipv6_only(&mut self, ipv6_only: bool) {
+        self.ipv6_only = Some(ipv6_only);
+    }
+
+    /// How long an initiator waits for its TCP connection to be established before failing
+    /// with an `ErrorKind::TimedOut` I/O error. Defaults to the timeout of the operating system. 
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.set_connect_timeout(connect_timeout);
+        self
+    }
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.socket_options.connect_timeout = Some(connect_timeout);
+    }
+
+    /// Enable TCP keepalive on the connection, probing an idle connection after `interval` and
+    /// then every `interval` until the peer answers. Disabled by default. 
+    ///
+    /// Keepalive detects a dead connection below the FIX heartbeats, such as through a firewall
+    /// that drops idle connections. Some platforms only support whole seconds. 
+    pub fn with_tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.set_tcp_keepalive(interval);
+        self
+    }
+    pub fn set_tcp_keepalive(&mut self, interval: Duration) {
+        self.socket_options.keepalive = Some(interval);
+    }
+
+    /// The size of the receive buffer of the connection, `SO_RCVBUF`, in bytes. Defaults to the
+    /// size chosen by the operating system, which may round or cap it. 
+    pub fn with_recv_buffer_size(mut self, size: u32) -> Self {
+        self.set_recv_buffer_size(size);
+        self
+    }
+    pub fn set_recv_buffer_size(&mut self, size: u32) {
+        self.socket_options.recv_buffer_size = Some(size);
+    }
+
+    /// The size of the send buffer of the connection, `SO_SNDBUF`, in bytes. Defaults to the size
+    /// chosen by the operating system, which may round or cap it. 
+    pub fn with_send_buffer_size(mut self, size: u32) -> Self {
+        self.set_send_buffer_size(size);
+        self
+    }
+    pub fn set_send_buffer_size(&mut self, size: u32) {
+        self.socket_options.send_buffer_size = Some(size);
+    }
+
+    /// The local address an initiator connects from, such as the address of a specific network
+    /// interface. Defaults to an address chosen by the operating system. 
+    ///
+    /// Use port 0 to let the operating system choose the port, since a fixed port cannot be used
+    /// again right after a disconnection. Ignored by acceptors. 
+    pub fn with_local_addr(mut self, local_addr: SocketAddr) -> Self {
+        self.set_local_addr(local_addr);
+        self
+    }
+    pub fn set_local_addr(&mut self, local_addr: SocketAddr) {
+        self.socket_options.local_addr = Some(local_addr);
+    }
+
+    /// Encrypt messages written to the store with AES-256-GCM, using the key for the epoch given
+    /// by `secret_provider`. Resending messages decrypts them transparently. 
+    ///
+    /// The ClOrdIDs kept for [duplicate detection] are encrypted too, and the sequence numbers are
+    /// sealed with the key, so that the store fails to open if they were changed without it.
+    /// Sequence numbers are still readable without the key. 
+    ///
+    /// Encryption must be enabled starting with a new epoch: a store that holds messages of the
+    /// epoch written unencrypted fails to open with an error that it was not encrypted. 
+    ///
+    /// [duplicate detection]: SessionSettingsBuilder::with_outgoing_dedup
+    pub fn with_store_encryption(mut self, secret_provider: Arc<dyn SecretProvider>) -> Self {
+        self.set_store_encryption(secret_provider);
+        self
+    }
+    pub fn set_store_encryption(&mut self, secret_provider: Arc<dyn SecretProvider>) {
+        self.store_encryption = Some(secret_provider);
+    }
+
+    /// Encrypt the store with the 256-bit AES `key`, for every epoch. See
+    /// [`with_store_encryption`](SessionSettingsBuilder::with_store_encryption), which fetches the
+    /// key from a [`SecretProvider`] instead. 
+    pub fn with_store_encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.set_store_encryption_key(key);
+        self
+    }
+    pub fn set_store_encryption_key(&mut self, key: [u8; 32]) {
+        self.set_store_encryption(Arc::new(StaticStoreKey(key)));
+    }
+
+    /// Seal the sequence numbers of an encrypted store that were written without a seal, and
+    /// trust them as they are. Defaults to `false`. 
+    ///
+    /// Stores encrypted before sequence numbers were sealed have none, and fail to open with
+    /// [store encryption](SessionSettingsBuilder::with_store_encryption) unless this is set for
+    /// the first start after upgrading. Leave it unset afterwards, so that a seal removed without
+    /// the key is caught. Stores that were not encrypted fail to open either way. 
+    pub fn with_store_seal_migration(mut self, store_seal_migration: bool) -> Self {
+        self.set_store_seal_migration(store_seal_migration);
+        self
+    }
+    pub fn set_store_seal_migration(&mut self, store_seal_migration: bool) {
+        self.store_seal_migration = Some(store_seal_migration);
+    }
+
+    /// Write the store to one file per UTC date instead of a single file. Defaults to `false`. 
+    ///
+    /// Each file is named after the store path and its date, so a store path of `store.db` is
+    /// written to files such as `store.20240102.db`. At midnight the engine closes the file of the
+    /// previous date and continues the sequence numbers in a new file. Resend requests that reach
+    /// back past midnight are answered from the files of earlier dates. 
+    pub fn with_store_shard_by_date(mut self, store_shard_by_date: bool) -> Self {
+        self.set_store_shard_by_date(store_shard_by_date);
+        self
+    }
+    pub fn set_store_shard_by_date(&mut self, store_shard_by_date: bool) {
+        self.store_shard_by_date = Some(store_shard_by_date);
+    }
+
+    /// Keep every application message received in the store, with its sequence number and the
+    /// time it was received. Defaults to `true`. 
+    ///
+    /// The messages can be read back with a [`StoreReader`](fix::replay::StoreReader), such as
+    /// for post-trade reconciliation. Latency-sensitive applications that keep their own record
+    /// can turn it off to skip the write. 
+    pub fn with_store_incoming(mut self, store_incoming: bool) -> Self {
+        self.set_store_incoming(store_incoming);
+        self
+    }
+    pub fn set_store_incoming(&mut self, store_incoming: bool) {
+        self.store_incoming = Some(store_incoming);
+    }
+
+    /// Additional tags the engine parses as header or trailer fields of incoming messages, for
+    /// peers that send custom header tags. 
+    pub fn with_field_sections(mut self, field_sections: FieldSections) -> Self {
+        self.set_field_sections(field_sections);
+        self
+    }
+    pub fn set_field_sections(&mut self, field_sections: FieldSections) {
+        self.field_sections = Some(field_sections);
+    }
+
+    /// Read the whole of each incoming message, so header fields that the peer puts after body
+    /// or trailer fields are still found. Defaults to `false`. 
+    ///
+    /// By default, the engine stops reading an application message at its first body field. 
+    pub fn with_tolerate_out_of_place_fields(mut self, tolerate_out_of_place_fields: bool) -> Self {
+        self.set_tolerate_out_of_place_fields(tolerate_out_of_place_fields);
+        self
+    }
+    pub fn set_tolerate_out_of_place_fields(&mut self, tolerate_out_of_place_fields: bool) {
+        self.tolerate_out_of_place_fields = Some(tolerate_out_of_place_fields);
+    }
+
+    /// The patterns used to recognize the `Text(58)` of a `Logout<5>` sent by a peer that
+    /// received a `MsgSeqNum(34)` lower than it expected. 
+    ///
+    /// The first capture group of each pattern must match the `MsgSeqNum(34)` the peer expected.
+    /// When a pattern matches, a [`SessionEvent::OutgoingSequenceTooLow`] is published. Defaults
+    /// to patterns that match texts such as `"MsgSeqNum too low, expecting 105 but received 3"` and
+    /// `"expected sequence number 105"`. 
+    pub fn with_sequence_too_low_patterns(mut self, sequence_too_low_patterns: Vec<Regex>) -> Self {
+        self.set_sequence_too_low_patterns(sequence_too_low_patterns);
+        self
+    }
+    pub fn set_sequence_too_low_patterns(&mut self, sequence_too_low_patterns: Vec<Regex>) {
+        self.sequence_too_low_patterns = Some(sequence_too_low_patterns);
+    }
+
+    /// Allow the engine to correct its outgoing sequence number when the peer logs out because the
+    /// `MsgSeqNum(34)` it received was too low. Defaults to `false`. 
+    ///
+    /// Setting this to `true` approves the correction ahead of time. When the peer sends a
+    /// `Logout<5>` matching one of the [`sequence_too_low_patterns`], the next
+    /// outgoing sequence number is raised to the one the peer expected and written to the store,
+    /// a [`SessionEvent::OutgoingSequenceHealed`] is published, and the engine reconnects and
+    /// sends a new `Logon<A>`. This happens at most once per engine, and only for initiators. 
+    ///
+    /// [`sequence_too_low_patterns`]: SessionSettingsBuilder::with_sequence_too_low_patterns
+    pub fn with_sequence_auto_heal(mut self, sequence_auto_heal: bool) -> Self {
+        self.set_sequence_auto_heal(sequence_auto_heal);
+        self
+    }
+    pub fn set_sequence_auto_heal(&mut self, sequence_auto_heal: bool) {
+        self.sequence_auto_heal = Some(sequence_auto_heal);
+    }
+
+    /// Tags to echo from incoming application messages into the outgoing messages about the same
+    /// order, for venues that require a proprietary tag to be returned on responses. 
+    ///
+    /// The values of these tags are recorded by the `ClOrdID(11)` of each incoming application
+    /// message. When an outgoing message has an `OrigClOrdID(41)` or `ClOrdID(11)` with recorded
+    /// values, each tag the message does not already contain is appended to it. 
+    pub fn with_echo_tags(mut self, echo_tags: Vec<u32>) -> Self {
+        self.set_echo_tags(echo_tags);
+        self
+    }
+    pub fn set_echo_tags(&mut self, echo_tags: Vec<u32>) {
+        self.echo_tags = echo_tags;
+    }
+
+    /// Check the application messages received with `PossResend(97)=Y` against the IDs of the
+    /// last `window` application messages received, and publish a
+    /// [`SessionEvent::PossResendReceived`] for each of them. Off by default. 
+    ///
+    /// A message is identified by its `MsgType(35)` and its `ExecID(17)`, or its `ClOrdID(11)` if
+    /// it has no `ExecID(17)`. A message with neither is never a known duplicate. 
+    pub fn with_poss_resend_window(mut self, window: usize) -> Self {
+        self.set_poss_resend_window(window);
+        self
+    }
+    pub fn set_poss_resend_window(&mut self, window: usize) {
+        self.poss_resend_window = Some(window);
+    }
+
+    /// Do not deliver, or store, the messages with `PossResend(97)=Y` that were already received.
+    /// Defaults to `false`, delivering them. Only applies with a
+    /// [`poss_resend_window`](SessionSettingsBuilder::with_poss_resend_window). 
+    pub fn with_drop_poss_resend_duplicates(mut self, drop_poss_resend_duplicates: bool) -> Self {
+        self.set_drop_poss_resend_duplicates(drop_poss_resend_duplicates);
+        self
+    }
+    pub fn set_drop_poss_resend_duplicates(&mut self, drop_poss_resend_duplicates: bool) {
+        self.drop_poss_resend_duplicates = Some(drop_poss_resend_duplicates);
+    }
+
+    /// How a `ResendRequest<2>` from the peer is answered: by resending the stored application
+    /// messages, or by a gap fill. Defaults to the resend policy of the
+    /// [venue quirks](SessionSettingsBuilder::with_venue_quirks), which is
+    /// [`ResendPolicy::Resend`] unless set. 
+    pub fn with_resend_policy(mut self, resend_policy: ResendPolicy) -> Self {
+        self.set_resend_policy(resend_policy);
+        self
+    }
+    pub fn set_resend_policy(&mut self, resend_policy: ResendPolicy) {
+        self.resend_policy = Some(resend_policy);
+    }
+
+    /// The most messages resent for one `ResendRequest<2>` from the peer. Only the last
+    /// `max_resend_window` messages of the requested range are resent, and the messages before
+    /// them are replaced by a gap fill, so that a request for a whole day does not replay every
+    /// order of the day. Unlimited by default. 
+    pub fn with_max_resend_window(mut self, max_resend_window: u32) -> Self {
+        self.set_max_resend_window(max_resend_window);
+        self
+    }
+    pub fn set_max_resend_window(&mut self, max_resend_window: u32) {
+        self.max_resend_window = Some(max_resend_window);
+    }
+
+    /// Keep sequence numbers and outgoing messages in a [`MemoryStore`] instead of a sqlite file
+    /// at the store path, which is then no longer required. 
+    ///
+    /// Nothing is kept across restarts of the process, so this is meant for tests and ephemeral
+    /// sessions that reset their sequence numbers on every logon. Store encryption and sharding
+    /// do not apply to a memory store. 
+    pub fn with_memory_store(mut self, memory_store: MemoryStore) -> Self {
+        self.set_memory_store(memory_store);
+        self
+    }
+    pub fn set_memory_store(&mut self, memory_store: MemoryStore) {
+        self.memory_store = Some(memory_store);
+    }
+
+    /// What the engine does once every handle to it was dropped without ending the session.
+    /// Defaults to [`OrphanPolicy::KeepAlive`]. 
+    pub fn with_orphan_policy(mut self, orphan_policy: OrphanPolicy) -> Self {
+        self.set_orphan_policy(orphan_policy);
+        self
+    }
+    pub fn set_orphan_policy(&mut self, orphan_policy: OrphanPolicy) {
+        self.orphan_policy = Some(orphan_policy);
+    }
+
+    /// When the FIX session is active. Without a schedule, the session is always active, and a
+    /// new session starts at the start time each day. 
+    ///
+    /// See [`fix::schedule`] for how the engine follows the schedule. The start time is not used
+    /// with a schedule. 
+    pub fn with_schedule(mut self, schedule: SessionSchedule) -> Self {
+        self.set_schedule(schedule);
+        self
+    }
+    pub fn set_schedule(&mut self, schedule: SessionSchedule) {
+        self.schedule = Some(schedule);
+    }
+
+    /// Build the [`SessionSettings`] struct. 
+    ///
+    /// Returns an `Err(ApplicationError::SettingRequired)` if not all of the required fields
+    /// were set.
+    pub fn build(self) -> Result<SessionSettings, ApplicationError> {
+        let begin_string = self.begin_string.unwrap_or(String::from("FIX.4.2"));
+        if begin_string != "FIX.4.2" {
+            return Err(ApplicationError::UnsupportedBeginString(begin_string));
+        }
+        let sender_comp_id = self.sender_comp_id.ok_or(ApplicationError::SettingRequired("sender_comp_id".to_string()))?;
+        let target_comp_id = self.target_comp_id.ok_or(ApplicationError::SettingRequired("target_comp_id".to_string()))?;
+        if self.endpoints.is_empty() {
+            return Err(ApplicationError::SettingRequired("addr".to_string()));
+        }
+        for endpoint in self.endpoints.iter() {
+            if let Endpoint::Host(ref host) = endpoint {
+                if !host
+                    .rsplit_once(':')
+                    .is_some_and(|(name, port)| !name.is_empty() && port.parse::<u16>().is_ok())
+                {
+                    return Err(ApplicationError::InvalidHost(host.to_string()));
+                }
+            }
+        }
+        let store_path = match (self.store_path, &self.memory_store) {
+            (Some(store_path), _) => store_path,
+            (None, Some(_)) => PathBuf::new(),
+            (None, None) => return Err(ApplicationError::SettingRequired("store_path".to_string())),
+        };
+        let log_dir = self.log_dir.ok_or(ApplicationError::SettingRequired("log_dir".to_string()))?;
+        if let Some((tag, _)) = self
+            .additional_header_fields
+            .iter()
+            .find(|(tag, _)| ENGINE_HEADER_TAGS.contains(tag))
+        {
+            return Err(ApplicationError::ReservedHeaderTag(*tag));
+        }
+        let logon_fields = self
+            .logon_credentials
+            .into_iter()
+            .flat_map(|(username, password)| [(553, username.into_bytes()), (554, password.into_bytes())])
+            .chain(self.additional_logon_fields)
+            .collect();
+
+        Ok(SessionSettings {
+            engine_type: FixEngineType::Client,
+            begin_string: Arc::new(begin_string),
+            epoch: Arc::new(self.epoch.unwrap_or(format!("{}_{}", &sender_comp_id, &target_comp_id))),
+            heartbeat_timeout: self.heartbeat_timeout.unwrap_or(Duration::from_secs(30)),
+            heartbeat_policy: self.heartbeat_policy.unwrap_or_default(),
+            logout_timeout: self.logout_timeout,
+            start_time: self.start_time.unwrap_or_default(),
+            checksum_validation: self
+                .checksum_validation
+                .unwrap_or(self.venue_quirks.checksum_validation),
+            max_message_size: self.max_message_size,
+            logon_msg_types: Arc::new(self.logon_msg_types),
+            quarantine_log: self.quarantine_log.unwrap_or(false),
+            log_rotation: self.log_rotation.unwrap_or_default(),
+            logger: self.logger.map(|logger| Arc::new(Mutex::new(logger))),
+            watchdog_test_request: self.watchdog_test_request.unwrap_or(false),
+            unmatched_test_req_id: self.unmatched_test_req_id.unwrap_or_default(),
+            outgoing_dedup: self.outgoing_dedup.unwrap_or(false),
+            outbound_validation: self.outbound_validation.unwrap_or(false),
+            outbox_capacity: self.outbox_capacity,
+            rate_limit: self.rate_limit,
+            live_buffer: self.live_buffer.unwrap_or(0),
+            #[cfg(feature = "chaos")]
+            chaos: self.chaos,
+            ipv6_only: self.ipv6_only.unwrap_or(false),
+            socket_options: self.socket_options,
+            store_encryption: self.store_encryption,
+            store_seal_migration: self.store_seal_migration.unwrap_or(false),
+            store_shard_by_date: self.store_shard_by_date.unwrap_or(false),
+            store_incoming: self.store_incoming.unwrap_or(true),
+            field_sections: Arc::new(self.field_sections.unwrap_or_default()),
+            tolerate_out_of_place_fields: self
+                .tolerate_out_of_place_fields
+                .unwrap_or(self.venue_quirks.tolerate_out_of_place_fields),
+            sequence_too_low_patterns: Arc::new(
+                self.sequence_too_low_patterns
+                    .unwrap_or_else(default_sequence_too_low_patterns),
+            ),
+            sequence_auto_heal: self.sequence_auto_heal.unwrap_or(false),
+            echo_tags: Arc::new(self.echo_tags),
+            poss_resend_window: self.poss_resend_window,
+            drop_poss_resend_duplicates: self.drop_poss_resend_duplicates.unwrap_or(false),
+            memory_store: self.memory_store,
+            orphan_policy: self.orphan_policy.unwrap_or_default(),
+            schedule: self.schedule,
+            logon_fields: Arc::new(logon_fields),
+            duplicate_logon: self.duplicate_logon.unwrap_or_default(),
+            session_callback: self.session_callback,
+            delivery_filter: self.delivery_filter.unwrap_or_default(),
+            pre_logon_guard: self.pre_logon_guard,
+            store_vacuum_budget: self.store_vacuum_budget,
+            reset_seq_num: self.reset_seq_num.unwrap_or(false),
+            reset_flag_on_initial_logon: self.reset_flag_on_initial_logon.unwrap_or(false),
+            timestamp_precision: self.timestamp_precision.unwrap_or(self.venue_quirks.timestamp_precision),
+            clock: self.clock.unwrap_or_else(|| Arc::new(SystemClock)),
+            resend_policy: self.resend_policy.unwrap_or(self.venue_quirks.resend_policy),
+            max_resend_window: self.max_resend_window,
+            header_extras: Arc::new(
+                self.venue_quirks
+                    .header_extras
+                    .into_iter()
+                    .chain(self.additional_header_fields)
+                    .collect(),
+            ),
+            sender_comp_id,
+            target_comp_id,
+            sender_sub_id: self.sender_sub_id,
+            target_sub_id: self.target_sub_id,
+            on_behalf_of_comp_id: self.on_behalf_of_comp_id,
+            deliver_to_comp_id: self.deliver_to_comp_id,
+            comp_id_mismatch: self.comp_id_mismatch.unwrap_or_default(),
+            sub_id_validation: self.sub_id_validation.unwrap_or(false),
+            endpoints: self.endpoints.into(),
+            store_path,
+            log_dir,
+        })
+    }
+}
+
+// The header and trailer fields the engine writes in every message it sends or resends.
+const ENGINE_HEADER_TAGS: [u32; 10] = [8, 9, 10, 34, 35, 43, 49, 52, 56, 122];
+
+fn default_sequence_too_low_patterns() -> Vec<Regex> {
+    vec![
+        Regex::new(r"(?i)too low.*?expect(?:ed|ing)\D*?(\d+)").unwrap(),
+        Regex::new(r"(?i)expected\s+(?:seq(?:uence)?\s*(?:num(?:ber)?)?|msgseqnum)?\s*[:=]?\s*(\d+)")
+            .unwrap(),
+    ]
+}
+
+impl SessionSettings {
+    /// Creates a new [`SessionSettingsBuilder`]
+    pub fn builder() -> SessionSettingsBuilder {
+        SessionSettingsBuilder::new()    
+    }
+
+    /// Settings for a throwaway session between `sender_comp_id` and `target_comp_id`, for
+    /// examples and quick tests. 
+    ///
+    /// Messages are kept in a new [`MemoryStore`], logs are written to a `forgefix` directory
+    /// under [`std::env::temp_dir`], and incoming messages are checked with
+    /// [`VenueQuirks::lenient`]. Every other setting has its default. Nothing needs to be created
+    /// first, and the sequence numbers start again from 1 in every process. 
+    pub fn ephemeral(sender_comp_id: &str, target_comp_id: &str, addr: SocketAddr) -> SessionSettings {
+        SessionSettings::builder()
+            .with_sender_comp_id(sender_comp_id)
+            .with_target_comp_id(target_comp_id)
+            .with_socket_addr(addr)
+            .with_memory_store(MemoryStore::new())
+            .with_log_dir(std::env::temp_dir().join("forgefix"))
+            .with_venue_quirks(VenueQuirks::lenient())
+            .build()
+            .expect("ephemeral settings have every required setting")
+    }
+
+    fn expected_sender_comp_id(&self) -> &str {
+        &self.target_comp_id
+    }
+
+    fn expected_target_comp_id(&self) -> &str {
+        &self.sender_comp_id
+    }
+}
+
+/// A handle on a FIX engine instance. 
+///
+/// The [`FixApplicationHandle`] allows for requesting the basic operations of starting the FIX connection, sending
+/// a message to the peer, and ending the connection. 
+///
+/// The handle offers asynchronous and synchronous APIs for these operations. As well as functions
+/// that return immedietly with a [`oneshot::Receiver`] that will eventually return the result of the
+/// operation. 
+///
+/// The underlying engine could stop running at any moment for a variety of reasons. Only until you
+/// attempt an operation, will you learn the engine has stopped by receiving an
+/// [`ApplicationError::SessionEnded`], which includes the error the engine ended with, if any. 
+///
+/// [`FixApplicationHandle`] `impl`'s [`Clone`], [`Send`] and [`Sync`] and therefore multiple
+/// copies of the handle can be made and passed to different threads that can all request messages
+/// to be sent. Only one thread has to call [`end`] for the engine to terminate the connection. 
 ///
 /// [`oneshot::Receiver`]: https://docs.rs/tokio/latest/tokio/sync/oneshot/struct.Receiver.html
 /// [`end`]: FixApplicationHandle::end
 ///
 /// # Example - Multiple Threads
 ///
-///```no_run
+///```
 /// use forgefix::{
 ///     SessionSettings, FixApplicationInitiator, ApplicationError
 /// }; 
@@ -340,12 +2014,13 @@ impl SessionSettings {
 /// # use anyhow::Result; 
 /// # #[tokio::main]
 /// # async fn main() -> Result<()> {
+/// #    let peer = forgefix::testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
 /// #    let settings = SessionSettings::builder()
 /// #        .with_sender_comp_id("my_id")
 /// #        .with_target_comp_id("peer_id")
-/// #        .with_store_path("./store".into())
-/// #        .with_log_dir("./log".into())
-/// #        .with_socket_addr("127.0.0.1:0".parse().unwrap())
+/// #        .with_store_path(peer.store_path())
+/// #        .with_log_dir(peer.log_dir())
+/// #        .with_socket_addr(peer.addr())
 /// #        .build()?; 
 ///
 /// let (handle, mut receiver) = FixApplicationInitiator::build(settings)?
@@ -353,6 +2028,9 @@ impl SessionSettings {
 ///     .await?; 
 /// receiver.close();
 ///
+/// // start the FIX connection
+/// handle.start_async().await?;
+///
 /// // FixApplicationHandle can be cloned
 /// let handle1 = handle.clone(); 
 /// let handle2 = handle.clone(); 
@@ -368,348 +2046,3520 @@ impl SessionSettings {
 ///     );
 ///     handle1.send_message_async(builder).await
 ///
-///     // ...
-/// }); 
+///     // ...
+/// }); 
+///
+/// // send to multiple tasks...
+/// let h2 = tokio::spawn(async move {
+///     let builder = MessageBuilder::new(
+///         &handle2.begin_string(), 
+///         MsgType::ORDER_SINGLE.into()
+///     );
+///     handle2.send_message_async(builder).await
+/// }); 
+///
+/// // wait for all threads to finish...
+/// let (res1, res2) = tokio::join!(h1, h2); 
+/// res1??;
+/// res2??;
+///     
+/// // end the FIX connection
+/// handle.end_async().await?;
+///  #   Ok(())
+/// # }
+///
+///```
+#[derive(Clone)]
+pub struct FixApplicationHandle {
+    request_sender: mpsc::UnboundedSender<Request>,
+    begin_string: Arc<String>,
+    target_comp_id: Arc<String>,
+    metrics: Arc<Metrics>,
+    event_sender: broadcast::Sender<SessionEvent>,
+    // subscribed before the engine was spawned, and handed to the first call to `session_events`
+    first_events: Arc<Mutex<Option<broadcast::Receiver<SessionEvent>>>>,
+    paused: Arc<AtomicBool>,
+    sent_orders: Arc<SentOrders>,
+    engine_error: Arc<OnceLock<EngineError>>,
+    close_reason: Arc<OnceLock<CloseReason>>,
+    outbox_permits: Option<Arc<Semaphore>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    pending_acks: Arc<PendingAcks>,
+    outbound_validation: bool,
+    negotiated: Arc<Mutex<Option<NegotiatedParams>>>,
+    sequences: Arc<OnceLock<Sequences>>,
+    builder_pool: Arc<BuilderPool>,
+    // the headers the engine adds to every message, to measure messages against the
+    // `MaxMessageSize(383)` of the peer
+    additional_headers: Arc<AdditionalHeaders>,
+}
+
+/// Room for one message in the queue of a FIX engine with an
+/// [`outbox_capacity`](SessionSettingsBuilder::with_outbox_capacity). 
+///
+/// See [`FixApplicationHandle::reserve`]. The room is given back if the permit is dropped
+/// without sending a message.
+pub struct SendPermit {
+    handle: FixApplicationHandle,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl SendPermit {
+    /// Send the message in `builder` using the reserved room. See
+    /// [`FixApplicationHandle::send_message`]. 
+    pub fn send(self, builder: MessageBuilder) -> Result<oneshot::Receiver<bool>, ApplicationError> {
+        self.handle.send_with_permit(builder, self.permit)
+    }
+}
+
+/// Events about the FIX session that are published by a FIX engine. 
+///
+/// See [`FixApplicationHandle::session_events`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// A `Logon<A>` message was received from the peer. 
+    LogonReceived {
+        /// The `HeartBtInt(108)` requested by the peer. 
+        heart_bt_int: Option<u32>,
+        /// The `MaxMessageSize(383)` of the peer, if included. 
+        max_message_size: Option<u32>,
+        /// The `NoMsgTypes(384)` repeating group of the peer. Empty if not included. 
+        msg_types: Vec<LogonMsgType>,
+        /// The `EncryptMethod(98)` of the peer. 
+        encrypt_method: Option<u32>,
+        /// Whether the `Logon<A>` had `ResetSeqNumFlag(141)=Y`. 
+        reset_seq_num: bool,
+    },
+    /// No application message or heartbeat was received from the peer within the `window` of
+    /// the watchdog. See [`FixApplicationHandle::expect_activity_within`]. 
+    StrategyWatchdogExpired {
+        /// The window the watchdog was registered with. 
+        window: Duration,
+    },
+    /// The session of the schedule ends soon, and the engine will log out at its `end`. See
+    /// [`SessionSchedule::with_end_warning`]. 
+    SessionEndingSoon {
+        /// The end of the session. 
+        end: DateTime<Utc>,
+    },
+    /// The peer did not confirm a `Logout<5>` message within the logout `timeout`, and the
+    /// engine closed the TCP connection. See [`SessionSettingsBuilder::with_logout_timeout`]. 
+    LogoutTimeout {
+        /// How long the engine waited for the peer's `Logout<5>`. 
+        timeout: Duration,
+    },
+    /// The sequence numbers were reset to 1 by a `Logon<A>` with `ResetSeqNumFlag(141)=Y`. The
+    /// reset is written to the store before this event is published. 
+    SequencesReset {
+        /// `true` if the peer requested the reset, or `false` if the engine requested it when
+        /// starting a new session. 
+        initiated_by_peer: bool,
+    },
+    /// The peer sent a `Logout<5>` saying the `MsgSeqNum(34)` it received was lower than
+    /// expected. See [`SessionSettingsBuilder::with_sequence_too_low_patterns`]. 
+    OutgoingSequenceTooLow {
+        /// The `MsgSeqNum(34)` the peer expected. 
+        expected: u32,
+        /// The next outgoing `MsgSeqNum(34)` of the engine. 
+        next_outgoing: u32,
+    },
+    /// The next outgoing `MsgSeqNum(34)` was raised to the one expected by the peer, and the
+    /// engine is reconnecting. See [`SessionSettingsBuilder::with_sequence_auto_heal`]. 
+    OutgoingSequenceHealed {
+        /// The next outgoing `MsgSeqNum(34)` before it was raised. 
+        from: u32,
+        /// The next outgoing `MsgSeqNum(34)`. 
+        to: u32,
+    },
+    /// Every [`FixApplicationHandle`] to the engine was dropped without ending the session. See
+    /// [`SessionSettingsBuilder::with_orphan_policy`]. 
+    AllHandlesDropped {
+        /// How long the engine waits before logging out, or `None` if it keeps the session
+        /// running. 
+        logout_after: Option<Duration>,
+    },
+    /// The session was logged on, after the `Logon<A>` messages were exchanged. 
+    LoggedOn,
+    /// The session was logged on, and no longer is, such as after the `Logout<5>` messages were
+    /// exchanged or a session error. 
+    LoggedOut,
+    /// The TCP connection to the peer was established, when the engine starts and each time it
+    /// reconnects. With more than one address to connect to, this tells which one is in use.
+    /// See [`SessionSettingsBuilder::with_socket_addrs`]. 
+    Connected {
+        /// The address of the peer. 
+        peer_addr: SocketAddr,
+    },
+    /// The engine ended, and the TCP connection to the peer was closed. This is the last event
+    /// published by the engine. 
+    Disconnected {
+        /// The error the engine ended with, or `None` if it ended normally. 
+        error: Option<String>,
+    },
+    /// The peer sent a `ResendRequest<2>`. 
+    ResendRequested {
+        /// The `BeginSeqNo(7)` of the request. 
+        begin_seq_no: u32,
+        /// The last `MsgSeqNum(34)` requested, which is the last one sent if `EndSeqNo(16)` was
+        /// `0`. 
+        end_seq_no: u32,
+    },
+    /// The peer sent a `Reject<3>`. 
+    RejectReceived {
+        /// The `RefSeqNum(45)` of the rejected message. 
+        ref_seq_num: Option<u32>,
+        /// The `SessionRejectReason(373)`, if included. 
+        session_reject_reason: Option<u32>,
+        /// The `Text(58)`, if included. 
+        text: Option<String>,
+    },
+    /// The peer sent a `BusinessMessageReject<j>`. Published before the message is delivered to
+    /// the application. Use [`BusinessMessageReject`] to send one. 
+    ///
+    /// [`BusinessMessageReject`]: fix::messages::BusinessMessageReject
+    BusinessRejectReceived {
+        /// The `RefSeqNum(45)` of the rejected message, if included. 
+        ref_seq_num: Option<u32>,
+        /// The `RefMsgType(372)` of the rejected message. 
+        ref_msg_type: Option<String>,
+        /// The `BusinessRejectReason(380)`. 
+        business_reject_reason: Option<u32>,
+        /// The `BusinessRejectRefID(379)`, such as the `ClOrdID(11)` of the rejected message, if
+        /// included. 
+        business_reject_ref_id: Option<String>,
+        /// The `Text(58)`, if included. 
+        text: Option<String>,
+    },
+    /// The store was opened, and vacuumed if [`SessionSettingsBuilder::with_store_vacuum_budget`]
+    /// is set. Published once the engine is started, before logging on. Not published for a
+    /// [`MemoryStore`]. 
+    StoreOpened {
+        /// The size and contents of the store. 
+        stats: StoreStats,
+    },
+    /// The peer sent a `Logon<A>` while the session was already logged on. See
+    /// [`DuplicateLogon`]. 
+    DuplicateLogonReceived {
+        /// The `MsgSeqNum(34)` of the `Logon<A>`. 
+        msg_seq_num: u32,
+        /// Whether the `Logon<A>` had `PossDupFlag(43)=Y`. 
+        poss_dup: bool,
+    },
+    /// The peer sent an application message with `PossResend(97)=Y`, which it may have already
+    /// sent with another `MsgSeqNum(34)`. Published before the message is delivered. See
+    /// [`SessionSettingsBuilder::with_poss_resend_window`]. 
+    PossResendReceived {
+        /// The `MsgSeqNum(34)` of the message. 
+        msg_seq_num: u32,
+        /// Whether a message with the same ID was already received. The message was dropped
+        /// if [`SessionSettingsBuilder::with_drop_poss_resend_duplicates`] is set. 
+        duplicate: bool,
+    },
+}
+
+const SESSION_EVENT_CAPACITY: usize = 64;
+
+/// The next `MsgSeqNum(34)` of each direction of a FIX session. 
+///
+/// See [`FixApplicationHandle::sequence_numbers`] and [`SequenceAdmin`]. 
+///
+/// [`SequenceAdmin`]: fix::admin::SequenceAdmin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SequenceNumbers {
+    /// The `MsgSeqNum(34)` expected on the next message from the peer. 
+    pub next_incoming: u32,
+    /// The `MsgSeqNum(34)` of the next message sent to the peer. 
+    pub next_outgoing: u32,
+}
+
+/// The session parameters in effect, from the last `Logon<A>` received from the peer. 
+///
+/// See [`FixApplicationHandle::negotiated`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NegotiatedParams {
+    /// The heartbeat interval in effect: the `HeartBtInt(108)` of the peer, or the
+    /// [`heartbeat_timeout`](SessionSettingsBuilder::with_heartbeat_timeout) if the peer did not
+    /// send one. 
+    pub heartbeat_interval: Duration,
+    /// The `EncryptMethod(98)` of the peer. 
+    pub encrypt_method: Option<u32>,
+    /// Whether the `Logon<A>` had `ResetSeqNumFlag(141)=Y`. 
+    pub reset_seq_num: bool,
+    /// The `MaxMessageSize(383)` of the peer, if included. 
+    pub max_message_size: Option<u32>,
+}
+
+/// The number of connections dropped by the pre-logon guard of a [`FixApplicationAcceptor`]. 
+///
+/// See [`SessionSettingsBuilder::with_pre_logon_guard`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RejectedConnections {
+    /// Connections that did not start with `8=FIX`, or were closed before sending anything. 
+    pub not_fix: u64,
+    /// Connections that did not send `8=FIX` within the timeout. 
+    pub timed_out: u64,
+    /// Connections to an acceptor with more than one session, whose CompIDs did not match any of
+    /// them. See [`FixApplicationAcceptor::add_session`]. 
+    pub unknown_comp_id: u64,
+    /// Connections to an acceptor with more than one session, for a session whose previous
+    /// connection did not end in time once it was shut down. 
+    pub already_connected: u64,
+}
+
+/// The size and contents of the store of a FIX engine, published when the engine starts. 
+///
+/// See [`SessionEvent::StoreOpened`]. A store sharded by date only reports its current file. 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    /// The size of the store file in bytes, not including its write-ahead log. 
+    pub size_bytes: u64,
+    /// The bytes of free pages in the store file, which are reused by later writes or released
+    /// by a vacuum. 
+    pub free_bytes: u64,
+    /// The bytes released by the incremental vacuum. See
+    /// [`SessionSettingsBuilder::with_store_vacuum_budget`]. 
+    pub vacuumed_bytes: u64,
+    /// The number of rows stored for each epoch. 
+    pub epochs: BTreeMap<String, EpochRows>,
+}
+
+impl StoreStats {
+    /// The fraction of the store file that is free pages, from `0.0` to `1.0`. 
+    pub fn fragmentation(&self) -> f64 {
+        if self.size_bytes == 0 {
+            return 0.0;
+        }
+        self.free_bytes as f64 / self.size_bytes as f64
+    }
+}
+
+/// The number of rows stored for an epoch. See [`StoreStats`]. 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochRows {
+    /// The number of messages sent. 
+    pub outgoing_messages: u64,
+    /// The number of messages received. 
+    pub incoming_messages: u64,
+    /// The number of `ClOrdID(11)`s kept for [`SessionSettingsBuilder::with_outgoing_dedup`]. 
+    pub sent_orders: u64,
+}
+
+/// A snapshot of the counters kept by a FIX engine. 
 ///
-/// // send to multiple tasks...
-/// let h2 = tokio::spawn(async move {
-///     let builder = MessageBuilder::new(
-///         &handle2.begin_string(), 
-///         MsgType::ORDER_SINGLE.into()
-///     );
-///     handle2.send_message_async(builder).await
-/// }); 
+/// See [`FixApplicationHandle::metrics`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionMetrics {
+    /// The number of messages read from the peer. 
+    pub messages_received: u64,
+    /// The number of incoming messages whose checksum was not validated. See
+    /// [`ChecksumValidation`]. 
+    pub checksums_skipped: u64,
+    /// The number of incoming messages that were garbled. 
+    pub garbled_messages_received: u64,
+    /// The number of incoming messages that were rejected. 
+    pub rejected_messages_received: u64,
+    /// The number of `TestRequest<1>` messages answered by a `Heartbeat<0>` with the matching
+    /// `TestReqID(112)`. 
+    pub test_requests_answered: u64,
+    /// The number of `Heartbeat<0>` messages whose `TestReqID(112)` did not match an outstanding
+    /// `TestRequest<1>`. See [`UnmatchedTestReqId`]. 
+    pub unmatched_test_req_ids: u64,
+    /// How long the peer took to answer the most recently answered `TestRequest<1>`. 
+    pub last_test_request_round_trip: Option<Duration>,
+    /// The number of messages read from the peer in the last complete second. 
+    pub messages_received_per_second: u64,
+    /// The largest number of messages read from the peer in any one second. 
+    pub max_messages_received_per_second: u64,
+    /// The number of messages sent to the peer, not counting resent messages. 
+    pub messages_sent: u64,
+    /// How long the most recently sent message took to be serialized and written to the
+    /// connection. 
+    pub last_send_latency: Option<Duration>,
+    /// The longest any sent message took to be serialized and written to the connection. 
+    pub max_send_latency: Option<Duration>,
+    /// The number of `ResendRequest<2>` messages received. 
+    pub resend_requests_received: u64,
+    /// The number of stored messages resent to the peer. 
+    pub messages_resent: u64,
+    /// The number of gap fills sent in place of stored messages that were not resent. 
+    pub gap_fills_sent: u64,
+    /// When the most recent `Heartbeat<0>` was received. 
+    pub last_heartbeat_received: Option<DateTime<Utc>>,
+}
+
+/// How responsive the peer of a FIX engine is. 
 ///
-/// // wait for all threads to finish...
-/// let (res1, res2) = tokio::join!(h1, h2); 
-/// res1??;
-/// res2??;
-///     
-/// // end the FIX connection
-/// handle.end_async().await?;
-///  #   Ok(())
-/// # }
+/// The peer is expected to send a message, at least a `Heartbeat<0>`, every heartbeat interval.
+/// A connection that is degrading usually shows first as a growing
+/// [`since_last_message_received`](SessionHealth::since_last_message_received), or as
+/// `TestRequest<1>` messages taking longer to be answered. 
 ///
-///```
+/// See [`FixApplicationHandle::session_health`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionHealth {
+    /// When the most recent message was read from the peer. 
+    pub last_message_received: Option<DateTime<Utc>>,
+    /// How long ago the most recent message was read from the peer. 
+    pub since_last_message_received: Option<Duration>,
+    /// How long the peer took to answer the most recently answered `TestRequest<1>`. 
+    pub last_test_request_round_trip: Option<Duration>,
+    /// The smoothed round-trip time of `TestRequest<1>` messages, in which each answer counts for
+    /// an eighth. 
+    pub average_test_request_round_trip: Option<Duration>,
+    /// The longest the peer took to answer a `TestRequest<1>`. 
+    pub max_test_request_round_trip: Option<Duration>,
+    /// The heartbeat interval negotiated with the peer, or `None` if no `Logon<A>` was received
+    /// yet. 
+    pub heartbeat_interval: Option<Duration>,
+}
+
+impl SessionHealth {
+    /// Whether the peer has been silent for longer than the heartbeat interval. The engine sends
+    /// a `TestRequest<1>` soon after, and disconnects if it is not answered. 
+    pub fn is_overdue(&self) -> bool {
+        match (self.since_last_message_received, self.heartbeat_interval) {
+            (Some(since), Some(heartbeat_interval)) => since > heartbeat_interval,
+            _ => false,
+        }
+    }
+}
+
+impl FixApplicationHandle {
+    /// Send a request to the engine to start the connection and return immediately. 
+    ///
+    /// The receiver will eventually yield `true` if a connection was successfully established, or
+    /// `false` othersize. 
+    pub fn start(&self) -> Result<oneshot::Receiver<bool>, ApplicationError> {
+        if self.request_sender.is_closed() {
+            return Err(self.session_ended());
+        }
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        let logon_request = Request::Logon { resp_sender };
+        let _ = self.request_sender.send(logon_request);
+        Ok(resp_receiver)
+    }
+    /// Send a request to the engine to start the connection and await asynchronously. 
+    pub async fn start_async(&self) -> Result<(), ApplicationError> {
+        let resp_sender = self.start()?;
+        if Ok(true) != resp_sender.await {
+            return Err(ApplicationError::LogonFailed);
+        }
+        Ok(())
+    }
+    /// Send a request to the engine to start a connection, and block until a result is returned. 
+    pub fn start_sync(&self) -> Result<(), ApplicationError> {
+        let resp_receiver = self.start()?; 
+        if Ok(true) != resp_receiver.blocking_recv() {
+            return Err(ApplicationError::LogonFailed);
+        }
+        Ok(())
+    }
+    
+
+    /// Send a request to the engine to send the message in the [`MessageBuilder`] to the peer, and return immediately. 
+    ///
+    /// If the request was successfully sent to the engine, a [`oneshot::Receiver`] will be
+    /// returned. 
+    ///
+    /// The receiver will yield `true` once the message has successfully sent over the TCP
+    /// connection. It will yeild `false` if a message cannot be sent. 
+    ///
+    /// [`oneshot::Receiver`]: https://docs.rs/tokio/latest/tokio/sync/oneshot/struct.Receiver.html
+    ///
+    /// Returns an `Err(ApplicationError::QueueFull)` if the queue of the engine is full. See
+    /// [`SessionSettingsBuilder::with_outbox_capacity`]. Returns an
+    /// `Err(ApplicationError::RateLimited)` if the message is over a [`RateLimit`] that rejects
+    /// messages, and an `Err(ApplicationError::MessageTooLarge)` if an application message is
+    /// longer than the `MaxMessageSize(383)` the peer sent in its `Logon<A>`. 
+    pub fn send_message(
+        &self,
+        builder: MessageBuilder,
+    ) -> Result<oneshot::Receiver<bool>, ApplicationError> {
+        if self.request_sender.is_closed() {
+            return Err(self.session_ended());
+        }
+        let permit = match &self.outbox_permits {
+            Some(permits) => Some(
+                Arc::clone(permits)
+                    .try_acquire_owned()
+                    .map_err(|_| ApplicationError::QueueFull)?,
+            ),
+            None => None,
+        };
+        self.send_with_permit(builder, permit)
+    }
+    /// Wait until there is room in the queue of the engine for one more message, and reserve it. 
+    ///
+    /// Returns immediately if the queue is unbounded. See
+    /// [`SessionSettingsBuilder::with_outbox_capacity`]. 
+    pub async fn reserve(&self) -> Result<SendPermit, ApplicationError> {
+        if self.request_sender.is_closed() {
+            return Err(self.session_ended());
+        }
+        let permit = match &self.outbox_permits {
+            Some(permits) => Some(
+                Arc::clone(permits)
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| self.session_ended())?,
+            ),
+            None => None,
+        };
+        Ok(SendPermit {
+            handle: self.clone(),
+            permit,
+        })
+    }
+    fn send_with_permit(
+        &self,
+        builder: MessageBuilder,
+        room: Option<OwnedSemaphorePermit>,
+    ) -> Result<oneshot::Receiver<bool>, ApplicationError> {
+        if self.request_sender.is_closed() {
+            return Err(self.session_ended());
+        }
+        if self.is_paused() && !is_session_message(builder.msg_type()) {
+            return Err(ApplicationError::SessionPaused);
+        }
+        if self.outbound_validation {
+            builder.validate().map_err(ApplicationError::InvalidMessage)?;
+        }
+        if !is_session_message(builder.msg_type()) {
+            self.check_message_size(&builder)?;
+        }
+        // the ID is given back if the order is refused from here on, or never written
+        let mut cl_ord_ids = None;
+        if self.sent_orders.enabled() && is_new_order(builder.msg_type()) {
+            if let Some(cl_ord_id) = builder.field(Tags::ClOrdID.into()) {
+                cl_ord_ids = Some(self.sent_orders.reserve(&[cl_ord_id]).ok_or(ApplicationError::AlreadySent)?);
+            }
+        }
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            if !is_session_message(builder.msg_type()) && !rate_limiter.try_acquire(1) {
+                return Err(ApplicationError::RateLimited);
+            }
+        }
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        let send_message_request = Request::SendMessage {
+            resp_sender,
+            builder,
+            permit: Some(OutboxPermit::new(room, cl_ord_ids)),
+        };
+        let _ = self.request_sender.send(send_message_request);
+        Ok(resp_receiver)
+    }
+    /// Send a request to the engine to send the message in `builder` and await asynchronously. 
+    pub async fn send_message_async(
+        &self,
+        builder: MessageBuilder,
+    ) -> Result<(), ApplicationError> {
+        let resp_sender = self.send_message(builder)?;
+        if Ok(true) != resp_sender.await {
+            return Err(ApplicationError::SendMessageFailed);
+        }
+        Ok(())
+    }
+    /// Send the message in `builder`, and await the peer's answer to it. 
+    ///
+    /// Where [`send_message_async`] returns once the message was written to the TCP stream, this
+    /// returns the first `ExecutionReport<8>` or `OrderCancelReject<9>` received with the same
+    /// `ClOrdID(11)` as the message, such as the acknowledgment of a new order or of a cancel
+    /// request. The answer is still delivered to the application message receiver as well. 
+    ///
+    /// Returns an `Err(ApplicationError::NoClOrdId)` if the message has no `ClOrdID(11)`, and an
+    /// `Err(ApplicationError::AckTimedOut)` if no answer was received within `timeout`. The
+    /// message may still have been sent in that case. 
+    ///
+    /// [`send_message_async`]: FixApplicationHandle::send_message_async
+    pub async fn send_message_acked(
+        &self,
+        builder: MessageBuilder,
+        timeout: Duration,
+    ) -> Result<Arc<MsgBuf>, ApplicationError> {
+        let cl_ord_id = builder
+            .field(Tags::ClOrdID.into())
+            .ok_or(ApplicationError::NoClOrdId)?
+            .to_vec();
+        // wait before sending, so a fast answer is not missed
+        let ack = self.pending_acks.register(&cl_ord_id);
+        let result = async {
+            self.send_message_async(builder).await?;
+            match tokio::time::timeout(timeout, ack).await {
+                Ok(Ok(msg)) => Ok(msg),
+                Ok(Err(_)) => Err(self.session_ended()),
+                Err(_) => Err(ApplicationError::AckTimedOut),
+            }
+        }
+        .await;
+        self.pending_acks.abandon(&cl_ord_id);
+        result
+    }
+    /// Send the messages in `builders`, in order, and await until the last one was written to
+    /// the TCP stream. 
+    ///
+    /// The messages are queued together, so no other message sent from a clone of this handle
+    /// comes between them, and they are written with as few system calls as possible. This is
+    /// faster than sending them one by one when sending many orders at once. 
+    ///
+    /// The batch is refused as a whole: if any message is invalid or was already sent, if the
+    /// queue of the engine does not have room for every message, or if a [`RateLimit`] that
+    /// rejects messages does not allow every message, none is sent. 
+    pub async fn send_batch(&self, builders: Vec<MessageBuilder>) -> Result<(), ApplicationError> {
+        if builders.is_empty() {
+            return Ok(());
+        }
+        if self.request_sender.is_closed() {
+            return Err(self.session_ended());
+        }
+        let is_app_message = |builder: &MessageBuilder| !is_session_message(builder.msg_type());
+        if self.is_paused() && builders.iter().any(is_app_message) {
+            return Err(ApplicationError::SessionPaused);
+        }
+        if self.outbound_validation {
+            for builder in builders.iter() {
+                builder.validate().map_err(ApplicationError::InvalidMessage)?;
+            }
+        }
+        for builder in builders.iter().filter(|builder| is_app_message(builder)) {
+            self.check_message_size(builder)?;
+        }
+        let room = match &self.outbox_permits {
+            Some(permits) => Some(
+                u32::try_from(builders.len())
+                    .ok()
+                    .and_then(|n| Arc::clone(permits).try_acquire_many_owned(n).ok())
+                    .ok_or(ApplicationError::QueueFull)?,
+            ),
+            None => None,
+        };
+        let mut cl_ord_ids = None;
+        if self.sent_orders.enabled() {
+            let batch_ids: Vec<&[u8]> = builders
+                .iter()
+                .filter(|builder| is_new_order(builder.msg_type()))
+                .filter_map(|builder| builder.field(Tags::ClOrdID.into()))
+                .collect();
+            cl_ord_ids = Some(self.sent_orders.reserve(&batch_ids).ok_or(ApplicationError::AlreadySent)?);
+        }
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            let app_messages = builders.iter().filter(|builder| is_app_message(builder)).count();
+            if !u32::try_from(app_messages).is_ok_and(|n| rate_limiter.try_acquire(n)) {
+                return Err(ApplicationError::RateLimited);
+            }
+        }
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        let _ = self.request_sender.send(Request::SendBatch {
+            resp_sender,
+            builders,
+            permit: Some(OutboxPermit::new(room, cl_ord_ids)),
+        });
+        if Ok(true) != resp_receiver.await {
+            return Err(ApplicationError::SendMessageFailed);
+        }
+        Ok(())
+    }
+    /// Send a request to the engine to send the message in `builder` and block until a result is
+    /// returned.
+    pub fn send_message_sync(
+        &self,
+        builder: MessageBuilder,
+    ) -> Result<(), ApplicationError> {
+        let resp_receiver = self.send_message(builder)?;
+        if Ok(true) != resp_receiver.blocking_recv() {
+            return Err(ApplicationError::SendMessageFailed);
+        }
+        Ok(())
+    }
+    /// Await until every message sent so far through this handle and its clones was written to
+    /// the TCP stream, and also committed to the store if `until` is [`Flush::Stored`]. 
+    ///
+    /// This marks a checkpoint for a batch of messages, without awaiting each of them. Messages
+    /// sent after the flush was requested are not waited for. Returns an
+    /// `Err(ApplicationError::SendMessageFailed)` if a message waited for was not sent, such as
+    /// when the connection dropped first. 
+    pub async fn flush_async(&self, until: Flush) -> Result<(), ApplicationError> {
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        self.request_sender
+            .send(Request::Flush { resp_sender, until })
+            .map_err(|_| self.session_ended())?;
+        match resp_receiver.await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ApplicationError::SendMessageFailed),
+            Err(_) => Err(self.session_ended()),
+        }
+    }
+
+    /// Send a request to the engine to end the FIX connection, and return immediately. 
+    ///
+    /// If the request was successfully send to the engine, a [`oneshot::Receiver`] will be
+    /// returned. 
+    ///
+    /// The receiver will yield `true` is the FIX connection is over, and ended without any issues.
+    /// Otherwise it will be `false`. 
+    ///
+    /// Ending is idempotent: if a `Logout<5>` is already queued or sent, such as by an earlier
+    /// call on any clone of this handle, no other one is sent, and the receiver yields the
+    /// outcome of that logout. 
+    ///
+    /// [`oneshot::Receiver`]: https://docs.rs/tokio/latest/tokio/sync/oneshot/struct.Receiver.html
+    pub fn end(&self) -> Result<oneshot::Receiver<bool>, ApplicationError> {
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        let logout_request = Request::Logout { resp_sender };
+        let _ = self.request_sender.send(logout_request);
+        Ok(resp_receiver)
+    }
+    /// Send a request to the engine to end the FIX connection, and await asynchronously. 
+    pub async fn end_async(&self) -> Result<(), ApplicationError> {
+        let resp_sender = self.end()?;
+        if Ok(true) != resp_sender.await {
+            return Err(ApplicationError::LogoutFailed);
+        }
+        Ok(())
+    }
+    /// Send a request to the engine to end the FIX connection, and block until a result is
+    /// returned.
+    pub fn end_sync(&self) -> Result<(), ApplicationError> {
+        let resp_receiver = self.end()?;
+        if Ok(true) != resp_receiver.blocking_recv() {
+            return Err(ApplicationError::LogoutFailed);
+        }
+        Ok(())
+    }
+
+    /// End the FIX connection after handling the application messages still queued as `drain`
+    /// says, and await asynchronously until the TCP connection is closed. 
+    ///
+    /// The `Logout<5>` is sent once the queue is drained, and the engine waits for the peer to
+    /// confirm it, as with [`end`]. If the connection is not closed within `timeout`, it is
+    /// closed anyway, and every message still queued is dropped. Messages sent after the
+    /// shutdown was requested are dropped. The returned [`ShutdownReport`] counts the dropped
+    /// messages. 
+    ///
+    /// [`end`]: FixApplicationHandle::end
+    pub async fn shutdown(&self, drain: Drain, timeout: Duration) -> Result<ShutdownReport, ApplicationError> {
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        self.request_sender
+            .send(Request::Shutdown { resp_sender, drain, timeout })
+            .map_err(|_| self.session_ended())?;
+        resp_receiver.await.map_err(|_| self.session_ended())
+    }
+
+    /// Stop sending application messages, without ending the FIX session. 
+    ///
+    /// While paused, [`send_message`] refuses application messages with
+    /// [`ApplicationError::SessionPaused`]. Heartbeats, test requests, resends and other session
+    /// messages continue as normal. Messages that were sent before pausing are not recalled. 
+    ///
+    /// Pausing applies to every clone of this handle. 
+    ///
+    /// [`send_message`]: FixApplicationHandle::send_message
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+    /// Allow application messages to be sent again after a [`pause`]. 
+    ///
+    /// [`pause`]: FixApplicationHandle::pause
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+    /// Whether sending application messages is paused. 
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Get the `BeginString(8)` of this FIX Session. Should generally be `"FIX.4.2"`. 
+    pub fn begin_string(&self) -> Arc<String> {
+        Arc::clone(&self.begin_string)
+    }
+
+    /// Get a [`MessageBuilder`] for a message of `msg_type` in this FIX session, whose buffer is
+    /// reused from the messages already sent. 
+    ///
+    /// The buffer goes back to a pool shared by the clones of this handle when the builder is
+    /// dropped, which is once the engine wrote the message, so that a high rate of orders does
+    /// not allocate a new buffer for each. 
+    ///
+    /// ```no_run
+    /// # use forgefix::{FixApplicationHandle, ApplicationError};
+    /// # use forgefix::fix::generated::{MsgType, Tags};
+    /// # async fn send(handle: &FixApplicationHandle) -> Result<(), ApplicationError> {
+    /// let order = handle
+    ///     .builder(MsgType::ORDER_SINGLE.into())
+    ///     .push(Tags::ClOrdID, b"order-1");
+    /// handle.send_message_async(order).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(&self, msg_type: char) -> MessageBuilder {
+        MessageBuilder::pooled(&self.begin_string, msg_type, &self.builder_pool)
+    }
+
+    /// Get the `TargetCompID(56)` of this FIX session, the `CompID` of the peer. 
+    ///
+    /// Tells apart the sessions accepted by a [`FixApplicationAcceptor`] with more than one
+    /// session. 
+    pub fn target_comp_id(&self) -> Arc<String> {
+        Arc::clone(&self.target_comp_id)
+    }
+
+    /// Get a snapshot of the engine's [`SessionMetrics`]. 
+    pub fn metrics(&self) -> SessionMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Get a snapshot of the [`SessionHealth`] of the connection: how long ago the peer last sent
+    /// a message, and how long it takes to answer `TestRequest<1>` messages. 
+    pub fn session_health(&self) -> SessionHealth {
+        let heartbeat_interval = self
+            .negotiated
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|negotiated| negotiated.heartbeat_interval);
+        self.metrics.health(heartbeat_interval)
+    }
+
+    /// Register a liveness watchdog with the engine, replacing any existing watchdog. 
+    ///
+    /// If no application message or heartbeat is received from the peer within `window`, the
+    /// engine publishes a [`SessionEvent::StrategyWatchdogExpired`], and restarts the window. If
+    /// enabled with [`SessionSettingsBuilder::with_watchdog_test_request`], a `TestRequest<1>` is
+    /// also sent to the peer. 
+    pub fn expect_activity_within(&self, window: Duration) -> Result<(), ApplicationError> {
+        self.set_watchdog(Some(window))
+    }
+    /// Remove the watchdog registered with [`expect_activity_within`]. 
+    ///
+    /// [`expect_activity_within`]: FixApplicationHandle::expect_activity_within
+    pub fn clear_watchdog(&self) -> Result<(), ApplicationError> {
+        self.set_watchdog(None)
+    }
+    /// Send a `SequenceReset<4>` with `GapFillFlag(123)=N`, so that the next message sent to the
+    /// peer has `MsgSeqNum(34)` `new_seq_no`, and await until it is sent. 
+    ///
+    /// This recovers a session whose sequence numbers went wrong on the side of the peer, and
+    /// should only be done when the peer expects it. The reset is sent after the messages already
+    /// queued, and is only sent while logged on, and not while the engine is waiting for the
+    /// peer to resend messages or answer a `TestRequest<1>`. The next outgoing sequence number is
+    /// written to the store once the reset is sent. 
+    ///
+    /// Returns [`ApplicationError::InvalidSequenceNumber`] if `new_seq_no` is not higher than
+    /// the next outgoing sequence number, as sequence numbers cannot be lowered, and
+    /// [`ApplicationError::SendMessageFailed`] if the reset was not sent. 
+    pub async fn reset_sequence(&self, new_seq_no: u32) -> Result<(), ApplicationError> {
+        if self
+            .sequence_numbers()
+            .is_some_and(|numbers| new_seq_no <= numbers.next_outgoing)
+        {
+            return Err(ApplicationError::InvalidSequenceNumber(new_seq_no));
+        }
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        self.request_sender
+            .send(Request::ResetSequence { resp_sender, new_seq_no })
+            .map_err(|_| self.session_ended())?;
+        if Ok(true) != resp_receiver.await {
+            return Err(ApplicationError::SendMessageFailed);
+        }
+        Ok(())
+    }
+
+    /// Send a `ResendRequest<2>` for the messages received from `begin_seq_no` to `end_seq_no`,
+    /// or to the last one received if `end_seq_no` is 0, and await until it is sent. 
+    ///
+    /// The application messages resent by the peer are delivered again, with
+    /// `PossDupFlag(43)=Y`. The request is only sent while logged on, and not while the engine is
+    /// waiting for the peer to resend messages or answer a `TestRequest<1>`. 
+    ///
+    /// Returns [`ApplicationError::InvalidSequenceNumber`] if `begin_seq_no` is 0 or was not
+    /// received yet, or `end_seq_no` is lower than `begin_seq_no`, and
+    /// [`ApplicationError::SendMessageFailed`] if the request was not sent. 
+    pub async fn request_resend(&self, begin_seq_no: u32, end_seq_no: u32) -> Result<(), ApplicationError> {
+        let received = self
+            .sequence_numbers()
+            .is_some_and(|numbers| begin_seq_no < numbers.next_incoming);
+        if begin_seq_no == 0 || !received {
+            return Err(ApplicationError::InvalidSequenceNumber(begin_seq_no));
+        }
+        if end_seq_no != 0 && end_seq_no < begin_seq_no {
+            return Err(ApplicationError::InvalidSequenceNumber(end_seq_no));
+        }
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        self.request_sender
+            .send(Request::Resend {
+                resp_sender,
+                begin_seq_no,
+                end_seq_no,
+            })
+            .map_err(|_| self.session_ended())?;
+        if Ok(true) != resp_receiver.await {
+            return Err(ApplicationError::SendMessageFailed);
+        }
+        Ok(())
+    }
+
+    fn set_watchdog(&self, window: Option<Duration>) -> Result<(), ApplicationError> {
+        self.request_sender
+            .send(Request::Watchdog { window })
+            .map_err(|_| self.session_ended())
+    }
+
+    /// Subscribe to the [`SessionEvent`]s published by the engine. 
+    ///
+    /// The first receiver returned for an engine, by this handle or any of its clones, was
+    /// subscribed before the engine was spawned, so it receives every event from the start of the
+    /// session, even if the session was started before subscribing. Later receivers only receive
+    /// events published after subscribing. If a receiver falls too far behind, the oldest events
+    /// are dropped and the receiver will yield a [`Lagged`] error. 
+    ///
+    /// [`Lagged`]: tokio::sync::broadcast::error::RecvError::Lagged
+    pub fn session_events(&self) -> broadcast::Receiver<SessionEvent> {
+        match self.first_events.lock().unwrap().take() {
+            Some(first_events) => first_events,
+            None => self.event_sender.subscribe(),
+        }
+    }
+
+    /// The error the engine ended with, or `None` if it is still running or ended normally. 
+    ///
+    /// The error is also included in the [`ApplicationError::SessionEnded`] returned once the
+    /// engine ended. 
+    pub fn last_engine_error(&self) -> Option<EngineError> {
+        self.engine_error.get().cloned()
+    }
+
+    /// Why the engine ended, or `None` if it is still running. 
+    ///
+    /// Tells a logon rejected by the peer from a connection closed or reset, a garbled message,
+    /// or a normal logout. An engine that ended with an error has a [`CloseReason::Error`] with
+    /// the same error as [`last_engine_error`](Self::last_engine_error). 
+    pub fn closed_reason(&self) -> Option<CloseReason> {
+        self.close_reason.get().cloned()
+    }
+
+    /// The session parameters negotiated with the peer, or `None` if no `Logon<A>` was received
+    /// yet. 
+    ///
+    /// The parameters are updated by every `Logon<A>` received, and kept after the engine ended. 
+    /// They are also published in [`SessionEvent::LogonReceived`]. 
+    pub fn negotiated(&self) -> Option<NegotiatedParams> {
+        self.negotiated.lock().unwrap().clone()
+    }
+
+    /// The next sequence numbers of the session, or `None` until the engine read them from its
+    /// store. 
+    ///
+    /// The numbers are read from the engine as it runs, and keep their last values after it
+    /// ended. To change them while no engine is running, use a [`SequenceAdmin`]. 
+    ///
+    /// [`SequenceAdmin`]: fix::admin::SequenceAdmin
+    pub fn sequence_numbers(&self) -> Option<SequenceNumbers> {
+        self.sequences.get().map(Sequences::numbers)
+    }
+
+    fn session_ended(&self) -> ApplicationError {
+        ApplicationError::SessionEnded(self.last_engine_error())
+    }
+
+    // Refuses a message longer than the `MaxMessageSize(383)` from the `Logon<A>` of the peer,
+    // measured with the next outgoing `MsgSeqNum(34)`.
+    fn check_message_size(&self, builder: &MessageBuilder) -> Result<(), ApplicationError> {
+        let Some(max_message_size) = self
+            .negotiated
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|negotiated| negotiated.max_message_size)
+        else {
+            return Ok(());
+        };
+        let msg_seq_num = self.sequence_numbers().map_or(1, |numbers| numbers.next_outgoing);
+        let len = builder.encoded_len(msg_seq_num, &self.additional_headers);
+        if len > max_message_size as usize {
+            return Err(ApplicationError::MessageTooLarge { len, max_message_size });
+        }
+        Ok(())
+    }
+}
+
+/// A struct that can initiate the TCP connection to the peer and create a FIX engine instance. 
+pub struct FixApplicationInitiator {
+    settings: SessionSettings,
+    stream_factory: StreamFactory,
+}
+
+impl FixApplicationInitiator {
+    /// Build a `FixApplicationInitiator` that will create a FIX engine using `settings`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        mut settings: SessionSettings,
+    ) -> Result<FixApplicationInitiator, ApplicationError> {
+        settings.engine_type = FixEngineType::Client;
+        let stream_factory = StreamFactory::build(&settings)?;
+        let fix_app_client = FixApplicationInitiator {
+            settings,
+            stream_factory,
+        };
+        Ok(fix_app_client)
+    }
+
+    /// Initiate a TCP connection and start the FIX engine with the current asynchronous runtime.
+    ///
+    /// If the connection is successfully made, a [`FixApplicationHandle`] will be returned, and an
+    /// `UnboundedReceiver<Arc<MsgBuf>>` will be returned. 
+    ///
+    /// The application handle can be used to start the FIX connection, send messages and end the
+    /// connection. 
+    ///
+    /// The receiver is a channel where all incoming, valid application messages can be received.
+    /// If you do not want to use the channel, it is recommended you call [`close`]. 
+    ///
+    /// [`close`]: tokio::sync::mpsc::UnboundedReceiver::close
+    pub async fn initiate(
+        self,
+    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError>
+    {
+        let stream = self.stream_factory.stream().await?;
+        let (app_message_event_sender, app_message_event_receiver) =
+            mpsc::unbounded_channel::<Arc<MsgBuf>>();
+        let (handle, session) = engine(
+            stream,
+            self.settings,
+            Delivery::Shared(app_message_event_sender),
+            Arc::new(Metrics::new()),
+        );
+        tokio::spawn(session);
+
+        Ok((handle, app_message_event_receiver))
+    }
+
+    /// Initiate a TCP connection and start the FIX engine with the current asynchronous runtime,
+    /// delivering incoming messages through an arena of `capacity` bytes. 
+    ///
+    /// Like [`initiate`], but each incoming application message is copied into a ring arena that
+    /// is allocated up front, and received as a [`MsgView`] that borrows from it, instead of
+    /// allocating an `Arc<MsgBuf>` per message. See the [`arena`] module. 
+    ///
+    /// [`initiate`]: FixApplicationInitiator::initiate
+    /// [`MsgView`]: fix::arena::MsgView
+    /// [`arena`]: fix::arena
+    pub async fn initiate_with_arena(
+        self,
+        capacity: usize,
+    ) -> Result<(FixApplicationHandle, ArenaReceiver), ApplicationError> {
+        let stream = self.stream_factory.stream().await?;
+        let (arena_sender, arena_receiver) = fix::arena::channel(capacity);
+        let (handle, session) = engine(
+            stream,
+            self.settings,
+            Delivery::Arena(arena_sender),
+            Arc::new(Metrics::new()),
+        );
+        tokio::spawn(session);
+
+        Ok((handle, arena_receiver))
+    }
+
+    /// Initiate a TCP connection and start the FIX engine that will be driven by `runtime`. 
+    pub fn initiate_with_runtime(
+        self,
+        runtime: tokio::runtime::Runtime, 
+    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError>
+    {
+        let stream = runtime.block_on(self.stream_factory.stream())?;
+        let (app_message_event_sender, app_message_event_receiver) =
+            mpsc::unbounded_channel::<Arc<MsgBuf>>();
+        let (handle, session) = engine(
+            stream,
+            self.settings,
+            Delivery::Shared(app_message_event_sender),
+            Arc::new(Metrics::new()),
+        );
+        std::thread::spawn(move || runtime.block_on(session)); 
+
+        Ok((handle, app_message_event_receiver))
+    }
+
+    /// Initiate a TCP connection, and a runtime will be created internally to drive the engine. 
+    ///
+    /// Without the `multi-thread` feature, this is the same as [`initiate_light`]. 
+    ///
+    /// [`initiate_light`]: FixApplicationInitiator::initiate_light
+    #[cfg(feature = "multi-thread")]
+    pub fn initiate_sync(
+        self
+    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError> 
+    {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?; 
+        self.initiate_with_runtime(runtime)
+    }
+    #[cfg(not(feature = "multi-thread"))]
+    pub fn initiate_sync(
+        self
+    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError> 
+    {
+        self.initiate_light()
+    }
+
+    /// Initiate a TCP connection, and drive the engine with a single-threaded runtime on one
+    /// background thread. 
+    ///
+    /// Intended for small utilities that connect, logon, send a handful of messages and exit,
+    /// using the `_sync` methods of [`FixApplicationHandle`]. Disable the default `multi-thread`
+    /// feature to build without tokio's multi-threaded scheduler. 
+    pub fn initiate_light(
+        self
+    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError> 
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?; 
+        self.initiate_with_runtime(runtime)
+    }
+}
+
+/// A struct that can accept TCP connections, and create a FIX engine instance for each connection. 
+pub struct FixApplicationAcceptor {
+    settings: SessionSettings,
+    sessions: HashMap<(String, String), AcceptorSession>,
+    stream_factory: StreamFactory,
+    comp_id_metrics: HashMap<String, Arc<Metrics>>,
+    rejected_connections: RejectedConnections,
+    // the accepted connections, once checked on tasks of their own
+    checked_sender: mpsc::UnboundedSender<Checked>,
+    checked_receiver: mpsc::UnboundedReceiver<Checked>,
+}
+
+// A session of an acceptor, and the engine running for it.
+struct AcceptorSession {
+    settings: SessionSettings,
+    // the one permit is held by the engine of the session while it runs
+    connected: Arc<Semaphore>,
+    engine: Option<mpsc::WeakUnboundedSender<Request>>,
+}
+
+// An accepted connection, once checked by the pre-logon guard and routed on a task of its own.
+enum Checked {
+    // Passed the pre-logon guard, with the CompIDs of its session if there is more than one.
+    Passed(TcpStream, Option<(String, String)>),
+    // Routed to a session whose previous connection has ended.
+    HandedOver(TcpStream, (String, String), OwnedSemaphorePermit),
+    NotFix,
+    TimedOut,
+    UnknownCompId,
+    AlreadyConnected,
+}
+
+impl Checked {
+    async fn check(stream: TcpStream, guard: Option<Duration>, route: bool) -> Checked {
+        if let Some(timeout) = guard {
+            match fix::guard::sniff(&stream, timeout).await {
+                fix::guard::Sniff::Fix => {}
+                fix::guard::Sniff::NotFix => return Checked::NotFix,
+                fix::guard::Sniff::TimedOut => return Checked::TimedOut,
+            }
+        }
+        if !route {
+            return Checked::Passed(stream, None);
+        }
+        // the peer's SenderCompID(49) is the TargetCompID of its session
+        match fix::guard::peek_comp_ids(&stream, guard.unwrap_or(ROUTING_TIMEOUT)).await {
+            Some((sender_comp_id, target_comp_id)) => Checked::Passed(stream, Some((target_comp_id, sender_comp_id))),
+            None => Checked::UnknownCompId,
+        }
+    }
+
+    // Wait for the engine of the previous connection of a session to end.
+    async fn hand_over(stream: TcpStream, session: (String, String), connected: Arc<Semaphore>) -> Checked {
+        match tokio::time::timeout(ROUTING_TIMEOUT, connected.acquire_owned()).await {
+            Ok(Ok(permit)) => Checked::HandedOver(stream, session, permit),
+            _ => Checked::AlreadyConnected,
+        }
+    }
+}
+
+// How long an acceptor with more than one session waits for the CompIDs of a new connection, if
+// it has no pre-logon guard, and for the engine of the previous connection of a session to end.
+const ROUTING_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl FixApplicationAcceptor {
+    /// Build a `FixApplicationAcceptor` from `settings`. 
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        mut settings: SessionSettings,
+    ) -> Result<FixApplicationAcceptor, ApplicationError> {
+        settings.engine_type = FixEngineType::Server;
+        let stream_factory = StreamFactory::build(&settings)?;
+        let mut sessions = HashMap::new();
+        sessions.insert(comp_ids(&settings), AcceptorSession::new(settings.clone()));
+        let (checked_sender, checked_receiver) = mpsc::unbounded_channel();
+        let fix_app_server = FixApplicationAcceptor {
+            settings,
+            sessions,
+            stream_factory,
+            comp_id_metrics: HashMap::new(),
+            rejected_connections: RejectedConnections::default(),
+            checked_sender,
+            checked_receiver,
+        };
+        Ok(fix_app_server)
+    }
+
+    /// Accept connections for another session, with its own `settings`, such as its store and
+    /// log directory. 
+    ///
+    /// Once a session is added, the acceptor reads the `SenderCompID(49)` and `TargetCompID(56)`
+    /// of the first message of each connection, and creates the engine with the settings of the
+    /// session they match, including the settings the acceptor was built with. A connection that
+    /// matches no session is closed without a reply and counted in [`rejected_connections`]. 
+    ///
+    /// A connection for a session that is already connected replaces the previous one, such as
+    /// when the peer reconnects before the engine noticed that the previous connection was lost:
+    /// the engine of the previous connection is shut down, without sending the messages still
+    /// queued, and the new connection is handed over once it has ended. The socket address of
+    /// `settings` is ignored, and a session with the same CompIDs as an existing one replaces it. 
+    ///
+    /// [`rejected_connections`]: FixApplicationAcceptor::rejected_connections
+    pub fn add_session(&mut self, mut settings: SessionSettings) {
+        settings.engine_type = FixEngineType::Server;
+        self.sessions.insert(comp_ids(&settings), AcceptorSession::new(settings));
+    }
+
+    /// The local address the acceptor is listening on. 
+    ///
+    /// Useful when the acceptor was built with port `0`, and the port was chosen by the OS. 
+    pub fn local_addr(&self) -> Result<SocketAddr, ApplicationError> {
+        Ok(self.stream_factory.local_addr()?)
+    }
+
+    /// Accept an incoming TCP connection and create a FIX engine. 
+    ///
+    /// Returns the handle to the created engine, and a channel to receive all valid, incoming application
+    /// messages. 
+    pub async fn accept(
+        &mut self,
+    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError>
+    {
+        let (stream, settings, connected) = self.routed_stream().await?;
+        let (app_message_event_sender, app_message_event_receiver) =
+            mpsc::unbounded_channel::<Arc<MsgBuf>>();
+        let metrics = self.session_metrics(&settings);
+        let key = comp_ids(&settings);
+        let (handle, session) = engine(
+            stream,
+            settings,
+            Delivery::Shared(app_message_event_sender),
+            metrics,
+        );
+        self.set_engine(&key, &handle);
+        tokio::task::spawn(async move {
+            session.await;
+            drop(connected);
+        });
+
+        Ok((handle, app_message_event_receiver))
+    }
+
+    /// Accept an incoming TCP connection and create a FIX engine that delivers incoming messages
+    /// through an arena of `capacity` bytes. 
+    ///
+    /// See [`FixApplicationInitiator::initiate_with_arena`]. 
+    pub async fn accept_with_arena(
+        &mut self,
+        capacity: usize,
+    ) -> Result<(FixApplicationHandle, ArenaReceiver), ApplicationError> {
+        let (stream, settings, connected) = self.routed_stream().await?;
+        let (arena_sender, arena_receiver) = fix::arena::channel(capacity);
+        let metrics = self.session_metrics(&settings);
+        let key = comp_ids(&settings);
+        let (handle, session) = engine(
+            stream,
+            settings,
+            Delivery::Arena(arena_sender),
+            metrics,
+        );
+        self.set_engine(&key, &handle);
+        tokio::task::spawn(async move {
+            session.await;
+            drop(connected);
+        });
+
+        Ok((handle, arena_receiver))
+    }
+
+    /// Get a snapshot of the [`SessionMetrics`] of every session accepted from the counterparty
+    /// `comp_id`, combined. 
+    ///
+    /// The metrics of each session can be read from its [`FixApplicationHandle`]. 
+    pub fn comp_id_metrics(&self, comp_id: &str) -> Option<SessionMetrics> {
+        self.comp_id_metrics.get(comp_id).map(|metrics| metrics.snapshot())
+    }
+
+    /// Get a snapshot of the combined [`SessionMetrics`] of every counterparty, keyed by its
+    /// `CompID`. 
+    pub fn metrics_by_comp_id(&self) -> HashMap<String, SessionMetrics> {
+        self.comp_id_metrics
+            .iter()
+            .map(|(comp_id, metrics)| (comp_id.clone(), metrics.snapshot()))
+            .collect()
+    }
+
+    /// The number of connections dropped by the pre-logon guard. See
+    /// [`SessionSettingsBuilder::with_pre_logon_guard`]. 
+    pub fn rejected_connections(&self) -> RejectedConnections {
+        self.rejected_connections
+    }
+
+    // Accept the next connection that passes the pre-logon guard, if enabled, and is routed to a
+    // session if there is more than one, with the settings of its session. Connections are checked
+    // on tasks of their own, and are returned in the order they pass. A routed session is
+    // connected until the returned permit is dropped.
+    async fn routed_stream(
+        &mut self,
+    ) -> Result<(TcpStream, SessionSettings, Option<OwnedSemaphorePermit>), ApplicationError> {
+        let guard = self.settings.pre_logon_guard;
+        loop {
+            let checked = tokio::select! {
+                accepted = self.stream_factory.stream() => {
+                    let stream = accepted?;
+                    let route = self.sessions.len() > 1;
+                    if guard.is_none() && !route {
+                        Checked::Passed(stream, None)
+                    } else {
+                        let checked_sender = self.checked_sender.clone();
+                        tokio::spawn(async move {
+                            let _ = checked_sender.send(Checked::check(stream, guard, route).await);
+                        });
+                        continue;
+                    }
+                }
+                Some(checked) = self.checked_receiver.recv() => checked,
+            };
+            match checked {
+                Checked::Passed(stream, None) => {
+                    let session = &self.sessions[&comp_ids(&self.settings)];
+                    return Ok((stream, session.settings.clone(), None));
+                }
+                Checked::Passed(stream, Some(key)) => {
+                    let Some(session) = self.sessions.get(&key) else {
+                        self.rejected_connections.unknown_comp_id += 1;
+                        continue;
+                    };
+                    if let Ok(permit) = Arc::clone(&session.connected).try_acquire_owned() {
+                        return Ok((stream, session.settings.clone(), Some(permit)));
+                    }
+                    // the peer reconnected, so the previous connection is replaced
+                    if let Some(engine) = session.engine.as_ref().and_then(|engine| engine.upgrade()) {
+                        let _ = engine.send(Request::Shutdown {
+                            resp_sender: oneshot::channel().0,
+                            drain: Drain::None,
+                            timeout: Duration::ZERO,
+                        });
+                    }
+                    let connected = Arc::clone(&session.connected);
+                    let checked_sender = self.checked_sender.clone();
+                    tokio::spawn(async move {
+                        let _ = checked_sender.send(Checked::hand_over(stream, key, connected).await);
+                    });
+                }
+                Checked::HandedOver(stream, key, permit) => match self.sessions.get(&key) {
+                    Some(session) => return Ok((stream, session.settings.clone(), Some(permit))),
+                    None => self.rejected_connections.unknown_comp_id += 1,
+                },
+                Checked::NotFix => self.rejected_connections.not_fix += 1,
+                Checked::TimedOut => self.rejected_connections.timed_out += 1,
+                Checked::UnknownCompId => self.rejected_connections.unknown_comp_id += 1,
+                Checked::AlreadyConnected => self.rejected_connections.already_connected += 1,
+            }
+        }
+    }
+
+    // Keep the request sender of the engine of a session, to shut it down if the session
+    // reconnects.
+    fn set_engine(&mut self, key: &(String, String), handle: &FixApplicationHandle) {
+        if let Some(session) = self.sessions.get_mut(key) {
+            session.engine = Some(handle.request_sender.downgrade());
+        }
+    }
+
+    // The metrics of a new session, which also count towards the metrics of its counterparty.
+    fn session_metrics(&mut self, settings: &SessionSettings) -> Arc<Metrics> {
+        let comp_id_metrics = self
+            .comp_id_metrics
+            .entry(settings.target_comp_id.clone())
+            .or_default();
+        Arc::new(Metrics::with_parent(Arc::clone(comp_id_metrics)))
+    }
+}
+
+impl AcceptorSession {
+    fn new(settings: SessionSettings) -> AcceptorSession {
+        AcceptorSession {
+            settings,
+            connected: Arc::new(Semaphore::new(1)),
+            engine: None,
+        }
+    }
+}
+
+// The `SenderCompID` and `TargetCompID` of a session.
+fn comp_ids(settings: &SessionSettings) -> (String, String) {
+    (settings.sender_comp_id.clone(), settings.target_comp_id.clone())
+}
+
+// Create the handle to a new FIX engine, and the future that runs the engine. 
+fn engine(
+    stream: TcpStream,
+    settings: SessionSettings,
+    delivery: Delivery,
+    metrics: Arc<Metrics>,
+) -> (FixApplicationHandle, impl Future<Output = ()>) {
+    let (request_sender, request_receiver) = mpsc::unbounded_channel::<Request>();
+    let begin_string = Arc::clone(&settings.begin_string); 
+    let target_comp_id = Arc::new(settings.target_comp_id.clone());
+    let session_metrics = Arc::clone(&metrics);
+    let (event_sender, first_events) = broadcast::channel(SESSION_EVENT_CAPACITY);
+    let session_event_sender = event_sender.clone();
+    let sent_orders = Arc::new(SentOrders::new(settings.outgoing_dedup));
+    let session_sent_orders = Arc::clone(&sent_orders);
+    let pending_acks = Arc::new(PendingAcks::default());
+    let session_pending_acks = Arc::clone(&pending_acks);
+    let outbound_validation = settings.outbound_validation;
+    let additional_headers = Arc::new(AdditionalHeaders::build(&settings));
+    let negotiated = Arc::new(Mutex::new(None));
+    let session_negotiated = Arc::clone(&negotiated);
+    let sequences = Arc::new(OnceLock::new());
+    let session_sequences = Arc::clone(&sequences);
+    let engine_error = Arc::new(OnceLock::new());
+    let session_engine_error = Arc::clone(&engine_error);
+    let close_reason = Arc::new(OnceLock::new());
+    let session_close_reason = Arc::clone(&close_reason);
+    let outbox_permits = settings
+        .outbox_capacity
+        .map(|capacity| Arc::new(Semaphore::new(capacity)));
+    let rate_limiter = settings
+        .rate_limit
+        .filter(|rate_limit| rate_limit.exceeded == RateLimitExceeded::Reject)
+        .map(|rate_limit| Arc::new(RateLimiter::new(&rate_limit)));
+    let span = tracing::info_span!(
+        "fix_session",
+        sender_comp_id = %settings.sender_comp_id,
+        target_comp_id = %settings.target_comp_id,
+    );
+
+    let session = async move {
+        let result = fix::spin_session(
+            stream,
+            request_receiver,
+            delivery,
+            settings,
+            session_metrics,
+            session_event_sender.clone(),
+            session_sent_orders,
+            session_pending_acks,
+            session_negotiated,
+            session_sequences,
+        )
+        .await;
+        let mut error = None;
+        let reason = match result {
+            Ok(reason) => {
+                tracing::info!(?reason, "engine ended");
+                reason
+            }
+            Err(ref e) => {
+                tracing::error!("engine ended: {e:?}");
+                let engine_error = session_engine_error.get_or_init(|| EngineError::from(e));
+                error = Some(engine_error.message.clone());
+                CloseReason::Error(engine_error.clone())
+            }
+        };
+        let _ = session_close_reason.set(reason);
+        let _ = session_event_sender.send(SessionEvent::Disconnected { error });
+    }
+    .instrument(span);
+
+    let handle = FixApplicationHandle {
+        request_sender,
+        begin_string,
+        target_comp_id,
+        metrics,
+        event_sender,
+        first_events: Arc::new(Mutex::new(Some(first_events))),
+        paused: Default::default(),
+        sent_orders,
+        engine_error,
+        close_reason,
+        outbox_permits,
+        rate_limiter,
+        pending_acks,
+        outbound_validation,
+        negotiated,
+        sequences,
+        builder_pool: Default::default(),
+        additional_headers,
+    };
+
+    (handle, session)
+}
+
 #[derive(Clone)]
-pub struct FixApplicationHandle {
-    request_sender: mpsc::UnboundedSender<Request>,
-    begin_string: Arc<String>,
+enum FixEngineType {
+    Client,
+    Server,
+}
+
+// Where an initiator connects to, or an acceptor listens on.
+#[derive(Clone, Debug)]
+enum Endpoint {
+    Addr(SocketAddr),
+    // a `host:port`, resolved each time it is used
+    Host(Arc<str>),
+}
+
+impl Endpoint {
+    async fn resolve(&self) -> Result<Vec<SocketAddr>, std::io::Error> {
+        match self {
+            Endpoint::Addr(addr) => Ok(vec![*addr]),
+            Endpoint::Host(host) => Ok(tokio::net::lookup_host(&**host).await?.collect()),
+        }
+    }
+
+    // Resolve to the first address, blocking while the host is looked up.
+    fn resolve_first(&self) -> Result<SocketAddr, std::io::Error> {
+        match self {
+            Endpoint::Addr(addr) => Ok(*addr),
+            Endpoint::Host(host) => std::net::ToSocketAddrs::to_socket_addrs(&**host)?
+                .next()
+                .ok_or_else(|| no_address(host)),
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Addr(addr) => write!(f, "{addr}"),
+            Endpoint::Host(host) => write!(f, "{host}"),
+        }
+    }
+}
+
+fn no_address(host: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::NotFound, format!("`{host}` resolved to no address"))
+}
+
+// The options of the TCP connection, set by `SessionSettingsBuilder`.
+#[derive(Clone, Copy, Debug, Default)]
+struct SocketOptions {
+    connect_timeout: Option<Duration>,
+    keepalive: Option<Duration>,
+    recv_buffer_size: Option<u32>,
+    send_buffer_size: Option<u32>,
+    local_addr: Option<SocketAddr>,
+}
+
+impl SocketOptions {
+    // Apply the options shared by initiators and acceptors to `socket`.
+    fn apply(&self, socket: socket2::SockRef<'_>) -> Result<(), std::io::Error> {
+        if let Some(interval) = self.keepalive {
+            let keepalive = socket2::TcpKeepalive::new().with_time(interval);
+            #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+            let keepalive = keepalive.with_interval(interval);
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size as usize)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size as usize)?;
+        }
+        Ok(())
+    }
+
+    async fn connect(&self, addr: SocketAddr) -> Result<TcpStream, std::io::Error> {
+        let socket = if addr.is_ipv6() {
+            TcpSocket::new_v6()?
+        } else {
+            TcpSocket::new_v4()?
+        };
+        self.apply(socket2::SockRef::from(&socket))?;
+        if let Some(local_addr) = self.local_addr {
+            socket.bind(local_addr)?;
+        }
+        match self.connect_timeout {
+            Some(connect_timeout) => tokio::time::timeout(connect_timeout, socket.connect(addr))
+                .await
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "connect timed out"))?,
+            None => socket.connect(addr).await,
+        }
+    }
 }
 
-impl FixApplicationHandle {
-    /// Send a request to the engine to start the connection and return immediately. 
-    ///
-    /// The receiver will eventually yield `true` if a connection was successfully established, or
-    /// `false` othersize. 
-    pub fn start(&self) -> Result<oneshot::Receiver<bool>, ApplicationError> {
-        if self.request_sender.is_closed() {
-            return Err(ApplicationError::SessionEnded);
+enum StreamFactory {
+    Server(TcpListener, SocketOptions),
+    Client(Arc<[Endpoint]>, SocketOptions),
+}
+
+impl StreamFactory {
+    fn build(settings: &SessionSettings) -> Result<Self, std::io::Error> {
+        let options = settings.socket_options;
+        if let FixEngineType::Client = settings.engine_type {
+            return Ok(StreamFactory::Client(Arc::clone(&settings.endpoints), options));
         }
-        let (resp_sender, resp_receiver) = oneshot::channel();
-        let logon_request = Request::Logon { resp_sender };
-        let _ = self.request_sender.send(logon_request);
-        Ok(resp_receiver)
+        let addr = settings.endpoints[0].resolve_first()?;
+        let listener = if addr.is_ipv6() {
+            let socket = socket2::Socket::new(socket2::Domain::IPV6, socket2::Type::STREAM, None)?;
+            socket.set_only_v6(settings.ipv6_only)?;
+            socket.set_nonblocking(true)?;
+            socket.bind(&addr.into())?;
+            socket.listen(1024)?;
+            TcpListener::from_std(socket.into())?
+        } else {
+            let socket = TcpSocket::new_v4()?;
+            socket.bind(addr)?;
+            socket.listen(1024)?
+        };
+        Ok(StreamFactory::Server(listener, options))
     }
-    /// Send a request to the engine to start the connection and await asynchronously. 
-    pub async fn start_async(&self) -> Result<(), ApplicationError> {
-        let resp_sender = self.start()?;
-        if Ok(true) != resp_sender.await {
-            return Err(ApplicationError::LogonFailed);
+    fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        match self {
+            StreamFactory::Server(listener, _) => listener.local_addr(),
+            StreamFactory::Client(endpoints, _) => endpoints[0].resolve_first(),
         }
-        Ok(())
     }
-    /// Send a request to the engine to start a connection, and block until a result is returned. 
-    pub fn start_sync(&self) -> Result<(), ApplicationError> {
-        let resp_receiver = self.start()?; 
-        if Ok(true) != resp_receiver.blocking_recv() {
-            return Err(ApplicationError::LogonFailed);
+    async fn stream(&self) -> Result<TcpStream, std::io::Error> {
+        match self {
+            StreamFactory::Server(listener, options) => {
+                let (stream, _from_addr) = listener.accept().await?;
+                options.apply(socket2::SockRef::from(&stream))?;
+                Ok(stream)
+            }
+            StreamFactory::Client(endpoints, options) => {
+                let mut result = Err(no_address(&endpoints[0].to_string()));
+                for endpoint in endpoints.iter() {
+                    let addrs = match endpoint.resolve().await {
+                        Ok(addrs) if addrs.is_empty() => {
+                            result = Err(no_address(&endpoint.to_string()));
+                            continue;
+                        }
+                        Ok(addrs) => addrs,
+                        Err(e) => {
+                            tracing::warn!(%endpoint, "could not resolve: {e}");
+                            result = Err(e);
+                            continue;
+                        }
+                    };
+                    for addr in addrs {
+                        result = options.connect(addr).await;
+                        if result.is_ok() {
+                            return result;
+                        }
+                        tracing::warn!(%endpoint, %addr, "could not connect");
+                    }
+                }
+                result
+            }
         }
-        Ok(())
     }
-    
+}
 
-    /// Send a request to the engine to send the message in the [`MessageBuilder`] to the peer, and return immediately. 
-    ///
-    /// If the request was successfully sent to the engine, a [`oneshot::Receiver`] will be
-    /// returned. 
-    ///
-    /// The receiver will yield `true` once the message has successfully sent over the TCP
-    /// connection. It will yeild `false` if a message cannot be sent. 
-    ///
-    /// [`oneshot::Receiver`]: https://docs.rs/tokio/latest/tokio/sync/oneshot/struct.Receiver.html
-    pub fn send_message(
-        &self,
-        builder: MessageBuilder,
-    ) -> Result<oneshot::Receiver<bool>, ApplicationError> {
-        if self.request_sender.is_closed() {
-            return Err(ApplicationError::SessionEnded);
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("forgefix-{}-{}", name, std::process::id()))
+    }
+
+    fn test_settings(name: &str, sender: &str, target: &str, addr: SocketAddr) -> SessionSettings {
+        let dir = test_dir(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        SessionSettings::builder()
+            .with_sender_comp_id(sender)
+            .with_target_comp_id(target)
+            .with_socket_addr(addr)
+            .with_store_path(dir.join(format!("{}.db", sender)))
+            .with_log_dir(dir.join("log"))
+            .build()
+            .unwrap()
+    }
+
+    async fn run_loopback_session(name: &str, listen_addr: SocketAddr, connect_ip: std::net::IpAddr) {
+        let mut acceptor =
+            FixApplicationAcceptor::build(test_settings(name, "server", "client", listen_addr)).unwrap();
+        let connect_addr = SocketAddr::new(connect_ip, acceptor.local_addr().unwrap().port());
+
+        let server = tokio::spawn(async move {
+            let (handle, mut receiver) = acceptor.accept().await.unwrap();
+            receiver.close();
+            handle.start_async().await.unwrap();
+            (handle, acceptor)
+        });
+
+        let (client, mut receiver) =
+            FixApplicationInitiator::build(test_settings(name, "client", "server", connect_addr))
+                .unwrap()
+                .initiate()
+                .await
+                .unwrap();
+        receiver.close();
+        client.start_async().await.unwrap();
+        let (server, acceptor) = server.await.unwrap();
+        let comp_id_metrics = acceptor.comp_id_metrics("client").unwrap();
+        assert_eq!(comp_id_metrics.messages_received, server.metrics().messages_received);
+        assert!(comp_id_metrics.messages_received >= 1);
+        assert!(comp_id_metrics.max_messages_received_per_second >= 1);
+        assert!(acceptor.comp_id_metrics("server").is_none());
+        client.end_async().await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir(name));
+    }
+
+    type Session = (FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>);
+
+    // Listen as the `server` of the `client` of `name` on a loopback port, and log on the next
+    // `sessions` connections one after the other. Returns the address to connect to, and the task
+    // that returns the last session.
+    fn spawn_server(name: &str, sessions: usize) -> (SocketAddr, tokio::task::JoinHandle<Session>) {
+        let mut acceptor =
+            FixApplicationAcceptor::build(test_settings(name, "server", "client", "127.0.0.1:0".parse().unwrap()))
+                .unwrap();
+        let addr = acceptor.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut session = None;
+            for _ in 0..sessions {
+                let (handle, receiver) = acceptor.accept().await.unwrap();
+                handle.start_async().await.unwrap();
+                session = Some((handle, receiver));
+            }
+            session.unwrap()
+        });
+        (addr, server)
+    }
+
+    #[tokio::test]
+    async fn test_resend_recovery() {
+        let name = "resend";
+        for run in 0..2 {
+            let (addr, server) = spawn_server(name, 1);
+            let (client, _receiver) = FixApplicationInitiator::build(test_settings(name, "client", "server", addr))
+                .unwrap()
+                .initiate()
+                .await
+                .unwrap();
+            client.start_async().await.unwrap();
+            let (_server, mut receiver) = server.await.unwrap();
+            if run == 0 {
+                let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                    .push(Tags::ClOrdID, b"order-1");
+                client.send_message_async(order).await.unwrap();
+            }
+            let msg = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            let has_field = |field: &[u8]| msg[..].windows(field.len()).any(|w| w == field);
+            assert!(has_field(b"\x0111=order-1\x01"));
+            assert_eq!(has_field(b"\x0143=Y\x01"), run == 1);
+            client.end_async().await.unwrap();
+
+            // The peer forgets the messages it received, and asks for all of them to be resent.
+            rusqlite::Connection::open(test_dir(name).join("server.db"))
+                .unwrap()
+                .execute("UPDATE sequences SET next_incoming = 1", ())
+                .unwrap();
         }
-        let (resp_sender, resp_receiver) = oneshot::channel();
-        let send_message_request = Request::SendMessage {
-            resp_sender,
-            builder,
+        let _ = std::fs::remove_dir_all(test_dir(name));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_auto_heal() {
+        let name = "auto-heal";
+        for run in 0..2 {
+            let mut acceptor =
+                FixApplicationAcceptor::build(test_settings(name, "server", "client", "127.0.0.1:0".parse().unwrap()))
+                    .unwrap();
+            let addr = acceptor.local_addr().unwrap();
+            // The peer confirms the logon of the second run, but then logs out because the
+            // sequence number is too low, so the client connects twice.
+            let server = tokio::spawn(async move {
+                let mut receivers: Vec<mpsc::UnboundedReceiver<Arc<MsgBuf>>> = Vec::new();
+                for _ in 0..=run {
+                    // The engine that logged out must write its sequence numbers to the store
+                    // before the next one reads them.
+                    for receiver in receivers.iter_mut() {
+                        while receiver.recv().await.is_some() {}
+                    }
+                    let (handle, receiver) = acceptor.accept().await.unwrap();
+                    let _ = handle.start_async().await;
+                    receivers.push(receiver);
+                }
+                receivers
+            });
+
+            let mut settings = test_settings(name, "client", "server", addr);
+            settings.sequence_auto_heal = true;
+            let (client, _receiver) = FixApplicationInitiator::build(settings)
+                .unwrap()
+                .initiate()
+                .await
+                .unwrap();
+            let mut events = client.session_events();
+            client.start_async().await.unwrap();
+
+            let mut healed = Vec::new();
+            let mut logons = 0;
+            while logons <= run {
+                let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                match event {
+                    SessionEvent::LogonReceived { .. } => logons += 1,
+                    SessionEvent::OutgoingSequenceTooLow { expected, .. } => healed.push(expected),
+                    SessionEvent::OutgoingSequenceHealed { to, .. } => healed.push(to),
+                    _ => {}
+                }
+            }
+            if run == 0 {
+                assert!(healed.is_empty());
+            } else {
+                assert_eq!(healed, vec![10, 10]);
+            }
+            client.end_async().await.unwrap();
+
+            // Wait for the peer to write its sequence numbers to the store.
+            for mut receiver in server.await.unwrap() {
+                while receiver.recv().await.is_some() {}
+            }
+
+            // The peer expects a higher sequence number than the client will send.
+            rusqlite::Connection::open(test_dir(name).join("server.db"))
+                .unwrap()
+                .execute("UPDATE sequences SET next_incoming = 10", ())
+                .unwrap();
+        }
+        let _ = std::fs::remove_dir_all(test_dir(name));
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_loopback_session() {
+        run_loopback_session("ipv6", "[::1]:0".parse().unwrap(), "::1".parse().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_listener() {
+        run_loopback_session("dual-stack", "[::]:0".parse().unwrap(), "127.0.0.1".parse().unwrap()).await;
+    }
+
+    #[test]
+    fn test_pause_resume() {
+        let (request_sender, mut request_receiver) = mpsc::unbounded_channel();
+        let (event_sender, _) = broadcast::channel(SESSION_EVENT_CAPACITY);
+        let handle = FixApplicationHandle {
+            request_sender,
+            begin_string: Arc::new(String::from("FIX.4.2")),
+            target_comp_id: Arc::new(String::from("peer_id")),
+            metrics: Arc::new(Metrics::new()),
+            event_sender,
+            first_events: Default::default(),
+            paused: Default::default(),
+            sent_orders: Default::default(),
+            engine_error: Default::default(),
+            close_reason: Default::default(),
+            outbox_permits: None,
+            rate_limiter: None,
+            pending_acks: Default::default(),
+            outbound_validation: false,
+            negotiated: Default::default(),
+            sequences: Default::default(),
+            builder_pool: Default::default(),
+            additional_headers: Default::default(),
         };
-        let _ = self.request_sender.send(send_message_request);
-        Ok(resp_receiver)
+        let order = || MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into());
+
+        handle.clone().pause();
+        assert!(handle.is_paused());
+        assert!(matches!(handle.send_message(order()), Err(ApplicationError::SessionPaused)));
+        assert!(handle
+            .send_message(MessageBuilder::new("FIX.4.2", fix::generated::MsgType::HEARTBEAT.into()))
+            .is_ok());
+        assert!(matches!(request_receiver.try_recv(), Ok(Request::SendMessage { .. })));
+        assert!(request_receiver.try_recv().is_err());
+
+        handle.resume();
+        assert!(handle.send_message(order()).is_ok());
+        assert!(matches!(request_receiver.try_recv(), Ok(Request::SendMessage { .. })));
     }
-    /// Send a request to the engine to send the message in `builder` and await asynchronously. 
-    pub async fn send_message_async(
-        &self,
-        builder: MessageBuilder,
-    ) -> Result<(), ApplicationError> {
-        let resp_sender = self.send_message(builder)?;
-        if Ok(true) != resp_sender.await {
-            return Err(ApplicationError::SendMessageFailed);
+
+    #[tokio::test]
+    async fn test_outbox_capacity() {
+        let (request_sender, mut request_receiver) = mpsc::unbounded_channel();
+        let (event_sender, _) = broadcast::channel(SESSION_EVENT_CAPACITY);
+        let handle = FixApplicationHandle {
+            request_sender,
+            begin_string: Arc::new(String::from("FIX.4.2")),
+            target_comp_id: Arc::new(String::from("peer_id")),
+            metrics: Arc::new(Metrics::new()),
+            event_sender,
+            first_events: Default::default(),
+            paused: Default::default(),
+            sent_orders: Default::default(),
+            engine_error: Default::default(),
+            close_reason: Default::default(),
+            outbox_permits: Some(Arc::new(Semaphore::new(2))),
+            rate_limiter: None,
+            pending_acks: Default::default(),
+            outbound_validation: false,
+            negotiated: Default::default(),
+            sequences: Default::default(),
+            builder_pool: Default::default(),
+            additional_headers: Default::default(),
+        };
+        let order = || MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into());
+
+        assert!(handle.send_message(order()).is_ok());
+        let permit = handle.reserve().await.unwrap();
+        assert!(matches!(handle.send_message(order()), Err(ApplicationError::QueueFull)));
+        let reserve = handle.reserve();
+        tokio::pin!(reserve);
+        assert!(poll_once(&mut reserve).await.is_none());
+
+        // the engine takes the first message from the queue
+        let Ok(Request::SendMessage { permit: first, .. }) = request_receiver.try_recv() else {
+            panic!("expected a message");
+        };
+        drop(first);
+        assert!(reserve.await.unwrap().send(order()).is_ok());
+        assert!(matches!(handle.send_message(order()), Err(ApplicationError::QueueFull)));
+        drop(permit);
+        assert!(handle.send_message(order()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_max_message_size() {
+        let (request_sender, mut request_receiver) = mpsc::unbounded_channel();
+        let (event_sender, _) = broadcast::channel(SESSION_EVENT_CAPACITY);
+        let settings = SessionSettings::ephemeral("my_id", "peer_id", "127.0.0.1:0".parse().unwrap());
+        let handle = FixApplicationHandle {
+            request_sender,
+            begin_string: Arc::new(String::from("FIX.4.2")),
+            target_comp_id: Arc::new(String::from("peer_id")),
+            metrics: Arc::new(Metrics::new()),
+            event_sender,
+            first_events: Default::default(),
+            paused: Default::default(),
+            sent_orders: Default::default(),
+            engine_error: Default::default(),
+            close_reason: Default::default(),
+            outbox_permits: None,
+            rate_limiter: None,
+            pending_acks: Default::default(),
+            outbound_validation: false,
+            negotiated: Arc::new(Mutex::new(Some(NegotiatedParams {
+                heartbeat_interval: Duration::from_secs(30),
+                encrypt_method: Some(0),
+                reset_seq_num: false,
+                max_message_size: Some(128),
+            }))),
+            sequences: Default::default(),
+            builder_pool: Default::default(),
+            additional_headers: Arc::new(AdditionalHeaders::build(&settings)),
+        };
+        let order = |text: &str| {
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                .push(Tags::ClOrdID, b"order-1")
+                .push(Tags::Text, text.as_bytes())
+        };
+
+        assert!(handle.send_message(order("small")).is_ok());
+        assert!(matches!(
+            handle.send_message(order(&"x".repeat(128))),
+            Err(ApplicationError::MessageTooLarge { max_message_size: 128, .. })
+        ));
+        assert!(matches!(
+            handle.send_batch(vec![order("small"), order(&"x".repeat(128))]).await,
+            Err(ApplicationError::MessageTooLarge { .. })
+        ));
+        let heartbeat = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::HEARTBEAT.into())
+            .push(Tags::TestReqID, "x".repeat(128).as_bytes());
+        assert!(handle.send_message(heartbeat).is_ok());
+        assert!(matches!(request_receiver.try_recv(), Ok(Request::SendMessage { .. })));
+        assert!(matches!(request_receiver.try_recv(), Ok(Request::SendMessage { .. })));
+        assert!(request_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_rejected() {
+        let mut counterparty = testing::Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let mut settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        settings.max_message_size = Some(512);
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        assert_eq!(testing::msg_type(&counterparty.next_message().await.unwrap()), Some('A'));
+
+        let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1")
+            .push(Tags::Text, "x".repeat(1024).as_bytes());
+        counterparty.send(order);
+        counterparty.send_heartbeat();
+        let test_request = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::TEST_REQUEST.into())
+            .push(Tags::TestReqID, b"after-oversized");
+        counterparty.send(test_request);
+
+        // the oversized message is rejected, and the messages after it are not taken for a gap
+        let reject = counterparty.next_message().await.unwrap();
+        assert_eq!(testing::msg_type(&reject), Some('3'));
+        assert_eq!(testing::field(&reject, Tags::RefSeqNum), Some(&b"2"[..]));
+        let heartbeat = counterparty.next_message().await.unwrap();
+        assert_eq!(testing::msg_type(&heartbeat), Some('0'));
+        assert_eq!(testing::field(&heartbeat, Tags::TestReqID), Some(&b"after-oversized"[..]));
+        assert_eq!(handle.sequence_numbers().unwrap().next_incoming, 5);
+        assert_eq!(handle.metrics().rejected_messages_received, 1);
+        handle.end_async().await.unwrap();
+    }
+
+    // Wait for the next `StrategyWatchdogExpired`, returning its window.
+    async fn watchdog_expired(events: &mut broadcast::Receiver<SessionEvent>) -> Duration {
+        loop {
+            if let SessionEvent::StrategyWatchdogExpired { window } = events.recv().await.unwrap() {
+                return window;
+            }
         }
-        Ok(())
     }
-    /// Send a request to the engine to send the message in `builder` and block until a result is
-    /// returned.
-    pub fn send_message_sync(
-        &self,
-        builder: MessageBuilder,
-    ) -> Result<(), ApplicationError> {
-        let resp_receiver = self.send_message(builder)?;
-        if Ok(true) != resp_receiver.blocking_recv() {
-            return Err(ApplicationError::SendMessageFailed);
+
+    // The next message of `msg_type` received by `counterparty`.
+    async fn next_of_type(counterparty: &mut testing::Counterparty, msg_type: char) -> MsgBuf {
+        loop {
+            let msg = counterparty.next_message().await.unwrap();
+            if testing::msg_type(&msg) == Some(msg_type) {
+                return msg;
+            }
         }
-        Ok(())
     }
 
-    /// Send a request to the engine to end the FIX connection, and return immediately. 
-    ///
-    /// If the request was successfully send to the engine, a [`oneshot::Receiver`] will be
-    /// returned. 
-    ///
-    /// The receiver will yield `true` is the FIX connection is over, and ended without any issues.
-    /// Otherwise it will be `false`. 
-    ///
-    /// [`oneshot::Receiver`]: https://docs.rs/tokio/latest/tokio/sync/oneshot/struct.Receiver.html
-    pub fn end(&self) -> Result<oneshot::Receiver<bool>, ApplicationError> {
-        let (resp_sender, resp_receiver) = oneshot::channel();
-        let logout_request = Request::Logout { resp_sender };
-        let _ = self.request_sender.send(logout_request);
-        Ok(resp_receiver)
+    // Settings that do not write to files, which would let the paused clock of a test run ahead.
+    fn paused_clock_settings(counterparty: &testing::Counterparty) -> SessionSettings {
+        let mut settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        settings.logger = Some(Arc::new(Mutex::new(Box::new(RecordingLogger::default()))));
+        settings
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_strategy_watchdog() {
+        let mut counterparty = testing::Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let mut settings = paused_clock_settings(&counterparty);
+        settings.watchdog_test_request = true;
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        handle.start_async().await.unwrap();
+        assert_eq!(testing::msg_type(&counterparty.next_message().await.unwrap()), Some('A'));
+
+        let window = Duration::from_secs(10);
+        let registered = tokio::time::Instant::now();
+        handle.expect_activity_within(window).unwrap();
+        assert_eq!(watchdog_expired(&mut events).await, window);
+        assert_eq!(registered.elapsed().as_secs(), 10);
+        let test_request = next_of_type(&mut counterparty, '1').await;
+        assert_eq!(testing::field(&test_request, Tags::TestReqID), Some(&b"WATCHDOG-1"[..]));
+
+        // the window restarts at each expiry, and each expiry has its own TestReqID(112)
+        assert_eq!(watchdog_expired(&mut events).await, window);
+        let test_request = next_of_type(&mut counterparty, '1').await;
+        assert_eq!(testing::field(&test_request, Tags::TestReqID), Some(&b"WATCHDOG-2"[..]));
+
+        handle.clear_watchdog().unwrap();
+        handle.end_async().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_strategy_watchdog_reset_by_activity() {
+        let mut counterparty = testing::Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let settings = paused_clock_settings(&counterparty);
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        handle.start_async().await.unwrap();
+        assert_eq!(testing::msg_type(&counterparty.next_message().await.unwrap()), Some('A'));
+
+        let window = Duration::from_secs(10);
+        let registered = tokio::time::Instant::now();
+        handle.expect_activity_within(window).unwrap();
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        let news = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::NEWS.into())
+            .push(Tags::Headline, b"activity");
+        counterparty.send(news);
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event, SessionEvent::StrategyWatchdogExpired { .. }));
+        }
+
+        // the window restarted with the application message
+        assert_eq!(watchdog_expired(&mut events).await, window);
+        assert_eq!(registered.elapsed().as_secs(), 16);
+        // no TestRequest<1> is sent unless enabled
+        counterparty.send_heartbeat();
+        handle.end_async().await.unwrap();
+        while let Some(msg) = counterparty.next_message().await {
+            assert_ne!(testing::msg_type(&msg), Some('1'));
+            if testing::msg_type(&msg) == Some('5') {
+                break;
+            }
+        }
+    }
+
+    // Poll `fut` once, and return its output if it is ready.
+    async fn poll_once<F: Future + Unpin>(fut: &mut F) -> Option<F::Output> {
+        std::future::poll_fn(|cx| {
+            std::task::Poll::Ready(match std::pin::Pin::new(&mut *fut).poll(cx) {
+                std::task::Poll::Ready(output) => Some(output),
+                std::task::Poll::Pending => None,
+            })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_end() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let store = MemoryStore::new();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(store.clone())
+            .build()
+            .unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+
+        let enders: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                tokio::spawn(async move { handle.end_async().await })
+            })
+            .collect();
+        let first = handle.end().unwrap();
+        for ender in enders {
+            assert!(ender.await.unwrap().is_ok());
+        }
+        assert_eq!(first.await, Ok(true));
+
+        let logouts = store
+            .outgoing_messages("my_id_peer_id")
+            .into_iter()
+            .filter(|(_, msg)| msg.windows(6).any(|field| field == b"\x0135=5\x01"))
+            .count();
+        assert_eq!(logouts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_negotiated() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(MemoryStore::new())
+            .with_heartbeat_timeout(Duration::from_secs(7))
+            .build()
+            .unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        assert_eq!(handle.negotiated(), None);
+        handle.start_async().await.unwrap();
+
+        let negotiated = handle.negotiated().unwrap();
+        // the peer sends its own default, which is in effect instead of the 7s requested
+        assert_eq!(negotiated.heartbeat_interval, Duration::from_secs(30));
+        assert_eq!(negotiated.encrypt_method, Some(0));
+        // a new session starts with a reset, which the peer confirms
+        assert!(negotiated.reset_seq_num);
+        loop {
+            if let SessionEvent::LogonReceived { encrypt_method, reset_seq_num, .. } = events.recv().await.unwrap() {
+                assert_eq!(encrypt_method, Some(0));
+                assert!(reset_seq_num);
+                break;
+            }
+        }
+        handle.end_async().await.unwrap();
+        assert_eq!(handle.negotiated(), Some(negotiated));
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingLogger(Arc<Mutex<Vec<Vec<u8>>>>);
+    impl Logger for RecordingLogger {
+        fn log_message(&mut self, msg: &MsgBuf) -> std::io::Result<()> {
+            self.0.lock().unwrap().push(msg.0.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_logger() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let logger = RecordingLogger::default();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(MemoryStore::new())
+            .with_logger(Box::new(logger.clone()))
+            .build()
+            .unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        handle.end_async().await.unwrap();
+
+        let has_field = |msg: &[u8], field: &[u8]| msg.windows(field.len()).any(|w| w == field);
+        let logged = logger.0.lock().unwrap();
+        let logons = logged.iter().filter(|msg| has_field(msg, b"\x0135=A\x01")).count();
+        // the logon sent, and the one received from the peer
+        assert_eq!(logons, 2);
+        assert!(logged.iter().any(|msg| has_field(msg, b"\x0135=5\x01")));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_numbers() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(MemoryStore::new())
+            .build()
+            .unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        let numbers = SequenceNumbers { next_incoming: 2, next_outgoing: 2 };
+        assert_eq!(handle.sequence_numbers(), Some(numbers));
+        handle.end_async().await.unwrap();
+        let numbers = SequenceNumbers { next_incoming: 3, next_outgoing: 3 };
+        assert_eq!(handle.sequence_numbers(), Some(numbers));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let store = MemoryStore::new();
+        let settings = |addr: SocketAddr, store: &MemoryStore| {
+            SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_socket_addr(addr)
+                .with_log_dir(peer.log_dir())
+                .with_memory_store(store.clone())
+                .build()
+                .unwrap()
+        };
+        let (handle, _receiver) = FixApplicationInitiator::build(settings(peer.addr(), &store))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        handle.start_async().await.unwrap();
+        let sent: Vec<_> = (0..3)
+            .map(|i| {
+                let order = MessageBuilder::new(&handle.begin_string(), fix::generated::MsgType::ORDER_SINGLE.into())
+                    .push(fix::generated::Tags::ClOrdID, format!("order-{i}").as_bytes());
+                handle.send_message(order).unwrap()
+            })
+            .collect();
+        let report = handle.shutdown(Drain::All, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(report, ShutdownReport { dropped: 0, timed_out: false, logged_out: true });
+        for sent in sent {
+            assert_eq!(sent.await, Ok(true));
+        }
+        let outgoing = store.outgoing_messages("my_id_peer_id");
+        let has_field = |msg: &[u8], field: &[u8]| msg.windows(field.len()).any(|w| w == field);
+        assert_eq!(outgoing.iter().filter(|(_, msg)| has_field(msg, b"\x0135=D\x01")).count(), 3);
+        assert!(has_field(&outgoing.last().unwrap().1, b"\x0135=5\x01"));
+
+        // a peer that accepts the connection but never answers
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings(silent.local_addr().unwrap(), &MemoryStore::new()))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        let _logon = handle.start().unwrap();
+        let report = handle.shutdown(Drain::None, Duration::from_millis(100)).await.unwrap();
+        assert!(report.timed_out);
+        assert!(!report.logged_out);
+    }
+
+    #[tokio::test]
+    async fn test_send_message_acked() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(MemoryStore::new())
+            .build()
+            .unwrap();
+        let (handle, mut receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        let order = |cl_ord_id: &[u8], order_qty: &[u8]| {
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                .push(Tags::ClOrdID, cl_ord_id)
+                .push(Tags::Symbol, b"AAA")
+                .push(Tags::Side, b"1")
+                .push(Tags::OrderQty, order_qty)
+        };
+        let timeout = Duration::from_secs(5);
+
+        let ack = handle.send_message_acked(order(b"order-1", b"100"), timeout).await.unwrap();
+        assert!(ack.to_string().contains("\x0111=order-1\x01"));
+        assert!(ack.to_string().contains("\x0139=0\x01"));
+        // the answer is still delivered to the application
+        assert_eq!(receiver.recv().await.unwrap().0, ack.0);
+
+        let ack = handle.send_message_acked(order(b"order-2", b"0"), timeout).await.unwrap();
+        assert!(ack.to_string().contains("\x0139=8\x01"));
+
+        let no_cl_ord_id = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into());
+        assert!(matches!(
+            handle.send_message_acked(no_cl_ord_id, timeout).await,
+            Err(ApplicationError::NoClOrdId)
+        ));
+        // the peer does not answer a quote
+        let quote = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::QUOTE.into())
+            .push(Tags::ClOrdID, b"quote-1");
+        assert!(matches!(
+            handle.send_message_acked(quote, Duration::from_millis(50)).await,
+            Err(ApplicationError::AckTimedOut)
+        ));
+        handle.end_async().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_outgoing_dedup_across_restarts() {
+        let order = |cl_ord_id: &[u8]| {
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                .push(Tags::ClOrdID, cl_ord_id)
+        };
+
+        for restart in [false, true] {
+            let (addr, server) = spawn_server("dedup", 1);
+            let mut settings = test_settings("dedup", "client", "server", addr);
+            settings.outgoing_dedup = true;
+            let (client, _receiver) =
+                FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+            client.start_async().await.unwrap();
+            let (server, _server_receiver) = server.await.unwrap();
+            let mut client_events = client.session_events();
+            let mut server_events = server.session_events();
+
+            if restart {
+                assert!(matches!(
+                    client.send_message_async(order(b"order-1")).await,
+                    Err(ApplicationError::AlreadySent)
+                ));
+                client.send_message_async(order(b"order-2")).await.unwrap();
+            } else {
+                client.send_message_async(order(b"order-1")).await.unwrap();
+                assert!(matches!(
+                    client.send_message_async(order(b"order-1")).await,
+                    Err(ApplicationError::AlreadySent)
+                ));
+                // only new orders are checked
+                for _ in 0..2 {
+                    let order_status =
+                        MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_STATUS_REQUEST.into())
+                            .push(Tags::ClOrdID, b"order-1");
+                    client.send_message_async(order_status).await.unwrap();
+                }
+            }
+            client.end_async().await.unwrap();
+            // the restarted engines read their stores once the previous ones are done with them
+            for events in [&mut client_events, &mut server_events] {
+                while !matches!(events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+            }
+        }
+        let _ = std::fs::remove_dir_all(test_dir("dedup"));
+    }
+
+    #[test]
+    fn test_light_initiator() {
+        let peer = testing::Counterparty::start_sync("server", "client").unwrap();
+        let (client, _receiver) = FixApplicationInitiator::build(test_settings("light", "client", "server", peer.addr()))
+            .unwrap()
+            .initiate_light()
+            .unwrap();
+        client.start_sync().unwrap();
+        client
+            .send_message_sync(MessageBuilder::new("FIX.4.2", fix::generated::MsgType::NEWS.into()))
+            .unwrap();
+        client.end_sync().unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("light"));
+    }
+
+    // A peer that logs on, then never answers the `Logout<5>` of the engine.
+    async fn unresponsive_peer(listener: tokio::net::TcpListener) -> std::io::Result<usize> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut stream, _) = listener.accept().await?;
+        let mut buf = vec![0; 1024];
+        let _ = stream.read(&mut buf).await?;
+
+        let body = format!(
+            "35=A\x0134=1\x0149=server\x0152={}\x0156=client\x0198=0\x01108=30\x01",
+            fix::encode::formatted_time()
+        );
+        let mut logon = format!("8=FIX.4.2\x019={}\x01{}", body.len(), body).into_bytes();
+        let checksum = logon.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        logon.extend(format!("10={:03}\x01", checksum).into_bytes());
+        stream.write_all(&logon).await?;
+
+        let mut read = 0;
+        loop {
+            match stream.read(&mut buf).await {
+                Ok(0) | Err(_) => return Ok(read),
+                Ok(n) => read += n,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logout_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = tokio::spawn(unresponsive_peer(listener));
+
+        let mut settings = test_settings("logout-timeout", "client", "server", addr);
+        settings.logout_timeout = Some(Duration::from_millis(200));
+        let (client, _receiver) = FixApplicationInitiator::build(settings)
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        let mut events = client.session_events();
+        client.start_async().await.unwrap();
+
+        let started = std::time::Instant::now();
+        assert!(matches!(client.end_async().await, Err(ApplicationError::LogoutFailed)));
+        assert!(started.elapsed() < Duration::from_secs(30));
+
+        loop {
+            match events.recv().await.unwrap() {
+                SessionEvent::LogoutTimeout { timeout } => {
+                    assert_eq!(timeout, Duration::from_millis(200));
+                    break;
+                }
+                _ => continue,
+            }
+        }
+        // the engine closed the connection, so the peer saw the `Logout<5>` and then EOF
+        assert!(peer.await.unwrap().unwrap() > 0);
+        let _ = std::fs::remove_dir_all(test_dir("logout-timeout"));
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_engine_logs_out() {
+        let (addr, server) = spawn_server("orphaned", 1);
+
+        let mut settings = test_settings("orphaned", "client", "server", addr);
+        settings.orphan_policy = OrphanPolicy::Logout(Duration::from_millis(100));
+        let (client, mut receiver) = FixApplicationInitiator::build(settings)
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        receiver.close();
+        let mut events = client.session_events();
+        client.start_async().await.unwrap();
+        let server = server.await.unwrap();
+        drop(client);
+
+        let ended = tokio::time::timeout(Duration::from_secs(30), async {
+            let mut dropped = false;
+            loop {
+                match events.recv().await {
+                    Ok(SessionEvent::AllHandlesDropped { logout_after }) => {
+                        assert_eq!(logout_after, Some(Duration::from_millis(100)));
+                        dropped = true;
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return dropped,
+                }
+            }
+        });
+        // the engine published the event, logged out, and ended
+        assert!(ended.await.unwrap());
+        drop(server);
+        let _ = std::fs::remove_dir_all(test_dir("orphaned"));
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_events() {
+        let (addr, server) = spawn_server("lifecycle", 1);
+
+        let (client, mut receiver) = FixApplicationInitiator::build(test_settings("lifecycle", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        receiver.close();
+        let mut events = client.session_events();
+        client.start_async().await.unwrap();
+        let _server = server.await.unwrap();
+        client.end_async().await.unwrap();
+
+        let lifecycle = tokio::time::timeout(Duration::from_secs(30), async {
+            let mut lifecycle = Vec::new();
+            loop {
+                match events.recv().await.unwrap() {
+                    event @ (SessionEvent::LoggedOn | SessionEvent::LoggedOut) => lifecycle.push(event),
+                    event @ SessionEvent::Disconnected { .. } => {
+                        lifecycle.push(event);
+                        return lifecycle;
+                    }
+                    _ => continue,
+                }
+            }
+        });
+        assert_eq!(
+            lifecycle.await.unwrap(),
+            vec![SessionEvent::LoggedOn, SessionEvent::LoggedOut, SessionEvent::Disconnected { error: None }]
+        );
+        assert_eq!(client.closed_reason(), Some(CloseReason::LoggedOut { text: None }));
+        let _ = std::fs::remove_dir_all(test_dir("lifecycle"));
+    }
+
+    #[tokio::test]
+    async fn test_session_events_from_start() {
+        let counterparty = testing::Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+
+        // subscribed once the session was started, and still sees it start
+        let mut events = handle.session_events();
+        let mut later_events = handle.session_events();
+        counterparty.reset_sequence_numbers();
+        while !matches!(later_events.recv().await.unwrap(), SessionEvent::SequencesReset { .. }) {}
+        handle.end_async().await.unwrap();
+
+        let transitions = tokio::time::timeout(Duration::from_secs(30), async {
+            let mut transitions = Vec::new();
+            loop {
+                match events.recv().await.unwrap() {
+                    event @ (SessionEvent::Connected { .. }
+                    | SessionEvent::LoggedOn
+                    | SessionEvent::SequencesReset { .. }
+                    | SessionEvent::LoggedOut) => transitions.push(event),
+                    event @ SessionEvent::Disconnected { .. } => {
+                        transitions.push(event);
+                        return transitions;
+                    }
+                    _ => continue,
+                }
+            }
+        });
+        assert_eq!(
+            transitions.await.unwrap(),
+            vec![
+                SessionEvent::Connected { peer_addr: counterparty.addr() },
+                SessionEvent::SequencesReset { initiated_by_peer: false },
+                SessionEvent::LoggedOn,
+                SessionEvent::SequencesReset { initiated_by_peer: true },
+                SessionEvent::LoggedOut,
+                SessionEvent::Disconnected { error: None },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_last_engine_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 16];
+            let _ = tokio::io::AsyncReadExt::read(&mut stream, &mut buf).await;
+        });
+
+        let (client, _receiver) = FixApplicationInitiator::build(test_settings("engine_error", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        let mut events = client.session_events();
+        assert_eq!(client.last_engine_error(), None);
+        let _ = client.start_async().await;
+        server.await.unwrap();
+
+        let disconnected = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                if let SessionEvent::Disconnected { error } = events.recv().await.unwrap() {
+                    return error;
+                }
+            }
+        });
+        let error = disconnected.await.unwrap();
+        let engine_error = client.last_engine_error().unwrap();
+        assert_eq!(error.as_ref(), Some(&engine_error.message));
+        assert!(engine_error.io_error_kind.is_some());
+
+        let builder = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into());
+        match client.send_message(builder) {
+            Err(e @ ApplicationError::SessionEnded(Some(_))) => {
+                assert!(e.to_string().ends_with(&engine_error.message))
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert_eq!(client.closed_reason(), Some(CloseReason::Error(engine_error)));
+        let _ = std::fs::remove_dir_all(test_dir("engine_error"));
+    }
+
+    #[tokio::test]
+    async fn test_closed_reason() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = tokio::io::AsyncReadExt::read(&mut stream, &mut buf).await;
+            let body = format!(
+                "35=5\x0149=server\x0156=client\x0134=1\x0152={}\x0158=bad password\x01",
+                fix::encode::formatted_time()
+            );
+            let msg = format!("8=FIX.4.2\x019={}\x01{body}", body.len());
+            let checksum = msg.bytes().map(u32::from).sum::<u32>() % 256;
+            let msg = format!("{msg}10={checksum:03}\x01");
+            tokio::io::AsyncWriteExt::write_all(&mut stream, msg.as_bytes()).await.unwrap();
+            while let Ok(1..) = tokio::io::AsyncReadExt::read(&mut stream, &mut buf).await {}
+        });
+
+        let (client, _receiver) = FixApplicationInitiator::build(test_settings("closed_reason", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        let mut events = client.session_events();
+        assert!(client.start_async().await.is_err());
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while !matches!(events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            client.closed_reason(),
+            Some(CloseReason::LogonRejected {
+                text: Some("bad password".to_string())
+            })
+        );
+        assert_eq!(client.last_engine_error(), None);
+        server.await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("closed_reason"));
+    }
+
+    struct FixedClock(DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
     }
-    /// Send a request to the engine to end the FIX connection, and await asynchronously. 
-    pub async fn end_async(&self) -> Result<(), ApplicationError> {
-        let resp_sender = self.end()?;
-        if Ok(true) != resp_sender.await {
-            return Err(ApplicationError::LogoutFailed);
+
+    #[tokio::test]
+    async fn test_clock() {
+        let now = Utc::now();
+        let (addr, server) = spawn_server("clock", 1);
+        let mut settings = test_settings("clock", "client", "server", addr);
+        settings.clock = Arc::new(FixedClock(now));
+        settings.timestamp_precision = TimestampPrecision::Micros;
+        let (client, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        client.start_async().await.unwrap();
+        let (_server, mut receiver) = server.await.unwrap();
+
+        let decided = now - chrono::Duration::milliseconds(1500);
+        for builder in [
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into()),
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into()).with_sending_time(decided),
+        ] {
+            client.send_message_async(builder.push_deferred_time(Tags::TransactTime)).await.unwrap();
         }
-        Ok(())
+        for time in [now, decided] {
+            let msg = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+            let msg = fix::decode::FixMessage::parse(&msg).unwrap();
+            let expected = time.format("%Y%m%d-%H:%M:%S%.6f").to_string();
+            assert_eq!(msg.get(Tags::SendingTime), Some(expected.as_bytes()));
+            assert_eq!(msg.get(Tags::TransactTime), Some(expected.as_bytes()));
+        }
+        client.end_async().await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("clock"));
     }
-    /// Send a request to the engine to end the FIX connection, and block until a result is
-    /// returned.
-    pub fn end_sync(&self) -> Result<(), ApplicationError> {
-        let resp_receiver = self.end()?;
-        if Ok(true) != resp_receiver.blocking_recv() {
-            return Err(ApplicationError::LogoutFailed);
+
+    #[tokio::test]
+    async fn test_request_resend() {
+        let (addr, server) = spawn_server("request_resend", 1);
+        let (client, mut receiver) = FixApplicationInitiator::build(test_settings("request_resend", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        client.start_async().await.unwrap();
+        let (server, _server_receiver) = server.await.unwrap();
+        for cl_ord_id in [b"order-1", b"order-2"] {
+            let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                .push(fix::generated::Tags::ClOrdID, cl_ord_id);
+            server.send_message_async(order).await.unwrap();
         }
-        Ok(())
+        for _ in 0..2 {
+            let msg = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+            let msg = fix::decode::FixMessage::parse(&msg).unwrap();
+            assert_eq!(msg.get(fix::generated::Tags::PossDupFlag), None);
+        }
+        assert!(matches!(
+            client.request_resend(4, 0).await,
+            Err(ApplicationError::InvalidSequenceNumber(4))
+        ));
+
+        client.request_resend(3, 0).await.unwrap();
+        let resent = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+        let resent = fix::decode::FixMessage::parse(&resent).unwrap();
+        assert_eq!(resent.get(fix::generated::Tags::ClOrdID), Some(&b"order-2"[..]));
+        assert_eq!(resent.get(fix::generated::Tags::PossDupFlag), Some(&b"Y"[..]));
+        assert_eq!(client.sequence_numbers().unwrap().next_incoming, 4);
+        client.end_async().await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("request_resend"));
     }
 
-    /// Get the `BeginString(8)` of this FIX Session. Should generally be `"FIX.4.2"`. 
-    pub fn begin_string(&self) -> Arc<String> {
-        Arc::clone(&self.begin_string)
+    #[tokio::test]
+    async fn test_reset_sequence() {
+        let (addr, server) = spawn_server("reset_sequence", 1);
+        let (client, _receiver) = FixApplicationInitiator::build(test_settings("reset_sequence", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        client.start_async().await.unwrap();
+        assert!(matches!(
+            client.reset_sequence(2).await,
+            Err(ApplicationError::InvalidSequenceNumber(2))
+        ));
+        client.reset_sequence(50).await.unwrap();
+        let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+            .push(fix::generated::Tags::ClOrdID, b"order-1");
+        client.send_message_async(order).await.unwrap();
+
+        let (server, mut server_receiver) = server.await.unwrap();
+        let order = server_receiver.recv().await.unwrap();
+        let order = fix::decode::FixMessage::parse(&order).unwrap();
+        assert_eq!(order.get_as::<u32>(fix::generated::Tags::MsgSeqNum).unwrap().unwrap(), 50);
+        assert_eq!(server.sequence_numbers().unwrap().next_incoming, 51);
+        client.end_async().await.unwrap();
+
+        // the next outgoing sequence number was stored
+        let admin = fix::admin::SequenceAdmin::open(&test_settings("reset_sequence", "client", "server", addr));
+        assert_eq!(admin.sequence_numbers().await.unwrap().next_outgoing, 52);
+        let _ = std::fs::remove_dir_all(test_dir("reset_sequence"));
     }
-}
 
-/// A struct that can initiate the TCP connection to the peer and create a FIX engine instance. 
-pub struct FixApplicationInitiator {
-    settings: SessionSettings,
-    stream_factory: StreamFactory,
-}
+    #[derive(Default)]
+    struct RecordingCallback {
+        received: std::sync::Mutex<Vec<Vec<u8>>>,
+    }
 
-impl FixApplicationInitiator {
-    /// Build a `FixApplicationInitiator` that will create a FIX engine using `settings`.
-    #[allow(clippy::too_many_arguments)]
-    pub fn build(
-        mut settings: SessionSettings,
-    ) -> Result<FixApplicationInitiator, ApplicationError> {
-        settings.engine_type = FixEngineType::Client;
-        let stream_factory = StreamFactory::build(&settings)?;
-        let fix_app_client = FixApplicationInitiator {
-            settings,
-            stream_factory,
-        };
-        Ok(fix_app_client)
+    impl SessionCallback for RecordingCallback {
+        fn on_admin_msg_out(&self, builder: &mut MessageBuilder) {
+            if builder.msg_type() == fix::generated::MsgType::LOGON.into() {
+                builder.push_mut(9001u32, b"custom");
+            }
+        }
+        fn on_app_msg_out(&self, builder: &mut MessageBuilder) {
+            builder.push_mut(9002u32, b"app");
+        }
+        fn on_admin_msg_in(&self, msg: &[u8]) {
+            self.received.lock().unwrap().push(msg.to_vec());
+        }
+        fn on_app_msg_in(&self, msg: &[u8]) {
+            self.received.lock().unwrap().push(msg.to_vec());
+        }
     }
 
-    /// Initiate a TCP connection and start the FIX engine with the current asynchronous runtime.
-    ///
-    /// If the connection is successfully made, a [`FixApplicationHandle`] will be returned, and an
-    /// `UnboundedReceiver<Arc<MsgBuf>>` will be returned. 
-    ///
-    /// The application handle can be used to start the FIX connection, send messages and end the
-    /// connection. 
-    ///
-    /// The receiver is a channel where all incoming, valid application messages can be received.
-    /// If you do not want to use the channel, it is recommended you call [`close`]. 
-    ///
-    /// [`close`]: tokio::sync::mpsc::UnboundedReceiver::close
-    pub async fn initiate(
-        self,
-    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError>
-    {
-        let stream = self.stream_factory.stream().await?;
-        let (request_sender, request_receiver) = mpsc::unbounded_channel::<Request>();
-        let (app_message_event_sender, app_message_event_receiver) =
-            mpsc::unbounded_channel::<Arc<MsgBuf>>();
-        let begin_string = Arc::clone(&self.settings.begin_string); 
-
-        tokio::spawn(async move {
-            if let Err(e) = fix::spin_session(
-                stream,
-                request_receiver,
-                app_message_event_sender,
-                self.settings,
-            )
+    #[tokio::test]
+    async fn test_session_callback() {
+        let server_callback = Arc::new(RecordingCallback::default());
+        let mut settings = test_settings("callback", "server", "client", "127.0.0.1:0".parse().unwrap());
+        settings.session_callback = Some(server_callback.clone());
+        let mut acceptor = FixApplicationAcceptor::build(settings).unwrap();
+        let addr = acceptor.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (handle, receiver) = acceptor.accept().await.unwrap();
+            handle.start_async().await.unwrap();
+            (handle, receiver)
+        });
+
+        let mut settings = test_settings("callback", "client", "server", addr);
+        settings.session_callback = Some(Arc::new(RecordingCallback::default()));
+        let (client, _receiver) = FixApplicationInitiator::build(settings)
+            .unwrap()
+            .initiate()
             .await
-            {
-                eprintln!("{e:?}");
-            }
+            .unwrap();
+        client.start_async().await.unwrap();
+        let (_server, mut receiver) = server.await.unwrap();
+        let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1");
+        client.send_message_async(order).await.unwrap();
+        let order = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        client.end_async().await.unwrap();
+
+        let has_field = |msg: &[u8], field: &[u8]| msg.windows(field.len()).any(|w| w == field);
+        let received = server_callback.received.lock().unwrap();
+        assert!(has_field(&received[0], b"\x0135=A\x01"));
+        assert!(has_field(&received[0], b"\x019001=custom\x01"));
+        assert!(has_field(&received[1], b"\x0135=D\x01"));
+        assert!(has_field(&received[1], b"\x019002=app\x01"));
+        assert_eq!(received[1], order[..].to_vec());
+        let _ = std::fs::remove_dir_all(test_dir("callback"));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_filter() {
+        use crate::testing::{field, Counterparty};
+        use fix::generated::MsgType;
+
+        let mut counterparty = Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let mut settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        settings.delivery_filter = DeliveryFilter::MsgTypes(vec![MsgType::EXECUTION_REPORT.into()]);
+        let (handle, mut receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        counterparty.next_message().await.unwrap();
+
+        let news = MessageBuilder::new("FIX.4.2", MsgType::NEWS.into()).push(Tags::Headline, b"news");
+        counterparty.send(news);
+        let order = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1")
+            .push(Tags::Symbol, b"AAPL")
+            .push(Tags::Side, b"1")
+            .push(Tags::OrderQty, b"100");
+        handle.send_message_async(order).await.unwrap();
+        let execution_report = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(field(&execution_report, Tags::MsgType), Some(&b"8"[..]));
+        // the news was sequenced, but not delivered
+        assert_eq!(field(&execution_report, Tags::MsgSeqNum), Some(&b"3"[..]));
+        assert_eq!(handle.sequence_numbers().unwrap().next_incoming, 4);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_flush() {
+        use crate::testing::Counterparty;
+        use fix::generated::MsgType;
+
+        let mut counterparty = Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        handle.start_async().await.unwrap();
+        counterparty.next_message().await.unwrap();
+        handle.flush_async(Flush::Written).await.unwrap();
+
+        let mut resp_receivers: Vec<_> = (0..10)
+            .map(|i| {
+                let order = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+                    .push(Tags::ClOrdID, format!("order-{i}").as_bytes());
+                handle.send_message(order).unwrap()
+            })
+            .collect();
+        handle.flush_async(Flush::Stored).await.unwrap();
+        for resp_receiver in resp_receivers.iter_mut() {
+            assert_eq!(resp_receiver.try_recv(), Ok(true));
+        }
+        assert_eq!(handle.sequence_numbers().unwrap().next_outgoing, 12);
+
+        counterparty.drop_connection();
+        while !matches!(events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+        assert!(handle.flush_async(Flush::Written).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pre_logon_guard() {
+        let mut settings = test_settings("guard", "server", "client", "127.0.0.1:0".parse().unwrap());
+        settings.pre_logon_guard = Some(Duration::from_secs(5));
+        let mut acceptor = FixApplicationAcceptor::build(settings).unwrap();
+        let addr = acceptor.local_addr().unwrap();
+
+        // a connection that sends nothing does not hold up the ones after it
+        let _silent = TcpStream::connect(addr).await.unwrap();
+        let mut scanner = TcpStream::connect(addr).await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(&mut scanner, b"\x16\x03\x01\x02\x00").await.unwrap();
+        let server = tokio::spawn(async move {
+            let (handle, _receiver) = acceptor.accept().await.unwrap();
+            handle.start_async().await.unwrap();
+            (acceptor, handle)
         });
 
-        let handle = FixApplicationHandle {
-            request_sender,
-            begin_string,
+        let (client, _receiver) = FixApplicationInitiator::build(test_settings("guard", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(2), client.start_async()).await.unwrap().unwrap();
+        let (acceptor, _server) = server.await.unwrap();
+        assert_eq!(acceptor.rejected_connections(), RejectedConnections { not_fix: 1, ..Default::default() });
+        client.end_async().await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("guard"));
+    }
+
+    #[test]
+    fn test_ephemeral() {
+        let settings = SessionSettings::ephemeral("my_id", "peer_id", "127.0.0.1:9876".parse().unwrap());
+        assert_eq!(settings.epoch.as_str(), "my_id_peer_id");
+        assert!(settings.memory_store.is_some());
+        assert!(settings.log_dir.starts_with(std::env::temp_dir()));
+        assert_eq!(settings.checksum_validation, ChecksumValidation::Skip);
+        assert!(settings.tolerate_out_of_place_fields);
+    }
+
+    #[tokio::test]
+    async fn test_additional_header_fields() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let builder = || {
+            SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_socket_addr(peer.addr())
+                .with_log_dir(peer.log_dir())
+                .with_memory_store(MemoryStore::new())
+                .with_additional_header_field(9001, "desk-7")
         };
+        assert!(matches!(
+            builder().with_additional_header_field(34, "1").build(),
+            Err(ApplicationError::ReservedHeaderTag(34))
+        ));
 
-        Ok((handle, app_message_event_receiver))
+        let settings = builder().build().unwrap();
+        let reader = fix::replay::StoreReader::open(&settings).unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        handle.end_async().await.unwrap();
+
+        let sent = reader.query(&fix::replay::MessageQuery::new()).await.unwrap();
+        assert_eq!(sent.iter().map(|m| m.msg_type).collect::<Vec<_>>(), vec!['A', '5']);
+        for stored in sent {
+            assert!(stored.msg.0.windows(13).any(|w| w == b"\x019001=desk-7\x01"));
+        }
     }
 
-    /// Initiate a TCP connection and start the FIX engine that will be driven by `runtime`. 
-    pub fn initiate_with_runtime(
-        self,
-        runtime: tokio::runtime::Runtime, 
-    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError>
-    {
-        let (request_sender, request_receiver) = mpsc::unbounded_channel::<Request>();
-        let (app_message_event_sender, app_message_event_receiver) =
-            mpsc::unbounded_channel::<Arc<MsgBuf>>();
-        let begin_string = Arc::clone(&self.settings.begin_string); 
-        let stream = runtime.block_on(self.stream_factory.stream())?;
-        
-        std::thread::spawn(move || {
-            if let Err(e) = runtime.block_on(fix::spin_session(
-                    stream,
-                    request_receiver,
-                    app_message_event_sender,
-                    self.settings,
-            ))
-            {
-                eprintln!("{e:?}");
-            }
-        }); 
-        let handle = FixApplicationHandle {
-            request_sender,
-            begin_string,
+    #[tokio::test]
+    async fn test_send_batch() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(MemoryStore::new())
+            .with_outgoing_dedup(true)
+            .build()
+            .unwrap();
+        let reader = fix::replay::StoreReader::open(&settings).unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+
+        let order = |cl_ord_id: &str| {
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                .push(Tags::ClOrdID, cl_ord_id.as_bytes())
+                .push(Tags::Symbol, b"AAPL")
         };
+        handle.send_batch(Vec::new()).await.unwrap();
+        handle
+            .send_batch(vec![order("order-1"), order("order-2"), order("order-3")])
+            .await
+            .unwrap();
+        // refused as a whole
+        assert!(matches!(
+            handle.send_batch(vec![order("order-4"), order("order-4")]).await,
+            Err(ApplicationError::AlreadySent)
+        ));
+        assert!(matches!(
+            handle.send_batch(vec![order("order-5"), order("order-1")]).await,
+            Err(ApplicationError::AlreadySent)
+        ));
+        handle.send_batch(vec![order("order-4"), order("order-5")]).await.unwrap();
+        handle.end_async().await.unwrap();
 
-        Ok((handle, app_message_event_receiver))
+        let query = fix::replay::MessageQuery::new().with_msg_type('D');
+        let sent = reader.query(&query).await.unwrap();
+        let cl_ord_ids: Vec<_> = sent
+            .iter()
+            .map(|stored| fix::decode::FixMessage::parse(&stored.msg).unwrap().get(Tags::ClOrdID).unwrap().to_vec())
+            .collect();
+        assert_eq!(cl_ord_ids, [b"order-1", b"order-2", b"order-3", b"order-4", b"order-5"]);
+        assert_eq!(sent.iter().map(|m| m.msg_seq_num).collect::<Vec<_>>(), vec![2, 3, 4, 5, 6]);
     }
 
-    /// Initiate a TCP connection, and a runtime will be created internally to drive the engine. 
-    pub fn initiate_sync(
-        self
-    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError> 
-    {
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()?; 
-        self.initiate_with_runtime(runtime)
+    #[tokio::test]
+    async fn test_outgoing_dedup_concurrent_sends() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("my_id")
+            .with_target_comp_id("peer_id")
+            .with_socket_addr(peer.addr())
+            .with_log_dir(peer.log_dir())
+            .with_memory_store(MemoryStore::new())
+            .with_outgoing_dedup(true)
+            .build()
+            .unwrap();
+        let reader = fix::replay::StoreReader::open(&settings).unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+
+        // two handles racing to send the same ID: only one order is queued
+        let mut resp_receivers = Vec::new();
+        for i in 0..50 {
+            let cl_ord_id = format!("order-{i}");
+            let barrier = std::sync::Barrier::new(2);
+            let results: Vec<_> = std::thread::scope(|scope| {
+                let senders: Vec<_> = (0..2)
+                    .map(|_| {
+                        let handle = handle.clone();
+                        let (barrier, cl_ord_id) = (&barrier, &cl_ord_id);
+                        scope.spawn(move || {
+                            let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+                                .push(Tags::ClOrdID, cl_ord_id.as_bytes());
+                            barrier.wait();
+                            handle.send_message(order)
+                        })
+                    })
+                    .collect();
+                senders.into_iter().map(|sender| sender.join().unwrap()).collect()
+            });
+            let (sent, refused): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+            assert_eq!(sent.len(), 1);
+            assert!(matches!(refused[..], [Err(ApplicationError::AlreadySent)]));
+            resp_receivers.extend(sent.into_iter().map(Result::unwrap));
+        }
+        for resp_receiver in resp_receivers {
+            assert_eq!(resp_receiver.await, Ok(true));
+        }
+        handle.end_async().await.unwrap();
+
+        let sent = reader.query(&fix::replay::MessageQuery::new().with_msg_type('D')).await.unwrap();
+        assert_eq!(sent.len(), 50);
     }
-}
 
-/// A struct that can accept TCP connections, and create a FIX engine instance for each connection. 
-pub struct FixApplicationAcceptor {
-    settings: SessionSettings,
-    stream_factory: StreamFactory,
-}
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let builder = |messages_per_second, exceeded| {
+            SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_socket_addr(peer.addr())
+                .with_log_dir(peer.log_dir())
+                .with_memory_store(MemoryStore::new())
+                .with_rate_limit(RateLimit {
+                    messages_per_second,
+                    burst: 2,
+                    exceeded,
+                })
+        };
+        let order = || MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into());
 
-impl FixApplicationAcceptor {
-    /// Build a `FixApplicationAcceptor` from `settings`. 
-    #[allow(clippy::too_many_arguments)]
-    pub fn build(
-        mut settings: SessionSettings,
-    ) -> Result<FixApplicationAcceptor, ApplicationError> {
-        settings.engine_type = FixEngineType::Server;
-        let stream_factory = StreamFactory::build(&settings)?;
-        let fix_app_server = FixApplicationAcceptor {
-            settings,
-            stream_factory,
+        // 2 orders are sent at once, then one every 50ms
+        let settings = builder(20, RateLimitExceeded::Delay).build().unwrap();
+        let reader = fix::replay::StoreReader::open(&settings).unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        let started = std::time::Instant::now();
+        handle.send_batch((0..6).map(|_| order()).collect()).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(190));
+        handle.end_async().await.unwrap();
+        let sent = reader.query(&fix::replay::MessageQuery::new()).await.unwrap();
+        let msg_types: String = sent.iter().map(|stored| stored.msg_type).collect();
+        assert_eq!(msg_types, "ADDDDDD5");
+
+        let settings = builder(1, RateLimitExceeded::Reject).build().unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        handle.send_message_async(order()).await.unwrap();
+        assert!(matches!(
+            handle.send_batch(vec![order(), order()]).await,
+            Err(ApplicationError::RateLimited)
+        ));
+        handle.send_message_async(order()).await.unwrap();
+        assert!(matches!(handle.send_message(order()), Err(ApplicationError::RateLimited)));
+        handle.end_async().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_sessions() {
+        let session = |sender: &str, target: &str, addr: SocketAddr| {
+            SessionSettings::builder()
+                .with_sender_comp_id(sender)
+                .with_target_comp_id(target)
+                .with_socket_addr(addr)
+                .with_memory_store(MemoryStore::new())
+                .with_log_dir(test_dir("sessions"))
+                .build()
+                .unwrap()
         };
-        Ok(fix_app_server)
+        let any_addr = "127.0.0.1:0".parse().unwrap();
+        let mut acceptor = FixApplicationAcceptor::build(session("server", "client_a", any_addr)).unwrap();
+        acceptor.add_session(session("server", "client_b", any_addr));
+        let addr = acceptor.local_addr().unwrap();
+        let (accepted_sender, mut accepted) = mpsc::unbounded_channel();
+        let server = tokio::spawn(async move {
+            loop {
+                let (handle, _receiver) = acceptor.accept().await.unwrap();
+                handle.start_async().await.unwrap();
+                accepted_sender.send((handle, acceptor.rejected_connections())).unwrap();
+            }
+        });
+
+        let initiate = |sender: &str| FixApplicationInitiator::build(session(sender, "server", addr)).unwrap().initiate();
+        let (client_b, _receiver) = initiate("client_b").await.unwrap();
+        client_b.start_async().await.unwrap();
+        let (server_b, rejected) = accepted.recv().await.unwrap();
+        assert_eq!(server_b.target_comp_id().as_str(), "client_b");
+        assert_eq!(rejected, RejectedConnections::default());
+
+        let (unknown, _receiver) = initiate("client_c").await.unwrap();
+        assert!(unknown.start_async().await.is_err());
+
+        let (client_a, _receiver) = initiate("client_a").await.unwrap();
+        client_a.start_async().await.unwrap();
+        let (server_a, rejected) = accepted.recv().await.unwrap();
+        assert_eq!(server_a.target_comp_id().as_str(), "client_a");
+        assert_eq!(rejected, RejectedConnections { unknown_comp_id: 1, ..Default::default() });
+
+        // a reconnect of a session that is still connected replaces the previous connection
+        let mut server_b_events = server_b.session_events();
+        let mut client_b_events = client_b.session_events();
+        let mut reconnect = session("client_b", "server", addr);
+        reconnect.reset_flag_on_initial_logon = true;
+        let (client_b_again, _receiver) = FixApplicationInitiator::build(reconnect).unwrap().initiate().await.unwrap();
+        client_b_again.start_async().await.unwrap();
+        while !matches!(server_b_events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+        let (server_b_again, rejected) = accepted.recv().await.unwrap();
+        assert_eq!(server_b_again.target_comp_id().as_str(), "client_b");
+        assert_eq!(rejected, RejectedConnections { unknown_comp_id: 1, ..Default::default() });
+        while !matches!(client_b_events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+
+        client_a.end_async().await.unwrap();
+        client_b_again.end_async().await.unwrap();
+        server.abort();
+        let _ = std::fs::remove_dir_all(test_dir("sessions"));
     }
 
-    /// Accept an incoming TCP connection and create a FIX engine. 
-    ///
-    /// Returns the handle to the created engine, and a channel to receive all valid, incoming application
-    /// messages. 
-    pub async fn accept(
-        &mut self,
-    ) -> Result<(FixApplicationHandle, mpsc::UnboundedReceiver<Arc<MsgBuf>>), ApplicationError>
-    {
-        let stream = self.stream_factory.stream().await?;
-        let settings = self.settings.clone();
-        let (request_sender, request_receiver) = mpsc::unbounded_channel::<Request>();
-        let (app_message_event_sender, app_message_event_receiver) =
-            mpsc::unbounded_channel::<Arc<MsgBuf>>();
-        let begin_string = Arc::clone(&self.settings.begin_string); 
+    #[tokio::test]
+    async fn test_reset_flag_on_initial_logon() {
+        let store = MemoryStore::new();
+        let (addr, server) = spawn_server("reset_flag", 2);
 
-        tokio::task::spawn(async move {
-            if let Err(e) =
-                fix::spin_session(stream, request_receiver, app_message_event_sender, settings)
-                    .await
-            {
-                eprintln!("{e:?}");
+        // the first session starts today, and the second one continues it unless reset
+        for reset_flag_on_initial_logon in [false, true] {
+            let mut settings = test_settings("reset_flag", "client", "server", addr);
+            settings.memory_store = Some(store.clone());
+            settings.reset_flag_on_initial_logon = reset_flag_on_initial_logon;
+            let (client, mut receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+            receiver.close();
+            let mut events = client.session_events();
+            client.start_async().await.unwrap();
+            assert_eq!(events.recv().await.unwrap(), SessionEvent::Connected { peer_addr: addr });
+            assert_eq!(events.recv().await.unwrap(), SessionEvent::SequencesReset { initiated_by_peer: false });
+            client.end_async().await.unwrap();
+            assert_eq!(store.sequences("client_server"), Some((3, 3)));
+        }
+        server.await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("reset_flag"));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_reset_handshake() {
+        let mut counterparty = testing::Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        handle.start_async().await.unwrap();
+        let sequences_reset = |events: &mut broadcast::Receiver<SessionEvent>| {
+            std::iter::from_fn(|| events.try_recv().ok()).find_map(|event| match event {
+                SessionEvent::SequencesReset { initiated_by_peer } => Some(initiated_by_peer),
+                _ => None,
+            })
+        };
+        let test_request = |test_req_id: &[u8]| {
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::TEST_REQUEST.into())
+                .push(Tags::TestReqID, test_req_id)
+        };
+
+        // the engine starts a new session with a reset, which the counterparty confirms
+        let logon = counterparty.next_message().await.unwrap();
+        assert_eq!(testing::msg_type(&logon), Some('A'));
+        assert_eq!(testing::field(&logon, Tags::ResetSeqNumFlag), Some(&b"Y"[..]));
+        counterparty.send(test_request(b"after-engine-reset"));
+        let heartbeat = counterparty.next_message().await.unwrap();
+        assert_eq!(testing::field(&heartbeat, Tags::TestReqID), Some(&b"after-engine-reset"[..]));
+        assert_eq!(testing::field(&heartbeat, Tags::MsgSeqNum), Some(&b"2"[..]));
+        assert_eq!(sequences_reset(&mut events), Some(false));
+
+        // the counterparty resets in the middle of the session, which the engine confirms
+        counterparty.reset_sequence_numbers();
+        let logon = counterparty.next_message().await.unwrap();
+        assert_eq!(testing::msg_type(&logon), Some('A'));
+        assert_eq!(testing::field(&logon, Tags::ResetSeqNumFlag), Some(&b"Y"[..]));
+        assert_eq!(testing::field(&logon, Tags::MsgSeqNum), Some(&b"1"[..]));
+        counterparty.send(test_request(b"after-peer-reset"));
+        let heartbeat = counterparty.next_message().await.unwrap();
+        assert_eq!(testing::field(&heartbeat, Tags::TestReqID), Some(&b"after-peer-reset"[..]));
+        assert_eq!(testing::field(&heartbeat, Tags::MsgSeqNum), Some(&b"2"[..]));
+        assert_eq!(sequences_reset(&mut events), Some(true));
+        let sequences = handle.sequence_numbers().unwrap();
+        assert_eq!((sequences.next_incoming, sequences.next_outgoing), (3, 3));
+        handle.end_async().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_poss_resend() {
+        let mut settings = test_settings("poss_resend", "server", "client", "127.0.0.1:0".parse().unwrap());
+        settings.poss_resend_window = Some(16);
+        settings.drop_poss_resend_duplicates = true;
+        let mut acceptor = FixApplicationAcceptor::build(settings).unwrap();
+        let addr = acceptor.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (handle, mut receiver) = acceptor.accept().await.unwrap();
+            let mut events = handle.session_events();
+            handle.start_async().await.unwrap();
+            let mut cl_ord_ids = Vec::new();
+            for _ in 0..2 {
+                let msg = fix::decode::FixMessage::parse(&receiver.recv().await.unwrap()).unwrap();
+                cl_ord_ids.push(msg.get(Tags::ClOrdID).unwrap().to_vec());
             }
+            let mut duplicates = Vec::new();
+            while duplicates.len() < 3 {
+                if let SessionEvent::PossResendReceived { duplicate, .. } = events.recv().await.unwrap() {
+                    duplicates.push(duplicate);
+                }
+            }
+            (cl_ord_ids, duplicates)
         });
 
-        let handle = FixApplicationHandle {
-            request_sender,
-            begin_string,
+        // every message of the client has PossResend(97)=Y
+        let mut settings = test_settings("poss_resend", "client", "server", addr);
+        settings.header_extras = Arc::new(vec![(97, b"Y".to_vec())]);
+        let (client, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        client.start_async().await.unwrap();
+        let order = |cl_ord_id: &[u8]| {
+            MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into()).push(Tags::ClOrdID, cl_ord_id)
         };
+        client
+            .send_batch(vec![order(b"order-1"), order(b"order-1"), order(b"order-2")])
+            .await
+            .unwrap();
 
-        Ok((handle, app_message_event_receiver))
+        let (cl_ord_ids, duplicates) = server.await.unwrap();
+        assert_eq!(cl_ord_ids, [b"order-1", b"order-2"]);
+        assert_eq!(duplicates, [false, true, false]);
+        client.end_async().await.unwrap();
+        let _ = std::fs::remove_dir_all(test_dir("poss_resend"));
     }
-}
 
-#[derive(Clone)]
-enum FixEngineType {
-    Client,
-    Server,
-}
+    #[tokio::test]
+    async fn test_business_reject() {
+        let (addr, server) = spawn_server("business_reject", 1);
+        let settings = test_settings("business_reject", "client", "server", addr);
+        let (client, mut receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = client.session_events();
+        client.start_async().await.unwrap();
+        let order = MessageBuilder::new("FIX.4.2", fix::generated::MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1")
+            .push(Tags::Symbol, b"ZZZZ");
+        client.send_message_async(order).await.unwrap();
 
-enum StreamFactory {
-    Server(TcpListener),
-    Client(std::net::SocketAddr),
-}
+        let (server, mut server_receiver) = server.await.unwrap();
+        let order = server_receiver.recv().await.unwrap();
+        let reject = fix::messages::BusinessMessageReject::for_message(
+            &order,
+            fix::generated::BusinessRejectReason::UNKNOWN_SECURITY,
+        )
+        .unwrap()
+        .with_text("unknown symbol");
+        server.send_message_async(reject.into()).await.unwrap();
 
-impl StreamFactory {
-    fn build(settings: &SessionSettings) -> Result<Self, std::io::Error> {
-        match settings.engine_type {
-            FixEngineType::Client => Ok(StreamFactory::Client(settings.addr)),
-            FixEngineType::Server => {
-                let socket = TcpSocket::new_v4()?;
-                socket.bind(settings.addr)?;
-                let listener = socket.listen(1024)?;
-                Ok(StreamFactory::Server(listener))
+        let msg = receiver.recv().await.unwrap();
+        assert!(matches!(
+            fix::messages::IncomingAppMessage::from(msg),
+            fix::messages::IncomingAppMessage::BusinessMessageReject(_)
+        ));
+        loop {
+            if let SessionEvent::BusinessRejectReceived {
+                ref_seq_num,
+                ref_msg_type,
+                business_reject_reason,
+                business_reject_ref_id,
+                text,
+            } = events.recv().await.unwrap()
+            {
+                assert_eq!(ref_seq_num, Some(2));
+                assert_eq!(ref_msg_type.as_deref(), Some("D"));
+                assert_eq!(business_reject_reason, Some(2));
+                assert_eq!(business_reject_ref_id.as_deref(), Some("order-1"));
+                assert_eq!(text.as_deref(), Some("unknown symbol"));
+                break;
             }
         }
+        client.end_async().await.unwrap();
+        drop(server);
+        let _ = std::fs::remove_dir_all(test_dir("business_reject"));
     }
-    async fn stream(&self) -> Result<TcpStream, std::io::Error> {
-        match self {
-            StreamFactory::Server(listener) => {
-                let (stream, _from_addr) = listener.accept().await?;
-                Ok(stream)
+
+    #[tokio::test]
+    async fn test_session_schedule() {
+        let (addr, server) = spawn_server("schedule", 1);
+
+        let now = chrono::Utc::now();
+        let schedule = SessionSchedule::daily(
+            (now + chrono::Duration::seconds(1)).time(),
+            (now + chrono::Duration::seconds(3)).time(),
+        )
+        .with_outside_window(fix::schedule::OutsideWindow::Queue)
+        .with_end_warning(Duration::from_secs(1));
+        let mut settings = test_settings("schedule", "client", "server", addr);
+        settings.schedule = Some(schedule);
+        let (client, mut receiver) = FixApplicationInitiator::build(settings)
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        receiver.close();
+        let mut events = client.session_events();
+
+        // the engine waits for the session to start before logging on, and holds the order
+        let started = client.start().unwrap();
+        let order = MessageBuilder::new(&client.begin_string(), fix::generated::MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1");
+        let sent = client.send_message(order).unwrap();
+        assert_eq!(started.await, Ok(true));
+        assert!(chrono::Utc::now() >= now + chrono::Duration::seconds(1));
+        assert_eq!(sent.await, Ok(true));
+        let server = server.await.unwrap();
+
+        // warns ahead of the end of the session, and logs out at the end
+        let ended = tokio::time::timeout(Duration::from_secs(30), async {
+            let mut ending_soon = None;
+            loop {
+                match events.recv().await {
+                    Ok(SessionEvent::SessionEndingSoon { end }) => ending_soon = Some((end, chrono::Utc::now())),
+                    Err(broadcast::error::RecvError::Closed) => return ending_soon,
+                    _ => {}
+                }
             }
-            StreamFactory::Client(addr) => {
-                let socket = TcpSocket::new_v4()?;
-                Ok(socket.connect(*addr).await?)
+        });
+        drop(client);
+        let (end, warned_at) = ended.await.unwrap().unwrap();
+        assert!(chrono::Utc::now() >= now + chrono::Duration::seconds(3));
+        assert!(warned_at >= end - chrono::Duration::seconds(1) && warned_at < end);
+        drop(server);
+        let _ = std::fs::remove_dir_all(test_dir("schedule"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_paces_held_messages() {
+        let (addr, server) = spawn_server("held_rate", 1);
+
+        let now = chrono::Utc::now();
+        let schedule = SessionSchedule::daily(
+            (now + chrono::Duration::seconds(1)).time(),
+            (now + chrono::Duration::seconds(30)).time(),
+        )
+        .with_outside_window(fix::schedule::OutsideWindow::Queue);
+        let mut settings = test_settings("held_rate", "client", "server", addr);
+        settings.schedule = Some(schedule);
+        settings.rate_limit = Some(RateLimit {
+            messages_per_second: 20,
+            burst: 2,
+            exceeded: RateLimitExceeded::Reject,
+        });
+        let (client, mut receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        receiver.close();
+
+        // the handle accepts 4 orders over 100ms, which are held until the session starts, then
+        // sent at the same rate
+        let started = client.start().unwrap();
+        let order = || MessageBuilder::new(&client.begin_string(), fix::generated::MsgType::ORDER_SINGLE.into());
+        let mut sent = vec![client.send_message(order()).unwrap(), client.send_message(order()).unwrap()];
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        sent.push(client.send_message(order()).unwrap());
+        sent.push(client.send_message(order()).unwrap());
+        assert_eq!(started.await, Ok(true));
+        let mut written_at = Vec::new();
+        for sent in sent {
+            assert_eq!(sent.await, Ok(true));
+            written_at.push(std::time::Instant::now());
+        }
+        assert!(written_at[3] - written_at[0] >= Duration::from_millis(90));
+        client.end_async().await.unwrap();
+        drop(server.await.unwrap());
+        let _ = std::fs::remove_dir_all(test_dir("held_rate"));
+    }
+
+    #[tokio::test]
+    async fn test_schedule_refuses_logon() {
+        let now = chrono::Utc::now();
+        let mut settings = test_settings("schedule_refuses", "server", "client", "127.0.0.1:0".parse().unwrap());
+        settings.schedule = Some(SessionSchedule::daily(
+            (now + chrono::Duration::hours(1)).time(),
+            (now + chrono::Duration::hours(2)).time(),
+        ));
+        let mut acceptor = FixApplicationAcceptor::build(settings).unwrap();
+        let addr = acceptor.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (handle, _receiver) = acceptor.accept().await.unwrap();
+            let mut events = handle.session_events();
+            assert!(handle.start_async().await.is_err());
+            while !matches!(events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+            handle
+        });
+
+        let (client, _receiver) = FixApplicationInitiator::build(test_settings("schedule_refuses", "client", "server", addr))
+            .unwrap()
+            .initiate()
+            .await
+            .unwrap();
+        let mut events = client.session_events();
+        assert!(client.start_async().await.is_err());
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !matches!(events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+        })
+        .await
+        .unwrap();
+        let server = server.await.unwrap();
+        assert_eq!(
+            client.closed_reason(),
+            Some(CloseReason::LogonRejected { text: Some(String::from("Logon outside of the session schedule")) })
+        );
+        assert_eq!(server.closed_reason(), Some(CloseReason::NotStarted));
+        let _ = std::fs::remove_dir_all(test_dir("schedule_refuses"));
+    }
+
+    #[test]
+    fn test_ipv6_only_listener() {
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("server")
+            .with_target_comp_id("client")
+            .with_socket_addr("[::]:0".parse().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .with_ipv6_only(true)
+            .build()
+            .unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let _guard = runtime.enter();
+        let acceptor = FixApplicationAcceptor::build(settings).unwrap();
+        let port = acceptor.local_addr().unwrap().port();
+        assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_socket_options() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let settings = SessionSettings::builder()
+            .with_sender_comp_id("client")
+            .with_target_comp_id("server")
+            .with_socket_addr(listener.local_addr().unwrap())
+            .with_store_path("./store".into())
+            .with_log_dir("./log".into())
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_tcp_keepalive(Duration::from_secs(20))
+            .with_recv_buffer_size(1 << 16)
+            .with_send_buffer_size(1 << 16)
+            .with_local_addr(local_addr)
+            .build()
+            .unwrap();
+        let stream = StreamFactory::build(&settings).unwrap().stream().await.unwrap();
+        let (_accepted, from_addr) = listener.accept().unwrap();
+        assert_eq!(from_addr, stream.local_addr().unwrap());
+
+        let socket = socket2::SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert!(socket.recv_buffer_size().unwrap() >= 1 << 16);
+        assert!(socket.send_buffer_size().unwrap() >= 1 << 16);
+    }
+
+    #[tokio::test]
+    async fn test_host() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let builder = |host: &str| {
+            SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_host(host)
+                .with_log_dir(peer.log_dir())
+                .with_memory_store(MemoryStore::new())
+        };
+        for host in ["localhost", ":9878", "localhost:port"] {
+            assert!(matches!(builder(host).build(), Err(ApplicationError::InvalidHost(_))));
+        }
+
+        // `localhost` may resolve to `::1` first, where the peer is not listening
+        let settings = builder(&format!("localhost:{}", peer.addr().port())).build().unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        handle.start_async().await.unwrap();
+        handle.end_async().await.unwrap();
+    }
+
+    #[test]
+    fn test_begin_string() {
+        let builder = || {
+            SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_socket_addr("127.0.0.1:0".parse().unwrap())
+                .with_log_dir(test_dir("begin_string"))
+                .with_memory_store(MemoryStore::new())
+        };
+        assert_eq!(builder().build().unwrap().begin_string.as_str(), "FIX.4.2");
+        assert_eq!(builder().with_begin_string("FIX.4.2").build().unwrap().begin_string.as_str(), "FIX.4.2");
+        for begin_string in ["FIX.4.4", "FIXT.1.1", ""] {
+            assert!(matches!(
+                builder().with_begin_string(begin_string).build(),
+                Err(ApplicationError::UnsupportedBeginString(unsupported)) if unsupported == begin_string
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socket_addrs() {
+        let peer = testing::Counterparty::start_sync("peer_id", "my_id").unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let builder = |addrs| {
+            SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_socket_addrs(addrs)
+                .with_log_dir(peer.log_dir())
+                .with_memory_store(MemoryStore::new())
+        };
+        assert!(matches!(builder(vec![]).build(), Err(ApplicationError::SettingRequired(_))));
+
+        let settings = builder(vec![closed, peer.addr()]).build().unwrap();
+        let (handle, _receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        handle.start_async().await.unwrap();
+        loop {
+            if let SessionEvent::Connected { peer_addr } = events.recv().await.unwrap() {
+                assert_eq!(peer_addr, peer.addr());
+                break;
             }
         }
+        handle.end_async().await.unwrap();
     }
 }
@@ -0,0 +1,260 @@
+//! Run several FIX sessions side by side
+//!
+//! A [`SessionManager`] owns the engines of several sessions, such as one per broker, running on
+//! the same tokio runtime. Each session is added with an id, which is used to send messages on
+//! that session. The application messages received by every session are delivered on a single
+//! channel, tagged with the id of their session.
+//!
+//! ```no_run
+//! use forgefix::manager::SessionManager;
+//! use forgefix::{ApplicationError, SessionSettings};
+//! use forgefix::fix::encode::MessageBuilder;
+//! use forgefix::fix::generated::MsgType;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ApplicationError> {
+//! let (mut manager, mut receiver) = SessionManager::new();
+//! for (broker, addr) in [("broker1", "10.0.0.1:9000"), ("broker2", "10.0.0.2:9000")] {
+//!     let settings = SessionSettings::builder()
+//!         .with_sender_comp_id("my_id")
+//!         .with_target_comp_id(broker)
+//!         .with_socket_addr(addr.parse().unwrap())
+//!         .with_store_path(format!("./{broker}.db").into())
+//!         .with_log_dir("./log".into())
+//!         .build()?;
+//!     manager.add_initiator(broker, settings).await?;
+//! }
+//! for (session_id, error) in manager.start_all().await {
+//!     eprintln!("{session_id} did not log on: {error}");
+//! }
+//!
+//! let builder = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into());
+//! manager.send_message_async("broker1", builder).await?;
+//!
+//! while let Some(msg) = receiver.recv().await {
+//!     println!("{}: {} bytes", msg.session_id, msg.message.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::fix::encode::MessageBuilder;
+use crate::fix::mem::MsgBuf;
+use crate::{ApplicationError, FixApplicationHandle, FixApplicationInitiator, SessionSettings};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
+
+/// An application message received by one of the sessions of a [`SessionManager`].
+#[derive(Clone, Debug)]
+pub struct SessionMessage {
+    /// The id the session was added with.
+    pub session_id: Arc<str>,
+    /// The message.
+    pub message: Arc<MsgBuf>,
+}
+
+/// The engines of several FIX sessions. See the [module documentation](self).
+pub struct SessionManager {
+    sessions: BTreeMap<Arc<str>, FixApplicationHandle>,
+    message_sender: mpsc::UnboundedSender<SessionMessage>,
+}
+
+impl SessionManager {
+    /// Create a manager without sessions, and the channel that receives the application
+    /// messages of all of its sessions.
+    pub fn new() -> (SessionManager, mpsc::UnboundedReceiver<SessionMessage>) {
+        let (message_sender, message_receiver) = mpsc::unbounded_channel();
+        let manager = SessionManager {
+            sessions: BTreeMap::new(),
+            message_sender,
+        };
+        (manager, message_receiver)
+    }
+
+    /// Connect to the peer of `settings`, and add its engine as `session_id`. The session is
+    /// not logged on until it is started.
+    ///
+    /// Returns an `Err(ApplicationError::DuplicateSession)` if a session was already added as
+    /// `session_id`.
+    pub async fn add_initiator(
+        &mut self,
+        session_id: &str,
+        settings: SessionSettings,
+    ) -> Result<&FixApplicationHandle, ApplicationError> {
+        if self.sessions.contains_key(session_id) {
+            return Err(ApplicationError::DuplicateSession(session_id.to_string()));
+        }
+        let (handle, mut receiver) = FixApplicationInitiator::build(settings)?.initiate().await?;
+
+        let session_id: Arc<str> = Arc::from(session_id);
+        let message_sender = self.message_sender.clone();
+        let forwarded_id = Arc::clone(&session_id);
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                let session_id = Arc::clone(&forwarded_id);
+                if message_sender
+                    .send(SessionMessage {
+                        session_id,
+                        message,
+                    })
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Ok(self.sessions.entry(session_id).or_insert(handle))
+    }
+
+    /// Remove the session `session_id` from the manager, and return its handle. The session
+    /// keeps running until it is ended, or its handle is dropped.
+    pub fn remove(&mut self, session_id: &str) -> Option<FixApplicationHandle> {
+        self.sessions.remove(session_id)
+    }
+
+    /// The ids of the sessions, in order.
+    pub fn session_ids(&self) -> impl Iterator<Item = &str> {
+        self.sessions.keys().map(|session_id| session_id.as_ref())
+    }
+
+    /// The handle to the engine of `session_id`.
+    ///
+    /// Returns an `Err(ApplicationError::UnknownSession)` if there is no such session.
+    pub fn handle(&self, session_id: &str) -> Result<&FixApplicationHandle, ApplicationError> {
+        self.sessions
+            .get(session_id)
+            .ok_or_else(|| ApplicationError::UnknownSession(session_id.to_string()))
+    }
+
+    /// Start every session at once, and wait until all of them logged on or failed. Returns the
+    /// sessions that failed to log on, with their error.
+    pub async fn start_all(&self) -> Vec<(Arc<str>, ApplicationError)> {
+        self.for_each_session(|handle| async move { handle.start_async().await })
+            .await
+    }
+
+    /// End every session at once, and wait until all of them logged out or failed. Returns the
+    /// sessions that failed to log out, with their error.
+    pub async fn end_all(&self) -> Vec<(Arc<str>, ApplicationError)> {
+        self.for_each_session(|handle| async move { handle.end_async().await })
+            .await
+    }
+
+    /// Send the message in `builder` on the session `session_id`. See
+    /// [`FixApplicationHandle::send_message`].
+    pub fn send_message(
+        &self,
+        session_id: &str,
+        builder: MessageBuilder,
+    ) -> Result<oneshot::Receiver<bool>, ApplicationError> {
+        self.handle(session_id)?.send_message(builder)
+    }
+
+    /// Send the message in `builder` on the session `session_id`, and await until it is sent.
+    /// See [`FixApplicationHandle::send_message_async`].
+    pub async fn send_message_async(
+        &self,
+        session_id: &str,
+        builder: MessageBuilder,
+    ) -> Result<(), ApplicationError> {
+        self.handle(session_id)?.send_message_async(builder).await
+    }
+
+    async fn for_each_session<F, Fut>(&self, f: F) -> Vec<(Arc<str>, ApplicationError)>
+    where
+        F: Fn(FixApplicationHandle) -> Fut,
+        Fut: std::future::Future<Output = Result<(), ApplicationError>> + Send + 'static,
+    {
+        let mut tasks = JoinSet::new();
+        let mut session_ids = HashMap::new();
+        for (session_id, handle) in &self.sessions {
+            let task = tasks.spawn(f(handle.clone()));
+            session_ids.insert(task.id(), Arc::clone(session_id));
+        }
+        let mut failed = Vec::new();
+        while let Some(joined) = tasks.join_next_with_id().await {
+            // a task that panicked fails its session as well
+            let (id, result) = match joined {
+                Ok((id, result)) => (id, result),
+                Err(e) => (e.id(), Err(ApplicationError::IoError(e.into()))),
+            };
+            if let (Err(e), Some(session_id)) = (result, session_ids.remove(&id)) {
+                failed.push((session_id, e));
+            }
+        }
+        failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        failed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fix::generated::{MsgType, Tags};
+    use crate::fix::memory_store::MemoryStore;
+    use crate::testing::Counterparty;
+
+    #[tokio::test]
+    async fn test_session_manager() {
+        let (mut manager, mut receiver) = SessionManager::new();
+        let peers = [
+            Counterparty::start_sync("peer_id", "my_id").unwrap(),
+            Counterparty::start_sync("peer_id", "my_id").unwrap(),
+        ];
+        for (session_id, peer) in ["a", "b"].into_iter().zip(&peers) {
+            let settings = SessionSettings::builder()
+                .with_sender_comp_id("my_id")
+                .with_target_comp_id("peer_id")
+                .with_socket_addr(peer.addr())
+                .with_log_dir(peer.log_dir())
+                .with_memory_store(MemoryStore::new())
+                .build()
+                .unwrap();
+            manager
+                .add_initiator(session_id, settings.clone())
+                .await
+                .unwrap();
+            assert!(matches!(
+                manager.add_initiator(session_id, settings).await,
+                Err(ApplicationError::DuplicateSession(_))
+            ));
+        }
+        assert_eq!(manager.session_ids().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(manager.start_all().await.is_empty());
+
+        let order = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1")
+            .push(Tags::OrderQty, b"100");
+        manager.send_message_async("b", order).await.unwrap();
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&*msg.session_id, "b");
+
+        let order = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into());
+        assert!(matches!(
+            manager.send_message_async("c", order).await,
+            Err(ApplicationError::UnknownSession(_))
+        ));
+
+        // a task that panics fails its session instead of being left out
+        manager.handle("a").unwrap().pause();
+        let failed = manager
+            .for_each_session(|handle| async move {
+                assert!(!handle.is_paused(), "paused");
+                Ok(())
+            })
+            .await;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(&*failed[0].0, "a");
+        assert!(matches!(failed[0].1, ApplicationError::IoError(_)));
+        manager.handle("a").unwrap().resume();
+
+        assert!(manager.end_all().await.is_empty());
+    }
+}
@@ -0,0 +1,636 @@
+//! An in-process FIX counterparty to test sessions against.
+//!
+//! A [`Counterparty`] listens on a local port and plays the acceptor of a FIX 4.2 session over
+//! plain TCP, without an engine of its own, so that a test decides exactly what the engine under
+//! test receives. It answers the `Logon<A>` of each connection, sends a `Heartbeat<0>` every
+//! `HeartBtInt(108)` requested by the engine, answers `TestRequest<1>` and `Logout<5>` messages,
+//! and answers each `NewOrderSingle<D>`, `OrderCancelRequest<F>` and `OrderStatusRequest<H>`
+//! with an `ExecutionReport<8>`. It keeps no messages, so a `ResendRequest<2>` is answered with a
+//! gap fill.
+//!
+//! It can also be told to misbehave: to drop the connection, to skip sequence numbers, or to send
+//! garbled messages. Every message received from the engine is handed to the test, see
+//! [`Counterparty::next_message`].
+//!
+//! An [`ArenaReader`] reads messages from bytes the way the engine reads them from its
+//! connection, to measure the read path on its own.
+//!
+//! Enabled with the `test-util` feature.
+//!
+//! ## Example
+//! ```rust
+//! use forgefix::fix::generated::MsgType;
+//! use forgefix::testing::{msg_type, Counterparty};
+//! use forgefix::{FixApplicationInitiator, SessionSettings};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut counterparty = Counterparty::start("BROKER", "MY_ID").await?;
+//!     let settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+//!     let (handle, _receiver) = FixApplicationInitiator::build(settings)?.initiate().await?;
+//!     handle.start_async().await?;
+//!     assert_eq!(msg_type(&counterparty.next_message().await.unwrap()), Some(MsgType::LOGON.into()));
+//!
+//!     // the next message of the counterparty opens a gap, which the engine asks to be resent
+//!     counterparty.skip_sequence_numbers(5);
+//!     counterparty.send_heartbeat();
+//!     assert_eq!(
+//!         msg_type(&counterparty.next_message().await.unwrap()),
+//!         Some(MsgType::RESEND_REQUEST.into())
+//!     );
+//!     Ok(())
+//! }
+//! ```
+
+use crate::fix::decode::{parse, MessageParseError, ParserCallback};
+use crate::fix::encode::{AdditionalHeaders, MessageBuilder};
+use crate::fix::generated::{ExecTransType, ExecType, MsgType, OrdStatus, Tags};
+use crate::fix::mem::MsgBuf;
+
+pub use crate::fix::stream::ArenaReader;
+
+use chrono::Utc;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+// the `HeartBtInt(108)` used if the engine did not send one
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+static NEXT_COUNTERPARTY: AtomicU32 = AtomicU32::new(0);
+
+/// A scriptable FIX counterparty, listening on `127.0.0.1`. See the [module](self) documentation.
+///
+/// Dropping the counterparty stops it, closes its connection, and removes its
+/// [`store_path`](Counterparty::store_path) and [`log_dir`](Counterparty::log_dir).
+pub struct Counterparty {
+    addr: SocketAddr,
+    dir: PathBuf,
+    commands: mpsc::UnboundedSender<Command>,
+    received: mpsc::UnboundedReceiver<MsgBuf>,
+    // the task on the runtime of the test, unless the counterparty runs on a thread of its own
+    task: Option<JoinHandle<()>>,
+}
+
+enum Command {
+    Send(MessageBuilder),
+    SendHeartbeat,
+    SendGarbled,
+    SendRaw(Vec<u8>),
+    Logout,
+    DropConnection,
+    SkipSequenceNumbers(u32),
+    ResetSequenceNumbers,
+    AutoAck(bool),
+}
+
+impl Counterparty {
+    /// Start a counterparty with the `SenderCompID(49)` of `sender_comp_id`, that accepts
+    /// sessions from `target_comp_id`. Connections are accepted one at a time, so the engine
+    /// under test can reconnect after the connection was dropped.
+    pub async fn start(sender_comp_id: &str, target_comp_id: &str) -> std::io::Result<Counterparty> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (simulator, received) = Simulator::new(sender_comp_id, target_comp_id);
+        let task = tokio::spawn(simulator.run(listener, command_receiver));
+        Ok(Counterparty {
+            addr,
+            dir: scratch_dir()?,
+            commands,
+            received,
+            task: Some(task),
+        })
+    }
+
+    /// Start a counterparty as in [`start`](Counterparty::start), on a background thread with a
+    /// runtime of its own. It does not need a runtime, so it also serves engines driven through
+    /// the blocking API.
+    pub fn start_sync(sender_comp_id: &str, target_comp_id: &str) -> std::io::Result<Counterparty> {
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (simulator, received) = Simulator::new(sender_comp_id, target_comp_id);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                if let Ok(listener) = TcpListener::from_std(listener) {
+                    simulator.run(listener, command_receiver).await;
+                }
+            });
+        });
+        Ok(Counterparty {
+            addr,
+            dir: scratch_dir()?,
+            commands,
+            received,
+            task: None,
+        })
+    }
+
+    /// The address the counterparty listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// A store path for the engine under test, in a directory of the counterparty.
+    pub fn store_path(&self) -> PathBuf {
+        self.dir.join("store.db")
+    }
+
+    /// A log directory for the engine under test, in a directory of the counterparty.
+    pub fn log_dir(&self) -> PathBuf {
+        self.dir.join("log")
+    }
+
+    /// The next message received from the engine, or `None` if the counterparty stopped. Garbled
+    /// bytes are not messages, and are skipped.
+    pub async fn next_message(&mut self) -> Option<MsgBuf> {
+        self.received.recv().await
+    }
+
+    /// Send the message in `builder`, with the next sequence number of the counterparty. The
+    /// header fields are added by the counterparty.
+    pub fn send(&self, builder: MessageBuilder) {
+        let _ = self.commands.send(Command::Send(builder));
+    }
+
+    /// Send a `Heartbeat<0>` now.
+    pub fn send_heartbeat(&self) {
+        let _ = self.commands.send(Command::SendHeartbeat);
+    }
+
+    /// Send a `Heartbeat<0>` with a `BodyLength(9)` one byte short, which the engine skips as
+    /// garbled. It does not use up a sequence number.
+    pub fn send_garbled(&self) {
+        let _ = self.commands.send(Command::SendGarbled);
+    }
+
+    /// Write `bytes` to the connection as they are.
+    pub fn send_raw(&self, bytes: impl Into<Vec<u8>>) {
+        let _ = self.commands.send(Command::SendRaw(bytes.into()));
+    }
+
+    /// Send a `Logout<5>`, and close the connection once the engine answers it.
+    pub fn logout(&self) {
+        let _ = self.commands.send(Command::Logout);
+    }
+
+    /// Close the connection now, without a `Logout<5>`.
+    pub fn drop_connection(&self) {
+        let _ = self.commands.send(Command::DropConnection);
+    }
+
+    /// Skip `n` sequence numbers, so that the next message sent opens a gap.
+    pub fn skip_sequence_numbers(&self, n: u32) {
+        let _ = self.commands.send(Command::SkipSequenceNumbers(n));
+    }
+
+    /// Send a `Logon<A>` with `ResetSeqNumFlag(141)=Y` and `MsgSeqNum(34)=1`, to reset the
+    /// sequence numbers in the middle of the session. The `Logon<A>` the engine answers with is
+    /// not answered in turn.
+    pub fn reset_sequence_numbers(&self) {
+        let _ = self.commands.send(Command::ResetSequenceNumbers);
+    }
+
+    /// Whether orders are answered with an `ExecutionReport<8>`. Enabled by default.
+    pub fn set_auto_ack(&self, auto_ack: bool) {
+        let _ = self.commands.send(Command::AutoAck(auto_ack));
+    }
+}
+
+impl Drop for Counterparty {
+    fn drop(&mut self) {
+        // a counterparty on a thread of its own stops once its commands are closed
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// A directory of its own for each counterparty, for the store and logs of the engine under test.
+fn scratch_dir() -> std::io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!(
+        "forgefix-counterparty-{}-{}",
+        std::process::id(),
+        NEXT_COUNTERPARTY.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+struct Simulator {
+    comp_ids: Vec<(u32, Vec<u8>)>,
+    headers: AdditionalHeaders,
+    next_seq_num: u32,
+    // a reset was sent, and the `Logon<A>` confirming it is not answered
+    awaiting_reset: bool,
+    auto_ack: bool,
+    exec_id: u32,
+    received: mpsc::UnboundedSender<MsgBuf>,
+}
+
+// What to do with the connection after a message or a command.
+enum Next {
+    Continue,
+    Close,
+}
+
+impl Simulator {
+    fn new(sender_comp_id: &str, target_comp_id: &str) -> (Simulator, mpsc::UnboundedReceiver<MsgBuf>) {
+        let (received_sender, received) = mpsc::unbounded_channel();
+        let comp_ids = vec![
+            (Tags::SenderCompID.into(), sender_comp_id.as_bytes().to_vec()),
+            (Tags::TargetCompID.into(), target_comp_id.as_bytes().to_vec()),
+        ];
+        let simulator = Simulator {
+            headers: AdditionalHeaders::new(comp_ids.clone()),
+            comp_ids,
+            next_seq_num: 1,
+            awaiting_reset: false,
+            auto_ack: true,
+            exec_id: 0,
+            received: received_sender,
+        };
+        (simulator, received)
+    }
+
+    async fn run(mut self, listener: TcpListener, mut commands: mpsc::UnboundedReceiver<Command>) {
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => return,
+                },
+                command = commands.recv() => match command {
+                    // nothing is sent while there is no connection
+                    Some(command) => {
+                        self.apply(command);
+                        continue;
+                    }
+                    None => return,
+                },
+            };
+            if !self.serve(stream, &mut commands).await {
+                return;
+            }
+        }
+    }
+
+    // Applies a command that does not need a connection.
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::SkipSequenceNumbers(n) => self.next_seq_num += n,
+            Command::AutoAck(auto_ack) => self.auto_ack = auto_ack,
+            _ => {}
+        }
+    }
+
+    // Serves one connection. Returns `false` once every handle to the counterparty was dropped.
+    async fn serve(&mut self, mut stream: TcpStream, commands: &mut mpsc::UnboundedReceiver<Command>) -> bool {
+        let mut buf = Vec::new();
+        let mut chunk = [0; 4096];
+        let mut heartbeat = tokio::time::interval(DEFAULT_HEARTBEAT_INTERVAL);
+        heartbeat.reset();
+        let mut logged_in = false;
+        loop {
+            let next = tokio::select! {
+                read = stream.read(&mut chunk) => match read {
+                    Ok(0) | Err(_) => Next::Close,
+                    Ok(n) => {
+                        buf.extend_from_slice(&chunk[..n]);
+                        let mut next = Next::Continue;
+                        while let Some(msg) = take_message(&mut buf) {
+                            if let Some(interval) = logon_heartbeat_interval(&msg) {
+                                heartbeat = tokio::time::interval(interval);
+                                heartbeat.reset();
+                                logged_in = true;
+                            }
+                            next = self.answer(&mut stream, &msg).await;
+                            let _ = self.received.send(msg);
+                            if matches!(next, Next::Close) {
+                                break;
+                            }
+                        }
+                        next
+                    }
+                },
+                _ = heartbeat.tick(), if logged_in => self.send_heartbeat(&mut stream, None).await,
+                command = commands.recv() => match command {
+                    Some(command) => self.command(&mut stream, command).await,
+                    None => return false,
+                },
+            };
+            if matches!(next, Next::Close) {
+                return true;
+            }
+        }
+    }
+
+    async fn command(&mut self, stream: &mut TcpStream, command: Command) -> Next {
+        match command {
+            Command::Send(builder) => self.send(stream, builder).await,
+            Command::SendHeartbeat => self.send_heartbeat(stream, None).await,
+            Command::SendGarbled => {
+                let builder = MessageBuilder::new("FIX.4.2", MsgType::HEARTBEAT.into());
+                let msg = self.encode(&builder, self.next_seq_num, &self.headers).await;
+                write(stream, &shorten_body_length(msg)).await
+            }
+            Command::SendRaw(bytes) => write(stream, &bytes).await,
+            Command::Logout => {
+                self.send(stream, MessageBuilder::new("FIX.4.2", MsgType::LOGOUT.into())).await
+            }
+            Command::DropConnection => Next::Close,
+            Command::ResetSequenceNumbers => {
+                self.next_seq_num = 1;
+                self.awaiting_reset = true;
+                let logon = MessageBuilder::new("FIX.4.2", MsgType::LOGON.into())
+                    .push(Tags::EncryptMethod, b"0")
+                    .push(Tags::HeartBtInt, b"30")
+                    .push(Tags::ResetSeqNumFlag, b"Y");
+                self.send(stream, logon).await
+            }
+            command => {
+                self.apply(command);
+                Next::Continue
+            }
+        }
+    }
+
+    async fn answer(&mut self, stream: &mut TcpStream, msg: &MsgBuf) -> Next {
+        let Some(Ok(msg_type)) = msg_type(msg).map(MsgType::try_from) else {
+            return Next::Continue;
+        };
+        match msg_type {
+            MsgType::LOGON if self.awaiting_reset => {
+                self.awaiting_reset = false;
+                Next::Continue
+            }
+            MsgType::LOGON => {
+                if field(msg, Tags::ResetSeqNumFlag) == Some(b"Y") {
+                    self.next_seq_num = 1;
+                }
+                let mut logon = MessageBuilder::new("FIX.4.2", MsgType::LOGON.into())
+                    .push(Tags::EncryptMethod, b"0")
+                    .push(Tags::HeartBtInt, field(msg, Tags::HeartBtInt).unwrap_or(b"30"));
+                if let Some(reset_seq_num_flag) = field(msg, Tags::ResetSeqNumFlag) {
+                    logon = logon.push(Tags::ResetSeqNumFlag, reset_seq_num_flag);
+                }
+                self.send(stream, logon).await
+            }
+            MsgType::TEST_REQUEST => {
+                self.send_heartbeat(stream, field(msg, Tags::TestReqID)).await
+            }
+            MsgType::RESEND_REQUEST => {
+                let begin_seq_no = field(msg, Tags::BeginSeqNo)
+                    .and_then(|value| std::str::from_utf8(value).ok()?.parse().ok())
+                    .unwrap_or(1);
+                self.send_gap_fill(stream, begin_seq_no).await
+            }
+            MsgType::LOGOUT => {
+                let logout = MessageBuilder::new("FIX.4.2", MsgType::LOGOUT.into());
+                self.send(stream, logout).await;
+                Next::Close
+            }
+            _ if self.auto_ack => {
+                match execution_report("FIX.4.2", &msg[..], self.exec_id + 1) {
+                    Some(execution_report) => {
+                        self.exec_id += 1;
+                        self.send(stream, execution_report).await
+                    }
+                    None => Next::Continue,
+                }
+            }
+            _ => Next::Continue,
+        }
+    }
+
+    async fn send_heartbeat(&mut self, stream: &mut TcpStream, test_req_id: Option<&[u8]>) -> Next {
+        let mut heartbeat = MessageBuilder::new("FIX.4.2", MsgType::HEARTBEAT.into());
+        if let Some(test_req_id) = test_req_id {
+            heartbeat = heartbeat.push(Tags::TestReqID, test_req_id);
+        }
+        self.send(stream, heartbeat).await
+    }
+
+    // Fills every message from `begin_seq_no` up to the next one.
+    async fn send_gap_fill(&mut self, stream: &mut TcpStream, begin_seq_no: u32) -> Next {
+        let gap_fill = MessageBuilder::new("FIX.4.2", MsgType::SEQUENCE_RESET.into())
+            .push(Tags::GapFillFlag, b"Y")
+            .push_int(Tags::NewSeqNo, self.next_seq_num.into());
+        let mut fields = vec![
+            (Tags::PossDupFlag.into(), b"Y".to_vec()),
+            (Tags::OrigSendingTime.into(), crate::fix::encode::formatted_time().into_bytes()),
+        ];
+        fields.extend(self.comp_ids.iter().cloned());
+        fields.sort_by_key(|(tag, _)| *tag);
+        let msg = self
+            .encode(&gap_fill, begin_seq_no.min(self.next_seq_num), &AdditionalHeaders::new(fields))
+            .await;
+        write(stream, &msg).await
+    }
+
+    async fn send(&mut self, stream: &mut TcpStream, builder: MessageBuilder) -> Next {
+        let msg = self.encode(&builder, self.next_seq_num, &self.headers).await;
+        self.next_seq_num += 1;
+        write(stream, &msg).await
+    }
+
+    async fn encode(&self, builder: &MessageBuilder, msg_seq_num: u32, headers: &AdditionalHeaders) -> Vec<u8> {
+        let mut msg = Vec::new();
+        builder
+            .build_async(&mut msg, msg_seq_num, headers, Utc::now())
+            .await
+            .expect("writing to a Vec cannot fail");
+        msg
+    }
+}
+
+async fn write(stream: &mut TcpStream, bytes: &[u8]) -> Next {
+    match stream.write_all(bytes).await {
+        Ok(()) => Next::Continue,
+        Err(_) => Next::Close,
+    }
+}
+
+// Takes the first whole message from `buf`. Bytes before the next `8=FIX` are discarded.
+fn take_message(buf: &mut Vec<u8>) -> Option<MsgBuf> {
+    let start = buf.windows(5).position(|window| window == b"8=FIX")?;
+    buf.drain(..start);
+    let len_start = buf.windows(3).position(|window| window == b"\x019=")? + 3;
+    let len_end = len_start + buf[len_start..].iter().position(|b| *b == b'\x01')?;
+    let body_length: usize = std::str::from_utf8(&buf[len_start..len_end]).ok()?.parse().ok()?;
+    let len = len_end + 1 + body_length + "10=000\x01".len();
+    if buf.len() < len {
+        return None;
+    }
+    Some(MsgBuf(buf.drain(..len).collect()))
+}
+
+/// Takes one from the `BodyLength(9)` of `msg`.
+fn shorten_body_length(msg: Vec<u8>) -> Vec<u8> {
+    let msg = String::from_utf8(msg).expect("messages built by the counterparty are ASCII");
+    let (begin_string, rest) = msg.split_once("\x019=").expect("every message has a BodyLength(9)");
+    let (body_length, rest) = rest.split_once('\x01').expect("every message has a BodyLength(9)");
+    let body_length: usize = body_length.parse().expect("BodyLength(9) is a number");
+    format!("{begin_string}\x019={}\x01{rest}", body_length - 1).into_bytes()
+}
+
+/// The value of the first `tag` in `msg`, such as a message received by a [`Counterparty`].
+pub fn field(msg: &MsgBuf, tag: impl Into<u32>) -> Option<&[u8]> {
+    let prefix = format!("{}=", tag.into());
+    msg[..]
+        .split(|b| *b == b'\x01')
+        .find_map(|field| field.strip_prefix(prefix.as_bytes()))
+}
+
+/// The `MsgType(35)` of `msg`.
+pub fn msg_type(msg: &MsgBuf) -> Option<char> {
+    match field(msg, Tags::MsgType)? {
+        [msg_type] => Some(*msg_type as char),
+        _ => None,
+    }
+}
+
+// The heartbeat interval requested by `msg`, if it is a `Logon<A>`.
+fn logon_heartbeat_interval(msg: &MsgBuf) -> Option<Duration> {
+    if msg_type(msg) != Some(MsgType::LOGON.into()) {
+        return None;
+    }
+    let interval = field(msg, Tags::HeartBtInt)
+        .and_then(|value| std::str::from_utf8(value).ok()?.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL);
+    Some(interval.max(Duration::from_secs(1)))
+}
+
+// The `ExecutionReport<8>` with `ExecID(17)` of `exec_id` that answers `msg`, if it is an order,
+// a cancel request or a status request.
+fn execution_report(begin_string: &str, msg: &[u8], exec_id: u32) -> Option<MessageBuilder> {
+    let mut order = OrderFields::default();
+    parse(msg, &mut order).ok()?;
+    let (exec_trans_type, exec_type, ord_status) = match order.msg_type.map(MsgType::try_from) {
+        Some(Ok(MsgType::ORDER_SINGLE)) if order.order_qty.parse() == Ok(0.0) => {
+            (ExecTransType::NEW, ExecType::REJECTED, OrdStatus::REJECTED)
+        }
+        Some(Ok(MsgType::ORDER_SINGLE)) => (ExecTransType::NEW, ExecType::NEW, OrdStatus::NEW),
+        Some(Ok(MsgType::ORDER_CANCEL_REQUEST)) => {
+            (ExecTransType::NEW, ExecType::CANCELED, OrdStatus::CANCELED)
+        }
+        Some(Ok(MsgType::ORDER_STATUS_REQUEST)) => {
+            (ExecTransType::STATUS, ExecType::NEW, OrdStatus::NEW)
+        }
+        _ => return None,
+    };
+    let mut builder = MessageBuilder::new(begin_string, MsgType::EXECUTION_REPORT.into())
+        .push(Tags::OrderID, order.orig_cl_ord_id.as_ref().unwrap_or(&order.cl_ord_id).as_bytes())
+        .push(Tags::ClOrdID, order.cl_ord_id.as_bytes())
+        .push(Tags::ExecID, exec_id.to_string().as_bytes())
+        .push(Tags::ExecTransType, <&[u8]>::from(exec_trans_type))
+        .push(Tags::ExecType, <&[u8]>::from(exec_type))
+        .push(Tags::OrdStatus, <&[u8]>::from(ord_status))
+        .push(Tags::Symbol, order.symbol.as_bytes())
+        .push(Tags::Side, order.side.as_bytes())
+        .push(Tags::LeavesQty, b"0")
+        .push(Tags::CumQty, b"0")
+        .push(Tags::AvgPx, b"0");
+    if let Some(orig_cl_ord_id) = order.orig_cl_ord_id {
+        builder = builder.push(Tags::OrigClOrdID, orig_cl_ord_id.as_bytes());
+    }
+    Some(builder)
+}
+
+#[derive(Default)]
+struct OrderFields {
+    msg_type: Option<char>,
+    cl_ord_id: String,
+    orig_cl_ord_id: Option<String>,
+    symbol: String,
+    side: String,
+    order_qty: String,
+}
+
+impl<'a> ParserCallback<'a> for OrderFields {
+    type Err = MessageParseError;
+    fn header(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        if let (Ok(Tags::MsgType), [msg_type]) = (key.try_into(), value) {
+            self.msg_type = Some(*msg_type as char);
+        }
+        Ok(true)
+    }
+    fn body(&mut self, key: u32, value: &'a [u8]) -> Result<bool, Self::Err> {
+        let value = String::from_utf8_lossy(value).into_owned();
+        match key.try_into() {
+            Ok(Tags::ClOrdID) => self.cl_ord_id = value,
+            Ok(Tags::OrigClOrdID) => self.orig_cl_ord_id = Some(value),
+            Ok(Tags::Symbol) => self.symbol = value,
+            Ok(Tags::Side) => self.side = value,
+            Ok(Tags::OrderQty) => self.order_qty = value,
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn trailer(&mut self, _key: u32, _value: &'a [u8]) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+    fn parse_error(&mut self, err: MessageParseError) -> Result<(), Self::Err> {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FixApplicationInitiator, SessionEvent, SessionSettings};
+
+    #[tokio::test]
+    async fn test_counterparty() {
+        let mut counterparty = Counterparty::start("BROKER", "MY_ID").await.unwrap();
+        let settings = SessionSettings::ephemeral("MY_ID", "BROKER", counterparty.addr());
+        let (handle, mut receiver) = FixApplicationInitiator::build(settings).unwrap().initiate().await.unwrap();
+        let mut events = handle.session_events();
+        handle.start_async().await.unwrap();
+        assert_eq!(msg_type(&counterparty.next_message().await.unwrap()), Some('A'));
+
+        let order = MessageBuilder::new("FIX.4.2", MsgType::ORDER_SINGLE.into())
+            .push(Tags::ClOrdID, b"order-1")
+            .push(Tags::Symbol, b"AAPL")
+            .push(Tags::Side, b"1")
+            .push(Tags::OrderQty, b"100");
+        handle.send_message_async(order).await.unwrap();
+        assert_eq!(msg_type(&counterparty.next_message().await.unwrap()), Some('D'));
+        let execution_report = receiver.recv().await.unwrap();
+        assert_eq!(msg_type(&execution_report), Some('8'));
+        assert_eq!(field(&execution_report, Tags::ClOrdID), Some(&b"order-1"[..]));
+
+        // garbled bytes are skipped, while a gap is filled at the request of the engine
+        counterparty.send_garbled();
+        counterparty.skip_sequence_numbers(3);
+        counterparty.send_heartbeat();
+        let resend_request = counterparty.next_message().await.unwrap();
+        assert_eq!(msg_type(&resend_request), Some('2'));
+        assert_eq!(field(&resend_request, Tags::BeginSeqNo), Some(&b"3"[..]));
+        assert_eq!(handle.metrics().garbled_messages_received, 1);
+        let test_request = MessageBuilder::new("FIX.4.2", MsgType::TEST_REQUEST.into())
+            .push(Tags::TestReqID, b"after-gap");
+        counterparty.send(test_request);
+        let heartbeat = counterparty.next_message().await.unwrap();
+        assert_eq!(field(&heartbeat, Tags::TestReqID), Some(&b"after-gap"[..]));
+        assert_eq!(handle.sequence_numbers().unwrap().next_incoming, 8);
+
+        counterparty.drop_connection();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !matches!(events.recv().await.unwrap(), SessionEvent::Disconnected { .. }) {}
+        })
+        .await
+        .unwrap();
+    }
+}
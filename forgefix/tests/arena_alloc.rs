@@ -0,0 +1,56 @@
+// Reading a message into the arena must not allocate once the buffers of the engine are warmed up.
+// The allocations are counted with a global allocator, which is why this test has a binary of its
+// own.
+
+use forgefix::testing::ArenaReader;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts the allocations of a thread while it is counting.
+struct CountingAlloc;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(|c| c.get()) {
+            ALLOCATIONS.with(|a| a.set(a.get() + 1));
+        }
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+#[tokio::test]
+async fn test_arena_read_does_not_allocate() {
+    const WARM_UP: usize = 100;
+    const MESSAGES: usize = 1000;
+
+    let body = "35=D\x0134=2\x0149=ISLD\x0152=20240506-13:59:15.021\x0156=TW\x0111=order-1\x0155=AAPL\x0154=1\x01";
+    let mut msg = format!("8=FIX.4.2\x019={}\x01{body}", body.len()).into_bytes();
+    let checksum = msg.iter().map(|b| *b as u32).sum::<u32>() % 256;
+    msg.extend(format!("10={checksum:03}\x01").bytes());
+    let stream = msg.repeat(WARM_UP + MESSAGES);
+    let (mut reader, mut receiver) = ArenaReader::new(&stream, 16 * msg.len());
+
+    let mut allocations = 0;
+    for n in 0..WARM_UP + MESSAGES {
+        COUNTING.with(|c| c.set(n >= WARM_UP));
+        assert_eq!(reader.read_next().await, Some('D'));
+        let view = receiver.try_recv().unwrap();
+        assert!(view.in_arena());
+        drop(view);
+        COUNTING.with(|c| c.set(false));
+        allocations = ALLOCATIONS.with(|a| a.get());
+    }
+    assert_eq!(allocations, 0);
+}